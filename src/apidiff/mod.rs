@@ -0,0 +1,509 @@
+pub mod report;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+  structure::class::{
+    Annotation, ClassFile, ClassFileAttribute, Constant, ConstantPool, FieldInfoAttribute, MethodInfoAttribute,
+  },
+  util::{
+    class::java_name,
+    signature::{ClassSignature, MethodSignature, TypeSignature, type_parameters_to_java},
+  },
+};
+
+// 2つの版の公開APIの比較と、JLS 13章のバイナリ互換性による分類
+
+const ACC_PUBLIC: u16 = 0x0001;
+const ACC_PRIVATE: u16 = 0x0002;
+const ACC_PROTECTED: u16 = 0x0004;
+const ACC_STATIC: u16 = 0x0008;
+const ACC_FINAL: u16 = 0x0010;
+const ACC_INTERFACE: u16 = 0x0200;
+const ACC_ABSTRACT: u16 = 0x0400;
+const ACC_SYNTHETIC: u16 = 0x1000;
+const ACC_ANNOTATION: u16 = 0x2000;
+const ACC_ENUM: u16 = 0x4000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Compatibility {
+  Breaking,
+  Compatible,
+}
+
+impl Compatibility {
+  pub fn name(&self) -> &'static str {
+    match self {
+      Compatibility::Breaking => "breaking",
+      Compatibility::Compatible => "compatible",
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ApiChange {
+  pub compatibility: Compatibility,
+  // "class a.B"、"method a.B.m(I)V"、"field a.B.f:I"
+  pub element: String,
+  pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiMember {
+  pub access_flags: u16,
+  pub signature: Option<String>,
+  pub annotations: BTreeSet<String>,
+  // メソッドのthrows (Exceptions属性)
+  pub exceptions: BTreeSet<String>,
+  // フィールドのConstantValue
+  pub constant: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiClass {
+  pub name: String,
+  // ネストしたクラスはInnerClassesのフラグ
+  pub access_flags: u16,
+  pub super_name: Option<String>,
+  pub interfaces: Vec<String>,
+  pub signature: Option<String>,
+  pub annotations: BTreeSet<String>,
+  pub permitted_subclasses: Option<BTreeSet<String>>,
+  // (名前, 記述子)
+  pub fields: BTreeMap<(String, String), ApiMember>,
+  pub methods: BTreeMap<(String, String), ApiMember>,
+}
+
+// 公開APIに含まれるか (publicかprotected)
+fn is_exported(access_flags: u16) -> bool {
+  access_flags & (ACC_PUBLIC | ACC_PROTECTED) != 0
+}
+
+// private < パッケージ < protected < public
+fn visibility(access_flags: u16) -> u8 {
+  if access_flags & ACC_PUBLIC != 0 {
+    3
+  } else if access_flags & ACC_PROTECTED != 0 {
+    2
+  } else if access_flags & ACC_PRIVATE != 0 {
+    0
+  } else {
+    1
+  }
+}
+
+fn visibility_name(access_flags: u16) -> &'static str {
+  ["private", "package-private", "protected", "public"][visibility(access_flags) as usize]
+}
+
+fn class_kind(access_flags: u16) -> &'static str {
+  if access_flags & ACC_ANNOTATION != 0 {
+    "annotation"
+  } else if access_flags & ACC_INTERFACE != 0 {
+    "interface"
+  } else if access_flags & ACC_ENUM != 0 {
+    "enum"
+  } else {
+    "class"
+  }
+}
+
+fn annotation_types(pool: &ConstantPool, annotations: &[Annotation]) -> impl Iterator<Item = String> {
+  annotations.iter().filter_map(|annotation| pool.get_utf8(annotation.type_index).ok()).map(|descriptor| {
+    java_name(descriptor.strip_prefix('L').and_then(|name| name.strip_suffix(';')).unwrap_or(&descriptor))
+  })
+}
+
+// ConstantValueの値 (JLSの定数なので呼び出し側に埋め込まれる)
+fn constant_text(pool: &ConstantPool, index: u16) -> Option<String> {
+  Some(match pool.get_class(index).ok()? {
+    Constant::Integer { bytes } => (*bytes as i32).to_string(),
+    Constant::Float { bytes } => format!("{}f", f32::from_bits(*bytes)),
+    Constant::Long { high_bytes, low_bytes } => format!("{}L", ((*high_bytes as u64) << 32 | *low_bytes as u64) as i64),
+    Constant::Double { high_bytes, low_bytes } => f64::from_bits((*high_bytes as u64) << 32 | *low_bytes as u64).to_string(),
+    Constant::String { string_index } => format!("{:?}", pool.get_utf8(*string_index).ok()?),
+    _ => return None,
+  })
+}
+
+impl ApiClass {
+  pub fn from_class_file(class_file: &ClassFile) -> Option<ApiClass> {
+    let pool = &class_file.constant_pool;
+    let name = pool.get_class_name(class_file.this_class).ok()?;
+    let mut class = ApiClass {
+      access_flags: class_file.access_flags,
+      super_name: if class_file.super_class == 0 { None } else { pool.get_class_name(class_file.super_class).ok() },
+      interfaces: class_file.interfaces.interfaces.iter().filter_map(|&index| pool.get_class_name(index).ok()).collect(),
+      signature: None,
+      annotations: BTreeSet::new(),
+      permitted_subclasses: None,
+      fields: BTreeMap::new(),
+      methods: BTreeMap::new(),
+      name,
+    };
+    for attribute in &class_file.attributes.attributes {
+      match attribute {
+        ClassFileAttribute::InnerClasses(inner_classes) => {
+          for inner in &inner_classes.classes {
+            if inner.inner_class_info_index == class_file.this_class {
+              class.access_flags = inner.inner_class_access_flags;
+            }
+          }
+        },
+        ClassFileAttribute::Signature(signature) => class.signature = pool.get_utf8(signature.signature_index).ok(),
+        ClassFileAttribute::RuntimeVisibleAnnotations(attribute) => class.annotations.extend(annotation_types(pool, &attribute.annotations)),
+        ClassFileAttribute::RuntimeInvisibleAnnotations(attribute) => class.annotations.extend(annotation_types(pool, &attribute.annotations)),
+        ClassFileAttribute::PermittedSubclasses(permitted) => {
+          class.permitted_subclasses = Some(permitted.classes.iter().filter_map(|&index| pool.get_class_name(index).ok()).collect())
+        },
+        _ => {},
+      }
+    }
+    for field in &class_file.fields.fields {
+      let (Ok(name), Ok(descriptor)) = (pool.get_utf8(field.name_index), pool.get_utf8(field.descriptor_index)) else {
+        continue;
+      };
+      let mut member =
+        ApiMember { access_flags: field.access_flags, signature: None, annotations: BTreeSet::new(), exceptions: BTreeSet::new(), constant: None };
+      for attribute in &field.attributes.attributes {
+        match attribute {
+          FieldInfoAttribute::ConstantValue(value) => member.constant = constant_text(pool, value.constant_value_index),
+          FieldInfoAttribute::Signature(signature) => member.signature = pool.get_utf8(signature.signature_index).ok(),
+          FieldInfoAttribute::RuntimeVisibleAnnotations(attribute) => member.annotations.extend(annotation_types(pool, &attribute.annotations)),
+          FieldInfoAttribute::RuntimeInvisibleAnnotations(attribute) => member.annotations.extend(annotation_types(pool, &attribute.annotations)),
+          _ => {},
+        }
+      }
+      class.fields.insert((name, descriptor), member);
+    }
+    for method in &class_file.methods.methods {
+      let (Ok(name), Ok(descriptor)) = (pool.get_utf8(method.name_index), pool.get_utf8(method.descriptor_index)) else {
+        continue;
+      };
+      let mut member =
+        ApiMember { access_flags: method.access_flags, signature: None, annotations: BTreeSet::new(), exceptions: BTreeSet::new(), constant: None };
+      for attribute in &method.attributes.attributes {
+        match attribute {
+          MethodInfoAttribute::Exceptions(exceptions) => {
+            member.exceptions.extend(exceptions.exception_index_table.iter().filter_map(|&index| pool.get_class_name(index).ok()))
+          },
+          MethodInfoAttribute::Signature(signature) => member.signature = pool.get_utf8(signature.signature_index).ok(),
+          MethodInfoAttribute::RuntimeVisibleAnnotations(attribute) => member.annotations.extend(annotation_types(pool, &attribute.annotations)),
+          MethodInfoAttribute::RuntimeInvisibleAnnotations(attribute) => member.annotations.extend(annotation_types(pool, &attribute.annotations)),
+          _ => {},
+        }
+      }
+      class.methods.insert((name, descriptor), member);
+    }
+    Some(class)
+  }
+
+  pub fn is_exported(&self) -> bool {
+    is_exported(self.access_flags)
+  }
+}
+
+pub fn api_classes(classes: &[ClassFile]) -> BTreeMap<String, ApiClass> {
+  classes.iter().filter_map(ApiClass::from_class_file).map(|class| (class.name.clone(), class)).collect()
+}
+
+// 同じ版の中で辿れる全ての上位の型
+fn supertypes(classes: &BTreeMap<String, ApiClass>, name: &str) -> BTreeSet<String> {
+  let mut supertypes = BTreeSet::new();
+  let mut stack: Vec<String> = classes.get(name).map(|class| class.super_name.iter().chain(&class.interfaces).cloned().collect()).unwrap_or_default();
+  while let Some(name) = stack.pop() {
+    if supertypes.insert(name.clone())
+      && let Some(class) = classes.get(&name)
+    {
+      stack.extend(class.super_name.iter().chain(&class.interfaces).cloned());
+    }
+  }
+  supertypes
+}
+
+// Signature属性を持つ要素の種類 (種類ごとにシグネチャの文法が違う、JVMS 4.7.9.1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SignatureKind {
+  Class,
+  Field,
+  Method,
+}
+
+// Signature属性のJavaでの表記 (読めなければそのまま)
+fn signature_text(kind: SignatureKind, signature: Option<&str>) -> String {
+  let Some(signature) = signature else {
+    return "(none)".to_string();
+  };
+  let class_name = |name: &str| java_name(name);
+  match kind {
+    SignatureKind::Method => {
+      if let Ok(method) = MethodSignature::parse(signature) {
+        let parameters: Vec<String> = method.parameters.iter().map(|parameter| parameter.to_java(&class_name)).collect();
+        let return_type = method.return_type.map(|return_type| return_type.to_java(&class_name)).unwrap_or_else(|| "void".to_string());
+        let type_parameters = type_parameters_to_java(&method.type_parameters, &class_name);
+        let space = if type_parameters.is_empty() { "" } else { " " };
+        return format!("{}{}{} ({})", type_parameters, space, return_type, parameters.join(", "));
+      }
+    },
+    SignatureKind::Field => {
+      if let Ok(field) = TypeSignature::parse(signature) {
+        return field.to_java(&class_name);
+      }
+    },
+    SignatureKind::Class => {
+      if let Ok(class) = ClassSignature::parse(signature) {
+        let interfaces: Vec<String> = class.interfaces.iter().map(|interface| interface.to_java(&class_name)).collect();
+        let type_parameters = type_parameters_to_java(&class.type_parameters, &class_name);
+        let space = if type_parameters.is_empty() { "" } else { " " };
+        let mut text = format!("{}{}extends {}", type_parameters, space, class.superclass.to_java(&class_name));
+        if !interfaces.is_empty() {
+          text.push_str(&format!(" implements {}", interfaces.join(", ")));
+        }
+        return text;
+      }
+    },
+  }
+  signature.to_string()
+}
+
+fn names(names: &BTreeSet<String>) -> String {
+  let names: Vec<String> = names.iter().map(|name| java_name(name)).collect();
+  names.join(", ")
+}
+
+struct Comparison<'a> {
+  old: &'a BTreeMap<String, ApiClass>,
+  new: &'a BTreeMap<String, ApiClass>,
+  changes: Vec<ApiChange>,
+}
+
+impl Comparison<'_> {
+  fn push(&mut self, compatibility: Compatibility, element: &str, message: String) {
+    self.changes.push(ApiChange { compatibility, element: element.to_string(), message });
+  }
+
+  // 修飾子の追加と削除で互換性が変わるもの
+  fn flag(&mut self, element: &str, (old, new): (u16, u16), flag: u16, name: &str, adding: Compatibility, removing: Compatibility) {
+    if old & flag == 0 && new & flag != 0 {
+      self.push(adding, element, format!("{} added", name));
+    } else if old & flag != 0 && new & flag == 0 {
+      self.push(removing, element, format!("{} removed", name));
+    }
+  }
+
+  fn visibility(&mut self, element: &str, old: u16, new: u16) {
+    if visibility(new) < visibility(old) {
+      self.push(
+        Compatibility::Breaking,
+        element,
+        format!("visibility reduced from {} to {}", visibility_name(old), visibility_name(new)),
+      );
+    } else if visibility(new) > visibility(old) {
+      self.push(
+        Compatibility::Compatible,
+        element,
+        format!("visibility increased from {} to {}", visibility_name(old), visibility_name(new)),
+      );
+    }
+  }
+
+  // 型引数の変更はイレイジャが同じならバイナリ互換 (JLS 13.4.13)、アノテーションはリンクに影響しない
+  fn metadata(&mut self, element: &str, kind: SignatureKind, old: (&Option<String>, &BTreeSet<String>), new: (&Option<String>, &BTreeSet<String>)) {
+    if old.0 != new.0 {
+      self.push(
+        Compatibility::Compatible,
+        element,
+        format!("generic signature changed from {} to {}", signature_text(kind, old.0.as_deref()), signature_text(kind, new.0.as_deref())),
+      );
+    }
+    let added: BTreeSet<String> = new.1.difference(old.1).cloned().collect();
+    let removed: BTreeSet<String> = old.1.difference(new.1).cloned().collect();
+    if !added.is_empty() {
+      self.push(Compatibility::Compatible, element, format!("annotations added: {}", names(&added)));
+    }
+    if !removed.is_empty() {
+      self.push(Compatibility::Compatible, element, format!("annotations removed: {}", names(&removed)));
+    }
+  }
+
+  fn compare_class(&mut self, old: &ApiClass, new: Option<&ApiClass>) {
+    let element = format!("{} {}", class_kind(old.access_flags), java_name(&old.name));
+    let Some(new) = new else {
+      self.push(Compatibility::Breaking, &element, "removed".to_string());
+      return;
+    };
+    self.visibility(&element, old.access_flags, new.access_flags);
+    if !new.is_exported() {
+      return;
+    }
+    // クラスとインターフェースの入れ替え (JLS 13.4.1, 13.5.1)
+    if class_kind(old.access_flags) != class_kind(new.access_flags) {
+      self.push(
+        Compatibility::Breaking,
+        &element,
+        format!("changed from {} to {}", class_kind(old.access_flags), class_kind(new.access_flags)),
+      );
+    }
+    if old.access_flags & ACC_INTERFACE == 0 {
+      self.flag(&element, (old.access_flags, new.access_flags), ACC_ABSTRACT, "abstract", Compatibility::Breaking, Compatibility::Compatible);
+    }
+    self.flag(&element, (old.access_flags, new.access_flags), ACC_FINAL, "final", Compatibility::Breaking, Compatibility::Compatible);
+    if old.access_flags & ACC_INTERFACE == 0 {
+      self.flag(&element, (old.access_flags, new.access_flags), ACC_STATIC, "static", Compatibility::Breaking, Compatibility::Breaking);
+    }
+
+    // 上位の型が減ると、キャストや代入、メソッドの検索が失敗する (JLS 13.4.4)
+    let old_supertypes = supertypes(self.old, &old.name);
+    let new_supertypes = supertypes(self.new, &new.name);
+    let removed: BTreeSet<String> = old_supertypes.difference(&new_supertypes).cloned().collect();
+    let added: BTreeSet<String> = new_supertypes.difference(&old_supertypes).cloned().collect();
+    if !removed.is_empty() {
+      self.push(Compatibility::Breaking, &element, format!("supertypes removed: {}", names(&removed)));
+    }
+    if !added.is_empty() {
+      self.push(Compatibility::Compatible, &element, format!("supertypes added: {}", names(&added)));
+    }
+
+    // sealed (JLS 13.4.2.1, 13.4.2.3)
+    match (&old.permitted_subclasses, &new.permitted_subclasses) {
+      (None, Some(_)) => self.push(Compatibility::Breaking, &element, "sealed added".to_string()),
+      (Some(_), None) => self.push(Compatibility::Compatible, &element, "sealed removed".to_string()),
+      (Some(old_permitted), Some(new_permitted)) => {
+        let removed: BTreeSet<String> = old_permitted.difference(new_permitted).cloned().collect();
+        let added: BTreeSet<String> = new_permitted.difference(old_permitted).cloned().collect();
+        if !removed.is_empty() {
+          self.push(Compatibility::Breaking, &element, format!("permitted subclasses removed: {}", names(&removed)));
+        }
+        if !added.is_empty() {
+          self.push(Compatibility::Compatible, &element, format!("permitted subclasses added: {}", names(&added)));
+        }
+      },
+      (None, None) => {},
+    }
+    self.metadata(&element, SignatureKind::Class, (&old.signature, &old.annotations), (&new.signature, &new.annotations));
+
+    for ((name, descriptor), old_field) in &old.fields {
+      if is_exported(old_field.access_flags) && old_field.access_flags & ACC_SYNTHETIC == 0 {
+        let element = format!("field {}.{}:{}", java_name(&old.name), name, descriptor);
+        self.compare_field(&element, old_field, new.fields.get(&(name.clone(), descriptor.clone())));
+      }
+    }
+    for (key, new_field) in &new.fields {
+      if is_exported(new_field.access_flags)
+        && new_field.access_flags & ACC_SYNTHETIC == 0
+        && !old.fields.get(key).is_some_and(|field| is_exported(field.access_flags))
+      {
+        self.push(Compatibility::Compatible, &format!("field {}.{}:{}", java_name(&new.name), key.0, key.1), "added".to_string());
+      }
+    }
+    for ((name, descriptor), old_method) in &old.methods {
+      if is_exported(old_method.access_flags) && old_method.access_flags & ACC_SYNTHETIC == 0 {
+        let element = format!("method {}.{}{}", java_name(&old.name), name, descriptor);
+        self.compare_method(&element, new, name, descriptor, old_method);
+      }
+    }
+    for (key, new_method) in &new.methods {
+      if is_exported(new_method.access_flags)
+        && new_method.access_flags & ACC_SYNTHETIC == 0
+        && !old.methods.get(key).is_some_and(|method| is_exported(method.access_flags))
+      {
+        // 抽象メソッドの追加もバイナリ互換だが、実装しているクラスはソースの修正が必要になる (JLS 13.4.16, 13.5.3)
+        let message = if new_method.access_flags & ACC_ABSTRACT != 0 { "abstract method added" } else { "added" };
+        self.push(Compatibility::Compatible, &format!("method {}.{}{}", java_name(&new.name), key.0, key.1), message.to_string());
+      }
+    }
+  }
+
+  fn compare_field(&mut self, element: &str, old: &ApiMember, new: Option<&ApiMember>) {
+    // 型の変更は記述子が変わるので削除として扱う (JLS 13.4.8)
+    let Some(new) = new else {
+      self.push(Compatibility::Breaking, element, "removed".to_string());
+      return;
+    };
+    self.visibility(element, old.access_flags, new.access_flags);
+    self.flag(element, (old.access_flags, new.access_flags), ACC_FINAL, "final", Compatibility::Breaking, Compatibility::Compatible);
+    self.flag(element, (old.access_flags, new.access_flags), ACC_STATIC, "static", Compatibility::Breaking, Compatibility::Breaking);
+    // 定数は呼び出し側に埋め込まれるので、再コンパイルするまで古い値が使われる (JLS 13.4.9)
+    if old.constant != new.constant {
+      let value = |constant: &Option<String>| constant.clone().unwrap_or_else(|| "(none)".to_string());
+      self.push(
+        Compatibility::Compatible,
+        element,
+        format!("constant value changed from {} to {} (existing binaries keep the old value)", value(&old.constant), value(&new.constant)),
+      );
+    }
+    self.metadata(element, SignatureKind::Field, (&old.signature, &old.annotations), (&new.signature, &new.annotations));
+  }
+
+  fn compare_method(&mut self, element: &str, new_class: &ApiClass, name: &str, descriptor: &str, old: &ApiMember) {
+    let Some(new) = new_class.methods.get(&(name.to_string(), descriptor.to_string())) else {
+      // スーパークラスに移したメソッドは引き続き見つかる (JLS 13.4.12)
+      let inherited = supertypes(self.new, &new_class.name).into_iter().find(|supertype| {
+        self.new.get(supertype).and_then(|class| class.methods.get(&(name.to_string(), descriptor.to_string()))).is_some_and(|method| {
+          is_exported(method.access_flags) && method.access_flags & ACC_STATIC == old.access_flags & ACC_STATIC
+        })
+      });
+      match inherited {
+        Some(supertype) => self.push(Compatibility::Compatible, element, format!("moved to supertype {}", java_name(&supertype))),
+        None => self.push(Compatibility::Breaking, element, "removed".to_string()),
+      }
+      return;
+    };
+    self.visibility(element, old.access_flags, new.access_flags);
+    self.flag(element, (old.access_flags, new.access_flags), ACC_ABSTRACT, "abstract", Compatibility::Breaking, Compatibility::Compatible);
+    // finalなクラスのメソッドはもともとオーバーライドできない (JLS 13.4.17)
+    if new_class.access_flags & ACC_FINAL == 0 {
+      self.flag(element, (old.access_flags, new.access_flags), ACC_FINAL, "final", Compatibility::Breaking, Compatibility::Compatible);
+    }
+    self.flag(element, (old.access_flags, new.access_flags), ACC_STATIC, "static", Compatibility::Breaking, Compatibility::Breaking);
+    // throwsはリンクに影響しない (JLS 13.4.21)
+    let added: BTreeSet<String> = new.exceptions.difference(&old.exceptions).cloned().collect();
+    let removed: BTreeSet<String> = old.exceptions.difference(&new.exceptions).cloned().collect();
+    if !added.is_empty() {
+      self.push(Compatibility::Compatible, element, format!("thrown exceptions added: {}", names(&added)));
+    }
+    if !removed.is_empty() {
+      self.push(Compatibility::Compatible, element, format!("thrown exceptions removed: {}", names(&removed)));
+    }
+    self.metadata(element, SignatureKind::Method, (&old.signature, &old.annotations), (&new.signature, &new.annotations));
+  }
+}
+
+// 古い版から新しい版への公開APIの変更
+pub fn compare(old: &BTreeMap<String, ApiClass>, new: &BTreeMap<String, ApiClass>) -> Vec<ApiChange> {
+  let mut comparison = Comparison { old, new, changes: Vec::new() };
+  for (name, old_class) in old {
+    if old_class.is_exported() {
+      comparison.compare_class(old_class, new.get(name));
+    }
+  }
+  for (name, new_class) in new {
+    if new_class.is_exported() && !old.get(name).is_some_and(ApiClass::is_exported) {
+      let element = format!("{} {}", class_kind(new_class.access_flags), java_name(name));
+      comparison.push(Compatibility::Compatible, &element, "added".to_string());
+    }
+  }
+  comparison.changes
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn signatures_are_parsed_with_the_grammar_of_the_element() {
+    // クラスのシグネチャはフィールドの型としても読めてしまう
+    assert_eq!(
+      signature_text(SignatureKind::Class, Some("Ljava/lang/Object;Ljava/lang/Comparable<La/B;>;")),
+      "extends java.lang.Object implements java.lang.Comparable<a.B>",
+    );
+    assert_eq!(
+      signature_text(SignatureKind::Class, Some("<T:Ljava/lang/Object;>Ljava/util/AbstractList<TT;>;")),
+      "<T> extends java.util.AbstractList<T>",
+    );
+    assert_eq!(signature_text(SignatureKind::Field, Some("Ljava/util/List<Ljava/lang/String;>;")), "java.util.List<java.lang.String>");
+    assert_eq!(signature_text(SignatureKind::Method, Some("<T:Ljava/lang/Object;>(TT;I)TT;")), "<T> T (T, int)");
+    assert_eq!(signature_text(SignatureKind::Field, Some("(I)V")), "(I)V");
+    assert_eq!(signature_text(SignatureKind::Method, None), "(none)");
+  }
+}
@@ -0,0 +1,75 @@
+use std::{fmt::Write as _, path::Path};
+
+use crate::{
+  apidiff::{ApiChange, Compatibility, api_classes, compare},
+  deps::read_input,
+  util::json,
+};
+
+pub fn to_text(changes: &[ApiChange]) -> String {
+  let mut text = String::new();
+  for change in changes {
+    let label = match change.compatibility {
+      Compatibility::Breaking => "BREAKING",
+      Compatibility::Compatible => "compatible",
+    };
+    let _ = writeln!(text, "{:<10}  {}: {}", label, change.element, change.message);
+  }
+  let breaking = changes.iter().filter(|change| change.compatibility == Compatibility::Breaking).count();
+  if !changes.is_empty() {
+    text.push('\n');
+  }
+  let _ = writeln!(text, "{} changes: {} breaking, {} compatible", changes.len(), breaking, changes.len() - breaking);
+  text
+}
+
+pub fn to_json(changes: &[ApiChange]) -> String {
+  let breaking = changes.iter().filter(|change| change.compatibility == Compatibility::Breaking).count();
+  let mut json = String::from("{\n");
+  let _ = writeln!(json, "  \"breaking\": {},", breaking);
+  let _ = writeln!(json, "  \"compatible\": {},", changes.len() - breaking);
+  json.push_str("  \"changes\": [");
+  for (i, change) in changes.iter().enumerate() {
+    let _ = write!(
+      json,
+      "{}\n    {{\"compatibility\": {}, \"element\": {}, \"message\": {}}}",
+      if i == 0 { "" } else { "," },
+      json::string(change.compatibility.name()),
+      json::string(&change.element),
+      json::string(&change.message),
+    );
+  }
+  json.push_str("\n  ]\n}\n");
+  json
+}
+
+// 2つの版 (クラスファイル、ディレクトリかjar) の公開APIを比べる
+// 終了コードは、互換性を壊す変更がなければ0、あれば1、読み込めないなどのエラーなら2
+pub fn print_command(program: &str, args: &[String]) -> i32 {
+  let (json, paths) = match args {
+    [flag, paths @ ..] if flag == "--json" => (true, paths),
+    paths => (false, paths),
+  };
+  let [old_path, new_path] = paths else {
+    eprintln!("Usage: {} apidiff [--json] <old class file | directory | jar> <new class file | directory | jar>", program);
+    return 2;
+  };
+  let mut versions = Vec::new();
+  for path in [old_path, new_path] {
+    match read_input(Path::new(path)) {
+      Ok((_, classes)) => versions.push(api_classes(&classes)),
+      Err(e) => {
+        eprintln!("Error: {}: {}", path, e);
+        return 2;
+      },
+    }
+  }
+  let mut changes = compare(&versions[0], &versions[1]);
+  changes.sort();
+  if json {
+    print!("{}", to_json(&changes));
+  } else {
+    print!("{}", to_text(&changes));
+  }
+  if changes.iter().any(|change| change.compatibility == Compatibility::Breaking) { 1 } else { 0 }
+}
@@ -0,0 +1,594 @@
+pub mod report;
+
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+  fmt,
+};
+
+use crate::{
+  class_leader,
+  structure::class::{BootstrapMethod, ClassFile, ClassFileAttribute, Constant, ConstantPool, Method, MethodInfoAttribute},
+  util::{class::method_matches, class_path::ClassPath},
+};
+
+// invoke命令から作る静的なコールグラフ (CHA/RTA) と、入口から到達できないメソッドの検出
+
+const ACC_PRIVATE: u16 = 0x0002;
+const ACC_STATIC: u16 = 0x0008;
+const ACC_INTERFACE: u16 = 0x0200;
+const ACC_ABSTRACT: u16 = 0x0400;
+
+// MethodHandleのreference_kind
+const REF_INVOKE_VIRTUAL: u8 = 5;
+const REF_INVOKE_STATIC: u8 = 6;
+const REF_INVOKE_SPECIAL: u8 = 7;
+const REF_NEW_INVOKE_SPECIAL: u8 = 8;
+const REF_INVOKE_INTERFACE: u8 = 9;
+
+// java.lang.Objectが読み込めないときに使う、オーバーライドできるメソッド
+const OBJECT_METHODS: [(&str, &str); 5] = [
+  ("toString", "()Ljava/lang/String;"),
+  ("hashCode", "()I"),
+  ("equals", "(Ljava/lang/Object;)Z"),
+  ("clone", "()Ljava/lang/Object;"),
+  ("finalize", "()V"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MethodRef {
+  pub class: String,
+  pub name: String,
+  pub descriptor: String,
+}
+
+impl MethodRef {
+  pub fn new(class: &str, name: &str, descriptor: &str) -> MethodRef {
+    MethodRef { class: class.to_string(), name: name.to_string(), descriptor: descriptor.to_string() }
+  }
+}
+
+impl fmt::Display for MethodRef {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}.{}{}", self.class.replace('/', "."), self.name, self.descriptor)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CallKind {
+  Static,
+  Special,
+  Virtual,
+  Interface,
+  // invokedynamicのブートストラップ引数のメソッドハンドル (ラムダ式の本体やメソッド参照)
+  Dynamic,
+  // new、getstatic/putstatic、invokestaticによるクラスの初期化 (<clinit>)
+  Initialization,
+}
+
+impl CallKind {
+  pub fn name(&self) -> &'static str {
+    match self {
+      CallKind::Static => "invokestatic",
+      CallKind::Special => "invokespecial",
+      CallKind::Virtual => "invokevirtual",
+      CallKind::Interface => "invokeinterface",
+      CallKind::Dynamic => "invokedynamic",
+      CallKind::Initialization => "clinit",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+  // Class Hierarchy Analysis: 受け手の型の全てのサブクラスに振り分ける
+  Cha,
+  // Rapid Type Analysis: 到達できるメソッドでnewしたクラスだけに振り分ける
+  Rta,
+}
+
+impl Algorithm {
+  pub fn name(&self) -> &'static str {
+    match self {
+      Algorithm::Cha => "CHA",
+      Algorithm::Rta => "RTA",
+    }
+  }
+}
+
+pub enum EntryPoint {
+  // public static void main(String[])
+  Main,
+  // クラスの内部名とメソッドの指定 ("name"か"name(descriptor)")
+  Method(String, String),
+  // このアノテーション (内部名) が付いたメソッド
+  Annotation(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CallEdge {
+  pub caller: MethodRef,
+  pub callee: MethodRef,
+  pub kind: CallKind,
+}
+
+#[derive(Debug, Clone)]
+struct CallSite {
+  kind: CallKind,
+  target: MethodRef,
+  // 受け手のクラスで振り分けるか (invokevirtual/invokeinterfaceと、それに相当するメソッドハンドル)
+  dispatch: bool,
+}
+
+struct MethodData {
+  access_flags: u16,
+  annotations: Vec<String>,
+  calls: Vec<CallSite>,
+  // newしたクラス
+  instantiations: Vec<String>,
+}
+
+struct ClassData {
+  super_name: Option<String>,
+  interfaces: Vec<String>,
+  access_flags: u16,
+  methods: Vec<(String, String, u16)>,
+  // 解析対象の入力のクラスか (falseならクラスパスから読んだ上位の型)
+  program: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct CallGraph {
+  pub algorithm: Option<&'static str>,
+  // 入力のクラスのメソッド (抽象メソッドを含む)
+  pub methods: BTreeMap<MethodRef, u16>,
+  pub edges: BTreeSet<CallEdge>,
+  pub entry_points: BTreeSet<MethodRef>,
+  pub reachable: BTreeSet<MethodRef>,
+  // ライブラリの型のメソッドをオーバーライドしていて、ライブラリから呼ばれうるメソッド
+  pub callbacks: BTreeSet<MethodRef>,
+  pub instantiated: BTreeSet<String>,
+}
+
+fn bootstrap_methods(class_file: &ClassFile) -> &[BootstrapMethod] {
+  class_file
+    .attributes
+    .attributes
+    .iter()
+    .find_map(|attribute| match attribute {
+      ClassFileAttribute::BootstrapMethods(attribute) => Some(attribute.bootstrap_methods.as_slice()),
+      _ => None,
+    })
+    .unwrap_or_default()
+}
+
+fn operand_index(data: &[u8]) -> u16 {
+  match data {
+    [high, low, ..] => u16::from_be_bytes([*high, *low]),
+    _ => 0,
+  }
+}
+
+fn member(pool: &ConstantPool, index: u16) -> Option<MethodRef> {
+  let (class, name, descriptor) = pool.get_member_ref(index).ok()?;
+  Some(MethodRef { class, name, descriptor })
+}
+
+// invokedynamicのブートストラップ引数に含まれるメソッドハンドルを呼び出しとみなす
+fn dynamic_calls(pool: &ConstantPool, bootstrap_methods: &[BootstrapMethod], index: u16, calls: &mut Vec<CallSite>) {
+  let Ok(Constant::InvokeDynamic { bootstrap_method_attr_index, .. }) = pool.get_class(index) else {
+    return;
+  };
+  let Some(bootstrap) = bootstrap_methods.get(*bootstrap_method_attr_index as usize) else {
+    return;
+  };
+  for &argument in std::iter::once(&bootstrap.bootstrap_method_attr_index).chain(&bootstrap.bootstrap_arguments) {
+    let Ok(Constant::MethodHandle { reference_kind, reference_index }) = pool.get_class(argument) else {
+      continue;
+    };
+    let Some(target) = member(pool, *reference_index) else {
+      continue;
+    };
+    let dispatch = match *reference_kind {
+      REF_INVOKE_STATIC | REF_INVOKE_SPECIAL | REF_NEW_INVOKE_SPECIAL => false,
+      REF_INVOKE_VIRTUAL | REF_INVOKE_INTERFACE => true,
+      _ => continue,
+    };
+    calls.push(CallSite { kind: CallKind::Dynamic, target, dispatch });
+  }
+}
+
+fn initialization(class: &str) -> CallSite {
+  CallSite { kind: CallKind::Initialization, target: MethodRef::new(class, "<clinit>", "()V"), dispatch: false }
+}
+
+fn method_data(class_file: &ClassFile, method: &Method) -> MethodData {
+  let pool = &class_file.constant_pool;
+  let bootstrap_methods = bootstrap_methods(class_file);
+  let mut data = MethodData { access_flags: method.access_flags, annotations: Vec::new(), calls: Vec::new(), instantiations: Vec::new() };
+  for attribute in &method.attributes.attributes {
+    match attribute {
+      MethodInfoAttribute::RuntimeVisibleAnnotations(attribute) => {
+        data.annotations.extend(attribute.annotations.iter().filter_map(|annotation| pool.get_utf8(annotation.type_index).ok()))
+      },
+      MethodInfoAttribute::RuntimeInvisibleAnnotations(attribute) => {
+        data.annotations.extend(attribute.annotations.iter().filter_map(|annotation| pool.get_utf8(annotation.type_index).ok()))
+      },
+      MethodInfoAttribute::Code(code) => {
+        for instruction in &code.code {
+          let index = operand_index(&instruction.data);
+          match instruction.opcode {
+            0xb6..=0xb9 => {
+              let kind = match instruction.opcode {
+                0xb6 => CallKind::Virtual,
+                0xb7 => CallKind::Special,
+                0xb8 => CallKind::Static,
+                _ => CallKind::Interface,
+              };
+              if let Some(target) = member(pool, index) {
+                if kind == CallKind::Static {
+                  data.calls.push(initialization(&target.class));
+                }
+                let dispatch = matches!(kind, CallKind::Virtual | CallKind::Interface);
+                data.calls.push(CallSite { kind, target, dispatch });
+              }
+            },
+            0xba => dynamic_calls(pool, bootstrap_methods, index, &mut data.calls),
+            // getstatic/putstatic
+            0xb2 | 0xb3 => {
+              if let Some(field) = member(pool, index) {
+                data.calls.push(initialization(&field.class));
+              }
+            },
+            // new
+            0xbb => {
+              if let Ok(class) = pool.get_class_name(index) {
+                data.calls.push(initialization(&class));
+                data.instantiations.push(class);
+              }
+            },
+            _ => {},
+          }
+        }
+      },
+      _ => {},
+    }
+  }
+  data
+}
+
+fn class_data(class_file: &ClassFile, program: bool) -> Option<(String, ClassData)> {
+  let pool = &class_file.constant_pool;
+  let name = pool.get_class_name(class_file.this_class).ok()?;
+  let super_name = if class_file.super_class == 0 { None } else { pool.get_class_name(class_file.super_class).ok() };
+  let interfaces = class_file.interfaces.interfaces.iter().filter_map(|&index| pool.get_class_name(index).ok()).collect();
+  let methods = class_file
+    .methods
+    .methods
+    .iter()
+    .filter_map(|method| Some((pool.get_utf8(method.name_index).ok()?, pool.get_utf8(method.descriptor_index).ok()?, method.access_flags)))
+    .collect();
+  Some((name, ClassData { super_name, interfaces, access_flags: class_file.access_flags, methods, program }))
+}
+
+struct Builder {
+  algorithm: Algorithm,
+  classes: HashMap<String, ClassData>,
+  methods: BTreeMap<MethodRef, MethodData>,
+  // 型ごとの、そのサブタイプになる具象クラス (入力のクラスのみ、自分自身を含む)
+  subtypes: HashMap<String, Vec<String>>,
+  graph: CallGraph,
+  queue: VecDeque<MethodRef>,
+  // RTAでnewしたクラスが増えたときに振り分け直す仮想呼び出し (受け手の型ごと)
+  virtual_sites: HashMap<String, Vec<(MethodRef, CallSite)>>,
+}
+
+impl Builder {
+  fn is_program(&self, class: &str) -> bool {
+    self.classes.get(class).is_some_and(|class| class.program)
+  }
+
+  // 自分自身を含む全ての上位の型 (読み込めない型はそこで止まる)
+  fn supertypes(&self, class: &str) -> BTreeSet<String> {
+    let mut supertypes = BTreeSet::new();
+    let mut stack = vec![class];
+    while let Some(name) = stack.pop() {
+      if !supertypes.insert(name.to_string()) {
+        continue;
+      }
+      if let Some(data) = self.classes.get(name) {
+        stack.extend(data.super_name.as_deref());
+        stack.extend(data.interfaces.iter().map(String::as_str));
+      }
+    }
+    supertypes.insert("java/lang/Object".to_string());
+    supertypes
+  }
+
+  fn declares(&self, class: &str, name: &str, descriptor: &str) -> Option<u16> {
+    let data = self.classes.get(class)?;
+    data.methods.iter().find(|(n, d, _)| n == name && d == descriptor).map(|(_, _, flags)| *flags)
+  }
+
+  // 静的な解決 (invokestatic/invokespecial): スーパークラスを辿って宣言を探す
+  fn resolve(&self, target: &MethodRef) -> MethodRef {
+    let mut class = Some(target.class.as_str());
+    while let Some(name) = class {
+      if self.declares(name, &target.name, &target.descriptor).is_some() {
+        return MethodRef::new(name, &target.name, &target.descriptor);
+      }
+      class = self.classes.get(name).and_then(|data| data.super_name.as_deref());
+    }
+    target.clone()
+  }
+
+  // 実行時のクラスclassで選ばれるメソッド (スーパークラス、次にデフォルトメソッド)
+  fn dispatch(&self, class: &str, name: &str, descriptor: &str) -> Option<MethodRef> {
+    let mut current = Some(class);
+    while let Some(class) = current {
+      if let Some(flags) = self.declares(class, name, descriptor)
+        && flags & ACC_STATIC == 0
+      {
+        return (flags & ACC_ABSTRACT == 0).then(|| MethodRef::new(class, name, descriptor));
+      }
+      current = self.classes.get(class).and_then(|data| data.super_name.as_deref());
+    }
+    let mut stack = vec![class];
+    let mut visited = BTreeSet::new();
+    while let Some(class) = stack.pop() {
+      if !visited.insert(class) {
+        continue;
+      }
+      let Some(data) = self.classes.get(class) else {
+        continue;
+      };
+      if data.access_flags & ACC_INTERFACE != 0
+        && let Some(flags) = self.declares(class, name, descriptor)
+        && flags & (ACC_STATIC | ACC_ABSTRACT | ACC_PRIVATE) == 0
+      {
+        return Some(MethodRef::new(class, name, descriptor));
+      }
+      stack.extend(data.super_name.as_deref());
+      stack.extend(data.interfaces.iter().map(String::as_str));
+    }
+    None
+  }
+
+  fn is_concrete(&self, class: &str) -> bool {
+    self.classes.get(class).is_some_and(|data| data.program && data.access_flags & (ACC_INTERFACE | ACC_ABSTRACT) == 0)
+  }
+
+  // 仮想呼び出しを受け手になりうる入力のクラスに振り分ける (RTAではその時点でnewしたクラスだけ)
+  fn virtual_targets(&self, target: &MethodRef) -> BTreeSet<MethodRef> {
+    let receivers = self.subtypes.get(&target.class).map(Vec::as_slice).unwrap_or_default();
+    receivers
+      .iter()
+      .filter(|class| self.algorithm == Algorithm::Cha || self.graph.instantiated.contains(*class))
+      .filter_map(|class| self.dispatch(class, &target.name, &target.descriptor))
+      .collect()
+  }
+
+  fn targets(&self, site: &CallSite) -> BTreeSet<MethodRef> {
+    if site.kind == CallKind::Initialization {
+      // 初期化するクラスとそのスーパークラスの<clinit>
+      let mut targets = BTreeSet::new();
+      let mut class = Some(site.target.class.as_str());
+      while let Some(name) = class.filter(|name| self.is_program(name)) {
+        if self.declares(name, "<clinit>", "()V").is_some() {
+          targets.insert(MethodRef::new(name, "<clinit>", "()V"));
+        }
+        class = self.classes.get(name).and_then(|data| data.super_name.as_deref());
+      }
+      return targets;
+    }
+    let resolved = self.resolve(&site.target);
+    // privateメソッドはオーバーライドされない (ネストメイトはinvokevirtualで呼ぶ)
+    if !site.dispatch || self.declares(&resolved.class, &resolved.name, &resolved.descriptor).is_some_and(|flags| flags & ACC_PRIVATE != 0) {
+      return BTreeSet::from([resolved]);
+    }
+    let mut targets = self.virtual_targets(&site.target);
+    if !self.is_program(&site.target.class) {
+      targets.insert(site.target.clone());
+    }
+    targets
+  }
+
+  fn add_edges(&mut self, caller: &MethodRef, kind: CallKind, targets: BTreeSet<MethodRef>, mark: bool) {
+    for callee in targets {
+      if mark {
+        self.mark(&callee);
+      }
+      self.graph.edges.insert(CallEdge { caller: caller.clone(), callee, kind });
+    }
+  }
+
+  fn mark(&mut self, method: &MethodRef) {
+    if self.methods.contains_key(method) && self.graph.reachable.insert(method.clone()) {
+      self.queue.push_back(method.clone());
+    }
+  }
+
+  // ライブラリの上位の型が宣言するメソッドのオーバーライド
+  fn library_overrides(&self, class: &str) -> Vec<MethodRef> {
+    let Some(data) = self.classes.get(class) else {
+      return Vec::new();
+    };
+    let mut supertypes = Vec::new();
+    let mut stack: Vec<&str> = data.super_name.iter().chain(&data.interfaces).map(String::as_str).collect();
+    let mut visited = BTreeSet::new();
+    while let Some(name) = stack.pop() {
+      if !visited.insert(name) {
+        continue;
+      }
+      match self.classes.get(name) {
+        Some(data) => {
+          if !data.program {
+            supertypes.push(Some(name));
+          }
+          stack.extend(data.super_name.as_deref());
+          stack.extend(data.interfaces.iter().map(String::as_str));
+        },
+        // 読み込めない型は、どのメソッドも呼ばれうるとみなす (Objectは既知のメソッドだけ)
+        None if name == "java/lang/Object" => {},
+        None => supertypes.push(None),
+      }
+    }
+    data
+      .methods
+      .iter()
+      .filter(|(name, _, flags)| flags & (ACC_STATIC | ACC_PRIVATE | ACC_ABSTRACT) == 0 && !name.starts_with('<'))
+      .filter(|(name, descriptor, _)| {
+        OBJECT_METHODS.contains(&(name.as_str(), descriptor.as_str()))
+          || supertypes.iter().any(|supertype| supertype.is_none_or(|supertype| self.declares(supertype, name, descriptor).is_some()))
+      })
+      .map(|(name, descriptor, _)| MethodRef::new(class, name, descriptor))
+      .collect()
+  }
+
+  fn instantiate(&mut self, class: &str) {
+    if !self.graph.instantiated.insert(class.to_string()) {
+      return;
+    }
+    if self.algorithm == Algorithm::Rta && self.is_concrete(class) {
+      for supertype in self.supertypes(class) {
+        let sites = self.virtual_sites.get(&supertype).cloned().unwrap_or_default();
+        for (caller, site) in sites {
+          if let Some(method) = self.dispatch(class, &site.target.name, &site.target.descriptor) {
+            self.add_edges(&caller, site.kind, BTreeSet::from([method]), true);
+          }
+        }
+      }
+    }
+    for method in self.library_overrides(class) {
+      self.graph.callbacks.insert(method.clone());
+      self.mark(&method);
+    }
+  }
+
+  fn process(&mut self, method: &MethodRef) {
+    let Some(data) = self.methods.get(method) else {
+      return;
+    };
+    let calls = data.calls.clone();
+    let instantiations = data.instantiations.clone();
+    for class in instantiations {
+      self.instantiate(&class);
+    }
+    for site in calls {
+      let targets = self.targets(&site);
+      if self.algorithm == Algorithm::Rta && site.dispatch {
+        self.virtual_sites.entry(site.target.class.clone()).or_default().push((method.clone(), site.clone()));
+      }
+      self.add_edges(method, site.kind, targets, true);
+    }
+  }
+
+  fn is_entry_point(&self, method: &MethodRef, data: &MethodData, entry_points: &[EntryPoint]) -> bool {
+    entry_points.iter().any(|entry_point| match entry_point {
+      EntryPoint::Main => {
+        method.name == "main" && method.descriptor == "([Ljava/lang/String;)V" && data.access_flags & ACC_STATIC != 0
+      },
+      EntryPoint::Method(class, filter) => *class == method.class && method_matches(filter, &method.name, &method.descriptor),
+      EntryPoint::Annotation(annotation) => data.annotations.iter().any(|descriptor| *descriptor == format!("L{};", annotation)),
+    })
+  }
+}
+
+// 入力のクラスの上位の型をクラスパスから読み込む
+fn load_supertypes(classes: &mut HashMap<String, ClassData>, class_path: &ClassPath, boot_class_path: Option<&ClassPath>) {
+  let mut pending: Vec<String> =
+    classes.values().flat_map(|data| data.super_name.iter().chain(&data.interfaces).cloned()).collect();
+  while let Some(name) = pending.pop() {
+    if classes.contains_key(&name) {
+      continue;
+    }
+    let bytes = boot_class_path.and_then(|path| path.read(&name)).or_else(|| class_path.read(&name));
+    let Some(class_file) = bytes.and_then(|bytes| class_leader::parse_bytes(&bytes).ok()) else {
+      continue;
+    };
+    if let Some((name, data)) = class_data(&class_file, false) {
+      pending.extend(data.super_name.iter().chain(&data.interfaces).cloned());
+      classes.insert(name, data);
+    }
+  }
+}
+
+impl CallGraph {
+  pub fn build(
+    inputs: &[(String, Vec<ClassFile>)],
+    class_path: &ClassPath,
+    boot_class_path: Option<&ClassPath>,
+    algorithm: Algorithm,
+    entry_points: &[EntryPoint],
+  ) -> CallGraph {
+    let mut classes = HashMap::new();
+    let mut methods = BTreeMap::new();
+    for class_file in inputs.iter().flat_map(|(_, classes)| classes) {
+      let Some((name, data)) = class_data(class_file, true) else {
+        continue;
+      };
+      for method in &class_file.methods.methods {
+        let pool = &class_file.constant_pool;
+        if let (Ok(method_name), Ok(descriptor)) = (pool.get_utf8(method.name_index), pool.get_utf8(method.descriptor_index)) {
+          methods.insert(MethodRef { class: name.clone(), name: method_name, descriptor }, method_data(class_file, method));
+        }
+      }
+      classes.insert(name, data);
+    }
+    load_supertypes(&mut classes, class_path, boot_class_path);
+
+    let mut builder = Builder {
+      algorithm,
+      classes,
+      methods,
+      subtypes: HashMap::new(),
+      graph: CallGraph::default(),
+      queue: VecDeque::new(),
+      virtual_sites: HashMap::new(),
+    };
+    let concrete: Vec<String> = builder.classes.keys().filter(|class| builder.is_concrete(class)).cloned().collect();
+    for class in concrete {
+      for supertype in builder.supertypes(&class) {
+        builder.subtypes.entry(supertype).or_default().push(class.clone());
+      }
+    }
+    builder.graph.algorithm = Some(algorithm.name());
+    builder.graph.methods = builder.methods.iter().map(|(method, data)| (method.clone(), data.access_flags)).collect();
+    let entries: Vec<MethodRef> =
+      builder.methods.iter().filter(|(method, data)| builder.is_entry_point(method, data, entry_points)).map(|(method, _)| method.clone()).collect();
+    for method in entries {
+      builder.graph.entry_points.insert(method.clone());
+      builder.mark(&method);
+      // エントリポイントを呼ぶ前に、そのクラスとスーパークラスが初期化される
+      for clinit in builder.targets(&initialization(&method.class)) {
+        builder.mark(&clinit);
+      }
+    }
+    while let Some(method) = builder.queue.pop_front() {
+      builder.process(&method);
+    }
+
+    // 到達できないメソッドからの呼び出しも辺として残す
+    let unreachable: Vec<MethodRef> = builder.methods.keys().filter(|method| !builder.graph.reachable.contains(*method)).cloned().collect();
+    for method in unreachable {
+      for site in builder.methods[&method].calls.clone() {
+        let targets = builder.targets(&site);
+        builder.add_edges(&method, site.kind, targets, false);
+      }
+    }
+    builder.graph
+  }
+
+  pub fn is_program(&self, method: &MethodRef) -> bool {
+    self.methods.contains_key(method)
+  }
+
+  // 到達できない、本体を持つメソッド
+  pub fn unreachable(&self) -> Vec<&MethodRef> {
+    self
+      .methods
+      .iter()
+      .filter(|(method, flags)| *flags & ACC_ABSTRACT == 0 && !self.reachable.contains(*method))
+      .map(|(method, _)| method)
+      .collect()
+  }
+}
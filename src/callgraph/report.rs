@@ -0,0 +1,202 @@
+use std::{collections::BTreeSet, fmt::Write as _, path::Path};
+
+use crate::{
+  callgraph::{Algorithm, CallGraph, CallKind, EntryPoint, MethodRef},
+  deps::read_input,
+  util::{class_path::ClassPathOptions, dot::escape, json},
+};
+
+enum Format {
+  Text,
+  Dot,
+  Json,
+}
+
+// 入口と到達できないメソッドの一覧
+pub fn to_text(graph: &CallGraph) -> String {
+  let mut text = String::new();
+  let _ = writeln!(text, "Algorithm: {}", graph.algorithm.unwrap_or_default());
+  let _ = writeln!(text, "Entry points ({}):", graph.entry_points.len());
+  for method in &graph.entry_points {
+    let _ = writeln!(text, "   {}", method);
+  }
+  let unreachable = graph.unreachable();
+  let _ = writeln!(text, "Reachable methods: {} of {}", graph.reachable.len(), graph.reachable.len() + unreachable.len());
+  let _ = writeln!(text, "Unreachable methods ({}):", unreachable.len());
+  for method in unreachable {
+    let _ = writeln!(text, "   {}", method);
+  }
+  text
+}
+
+// 入口は太線、到達できないメソッドは灰色、ライブラリのメソッドは破線の楕円
+pub fn to_dot(graph: &CallGraph) -> String {
+  let mut dot = String::from("digraph \"callgraph\" {\n");
+  dot.push_str("  node [shape=box, fontname=\"monospace\"];\n");
+  let library: BTreeSet<&MethodRef> = graph.edges.iter().map(|edge| &edge.callee).filter(|method| !graph.is_program(method)).collect();
+  for method in graph.methods.keys() {
+    let style = if graph.entry_points.contains(method) {
+      " [style=bold]"
+    } else if graph.reachable.contains(method) {
+      ""
+    } else {
+      " [color=gray, fontcolor=gray]"
+    };
+    let _ = writeln!(dot, "  \"{}\"{};", escape(&method.to_string()), style);
+  }
+  for method in library {
+    let _ = writeln!(dot, "  \"{}\" [shape=ellipse, style=dashed];", escape(&method.to_string()));
+  }
+  for edge in &graph.edges {
+    let attributes = match edge.kind {
+      CallKind::Dynamic => " [label=\"indy\"]",
+      CallKind::Initialization => " [style=dashed]",
+      _ => "",
+    };
+    let _ = writeln!(dot, "  \"{}\" -> \"{}\"{};", escape(&edge.caller.to_string()), escape(&edge.callee.to_string()), attributes);
+  }
+  dot.push_str("}\n");
+  dot
+}
+
+fn method_json(method: &MethodRef) -> String {
+  format!(
+    "{{\"class\": {}, \"name\": {}, \"descriptor\": {}}}",
+    json::string(&method.class.replace('/', ".")),
+    json::string(&method.name),
+    json::string(&method.descriptor)
+  )
+}
+
+pub fn to_json(graph: &CallGraph) -> String {
+  let mut json = String::from("{\n");
+  let _ = writeln!(json, "  \"algorithm\": {},", json::string(graph.algorithm.unwrap_or_default()));
+  let entry_points: Vec<String> = graph.entry_points.iter().map(method_json).collect();
+  let _ = writeln!(json, "  \"entryPoints\": [{}],", entry_points.join(", "));
+  json.push_str("  \"methods\": [");
+  for (i, method) in graph.methods.keys().enumerate() {
+    let _ = write!(
+      json,
+      "{}\n    {{\"method\": {}, \"reachable\": {}, \"callback\": {}}}",
+      if i == 0 { "" } else { "," },
+      method_json(method),
+      graph.reachable.contains(method),
+      graph.callbacks.contains(method),
+    );
+  }
+  json.push_str("\n  ],\n  \"edges\": [");
+  for (i, edge) in graph.edges.iter().enumerate() {
+    let _ = write!(
+      json,
+      "{}\n    {{\"caller\": {}, \"callee\": {}, \"kind\": {}, \"library\": {}}}",
+      if i == 0 { "" } else { "," },
+      method_json(&edge.caller),
+      method_json(&edge.callee),
+      json::string(edge.kind.name()),
+      !graph.is_program(&edge.callee),
+    );
+  }
+  let unreachable: Vec<String> = graph.unreachable().into_iter().map(method_json).collect();
+  json.push_str("\n  ],\n  \"unreachable\": [");
+  for (i, method) in unreachable.iter().enumerate() {
+    let _ = write!(json, "{}\n    {}", if i == 0 { "" } else { "," }, method);
+  }
+  json.push_str("\n  ]\n}\n");
+  json
+}
+
+// "Class.method"か"Class.method(descriptor)"の指定をクラスの内部名とメソッドの指定に分ける
+fn parse_entry(text: &str) -> Option<EntryPoint> {
+  let head = &text[..text.find('(').unwrap_or(text.len())];
+  let dot = head.rfind('.')?;
+  Some(EntryPoint::Method(text[..dot].replace('.', "/"), text[dot + 1..].to_string()))
+}
+
+// クラスファイル、ディレクトリ、jarのコールグラフを作り、到達できないメソッドを表示する
+pub fn print_command(program: &str, args: &[String]) -> i32 {
+  let usage = || {
+    eprintln!(
+      "Usage: {} callgraph [-cp <path>] [-Xbootclasspath:<path>] [--rta] [--entry <class>.<method>[<descriptor>]]... [--entry-annotation <annotation>]... [--dot | --json] <class file | directory | jar>...",
+      program
+    );
+    2
+  };
+  let mut class_path_options = ClassPathOptions::default();
+  let mut algorithm = Algorithm::Cha;
+  let mut entry_points = vec![EntryPoint::Main];
+  let mut format = Format::Text;
+  let mut paths = Vec::new();
+  let mut i = 0;
+  while i < args.len() {
+    match class_path_options.parse_arg(&args[i..]) {
+      Ok(0) => {},
+      Ok(used) => {
+        i += used;
+        continue;
+      },
+      Err(_) => return usage(),
+    }
+    let arg = &args[i];
+    if arg == "--entry" || arg == "--entry-annotation" {
+      i += 1;
+      let Some(value) = args.get(i) else {
+        return usage();
+      };
+      if arg == "--entry" {
+        match parse_entry(value) {
+          Some(entry_point) => entry_points.push(entry_point),
+          None => return usage(),
+        }
+      } else {
+        entry_points.push(EntryPoint::Annotation(value.replace('.', "/")));
+      }
+    } else if arg == "--cha" {
+      algorithm = Algorithm::Cha;
+    } else if arg == "--rta" {
+      algorithm = Algorithm::Rta;
+    } else if arg == "--dot" {
+      format = Format::Dot;
+    } else if arg == "--json" {
+      format = Format::Json;
+    } else if arg.starts_with('-') {
+      return usage();
+    } else {
+      paths.push(arg);
+    }
+    i += 1;
+  }
+  if paths.is_empty() {
+    return usage();
+  }
+  let class_path = match class_path_options.class_path("") {
+    Ok(class_path) => class_path,
+    Err(e) => {
+      eprintln!("Error: {}", e);
+      return 1;
+    },
+  };
+  let boot_class_path = match class_path_options.boot_class_path() {
+    Ok(boot_class_path) => boot_class_path,
+    Err(e) => {
+      eprintln!("Error: {}", e);
+      return 1;
+    },
+  };
+  let mut inputs = Vec::new();
+  for path in paths {
+    match read_input(Path::new(path)) {
+      Ok(input) => inputs.push(input),
+      Err(e) => {
+        eprintln!("Error: {}: {}", path, e);
+        return 1;
+      },
+    }
+  }
+  let graph = CallGraph::build(&inputs, &class_path, boot_class_path.as_ref(), algorithm, &entry_points);
+  match format {
+    Format::Text => print!("{}", to_text(&graph)),
+    Format::Dot => print!("{}", to_dot(&graph)),
+    Format::Json => print!("{}", to_json(&graph)),
+  }
+  0
+}
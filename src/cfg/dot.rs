@@ -0,0 +1,144 @@
+use std::fs;
+
+use crate::{
+  cfg::{ControlFlowGraph, EdgeKind},
+  class_leader,
+  structure::class::{ConstantPool, MethodInfoAttribute},
+  util::{class::method_matches, dot::escape, graph::NaturalLoop},
+};
+
+// 制御フローの辺に重ねて表示するもの
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Overlay {
+  // 直接支配ブロックからの点線の辺
+  pub dominators: bool,
+  // 自然ループごとにブロックを囲む
+  pub loops: bool,
+}
+
+// ノードごとにブロックの命令を左寄せで並べたGraphvizのDOT
+pub fn to_dot(graph: &ControlFlowGraph, name: &str, pool: Option<&ConstantPool>, overlay: Overlay) -> String {
+  let mut dot = format!("digraph \"{}\" {{\n", escape(name));
+  dot.push_str("  node [shape=box, fontname=\"monospace\"];\n");
+  let reachable = graph.reachable();
+  let node = |i: usize, indent: &str| {
+    let mut label = format!("B{}\\l", i);
+    for &pc in &graph.blocks[i].instructions {
+      label.push_str(&format!("{}: {}\\l", pc, escape(&graph.instruction_text(pc, pool))));
+    }
+    // 到達できないブロックは灰色にする
+    let style = if reachable[i] { "" } else { ", color=gray, fontcolor=gray" };
+    format!("{}B{} [label=\"{}\"{}];\n", indent, i, label, style)
+  };
+  let loops = if overlay.loops { graph.loops() } else { Vec::new() };
+  // ブロックを含む最も内側のループ
+  let innermost: Vec<Option<usize>> = (0..graph.blocks.len())
+    .map(|block| (0..loops.len()).filter(|&l| loops[l].body.contains(&block)).min_by_key(|&l| loops[l].body.len()))
+    .collect();
+  for l in (0..loops.len()).filter(|&l| loops[l].parent.is_none()) {
+    write_loop(&mut dot, &loops, &innermost, l, 1, &node);
+  }
+  for i in (0..graph.blocks.len()).filter(|&i| innermost[i].is_none()) {
+    dot.push_str(&node(i, "  "));
+  }
+  for (i, block) in graph.blocks.iter().enumerate() {
+    for edge in &block.successors {
+      let attributes = match &edge.kind {
+        EdgeKind::Fallthrough | EdgeKind::Jump => String::new(),
+        EdgeKind::Branch(taken) => format!(" [label=\"{}\"]", taken),
+        EdgeKind::Switch(Some(key)) => format!(" [label=\"{}\"]", key),
+        EdgeKind::Switch(None) => " [label=\"default\"]".to_string(),
+        EdgeKind::Jsr => " [label=\"jsr\", style=dashed]".to_string(),
+        EdgeKind::Ret => " [label=\"ret\", style=dashed]".to_string(),
+        EdgeKind::Exception(catch_type) => {
+          format!(" [label=\"{}\", style=dashed, color=red, fontcolor=red]", escape(catch_type.as_deref().unwrap_or("any")))
+        },
+      };
+      dot.push_str(&format!("  B{} -> B{}{};\n", i, edge.target, attributes));
+    }
+  }
+  if overlay.dominators {
+    for (i, dominator) in graph.immediate_dominators().iter().enumerate() {
+      if let Some(dominator) = dominator {
+        dot.push_str(&format!("  B{} -> B{} [style=dotted, color=blue, arrowhead=empty, constraint=false];\n", dominator, i));
+      }
+    }
+  }
+  dot.push_str("}\n");
+  dot
+}
+
+// ループのクラスタの中に、内側のループとこのループが最も内側になるブロックを置く
+fn write_loop(dot: &mut String, loops: &[NaturalLoop], innermost: &[Option<usize>], l: usize, depth: usize, node: &dyn Fn(usize, &str) -> String) {
+  let indent = "  ".repeat(depth);
+  dot.push_str(&format!("{}subgraph cluster_loop{} {{\n", indent, l));
+  dot.push_str(&format!("{}  label=\"loop B{}\";\n{}  style=dashed;\n", indent, loops[l].header, indent));
+  for inner in (0..loops.len()).filter(|&inner| loops[inner].parent == Some(l)) {
+    write_loop(dot, loops, innermost, inner, depth + 1, node);
+  }
+  for block in loops[l].body.iter().copied().filter(|&block| innermost[block] == Some(l)) {
+    dot.push_str(&node(block, &format!("{}  ", indent)));
+  }
+  dot.push_str(&format!("{}}}\n", indent));
+}
+
+// クラスファイルのメソッドの制御フローグラフをDOTで表示する
+pub fn print_command(program: &str, args: &[String]) -> i32 {
+  let mut overlay = Overlay::default();
+  let mut positional = Vec::new();
+  for arg in args {
+    match arg.as_str() {
+      "--dominators" => overlay.dominators = true,
+      "--loops" => overlay.loops = true,
+      _ => positional.push(arg),
+    }
+  }
+  let (path, filter) = match positional[..] {
+    [path] if !path.starts_with('-') => (path, None),
+    [path, method] if !path.starts_with('-') => (path, Some(method.as_str())),
+    _ => {
+      eprintln!("Usage: {} cfg [--dominators] [--loops] <class file> [<method>[<descriptor>]]", program);
+      return 2;
+    },
+  };
+  let class_file = match fs::read(path).and_then(|bytes| class_leader::parse_bytes(&bytes)) {
+    Ok(class_file) => class_file,
+    Err(e) => {
+      eprintln!("Error: {}: {}", path, e);
+      return 1;
+    },
+  };
+  let pool = &class_file.constant_pool;
+  let class_name = pool.get_class_name(class_file.this_class).unwrap_or_default();
+  let mut status = 0;
+  let mut found = false;
+  for method in &class_file.methods.methods {
+    let code = method.attributes.attributes.iter().find_map(|attribute| match attribute {
+      MethodInfoAttribute::Code(code) => Some(code),
+      _ => None,
+    });
+    let Some(code) = code else {
+      continue;
+    };
+    let name = pool.get_utf8(method.name_index).unwrap_or_default();
+    let descriptor = pool.get_utf8(method.descriptor_index).unwrap_or_default();
+    if filter.is_some_and(|filter| !method_matches(filter, &name, &descriptor)) {
+      continue;
+    }
+    found = true;
+    match ControlFlowGraph::build(code, pool) {
+      Ok(graph) => print!("{}", to_dot(&graph, &format!("{}.{}{}", class_name, name, descriptor), Some(pool), overlay)),
+      Err(e) => {
+        eprintln!("Error: {}{}: {}", name, descriptor, e);
+        status = 1;
+      },
+    }
+  }
+  if let Some(filter) = filter
+    && !found
+  {
+    eprintln!("Error: method not found: {}", filter);
+    return 1;
+  }
+  status
+}
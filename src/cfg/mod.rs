@@ -0,0 +1,348 @@
+pub mod dot;
+
+use std::collections::BTreeSet;
+
+use crate::{
+  runtime::trace::instruction_text,
+  structure::class::{CodeAttribute, ConstantPool},
+  util::graph::{self, NaturalLoop},
+};
+
+// メソッドのバイトコードの制御フローグラフ
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EdgeKind {
+  // 分岐しない命令から次の命令へ
+  Fallthrough,
+  // goto
+  Jump,
+  // 条件分岐の成立 (true) と不成立 (false)
+  Branch(bool),
+  // switchのcase (Noneならdefault)
+  Switch(Option<i32>),
+  // jsrからサブルーチンへ
+  Jsr,
+  // retからjsrの次の命令へ
+  Ret,
+  // 例外ハンドラへ (catch_typeがNoneなら全ての例外)
+  Exception(Option<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+  pub target: usize,
+  pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone)]
+pub struct BasicBlock {
+  pub start_pc: usize,
+  // 最後の命令の次の位置
+  pub end_pc: usize,
+  // 命令の位置
+  pub instructions: Vec<usize>,
+  pub successors: Vec<Edge>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ControlFlowGraph {
+  // blocks[0]が入口 (到達できないブロックも含め、位置の順)
+  pub blocks: Vec<BasicBlock>,
+  code: Vec<u8>,
+}
+
+// 命令の後に進む先
+enum Flow {
+  Next,
+  Goto(usize),
+  Conditional(usize),
+  Switch(Vec<(Option<i32>, usize)>),
+  Jsr(usize),
+  Ret,
+  Exit,
+}
+
+fn flow(code: &[u8], pc: usize) -> Flow {
+  let u8_at = |at: usize| code.get(at).copied().unwrap_or(0);
+  let i16_at = |at: usize| i16::from_be_bytes([u8_at(at), u8_at(at + 1)]) as i64;
+  let i32_at = |at: usize| i32::from_be_bytes([u8_at(at), u8_at(at + 1), u8_at(at + 2), u8_at(at + 3)]);
+  let target = |offset: i64| (pc as i64 + offset) as usize;
+  match u8_at(pc) {
+    0x99..=0xa6 | 0xc6 | 0xc7 => Flow::Conditional(target(i16_at(pc + 1))),
+    0xa7 => Flow::Goto(target(i16_at(pc + 1))),
+    0xc8 => Flow::Goto(target(i32_at(pc + 1) as i64)),
+    0xa8 => Flow::Jsr(target(i16_at(pc + 1))),
+    0xc9 => Flow::Jsr(target(i32_at(pc + 1) as i64)),
+    0xa9 => Flow::Ret,
+    0xc4 if u8_at(pc + 1) == 0xa9 => Flow::Ret,
+    0xaa => {
+      let base = pc + 1 + (4 - (pc + 1) % 4) % 4;
+      let (low, high) = (i32_at(base + 4), i32_at(base + 8));
+      let mut cases: Vec<(Option<i32>, usize)> =
+        (low..=high).enumerate().map(|(i, key)| (Some(key), target(i32_at(base + 12 + i * 4) as i64))).collect();
+      cases.push((None, target(i32_at(base) as i64)));
+      Flow::Switch(cases)
+    },
+    0xab => {
+      let base = pc + 1 + (4 - (pc + 1) % 4) % 4;
+      let pairs = i32_at(base + 4).max(0) as usize;
+      let mut cases: Vec<(Option<i32>, usize)> =
+        (0..pairs).map(|i| (Some(i32_at(base + 8 + i * 8)), target(i32_at(base + 12 + i * 8) as i64))).collect();
+      cases.push((None, target(i32_at(base) as i64)));
+      Flow::Switch(cases)
+    },
+    0xac..=0xb1 | 0xbf => Flow::Exit,
+    _ => Flow::Next,
+  }
+}
+
+impl ControlFlowGraph {
+  pub fn build(code: &CodeAttribute, pool: &ConstantPool) -> Result<ControlFlowGraph, String> {
+    Self::build_split(code, pool, &|_| false)
+  }
+
+  // split_afterが真になる位置の命令の後でもブロックを分ける (IRで例外を投げる命令ごとにハンドラへの辺を張るため)
+  pub fn build_split(code: &CodeAttribute, pool: &ConstantPool, split_after: &dyn Fn(usize) -> bool) -> Result<ControlFlowGraph, String> {
+    let bytes: Vec<u8> = code.code.iter().flat_map(|c| std::iter::once(c.opcode).chain(c.data.iter().copied())).collect();
+    let mut pcs = Vec::new();
+    let mut pc = 0;
+    for code_byte in &code.code {
+      pcs.push(pc);
+      pc += 1 + code_byte.data.len();
+    }
+    if pcs.is_empty() {
+      return Err("empty bytecode".to_string());
+    }
+
+    // ブロックの先頭: 入口、分岐先、分岐や終了の次の命令、例外の範囲の境界とハンドラ
+    let mut leaders = BTreeSet::from([0]);
+    for (i, &pc) in pcs.iter().enumerate() {
+      let targets = match flow(&bytes, pc) {
+        Flow::Next if !split_after(pc) => continue,
+        Flow::Next => Vec::new(),
+        Flow::Goto(target) | Flow::Conditional(target) | Flow::Jsr(target) => vec![target],
+        Flow::Switch(cases) => cases.into_iter().map(|(_, target)| target).collect(),
+        Flow::Ret | Flow::Exit => Vec::new(),
+      };
+      leaders.extend(targets);
+      if let Some(next) = pcs.get(i + 1) {
+        leaders.insert(*next);
+      }
+    }
+    for entry in &code.exception_table {
+      leaders.extend([entry.start_pc as usize, entry.handler_pc as usize]);
+      if (entry.end_pc as usize) < bytes.len() {
+        leaders.insert(entry.end_pc as usize);
+      }
+    }
+    if let Some(leader) = leaders.iter().find(|leader| pcs.binary_search(leader).is_err()) {
+      return Err(format!("branch target {} is not at an instruction boundary", leader));
+    }
+
+    let mut blocks: Vec<BasicBlock> = Vec::new();
+    for (i, &pc) in pcs.iter().enumerate() {
+      if leaders.contains(&pc) {
+        blocks.push(BasicBlock { start_pc: pc, end_pc: pc, instructions: Vec::new(), successors: Vec::new() });
+      }
+      let block = blocks.last_mut().expect("pc 0 is a leader");
+      block.instructions.push(pc);
+      block.end_pc = pcs.get(i + 1).copied().unwrap_or(bytes.len());
+    }
+    let mut graph = ControlFlowGraph { blocks, code: bytes };
+
+    let mut jsr_calls = Vec::new();
+    for index in 0..graph.blocks.len() {
+      let block = &graph.blocks[index];
+      let last = *block.instructions.last().expect("block is not empty");
+      let next = graph.block_at(block.end_pc).filter(|_| block.end_pc < graph.code.len());
+      let fallthrough = || next.ok_or_else(|| format!("control flow falls off the end of the code at pc {}", last));
+      let edge = |target: usize, kind: EdgeKind| graph.block_at(target).map(|target| Edge { target, kind });
+      let mut successors: Vec<Edge> = match flow(&graph.code, last) {
+        Flow::Next => vec![Edge { target: fallthrough()?, kind: EdgeKind::Fallthrough }],
+        Flow::Goto(target) => edge(target, EdgeKind::Jump).into_iter().collect(),
+        Flow::Conditional(target) => {
+          edge(target, EdgeKind::Branch(true)).into_iter().chain([Edge { target: fallthrough()?, kind: EdgeKind::Branch(false) }]).collect()
+        },
+        Flow::Switch(cases) => cases.into_iter().filter_map(|(key, target)| edge(target, EdgeKind::Switch(key))).collect(),
+        Flow::Jsr(target) => {
+          jsr_calls.push((index, graph.block_at(target), fallthrough()?));
+          edge(target, EdgeKind::Jsr).into_iter().collect()
+        },
+        Flow::Ret | Flow::Exit => Vec::new(),
+      };
+      for entry in &code.exception_table {
+        if (entry.start_pc as usize..entry.end_pc as usize).contains(&block.start_pc)
+          && let Some(handler) = graph.block_at(entry.handler_pc as usize)
+        {
+          let catch_type = if entry.catch_type == 0 { None } else { Some(pool.get_class_name(entry.catch_type)?) };
+          successors.push(Edge { target: handler, kind: EdgeKind::Exception(catch_type) });
+        }
+      }
+      graph.blocks[index].successors = successors;
+    }
+    graph.link_subroutines(&jsr_calls);
+    Ok(graph)
+  }
+
+  // retを含むブロックから、そのサブルーチンを呼んだjsrの次の命令への辺を張る
+  // サブルーチンの範囲は、入口からjsrの呼び出しを飛び越えてたどれるブロックとする
+  fn link_subroutines(&mut self, jsr_calls: &[(usize, Option<usize>, usize)]) {
+    let entries: BTreeSet<usize> = jsr_calls.iter().filter_map(|(_, entry, _)| *entry).collect();
+    for entry in entries {
+      let return_sites: Vec<usize> = jsr_calls.iter().filter(|(_, e, _)| *e == Some(entry)).map(|(_, _, site)| *site).collect();
+      let mut visited = vec![false; self.blocks.len()];
+      let mut stack = vec![entry];
+      while let Some(block) = stack.pop() {
+        if std::mem::replace(&mut visited[block], true) {
+          continue;
+        }
+        for edge in &self.blocks[block].successors {
+          if !matches!(edge.kind, EdgeKind::Jsr | EdgeKind::Ret) {
+            stack.push(edge.target);
+          }
+        }
+        if let Some((_, _, site)) = jsr_calls.iter().find(|(caller, _, _)| *caller == block) {
+          stack.push(*site);
+        }
+      }
+      for block in (0..self.blocks.len()).filter(|&block| visited[block]) {
+        let last = *self.blocks[block].instructions.last().expect("block is not empty");
+        if matches!(flow(&self.code, last), Flow::Ret) {
+          for &site in &return_sites {
+            let edge = Edge { target: site, kind: EdgeKind::Ret };
+            if !self.blocks[block].successors.contains(&edge) {
+              self.blocks[block].successors.push(edge);
+            }
+          }
+        }
+      }
+    }
+  }
+
+  // pcから始まるブロック
+  pub fn block_at(&self, pc: usize) -> Option<usize> {
+    self.blocks.binary_search_by_key(&pc, |block| block.start_pc).ok()
+  }
+
+  // pcの命令を含むブロック
+  pub fn block_containing(&self, pc: usize) -> Option<usize> {
+    self.blocks.iter().position(|block| (block.start_pc..block.end_pc).contains(&pc))
+  }
+
+  pub fn instruction_text(&self, pc: usize, pool: Option<&ConstantPool>) -> String {
+    instruction_text(&self.code, pc, pool)
+  }
+
+  // ブロックごとの後続 (重複を除く)
+  pub fn successor_lists(&self) -> Vec<Vec<usize>> {
+    self
+      .blocks
+      .iter()
+      .map(|block| {
+        let mut targets: Vec<usize> = Vec::new();
+        for edge in &block.successors {
+          if !targets.contains(&edge.target) {
+            targets.push(edge.target);
+          }
+        }
+        targets
+      })
+      .collect()
+  }
+
+  pub fn predecessors(&self) -> Vec<Vec<usize>> {
+    graph::predecessors(&self.successor_lists())
+  }
+
+  // 入口から到達できるブロック
+  pub fn reachable(&self) -> Vec<bool> {
+    let mut reachable = vec![false; self.blocks.len()];
+    for block in graph::reverse_postorder(&self.successor_lists(), 0) {
+      reachable[block] = true;
+    }
+    reachable
+  }
+
+  // ブロックごとの直接支配ブロック (入口と到達できないブロックはNone)
+  pub fn immediate_dominators(&self) -> Vec<Option<usize>> {
+    graph::immediate_dominators(&self.successor_lists(), 0)
+  }
+
+  // 自然ループ (外側のループが先)
+  pub fn loops(&self) -> Vec<NaturalLoop> {
+    graph::natural_loops(&self.successor_lists(), 0)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::structure::class::MethodInfoAttribute;
+
+  // 後続のブロックだけを与えたグラフ (命令は持たない)
+  fn graph(successors: &[&[usize]]) -> ControlFlowGraph {
+    let blocks = successors.iter().enumerate()
+      .map(|(i, targets)| BasicBlock {
+        start_pc: i,
+        end_pc: i + 1,
+        instructions: vec![i],
+        successors: targets.iter().map(|&target| Edge { target, kind: EdgeKind::Jump }).collect(),
+      })
+      .collect();
+    ControlFlowGraph { blocks, code: Vec::new() }
+  }
+
+  fn code_attribute(code: &[u8]) -> CodeAttribute {
+    let mut input = Vec::new();
+    input.extend((2 + 2 + 4 + code.len() as u32 + 2 + 2).to_be_bytes());
+    input.extend([0, 1, 0, 2]);
+    input.extend((code.len() as u32).to_be_bytes());
+    input.extend(code);
+    input.extend([0, 0, 0, 0]);
+    match MethodInfoAttribute::parse(&input, "Code", 1, &ConstantPool::default()) {
+      Ok((_, MethodInfoAttribute::Code(attribute))) => attribute,
+      _ => panic!("expected a Code attribute"),
+    }
+  }
+
+  #[test]
+  fn nested_loops_and_dominators() {
+    // 0 -> 1 (外側のヘッダ) -> 2 (内側のヘッダ) <-> 3、2 -> 4 -> 1、1 -> 5
+    let graph = graph(&[&[1], &[2, 5], &[3, 4], &[2], &[1], &[]]);
+    let dominators = graph.immediate_dominators();
+    assert_eq!(dominators, [None, Some(0), Some(1), Some(2), Some(2), Some(1)]);
+    assert_eq!(graph::dominator_tree(&dominators), [vec![1], vec![2, 5], vec![3, 4], vec![], vec![], vec![]]);
+    assert!(graph::dominates(&dominators, 1, 4));
+    assert!(!graph::dominates(&dominators, 3, 4));
+    assert_eq!(graph.loops(), [
+      NaturalLoop { header: 1, body: vec![1, 2, 3, 4], latches: vec![4], parent: None },
+      NaturalLoop { header: 2, body: vec![2, 3], latches: vec![3], parent: Some(0) },
+    ]);
+  }
+
+  #[test]
+  fn irreducible_loop_is_not_a_natural_loop() {
+    // 1と2は互いに行き来するが、どちらも入口から直接入れるので他方を支配しない
+    let graph = graph(&[&[1, 2], &[2, 3], &[1], &[]]);
+    let dominators = graph.immediate_dominators();
+    assert_eq!(dominators, [None, Some(0), Some(0), Some(1)]);
+    assert!(!graph::dominates(&dominators, 1, 2));
+    assert!(!graph::dominates(&dominators, 2, 1));
+    assert!(graph.loops().is_empty());
+  }
+
+  #[test]
+  fn jsr_and_ret_link_subroutine_to_every_return_site() {
+    // 0: jsr 7、3: jsr 7、6: return、7: astore_1、8: ret 1
+    let code = code_attribute(&[0xa8, 0, 7, 0xa8, 0, 4, 0xb1, 0x4c, 0xa9, 1]);
+    let graph = ControlFlowGraph::build(&code, &ConstantPool::default()).unwrap();
+    let starts: Vec<usize> = graph.blocks.iter().map(|block| block.start_pc).collect();
+    assert_eq!(starts, [0, 3, 6, 7]);
+    assert_eq!(graph.blocks[0].successors, [Edge { target: 3, kind: EdgeKind::Jsr }]);
+    assert_eq!(graph.blocks[3].successors, [Edge { target: 1, kind: EdgeKind::Ret }, Edge { target: 2, kind: EdgeKind::Ret }]);
+    assert_eq!(graph.block_containing(8), Some(3));
+    // サブルーチンからjsrの次に戻るので、戻り先はサブルーチンに支配される
+    assert_eq!(graph.immediate_dominators(), [None, Some(3), Some(3), Some(0)]);
+    assert_eq!(graph.loops(), [NaturalLoop { header: 3, body: vec![1, 3], latches: vec![1], parent: None }]);
+  }
+}
@@ -25,8 +25,15 @@ fn parse_all(input: &[u8]) -> IResult<&[u8], ClassFile> {
   }
 }
 
+pub fn parse_bytes(bytes: &[u8]) -> Result<ClassFile> {
+  match parse_all(bytes) {
+    Ok((_, class_file)) => Ok(class_file),
+    Err(_) => Err(io::Error::new(io::ErrorKind::InvalidData, "Failed to parse class file")),
+  }
+}
+
 pub fn read_file(path: &str) -> Result<ClassFile> {
-  let mut file = File::open(&path)?;
+  let mut file = File::open(path)?;
   let metadata = file.metadata()?;
   println!("File size: {} bytes", metadata.len());
   let mut bytes = vec![];
@@ -34,8 +41,5 @@ pub fn read_file(path: &str) -> Result<ClassFile> {
   let hex_string: String = hex_viewer(&bytes);
   println!("hex: \n{}", hex_string);
 
-  match parse_all(&bytes) {
-    Ok((_, class_file)) => Ok(class_file),
-    Err(_) => Err(io::Error::new(io::ErrorKind::InvalidData, "Failed to parse class file")),
-  }
-}
\ No newline at end of file
+  parse_bytes(&bytes)
+}
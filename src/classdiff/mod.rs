@@ -0,0 +1,719 @@
+pub mod report;
+
+use std::{
+  collections::{BTreeMap, HashMap},
+  iter::once,
+};
+
+use crate::{
+  runtime::trace::constant_comment,
+  structure::{
+    class::{
+      Annotation, BootstrapMethod, ClassFile, ClassFileAttribute, CodeAttribute, CodeNestedAttribute, Constant, ConstantPool,
+      ElementValue, ElementValueEnum, FieldInfoAttribute, MethodInfoAttribute, ParameterAnnotation, RecordComponentInfoAttribute,
+      StackMapFrame, TargetInfo, TypeAnnotation, VerificationTypeInfo,
+    },
+    code::{instruction_length, operand_text, CODE_BYTES},
+  },
+  util::class::{class_access_flags, field_access_flags, inner_class_access_flags, method_access_flags},
+};
+
+// 2つのクラスファイルの構造的な差分
+// コンスタントプールの番号を値に置き換えてから比べるので、エントリの並び替えだけの違いは差分にならない
+
+// 属性などの比較の単位 (名前と、値を1行ずつ表したもの)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+  pub name: String,
+  pub lines: Vec<String>,
+}
+
+// コンスタントプールの番号に依存しない形にしたクラスファイル
+#[derive(Debug, Default)]
+pub struct ClassModel {
+  pub name: String,
+  // コンスタントプールの値ごとのエントリ数
+  pub constants: BTreeMap<String, usize>,
+  // ヘッダとクラスの属性
+  pub sections: Vec<Section>,
+  // "name:descriptor"ごとのフィールド
+  pub fields: BTreeMap<String, Vec<Section>>,
+  // "name(descriptor)"ごとのメソッド
+  pub methods: BTreeMap<String, Vec<Section>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+  Added,
+  Removed,
+  Changed,
+}
+
+impl ChangeKind {
+  pub fn name(&self) -> &'static str {
+    match self {
+      ChangeKind::Added => "added",
+      ChangeKind::Removed => "removed",
+      ChangeKind::Changed => "changed",
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+  Same(String),
+  Removed(String),
+  Added(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Difference {
+  // "class"、"field name:descriptor"、"method name(descriptor)"
+  pub element: String,
+  // "constant pool"、"header"、"flags"、属性名 (フィールドやメソッドそのものの追加と削除なら空)
+  pub section: String,
+  pub kind: ChangeKind,
+  pub lines: Vec<DiffLine>,
+}
+
+// 同じ名前の属性が複数あれば1つの比較単位にまとめる
+fn push_section(sections: &mut Vec<Section>, name: &str, lines: Vec<String>) {
+  match sections.iter_mut().find(|section| section.name == name) {
+    Some(section) => section.lines.extend(lines),
+    None => sections.push(Section { name: name.to_string(), lines }),
+  }
+}
+
+fn flags_text(flags: u16, names: fn(u16) -> String) -> String {
+  format!("0x{:04x} ({})", flags, names(flags).trim())
+}
+
+// pcを命令の通し番号で表す (命令の境界でなければpcのまま)
+fn label(labels: &HashMap<usize, usize>, pc: usize) -> String {
+  match labels.get(&pc) {
+    Some(ordinal) => format!("L{}", ordinal),
+    None => format!("pc {}", pc),
+  }
+}
+
+struct Renderer<'a> {
+  pool: &'a ConstantPool,
+  bootstrap_methods: &'a [BootstrapMethod],
+}
+
+impl Renderer<'_> {
+  fn utf8(&self, index: u16) -> String {
+    self.pool.get_utf8(index).unwrap_or_else(|_| format!("#{}", index))
+  }
+
+  fn class_name(&self, index: u16) -> String {
+    self.pool.get_class_name(index).unwrap_or_else(|_| format!("#{}", index))
+  }
+
+  // 0は「なし」を表す番号
+  fn optional(&self, index: u16, text: impl Fn(u16) -> String) -> String {
+    if index == 0 { "-".to_string() } else { text(index) }
+  }
+
+  // ブートストラップメソッドは番号ではなく、メソッドハンドルと引数で表す
+  fn bootstrap_method(&self, index: u16) -> String {
+    match self.bootstrap_methods.get(index as usize) {
+      Some(method) => {
+        let arguments: Vec<String> = method.bootstrap_arguments.iter().map(|&argument| self.constant(argument)).collect();
+        format!("{} [{}]", self.constant(method.bootstrap_method_attr_index), arguments.join(", "))
+      },
+      None => format!("bootstrap #{}", index),
+    }
+  }
+
+  fn constant(&self, index: u16) -> String {
+    let name_and_type = |index: u16| match self.pool.get_name_and_type(index) {
+      Ok((name, descriptor)) => format!("{}:{}", name, descriptor),
+      Err(_) => format!("#{}", index),
+    };
+    match self.pool.get_class(index) {
+      Ok(Constant::Utf8 { .. }) => format!("Utf8 {}", self.utf8(index)),
+      Ok(Constant::NameAndType { name_index, descriptor_index }) => {
+        format!("NameAndType {}:{}", self.utf8(*name_index), self.utf8(*descriptor_index))
+      },
+      Ok(Constant::Module { name_index }) => format!("Module {}", self.utf8(*name_index)),
+      Ok(Constant::Package { name_index }) => format!("Package {}", self.utf8(*name_index)),
+      Ok(Constant::Dynamic { bootstrap_method_attr_index, name_and_type_index }) => {
+        format!("Dynamic {} {}", name_and_type(*name_and_type_index), self.bootstrap_method(*bootstrap_method_attr_index))
+      },
+      Ok(Constant::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index }) => {
+        format!("InvokeDynamic {} {}", name_and_type(*name_and_type_index), self.bootstrap_method(*bootstrap_method_attr_index))
+      },
+      _ => constant_comment(self.pool, index).unwrap_or_else(|| format!("#{}", index)),
+    }
+  }
+
+  fn element_value(&self, value: &ElementValue) -> String {
+    match &value.value {
+      ElementValueEnum::ConstValueIndex(index) if value.tag == b's' => format!("\"{}\"", self.utf8(*index)),
+      ElementValueEnum::ConstValueIndex(index) => format!("{} {}", value.tag as char, self.constant(*index)),
+      ElementValueEnum::EnumConstValue { type_name_index, const_name_index } => {
+        format!("{}.{}", self.utf8(*type_name_index), self.utf8(*const_name_index))
+      },
+      ElementValueEnum::ClassInfoIndex(index) => format!("{}.class", self.utf8(*index)),
+      ElementValueEnum::AnnotationValue(annotation) => self.annotation(annotation),
+      ElementValueEnum::ArrayValue { values, .. } => {
+        let values: Vec<String> = values.iter().map(|value| self.element_value(value)).collect();
+        format!("{{{}}}", values.join(", "))
+      },
+    }
+  }
+
+  fn annotation(&self, annotation: &Annotation) -> String {
+    let pairs: Vec<String> = annotation.element_value_pairs.iter()
+      .map(|pair| format!("{}={}", self.utf8(pair.element_name_index), self.element_value(&pair.value)))
+      .collect();
+    format!("@{}({})", self.utf8(annotation.type_index), pairs.join(", "))
+  }
+
+  fn parameter_annotations(&self, parameters: &[ParameterAnnotation]) -> Vec<String> {
+    parameters.iter().enumerate()
+      .flat_map(|(i, parameter)| parameter.annotations.iter().map(move |annotation| format!("parameter {}: {}", i, self.annotation(annotation))))
+      .collect()
+  }
+
+  fn type_annotation(&self, annotation: &TypeAnnotation, labels: &HashMap<usize, usize>) -> String {
+    let target = match &annotation.target_info {
+      TargetInfo::TypeParameter { type_parameter_index } => format!("type parameter {}", type_parameter_index),
+      TargetInfo::Supertype { supertype_index } => format!("supertype {}", supertype_index),
+      TargetInfo::TypeParameterBound { type_parameter_index, bound_index } => {
+        format!("type parameter {} bound {}", type_parameter_index, bound_index)
+      },
+      TargetInfo::Empty {} => "empty".to_string(),
+      TargetInfo::FormalParameter { formal_parameter_index } => format!("parameter {}", formal_parameter_index),
+      TargetInfo::Throws { throws_type_index } => format!("throws {}", throws_type_index),
+      TargetInfo::Localvar { local_var_table, .. } => {
+        let ranges: Vec<String> = local_var_table.iter()
+          .map(|entry| {
+            let start = entry.start_pc as usize;
+            format!("{}..{} slot {}", label(labels, start), label(labels, start + entry.length as usize), entry.index)
+          })
+          .collect();
+        format!("local variable [{}]", ranges.join(", "))
+      },
+      TargetInfo::Catch { exception_table_index } => format!("catch {}", exception_table_index),
+      TargetInfo::Offset { offset } => format!("offset {}", label(labels, *offset as usize)),
+      TargetInfo::TypeArgument { offset, type_argument_index } => {
+        format!("offset {} type argument {}", label(labels, *offset as usize), type_argument_index)
+      },
+    };
+    let path: Vec<String> = annotation.target_path.path.iter()
+      .map(|entry| format!("{}:{}", entry.type_path_kind, entry.type_argument_index))
+      .collect();
+    let pairs: Vec<String> = annotation.element_value_pairs.iter()
+      .map(|pair| format!("{}={}", self.utf8(pair.element_name_index), self.element_value(&pair.value)))
+      .collect();
+    format!(
+      "0x{:02x} {} path [{}] @{}({})",
+      annotation.target_type,
+      target,
+      path.join("."),
+      self.utf8(annotation.type_index),
+      pairs.join(", ")
+    )
+  }
+
+  fn type_annotations(&self, annotations: &[TypeAnnotation], labels: &HashMap<usize, usize>) -> Vec<String> {
+    annotations.iter().map(|annotation| self.type_annotation(annotation, labels)).collect()
+  }
+
+  fn verification_type(&self, info: &VerificationTypeInfo, labels: &HashMap<usize, usize>) -> String {
+    match info {
+      VerificationTypeInfo::TopVariableInfo { .. } => "top".to_string(),
+      VerificationTypeInfo::IntegerVariableInfo { .. } => "int".to_string(),
+      VerificationTypeInfo::FloatVariableInfo { .. } => "float".to_string(),
+      VerificationTypeInfo::LongVariableInfo { .. } => "long".to_string(),
+      VerificationTypeInfo::DoubleVariableInfo { .. } => "double".to_string(),
+      VerificationTypeInfo::NullVariableInfo { .. } => "null".to_string(),
+      VerificationTypeInfo::UninitializedThisVariableInfo { .. } => "uninitializedThis".to_string(),
+      VerificationTypeInfo::ObjectVariableInfo { cpool_index, .. } => self.class_name(*cpool_index),
+      VerificationTypeInfo::UninitializedVariableInfo { offset, .. } => format!("uninitialized {}", label(labels, *offset as usize)),
+    }
+  }
+
+  fn verification_types(&self, types: &[VerificationTypeInfo], labels: &HashMap<usize, usize>) -> String {
+    let types: Vec<String> = types.iter().map(|info| self.verification_type(info, labels)).collect();
+    format!("[{}]", types.join(", "))
+  }
+
+  // フレームの位置は命令の通し番号にし、拡張形式かどうかの違いは無視する
+  fn stack_map_frames(&self, frames: &[StackMapFrame], labels: &HashMap<usize, usize>) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut offset: i64 = -1;
+    for frame in frames {
+      let (delta, text) = match frame {
+        StackMapFrame::SameFrame { frame_type } => (*frame_type as u16, "same".to_string()),
+        StackMapFrame::SameLocals1StackItemFrame { frame_type, stack } => {
+          (*frame_type as u16 - 64, format!("same_locals_1_stack_item {}", self.verification_types(stack, labels)))
+        },
+        StackMapFrame::SameLocals1StackItemFrameExtended { offset_delta, stack, .. } => {
+          (*offset_delta, format!("same_locals_1_stack_item {}", self.verification_types(stack, labels)))
+        },
+        StackMapFrame::ChopFrame { frame_type, offset_delta } => (*offset_delta, format!("chop {}", 251 - *frame_type as u16)),
+        StackMapFrame::SameFrameExtended { offset_delta, .. } => (*offset_delta, "same".to_string()),
+        StackMapFrame::AppendFrame { offset_delta, locals, .. } => {
+          (*offset_delta, format!("append {}", self.verification_types(locals, labels)))
+        },
+        StackMapFrame::FullFrame { offset_delta, locals, stack, .. } => (
+          *offset_delta,
+          format!("full locals {} stack {}", self.verification_types(locals, labels), self.verification_types(stack, labels)),
+        ),
+      };
+      offset += delta as i64 + 1;
+      lines.push(format!("{}: {}", label(labels, offset as usize), text));
+    }
+    lines
+  }
+
+  // 命令を1行ずつ表す
+  // オペランドはコンスタントプールの値にし、分岐先は命令数での相対位置にするので、
+  // コンスタントプールの並びやldc/ldc_w、goto/goto_wの違い、離れた場所への命令の追加は影響しない
+  fn instructions(&self, code: &[u8]) -> (HashMap<usize, usize>, Vec<String>) {
+    let mut pcs = Vec::new();
+    let mut pc = 0;
+    while pc < code.len() {
+      pcs.push(pc);
+      pc += instruction_length(code, pc).unwrap_or(1);
+    }
+    let mut labels: HashMap<usize, usize> = pcs.iter().enumerate().map(|(ordinal, &pc)| (pc, ordinal)).collect();
+    labels.insert(code.len(), pcs.len());
+    let lines = pcs.iter().enumerate().map(|(ordinal, &pc)| self.instruction(code, pc, ordinal, &labels)).collect();
+    (labels, lines)
+  }
+
+  fn instruction(&self, code: &[u8], pc: usize, ordinal: usize, labels: &HashMap<usize, usize>) -> String {
+    // 分岐先は、この命令から数えた命令の数で表す
+    let target = |target: usize| match labels.get(&target) {
+      Some(&target) => format!("{:+}", target as i64 - ordinal as i64),
+      None => format!("pc {}", target),
+    };
+    let opcode = code.get(pc).copied().unwrap_or(0);
+    let name = match opcode {
+      0x13 => "ldc",
+      0xc8 => "goto",
+      0xc9 => "jsr",
+      _ => CODE_BYTES.get(&opcode).map_or("unknown", |code_byte| code_byte.name),
+    };
+    let operands = operand_text(code, pc, &|index, extra| format!("{}{}", self.constant(index), extra), &target);
+    if operands.is_empty() { name.to_string() } else { format!("{} {}", name, operands) }
+  }
+
+  fn code(&self, code: &CodeAttribute, sections: &mut Vec<Section>) {
+    let bytes: Vec<u8> = code.code.iter().flat_map(|code_byte| once(code_byte.opcode).chain(code_byte.data.iter().copied())).collect();
+    let (labels, instructions) = self.instructions(&bytes);
+    let mut lines = vec![format!("max_stack {}, max_locals {}", code.max_stack, code.max_locals)];
+    lines.extend(instructions);
+    for entry in &code.exception_table {
+      lines.push(format!(
+        "exception {}..{} -> {} catch {}",
+        label(&labels, entry.start_pc as usize),
+        label(&labels, entry.end_pc as usize),
+        label(&labels, entry.handler_pc as usize),
+        if entry.catch_type == 0 { "any".to_string() } else { self.class_name(entry.catch_type) }
+      ));
+    }
+    push_section(sections, "Code", lines);
+    for attribute in &code.attributes.attributes {
+      let (name, lines) = match attribute {
+        CodeNestedAttribute::LineNumberTable(table) => (
+          "LineNumberTable",
+          table.line_number_table.iter()
+            .map(|entry| format!("{}: line {}", label(&labels, entry.start_pc as usize), entry.line_number))
+            .collect(),
+        ),
+        CodeNestedAttribute::LocalVariableTable(table) => (
+          "LocalVariableTable",
+          table.local_variable_table.iter()
+            .map(|entry| {
+              let start = entry.start_pc as usize;
+              format!(
+                "slot {} {} {} {}..{}",
+                entry.index,
+                self.utf8(entry.name_index),
+                self.utf8(entry.descriptor_index),
+                label(&labels, start),
+                label(&labels, start + entry.length as usize)
+              )
+            })
+            .collect(),
+        ),
+        CodeNestedAttribute::LocalVariableTypeTable(table) => (
+          "LocalVariableTypeTable",
+          table.local_variable_type_table.iter()
+            .map(|entry| {
+              let start = entry.start_pc as usize;
+              format!(
+                "slot {} {} {} {}..{}",
+                entry.index,
+                self.utf8(entry.name_index),
+                self.utf8(entry.signature_index),
+                label(&labels, start),
+                label(&labels, start + entry.length as usize)
+              )
+            })
+            .collect(),
+        ),
+        CodeNestedAttribute::StackMapTable(table) => ("StackMapTable", self.stack_map_frames(&table.entries, &labels)),
+        CodeNestedAttribute::RuntimeVisibleTypeAnnotations(attribute) => {
+          ("RuntimeVisibleTypeAnnotations", self.type_annotations(&attribute.annotations, &labels))
+        },
+        CodeNestedAttribute::RuntimeInvisibleTypeAnnotations(attribute) => {
+          ("RuntimeInvisibleTypeAnnotations", self.type_annotations(&attribute.annotations, &labels))
+        },
+      };
+      push_section(sections, name, lines);
+    }
+  }
+
+  fn class_attribute(&self, attribute: &ClassFileAttribute) -> (&'static str, Vec<String>) {
+    let none = HashMap::new();
+    match attribute {
+      ClassFileAttribute::SourceFile(attribute) => ("SourceFile", vec![self.utf8(attribute.source_file_index)]),
+      ClassFileAttribute::SourceDebugExtension(attribute) => (
+        "SourceDebugExtension",
+        String::from_utf8_lossy(&attribute.debug_extension).lines().map(str::to_string).collect(),
+      ),
+      ClassFileAttribute::LineNumberTable(table) => (
+        "LineNumberTable",
+        table.line_number_table.iter().map(|entry| format!("pc {}: line {}", entry.start_pc, entry.line_number)).collect(),
+      ),
+      ClassFileAttribute::InnerClasses(attribute) => (
+        "InnerClasses",
+        attribute.classes.iter()
+          .map(|class| {
+            format!(
+              "{} outer {} name {} flags {}",
+              self.class_name(class.inner_class_info_index),
+              self.optional(class.outer_class_info_index, |index| self.class_name(index)),
+              self.optional(class.inner_name_index, |index| self.utf8(index)),
+              flags_text(class.inner_class_access_flags, inner_class_access_flags)
+            )
+          })
+          .collect(),
+      ),
+      ClassFileAttribute::EnclosingMethod(attribute) => (
+        "EnclosingMethod",
+        vec![format!(
+          "{} method {}",
+          self.class_name(attribute.class_index),
+          self.optional(attribute.method_index, |index| match self.pool.get_name_and_type(index) {
+            Ok((name, descriptor)) => format!("{}{}", name, descriptor),
+            Err(_) => format!("#{}", index),
+          })
+        )],
+      ),
+      // 命令からは番号ではなく内容で参照するので、並びは無視する
+      ClassFileAttribute::BootstrapMethods(attribute) => {
+        let mut lines: Vec<String> = (0..attribute.bootstrap_methods.len()).map(|i| self.bootstrap_method(i as u16)).collect();
+        lines.sort();
+        ("BootstrapMethods", lines)
+      },
+      ClassFileAttribute::Module(module) => {
+        let mut lines = vec![format!(
+          "module {} flags 0x{:04x} version {}",
+          self.constant(module.module_name_index),
+          module.module_flags,
+          self.optional(module.module_version_index, |index| self.utf8(index))
+        )];
+        let targets = |targets: &[u16]| {
+          let targets: Vec<String> = targets.iter().map(|&index| self.constant(index)).collect();
+          targets.join(", ")
+        };
+        for requires in &module.requires {
+          lines.push(format!(
+            "requires {} flags 0x{:04x} version {}",
+            self.constant(requires.requires_index),
+            requires.requires_flags,
+            self.optional(requires.requires_version_index, |index| self.utf8(index))
+          ));
+        }
+        for exports in &module.exports {
+          lines.push(format!("exports {} flags 0x{:04x} to [{}]", self.constant(exports.exports_index), exports.exports_flags, targets(&exports.exports_to)));
+        }
+        for opens in &module.opens {
+          lines.push(format!("opens {} flags 0x{:04x} to [{}]", self.constant(opens.opens_index), opens.opens_flags, targets(&opens.opens_to)));
+        }
+        for &uses in &module.uses {
+          lines.push(format!("uses {}", self.class_name(uses)));
+        }
+        for provides in &module.provides {
+          lines.push(format!("provides {} with [{}]", self.class_name(provides.provides_index), targets(&provides.provides_with)));
+        }
+        ("Module", lines)
+      },
+      ClassFileAttribute::ModulePackages(attribute) => {
+        ("ModulePackages", attribute.packages.iter().map(|&index| self.constant(index)).collect())
+      },
+      ClassFileAttribute::ModuleMainClass(attribute) => ("ModuleMainClass", vec![self.class_name(attribute.main_class_index)]),
+      ClassFileAttribute::NestHost(attribute) => ("NestHost", vec![self.class_name(attribute.nest_host_index)]),
+      ClassFileAttribute::NestMembers(attribute) => {
+        ("NestMembers", attribute.classes.iter().map(|&index| self.class_name(index)).collect())
+      },
+      ClassFileAttribute::PermittedSubclasses(attribute) => {
+        ("PermittedSubclasses", attribute.classes.iter().map(|&index| self.class_name(index)).collect())
+      },
+      ClassFileAttribute::Record(attribute) => {
+        let mut lines = Vec::new();
+        for component in &attribute.record_components {
+          lines.push(format!("{}:{}", self.utf8(component.name_index), self.utf8(component.descriptor_index)));
+          for attribute in &component.attributes.attributes {
+            let component_lines = match attribute {
+              RecordComponentInfoAttribute::Signature(signature) => vec![format!("Signature {}", self.utf8(signature.signature_index))],
+              RecordComponentInfoAttribute::RuntimeVisibleAnnotations(attribute) => {
+                attribute.annotations.iter().map(|annotation| self.annotation(annotation)).collect()
+              },
+              RecordComponentInfoAttribute::RuntimeInvisibleAnnotations(attribute) => {
+                attribute.annotations.iter().map(|annotation| self.annotation(annotation)).collect()
+              },
+              RecordComponentInfoAttribute::RuntimeVisibleTypeAnnotations(attribute) => self.type_annotations(&attribute.annotations, &none),
+              RecordComponentInfoAttribute::RuntimeInvisibleTypeAnnotations(attribute) => self.type_annotations(&attribute.annotations, &none),
+            };
+            lines.extend(component_lines.into_iter().map(|line| format!("  {}", line)));
+          }
+        }
+        ("Record", lines)
+      },
+      ClassFileAttribute::RuntimeVisibleAnnotations(attribute) => {
+        ("RuntimeVisibleAnnotations", attribute.annotations.iter().map(|annotation| self.annotation(annotation)).collect())
+      },
+      ClassFileAttribute::RuntimeInvisibleAnnotations(attribute) => {
+        ("RuntimeInvisibleAnnotations", attribute.annotations.iter().map(|annotation| self.annotation(annotation)).collect())
+      },
+      ClassFileAttribute::RuntimeVisibleTypeAnnotations(attribute) => {
+        ("RuntimeVisibleTypeAnnotations", self.type_annotations(&attribute.annotations, &none))
+      },
+      ClassFileAttribute::RuntimeInvisibleTypeAnnotations(attribute) => {
+        ("RuntimeInvisibleTypeAnnotations", self.type_annotations(&attribute.annotations, &none))
+      },
+      ClassFileAttribute::Synthetic(_) => ("Synthetic", vec!["synthetic".to_string()]),
+      ClassFileAttribute::Deprecated(_) => ("Deprecated", vec!["deprecated".to_string()]),
+      ClassFileAttribute::Signature(attribute) => ("Signature", vec![self.utf8(attribute.signature_index)]),
+    }
+  }
+
+  fn field_attribute(&self, attribute: &FieldInfoAttribute) -> (&'static str, Vec<String>) {
+    let none = HashMap::new();
+    match attribute {
+      FieldInfoAttribute::ConstantValue(attribute) => ("ConstantValue", vec![self.constant(attribute.constant_value_index)]),
+      FieldInfoAttribute::Synthetic(_) => ("Synthetic", vec!["synthetic".to_string()]),
+      FieldInfoAttribute::Deprecated(_) => ("Deprecated", vec!["deprecated".to_string()]),
+      FieldInfoAttribute::Signature(attribute) => ("Signature", vec![self.utf8(attribute.signature_index)]),
+      FieldInfoAttribute::RuntimeVisibleAnnotations(attribute) => {
+        ("RuntimeVisibleAnnotations", attribute.annotations.iter().map(|annotation| self.annotation(annotation)).collect())
+      },
+      FieldInfoAttribute::RuntimeInvisibleAnnotations(attribute) => {
+        ("RuntimeInvisibleAnnotations", attribute.annotations.iter().map(|annotation| self.annotation(annotation)).collect())
+      },
+      FieldInfoAttribute::RuntimeVisibleTypeAnnotations(attribute) => {
+        ("RuntimeVisibleTypeAnnotations", self.type_annotations(&attribute.annotations, &none))
+      },
+      FieldInfoAttribute::RuntimeInvisibleTypeAnnotations(attribute) => {
+        ("RuntimeInvisibleTypeAnnotations", self.type_annotations(&attribute.annotations, &none))
+      },
+    }
+  }
+
+  fn method_attribute(&self, attribute: &MethodInfoAttribute, sections: &mut Vec<Section>) {
+    let none = HashMap::new();
+    let (name, lines) = match attribute {
+      MethodInfoAttribute::Code(code) => return self.code(code, sections),
+      MethodInfoAttribute::Exceptions(attribute) => {
+        ("Exceptions", attribute.exception_index_table.iter().map(|&index| self.class_name(index)).collect())
+      },
+      MethodInfoAttribute::AnnotationDefault(attribute) => ("AnnotationDefault", vec![self.element_value(&attribute.default_value)]),
+      MethodInfoAttribute::MethodParameters(attribute) => (
+        "MethodParameters",
+        attribute.parameters.iter()
+          .map(|parameter| format!("{} flags 0x{:04x}", self.optional(parameter.name_index, |index| self.utf8(index)), parameter.access_flags))
+          .collect(),
+      ),
+      MethodInfoAttribute::Synthetic(_) => ("Synthetic", vec!["synthetic".to_string()]),
+      MethodInfoAttribute::Deprecated(_) => ("Deprecated", vec!["deprecated".to_string()]),
+      MethodInfoAttribute::Signature(attribute) => ("Signature", vec![self.utf8(attribute.signature_index)]),
+      MethodInfoAttribute::RuntimeVisibleAnnotations(attribute) => {
+        ("RuntimeVisibleAnnotations", attribute.annotations.iter().map(|annotation| self.annotation(annotation)).collect())
+      },
+      MethodInfoAttribute::RuntimeInvisibleAnnotations(attribute) => {
+        ("RuntimeInvisibleAnnotations", attribute.annotations.iter().map(|annotation| self.annotation(annotation)).collect())
+      },
+      MethodInfoAttribute::RuntimeVisibleTypeAnnotations(attribute) => {
+        ("RuntimeVisibleTypeAnnotations", self.type_annotations(&attribute.annotations, &none))
+      },
+      MethodInfoAttribute::RuntimeInvisibleTypeAnnotations(attribute) => {
+        ("RuntimeInvisibleTypeAnnotations", self.type_annotations(&attribute.annotations, &none))
+      },
+      MethodInfoAttribute::RuntimeVisibleParameterAnnotations(attribute) => {
+        ("RuntimeVisibleParameterAnnotations", self.parameter_annotations(&attribute.parameter_annotations))
+      },
+      MethodInfoAttribute::RuntimeInvisibleParameterAnnotations(attribute) => {
+        ("RuntimeInvisibleParameterAnnotations", self.parameter_annotations(&attribute.parameter_annotations))
+      },
+    };
+    push_section(sections, name, lines);
+  }
+}
+
+impl ClassModel {
+  pub fn new(class_file: &ClassFile) -> ClassModel {
+    let pool = &class_file.constant_pool;
+    let bootstrap_methods = class_file.attributes.attributes.iter()
+      .find_map(|attribute| match attribute {
+        ClassFileAttribute::BootstrapMethods(attribute) => Some(attribute.bootstrap_methods.as_slice()),
+        _ => None,
+      })
+      .unwrap_or_default();
+    let renderer = Renderer { pool, bootstrap_methods };
+    let mut model = ClassModel { name: renderer.class_name(class_file.this_class), ..ClassModel::default() };
+
+    for (i, constant) in pool.constants.iter().enumerate() {
+      if !matches!(constant, Constant::Unknown) {
+        *model.constants.entry(renderer.constant(i as u16 + 1)).or_default() += 1;
+      }
+    }
+
+    let mut header = vec![
+      format!("version {}.{}", class_file.header.major, class_file.header.minor),
+      format!("flags {}", flags_text(class_file.access_flags, class_access_flags)),
+      format!("this {}", model.name),
+      format!("super {}", renderer.optional(class_file.super_class, |index| renderer.class_name(index))),
+    ];
+    header.extend(class_file.interfaces.interfaces.iter().map(|&index| format!("implements {}", renderer.class_name(index))));
+    model.sections.push(Section { name: "header".to_string(), lines: header });
+    for attribute in &class_file.attributes.attributes {
+      let (name, lines) = renderer.class_attribute(attribute);
+      push_section(&mut model.sections, name, lines);
+    }
+
+    for field in &class_file.fields.fields {
+      let mut sections = vec![Section { name: "flags".to_string(), lines: vec![flags_text(field.access_flags, field_access_flags)] }];
+      for attribute in &field.attributes.attributes {
+        let (name, lines) = renderer.field_attribute(attribute);
+        push_section(&mut sections, name, lines);
+      }
+      model.fields.insert(format!("{}:{}", renderer.utf8(field.name_index), renderer.utf8(field.descriptor_index)), sections);
+    }
+    for method in &class_file.methods.methods {
+      let mut sections = vec![Section { name: "flags".to_string(), lines: vec![flags_text(method.access_flags, method_access_flags)] }];
+      for attribute in &method.attributes.attributes {
+        renderer.method_attribute(attribute, &mut sections);
+      }
+      model.methods.insert(format!("{}{}", renderer.utf8(method.name_index), renderer.utf8(method.descriptor_index)), sections);
+    }
+    model
+  }
+}
+
+// 共通の先頭と末尾を除いてから、最長共通部分列で行ごとの差分を取る
+pub fn diff_lines(old: &[String], new: &[String]) -> Vec<DiffLine> {
+  let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+  let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+  let a = &old[prefix..old.len() - suffix];
+  let b = &new[prefix..new.len() - suffix];
+  let mut result: Vec<DiffLine> = old[..prefix].iter().cloned().map(DiffLine::Same).collect();
+  // 大きすぎる表は作らず、全て置き換わったものとする
+  if a.len().saturating_mul(b.len()) > 25_000_000 {
+    result.extend(a.iter().cloned().map(DiffLine::Removed));
+    result.extend(b.iter().cloned().map(DiffLine::Added));
+  } else {
+    // table[i * width + j]はa[i..]とb[j..]の最長共通部分列の長さ
+    let width = b.len() + 1;
+    let mut table = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+      for j in (0..b.len()).rev() {
+        table[i * width + j] = if a[i] == b[j] {
+          table[(i + 1) * width + j + 1] + 1
+        } else {
+          table[(i + 1) * width + j].max(table[i * width + j + 1])
+        };
+      }
+    }
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+      if i < a.len() && j < b.len() && a[i] == b[j] {
+        result.push(DiffLine::Same(a[i].clone()));
+        i += 1;
+        j += 1;
+      } else if i < a.len() && (j == b.len() || table[(i + 1) * width + j] >= table[i * width + j + 1]) {
+        result.push(DiffLine::Removed(a[i].clone()));
+        i += 1;
+      } else {
+        result.push(DiffLine::Added(b[j].clone()));
+        j += 1;
+      }
+    }
+  }
+  result.extend(old[old.len() - suffix..].iter().cloned().map(DiffLine::Same));
+  result
+}
+
+fn compare_sections(element: &str, old: &[Section], new: &[Section], differences: &mut Vec<Difference>) {
+  for section in old {
+    match new.iter().find(|other| other.name == section.name) {
+      Some(other) if other.lines != section.lines => differences.push(Difference {
+        element: element.to_string(),
+        section: section.name.clone(),
+        kind: ChangeKind::Changed,
+        lines: diff_lines(&section.lines, &other.lines),
+      }),
+      Some(_) => {},
+      None => differences.push(Difference {
+        element: element.to_string(),
+        section: section.name.clone(),
+        kind: ChangeKind::Removed,
+        lines: section.lines.iter().cloned().map(DiffLine::Removed).collect(),
+      }),
+    }
+  }
+  for section in new.iter().filter(|section| !old.iter().any(|other| other.name == section.name)) {
+    differences.push(Difference {
+      element: element.to_string(),
+      section: section.name.clone(),
+      kind: ChangeKind::Added,
+      lines: section.lines.iter().cloned().map(DiffLine::Added).collect(),
+    });
+  }
+}
+
+fn compare_members(kind: &str, old: &BTreeMap<String, Vec<Section>>, new: &BTreeMap<String, Vec<Section>>, differences: &mut Vec<Difference>) {
+  let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+  keys.sort();
+  keys.dedup();
+  for key in keys {
+    let element = format!("{} {}", kind, key);
+    match (old.get(key), new.get(key)) {
+      (Some(old), Some(new)) => compare_sections(&element, old, new, differences),
+      (old, _) => differences.push(Difference {
+        element,
+        section: String::new(),
+        kind: if old.is_some() { ChangeKind::Removed } else { ChangeKind::Added },
+        lines: Vec::new(),
+      }),
+    }
+  }
+}
+
+pub fn compare(old: &ClassModel, new: &ClassModel) -> Vec<Difference> {
+  let mut differences = Vec::new();
+  // コンスタントプールは値の多重集合として比べる
+  let mut lines = Vec::new();
+  for (value, &count) in &old.constants {
+    let remaining = new.constants.get(value).copied().unwrap_or(0);
+    lines.extend((remaining..count).map(|_| DiffLine::Removed(value.clone())));
+  }
+  for (value, &count) in &new.constants {
+    let remaining = old.constants.get(value).copied().unwrap_or(0);
+    lines.extend((remaining..count).map(|_| DiffLine::Added(value.clone())));
+  }
+  if !lines.is_empty() {
+    differences.push(Difference { element: "class".to_string(), section: "constant pool".to_string(), kind: ChangeKind::Changed, lines });
+  }
+  compare_sections("class", &old.sections, &new.sections, &mut differences);
+  compare_members("field", &old.fields, &new.fields, &mut differences);
+  compare_members("method", &old.methods, &new.methods, &mut differences);
+  differences
+}
@@ -0,0 +1,144 @@
+use std::{collections::BTreeMap, fmt::Write as _, fs};
+
+use crate::{
+  class_leader,
+  classdiff::{ClassModel, DiffLine, Difference, compare},
+  util::json,
+};
+
+// 変更のない行は変更の前後にこの行数だけ表示する
+const CONTEXT: usize = 2;
+
+fn section_label(difference: &Difference) -> &str {
+  if difference.section.is_empty() {
+    if difference.element.starts_with("field") { "fields" } else { "methods" }
+  } else {
+    &difference.section
+  }
+}
+
+pub fn to_text(differences: &[Difference]) -> String {
+  let mut text = String::new();
+  for difference in differences {
+    if difference.section.is_empty() {
+      let _ = writeln!(text, "{}: {}", difference.element, difference.kind.name());
+      continue;
+    }
+    let _ = writeln!(text, "{}: {} {}", difference.element, difference.section, difference.kind.name());
+    let changed: Vec<usize> = difference.lines.iter().enumerate()
+      .filter(|(_, line)| !matches!(line, DiffLine::Same(_)))
+      .map(|(i, _)| i)
+      .collect();
+    let near = |i: usize| changed.iter().any(|&c| c.abs_diff(i) <= CONTEXT);
+    let mut skipped = false;
+    for (i, line) in difference.lines.iter().enumerate() {
+      if !near(i) {
+        skipped = true;
+        continue;
+      }
+      if skipped {
+        text.push_str("  ...\n");
+        skipped = false;
+      }
+      let _ = match line {
+        DiffLine::Same(line) => writeln!(text, "    {}", line),
+        DiffLine::Removed(line) => writeln!(text, "  - {}", line),
+        DiffLine::Added(line) => writeln!(text, "  + {}", line),
+      };
+    }
+    if skipped {
+      text.push_str("  ...\n");
+    }
+  }
+  if differences.is_empty() {
+    text.push_str("No structural differences\n");
+  } else {
+    let mut sections: BTreeMap<&str, usize> = BTreeMap::new();
+    for difference in differences {
+      *sections.entry(section_label(difference)).or_default() += 1;
+    }
+    let sections: Vec<String> = sections.iter().map(|(section, count)| format!("{} {}", section, count)).collect();
+    let _ = writeln!(text, "\n{} differences ({})", differences.len(), sections.join(", "));
+  }
+  text
+}
+
+pub fn to_json(differences: &[Difference]) -> String {
+  let mut json = String::from("{\n");
+  let _ = writeln!(json, "  \"identical\": {},", differences.is_empty());
+  json.push_str("  \"differences\": [");
+  for (i, difference) in differences.iter().enumerate() {
+    let removed = difference.lines.iter().filter_map(|line| match line {
+      DiffLine::Removed(line) => Some(line.as_str()),
+      _ => None,
+    });
+    let added = difference.lines.iter().filter_map(|line| match line {
+      DiffLine::Added(line) => Some(line.as_str()),
+      _ => None,
+    });
+    let _ = write!(
+      json,
+      "{}\n    {{\"element\": {}, \"section\": {}, \"kind\": {}, \"removed\": {}, \"added\": {}}}",
+      if i == 0 { "" } else { "," },
+      json::string(&difference.element),
+      json::string(section_label(difference)),
+      json::string(difference.kind.name()),
+      json::string_array(removed),
+      json::string_array(added),
+    );
+  }
+  json.push_str("\n  ]\n}\n");
+  json
+}
+
+// 2つのクラスファイルを構造的に比べる
+// 終了コードは、差分がなければ0、あれば1、読み込めないなどのエラーなら2
+pub fn print_command(program: &str, args: &[String]) -> i32 {
+  let usage = || {
+    eprintln!("Usage: {} diff [--json] [--ignore <attribute>]... <old class file> <new class file>", program);
+    2
+  };
+  let mut json = false;
+  let mut ignored = Vec::new();
+  let mut paths = Vec::new();
+  let mut i = 0;
+  while i < args.len() {
+    let arg = &args[i];
+    if arg == "--json" {
+      json = true;
+    } else if arg == "--ignore" {
+      i += 1;
+      let Some(value) = args.get(i) else {
+        return usage();
+      };
+      ignored.push(value.clone());
+    } else if arg.starts_with('-') {
+      return usage();
+    } else {
+      paths.push(arg);
+    }
+    i += 1;
+  }
+  let [old_path, new_path] = paths[..] else {
+    return usage();
+  };
+  let mut models = Vec::new();
+  for path in [old_path, new_path] {
+    match fs::read(path).and_then(|bytes| class_leader::parse_bytes(&bytes)) {
+      Ok(class_file) => models.push(ClassModel::new(&class_file)),
+      Err(e) => {
+        eprintln!("Error: {}: {}", path, e);
+        return 2;
+      },
+    }
+  }
+  let mut differences = compare(&models[0], &models[1]);
+  differences.retain(|difference| !ignored.contains(&difference.section));
+  if json {
+    print!("{}", to_json(&differences));
+  } else {
+    print!("{}", to_text(&differences));
+  }
+  if differences.is_empty() { 0 } else { 1 }
+}
+
@@ -0,0 +1,1051 @@
+use crate::{
+  decompiler::names::Names,
+  util::{
+    descriptor::{FieldType, MethodDescriptor},
+    number::{double_to_string, float_to_string},
+    signature::TypeSignature,
+  },
+};
+
+// 復元したJavaのソースの式と文
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+  Int(i32),
+  Long(i64),
+  Float(f32),
+  Double(f64),
+  Boolean(bool),
+  Char(u16),
+  String(String),
+  Null,
+  Class(FieldType),
+  // MethodTypeやMethodHandleなどJavaのリテラルで書けない定数
+  Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+  Literal(Literal),
+  // Scope::localsの番号
+  Local(usize),
+  // ブロックをまたぐオペランドスタックの値 ($0, $1, ...)
+  Stack(usize),
+  This,
+  // 例外ハンドラの入口でスタックに積まれている例外
+  Caught,
+  // objectがNoneならstaticフィールド
+  Field { object: Option<Box<Expr>>, class: String, name: String, descriptor: String },
+  ArrayElement(Box<Expr>, Box<Expr>),
+  ArrayLength(Box<Expr>),
+  // objectがNoneならstaticメソッド、specialはinvokespecial
+  Invoke { object: Option<Box<Expr>>, class: String, name: String, descriptor: String, args: Vec<Expr>, special: bool },
+  New { class: String, descriptor: String, args: Vec<Expr> },
+  // newの後、コンストラクタを呼ぶ前のオブジェクト
+  Uninit(usize, String),
+  // array_typeは配列全体の型、lengthsは指定された次元の長さ
+  NewArray { array_type: FieldType, lengths: Vec<Expr> },
+  // new int[]{...} (idで同じ配列への格納をまとめる)
+  ArrayInit { id: usize, array_type: FieldType, values: Vec<Option<Expr>> },
+  Unary(&'static str, Box<Expr>),
+  // x++ や x-- (値は増減する前のもの)
+  PostIncrement(&'static str, Box<Expr>),
+  Binary(&'static str, Box<Expr>, Box<Expr>),
+  // lcmp, fcmpl, dcmpgなど (ifで使われなかったもの)
+  Compare(Box<Expr>, Box<Expr>),
+  Cast(FieldType, Box<Expr>),
+  InstanceOf(Box<Expr>, FieldType),
+  Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
+  // 文字列の連結 (先頭から順に+でつなぐ)
+  Concat(Vec<Expr>),
+  // bodyは表示済みのソース (blockならブロックの中身の行)
+  Lambda { params: Vec<String>, body: String, block: bool },
+  // Class::name または expr::name
+  MethodRef { class: Option<String>, object: Option<Box<Expr>>, name: String },
+  // 認識できなかったinvokedynamic
+  Dynamic { name: String, args: Vec<Expr> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Catch {
+  // 空ならcatch (Throwable)
+  pub types: Vec<String>,
+  // 例外を格納するローカル変数 (Noneなら使われない)
+  pub local: Option<usize>,
+  pub body: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Case {
+  // Noneはdefault
+  pub labels: Vec<Option<i32>>,
+  pub body: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+  Expr(Expr),
+  Assign(Expr, Expr),
+  Return(Option<Expr>),
+  Throw(Expr),
+  If(Expr, Vec<Stmt>, Vec<Stmt>),
+  While(Expr, Vec<Stmt>),
+  DoWhile(Vec<Stmt>, Expr),
+  For(Option<Box<Stmt>>, Expr, Option<Box<Stmt>>, Vec<Stmt>),
+  Switch(Expr, Vec<Case>),
+  // 本体、catch、finallyの本体 (空ならfinallyはない)
+  Try(Vec<Stmt>, Vec<Catch>, Vec<Stmt>),
+  Labeled(String, Box<Stmt>),
+  // ラベルで抜けるためのブロック
+  Block(Vec<Stmt>),
+  Synchronized(Expr, Vec<Stmt>),
+  Break(Option<String>),
+  Continue(Option<String>),
+  Monitor(bool, Expr),
+  // 構造にできなかった分岐 (飛び先のpc)
+  Goto(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct Local {
+  pub name: String,
+  pub field_type: Option<FieldType>,
+  pub signature: Option<TypeSignature>,
+  // 引数は宣言しない
+  pub parameter: bool,
+}
+
+// メソッドの本体を表示するのに必要な情報
+#[derive(Debug, Clone)]
+pub struct Scope {
+  pub class_name: String,
+  pub locals: Vec<Local>,
+  pub return_type: Option<FieldType>,
+}
+
+impl Expr {
+  pub fn int(value: i32) -> Expr {
+    Expr::Literal(Literal::Int(value))
+  }
+
+  pub fn unary(op: &'static str, value: Expr) -> Expr {
+    Expr::Unary(op, Box::new(value))
+  }
+
+  pub fn binary(op: &'static str, left: Expr, right: Expr) -> Expr {
+    Expr::Binary(op, Box::new(left), Box::new(right))
+  }
+
+  // 直下の式
+  pub fn children(&self) -> Vec<&Expr> {
+    match self {
+      Expr::Literal(_) | Expr::Local(_) | Expr::Stack(_) | Expr::This | Expr::Caught | Expr::Uninit(..) | Expr::Lambda { .. } => Vec::new(),
+      Expr::Field { object, .. } => object.iter().map(|object| &**object).collect(),
+      Expr::ArrayElement(array, index) => vec![array, index],
+      Expr::ArrayLength(value) | Expr::Unary(_, value) | Expr::PostIncrement(_, value) | Expr::Cast(_, value) | Expr::InstanceOf(value, _) => {
+        vec![value]
+      },
+      Expr::Invoke { object, args, .. } => object.iter().map(|object| &**object).chain(args).collect(),
+      Expr::New { args, .. } | Expr::Concat(args) | Expr::Dynamic { args, .. } => args.iter().collect(),
+      Expr::NewArray { lengths, .. } => lengths.iter().collect(),
+      Expr::ArrayInit { values, .. } => values.iter().flatten().collect(),
+      Expr::Binary(_, left, right) | Expr::Compare(left, right) => vec![left, right],
+      Expr::Ternary(condition, then_value, else_value) => vec![condition, then_value, else_value],
+      Expr::MethodRef { object, .. } => object.iter().map(|object| &**object).collect(),
+    }
+  }
+
+  pub fn children_mut(&mut self) -> Vec<&mut Expr> {
+    match self {
+      Expr::Literal(_) | Expr::Local(_) | Expr::Stack(_) | Expr::This | Expr::Caught | Expr::Uninit(..) | Expr::Lambda { .. } => Vec::new(),
+      Expr::Field { object, .. } => object.iter_mut().map(|object| &mut **object).collect(),
+      Expr::ArrayElement(array, index) => vec![array, index],
+      Expr::ArrayLength(value) | Expr::Unary(_, value) | Expr::PostIncrement(_, value) | Expr::Cast(_, value) | Expr::InstanceOf(value, _) => {
+        vec![value]
+      },
+      Expr::Invoke { object, args, .. } => object.iter_mut().map(|object| &mut **object).chain(args).collect(),
+      Expr::New { args, .. } | Expr::Concat(args) | Expr::Dynamic { args, .. } => args.iter_mut().collect(),
+      Expr::NewArray { lengths, .. } => lengths.iter_mut().collect(),
+      Expr::ArrayInit { values, .. } => values.iter_mut().flatten().collect(),
+      Expr::Binary(_, left, right) | Expr::Compare(left, right) => vec![left, right],
+      Expr::Ternary(condition, then_value, else_value) => vec![condition, then_value, else_value],
+      Expr::MethodRef { object, .. } => object.iter_mut().map(|object| &mut **object).collect(),
+    }
+  }
+
+  pub fn any(&self, predicate: &dyn Fn(&Expr) -> bool) -> bool {
+    predicate(self) || self.children().into_iter().any(|child| child.any(predicate))
+  }
+
+  // 一致する部分式を置き換える
+  pub fn replace(&mut self, from: &Expr, to: &Expr) -> usize {
+    if self == from {
+      *self = to.clone();
+      return 1;
+    }
+    self.children_mut().into_iter().map(|child| child.replace(from, to)).sum()
+  }
+
+  pub fn count(&self, target: &Expr) -> usize {
+    if self == target {
+      return 1;
+    }
+    self.children().into_iter().map(|child| child.count(target)).sum()
+  }
+
+  // 他の文をまたいで評価を遅らせても値が変わらない式
+  pub fn is_stable(&self) -> bool {
+    match self {
+      Expr::Literal(_) | Expr::Local(_) | Expr::Stack(_) | Expr::This | Expr::Caught | Expr::Uninit(..) | Expr::Lambda { .. } => true,
+      Expr::Unary(..) | Expr::Binary(..) | Expr::Compare(..) | Expr::Cast(..) | Expr::InstanceOf(..) | Expr::Ternary(..) | Expr::Concat(_) => {
+        self.children().into_iter().all(Expr::is_stable)
+      },
+      Expr::ArrayInit { values, .. } => values.iter().flatten().all(Expr::is_stable),
+      _ => false,
+    }
+  }
+
+  // 複製してもよい単純な式
+  pub fn is_simple(&self) -> bool {
+    matches!(self, Expr::Literal(_) | Expr::Local(_) | Expr::Stack(_) | Expr::This | Expr::Caught | Expr::Uninit(..) | Expr::ArrayInit { .. })
+  }
+}
+
+impl Stmt {
+  // 最後まで実行したら次の文に進むかどうか
+  pub fn falls_through(&self) -> bool {
+    match self {
+      Stmt::Return(_) | Stmt::Throw(_) | Stmt::Break(_) | Stmt::Continue(_) | Stmt::Goto(_) => false,
+      Stmt::If(_, then_body, else_body) => !(ends_abruptly(then_body) && ends_abruptly(else_body)),
+      Stmt::Try(body, catches, finally) => !(ends_abruptly(finally) || ends_abruptly(body) && catches.iter().all(|catch| ends_abruptly(&catch.body))),
+      Stmt::Block(body) | Stmt::Synchronized(_, body) => !ends_abruptly(body),
+      _ => true,
+    }
+  }
+
+  // 文の中の式 (入れ子の文は含まない)
+  pub fn exprs_mut(&mut self) -> Vec<&mut Expr> {
+    match self {
+      Stmt::Expr(value) | Stmt::Throw(value) | Stmt::Monitor(_, value) | Stmt::If(value, ..) | Stmt::While(value, _) | Stmt::DoWhile(_, value) => {
+        vec![value]
+      },
+      Stmt::Switch(value, _) | Stmt::Synchronized(value, _) => vec![value],
+      Stmt::Assign(target, value) => vec![target, value],
+      Stmt::Return(value) => value.iter_mut().collect(),
+      Stmt::For(_, condition, ..) => vec![condition],
+      _ => Vec::new(),
+    }
+  }
+
+  pub fn exprs(&self) -> Vec<&Expr> {
+    match self {
+      Stmt::Expr(value) | Stmt::Throw(value) | Stmt::Monitor(_, value) | Stmt::If(value, ..) | Stmt::While(value, _) | Stmt::DoWhile(_, value) => {
+        vec![value]
+      },
+      Stmt::Switch(value, _) | Stmt::Synchronized(value, _) => vec![value],
+      Stmt::Assign(target, value) => vec![target, value],
+      Stmt::Return(value) => value.iter().collect(),
+      Stmt::For(_, condition, ..) => vec![condition],
+      _ => Vec::new(),
+    }
+  }
+
+  // 入れ子の文の列
+  pub fn bodies_mut(&mut self) -> Vec<&mut Vec<Stmt>> {
+    match self {
+      Stmt::If(_, then_body, else_body) => vec![then_body, else_body],
+      Stmt::While(_, body) | Stmt::DoWhile(body, _) => vec![body],
+      Stmt::For(_, _, _, body) => vec![body],
+      Stmt::Switch(_, cases) => cases.iter_mut().map(|case| &mut case.body).collect(),
+      Stmt::Try(body, catches, finally) => std::iter::once(body).chain(catches.iter_mut().map(|catch| &mut catch.body)).chain(std::iter::once(finally)).collect(),
+      Stmt::Labeled(_, stmt) => stmt.bodies_mut(),
+      Stmt::Block(body) | Stmt::Synchronized(_, body) => vec![body],
+      _ => Vec::new(),
+    }
+  }
+}
+
+fn mentions(expr: &Expr, local: usize) -> bool {
+  expr.any(&|expr| *expr == Expr::Local(local))
+}
+
+// 入れ子の文の列 (switchの各caseとtryのcatchを含む)
+fn nested_bodies(stmt: &Stmt) -> Vec<&[Stmt]> {
+  match stmt {
+    Stmt::If(_, then_body, else_body) => vec![then_body, else_body],
+    Stmt::While(_, body) | Stmt::DoWhile(body, _) | Stmt::For(_, _, _, body) => vec![body],
+    Stmt::Switch(_, cases) => cases.iter().map(|case| case.body.as_slice()).collect(),
+    Stmt::Try(body, catches, finally) => {
+      std::iter::once(body.as_slice()).chain(catches.iter().map(|catch| catch.body.as_slice())).chain(std::iter::once(finally.as_slice())).collect()
+    },
+    Stmt::Labeled(_, stmt) => nested_bodies(stmt),
+    Stmt::Block(body) | Stmt::Synchronized(_, body) => vec![body],
+    _ => Vec::new(),
+  }
+}
+
+fn body_uses_local(body: &[Stmt], local: usize) -> bool {
+  body.iter().any(|stmt| uses_local(stmt, local))
+}
+
+// ローカル変数を読み書きする文か (catchの引数を含む)
+fn uses_local(stmt: &Stmt, local: usize) -> bool {
+  match stmt {
+    Stmt::Labeled(_, stmt) => uses_local(stmt, local),
+    Stmt::For(init, _, update, _) if init.iter().chain(update).any(|stmt| uses_local(stmt, local)) => true,
+    Stmt::Try(_, catches, _) if catches.iter().any(|catch| catch.local == Some(local)) => true,
+    _ => stmt.exprs().into_iter().any(|expr| mentions(expr, local)) || nested_bodies(stmt).into_iter().any(|body| body_uses_local(body, local)),
+  }
+}
+
+// 自身を参照しない値の代入 (変数の宣言を兼ねられる)
+fn is_declaration(stmt: &Stmt, local: usize) -> bool {
+  matches!(stmt, Stmt::Assign(Expr::Local(target), value) if *target == local && !mentions(value, local))
+}
+
+// ローカル変数を使う入れ子の文の列 (catchの引数にする変数はそのcatchの中だけで有効なので除く)
+fn scopes_using(stmt: &Stmt, local: usize) -> Vec<&[Stmt]> {
+  match stmt {
+    Stmt::Labeled(_, stmt) => scopes_using(stmt, local),
+    Stmt::Try(body, catches, finally) => std::iter::once(body.as_slice())
+      .chain(catches.iter().filter(|catch| catch.local != Some(local)).map(|catch| catch.body.as_slice()))
+      .chain(std::iter::once(finally.as_slice()))
+      .filter(|body| body_uses_local(body, local))
+      .collect(),
+    _ => nested_bodies(stmt).into_iter().filter(|body| body_uses_local(body, local)).collect(),
+  }
+}
+
+// ローカル変数をこの文の中のスコープで宣言できるか
+// (forの初期化かcatchの引数で宣言するか、1つの入れ子の文の列の中だけで使う)
+// tryの本体、catch、finallyはそれぞれ別のスコープなので、2つ以上で使う変数はtryの外で宣言する
+fn declared_inside(stmt: &Stmt, local: usize) -> bool {
+  match stmt {
+    Stmt::Labeled(_, stmt) => declared_inside(stmt, local),
+    Stmt::For(Some(init), ..) if is_declaration(init, local) => true,
+    Stmt::For(init, _, update, _) if init.iter().chain(update).any(|stmt| uses_local(stmt, local)) => false,
+    Stmt::Try(_, catches, _) if catches.iter().any(|catch| catch.local == Some(local)) => scopes_using(stmt, local).len() <= 1,
+    _ => !stmt.exprs().into_iter().any(|expr| mentions(expr, local)) && scopes_using(stmt, local).len() == 1,
+  }
+}
+
+// 文の中で、ローカル変数を読む前に必ず代入するか (declared_insideな文について、直前の値を使わないか)
+fn is_fresh(stmt: &Stmt, local: usize) -> bool {
+  match stmt {
+    Stmt::Labeled(_, stmt) => is_fresh(stmt, local),
+    _ if is_declaration(stmt, local) => true,
+    Stmt::For(Some(init), ..) if is_declaration(init, local) => true,
+    _ => scopes_using(stmt, local)
+      .into_iter()
+      .all(|body| body.iter().find(|stmt| uses_local(stmt, local)).is_some_and(|stmt| is_fresh(stmt, local))),
+  }
+}
+
+// 最初に見つかる、ローカル変数に代入する値
+fn first_assignment(body: &[Stmt], local: usize) -> Option<&Expr> {
+  body.iter().find_map(|stmt| match stmt {
+    Stmt::Assign(Expr::Local(target), value) if *target == local => Some(value),
+    Stmt::For(Some(init), ..) if matches!(&**init, Stmt::Assign(Expr::Local(target), _) if *target == local) => first_assignment(std::slice::from_ref(&**init), local),
+    stmt => nested_bodies(stmt).into_iter().find_map(|body| first_assignment(body, local)),
+  })
+}
+
+pub fn ends_abruptly(body: &[Stmt]) -> bool {
+  body.last().is_some_and(|stmt| !stmt.falls_through())
+}
+
+pub fn is_comparison(op: &str) -> bool {
+  matches!(op, "==" | "!=" | "<" | ">=" | ">" | "<=")
+}
+
+// 条件の否定 (比較は演算子を反転する)
+pub fn negate(condition: Expr) -> Expr {
+  match condition {
+    Expr::Unary("!", value) => *value,
+    Expr::Binary(op, left, right) if is_comparison(op) => {
+      let op = match op {
+        "==" => "!=",
+        "!=" => "==",
+        "<" => ">=",
+        ">=" => "<",
+        ">" => "<=",
+        _ => ">",
+      };
+      Expr::Binary(op, left, right)
+    },
+    Expr::Binary("&&", left, right) => Expr::binary("||", negate(*left), negate(*right)),
+    Expr::Binary("||", left, right) => Expr::binary("&&", negate(*left), negate(*right)),
+    Expr::Literal(Literal::Boolean(value)) => Expr::Literal(Literal::Boolean(!value)),
+    condition => Expr::unary("!", condition),
+  }
+}
+
+impl Scope {
+  // 式の静的な型 (分からなければNone)
+  pub fn expr_type(&self, expr: &Expr) -> Option<FieldType> {
+    match expr {
+      Expr::Literal(literal) => match literal {
+        Literal::Int(_) => Some(FieldType::Int),
+        Literal::Long(_) => Some(FieldType::Long),
+        Literal::Float(_) => Some(FieldType::Float),
+        Literal::Double(_) => Some(FieldType::Double),
+        Literal::Boolean(_) => Some(FieldType::Boolean),
+        Literal::Char(_) => Some(FieldType::Char),
+        Literal::String(_) => Some(FieldType::Object("java/lang/String".to_string())),
+        Literal::Class(_) => Some(FieldType::Object("java/lang/Class".to_string())),
+        Literal::Null | Literal::Other(_) => None,
+      },
+      Expr::Local(local) => self.locals.get(*local).and_then(|local| local.field_type.clone()),
+      Expr::This => Some(FieldType::Object(self.class_name.clone())),
+      Expr::Caught => Some(FieldType::Object("java/lang/Throwable".to_string())),
+      Expr::Field { descriptor, .. } => FieldType::parse(descriptor).ok(),
+      Expr::ArrayElement(array, _) => match self.expr_type(array) {
+        Some(FieldType::Array(component)) => Some(*component),
+        _ => None,
+      },
+      Expr::ArrayLength(_) | Expr::Compare(..) => Some(FieldType::Int),
+      Expr::Invoke { descriptor, .. } => MethodDescriptor::parse(descriptor).ok().and_then(|descriptor| descriptor.return_type),
+      Expr::New { class, .. } | Expr::Uninit(_, class) => Some(FieldType::Object(class.clone())),
+      Expr::NewArray { array_type, .. } | Expr::ArrayInit { array_type, .. } | Expr::Cast(array_type, _) => Some(array_type.clone()),
+      Expr::InstanceOf(..) => Some(FieldType::Boolean),
+      Expr::Unary("!", _) => Some(FieldType::Boolean),
+      Expr::Unary(_, value) => self.expr_type(value).map(promote),
+      Expr::PostIncrement(_, value) => self.expr_type(value),
+      Expr::Binary(op, left, right) => {
+        if is_comparison(op) || *op == "&&" || *op == "||" {
+          return Some(FieldType::Boolean);
+        }
+        let left = self.expr_type(left)?;
+        if matches!(*op, "&" | "|" | "^") && left == FieldType::Boolean {
+          return Some(FieldType::Boolean);
+        }
+        if matches!(*op, "<<" | ">>" | ">>>") {
+          return Some(promote(left));
+        }
+        let right = self.expr_type(right).unwrap_or(FieldType::Int);
+        Some(if left.is_wide() || !right.is_wide() && left == FieldType::Float { promote(left) } else { promote(right) })
+      },
+      Expr::Ternary(_, then_value, else_value) => self.expr_type(then_value).or_else(|| self.expr_type(else_value)),
+      Expr::Concat(_) => Some(FieldType::Object("java/lang/String".to_string())),
+      Expr::Stack(_) | Expr::Lambda { .. } | Expr::MethodRef { .. } | Expr::Dynamic { .. } => None,
+    }
+  }
+}
+
+// 算術演算でintに広げられる型
+fn promote(field_type: FieldType) -> FieldType {
+  match field_type {
+    FieldType::Byte | FieldType::Short | FieldType::Char => FieldType::Int,
+    field_type => field_type,
+  }
+}
+
+// バイトコードではintになっているbooleanとcharの値を、使われる型に合わせる
+pub fn coerce(expr: Expr, target: Option<&FieldType>) -> Expr {
+  match (expr, target) {
+    (Expr::Literal(Literal::Int(value)), Some(FieldType::Boolean)) if value == 0 || value == 1 => Expr::Literal(Literal::Boolean(value == 1)),
+    (Expr::Literal(Literal::Int(value)), Some(FieldType::Char)) if (0..=0xffff).contains(&value) => Expr::Literal(Literal::Char(value as u16)),
+    (Expr::Ternary(condition, then_value, else_value), Some(FieldType::Boolean)) => {
+      match (&*then_value, &*else_value) {
+        (Expr::Literal(Literal::Int(1)), Expr::Literal(Literal::Int(0))) => *condition,
+        (Expr::Literal(Literal::Int(0)), Expr::Literal(Literal::Int(1))) => negate(*condition),
+        _ => Expr::Ternary(condition, Box::new(coerce(*then_value, target)), Box::new(coerce(*else_value, target))),
+      }
+    },
+    (Expr::Ternary(condition, then_value, else_value), Some(_)) => {
+      Expr::Ternary(condition, Box::new(coerce(*then_value, target)), Box::new(coerce(*else_value, target)))
+    },
+    (expr, _) => expr,
+  }
+}
+
+// 演算子の優先順位 (大きいほど強く結合する)
+fn precedence(expr: &Expr) -> u8 {
+  match expr {
+    Expr::PostIncrement(..) => 15,
+    Expr::Unary(..) | Expr::Cast(..) => 14,
+    Expr::Binary(op, ..) => binary_precedence(op),
+    Expr::InstanceOf(..) => 10,
+    Expr::Concat(_) => 12,
+    Expr::Ternary(..) => 3,
+    Expr::Lambda { .. } => 1,
+    Expr::Literal(Literal::Int(value)) if *value < 0 => 14,
+    Expr::Literal(Literal::Long(value)) if *value < 0 => 14,
+    _ => 16,
+  }
+}
+
+fn binary_precedence(op: &str) -> u8 {
+  match op {
+    "*" | "/" | "%" => 13,
+    "+" | "-" => 12,
+    "<<" | ">>" | ">>>" => 11,
+    "<" | ">" | "<=" | ">=" => 10,
+    "==" | "!=" => 9,
+    "&" => 8,
+    "^" => 7,
+    "|" => 6,
+    "&&" => 5,
+    _ => 4,
+  }
+}
+
+fn char_literal(value: u16) -> String {
+  match value {
+    0x27 => "'\\''".to_string(),
+    0x5c => "'\\\\'".to_string(),
+    0x0a => "'\\n'".to_string(),
+    0x09 => "'\\t'".to_string(),
+    0x0d => "'\\r'".to_string(),
+    0x08 => "'\\b'".to_string(),
+    0x0c => "'\\f'".to_string(),
+    0x20..=0x7e => format!("'{}'", value as u8 as char),
+    _ => format!("'\\u{:04x}'", value),
+  }
+}
+
+pub fn string_literal(value: &str) -> String {
+  let mut text = String::from("\"");
+  for c in value.chars() {
+    match c {
+      '"' => text.push_str("\\\""),
+      '\\' => text.push_str("\\\\"),
+      '\n' => text.push_str("\\n"),
+      '\t' => text.push_str("\\t"),
+      '\r' => text.push_str("\\r"),
+      '\u{8}' => text.push_str("\\b"),
+      '\u{c}' => text.push_str("\\f"),
+      c if c.is_control() => text.push_str(&format!("\\u{:04x}", c as u32)),
+      c => text.push(c),
+    }
+  }
+  text.push('"');
+  text
+}
+
+// Javaのソースの字下げ
+const INDENT: &str = "    ";
+
+pub struct Printer<'a> {
+  names: &'a Names,
+  scope: &'a Scope,
+  lines: Vec<String>,
+  indent: usize,
+  declared: Vec<bool>,
+}
+
+impl<'a> Printer<'a> {
+  pub fn new(names: &'a Names, scope: &'a Scope, indent: usize) -> Printer<'a> {
+    let declared = scope.locals.iter().map(|local| local.parameter).collect();
+    Printer { names, scope, lines: Vec::new(), indent, declared }
+  }
+
+  pub fn finish(self) -> Vec<String> {
+    self.lines
+  }
+
+  fn line(&mut self, text: &str) {
+    let indent = INDENT.repeat(self.indent);
+    // ラムダ式の本体など複数行になる式は同じ字下げで続ける
+    let text = text.replace('\n', &format!("\n{}", indent));
+    self.lines.push(format!("{}{}", indent, text));
+  }
+
+  fn local_name(&self, local: usize) -> String {
+    self.scope.locals.get(local).map(|local| local.name.clone()).unwrap_or_else(|| format!("local{}", local))
+  }
+
+  pub fn local_type(&self, local: usize) -> String {
+    match self.scope.locals.get(local) {
+      Some(Local { signature: Some(signature), .. }) => self.names.signature(signature),
+      Some(Local { field_type: Some(field_type), .. }) => self.names.field_type(field_type),
+      _ => "var".to_string(),
+    }
+  }
+
+  fn class_prefix(&self, class: &str) -> String {
+    if class == self.scope.class_name { String::new() } else { format!("{}.", self.names.class(class)) }
+  }
+
+  fn args(&self, args: &[Expr], descriptor: &str) -> String {
+    let parameters = MethodDescriptor::parse(descriptor).map(|descriptor| descriptor.parameters).unwrap_or_default();
+    let args: Vec<String> = args.iter().enumerate().map(|(i, arg)| self.expr(&coerce(arg.clone(), parameters.get(i)), 2)).collect();
+    args.join(", ")
+  }
+
+  fn literal(&self, literal: &Literal) -> String {
+    match literal {
+      Literal::Int(value) => value.to_string(),
+      Literal::Long(value) => format!("{}L", value),
+      Literal::Float(value) if value.is_nan() => "Float.NaN".to_string(),
+      Literal::Float(value) if value.is_infinite() => format!("Float.{}_INFINITY", if *value > 0.0 { "POSITIVE" } else { "NEGATIVE" }),
+      Literal::Float(value) => format!("{}f", float_to_string(*value)),
+      Literal::Double(value) if value.is_nan() => "Double.NaN".to_string(),
+      Literal::Double(value) if value.is_infinite() => format!("Double.{}_INFINITY", if *value > 0.0 { "POSITIVE" } else { "NEGATIVE" }),
+      Literal::Double(value) => double_to_string(*value),
+      Literal::Boolean(value) => value.to_string(),
+      Literal::Char(value) => char_literal(*value),
+      Literal::String(value) => string_literal(value),
+      Literal::Null => "null".to_string(),
+      Literal::Class(field_type) => format!("{}.class", self.names.field_type(field_type)),
+      Literal::Other(text) => format!("/* {} */ null", text),
+    }
+  }
+
+  // min以上の優先順位でなければ括弧で囲む
+  pub fn expr(&self, expr: &Expr, min: u8) -> String {
+    let text = match expr {
+      Expr::Literal(literal) => self.literal(literal),
+      Expr::Local(local) => self.local_name(*local),
+      Expr::Stack(index) => format!("${}", index),
+      Expr::This => "this".to_string(),
+      Expr::Caught => "$exception".to_string(),
+      Expr::Field { object: Some(object), name, .. } => format!("{}.{}", self.expr(object, 16), name),
+      Expr::Field { object: None, class, name, .. } => format!("{}{}", self.class_prefix(class), name),
+      Expr::ArrayElement(array, index) => format!("{}[{}]", self.expr(array, 16), self.expr(index, 0)),
+      Expr::ArrayLength(array) => format!("{}.length", self.expr(array, 16)),
+      Expr::Invoke { object, class, name, descriptor, args, special } => {
+        let args = self.args(args, descriptor);
+        match object.as_deref() {
+          Some(Expr::This) if name == "<init>" => {
+            format!("{}({})", if *class == self.scope.class_name { "this" } else { "super" }, args)
+          },
+          Some(Expr::This) if *special && *class != self.scope.class_name => format!("super.{}({})", name, args),
+          Some(object) => format!("{}.{}({})", self.expr(object, 16), name, args),
+          None => format!("{}{}({})", self.class_prefix(class), name, args),
+        }
+      },
+      Expr::New { class, descriptor, args } => format!("new {}({})", self.names.class(class), self.args(args, descriptor)),
+      Expr::Uninit(_, class) => format!("/* uninitialized */ new {}", self.names.class(class)),
+      Expr::NewArray { array_type, lengths } => {
+        let mut element = array_type;
+        let mut dimensions = 0;
+        while let FieldType::Array(component) = element {
+          element = component;
+          dimensions += 1;
+        }
+        let mut text = format!("new {}", self.names.field_type(element));
+        for length in lengths {
+          text.push_str(&format!("[{}]", self.expr(length, 0)));
+        }
+        text.push_str(&"[]".repeat(dimensions - lengths.len().min(dimensions)));
+        text
+      },
+      Expr::ArrayInit { array_type, values, .. } => {
+        let component = match array_type {
+          FieldType::Array(component) => Some(&**component),
+          _ => None,
+        };
+        let values: Vec<String> = values
+          .iter()
+          .map(|value| match value {
+            Some(value) => self.expr(&coerce(value.clone(), component), 2),
+            None if component.is_some_and(FieldType::is_reference) => "null".to_string(),
+            None if component == Some(&FieldType::Boolean) => "false".to_string(),
+            None => "0".to_string(),
+          })
+          .collect();
+        format!("new {}{{{}}}", self.names.field_type(array_type), values.join(", "))
+      },
+      Expr::Unary(op, value) => {
+        let operand = self.expr(value, 14);
+        // - -x や ! の連続が別の演算子に見えないようにする
+        if operand.starts_with(op) { format!("{}({})", op, operand) } else { format!("{}{}", op, operand) }
+      },
+      Expr::PostIncrement(op, value) => format!("{}{}", self.expr(value, 16), op),
+      Expr::Binary(op, left, right) => {
+        let precedence = binary_precedence(op);
+        let (left, right) = self.comparison_operands(op, left, right);
+        format!("{} {} {}", self.expr(&left, precedence), op, self.expr(&right, precedence + 1))
+      },
+      Expr::Compare(left, right) => {
+        let class = match self.scope.expr_type(left).or_else(|| self.scope.expr_type(right)) {
+          Some(FieldType::Float) => "Float",
+          Some(FieldType::Double) => "Double",
+          _ => "Long",
+        };
+        format!("{}.compare({}, {})", class, self.expr(left, 0), self.expr(right, 0))
+      },
+      Expr::Cast(field_type, value) => format!("({}) {}", self.names.field_type(field_type), self.expr(value, 14)),
+      Expr::InstanceOf(value, field_type) => format!("{} instanceof {}", self.expr(value, 10), self.names.field_type(field_type)),
+      Expr::Ternary(condition, then_value, else_value) => {
+        let condition = coerce((**condition).clone(), Some(&FieldType::Boolean));
+        format!("{} ? {} : {}", self.expr(&condition, 4), self.expr(then_value, 4), self.expr(else_value, 3))
+      },
+      Expr::Concat(parts) => {
+        let parts: Vec<String> = parts.iter().map(|part| self.expr(part, 13)).collect();
+        parts.join(" + ")
+      },
+      Expr::Lambda { params, body, block } => {
+        let params = if params.len() == 1 { params[0].clone() } else { format!("({})", params.join(", ")) };
+        if *block {
+          let body: String = body.lines().map(|line| format!("{}{}\n", INDENT, line)).collect();
+          format!("{} -> {{\n{}}}", params, body)
+        } else {
+          format!("{} -> {}", params, body)
+        }
+      },
+      Expr::MethodRef { class, object, name } => {
+        let target = match (class, object) {
+          (_, Some(object)) => self.expr(object, 16),
+          (Some(class), None) => self.names.class(class),
+          (None, None) => "this".to_string(),
+        };
+        format!("{}::{}", target, name)
+      },
+      Expr::Dynamic { name, args } => {
+        let args: Vec<String> = args.iter().map(|arg| self.expr(arg, 2)).collect();
+        format!("/* invokedynamic */ {}({})", name, args.join(", "))
+      },
+    };
+    if precedence(expr) < min { format!("({})", text) } else { text }
+  }
+
+  // booleanやcharとの比較では、もう一方のintのリテラルを合わせる
+  fn comparison_operands(&self, op: &str, left: &Expr, right: &Expr) -> (Expr, Expr) {
+    if !is_comparison(op) && !matches!(op, "&" | "|" | "^") {
+      return (left.clone(), right.clone());
+    }
+    let left_type = self.scope.expr_type(left);
+    let right_type = self.scope.expr_type(right);
+    (coerce(left.clone(), right_type.as_ref()), coerce(right.clone(), left_type.as_ref()))
+  }
+
+  // 文の末尾の;を除いた表記 (forの初期化と更新でも使う)
+  fn simple_stmt(&mut self, stmt: &Stmt) -> String {
+    match stmt {
+      Stmt::Assign(target, value) => {
+        let target_type = match target {
+          Expr::Local(local) => self.scope.locals.get(*local).and_then(|local| local.field_type.clone()),
+          Expr::Stack(_) => None,
+          target => self.scope.expr_type(target),
+        };
+        let value = coerce(value.clone(), target_type.as_ref());
+        if let Expr::Local(local) = target
+          && !self.declared.get(*local).copied().unwrap_or(true)
+        {
+          self.declared[*local] = true;
+          // 型の分からない変数は代入する値の型で宣言する
+          let type_text = match self.scope.locals.get(*local) {
+            Some(Local { field_type: None, signature: None, .. }) => match self.scope.expr_type(&value) {
+              Some(field_type) => self.names.field_type(&field_type),
+              None => self.local_type(*local),
+            },
+            _ => self.local_type(*local),
+          };
+          return format!("{} {} = {}", type_text, self.local_name(*local), self.expr(&value, 1));
+        }
+        let target_text = self.expr(target, 16);
+        if let Expr::Binary(op, left, right) = &value
+          && **left == *target
+          && matches!(*op, "+" | "-" | "*" | "/" | "%" | "&" | "|" | "^" | "<<" | ">>" | ">>>")
+        {
+          if matches!(*op, "+" | "-") && matches!(**right, Expr::Literal(Literal::Int(1) | Literal::Long(1))) {
+            return format!("{}{}{}", target_text, op, op);
+          }
+          return format!("{} {}= {}", target_text, op, self.expr(right, 1));
+        }
+        format!("{} = {}", target_text, self.expr(&value, 1))
+      },
+      Stmt::Expr(value) => self.expr(value, 0),
+      _ => String::new(),
+    }
+  }
+
+  fn block(&mut self, body: &[Stmt]) {
+    self.indent += 1;
+    self.stmts(body);
+    self.indent -= 1;
+  }
+
+  pub fn stmts(&mut self, body: &[Stmt]) {
+    let declarations = self.declarations(body);
+    for (i, stmt) in body.iter().enumerate() {
+      for &(_, local) in declarations.iter().filter(|(at, _)| *at == i) {
+        self.declared[local] = true;
+        let type_text = self.declaration_type(local, &body[i..]);
+        self.line(&format!("{} {};", type_text, self.local_name(local)));
+      }
+      // 入れ子の文の中で宣言した変数はその文の外では使えない
+      let outer = (!matches!(stmt, Stmt::Assign(..) | Stmt::Expr(_))).then(|| self.declared.clone());
+      self.stmt(stmt);
+      if let Some(outer) = outer {
+        self.declared = outer;
+      }
+    }
+  }
+
+  // 変数は使う全ての文を含む最も内側の文の列で宣言する
+  // その列で最初に使う文が代入ならそこで宣言し、そうでなければその文の前に宣言だけを置く (文の番号と変数)
+  fn declarations(&self, body: &[Stmt]) -> Vec<(usize, usize)> {
+    let mut declarations = Vec::new();
+    for local in (0..self.declared.len()).filter(|&local| !self.declared[local]) {
+      let uses: Vec<usize> = body.iter().enumerate().filter(|(_, stmt)| uses_local(stmt, local)).map(|(i, _)| i).collect();
+      if let [only] = uses[..]
+        && declared_inside(&body[only], local)
+      {
+        continue;
+      }
+      // 中で代入してから使う文 (別々のforのカウンタなど) はそれぞれのスコープで宣言する
+      let Some(&first) = uses.iter().find(|&&i| !(declared_inside(&body[i], local) && is_fresh(&body[i], local))) else {
+        continue;
+      };
+      // 前の文で代入した値を読むかもしれないので、最初に使う文の前で宣言する
+      if !is_declaration(&body[first], local) {
+        declarations.push((uses[0], local));
+      }
+    }
+    declarations
+  }
+
+  // 型の分からない変数は最初に代入する値の型で宣言する
+  fn declaration_type(&self, local: usize, body: &[Stmt]) -> String {
+    if let Some(Local { field_type: None, signature: None, .. }) = self.scope.locals.get(local)
+      && let Some(field_type) = first_assignment(body, local).and_then(|value| self.scope.expr_type(value))
+    {
+      return self.names.field_type(&field_type);
+    }
+    self.local_type(local)
+  }
+
+  fn condition(&self, condition: &Expr) -> String {
+    self.expr(&coerce(condition.clone(), Some(&FieldType::Boolean)), 0)
+  }
+
+  pub fn stmt(&mut self, stmt: &Stmt) {
+    match stmt {
+      Stmt::Expr(_) | Stmt::Assign(..) => {
+        let text = self.simple_stmt(stmt);
+        self.line(&format!("{};", text));
+      },
+      Stmt::Return(None) => self.line("return;"),
+      Stmt::Return(Some(value)) => {
+        let value = coerce(value.clone(), self.scope.return_type.as_ref());
+        self.line(&format!("return {};", self.expr(&value, 0)));
+      },
+      Stmt::Throw(value) => self.line(&format!("throw {};", self.expr(value, 0))),
+      Stmt::If(..) => {
+        let mut current = stmt;
+        let mut keyword = "if";
+        loop {
+          let Stmt::If(condition, then_body, else_body) = current else {
+            unreachable!();
+          };
+          let text = format!("{} ({}) {{", keyword, self.condition(condition));
+          if keyword == "if" {
+            self.line(&text);
+          } else {
+            let last = self.lines.pop().unwrap_or_default();
+            self.lines.push(format!("{} {}", last, text));
+          }
+          self.block(then_body);
+          match else_body.as_slice() {
+            [] => {
+              self.line("}");
+              break;
+            },
+            // 宣言を置く必要があればelse ifにしない
+            [nested @ Stmt::If(..)] if self.declarations(else_body).is_empty() => {
+              self.line("}");
+              keyword = "else if";
+              current = nested;
+            },
+            _ => {
+              self.line("} else {");
+              self.block(else_body);
+              self.line("}");
+              break;
+            },
+          }
+        }
+      },
+      Stmt::While(condition, body) => {
+        self.line(&format!("while ({}) {{", self.condition(condition)));
+        self.block(body);
+        self.line("}");
+      },
+      Stmt::DoWhile(body, condition) => {
+        self.line("do {");
+        self.block(body);
+        self.line(&format!("}} while ({});", self.condition(condition)));
+      },
+      Stmt::For(init, condition, update, body) => {
+        let init = init.as_deref().map(|init| self.simple_stmt(init)).unwrap_or_default();
+        let update = update.as_deref().map(|update| self.simple_stmt(update)).unwrap_or_default();
+        self.line(&format!("for ({}; {}; {}) {{", init, self.condition(condition), update));
+        self.block(body);
+        self.line("}");
+      },
+      Stmt::Switch(value, cases) => {
+        self.line(&format!("switch ({}) {{", self.expr(value, 0)));
+        self.indent += 1;
+        for case in cases {
+          for label in &case.labels {
+            match label {
+              Some(key) => self.line(&format!("case {}:", key)),
+              None => self.line("default:"),
+            }
+          }
+          self.block(&case.body);
+        }
+        self.indent -= 1;
+        self.line("}");
+      },
+      Stmt::Try(body, catches, finally) => {
+        self.line("try {");
+        self.block(body);
+        for catch in catches {
+          let types: Vec<String> = if catch.types.is_empty() {
+            vec!["Throwable".to_string()]
+          } else {
+            catch.types.iter().map(|name| self.names.class(name)).collect()
+          };
+          let name = match catch.local {
+            Some(local) => {
+              if let Some(declared) = self.declared.get_mut(local) {
+                *declared = true;
+              }
+              self.local_name(local)
+            },
+            None => "ignored".to_string(),
+          };
+          self.line(&format!("}} catch ({} {}) {{", types.join(" | "), name));
+          self.block(&catch.body);
+        }
+        if !finally.is_empty() {
+          self.line("} finally {");
+          self.block(finally);
+        }
+        self.line("}");
+      },
+      Stmt::Labeled(label, stmt) => match &**stmt {
+        Stmt::Block(body) => {
+          self.line(&format!("{}: {{", label));
+          self.block(body);
+          self.line("}");
+        },
+        stmt => {
+          self.line(&format!("{}:", label));
+          self.stmt(stmt);
+        },
+      },
+      Stmt::Block(body) => {
+        self.line("{");
+        self.block(body);
+        self.line("}");
+      },
+      Stmt::Synchronized(value, body) => {
+        self.line(&format!("synchronized ({}) {{", self.expr(value, 0)));
+        self.block(body);
+        self.line("}");
+      },
+      Stmt::Break(None) => self.line("break;"),
+      Stmt::Break(Some(label)) => self.line(&format!("break {};", label)),
+      Stmt::Continue(None) => self.line("continue;"),
+      Stmt::Continue(Some(label)) => self.line(&format!("continue {};", label)),
+      Stmt::Monitor(true, value) => self.line(&format!("/* monitorenter */ {};", self.expr(value, 0))),
+      Stmt::Monitor(false, value) => self.line(&format!("/* monitorexit */ {};", self.expr(value, 0))),
+      Stmt::Goto(pc) => self.line(&format!("// goto {}", pc)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn print(locals: &[(&str, bool)], body: &[Stmt]) -> Vec<String> {
+    let names = Names::new("Test");
+    let locals = locals
+      .iter()
+      .map(|&(name, parameter)| Local { name: name.to_string(), field_type: Some(FieldType::Int), signature: None, parameter })
+      .collect();
+    let scope = Scope { class_name: "Test".to_string(), locals, return_type: Some(FieldType::Int) };
+    let mut printer = Printer::new(&names, &scope, 0);
+    printer.stmts(body);
+    printer.finish()
+  }
+
+  fn assign(local: usize, value: Expr) -> Stmt {
+    Stmt::Assign(Expr::Local(local), value)
+  }
+
+  // int r; if (a > 0) r = a * 2; else r = -a; return r + 1;
+  #[test]
+  fn declaration_is_hoisted_above_branches() {
+    let a = || Expr::Local(0);
+    let body = [
+      Stmt::If(
+        Expr::binary(">", a(), Expr::int(0)),
+        vec![assign(1, Expr::binary("*", a(), Expr::int(2)))],
+        vec![assign(1, Expr::unary("-", a()))],
+      ),
+      Stmt::Return(Some(Expr::binary("+", Expr::Local(1), Expr::int(1)))),
+    ];
+    assert_eq!(
+      print(&[("a", true), ("r", false)], &body),
+      ["int r;", "if (a > 0) {", "    r = a * 2;", "} else {", "    r = -a;", "}", "return r + 1;"]
+    );
+  }
+
+  // 別々のブロックで代入してから使う変数は、それぞれのブロックで宣言する
+  #[test]
+  fn independent_uses_are_declared_in_their_own_scopes() {
+    let loop_body = |local: usize| {
+      Stmt::While(Expr::Literal(Literal::Boolean(true)), vec![assign(local, Expr::int(1)), Stmt::Return(Some(Expr::Local(local)))])
+    };
+    assert_eq!(
+      print(&[("t", false)], &[loop_body(0), loop_body(0)]),
+      ["while (true) {", "    int t = 1;", "    return t;", "}", "while (true) {", "    int t = 1;", "    return t;", "}"]
+    );
+  }
+
+  // int k; try { k = 1; } catch (Throwable e) { k = 2; } finally { g(k); } return k;
+  #[test]
+  fn locals_shared_by_try_scopes_are_declared_outside() {
+    let body = [
+      Stmt::Try(
+        vec![assign(0, Expr::int(1))],
+        vec![Catch { types: Vec::new(), local: Some(1), body: vec![assign(0, Expr::int(2))] }],
+        vec![Stmt::Expr(Expr::Local(0))],
+      ),
+      Stmt::Return(Some(Expr::Local(0))),
+    ];
+    assert_eq!(
+      print(&[("k", false), ("e", false)], &body),
+      ["int k;", "try {", "    k = 1;", "} catch (Throwable e) {", "    k = 2;", "} finally {", "    k;", "}", "return k;"]
+    );
+  }
+
+  #[test]
+  fn increments_are_printed_as_operators() {
+    let i = || Expr::Local(0);
+    let body = [
+      Stmt::Expr(Expr::binary("+", Expr::PostIncrement("++", Box::new(i())), i())),
+      Stmt::Assign(i(), Expr::binary("-", i(), Expr::Literal(Literal::Long(1)))),
+    ];
+    assert_eq!(print(&[("i", true)], &body), ["i++ + i;", "i--;"]);
+  }
+
+  // do-whileの条件で使う変数は本体の外で宣言する
+  #[test]
+  fn variable_used_in_loop_condition_is_declared_before_loop() {
+    let body = [
+      Stmt::DoWhile(vec![assign(0, Expr::int(1))], Expr::binary("!=", Expr::Local(0), Expr::int(0))),
+      Stmt::Return(Some(Expr::int(0))),
+    ];
+    assert_eq!(print(&[("k", false)], &body), ["int k;", "do {", "    k = 1;", "} while (k != 0);", "return 0;"]);
+  }
+
+  #[test]
+  fn labeled_block_and_synchronized() {
+    let body = [Stmt::Synchronized(
+      Expr::This,
+      vec![Stmt::Labeled("label0".to_string(), Box::new(Stmt::Block(vec![Stmt::If(Expr::Local(0), vec![Stmt::Break(Some("label0".to_string()))], Vec::new())])))],
+    )];
+    assert_eq!(
+      print(&[("b", true)], &body),
+      ["synchronized (this) {", "    label0: {", "        if (b) {", "            break label0;", "        }", "    }", "}"]
+    );
+  }
+}
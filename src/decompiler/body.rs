@@ -0,0 +1,1188 @@
+use std::{collections::HashMap, mem};
+
+use crate::{
+  cfg::ControlFlowGraph,
+  decompiler::{
+    ast::{negate, Expr, Literal, Local, Scope, Stmt},
+    ClassContext,
+  },
+  structure::{
+    class::{CodeAttribute, CodeNestedAttribute, Constant as PoolConstant, ConstantPool, Method, MethodInfoAttribute},
+    code::{CodeByte, StackShuffle},
+  },
+  util::{
+    descriptor::{FieldType, MethodDescriptor},
+    graph::{predecessors, reverse_postorder},
+    signature::TypeSignature,
+  },
+};
+
+// 文の列に直したバイトコードの基本ブロック
+
+#[derive(Debug, Clone)]
+pub enum Exit {
+  Goto(usize),
+  // 条件が成り立てば1つ目、成り立たなければ2つ目のブロックへ
+  If(Expr, usize, usize),
+  // Noneはdefault
+  Switch(Expr, Vec<(Option<i32>, usize)>),
+  Return(Option<Expr>),
+  Throw(Expr),
+}
+
+#[derive(Debug, Clone)]
+pub struct Block {
+  pub pc: usize,
+  pub stmts: Vec<Stmt>,
+  pub exit: Exit,
+  // 出口でのオペランドスタックの深さ ($0から$(exit_depth - 1)が後続に渡る)
+  pub exit_depth: usize,
+}
+
+// 範囲が同じ例外テーブルのエントリをまとめたもの
+#[derive(Debug, Clone)]
+pub struct Region {
+  pub start_pc: usize,
+  pub end_pc: usize,
+  // catch_typeとハンドラのブロック (Noneなら全ての例外)
+  pub handlers: Vec<(Option<String>, usize)>,
+}
+
+pub struct MethodBody {
+  pub scope: Scope,
+  // ControlFlowGraphと同じ番号 (到達できないブロックや併合したブロックはNone)
+  pub blocks: Vec<Option<Block>>,
+  pub regions: Vec<Region>,
+  // 例外ハンドラの入口
+  pub handlers: Vec<usize>,
+}
+
+impl Exit {
+  pub fn successors(&self) -> Vec<usize> {
+    let mut targets: Vec<usize> = match self {
+      Exit::Goto(target) => vec![*target],
+      Exit::If(_, then_block, else_block) => vec![*then_block, *else_block],
+      Exit::Switch(_, cases) => cases.iter().map(|(_, target)| *target).collect(),
+      Exit::Return(_) | Exit::Throw(_) => Vec::new(),
+    };
+    let mut seen = Vec::new();
+    targets.retain(|target| {
+      let first = !seen.contains(target);
+      seen.push(*target);
+      first
+    });
+    targets
+  }
+
+  fn targets_mut(&mut self) -> Vec<&mut usize> {
+    match self {
+      Exit::Goto(target) => vec![target],
+      Exit::If(_, then_block, else_block) => vec![then_block, else_block],
+      Exit::Switch(_, cases) => cases.iter_mut().map(|(_, target)| target).collect(),
+      Exit::Return(_) | Exit::Throw(_) => Vec::new(),
+    }
+  }
+
+  pub fn exprs_mut(&mut self) -> Vec<&mut Expr> {
+    match self {
+      Exit::If(value, ..) | Exit::Switch(value, _) | Exit::Throw(value) | Exit::Return(Some(value)) => vec![value],
+      Exit::Goto(_) | Exit::Return(None) => Vec::new(),
+    }
+  }
+}
+
+// 文の中でvariableを読む回数 (代入先の$kそのものは数えない)
+fn uses(stmt: &Stmt, variable: &Expr) -> usize {
+  let mut stmt = stmt.clone();
+  if let Stmt::Assign(target, value) = &mut stmt
+    && *target == *variable
+  {
+    return value.count(variable);
+  }
+  stmt.exprs_mut().into_iter().map(|expr| expr.count(variable)).sum()
+}
+
+fn substitute(stmt: &mut Stmt, variable: &Expr, value: &Expr) {
+  if let Stmt::Assign(target, assigned) = stmt
+    && *target == *variable
+  {
+    assigned.replace(variable, value);
+    return;
+  }
+  for expr in stmt.exprs_mut() {
+    expr.replace(variable, value);
+  }
+}
+
+// LocalVariableTableのエントリ (引数を除く)
+struct Variable {
+  slot: usize,
+  start: usize,
+  end: usize,
+  name: String,
+  field_type: Option<FieldType>,
+}
+
+// 命令が読み書きするローカル変数 (スロット、読むか、書くか)
+fn local_access(code_byte: &CodeByte) -> Option<(usize, bool, bool)> {
+  let data = &code_byte.data;
+  match code_byte.opcode {
+    0x15..=0x19 | 0xa9 => Some((*data.first()? as usize, true, false)),
+    0x1a..=0x2d => Some((((code_byte.opcode - 0x1a) % 4) as usize, true, false)),
+    0x36..=0x3a => Some((*data.first()? as usize, false, true)),
+    0x3b..=0x4e => Some((((code_byte.opcode - 0x3b) % 4) as usize, false, true)),
+    0x84 => Some((*data.first()? as usize, true, true)),
+    0xc4 => {
+      let slot = u16::from_be_bytes([*data.get(1)?, *data.get(2)?]) as usize;
+      match data[0] {
+        0x15..=0x19 | 0xa9 => Some((slot, true, false)),
+        0x36..=0x3a => Some((slot, false, true)),
+        0x84 => Some((slot, true, true)),
+        _ => None,
+      }
+    },
+    _ => None,
+  }
+}
+
+// javacは初期化せずに宣言した変数の範囲を、代入されていない区間で分ける
+// ある範囲で読む値を別の範囲で代入していれば、同じ名前と型のエントリを1つの変数にする (エントリごとの代表の番号)
+fn merge_split_ranges(variables: &[Variable], graph: &ControlFlowGraph, instructions: &HashMap<usize, &CodeByte>) -> Vec<usize> {
+  let mut groups: Vec<usize> = (0..variables.len()).collect();
+  let find = |groups: &[usize], mut i: usize| {
+    while groups[i] != i {
+      i = groups[i];
+    }
+    i
+  };
+  let range_at = |slot: usize, pc: usize| variables.iter().position(|v| v.slot == slot && (v.start..v.end).contains(&pc));
+  let predecessors = graph.predecessors();
+  for (&pc, code_byte) in instructions {
+    let Some((slot, true, _)) = local_access(code_byte) else {
+      continue;
+    };
+    let (Some(reader), Some(block)) = (range_at(slot, pc), graph.block_containing(pc)) else {
+      continue;
+    };
+    // 読む位置から逆に辿り、値を代入した命令を探す
+    let position = graph.blocks[block].instructions.iter().position(|&at| at == pc).unwrap_or(0);
+    let mut pending = vec![(block, position)];
+    let mut visited = vec![false; graph.blocks.len()];
+    while let Some((block, end)) = pending.pop() {
+      let store = graph.blocks[block].instructions[..end]
+        .iter()
+        .rev()
+        .find(|&&at| instructions.get(&at).and_then(|code_byte| local_access(code_byte)).is_some_and(|(s, _, store)| store && s == slot));
+      match store {
+        Some(&store) => {
+          let next_pc = store + 1 + instructions[&store].data.len();
+          if let Some(writer) = range_at(slot, next_pc).or_else(|| range_at(slot, store))
+            && variables[writer].name == variables[reader].name
+            && variables[writer].field_type == variables[reader].field_type
+          {
+            let (a, b) = (find(&groups, writer), find(&groups, reader));
+            groups[a.max(b)] = a.min(b);
+          }
+        },
+        None => {
+          for &predecessor in &predecessors[block] {
+            if !visited[predecessor] {
+              visited[predecessor] = true;
+              pending.push((predecessor, graph.blocks[predecessor].instructions.len()));
+            }
+          }
+        },
+      }
+    }
+  }
+  (0..variables.len()).map(|i| find(&groups, i)).collect()
+}
+
+// ローカル変数のスロットとScope::localsの対応
+struct Locals {
+  // スロット、有効な範囲、localsの番号
+  ranges: Vec<(usize, usize, usize, usize)>,
+  parameters: HashMap<usize, usize>,
+  // LocalVariableTableがない時のスロットと型の種類ごとの変数
+  others: HashMap<(usize, u8), usize>,
+  locals: Vec<Local>,
+}
+
+impl Locals {
+  fn new(
+    context: &ClassContext,
+    method: &Method,
+    code: &CodeAttribute,
+    descriptor: &MethodDescriptor,
+    is_static: bool,
+    graph: &ControlFlowGraph,
+    instructions: &HashMap<usize, &CodeByte>,
+  ) -> Result<Locals, String> {
+    let pool = context.pool;
+    let mut table = Vec::new();
+    let mut type_table = Vec::new();
+    for attribute in &code.attributes.attributes {
+      match attribute {
+        CodeNestedAttribute::LocalVariableTable(attribute) => table.extend(&attribute.local_variable_table),
+        CodeNestedAttribute::LocalVariableTypeTable(attribute) => type_table.extend(&attribute.local_variable_type_table),
+        _ => {},
+      }
+    }
+    let parameter_names: Vec<Option<String>> = method
+      .attributes
+      .attributes
+      .iter()
+      .find_map(|attribute| match attribute {
+        MethodInfoAttribute::MethodParameters(attribute) => Some(&attribute.parameters),
+        _ => None,
+      })
+      .map(|parameters| parameters.iter().map(|parameter| pool.get_utf8(parameter.name_index).ok().filter(|name| !name.is_empty())).collect())
+      .unwrap_or_default();
+
+    let mut locals = Locals { ranges: Vec::new(), parameters: HashMap::new(), others: HashMap::new(), locals: Vec::new() };
+    let signature_at = |slot: usize, start_pc: u16| {
+      type_table
+        .iter()
+        .find(|entry| entry.index as usize == slot && entry.start_pc == start_pc)
+        .and_then(|entry| pool.get_utf8(entry.signature_index).ok())
+        .and_then(|signature| TypeSignature::parse(&signature).ok())
+    };
+    let mut slot = usize::from(!is_static);
+    for (i, parameter) in descriptor.parameters.iter().enumerate() {
+      let entry = table.iter().find(|entry| entry.index as usize == slot && entry.start_pc == 0);
+      let name = match entry {
+        Some(entry) => pool.get_utf8(entry.name_index)?,
+        None => parameter_names.get(i).cloned().flatten().unwrap_or_else(|| format!("arg{}", i)),
+      };
+      locals.parameters.insert(slot, locals.locals.len());
+      locals.locals.push(Local { name, field_type: Some(parameter.clone()), signature: signature_at(slot, 0), parameter: true });
+      slot += if parameter.is_wide() { 2 } else { 1 };
+    }
+    let mut variables = Vec::new();
+    for entry in &table {
+      let slot = entry.index as usize;
+      let (start, end) = (entry.start_pc as usize, entry.start_pc as usize + entry.length as usize);
+      match locals.parameters.get(&slot) {
+        Some(&local) if entry.start_pc == 0 => locals.ranges.push((slot, start, end, local)),
+        _ => {
+          let name = pool.get_utf8(entry.name_index)?;
+          if name == "this" {
+            continue;
+          }
+          let field_type = FieldType::parse(&pool.get_utf8(entry.descriptor_index)?).ok();
+          variables.push(Variable { slot, start, end, name, field_type });
+        },
+      }
+    }
+    let groups = merge_split_ranges(&variables, graph, instructions);
+    let mut group_locals: HashMap<usize, usize> = HashMap::new();
+    for (variable, group) in variables.into_iter().zip(groups) {
+      let local = match group_locals.get(&group) {
+        Some(&local) => local,
+        None => {
+          let signature = signature_at(variable.slot, variable.start as u16);
+          locals.locals.push(Local { name: variable.name, field_type: variable.field_type, signature, parameter: false });
+          group_locals.insert(group, locals.locals.len() - 1);
+          locals.locals.len() - 1
+        },
+      };
+      locals.ranges.push((variable.slot, variable.start, variable.end, local));
+    }
+    Ok(locals)
+  }
+
+  fn in_range(&self, slot: usize, pc: usize) -> Option<usize> {
+    self.ranges.iter().find(|(s, start, end, _)| *s == slot && (*start..*end).contains(&pc)).map(|range| range.3)
+  }
+
+  // kindは'I', 'J', 'F', 'D', 'A'
+  fn find(&mut self, slot: usize, pcs: &[usize], kind: u8) -> usize {
+    if let Some(local) = pcs.iter().find_map(|&pc| self.in_range(slot, pc)) {
+      return local;
+    }
+    if let Some(&local) = self.parameters.get(&slot) {
+      return local;
+    }
+    let count = self.locals.len();
+    let local = *self.others.entry((slot, kind)).or_insert(count);
+    if local == count {
+      let (prefix, field_type) = match kind {
+        b'I' => ("i", Some(FieldType::Int)),
+        b'J' => ("l", Some(FieldType::Long)),
+        b'F' => ("f", Some(FieldType::Float)),
+        b'D' => ("d", Some(FieldType::Double)),
+        _ => ("o", None),
+      };
+      self.locals.push(Local { name: format!("{}{}", prefix, slot), field_type, signature: None, parameter: false });
+    }
+    local
+  }
+}
+
+const KINDS: [u8; 5] = [b'I', b'J', b'F', b'D', b'A'];
+
+struct Simulator<'a, 'b> {
+  context: &'a ClassContext<'b>,
+  graph: &'a ControlFlowGraph,
+  locals: Locals,
+  is_static: bool,
+  // 値とlong/doubleかどうか
+  stack: Vec<(Expr, bool)>,
+  stmts: Vec<Stmt>,
+  // ブロックの中だけで使う一時的な$k
+  next_temporary: usize,
+  next_object: usize,
+}
+
+impl Simulator<'_, '_> {
+  fn push(&mut self, value: Expr, wide: bool) {
+    self.stack.push((value, wide));
+  }
+
+  fn pop_entry(&mut self) -> Result<(Expr, bool), String> {
+    self.stack.pop().ok_or_else(|| "operand stack underflow".to_string())
+  }
+
+  fn pop(&mut self) -> Result<Expr, String> {
+    Ok(self.pop_entry()?.0)
+  }
+
+  fn pop_n(&mut self, count: usize) -> Result<Vec<Expr>, String> {
+    if self.stack.len() < count {
+      return Err("operand stack underflow".to_string());
+    }
+    Ok(self.stack.split_off(self.stack.len() - count).into_iter().map(|(value, _)| value).collect())
+  }
+
+  fn block(&self, pc: usize) -> Result<usize, String> {
+    self.graph.block_at(pc).ok_or_else(|| format!("branch target {} is not a block", pc))
+  }
+
+  // スタックのindexの値を$kに代入して置き換える
+  fn spill_at(&mut self, index: usize) {
+    if self.stack[index].0 == Expr::Stack(index) {
+      return;
+    }
+    let own = Expr::Stack(index);
+    let shared = self.stack.iter().enumerate().any(|(i, (value, _))| i != index && value.count(&own) > 0);
+    let variable = if shared {
+      self.next_temporary += 1;
+      Expr::Stack(self.next_temporary - 1)
+    } else {
+      own
+    };
+    let value = mem::replace(&mut self.stack[index].0, variable.clone());
+    self.stmts.push(Stmt::Assign(variable, value));
+  }
+
+  // 文を追加する前に、先に評価されるべきスタックの値を代入しておく
+  fn emit(&mut self, stmt: Stmt, written: Option<&Expr>) {
+    for index in 0..self.stack.len() {
+      let value = &self.stack[index].0;
+      if !value.is_stable() || written.is_some_and(|written| value.count(written) > 0) {
+        self.spill_at(index);
+      }
+    }
+    self.stmts.push(stmt);
+  }
+
+  fn load(&mut self, slot: usize, pc: usize, kind: u8) {
+    let value = if kind == b'A' && slot == 0 && !self.is_static {
+      Expr::This
+    } else {
+      Expr::Local(self.locals.find(slot, &[pc], kind))
+    };
+    self.push(value, kind == b'J' || kind == b'D');
+  }
+
+  fn store(&mut self, slot: usize, pc: usize, next_pc: usize, kind: u8) -> Result<(), String> {
+    let value = self.pop()?;
+    // 変数の有効範囲は代入した次の命令から始まる
+    let local = Expr::Local(self.locals.find(slot, &[next_pc, pc], kind));
+    if !self.post_increment(&local, &value) {
+      self.emit(Stmt::Assign(local.clone(), value), Some(&local));
+    }
+    Ok(())
+  }
+
+  // 増減する前に積んだ値が残っていれば、それを x++ や x-- にする
+  fn post_increment(&mut self, target: &Expr, value: &Expr) -> bool {
+    let (op, read) = match value {
+      Expr::Binary(op @ ("+" | "-"), left, right) if matches!(**right, Expr::Literal(Literal::Int(1) | Literal::Long(1))) => {
+        (if *op == "+" { "++" } else { "--" }, &**left)
+      },
+      _ => return false,
+    };
+    // 変数ならその変数、フィールドや配列の要素なら直前に読んで$kに入れた値が積まれている
+    let spilled = self.stmts.last() == Some(&Stmt::Assign(read.clone(), target.clone()));
+    if !(matches!(target, Expr::Local(_)) && read == target || matches!(read, Expr::Stack(_)) && spilled) {
+      return false;
+    }
+    let readers: Vec<usize> = (0..self.stack.len()).filter(|&index| self.stack[index].0.count(read) > 0).collect();
+    // 後から積んだ値は増減した後を読むので、一番上で読む値でなければならない
+    match readers.last() {
+      Some(&index) if self.stack[index].0 == *read && (!spilled || readers.len() == 1) && self.stack[index + 1..].iter().all(|(value, _)| value.is_stable()) => {
+        if spilled {
+          self.stmts.pop();
+        }
+        self.stack[index].0 = Expr::PostIncrement(op, Box::new(target.clone()));
+        true
+      },
+      _ => false,
+    }
+  }
+
+  // フィールドや配列の要素への代入
+  fn assign(&mut self, target: Expr, value: Expr) {
+    if !self.post_increment(&target, &value) {
+      self.emit(Stmt::Assign(target, value), None);
+    }
+  }
+
+  fn increment(&mut self, slot: usize, pc: usize, delta: i32) {
+    let local = Expr::Local(self.locals.find(slot, &[pc], b'I'));
+    let value = if delta < 0 {
+      Expr::binary("-", local.clone(), Expr::int(delta.wrapping_neg()))
+    } else {
+      Expr::binary("+", local.clone(), Expr::int(delta))
+    };
+    if !self.post_increment(&local, &value) {
+      self.emit(Stmt::Assign(local.clone(), value), Some(&local));
+    }
+  }
+
+  // pop, dupなどのスタック操作
+  fn shuffle(&mut self, opcode: u8) -> Result<(), String> {
+    let (n, below) = match StackShuffle::parse(opcode, |depth| self.stack.iter().rev().nth(depth).map(|(_, wide)| *wide))? {
+      StackShuffle::Pop(n) => {
+        for value in self.pop_n(n)? {
+          // javacがメソッド参照のレシーバに入れるnullチェックは省く
+          if let Expr::Invoke { class, name, args, .. } = &value
+            && class == "java/util/Objects"
+            && name == "requireNonNull"
+            && args.len() == 1
+            && args[0].is_stable()
+          {
+            continue;
+          }
+          // 捨てられる値でも呼び出しは文として残す
+          if matches!(value, Expr::Invoke { .. } | Expr::New { .. } | Expr::Dynamic { .. }) {
+            self.emit(Stmt::Expr(value), None);
+          }
+        }
+        return Ok(());
+      },
+      StackShuffle::Swap => {
+        let n = self.stack.len();
+        self.stack.swap(n - 1, n - 2);
+        return Ok(());
+      },
+      StackShuffle::Dup { top, below } => (top, below),
+    };
+    let start = self.stack.len() - n;
+    for index in start..self.stack.len() {
+      // 長さが定数の配列を複製するのは初期化子の始まり
+      if let Expr::NewArray { array_type, lengths } = &self.stack[index].0
+        && let [Expr::Literal(Literal::Int(length))] = lengths.as_slice()
+        && (1..=1024).contains(length)
+      {
+        let id = self.next_object;
+        self.next_object += 1;
+        self.stack[index].0 = Expr::ArrayInit { id, array_type: array_type.clone(), values: vec![None; *length as usize] };
+      }
+      // 副作用のある式は2回評価しないように変数に入れる
+      if !self.stack[index].0.is_stable() {
+        for before in 0..index {
+          if !self.stack[before].0.is_stable() {
+            self.spill_at(before);
+          }
+        }
+        self.spill_at(index);
+      }
+    }
+    let values: Vec<(Expr, bool)> = self.stack[start..].to_vec();
+    let at = start - below;
+    for (i, value) in values.into_iter().enumerate() {
+      self.stack.insert(at + i, value);
+    }
+    Ok(())
+  }
+
+  fn invoke_args(&mut self, descriptor: &MethodDescriptor, receiver: bool) -> Result<(Option<Expr>, Vec<Expr>), String> {
+    let mut args = self.pop_n(descriptor.parameters.len() + usize::from(receiver))?;
+    let object = if receiver { Some(args.remove(0)) } else { None };
+    Ok((object, args))
+  }
+
+  // 結果があればスタックに積み、なければ文にする
+  fn result(&mut self, value: Expr, return_type: &Option<FieldType>) {
+    match return_type {
+      Some(return_type) => self.push(value, return_type.is_wide()),
+      None => self.emit(Stmt::Expr(value), None),
+    }
+  }
+
+  fn invoke(&mut self, opcode: u8, index: u16) -> Result<(), String> {
+    let (class, name, descriptor) = self.context.pool.get_member_ref(index)?;
+    let parsed = MethodDescriptor::parse(&descriptor)?;
+    let (object, args) = self.invoke_args(&parsed, opcode != 0xb8)?;
+    if name == "<init>"
+      && let Some(Expr::Uninit(id, uninit_class)) = &object
+    {
+      let created = Expr::New { class: uninit_class.clone(), descriptor, args };
+      let uninit = Expr::Uninit(*id, uninit_class.clone());
+      let mut replaced = 0;
+      for (value, _) in &mut self.stack {
+        replaced += value.replace(&uninit, &created);
+      }
+      if replaced == 0 {
+        self.emit(Stmt::Expr(created), None);
+      }
+      return Ok(());
+    }
+    let value = Expr::Invoke { object: object.map(Box::new), class, name, descriptor, args, special: opcode == 0xb7 };
+    self.result(value, &parsed.return_type);
+    Ok(())
+  }
+
+  fn invoke_dynamic(&mut self, index: u16) -> Result<(), String> {
+    let pool = self.context.pool;
+    let PoolConstant::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index } = pool.get_class(index)? else {
+      return Err("invokedynamic does not refer to an InvokeDynamic constant".to_string());
+    };
+    let bootstrap = *bootstrap_method_attr_index as usize;
+    let (name, descriptor) = pool.get_name_and_type(*name_and_type_index)?;
+    let parsed = MethodDescriptor::parse(&descriptor)?;
+    let (_, args) = self.invoke_args(&parsed, false)?;
+    let value = self.context.dynamic(bootstrap, &name, &parsed, args);
+    self.result(value, &parsed.return_type);
+    Ok(())
+  }
+
+  fn instruction(&mut self, pc: usize, code_byte: &CodeByte, next_pc: usize) -> Result<Option<Exit>, String> {
+    let data = &code_byte.data;
+    let u16_at = |at: usize| -> Result<u16, String> {
+      Ok(u16::from_be_bytes([*data.get(at).ok_or("truncated operand")?, *data.get(at + 1).ok_or("truncated operand")?]))
+    };
+    let i32_at = |at: usize| -> Result<i32, String> {
+      let bytes = data.get(at..at + 4).ok_or("truncated operand")?;
+      Ok(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    let target = |offset: i32| (pc as i64 + offset as i64) as usize;
+    let opcode = code_byte.opcode;
+    match opcode {
+      0x00 => {},
+      0x01 => self.push(Expr::Literal(Literal::Null), false),
+      0x02..=0x08 => self.push(Expr::int(opcode as i32 - 3), false),
+      0x09 | 0x0a => self.push(Expr::Literal(Literal::Long(opcode as i64 - 9)), true),
+      0x0b..=0x0d => self.push(Expr::Literal(Literal::Float((opcode - 0x0b) as f32)), false),
+      0x0e | 0x0f => self.push(Expr::Literal(Literal::Double((opcode - 0x0e) as f64)), true),
+      0x10 => self.push(Expr::int(data[0] as i8 as i32), false),
+      0x11 => self.push(Expr::int(u16_at(0)? as i16 as i32), false),
+      0x12..=0x14 => {
+        let index = if opcode == 0x12 { data[0] as u16 } else { u16_at(0)? };
+        let (value, wide) = constant(self.context.pool, index)?;
+        self.push(value, wide);
+      },
+      0x15..=0x19 => self.load(data[0] as usize, pc, KINDS[(opcode - 0x15) as usize]),
+      0x1a..=0x2d => self.load(((opcode - 0x1a) % 4) as usize, pc, KINDS[((opcode - 0x1a) / 4) as usize]),
+      0x2e..=0x35 => {
+        let index = self.pop()?;
+        let array = self.pop()?;
+        self.push(Expr::ArrayElement(Box::new(array), Box::new(index)), matches!(opcode, 0x2f | 0x31));
+      },
+      0x36..=0x3a => self.store(data[0] as usize, pc, next_pc, KINDS[(opcode - 0x36) as usize])?,
+      0x3b..=0x4e => self.store(((opcode - 0x3b) % 4) as usize, pc, next_pc, KINDS[((opcode - 0x3b) / 4) as usize])?,
+      0x4f..=0x56 => {
+        let value = self.pop()?;
+        let index = self.pop()?;
+        let array = self.pop()?;
+        // 配列の初期化子の要素
+        if let (Expr::ArrayInit { id, .. }, Expr::Literal(Literal::Int(position))) = (&array, &index) {
+          let mut stored = false;
+          for (entry, _) in &mut self.stack {
+            if let Expr::ArrayInit { id: entry_id, values, .. } = entry
+              && entry_id == id
+              && let Some(slot) = values.get_mut(*position as usize)
+            {
+              *slot = Some(value.clone());
+              stored = true;
+            }
+          }
+          if stored {
+            return Ok(None);
+          }
+        }
+        self.assign(Expr::ArrayElement(Box::new(array), Box::new(index)), value);
+      },
+      0x57..=0x5f => self.shuffle(opcode)?,
+      0x60..=0x83 => {
+        let (op, wide) = match opcode {
+          0x60..=0x73 => (["+", "-", "*", "/", "%"][(opcode - 0x60) as usize / 4], (opcode - 0x60) % 2 == 1),
+          0x74..=0x77 => ("neg", (opcode - 0x74) % 2 == 1),
+          0x78..=0x7d => (["<<", ">>", ">>>"][(opcode - 0x78) as usize / 2], (opcode - 0x78) % 2 == 1),
+          _ => (["&", "|", "^"][(opcode - 0x7e) as usize / 2], (opcode - 0x7e) % 2 == 1),
+        };
+        let right = self.pop()?;
+        if op == "neg" {
+          self.push(Expr::unary("-", right), wide);
+        } else {
+          let left = self.pop()?;
+          self.push(Expr::binary(op, left, right), wide);
+        }
+      },
+      0x84 => self.increment(data[0] as usize, pc, data[1] as i8 as i32),
+      0x85..=0x93 => {
+        const TARGETS: [FieldType; 15] = [
+          FieldType::Long,
+          FieldType::Float,
+          FieldType::Double,
+          FieldType::Int,
+          FieldType::Float,
+          FieldType::Double,
+          FieldType::Int,
+          FieldType::Long,
+          FieldType::Double,
+          FieldType::Int,
+          FieldType::Long,
+          FieldType::Float,
+          FieldType::Byte,
+          FieldType::Char,
+          FieldType::Short,
+        ];
+        let target = TARGETS[(opcode - 0x85) as usize].clone();
+        let value = self.pop()?;
+        let wide = target.is_wide();
+        self.push(Expr::Cast(target, Box::new(value)), wide);
+      },
+      0x94..=0x98 => {
+        let right = self.pop()?;
+        let left = self.pop()?;
+        self.push(Expr::Compare(Box::new(left), Box::new(right)), false);
+      },
+      0x99..=0xa6 | 0xc6 | 0xc7 => {
+        const OPS: [&str; 6] = ["==", "!=", "<", ">=", ">", "<="];
+        let condition = match opcode {
+          0x99..=0x9e => match self.pop()? {
+            Expr::Compare(left, right) => Expr::Binary(OPS[(opcode - 0x99) as usize], left, right),
+            value => Expr::binary(OPS[(opcode - 0x99) as usize], value, Expr::int(0)),
+          },
+          0x9f..=0xa6 => {
+            let right = self.pop()?;
+            let left = self.pop()?;
+            Expr::binary(OPS[((opcode - 0x9f) % 6) as usize], left, right)
+          },
+          _ => Expr::binary(if opcode == 0xc6 { "==" } else { "!=" }, self.pop()?, Expr::Literal(Literal::Null)),
+        };
+        let then_block = self.block(target(u16_at(0)? as i16 as i32))?;
+        return Ok(Some(Exit::If(condition, then_block, self.block(next_pc)?)));
+      },
+      0xa7 => return Ok(Some(Exit::Goto(self.block(target(u16_at(0)? as i16 as i32))?))),
+      0xc8 => return Ok(Some(Exit::Goto(self.block(target(i32_at(0)?))?))),
+      0xa8 | 0xa9 | 0xc9 => return Err("jsr/ret subroutines are not supported".to_string()),
+      0xaa | 0xab => {
+        let value = self.pop()?;
+        let padding = (4 - (pc + 1) % 4) % 4;
+        let mut cases = Vec::new();
+        if opcode == 0xaa {
+          let (low, high) = (i32_at(padding + 4)?, i32_at(padding + 8)?);
+          for (i, key) in (low..=high).enumerate() {
+            cases.push((Some(key), self.block(target(i32_at(padding + 12 + i * 4)?))?));
+          }
+        } else {
+          for i in 0..i32_at(padding + 4)?.max(0) as usize {
+            cases.push((Some(i32_at(padding + 8 + i * 8)?), self.block(target(i32_at(padding + 12 + i * 8)?))?));
+          }
+        }
+        cases.push((None, self.block(target(i32_at(padding)?))?));
+        return Ok(Some(Exit::Switch(value, cases)));
+      },
+      0xac..=0xb0 => return Ok(Some(Exit::Return(Some(self.pop()?)))),
+      0xb1 => return Ok(Some(Exit::Return(None))),
+      0xb2..=0xb5 => {
+        let (class, name, descriptor) = self.context.pool.get_member_ref(u16_at(0)?)?;
+        let wide = FieldType::parse(&descriptor)?.is_wide();
+        match opcode {
+          0xb2 => self.push(Expr::Field { object: None, class, name, descriptor }, wide),
+          0xb3 => {
+            let value = self.pop()?;
+            self.assign(Expr::Field { object: None, class, name, descriptor }, value);
+          },
+          0xb4 => {
+            let object = self.pop()?;
+            self.push(Expr::Field { object: Some(Box::new(object)), class, name, descriptor }, wide);
+          },
+          _ => {
+            let value = self.pop()?;
+            let object = self.pop()?;
+            self.assign(Expr::Field { object: Some(Box::new(object)), class, name, descriptor }, value);
+          },
+        }
+      },
+      0xb6..=0xb9 => self.invoke(opcode, u16_at(0)?)?,
+      0xba => self.invoke_dynamic(u16_at(0)?)?,
+      0xbb => {
+        let class = self.context.pool.get_class_name(u16_at(0)?)?;
+        self.next_object += 1;
+        self.push(Expr::Uninit(self.next_object - 1, class), false);
+      },
+      0xbc | 0xbd => {
+        let element = if opcode == 0xbc {
+          const ELEMENTS: [FieldType; 8] =
+            [FieldType::Boolean, FieldType::Char, FieldType::Float, FieldType::Double, FieldType::Byte, FieldType::Short, FieldType::Int, FieldType::Long];
+          ELEMENTS.get((data[0] as usize).wrapping_sub(4)).cloned().ok_or_else(|| format!("invalid array type {}", data[0]))?
+        } else {
+          let class = self.context.pool.get_class_name(u16_at(0)?)?;
+          if class.starts_with('[') { FieldType::parse(&class)? } else { FieldType::Object(class) }
+        };
+        let length = self.pop()?;
+        self.push(Expr::NewArray { array_type: FieldType::Array(Box::new(element)), lengths: vec![length] }, false);
+      },
+      0xbe => {
+        let array = self.pop()?;
+        self.push(Expr::ArrayLength(Box::new(array)), false);
+      },
+      0xbf => return Ok(Some(Exit::Throw(self.pop()?))),
+      0xc0 | 0xc1 => {
+        let class = self.context.pool.get_class_name(u16_at(0)?)?;
+        let field_type = if class.starts_with('[') { FieldType::parse(&class)? } else { FieldType::Object(class) };
+        let value = self.pop()?;
+        if opcode == 0xc0 {
+          self.push(Expr::Cast(field_type, Box::new(value)), false);
+        } else {
+          self.push(Expr::InstanceOf(Box::new(value), field_type), false);
+        }
+      },
+      0xc2 | 0xc3 => {
+        let object = self.pop()?;
+        self.emit(Stmt::Monitor(opcode == 0xc2, object), None);
+      },
+      0xc4 => {
+        let slot = u16_at(1)? as usize;
+        match data[0] {
+          0x15..=0x19 => self.load(slot, pc, KINDS[(data[0] - 0x15) as usize]),
+          0x36..=0x3a => self.store(slot, pc, next_pc, KINDS[(data[0] - 0x36) as usize])?,
+          0x84 => self.increment(slot, pc, u16_at(3)? as i16 as i32),
+          _ => return Err(format!("unsupported wide instruction {:#x}", data[0])),
+        }
+      },
+      0xc5 => {
+        let class = self.context.pool.get_class_name(u16_at(0)?)?;
+        let lengths = self.pop_n(data[2] as usize)?;
+        self.push(Expr::NewArray { array_type: FieldType::parse(&class)?, lengths }, false);
+      },
+      _ => return Err(format!("unsupported instruction {}", code_byte.name)),
+    }
+    Ok(None)
+  }
+
+  // 後続に渡るスタックの値を$0, $1, ...に代入する
+  fn spill_exit(&mut self) {
+    for index in 0..self.stack.len() {
+      let own = Expr::Stack(index);
+      if self.stack[index].0 == own {
+        continue;
+      }
+      for later in index + 1..self.stack.len() {
+        if self.stack[later].0.count(&own) > 0 {
+          self.next_temporary += 1;
+          let variable = Expr::Stack(self.next_temporary - 1);
+          let value = mem::replace(&mut self.stack[later].0, variable.clone());
+          self.stmts.push(Stmt::Assign(variable, value));
+        }
+      }
+      let value = mem::replace(&mut self.stack[index].0, own.clone());
+      self.stmts.push(Stmt::Assign(own, value));
+    }
+  }
+}
+
+// ldcやConstantValueの定数 (longとdoubleなら2ワード)
+pub fn constant(pool: &ConstantPool, index: u16) -> Result<(Expr, bool), String> {
+  let literal = match pool.get_class(index)? {
+    PoolConstant::Integer { bytes } => Literal::Int(*bytes as i32),
+    PoolConstant::Float { bytes } => Literal::Float(f32::from_bits(*bytes)),
+    PoolConstant::Long { high_bytes, low_bytes } => Literal::Long(((*high_bytes as u64) << 32 | *low_bytes as u64) as i64),
+    PoolConstant::Double { high_bytes, low_bytes } => Literal::Double(f64::from_bits((*high_bytes as u64) << 32 | *low_bytes as u64)),
+    PoolConstant::String { string_index } => Literal::String(pool.get_utf8(*string_index)?),
+    PoolConstant::Class { name_index } => {
+      let name = pool.get_utf8(*name_index)?;
+      Literal::Class(if name.starts_with('[') { FieldType::parse(&name)? } else { FieldType::Object(name) })
+    },
+    PoolConstant::MethodType { descriptor_index } => Literal::Other(format!("MethodType {}", pool.get_utf8(*descriptor_index)?)),
+    PoolConstant::MethodHandle { reference_kind, reference_index } => {
+      let (class, name, descriptor) = pool.get_member_ref(*reference_index)?;
+      Literal::Other(format!("MethodHandle {} {}.{}:{}", reference_kind, class, name, descriptor))
+    },
+    PoolConstant::Dynamic { name_and_type_index, .. } => {
+      let (name, descriptor) = pool.get_name_and_type(*name_and_type_index)?;
+      let wide = FieldType::parse(&descriptor)?.is_wide();
+      return Ok((Expr::Literal(Literal::Other(format!("Dynamic {}:{}", name, descriptor))), wide));
+    },
+    c => return Err(format!("Unsupported ldc constant: {:?}", c)),
+  };
+  let wide = matches!(literal, Literal::Long(_) | Literal::Double(_));
+  Ok((Expr::Literal(literal), wide))
+}
+
+pub fn build(context: &ClassContext, method: &Method, code: &CodeAttribute) -> Result<MethodBody, String> {
+  let pool = context.pool;
+  let descriptor = MethodDescriptor::parse(&pool.get_utf8(method.descriptor_index)?)?;
+  let is_static = method.access_flags & 0x0008 != 0;
+  let graph = ControlFlowGraph::build(code, pool)?;
+  let mut instructions = HashMap::new();
+  let mut pc = 0;
+  for code_byte in &code.code {
+    instructions.insert(pc, code_byte);
+    pc += 1 + code_byte.data.len();
+  }
+
+  let mut regions: Vec<Region> = Vec::new();
+  let mut handlers = Vec::new();
+  for entry in &code.exception_table {
+    let handler = graph.block_at(entry.handler_pc as usize).ok_or("exception handler is not at a block")?;
+    let catch_type = if entry.catch_type == 0 { None } else { Some(pool.get_class_name(entry.catch_type)?) };
+    let (start_pc, end_pc) = (entry.start_pc as usize, entry.end_pc as usize);
+    if !handlers.contains(&handler) {
+      handlers.push(handler);
+    }
+    // synchronizedのハンドラが自身を覆う範囲はソースのtryにならない
+    if (start_pc..end_pc).contains(&(entry.handler_pc as usize)) {
+      continue;
+    }
+    match regions.iter_mut().find(|region| region.start_pc == start_pc && region.end_pc == end_pc) {
+      Some(region) => region.handlers.push((catch_type, handler)),
+      None => regions.push(Region { start_pc, end_pc, handlers: vec![(catch_type, handler)] }),
+    }
+  }
+
+  // synchronizedやfinallyの本体から、抜ける経路 (monitorexitやfinallyの複製の後のgotoかreturn) だけを除いた範囲は1つのtryにする
+  let after_goto = |mut pc: usize| loop {
+    let code_byte = instructions.get(&pc)?;
+    let next_pc = pc + 1 + code_byte.data.len();
+    match code_byte.opcode {
+      0xa7 | 0xc8 | 0xac..=0xb1 => return Some(next_pc),
+      // 途中で分岐する命令があれば抜ける経路ではない
+      0x99..=0xa6 | 0xa8 | 0xa9 | 0xaa | 0xab | 0xbf | 0xc6 | 0xc7 | 0xc9 => return None,
+      _ => pc = next_pc,
+    }
+  };
+  while let Some((i, j)) = (0..regions.len())
+    .flat_map(|i| (0..regions.len()).map(move |j| (i, j)))
+    .find(|&(i, j)| regions[i].handlers == regions[j].handlers && after_goto(regions[i].end_pc) == Some(regions[j].start_pc))
+  {
+    regions[i].end_pc = regions[j].end_pc;
+    regions.remove(j);
+  }
+
+  let locals = Locals::new(context, method, code, &descriptor, is_static, &graph, &instructions)?;
+  let mut simulator = Simulator {
+    context,
+    graph: &graph,
+    locals,
+    is_static,
+    stack: Vec::new(),
+    stmts: Vec::new(),
+    next_temporary: code.max_stack as usize,
+    next_object: 0,
+  };
+  let count = graph.blocks.len();
+  let mut entry_widths: Vec<Option<Vec<bool>>> = vec![None; count];
+  entry_widths[0] = Some(Vec::new());
+  let mut blocks: Vec<Option<Block>> = vec![None; count];
+  for index in reverse_postorder(&graph.successor_lists(), 0) {
+    let code_block = &graph.blocks[index];
+    simulator.stack = if handlers.contains(&index) {
+      vec![(Expr::Caught, false)]
+    } else {
+      entry_widths[index].clone().unwrap_or_default().into_iter().enumerate().map(|(k, wide)| (Expr::Stack(k), wide)).collect()
+    };
+    simulator.stmts = Vec::new();
+    let mut exit = None;
+    for &pc in &code_block.instructions {
+      let code_byte = instructions[&pc];
+      let next_pc = pc + 1 + code_byte.data.len();
+      exit = simulator.instruction(pc, code_byte, next_pc)?;
+    }
+    let exit = match exit {
+      Some(exit) => exit,
+      None => Exit::Goto(simulator.block(code_block.end_pc)?),
+    };
+    simulator.spill_exit();
+    let widths: Vec<bool> = simulator.stack.iter().map(|(_, wide)| *wide).collect();
+    for successor in exit.successors() {
+      entry_widths[successor].get_or_insert_with(|| widths.clone());
+    }
+    blocks[index] = Some(Block { pc: code_block.start_pc, stmts: mem::take(&mut simulator.stmts), exit, exit_depth: widths.len() });
+  }
+
+  let scope = Scope {
+    class_name: context.class_name.clone(),
+    locals: simulator.locals.locals,
+    return_type: descriptor.return_type.clone(),
+  };
+  let mut body = MethodBody { scope, blocks, regions, handlers };
+  body.simplify();
+  Ok(body)
+}
+
+impl MethodBody {
+  pub fn block(&self, index: usize) -> &Block {
+    self.blocks[index].as_ref().expect("block exists")
+  }
+
+  pub fn successor_lists(&self) -> Vec<Vec<usize>> {
+    self.blocks.iter().map(|block| block.as_ref().map(|block| block.exit.successors()).unwrap_or_default()).collect()
+  }
+
+  // pcを範囲に含む例外テーブルのまとまり
+  pub fn covering(&self, pc: usize) -> Vec<usize> {
+    (0..self.regions.len()).filter(|&i| (self.regions[i].start_pc..self.regions[i].end_pc).contains(&pc)).collect()
+  }
+
+  // 併合してよい後続 (他から入ってこない、例外の範囲が同じか後続が例外を投げない)
+  fn mergeable(&self, predecessors: &[Vec<usize>], from: usize, to: usize) -> bool {
+    let next = self.block(to);
+    let returns = next.stmts.is_empty() && matches!(&next.exit, Exit::Return(None | Some(Expr::Stack(_) | Expr::Literal(_))));
+    to != from
+      && to != 0
+      && predecessors[to] == [from]
+      && !self.handlers.contains(&to)
+      && (returns || self.covering(self.block(from).pc) == self.covering(next.pc))
+  }
+
+  // ブロックの併合、三項演算子と&&/||の復元を変化がなくなるまで繰り返す
+  fn simplify(&mut self) {
+    loop {
+      let mut changed = false;
+      for index in 0..self.blocks.len() {
+        if self.blocks[index].is_some() {
+          changed |= self.skip_empty(index) || self.merge_chain(index) || self.merge_ternary(index) || self.merge_condition(index);
+          changed |= self.inline_stack_values(index);
+          changed |= self.merge_constructor(index);
+        }
+      }
+      if !changed {
+        break;
+      }
+    }
+  }
+
+  // 引数の評価に分岐があってnewとコンストラクタの呼び出しが離れたものを一つの式にする
+  fn merge_constructor(&mut self, index: usize) -> bool {
+    let block = self.blocks[index].as_mut().expect("block exists");
+    for i in 0..block.stmts.len() {
+      let Stmt::Expr(Expr::Invoke { object: Some(object), name, descriptor, args, .. }) = &block.stmts[i] else {
+        continue;
+      };
+      let Expr::Uninit(_, class) = &**object else {
+        continue;
+      };
+      if name != "<init>" {
+        continue;
+      }
+      let uninit = (**object).clone();
+      let created = Expr::New { class: class.clone(), descriptor: descriptor.clone(), args: args.clone() };
+      // newの値を受け取った$kは、コンストラクタを呼んだ後で作られたオブジェクトを受け取る
+      let mut holders = Vec::new();
+      let mut position = 0;
+      block.stmts.retain(|stmt| {
+        position += 1;
+        match stmt {
+          Stmt::Assign(variable @ Expr::Stack(_), value) if position <= i && *value == uninit => {
+            holders.push(variable.clone());
+            false
+          },
+          _ => true,
+        }
+      });
+      let at = i - holders.len();
+      match holders.split_first() {
+        Some((first, rest)) => {
+          block.stmts[at] = Stmt::Assign(first.clone(), created);
+          for (k, holder) in rest.iter().enumerate() {
+            block.stmts.insert(at + 1 + k, Stmt::Assign(holder.clone(), first.clone()));
+          }
+        },
+        None => block.stmts[at] = Stmt::Expr(created),
+      }
+      return true;
+    }
+    false
+  }
+
+  // 文のないgotoだけのブロックを飛ばして、その先へ直接分岐する
+  fn skip_empty(&mut self, index: usize) -> bool {
+    let forward = |target: usize| match &self.blocks[target] {
+      Some(Block { stmts, exit: Exit::Goto(next), .. }) if stmts.is_empty() && target != 0 && *next != target && !self.handlers.contains(&target) => Some(*next),
+      _ => None,
+    };
+    let mut redirects = Vec::new();
+    for target in self.block(index).exit.successors() {
+      if target != index
+        && let Some(next) = forward(target)
+      {
+        redirects.push((target, next));
+      }
+    }
+    if redirects.is_empty() {
+      return false;
+    }
+    let block = self.blocks[index].as_mut().expect("block exists");
+    for target in block.exit.targets_mut() {
+      if let Some((_, next)) = redirects.iter().find(|(from, _)| *from == *target) {
+        *target = *next;
+      }
+    }
+    // どこからも分岐しなくなったブロックは消す
+    let successors = self.successor_lists();
+    for (from, _) in redirects {
+      if !successors.iter().any(|targets| targets.contains(&from)) {
+        self.blocks[from] = None;
+      }
+    }
+    true
+  }
+
+  fn merge_chain(&mut self, index: usize) -> bool {
+    let predecessors = predecessors(&self.successor_lists());
+    let Exit::Goto(next) = self.block(index).exit else {
+      return false;
+    };
+    if !self.mergeable(&predecessors, index, next) {
+      return false;
+    }
+    let next_block = self.blocks[next].take().expect("block exists");
+    let block = self.blocks[index].as_mut().expect("block exists");
+    block.stmts.extend(next_block.stmts);
+    block.exit = next_block.exit;
+    block.exit_depth = next_block.exit_depth;
+    true
+  }
+
+  // 分岐の両側で同じ$kに値を入れて合流するなら c ? a : b にする
+  fn merge_ternary(&mut self, index: usize) -> bool {
+    let predecessors = predecessors(&self.successor_lists());
+    let Exit::If(condition, then_block, else_block) = &self.block(index).exit else {
+      return false;
+    };
+    let (condition, then_block, else_block) = (condition.clone(), *then_block, *else_block);
+    if then_block == else_block || !self.mergeable(&predecessors, index, then_block) || !self.mergeable(&predecessors, index, else_block) {
+      return false;
+    }
+    let (then_side, else_side) = (self.block(then_block), self.block(else_block));
+    let exit = match (then_side.stmts.as_slice(), &then_side.exit, else_side.stmts.as_slice(), &else_side.exit) {
+      ([Stmt::Assign(Expr::Stack(a), then_value)], Exit::Goto(a_next), [Stmt::Assign(Expr::Stack(b), else_value)], Exit::Goto(b_next))
+        if a == b && a_next == b_next && then_side.exit_depth == else_side.exit_depth =>
+      {
+        let value = Expr::Ternary(Box::new(negate(condition)), Box::new(else_value.clone()), Box::new(then_value.clone()));
+        let (variable, next, depth) = (*a, *a_next, then_side.exit_depth);
+        let block = self.blocks[index].as_mut().expect("block exists");
+        block.stmts.push(Stmt::Assign(Expr::Stack(variable), value));
+        block.exit_depth = depth;
+        Exit::Goto(next)
+      },
+      // return a > b; のようなbooleanの値を返す分岐
+      ([], Exit::Return(Some(then_value @ Expr::Literal(Literal::Int(0 | 1)))), [], Exit::Return(Some(else_value @ Expr::Literal(Literal::Int(0 | 1)))))
+        if then_value != else_value =>
+      {
+        Exit::Return(Some(Expr::Ternary(Box::new(negate(condition)), Box::new(else_value.clone()), Box::new(then_value.clone()))))
+      },
+      _ => return false,
+    };
+    self.blocks[then_block] = None;
+    self.blocks[else_block] = None;
+    self.blocks[index].as_mut().expect("block exists").exit = exit;
+    true
+  }
+
+  // 文のない条件分岐のブロックに続く条件分岐を && と || でまとめる
+  fn merge_condition(&mut self, index: usize) -> bool {
+    let predecessors = predecessors(&self.successor_lists());
+    let Exit::If(first, then_block, else_block) = &self.block(index).exit else {
+      return false;
+    };
+    let (first, then_block, else_block) = (first.clone(), *then_block, *else_block);
+    for (inner, other, inner_is_then) in [(else_block, then_block, false), (then_block, else_block, true)] {
+      if inner == other || !self.mergeable(&predecessors, index, inner) {
+        continue;
+      }
+      let inner_block = self.block(inner);
+      let Exit::If(second, second_then, second_else) = &inner_block.exit else {
+        continue;
+      };
+      if !inner_block.stmts.is_empty() || inner_block.exit_depth != self.block(index).exit_depth || *second_then == inner || *second_else == inner {
+        continue;
+      }
+      let (second, second_then, second_else) = (second.clone(), *second_then, *second_else);
+      let exit = match (inner_is_then, other == second_then, other == second_else) {
+        // firstが偽ならsecondを調べる
+        (false, true, _) => Exit::If(Expr::binary("||", first.clone(), second), other, second_else),
+        (false, _, true) => Exit::If(Expr::binary("&&", negate(first.clone()), second), second_then, other),
+        // firstが真ならsecondを調べる
+        (true, _, true) => Exit::If(Expr::binary("&&", first.clone(), second), second_then, other),
+        (true, true, _) => Exit::If(Expr::binary("&&", first.clone(), negate(second)), second_else, other),
+        _ => continue,
+      };
+      self.blocks[inner] = None;
+      self.blocks[index].as_mut().expect("block exists").exit = exit;
+      return true;
+    }
+    false
+  }
+
+  // 一度だけ使われる$kへの代入を、すぐ後の文や出口に埋め込む
+  fn inline_stack_values(&mut self, index: usize) -> bool {
+    let block = self.blocks[index].as_mut().expect("block exists");
+    let mut changed = false;
+    let mut i = block.stmts.len();
+    while i > 0 {
+      i -= 1;
+      let Stmt::Assign(variable @ Expr::Stack(k), value) = &block.stmts[i] else {
+        continue;
+      };
+      let (variable, value, k) = (variable.clone(), value.clone(), *k);
+      let next_uses = match block.stmts.get_mut(i + 1) {
+        Some(next) => uses(next, &variable),
+        None => block.exit.exprs_mut().into_iter().map(|expr| expr.count(&variable)).sum(),
+      };
+      if next_uses != 1 {
+        continue;
+      }
+      // その後で使われない (代入し直されるか、出口で後続に渡らない)
+      let assigns = |stmt: &Stmt| matches!(stmt, Stmt::Assign(target, _) if *target == variable);
+      let mut live = k < block.exit_depth;
+      if block.stmts.get(i + 1).is_some_and(assigns) {
+        live = false;
+      } else {
+        for stmt in block.stmts.iter_mut().skip(i + 2) {
+          if uses(stmt, &variable) > 0 {
+            live = true;
+            break;
+          }
+          if assigns(stmt) {
+            live = false;
+            break;
+          }
+        }
+        if !live && i + 1 < block.stmts.len() && block.exit.exprs_mut().into_iter().any(|expr| expr.count(&variable) > 0) {
+          live = !block.stmts[i + 2..].iter().any(assigns);
+        }
+      }
+      if live {
+        continue;
+      }
+      match block.stmts.get_mut(i + 1) {
+        Some(next) => substitute(next, &variable, &value),
+        None => {
+          for expr in block.exit.exprs_mut() {
+            expr.replace(&variable, &value);
+          }
+        },
+      }
+      block.stmts.remove(i);
+      changed = true;
+    }
+    changed
+  }
+}
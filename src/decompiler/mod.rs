@@ -0,0 +1,602 @@
+pub mod ast;
+pub mod body;
+pub mod names;
+pub mod structure;
+
+use std::fs;
+
+use crate::{
+  class_leader,
+  decompiler::{
+    ast::{coerce, Expr, Literal, Printer, Scope, Stmt},
+    body::MethodBody,
+    names::Names,
+  },
+  structure::class::{
+    BootstrapMethod, ClassFile, ClassFileAttribute, Constant as PoolConstant, ConstantPool, Field, FieldInfoAttribute, Method, MethodInfoAttribute,
+  },
+  util::{
+    descriptor::{FieldType, MethodDescriptor},
+    signature::{type_parameters_to_java, ClassSignature, MethodSignature, TypeSignature},
+  },
+};
+
+// クラスファイルからJavaのソースに近いものを復元する
+
+// 逆コンパイル中のクラスの情報
+pub struct ClassContext<'a> {
+  pub class_file: &'a ClassFile,
+  pub pool: &'a ConstantPool,
+  pub class_name: String,
+  pub names: Names,
+  bootstrap_methods: &'a [BootstrapMethod],
+}
+
+const PUBLIC: u16 = 0x0001;
+const PRIVATE: u16 = 0x0002;
+const PROTECTED: u16 = 0x0004;
+const STATIC: u16 = 0x0008;
+const FINAL: u16 = 0x0010;
+const SYNCHRONIZED: u16 = 0x0020;
+const VOLATILE: u16 = 0x0040;
+const BRIDGE: u16 = 0x0040;
+const TRANSIENT: u16 = 0x0080;
+const VARARGS: u16 = 0x0080;
+const NATIVE: u16 = 0x0100;
+const INTERFACE: u16 = 0x0200;
+const ABSTRACT: u16 = 0x0400;
+const SYNTHETIC: u16 = 0x1000;
+const ANNOTATION: u16 = 0x2000;
+const ENUM: u16 = 0x4000;
+
+// MethodHandleのreference_kind (JVMS 5.4.3.5)
+const REF_INVOKE_VIRTUAL: u8 = 5;
+const REF_INVOKE_SPECIAL: u8 = 7;
+const REF_NEW_INVOKE_SPECIAL: u8 = 8;
+const REF_INVOKE_INTERFACE: u8 = 9;
+
+fn modifiers(access_flags: u16, flags: &[(u16, &str)]) -> String {
+  flags.iter().filter(|(flag, _)| access_flags & flag != 0).map(|(_, keyword)| format!("{} ", keyword)).collect()
+}
+
+fn string_constant(pool: &ConstantPool, index: u16) -> Result<String, String> {
+  match pool.get_class(index)? {
+    PoolConstant::String { string_index } => pool.get_utf8(*string_index),
+    c => Err(format!("Not a String constant: {:?}", c)),
+  }
+}
+
+fn method_handle(pool: &ConstantPool, index: u16) -> Result<(u8, String, String, String), String> {
+  match pool.get_class(index)? {
+    PoolConstant::MethodHandle { reference_kind, reference_index } => {
+      let (class, name, descriptor) = pool.get_member_ref(*reference_index)?;
+      Ok((*reference_kind, class, name, descriptor))
+    },
+    c => Err(format!("Not a MethodHandle constant: {:?}", c)),
+  }
+}
+
+// super()の呼び出しで引数がないもの (コンストラクタの先頭なら省略できる)
+fn is_default_super(stmt: &Stmt, class_name: &str) -> bool {
+  matches!(stmt, Stmt::Expr(Expr::Invoke { object: Some(object), class, name, args, .. })
+    if **object == Expr::This && name == "<init>" && class != class_name && args.is_empty())
+}
+
+impl<'a> ClassContext<'a> {
+  pub fn new(class_file: &'a ClassFile) -> Result<ClassContext<'a>, String> {
+    let pool = &class_file.constant_pool;
+    let class_name = pool.get_class_name(class_file.this_class)?;
+    let bootstrap_methods = class_file
+      .attributes
+      .attributes
+      .iter()
+      .find_map(|attribute| match attribute {
+        ClassFileAttribute::BootstrapMethods(attribute) => Some(attribute.bootstrap_methods.as_slice()),
+        _ => None,
+      })
+      .unwrap_or_default();
+    Ok(ClassContext { class_file, pool, names: Names::new(&class_name), class_name, bootstrap_methods })
+  }
+
+  fn find_method(&self, name: &str, descriptor: &str) -> Option<&'a Method> {
+    self.class_file.methods.methods.iter().find(|method| {
+      self.pool.get_utf8(method.name_index).is_ok_and(|n| n == name) && self.pool.get_utf8(method.descriptor_index).is_ok_and(|d| d == descriptor)
+    })
+  }
+
+  // メソッドの本体を構造化した文の列
+  pub fn method_body(&self, method: &Method) -> Result<Option<(MethodBody, Vec<Stmt>)>, String> {
+    let code = method.attributes.attributes.iter().find_map(|attribute| match attribute {
+      MethodInfoAttribute::Code(code) => Some(code),
+      _ => None,
+    });
+    let Some(code) = code else {
+      return Ok(None);
+    };
+    let body = body::build(self, method, code)?;
+    let mut stmts = structure::structure(&body);
+    if self.pool.get_utf8(method.name_index)? == "<init>" && stmts.first().is_some_and(|stmt| is_default_super(stmt, &self.class_name)) {
+      stmts.remove(0);
+    }
+    Ok(Some((body, stmts)))
+  }
+
+  // invokedynamicの呼び出しを文字列の連結やラムダ式にする
+  pub fn dynamic(&self, bootstrap: usize, name: &str, descriptor: &MethodDescriptor, args: Vec<Expr>) -> Expr {
+    let recognized = self.bootstrap_methods.get(bootstrap).and_then(|method| {
+      let (_, class, bootstrap_name, _) = method_handle(self.pool, method.bootstrap_method_attr_index).ok()?;
+      match (class.as_str(), bootstrap_name.as_str()) {
+        ("java/lang/invoke/StringConcatFactory", "makeConcatWithConstants") => self.concat(method, descriptor, &args).ok(),
+        ("java/lang/invoke/StringConcatFactory", "makeConcat") => Some(self.concat_parts(args.clone(), descriptor)),
+        ("java/lang/invoke/LambdaMetafactory", "metafactory" | "altMetafactory") => self.lambda(method, &args).ok(),
+        _ => None,
+      }
+    });
+    recognized.unwrap_or_else(|| Expr::Dynamic { name: name.to_string(), args })
+  }
+
+  // レシピの\u{1}が引数、\u{2}が静的な定数
+  fn concat(&self, method: &BootstrapMethod, descriptor: &MethodDescriptor, args: &[Expr]) -> Result<Expr, String> {
+    let recipe = string_constant(self.pool, *method.bootstrap_arguments.first().ok_or("no recipe")?)?;
+    let mut constants = method.bootstrap_arguments.iter().skip(1);
+    let mut args = args.iter();
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    for c in recipe.chars() {
+      let part = match c {
+        '\u{1}' => args.next().cloned().ok_or("too few arguments for the recipe")?,
+        '\u{2}' => body::constant(self.pool, *constants.next().ok_or("too few constants for the recipe")?)?.0,
+        c => {
+          text.push(c);
+          continue;
+        },
+      };
+      if !text.is_empty() {
+        parts.push(Expr::Literal(Literal::String(std::mem::take(&mut text))));
+      }
+      parts.push(part);
+    }
+    if !text.is_empty() {
+      parts.push(Expr::Literal(Literal::String(text)));
+    }
+    Ok(self.concat_parts(parts, descriptor))
+  }
+
+  fn concat_parts(&self, mut parts: Vec<Expr>, descriptor: &MethodDescriptor) -> Expr {
+    let string = FieldType::Object("java/lang/String".to_string());
+    let is_string = |part: &Expr| {
+      matches!(part, Expr::Literal(Literal::String(_)) | Expr::Concat(_))
+        || descriptor.parameters.iter().zip(&parts).any(|(parameter, arg)| *parameter == string && arg == part)
+    };
+    // 先頭の2つのどちらかが文字列でないと数値の加算になってしまう
+    if !parts.iter().take(2).any(is_string) {
+      parts.insert(0, Expr::Literal(Literal::String(String::new())));
+    }
+    Expr::Concat(parts)
+  }
+
+  // ブートストラップ引数の2番目が実装のメソッド
+  fn lambda(&self, method: &BootstrapMethod, args: &[Expr]) -> Result<Expr, String> {
+    let implementation = *method.bootstrap_arguments.get(1).ok_or("no implementation method")?;
+    let (kind, class, implementation_name, implementation_descriptor) = method_handle(self.pool, implementation)?;
+    if class == self.class_name
+      && implementation_name.starts_with("lambda$")
+      && let Some(target) = self.find_method(&implementation_name, &implementation_descriptor)
+    {
+      return self.lambda_body(target, kind, args);
+    }
+    Ok(match kind {
+      REF_NEW_INVOKE_SPECIAL => Expr::MethodRef { class: Some(class), object: None, name: "new".to_string() },
+      REF_INVOKE_VIRTUAL | REF_INVOKE_INTERFACE | REF_INVOKE_SPECIAL if args.len() == 1 => {
+        let object = match (&args[0], kind) {
+          (Expr::This, REF_INVOKE_SPECIAL) if class != self.class_name => return Ok(Expr::MethodRef { class: Some("super".to_string()), object: None, name: implementation_name }),
+          (object, _) => object.clone(),
+        };
+        Expr::MethodRef { class: None, object: Some(Box::new(object)), name: implementation_name }
+      },
+      _ => Expr::MethodRef { class: Some(class), object: None, name: implementation_name },
+    })
+  }
+
+  fn lambda_body(&self, target: &Method, kind: u8, args: &[Expr]) -> Result<Expr, String> {
+    let (body, stmts) = self.method_body(target)?.ok_or("lambda without code")?;
+    // 捕捉した値は実装のメソッドの先頭の引数 (インスタンスメソッドならレシーバを除く)
+    let receiver = matches!(kind, REF_INVOKE_VIRTUAL | REF_INVOKE_SPECIAL | REF_INVOKE_INTERFACE);
+    let captured = args.len() - usize::from(receiver && !args.is_empty());
+    let params: Vec<String> = body.scope.locals.iter().filter(|local| local.parameter).skip(captured).map(|local| local.name.clone()).collect();
+    let mut printer = Printer::new(&self.names, &body.scope, 0);
+    let (text, block) = match stmts.as_slice() {
+      [Stmt::Return(Some(value))] => (printer.expr(&coerce(value.clone(), body.scope.return_type.as_ref()), 0), false),
+      [Stmt::Expr(value)] => (printer.expr(value, 0), false),
+      _ => {
+        printer.stmts(&stmts);
+        (printer.finish().join("\n"), true)
+      },
+    };
+    Ok(Expr::Lambda { params, body: text, block })
+  }
+
+  fn signature_attribute(&self, mut attributes: impl Iterator<Item = Option<u16>>) -> Option<String> {
+    attributes.find_map(|index| index).and_then(|index| self.pool.get_utf8(index).ok())
+  }
+
+  fn field_lines(&self, field: &Field, is_interface: bool, lines: &mut Vec<String>) -> Result<(), String> {
+    let name = self.pool.get_utf8(field.name_index)?;
+    let field_type = FieldType::parse(&self.pool.get_utf8(field.descriptor_index)?)?;
+    let signature = self
+      .signature_attribute(field.attributes.attributes.iter().map(|attribute| match attribute {
+        FieldInfoAttribute::Signature(attribute) => Some(attribute.signature_index),
+        _ => None,
+      }))
+      .and_then(|signature| TypeSignature::parse(&signature).ok());
+    let type_text = match &signature {
+      Some(signature) => self.names.signature(signature),
+      None => self.names.field_type(&field_type),
+    };
+    let access_flags = if is_interface { field.access_flags & !(PUBLIC | STATIC | FINAL) } else { field.access_flags };
+    let flags =
+      modifiers(access_flags, &[(PUBLIC, "public"), (PROTECTED, "protected"), (PRIVATE, "private"), (STATIC, "static"), (FINAL, "final"), (TRANSIENT, "transient"), (VOLATILE, "volatile")]);
+    let value = field.attributes.attributes.iter().find_map(|attribute| match attribute {
+      FieldInfoAttribute::ConstantValue(attribute) => Some(attribute.constant_value_index),
+      _ => None,
+    });
+    let initializer = match value {
+      Some(index) => {
+        let scope = Scope { class_name: self.class_name.clone(), locals: Vec::new(), return_type: None };
+        let value = coerce(body::constant(self.pool, index)?.0, Some(&field_type));
+        format!(" = {}", Printer::new(&self.names, &scope, 0).expr(&value, 0))
+      },
+      None => String::new(),
+    };
+    lines.push(format!("    {}{} {}{};", flags, type_text, name, initializer));
+    Ok(())
+  }
+
+  fn method_lines(&self, method: &Method, is_interface: bool, is_enum: bool, lines: &mut Vec<String>) -> Result<(), String> {
+    let pool = self.pool;
+    let name = pool.get_utf8(method.name_index)?;
+    let descriptor = MethodDescriptor::parse(&pool.get_utf8(method.descriptor_index)?)?;
+    let (body, error) = match self.method_body(method) {
+      Ok(body) => (body, None),
+      Err(e) => (None, Some(e)),
+    };
+    if name == "<clinit>" {
+      lines.push("    static {".to_string());
+    } else {
+      let signature = self
+        .signature_attribute(method.attributes.attributes.iter().map(|attribute| match attribute {
+          MethodInfoAttribute::Signature(attribute) => Some(attribute.signature_index),
+          _ => None,
+        }))
+        .and_then(|signature| MethodSignature::parse(&signature).ok());
+      let mut flags = method.access_flags;
+      let mut prefix = String::new();
+      if is_interface {
+        flags &= !PUBLIC;
+        if flags & (ABSTRACT | STATIC | PRIVATE) == 0 {
+          prefix.push_str("default ");
+        }
+        flags &= !ABSTRACT;
+      }
+      let mut text = format!(
+        "    {}{}",
+        modifiers(flags, &[(PUBLIC, "public"), (PROTECTED, "protected"), (PRIVATE, "private"), (ABSTRACT, "abstract"), (STATIC, "static"), (FINAL, "final"), (SYNCHRONIZED, "synchronized"), (NATIVE, "native")]),
+        prefix
+      );
+      if let Some(signature) = &signature {
+        let type_parameters = type_parameters_to_java(&signature.type_parameters, &|name| self.names.class(name));
+        if !type_parameters.is_empty() {
+          text.push_str(&format!("{} ", type_parameters));
+        }
+      }
+      if name == "<init>" {
+        let simple = self.names.class(&self.class_name);
+        text.push_str(simple.rsplit('.').next().unwrap_or(&simple));
+      } else {
+        let return_type = match (&signature, &descriptor.return_type) {
+          (Some(signature), _) => signature.return_type.as_ref().map(|return_type| self.names.signature(return_type)).unwrap_or("void".to_string()),
+          (None, Some(return_type)) => self.names.field_type(return_type),
+          (None, None) => "void".to_string(),
+        };
+        text.push_str(&format!("{} {}", return_type, name));
+      }
+
+      // 引数の名前は本体のローカル変数から、本体がなければMethodParameters属性から
+      let parameter_names: Vec<String> = match &body {
+        Some((body, _)) => body.scope.locals.iter().filter(|local| local.parameter).map(|local| local.name.clone()).collect(),
+        None => {
+          let names = method.attributes.attributes.iter().find_map(|attribute| match attribute {
+            MethodInfoAttribute::MethodParameters(attribute) => Some(&attribute.parameters),
+            _ => None,
+          });
+          (0..descriptor.parameters.len())
+            .map(|i| {
+              names.and_then(|names| names.get(i)).and_then(|parameter| pool.get_utf8(parameter.name_index).ok()).unwrap_or_else(|| format!("arg{}", i))
+            })
+            .collect()
+        },
+      };
+      let generic_parameters = signature.as_ref().map(|signature| &signature.parameters).filter(|parameters| parameters.len() == descriptor.parameters.len());
+      let mut parameters: Vec<String> = descriptor
+        .parameters
+        .iter()
+        .enumerate()
+        .map(|(i, parameter)| {
+          let type_text = match generic_parameters {
+            Some(parameters) => self.names.signature(&parameters[i]),
+            None => self.names.field_type(parameter),
+          };
+          format!("{} {}", type_text, parameter_names.get(i).cloned().unwrap_or_else(|| format!("arg{}", i)))
+        })
+        .collect();
+      if method.access_flags & VARARGS != 0
+        && let Some(last) = parameters.last_mut()
+        && let Some(at) = last.rfind("[] ")
+      {
+        last.replace_range(at..at + 2, "...");
+      }
+      // enumのコンストラクタの先頭の2つの引数は名前と序数
+      if is_enum && name == "<init>" && descriptor.parameters.len() >= 2 && generic_parameters.is_none() {
+        parameters.drain(..2);
+      }
+      text.push_str(&format!("({})", parameters.join(", ")));
+
+      let throws: Vec<String> = match signature.as_ref().filter(|signature| !signature.throws.is_empty()) {
+        Some(signature) => signature.throws.iter().map(|throw| self.names.signature(throw)).collect(),
+        None => method
+          .attributes
+          .attributes
+          .iter()
+          .find_map(|attribute| match attribute {
+            MethodInfoAttribute::Exceptions(attribute) => Some(&attribute.exception_index_table),
+            _ => None,
+          })
+          .into_iter()
+          .flatten()
+          .filter_map(|&index| pool.get_class_name(index).ok())
+          .map(|class| self.names.class(&class))
+          .collect(),
+      };
+      if !throws.is_empty() {
+        text.push_str(&format!(" throws {}", throws.join(", ")));
+      }
+      if body.is_none() && error.is_none() {
+        lines.push(format!("{};", text));
+        return Ok(());
+      }
+      lines.push(format!("{} {{", text));
+    }
+
+    match (body, error) {
+      (Some((body, mut stmts)), _) => {
+        if is_enum {
+          self.strip_enum_initialization(&name, &mut stmts);
+        }
+        let mut printer = Printer::new(&self.names, &body.scope, 2);
+        printer.stmts(&stmts);
+        lines.extend(printer.finish());
+      },
+      (None, Some(e)) => lines.push(format!("        // Failed to decompile: {}", e)),
+      (None, None) => {},
+    }
+    lines.push("    }".to_string());
+    Ok(())
+  }
+
+  // enumの定数と$VALUESの初期化、コンストラクタのsuper(name, ordinal)は宣言に含まれる
+  fn strip_enum_initialization(&self, name: &str, stmts: &mut Vec<Stmt>) {
+    match name {
+      "<clinit>" => stmts.retain(|stmt| {
+        !matches!(stmt, Stmt::Assign(Expr::Field { object: None, class, name, .. }, _)
+          if *class == self.class_name && (name == "$VALUES" || self.enum_constants().contains(name)))
+      }),
+      "<init>" => {
+        if matches!(stmts.first(), Some(Stmt::Expr(Expr::Invoke { object: Some(object), class, name, .. }))
+          if **object == Expr::This && name == "<init>" && class == "java/lang/Enum")
+        {
+          stmts.remove(0);
+        }
+      },
+      _ => {},
+    }
+  }
+
+  fn enum_constants(&self) -> Vec<String> {
+    self
+      .class_file
+      .fields
+      .fields
+      .iter()
+      .filter(|field| field.access_flags & ENUM != 0)
+      .filter_map(|field| self.pool.get_utf8(field.name_index).ok())
+      .collect()
+  }
+
+  fn is_object_method(&self, method: &Method, name: &str) -> bool {
+    matches!(self.method_body(method), Ok(Some((_, stmts)))
+      if matches!(stmts.as_slice(), [Stmt::Return(Some(Expr::Dynamic { name: dynamic, .. }))] if dynamic == name))
+  }
+
+  // クラス全体のソース
+  pub fn decompile(&self) -> Result<String, String> {
+    let class_file = self.class_file;
+    let pool = self.pool;
+    let access_flags = class_file.access_flags;
+    let is_interface = access_flags & INTERFACE != 0;
+    let is_enum = access_flags & ENUM != 0;
+    let super_class = if class_file.super_class == 0 { None } else { Some(pool.get_class_name(class_file.super_class)?) };
+    let is_record = super_class.as_deref() == Some("java/lang/Record");
+
+    let signature = self
+      .signature_attribute(class_file.attributes.attributes.iter().map(|attribute| match attribute {
+        ClassFileAttribute::Signature(attribute) => Some(attribute.signature_index),
+        _ => None,
+      }))
+      .and_then(|signature| ClassSignature::parse(&signature).ok());
+    let permitted: Vec<String> = class_file
+      .attributes
+      .attributes
+      .iter()
+      .find_map(|attribute| match attribute {
+        ClassFileAttribute::PermittedSubclasses(attribute) => Some(&attribute.classes),
+        _ => None,
+      })
+      .into_iter()
+      .flatten()
+      .filter_map(|&index| pool.get_class_name(index).ok())
+      .collect();
+
+    let mut flags = access_flags & (PUBLIC | FINAL | ABSTRACT);
+    let keyword = if access_flags & ANNOTATION != 0 {
+      flags &= !ABSTRACT;
+      "@interface"
+    } else if is_interface {
+      flags &= !ABSTRACT;
+      "interface"
+    } else if is_enum {
+      flags &= !(FINAL | ABSTRACT);
+      "enum"
+    } else if is_record {
+      flags &= !FINAL;
+      "record"
+    } else {
+      "class"
+    };
+    let simple = self.names.class(&self.class_name);
+    let mut header = format!("{}{}", modifiers(flags, &[(PUBLIC, "public"), (ABSTRACT, "abstract"), (FINAL, "final")]), if permitted.is_empty() { "" } else { "sealed " });
+    header.push_str(&format!("{} {}", keyword, simple.rsplit('.').next().unwrap_or(&simple)));
+    if let Some(signature) = &signature {
+      header.push_str(&type_parameters_to_java(&signature.type_parameters, &|name| self.names.class(name)));
+    }
+
+    let mut lines = Vec::new();
+    let mut component_fields = Vec::new();
+    if is_record {
+      let components = class_file.attributes.attributes.iter().find_map(|attribute| match attribute {
+        ClassFileAttribute::Record(attribute) => Some(&attribute.record_components),
+        _ => None,
+      });
+      let mut texts = Vec::new();
+      for component in components.into_iter().flatten() {
+        let name = pool.get_utf8(component.name_index)?;
+        texts.push(format!("{} {}", self.names.field_type(&FieldType::parse(&pool.get_utf8(component.descriptor_index)?)?), name));
+        component_fields.push(name);
+      }
+      header.push_str(&format!("({})", texts.join(", ")));
+    }
+
+    let superclass = match &signature {
+      Some(signature) => Some(self.names.signature(&signature.superclass)),
+      None => super_class.as_deref().map(|name| self.names.class(name)),
+    };
+    let interfaces: Vec<String> = match &signature {
+      Some(signature) => signature.interfaces.iter().map(|interface| self.names.signature(interface)).collect(),
+      None => class_file.interfaces.interfaces.iter().filter_map(|&index| pool.get_class_name(index).ok()).map(|name| self.names.class(&name)).collect(),
+    };
+    let implicit_super = is_interface || is_enum || is_record || super_class.as_deref().is_none_or(|name| name == "java/lang/Object");
+    if let Some(superclass) = superclass.filter(|_| !implicit_super) {
+      header.push_str(&format!(" extends {}", superclass));
+    }
+    if !interfaces.is_empty() {
+      header.push_str(&format!(" {} {}", if is_interface { "extends" } else { "implements" }, interfaces.join(", ")));
+    }
+    if !permitted.is_empty() {
+      let permitted: Vec<String> = permitted.iter().map(|name| self.names.class(name)).collect();
+      header.push_str(&format!(" permits {}", permitted.join(", ")));
+    }
+    lines.push(format!("{} {{", header));
+
+    let mut sections = Vec::new();
+    if is_enum {
+      let constants = self.enum_constants();
+      if !constants.is_empty() {
+        sections.push(vec![format!("    {};", constants.join(", "))]);
+      }
+    }
+    let mut fields = Vec::new();
+    for field in &class_file.fields.fields {
+      let synthetic = field.attributes.attributes.iter().any(|attribute| matches!(attribute, FieldInfoAttribute::Synthetic(_)));
+      let name = pool.get_utf8(field.name_index)?;
+      if field.access_flags & (SYNTHETIC | ENUM) != 0 || synthetic || (is_record && field.access_flags & STATIC == 0 && component_fields.contains(&name)) {
+        continue;
+      }
+      self.field_lines(field, is_interface, &mut fields)?;
+    }
+    if !fields.is_empty() {
+      sections.push(fields);
+    }
+    for method in &class_file.methods.methods {
+      let synthetic = method.attributes.attributes.iter().any(|attribute| matches!(attribute, MethodInfoAttribute::Synthetic(_)));
+      if method.access_flags & (SYNTHETIC | BRIDGE) != 0 || synthetic {
+        continue;
+      }
+      let name = pool.get_utf8(method.name_index)?;
+      let descriptor = pool.get_utf8(method.descriptor_index)?;
+      // enumのvalues()とvalueOf()はコンパイラが生成する
+      if is_enum && (name == "values" && descriptor.starts_with("()") || name == "valueOf" && descriptor.starts_with("(Ljava/lang/String;)")) {
+        continue;
+      }
+      // recordのtoString()などはObjectMethodsのinvokedynamicで生成される
+      if is_record && self.is_object_method(method, &name) {
+        continue;
+      }
+      let mut method_lines = Vec::new();
+      self.method_lines(method, is_interface, is_enum, &mut method_lines)?;
+      // 空のstatic初期化子と引数のない空のコンストラクタは省略する
+      if method_lines.len() == 2 && (name == "<clinit>" || name == "<init>" && (descriptor == "()V" || is_enum)) {
+        continue;
+      }
+      sections.push(method_lines);
+    }
+    for (i, section) in sections.into_iter().enumerate() {
+      if i > 0 {
+        lines.push(String::new());
+      }
+      lines.extend(section);
+    }
+    lines.push("}".to_string());
+
+    // importは本体の名前を解決した後で決まる
+    let mut source = String::new();
+    if !self.names.package().is_empty() {
+      source.push_str(&format!("package {};\n\n", self.names.package().replace('/', ".")));
+    }
+    let imports = self.names.import_list();
+    for import in &imports {
+      source.push_str(&format!("import {};\n", import));
+    }
+    if !imports.is_empty() {
+      source.push('\n');
+    }
+    for line in lines {
+      source.push_str(&line);
+      source.push('\n');
+    }
+    Ok(source)
+  }
+}
+
+// クラスファイルを逆コンパイルしてJavaのソースを表示する
+pub fn print_command(program: &str, args: &[String]) -> i32 {
+  let [path] = args else {
+    eprintln!("Usage: {} decompile <class file>", program);
+    return 2;
+  };
+  let class_file = match fs::read(path).and_then(|bytes| class_leader::parse_bytes(&bytes)) {
+    Ok(class_file) => class_file,
+    Err(e) => {
+      eprintln!("Error: {}: {}", path, e);
+      return 1;
+    },
+  };
+  match ClassContext::new(&class_file).and_then(|context| context.decompile()) {
+    Ok(source) => {
+      print!("{}", source);
+      0
+    },
+    Err(e) => {
+      eprintln!("Error: {}: {}", path, e);
+      1
+    },
+  }
+}
@@ -0,0 +1,100 @@
+use std::{cell::RefCell, collections::BTreeMap};
+
+use crate::util::{descriptor::FieldType, signature::TypeSignature};
+
+// クラスの内部名をソースでの名前にして、必要なimportを集める
+pub struct Names {
+  // このクラスのパッケージ (内部名、'/'区切り)
+  package: String,
+  // 単純名 -> トップレベルのクラスの内部名
+  imports: RefCell<BTreeMap<String, String>>,
+}
+
+fn split_package(name: &str) -> (&str, &str) {
+  match name.rfind('/') {
+    Some(at) => (&name[..at], &name[at + 1..]),
+    None => ("", name),
+  }
+}
+
+// Outer$Inner -> Outer.Inner (匿名クラスやローカルクラスの$1などはそのまま)
+fn nested_name(simple: &str) -> String {
+  let mut text = String::new();
+  for (i, part) in simple.split('$').enumerate() {
+    if i > 0 {
+      let anonymous = part.is_empty() || part.starts_with(|c: char| c.is_ascii_digit());
+      text.push(if anonymous { '$' } else { '.' });
+    }
+    text.push_str(part);
+  }
+  text
+}
+
+// 単純名とトップレベルのクラスの内部名
+fn top_level(name: &str) -> (&str, String) {
+  let (package, simple) = split_package(name);
+  let top = simple.split('$').next().unwrap_or(simple);
+  if package.is_empty() { (top, top.to_string()) } else { (top, format!("{}/{}", package, top)) }
+}
+
+impl Names {
+  pub fn new(this_class: &str) -> Names {
+    let (top, qualified) = top_level(this_class);
+    let imports = BTreeMap::from([(top.to_string(), qualified)]);
+    let (package, _) = split_package(this_class);
+    Names { package: package.to_string(), imports: RefCell::new(imports) }
+  }
+
+  pub fn package(&self) -> &str {
+    &self.package
+  }
+
+  pub fn class(&self, name: &str) -> String {
+    if name.starts_with('[') {
+      return match FieldType::parse(name) {
+        Ok(field_type) => self.field_type(&field_type),
+        Err(_) => name.to_string(),
+      };
+    }
+    let (_, simple) = split_package(name);
+    let (top, qualified) = top_level(name);
+    let mut imports = self.imports.borrow_mut();
+    match imports.get(top) {
+      Some(imported) if *imported == qualified => nested_name(simple),
+      // 単純名が他のクラスと衝突するなら完全修飾名にする
+      Some(_) => nested_name(name).replace('/', "."),
+      None => {
+        imports.insert(top.to_string(), qualified);
+        nested_name(simple)
+      },
+    }
+  }
+
+  pub fn field_type(&self, field_type: &FieldType) -> String {
+    match field_type {
+      FieldType::Object(name) => self.class(name),
+      FieldType::Array(component) => format!("{}[]", self.field_type(component)),
+      base => base.to_string(),
+    }
+  }
+
+  pub fn signature(&self, signature: &TypeSignature) -> String {
+    signature.to_java(&|name| self.class(name))
+  }
+
+  // java.langと同じパッケージのクラスを除いたimport文の対象 (完全修飾名の順)
+  pub fn import_list(&self) -> Vec<String> {
+    let mut list: Vec<String> = self
+      .imports
+      .borrow()
+      .values()
+      .filter(|name| {
+        let (package, _) = split_package(name);
+        package != "java/lang" && package != self.package
+      })
+      .map(|name| name.replace('/', "."))
+      .collect();
+    list.sort();
+    list
+  }
+}
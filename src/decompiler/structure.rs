@@ -0,0 +1,1089 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+  decompiler::{
+    ast::{coerce, ends_abruptly, negate, Case, Catch, Expr, Literal, Scope, Stmt},
+    body::{Block, Exit, MethodBody},
+  },
+  util::{
+    descriptor::FieldType,
+    graph::{dominator_tree, immediate_dominators, natural_loops, predecessors, reverse_postorder, NaturalLoop},
+  },
+};
+
+// ブロックのグラフから if/while/switch/try の入れ子を復元する
+
+// breakやcontinueで抜けられる文
+#[derive(Debug, Clone)]
+struct Breakable {
+  // ループならヘッダ (switchはNone)
+  header: Option<usize>,
+  follow: Option<usize>,
+  label: usize,
+  // ラベル付きのブロック (breakには必ずラベルが要る)
+  block: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Context {
+  // ここに着いたら文の列を終える
+  stops: Vec<usize>,
+  breakables: Vec<Breakable>,
+  // 最も内側のループのヘッダ
+  loop_header: Option<usize>,
+  // 開いているtry
+  regions: Vec<usize>,
+  // 本体を構造化しているハンドラ
+  handlers: Vec<usize>,
+}
+
+impl Context {
+  fn is_block_follow(&self, index: usize) -> bool {
+    self.breakables.iter().any(|breakable| breakable.block && breakable.follow == Some(index))
+  }
+
+  // 合流点をラベル付きのブロックの後に置き、着いたら文の列を終える
+  fn push_block(&mut self, follow: usize, label: usize) {
+    self.stops.push(follow);
+    self.breakables.push(Breakable { header: None, follow: Some(follow), label, block: true });
+  }
+}
+
+struct Structurer<'a> {
+  body: &'a MethodBody,
+  predecessors: Vec<Vec<usize>>,
+  // 例外の辺も含めたグラフの支配木
+  dominators: Vec<Option<usize>>,
+  dominator_children: Vec<Vec<usize>>,
+  // 通常の辺だけのグラフの直後支配ブロック
+  post_dominators: Vec<Option<usize>>,
+  // 逆後順の位置
+  order: Vec<usize>,
+  loops: HashMap<usize, NaturalLoop>,
+  emitted: Vec<bool>,
+  next_label: usize,
+  used_labels: HashSet<usize>,
+}
+
+pub fn structure(body: &MethodBody) -> Vec<Stmt> {
+  let count = body.blocks.len();
+  let successors = body.successor_lists();
+  let root = count;
+  let mut flow = successors.clone();
+  for (index, block) in body.blocks.iter().enumerate() {
+    if let Some(block) = block {
+      for region in body.covering(block.pc) {
+        for (_, handler) in &body.regions[region].handlers {
+          if !flow[index].contains(handler) {
+            flow[index].push(*handler);
+          }
+        }
+      }
+    }
+  }
+  flow.push(vec![0]);
+  let dominators = immediate_dominators(&flow, root);
+  let dominator_children = dominator_tree(&dominators);
+  let loops = natural_loops(&flow, root).into_iter().map(|natural| (natural.header, natural)).collect();
+  let mut order = vec![usize::MAX; count + 1];
+  for (position, index) in reverse_postorder(&flow, root).into_iter().enumerate() {
+    order[index] = position;
+  }
+
+  // 出口のないブロックを仮想の出口につないだ逆向きのグラフ
+  let exit = count;
+  let mut reverse = predecessors(&successors);
+  reverse.push((0..count).filter(|&index| body.blocks[index].is_some() && successors[index].is_empty()).collect());
+  let post_dominators = immediate_dominators(&reverse, exit).into_iter().map(|dominator| dominator.filter(|&d| d != exit)).collect();
+
+  let mut structurer = Structurer {
+    body,
+    predecessors: predecessors(&successors),
+    dominators,
+    dominator_children,
+    post_dominators,
+    order,
+    loops,
+    emitted: vec![false; count],
+    next_label: 0,
+    used_labels: HashSet::new(),
+  };
+  let mut stmts = structurer.sequence(0, &Context::default(), false);
+  finish(&mut stmts, &body.scope);
+  stmts
+}
+
+fn label_name(label: usize) -> String {
+  format!("label{}", label)
+}
+
+// 最後の文がこのループへのcontinueなら取り除く
+fn strip_continue(body: &mut Vec<Stmt>) {
+  if matches!(body.last(), Some(Stmt::Continue(None))) {
+    body.pop();
+  }
+}
+
+// ループの入れ子を除いてラベルなしのcontinueがあるか
+fn has_continue(body: &[Stmt]) -> bool {
+  body.iter().any(|stmt| match stmt {
+    Stmt::Continue(None) => true,
+    Stmt::While(..) | Stmt::DoWhile(..) | Stmt::For(..) => false,
+    Stmt::Labeled(_, stmt) if matches!(**stmt, Stmt::While(..) | Stmt::DoWhile(..) | Stmt::For(..)) => false,
+    stmt => stmt.clone().bodies_mut().into_iter().any(|body| has_continue(body)),
+  })
+}
+
+impl Structurer<'_> {
+  fn in_loop(&self, header: usize, index: usize) -> bool {
+    self.loops.get(&header).is_some_and(|natural| natural.body.binary_search(&index).is_ok())
+  }
+
+  fn dominates(&self, dominator: usize, mut index: usize) -> bool {
+    loop {
+      if index == dominator {
+        return true;
+      }
+      match self.dominators[index] {
+        Some(next) if next != index => index = next,
+        _ => return false,
+      }
+    }
+  }
+
+  fn in_region(&self, region: usize, index: usize) -> bool {
+    let region = &self.body.regions[region];
+    (region.start_pc..region.end_pc).contains(&self.body.block(index).pc)
+  }
+
+  fn label(&mut self, label: usize) -> Option<String> {
+    self.used_labels.insert(label);
+    Some(label_name(label))
+  }
+
+  // 構造の外へ出る分岐ならbreakかcontinue
+  fn jump(&mut self, index: usize, context: &Context) -> Option<Stmt> {
+    let innermost = context.breakables.iter().rposition(|breakable| !breakable.block);
+    let innermost_loop = context.breakables.iter().rposition(|breakable| breakable.header.is_some());
+    for (i, breakable) in context.breakables.iter().enumerate().rev() {
+      if breakable.header == Some(index) {
+        let label = if Some(i) == innermost_loop { None } else { self.label(breakable.label) };
+        return Some(Stmt::Continue(label));
+      }
+      if breakable.follow == Some(index) {
+        let label = if Some(i) == innermost { None } else { self.label(breakable.label) };
+        return Some(Stmt::Break(label));
+      }
+    }
+    None
+  }
+
+  // 何度出力してもよい小さな終端のブロック
+  fn duplicable(&self, index: usize) -> bool {
+    let block = self.body.block(index);
+    matches!(block.exit, Exit::Return(_) | Exit::Throw(_)) && block.stmts.len() <= 3
+  }
+
+  // 条件式の値を$kに入れて合流点へ進むだけのブロック
+  fn copies_value(&self, index: usize) -> bool {
+    let block = self.body.block(index);
+    matches!(block.exit, Exit::Goto(_)) && matches!(block.stmts.as_slice(), [Stmt::Assign(Expr::Stack(_), value)] if value.is_simple())
+  }
+
+  fn region_to_open(&self, index: usize, context: &Context) -> Option<usize> {
+    let pc = self.body.block(index).pc;
+    self
+      .body
+      .covering(pc)
+      .into_iter()
+      .filter(|region| !context.regions.contains(region))
+      .filter(|&region| !self.body.regions[region].handlers.iter().any(|(_, handler)| context.handlers.contains(handler)))
+      .max_by_key(|&region| (self.body.regions[region].end_pc - self.body.regions[region].start_pc, usize::MAX - region))
+  }
+
+  fn sequence(&mut self, start: usize, context: &Context, entering: bool) -> Vec<Stmt> {
+    let mut stmts = Vec::new();
+    let mut current = Some(start);
+    // ループのヘッダから入る時はcontinueやループの検出をしない
+    let mut entering = entering;
+    while let Some(index) = current {
+      if !entering {
+        // 内側の文の終わりに着いたら抜ける (外側の文の終わりへはラベル付きのbreakで抜ける)
+        if context.stops.last() == Some(&index) || context.stops.contains(&index) && !context.is_block_follow(index) {
+          break;
+        }
+        if let Some(jump) = self.jump(index, context) {
+          stmts.push(jump);
+          break;
+        }
+      }
+      if self.emitted[index] && !self.duplicable(index) && !self.copies_value(index) {
+        // 直前の文から抜けない時は辿り着かない
+        if !ends_abruptly(&stmts) {
+          stmts.push(Stmt::Goto(self.body.block(index).pc));
+        }
+        break;
+      }
+      if let Some(region) = self.region_to_open(index, context) {
+        let is_loop = !entering && self.loops.contains_key(&index);
+        let loop_inside = is_loop && self.loops[&index].body.iter().all(|&member| self.in_region(region, member));
+        if !is_loop || loop_inside {
+          let (stmt, follow) = self.structure_try(index, region, context, entering);
+          stmts.push(stmt);
+          current = follow;
+          entering = false;
+          continue;
+        }
+      }
+      if !entering && self.loops.contains_key(&index) {
+        let (stmt, follow) = self.structure_loop(index, context);
+        stmts.push(stmt);
+        current = follow;
+        continue;
+      }
+      entering = false;
+      self.emitted[index] = true;
+      let block = self.body.block(index);
+      stmts.extend(block.stmts.iter().cloned());
+      current = match &block.exit {
+        Exit::Goto(next) => Some(*next),
+        Exit::Return(value) => {
+          stmts.push(Stmt::Return(value.clone()));
+          None
+        },
+        Exit::Throw(value) => {
+          stmts.push(Stmt::Throw(value.clone()));
+          None
+        },
+        Exit::If(condition, then_block, else_block) => {
+          let merge = self.structure_if(index, condition, *then_block, *else_block, context, &mut stmts);
+          // どの節も抜けるなら合流点には辿り着かない
+          merge.filter(|_| !ends_abruptly(&stmts))
+        },
+        Exit::Switch(value, cases) => self.structure_switch(index, value, cases, context, &mut stmts),
+      };
+    }
+    stmts
+  }
+
+  // 分岐が合流するブロック
+  fn merge_point(&self, index: usize, context: &Context) -> Option<usize> {
+    // ループの外の合流点でも、breakやcontinueの行き先でなければ分岐の後に続けて書ける
+    let valid = |merge: usize| {
+      !self.emitted[merge]
+        && (context.loop_header.is_none_or(|header| self.in_loop(header, merge))
+          || !context.breakables.iter().any(|breakable| breakable.header == Some(merge) || breakable.follow == Some(merge)))
+    };
+    if let Some(merge) = self.post_dominators[index]
+      && valid(merge)
+    {
+      return Some(merge);
+    }
+    // returnなどで直後支配がなくても、両方の分岐から入ってくる支配木の子は合流点になる
+    self.dominator_children[index]
+      .iter()
+      .copied()
+      .filter(|&child| child < self.body.blocks.len() && valid(child))
+      .filter(|&child| {
+        let entering: Vec<&usize> = match self.loops.get(&child) {
+          Some(natural) if !self.in_loop(child, index) => {
+            self.predecessors[child].iter().filter(|p| natural.body.binary_search(p).is_err()).collect()
+          },
+          _ => self.predecessors[child].iter().collect(),
+        };
+        entering.len() >= 2
+      })
+      .min_by_key(|&child| self.order[child])
+  }
+
+  // 合流点の他に、複数の経路から入ってくる支配木の子 (ラベル付きのブロックを抜けた先にする)
+  fn inner_merges(&self, index: usize, merge: Option<usize>, context: &Context) -> Vec<usize> {
+    self.dominator_children[index]
+      .iter()
+      .copied()
+      .filter(|&child| child < self.body.blocks.len() && Some(child) != merge && !self.emitted[child] && !context.stops.contains(&child))
+      .filter(|&child| !self.loops.contains_key(&child) && !self.duplicable(child) && !self.copies_value(child))
+      .filter(|&child| context.loop_header.is_none_or(|header| self.in_loop(header, child)))
+      .filter(|&child| !context.breakables.iter().any(|breakable| breakable.header == Some(child) || breakable.follow == Some(child)))
+      .filter(|&child| self.predecessors[child].len() >= 2)
+      .collect()
+  }
+
+  fn structure_if(&mut self, index: usize, condition: &Expr, jump: usize, fallthrough: usize, context: &Context, stmts: &mut Vec<Stmt>) -> Option<usize> {
+    // 分岐から外側の文の終わり (次のcaseなど) に着くなら、ifの後には何も続けられない
+    let reaches_stop = context
+      .stops
+      .last()
+      .is_some_and(|&stop| !context.is_block_follow(stop) && self.predecessors[stop].iter().any(|&from| self.dominates(index, from)));
+    let merge = self.merge_point(index, context).filter(|&merge| !reaches_stop || context.stops.last() == Some(&merge));
+    let mut merges = if reaches_stop { Vec::new() } else { self.inner_merges(index, merge, context) };
+    // 合流点ごとにラベル付きのブロックを作り、手前の合流点ほど内側にする
+    // (ブロックの終わりに着けばその合流点に進み、外側の合流点へはbreakで抜ける)
+    merges.extend(merge);
+    merges.sort_by_key(|&merge| self.order[merge]);
+    let labels: Vec<usize> = (0..merges.len()).map(|i| self.next_label + i).collect();
+    self.next_label += merges.len();
+    let mut inner = context.clone();
+    for (&merge, &label) in merges.iter().zip(&labels).rev() {
+      inner.push_block(merge, label);
+    }
+    // 分岐しない側がソースのthen節
+    let then_body = self.sequence(fallthrough, &inner, false);
+    let else_body = self.sequence(jump, &inner, false);
+    let mut body = Vec::new();
+    push_if(&mut body, negate(condition.clone()), then_body, else_body);
+    for (i, &label) in labels.iter().enumerate() {
+      inner.stops.pop();
+      inner.breakables.pop();
+      body = labeled_block(label_name(label), body);
+      if i + 1 < merges.len() {
+        body.extend(self.sequence(merges[i], &inner, false));
+      }
+    }
+    stmts.extend(body);
+    merges.last().copied()
+  }
+
+  fn structure_switch(&mut self, index: usize, value: &Expr, cases: &[(Option<i32>, usize)], context: &Context, stmts: &mut Vec<Stmt>) -> Option<usize> {
+    let merge = self.merge_point(index, context);
+    let label = self.next_label;
+    self.next_label += 1;
+    let mut targets: Vec<usize> = Vec::new();
+    for (_, target) in cases {
+      if Some(*target) != merge && !targets.contains(target) {
+        targets.push(*target);
+      }
+    }
+    targets.sort_by_key(|&target| self.body.block(target).pc);
+    let mut inner = context.clone();
+    // caseの終わりから合流点へはbreakになる
+    inner.breakables.push(Breakable { header: None, follow: merge, label, block: false });
+    let mut switch_cases = Vec::new();
+    for (i, &target) in targets.iter().enumerate() {
+      let mut labels: Vec<Option<i32>> = cases.iter().filter(|(_, t)| *t == target).map(|(key, _)| *key).collect();
+      labels.sort_by_key(|key| key.is_none());
+      let mut case_context = inner.clone();
+      // 次のcaseに着いたらフォールスルー
+      case_context.stops.extend(targets.get(i + 1));
+      let body = self.sequence(target, &case_context, false);
+      switch_cases.push(Case { labels, body });
+    }
+    let breaks: Vec<Option<i32>> = cases.iter().filter(|(key, target)| key.is_some() && Some(*target) == merge).map(|(key, _)| *key).collect();
+    if !breaks.is_empty() {
+      switch_cases.push(Case { labels: breaks, body: vec![Stmt::Break(None)] });
+    }
+    if let Some(last) = switch_cases.last_mut()
+      && matches!(last.body.last(), Some(Stmt::Break(None)))
+    {
+      last.body.pop();
+    }
+    let stmt = Stmt::Switch(value.clone(), switch_cases);
+    stmts.push(if self.used_labels.contains(&label) { Stmt::Labeled(label_name(label), Box::new(stmt)) } else { stmt });
+    merge
+  }
+
+  // ループを抜けた先
+  fn loop_follow(&self, header: usize, context: &Context) -> Option<usize> {
+    let natural = &self.loops[&header];
+    let inside = |index: usize| natural.body.binary_search(&index).is_ok();
+    if let Exit::If(_, then_block, else_block) = &self.body.block(header).exit {
+      match (inside(*then_block), inside(*else_block)) {
+        (true, false) => return Some(*else_block),
+        (false, true) => return Some(*then_block),
+        _ => {},
+      }
+    }
+    let mut exits = Vec::new();
+    for &member in &natural.body {
+      if member >= self.body.blocks.len() || self.body.blocks[member].is_none() {
+        continue;
+      }
+      for successor in self.body.block(member).exit.successors() {
+        let outer = context.breakables.iter().any(|breakable| breakable.header == Some(successor) || breakable.follow == Some(successor));
+        if !inside(successor) && !outer && !exits.contains(&successor) {
+          exits.push(successor);
+        }
+      }
+    }
+    // returnで終わるブロックよりも、後に続きのある出口を選ぶ
+    exits.iter().copied().filter(|&exit| !self.duplicable(exit)).max_by_key(|&exit| self.body.block(exit).pc).or_else(|| {
+      exits.iter().copied().max_by_key(|&exit| self.body.block(exit).pc).filter(|_| exits.len() == 1)
+    })
+  }
+
+  // 本体の複数の場所から入ってきてヘッダに戻るだけのブロック (forの更新式など)
+  fn shared_latch(&self, header: usize) -> Option<usize> {
+    let back_edges: Vec<usize> = self.predecessors[header].iter().copied().filter(|&member| self.in_loop(header, member)).collect();
+    let [latch] = back_edges[..] else {
+      return None;
+    };
+    let goes_back = matches!(self.body.block(latch).exit, Exit::Goto(next) if next == header);
+    (latch != header && goes_back && self.predecessors[latch].len() >= 2).then_some(latch)
+  }
+
+  // 共有された戻りのブロックがあれば、本体をそこへ抜けるラベル付きのブロックにする
+  fn loop_body(&mut self, start: usize, inner: &Context, entering: bool, latch: Option<(usize, usize)>) -> Vec<Stmt> {
+    let Some((latch, label)) = latch else {
+      return self.sequence(start, inner, entering);
+    };
+    let mut context = inner.clone();
+    context.push_block(latch, label);
+    let mut body = labeled_block(label_name(label), self.sequence(start, &context, entering));
+    if !ends_abruptly(&body) {
+      body.extend(self.sequence(latch, inner, false));
+    }
+    body
+  }
+
+  fn structure_loop(&mut self, header: usize, context: &Context) -> (Stmt, Option<usize>) {
+    let follow = self.loop_follow(header, context);
+    let label = self.next_label;
+    self.next_label += 1;
+    let mut inner = context.clone();
+    inner.stops.clear();
+    inner.breakables.push(Breakable { header: Some(header), follow, label, block: false });
+    inner.loop_header = Some(header);
+    let latch = self.shared_latch(header).map(|latch| {
+      self.next_label += 1;
+      (latch, self.next_label - 1)
+    });
+
+    let block = self.body.block(header);
+    let mut condition = None;
+    if block.stmts.is_empty()
+      && self.region_to_open(header, context).is_none()
+      && let Exit::If(test, jump, fallthrough) = &block.exit
+    {
+      if follow == Some(*jump) && self.in_loop(header, *fallthrough) {
+        condition = Some((negate(test.clone()), *fallthrough));
+      } else if follow == Some(*fallthrough) && self.in_loop(header, *jump) {
+        condition = Some((test.clone(), *jump));
+      }
+    }
+    let stmt = match condition {
+      Some((condition, start)) => {
+        self.emitted[header] = true;
+        let mut body = self.loop_body(start, &inner, false, latch);
+        strip_continue(&mut body);
+        Stmt::While(condition, body)
+      },
+      None => {
+        let mut body = self.loop_body(header, &inner, true, latch);
+        strip_continue(&mut body);
+        do_while(body, self.used_labels.contains(&label))
+      },
+    };
+    let stmt = if self.used_labels.contains(&label) { Stmt::Labeled(label_name(label), Box::new(stmt)) } else { stmt };
+    (stmt, follow)
+  }
+
+  // catch (Throwable t) { F; throw t; } のハンドラがあれば、Fだけのブロックから進む先 (なければそのブロック)
+  fn skip_finally_copy(&self, region: usize, exit: usize) -> usize {
+    let finally = self.body.regions[region].handlers.iter().find_map(|(catch_type, handler)| {
+      let block = self.body.block(*handler);
+      match (catch_type, block.stmts.split_first(), &block.exit) {
+        (None, Some((Stmt::Assign(caught @ Expr::Local(_), Expr::Caught), finally)), Exit::Throw(thrown)) if caught == thrown && !finally.is_empty() => Some(finally),
+        _ => None,
+      }
+    });
+    match (finally, self.body.block(exit)) {
+      (Some(finally), Block { stmts, exit: Exit::Goto(next), .. }) if stmts == finally && self.body.blocks[*next].is_some() => *next,
+      _ => exit,
+    }
+  }
+
+  fn structure_try(&mut self, index: usize, region: usize, context: &Context, entering: bool) -> (Stmt, Option<usize>) {
+    let (start_pc, end_pc) = (self.body.regions[region].start_pc, self.body.regions[region].end_pc);
+    let mut exits: Vec<usize> = Vec::new();
+    for (member, block) in self.body.blocks.iter().enumerate() {
+      let Some(block) = block else {
+        continue;
+      };
+      if !(start_pc..end_pc).contains(&block.pc) || self.order[member] == usize::MAX {
+        continue;
+      }
+      for successor in block.exit.successors() {
+        // finallyの複製は本体の最後に含め、その先を後に続く文にする
+        let successor = if self.in_region(region, successor) { successor } else { self.skip_finally_copy(region, successor) };
+        if !self.in_region(region, successor) && !exits.contains(&successor) {
+          exits.push(successor);
+        }
+      }
+    }
+    let follow = exits
+      .iter()
+      .copied()
+      .filter(|&exit| self.body.block(exit).pc >= end_pc && !self.duplicable(exit))
+      .min_by_key(|&exit| self.body.block(exit).pc)
+      .or_else(|| exits.iter().copied().filter(|&exit| !self.duplicable(exit)).min_by_key(|&exit| self.body.block(exit).pc))
+      // 範囲の後に続く出口が1つだけなら、returnで終わる小さなブロックでもtryの後に書く
+      .or_else(|| match exits.iter().filter(|&&exit| self.body.block(exit).pc >= end_pc).collect::<Vec<_>>()[..] {
+        [&exit] => Some(exit),
+        _ => None,
+      });
+
+    let label = self.next_label;
+    self.next_label += 1;
+    let mut inner = context.clone();
+    inner.regions.push(region);
+    if let Some(follow) = follow {
+      inner.push_block(follow, label);
+    }
+    let body = self.sequence(index, &inner, entering);
+
+    let mut handlers: Vec<(Vec<String>, usize)> = Vec::new();
+    for (catch_type, handler) in &self.body.regions[region].handlers {
+      match handlers.iter_mut().find(|(_, h)| h == handler) {
+        Some((types, _)) => types.extend(catch_type.clone()),
+        None => handlers.push((catch_type.iter().cloned().collect(), *handler)),
+      }
+    }
+    let mut catches = Vec::new();
+    for (types, handler) in handlers {
+      let mut handler_context = context.clone();
+      handler_context.handlers.push(handler);
+      if let Some(follow) = follow {
+        handler_context.push_block(follow, label);
+      }
+      // 同じハンドラが分割された範囲から共有されていても本体を出力する
+      self.emitted[handler] = false;
+      let mut body = self.sequence(handler, &handler_context, false);
+      // finallyのtryが先に開いていれば例外の代入はその中にある
+      let first = match body.first_mut() {
+        Some(Stmt::Try(inner, ..)) => inner,
+        _ => &mut body,
+      };
+      let local = match first.first() {
+        Some(Stmt::Assign(Expr::Local(local), Expr::Caught)) => {
+          let local = *local;
+          first.remove(0);
+          Some(local)
+        },
+        _ => None,
+      };
+      catches.push(Catch { types, local, body });
+    }
+    // 内側の文から後に続く文へ抜けるならtryにラベルを付ける
+    let mut stmt = Stmt::Try(body, catches, Vec::new());
+    let name = label_name(label);
+    strip_break(&mut stmt, &name);
+    if breaks_to(std::slice::from_ref(&stmt), &name) {
+      stmt = Stmt::Labeled(name, Box::new(stmt));
+    }
+    (stmt, follow)
+  }
+}
+
+// 末尾のbreakを除き、まだラベルで抜けるならブロックにする
+fn labeled_block(label: String, mut body: Vec<Stmt>) -> Vec<Stmt> {
+  strip_last_break(&mut body, &label);
+  if breaks_to(&body, &label) { vec![Stmt::Labeled(label, Box::new(Stmt::Block(body)))] } else { body }
+}
+
+// 文の終わりに抜けるだけのbreak (最後の文がifやtryならその各節の最後) を取り除く
+fn strip_break(stmt: &mut Stmt, label: &str) {
+  match stmt {
+    Stmt::If(..) | Stmt::Try(..) | Stmt::Block(_) | Stmt::Synchronized(..) => {
+      for body in stmt.bodies_mut() {
+        strip_last_break(body, label);
+      }
+    },
+    Stmt::Labeled(_, stmt) if matches!(**stmt, Stmt::Block(_) | Stmt::Try(..)) => strip_break(stmt, label),
+    _ => {},
+  }
+}
+
+fn strip_last_break(body: &mut Vec<Stmt>, label: &str) {
+  match body.last_mut() {
+    Some(Stmt::Break(Some(name))) if name == label => {
+      body.pop();
+    },
+    Some(stmt) => strip_break(stmt, label),
+    None => {},
+  }
+}
+
+fn breaks_to(body: &[Stmt], label: &str) -> bool {
+  body.iter().any(|stmt| match stmt {
+    Stmt::Break(Some(name)) => name == label,
+    stmt => stmt.clone().bodies_mut().into_iter().any(|body| breaks_to(body, label)),
+  })
+}
+
+fn push_if(stmts: &mut Vec<Stmt>, condition: Expr, then_body: Vec<Stmt>, else_body: Vec<Stmt>) {
+  if then_body.is_empty() && else_body.is_empty() {
+    stmts.push(Stmt::If(condition, then_body, else_body));
+  } else if then_body.is_empty() {
+    push_if(stmts, negate(condition), else_body, then_body);
+  } else if !else_body.is_empty() && ends_abruptly(&then_body) {
+    // then節が戻らないならelseを外に出す
+    stmts.push(Stmt::If(condition, then_body, Vec::new()));
+    stmts.extend(else_body);
+  } else {
+    stmts.push(Stmt::If(condition, then_body, else_body));
+  }
+}
+
+// while (true) { ...; if (c) continue; break; } を do { ... } while (c); にする
+fn do_while(mut body: Vec<Stmt>, labeled: bool) -> Stmt {
+  let condition = match body.as_slice() {
+    [.., Stmt::If(condition, then_body, else_body), Stmt::Break(None)] if then_body == &[Stmt::Continue(None)] && else_body.is_empty() => {
+      let condition = condition.clone();
+      body.truncate(body.len() - 2);
+      Some(condition)
+    },
+    [.., Stmt::If(condition, then_body, else_body)] if then_body == &[Stmt::Break(None)] && else_body.is_empty() => {
+      let condition = negate(condition.clone());
+      body.pop();
+      Some(condition)
+    },
+    _ => None,
+  };
+  match condition {
+    Some(condition) if !labeled && !has_continue(&body) => Stmt::DoWhile(body, condition),
+    Some(condition) => {
+      body.push(Stmt::If(negate(condition), vec![Stmt::Break(None)], Vec::new()));
+      Stmt::While(Expr::Literal(Literal::Boolean(true)), body)
+    },
+    None => Stmt::While(Expr::Literal(Literal::Boolean(true)), body),
+  }
+}
+
+// 構造化した後の整形
+fn finish(stmts: &mut Vec<Stmt>, scope: &Scope) {
+  synchronized_blocks(stmts);
+  finally_blocks(stmts);
+  simplify(stmts, scope);
+  if matches!(stmts.last(), Some(Stmt::Return(None))) {
+    stmts.pop();
+  }
+}
+
+// monitorenterと、monitorexitして投げ直すハンドラのtryを synchronized にする
+fn synchronized_blocks(stmts: &mut Vec<Stmt>) {
+  for stmt in stmts.iter_mut() {
+    for body in stmt.bodies_mut() {
+      synchronized_blocks(body);
+    }
+  }
+  let mut i = 0;
+  while i < stmts.len() {
+    match synchronized_block(stmts, i) {
+      Some((start, stmt)) => {
+        stmts.splice(start..i + 2, [stmt]);
+        i = start + 1;
+      },
+      None => i += 1,
+    }
+  }
+}
+
+// atのmonitorenterから始まるsynchronizedの文 (前にあるロックの保存から置き換える)
+fn synchronized_block(stmts: &[Stmt], at: usize) -> Option<(usize, Stmt)> {
+  let [Stmt::Monitor(true, object), Stmt::Try(body, catches, finally), ..] = &stmts[at..] else {
+    return None;
+  };
+  let [Catch { types, local: Some(caught), body: handler }] = catches.as_slice() else {
+    return None;
+  };
+  let [Stmt::Monitor(false, lock), Stmt::Throw(Expr::Local(thrown))] = handler.as_slice() else {
+    return None;
+  };
+  if !types.is_empty() || thrown != caught || !matches!(lock, Expr::Local(_)) || !finally.is_empty() {
+    return None;
+  }
+  // ロックする値はdupしてmonitorexitのためにローカル変数に保存してある
+  let (start, value) = match &stmts[..at] {
+    [.., Stmt::Assign(Expr::Stack(stack), value), Stmt::Assign(target, Expr::Stack(saved))]
+      if target == lock && saved == stack && *object == Expr::Stack(*stack) =>
+    {
+      (at - 2, value)
+    },
+    [.., Stmt::Assign(target, value)] if target == lock && value == object => (at - 1, value),
+    _ => return None,
+  };
+  let mut body = body.clone();
+  remove_monitor_exits(&mut body, lock);
+  if body.iter().any(|stmt| stmt_uses(stmt, lock)) {
+    return None;
+  }
+  inline_results(&mut body);
+  Some((start, Stmt::Synchronized(value.clone(), body)))
+}
+
+// catch (Throwable t) { F; throw t; } のハンドラのF
+fn finally_handler(catch: &Catch) -> Option<&[Stmt]> {
+  let Catch { types, local: Some(caught), body } = catch else {
+    return None;
+  };
+  let [handler @ .., Stmt::Throw(Expr::Local(thrown))] = body.as_slice() else {
+    return None;
+  };
+  let uses_caught = handler.iter().any(|stmt| stmt_uses(stmt, &Expr::Local(*caught)));
+  (types.is_empty() && thrown == caught && !handler.is_empty() && !uses_caught).then_some(handler)
+}
+
+// javacはfinallyの本体を、tryの本体とcatchから抜ける経路ごとに複製し、残りの例外は全ての例外を受けるハンドラで実行して投げ直す
+// そのハンドラをfinallyにして、複製を取り除く
+fn finally_blocks(stmts: &mut Vec<Stmt>) {
+  let mut i = 0;
+  while i < stmts.len() {
+    if let Some(finally) = try_finally(&mut stmts[i]) {
+      // 本体の最後から続く複製
+      if stmts[i + 1..].starts_with(&finally) {
+        stmts.drain(i + 1..i + 1 + finally.len());
+      }
+      // 値を返すなら、javacは一時変数に入れてから複製を実行して返す
+      if let Some(Stmt::Return(Some(variable @ Expr::Local(_)))) = stmts.get(i + 1) {
+        let variable = variable.clone();
+        if assigns_last(&mut stmts[i..i + 1], &variable, false) {
+          assigns_last(&mut stmts[i..i + 1], &variable, true);
+          stmts.remove(i + 1);
+        }
+      }
+    }
+    for body in stmts[i].bodies_mut() {
+      finally_blocks(body);
+    }
+    i += 1;
+  }
+}
+
+fn try_finally(stmt: &mut Stmt) -> Option<Vec<Stmt>> {
+  let Stmt::Try(body, catches, finally) = (match stmt {
+    Stmt::Labeled(_, stmt) => &mut **stmt,
+    stmt => stmt,
+  }) else {
+    return None;
+  };
+  let handler = finally_handler(catches.last()?)?.to_vec();
+  if !finally.is_empty() {
+    return None;
+  }
+  catches.pop();
+  strip_copies(body, &handler, true);
+  for catch in catches.iter_mut() {
+    // catchの本体も同じハンドラで覆われていて、内側のtryになっている
+    if let Some(Stmt::Try(_, inner_catches, inner_finally)) = catch.body.first()
+      && let [inner_catch] = inner_catches.as_slice()
+      && inner_finally.is_empty()
+      && finally_handler(inner_catch) == Some(&handler)
+    {
+      let Stmt::Try(inner, ..) = catch.body.remove(0) else {
+        unreachable!();
+      };
+      catch.body.splice(0..0, inner);
+    }
+    strip_copies(&mut catch.body, &handler, true);
+  }
+  *finally = handler.clone();
+  Some(handler)
+}
+
+// return、break、continueの直前 (at_endなら列の最後も) にあるfinallyの複製を取り除く
+fn strip_copies(body: &mut Vec<Stmt>, finally: &[Stmt], at_end: bool) {
+  for stmt in body.iter_mut() {
+    for nested in stmt.bodies_mut() {
+      strip_copies(nested, finally, false);
+    }
+  }
+  let mut k = finally.len();
+  while k < body.len() {
+    if !matches!(body[k], Stmt::Return(_) | Stmt::Break(_) | Stmt::Continue(_)) || body[k - finally.len()..k] != *finally {
+      k += 1;
+      continue;
+    }
+    body.drain(k - finally.len()..k);
+    k -= finally.len();
+    // 複製の前に戻り値を入れた一時変数は、複製がなくなれば要らない
+    if k > 0
+      && let (Stmt::Assign(target, value), Stmt::Return(Some(variable @ Expr::Local(_)))) = (&body[k - 1], &body[k])
+      && target == variable
+      && !finally.iter().any(|stmt| stmt_uses(stmt, variable))
+    {
+      body[k - 1] = Stmt::Return(Some(value.clone()));
+      body.remove(k);
+      k -= 1;
+    }
+    k += finally.len().max(1);
+  }
+  if at_end && body.ends_with(finally) {
+    body.truncate(body.len() - finally.len());
+  } else if at_end && let Some(Stmt::If(condition, then_body, else_body)) = body.last_mut() && !else_body.is_empty() {
+    // 両方の節が本体の最後に落ちるなら、それぞれの最後にある複製を除く
+    strip_copies(then_body, finally, true);
+    strip_copies(else_body, finally, true);
+    if then_body.is_empty() {
+      *condition = negate(condition.clone());
+      *then_body = std::mem::take(else_body);
+    }
+  }
+}
+
+fn remove_monitor_exits(stmts: &mut Vec<Stmt>, lock: &Expr) {
+  stmts.retain(|stmt| !matches!(stmt, Stmt::Monitor(false, value) if value == lock));
+  for stmt in stmts.iter_mut() {
+    for body in stmt.bodies_mut() {
+      remove_monitor_exits(body, lock);
+    }
+    // monitorexitだけだったthen節は空になる
+    if let Stmt::If(condition, then_body, else_body) = stmt
+      && then_body.is_empty()
+      && !else_body.is_empty()
+    {
+      *stmt = Stmt::If(negate(condition.clone()), std::mem::take(else_body), Vec::new());
+    }
+  }
+}
+
+// monitorexitの前に$kに退避した戻り値を、returnの式に戻す
+fn inline_results(stmts: &mut Vec<Stmt>) {
+  for stmt in stmts.iter_mut() {
+    for body in stmt.bodies_mut() {
+      inline_results(body);
+    }
+  }
+  let mut i = 1;
+  while i < stmts.len() {
+    if let (Stmt::Assign(Expr::Stack(stack), value), Stmt::Return(Some(Expr::Stack(returned)))) = (&stmts[i - 1], &stmts[i])
+      && stack == returned
+    {
+      stmts[i] = Stmt::Return(Some(value.clone()));
+      stmts.remove(i - 1);
+    } else {
+      i += 1;
+    }
+  }
+}
+
+fn simplify(stmts: &mut Vec<Stmt>, scope: &Scope) {
+  for stmt in stmts.iter_mut() {
+    for expr in stmt.exprs_mut() {
+      simplify_expr(expr, scope);
+    }
+    for body in stmt.bodies_mut() {
+      simplify(body, scope);
+    }
+  }
+  sink_returns(stmts);
+  for_loops(stmts);
+}
+
+// if (c) { $0 = a; } else { $0 = b; } return $0; の return を分岐の中に移す (tryの本体とcatchも同様)
+fn sink_returns(stmts: &mut Vec<Stmt>) {
+  let [.., Stmt::If(..) | Stmt::Try(..), Stmt::Return(Some(variable @ Expr::Stack(_)))] = stmts.as_slice() else {
+    return;
+  };
+  let variable = variable.clone();
+  let at = stmts.len() - 2;
+  if assigns_last(&mut stmts[at..at + 1], &variable, false) {
+    assigns_last(&mut stmts[at..at + 1], &variable, true);
+    stmts.pop();
+    tidy_returns(stmts);
+  }
+}
+
+// 移したreturnで0か1を返す分岐は条件式にして、elseは外に出す
+fn tidy_returns(stmts: &mut Vec<Stmt>) {
+  let Some(Stmt::If(condition, then_body, else_body)) = stmts.last_mut() else {
+    return;
+  };
+  tidy_returns(then_body);
+  tidy_returns(else_body);
+  let boolean = |body: &[Stmt]| match body {
+    [Stmt::Return(Some(value @ Expr::Literal(Literal::Int(0 | 1))))] => Some(value.clone()),
+    _ => None,
+  };
+  if let (Some(then_value), Some(else_value)) = (boolean(then_body), boolean(else_body))
+    && then_value != else_value
+  {
+    let value = Expr::Ternary(Box::new(condition.clone()), Box::new(then_value), Box::new(else_value));
+    *stmts.last_mut().expect("not empty") = Stmt::Return(Some(value));
+  } else if !else_body.is_empty() && ends_abruptly(then_body) {
+    let else_body = std::mem::take(else_body);
+    stmts.extend(else_body);
+    tidy_returns(stmts);
+  }
+}
+
+// 全ての経路がvariableへの代入で終わるか (applyならその代入をreturnにする)
+fn assigns_last(body: &mut [Stmt], variable: &Expr, apply: bool) -> bool {
+  match body.last_mut() {
+    Some(Stmt::Assign(target, value)) if target == variable => {
+      if apply {
+        let value = value.clone();
+        *body.last_mut().expect("not empty") = Stmt::Return(Some(value));
+      }
+      true
+    },
+    Some(Stmt::If(_, then_body, else_body)) if !else_body.is_empty() => {
+      assigns_last(then_body, variable, apply) && assigns_last(else_body, variable, apply)
+    },
+    Some(Stmt::Try(body, catches, finally)) if !finally.iter().any(|stmt| stmt_uses(stmt, variable)) => {
+      assigns_last(body, variable, apply) && catches.iter_mut().all(|catch| assigns_last(&mut catch.body, variable, apply))
+    },
+    Some(stmt) => ends_abruptly(std::slice::from_ref(stmt)) && !matches!(stmt, Stmt::Break(_) | Stmt::Continue(_) | Stmt::Goto(_)),
+    None => false,
+  }
+}
+
+// x != 0 のようなbooleanとintの比較を戻す
+fn simplify_expr(expr: &mut Expr, scope: &Scope) {
+  for child in expr.children_mut() {
+    simplify_expr(child, scope);
+  }
+  if let Expr::Binary(op @ ("==" | "!="), left, right) = expr
+    && **right == Expr::int(0)
+  {
+    let boolean = match &**left {
+      Expr::Ternary(_, then_value, else_value)
+        if matches!((&**then_value, &**else_value), (Expr::Literal(Literal::Int(1)), Expr::Literal(Literal::Int(0))) | (Expr::Literal(Literal::Int(0)), Expr::Literal(Literal::Int(1)))) =>
+      {
+        Some(coerce((**left).clone(), Some(&FieldType::Boolean)))
+      },
+      value if scope.expr_type(value) == Some(FieldType::Boolean) => Some(value.clone()),
+      _ => None,
+    };
+    if let Some(boolean) = boolean {
+      *expr = if *op == "!=" { boolean } else { negate(boolean) };
+    }
+  }
+}
+
+// 変数の初期化、条件、最後の更新がそろったwhileをforにする
+fn for_loops(stmts: &mut Vec<Stmt>) {
+  let mut i = 1;
+  while i < stmts.len() {
+    if let Stmt::While(condition, body) = &stmts[i]
+      && let Some(Stmt::Assign(variable @ Expr::Local(_), _)) = body.last()
+      && matches!(&stmts[i - 1], Stmt::Assign(target, _) if target == variable)
+      && condition.count(variable) > 0
+      && !has_continue(&body[..body.len() - 1])
+      && !stmts[i + 1..].iter().any(|stmt| stmt_uses(stmt, variable))
+    {
+      let Stmt::While(condition, mut body) = stmts.remove(i) else {
+        unreachable!();
+      };
+      let update = body.pop().map(Box::new);
+      let init = Box::new(stmts.remove(i - 1));
+      stmts.insert(i - 1, Stmt::For(Some(init), condition, update, body));
+    } else {
+      i += 1;
+    }
+  }
+}
+
+fn stmt_uses(stmt: &Stmt, variable: &Expr) -> bool {
+  let mut stmt = stmt.clone();
+  stmt.exprs_mut().into_iter().any(|expr| expr.count(variable) > 0) || stmt.bodies_mut().into_iter().any(|body| body.iter().any(|stmt| stmt_uses(stmt, variable)))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn local(index: usize) -> Expr {
+    Expr::Local(index)
+  }
+
+  // synchronized (x) { return a; } のjavacの出力をそのまま構造化したもの
+  #[test]
+  fn monitor_try_becomes_synchronized() {
+    let lock = local(2);
+    let mut stmts = vec![
+      Stmt::Assign(Expr::Stack(0), local(0)),
+      Stmt::Assign(lock.clone(), Expr::Stack(0)),
+      Stmt::Monitor(true, Expr::Stack(0)),
+      Stmt::Try(
+        vec![
+          Stmt::If(local(1), vec![Stmt::Monitor(false, lock.clone())], vec![Stmt::Expr(local(1))]),
+          Stmt::Assign(Expr::Stack(0), local(1)),
+          Stmt::Monitor(false, lock.clone()),
+          Stmt::Return(Some(Expr::Stack(0))),
+        ],
+        vec![Catch { types: Vec::new(), local: Some(3), body: vec![Stmt::Monitor(false, lock), Stmt::Throw(local(3))] }],
+        Vec::new(),
+      ),
+    ];
+    synchronized_blocks(&mut stmts);
+    assert_eq!(
+      stmts,
+      [Stmt::Synchronized(local(0), vec![
+        Stmt::If(negate(local(1)), vec![Stmt::Expr(local(1))], Vec::new()),
+        Stmt::Return(Some(local(1))),
+      ])]
+    );
+  }
+
+  // 例外を投げ直さないハンドラはsynchronizedにしない
+  #[test]
+  fn other_handlers_are_not_synchronized() {
+    let stmts = vec![
+      Stmt::Assign(local(1), Expr::This),
+      Stmt::Monitor(true, Expr::This),
+      Stmt::Try(
+        vec![Stmt::Monitor(false, local(1))],
+        vec![Catch { types: Vec::new(), local: Some(2), body: vec![Stmt::Monitor(false, local(1)), Stmt::Return(None)] }],
+        Vec::new(),
+      ),
+    ];
+    let mut result = stmts.clone();
+    synchronized_blocks(&mut result);
+    assert_eq!(result, stmts);
+  }
+
+  // try { return a; } catch (E e) { return b; } finally { f(); } のjavacの出力をそのまま構造化したもの
+  #[test]
+  fn finally_copies_become_finally_block() {
+    let finally = vec![Stmt::Expr(local(1))];
+    let rethrow = |index| Catch { types: Vec::new(), local: Some(index), body: [finally.clone(), vec![Stmt::Throw(local(index))]].concat() };
+    let mut stmts = vec![Stmt::Try(
+      [vec![Stmt::Assign(local(2), local(0))], finally.clone(), vec![Stmt::Return(Some(local(2)))]].concat(),
+      vec![
+        Catch {
+          types: vec!["E".to_string()],
+          local: Some(2),
+          body: vec![Stmt::Try([vec![Stmt::Assign(local(3), local(5))], finally.clone(), vec![Stmt::Return(Some(local(3)))]].concat(), vec![rethrow(4)], Vec::new())],
+        },
+        rethrow(4),
+      ],
+      Vec::new(),
+    )];
+    finally_blocks(&mut stmts);
+    assert_eq!(
+      stmts,
+      [Stmt::Try(
+        vec![Stmt::Return(Some(local(0)))],
+        vec![Catch { types: vec!["E".to_string()], local: Some(2), body: vec![Stmt::Return(Some(local(5)))] }],
+        finally,
+      )]
+    );
+  }
+
+  // 投げ直す前に例外を使うハンドラはfinallyにしない
+  #[test]
+  fn handlers_using_exception_are_not_finally() {
+    let stmts = vec![Stmt::Try(
+      vec![Stmt::Expr(local(0)), Stmt::Expr(local(1))],
+      vec![Catch { types: Vec::new(), local: Some(2), body: vec![Stmt::Expr(local(2)), Stmt::Throw(local(2))] }],
+      Vec::new(),
+    )];
+    let mut result = stmts.clone();
+    finally_blocks(&mut result);
+    assert_eq!(result, stmts);
+  }
+
+  #[test]
+  fn trailing_breaks_are_removed_from_labeled_block() {
+    let label = label_name(0);
+    let body = vec![Stmt::If(local(0), vec![Stmt::Expr(local(1)), Stmt::Break(Some(label.clone()))], vec![Stmt::Break(Some(label.clone()))])];
+    assert_eq!(labeled_block(label.clone(), body), [Stmt::If(local(0), vec![Stmt::Expr(local(1))], Vec::new())]);
+
+    // 途中から抜けるbreakが残ればブロックにする
+    let body = vec![Stmt::If(local(0), vec![Stmt::Break(Some(label.clone()))], Vec::new()), Stmt::Expr(local(1)), Stmt::Break(Some(label.clone()))];
+    assert_eq!(
+      labeled_block(label.clone(), body),
+      [Stmt::Labeled(label.clone(), Box::new(Stmt::Block(vec![Stmt::If(local(0), vec![Stmt::Break(Some(label))], Vec::new()), Stmt::Expr(local(1))])))]
+    );
+  }
+}
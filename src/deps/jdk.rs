@@ -0,0 +1,329 @@
+use std::{
+  collections::HashMap,
+  env, fs, io,
+  path::{Path, PathBuf},
+};
+
+use crate::{deps::package_name, util::zip::ZipArchive};
+
+// ブートクラスパスの指定がないときに参照先を探すJDK
+
+// JDK 17の各モジュールが公開しているパッケージ (パッケージ名の順)
+static EXPORTED_PACKAGES: &[(&str, &str)] = &[
+  ("com/sun/jarsigner", "jdk.jartool"),
+  ("com/sun/java/accessibility/util", "jdk.accessibility"),
+  ("com/sun/jdi", "jdk.jdi"),
+  ("com/sun/jdi/connect", "jdk.jdi"),
+  ("com/sun/jdi/connect/spi", "jdk.jdi"),
+  ("com/sun/jdi/event", "jdk.jdi"),
+  ("com/sun/jdi/request", "jdk.jdi"),
+  ("com/sun/management", "jdk.management"),
+  ("com/sun/net/httpserver", "jdk.httpserver"),
+  ("com/sun/net/httpserver/spi", "jdk.httpserver"),
+  ("com/sun/nio/file", "jdk.unsupported"),
+  ("com/sun/nio/sctp", "jdk.sctp"),
+  ("com/sun/security/auth", "jdk.security.auth"),
+  ("com/sun/security/auth/callback", "jdk.security.auth"),
+  ("com/sun/security/auth/login", "jdk.security.auth"),
+  ("com/sun/security/auth/module", "jdk.security.auth"),
+  ("com/sun/security/jgss", "jdk.security.jgss"),
+  ("com/sun/source/doctree", "jdk.compiler"),
+  ("com/sun/source/tree", "jdk.compiler"),
+  ("com/sun/source/util", "jdk.compiler"),
+  ("com/sun/tools/attach", "jdk.attach"),
+  ("com/sun/tools/attach/spi", "jdk.attach"),
+  ("com/sun/tools/javac", "jdk.compiler"),
+  ("com/sun/tools/jconsole", "jdk.jconsole"),
+  ("java/applet", "java.desktop"),
+  ("java/awt", "java.desktop"),
+  ("java/awt/color", "java.desktop"),
+  ("java/awt/datatransfer", "java.datatransfer"),
+  ("java/awt/desktop", "java.desktop"),
+  ("java/awt/dnd", "java.desktop"),
+  ("java/awt/event", "java.desktop"),
+  ("java/awt/font", "java.desktop"),
+  ("java/awt/geom", "java.desktop"),
+  ("java/awt/im", "java.desktop"),
+  ("java/awt/im/spi", "java.desktop"),
+  ("java/awt/image", "java.desktop"),
+  ("java/awt/image/renderable", "java.desktop"),
+  ("java/awt/print", "java.desktop"),
+  ("java/beans", "java.desktop"),
+  ("java/beans/beancontext", "java.desktop"),
+  ("java/io", "java.base"),
+  ("java/lang", "java.base"),
+  ("java/lang/annotation", "java.base"),
+  ("java/lang/constant", "java.base"),
+  ("java/lang/instrument", "java.instrument"),
+  ("java/lang/invoke", "java.base"),
+  ("java/lang/management", "java.management"),
+  ("java/lang/module", "java.base"),
+  ("java/lang/ref", "java.base"),
+  ("java/lang/reflect", "java.base"),
+  ("java/lang/runtime", "java.base"),
+  ("java/math", "java.base"),
+  ("java/net", "java.base"),
+  ("java/net/http", "java.net.http"),
+  ("java/net/spi", "java.base"),
+  ("java/nio", "java.base"),
+  ("java/nio/channels", "java.base"),
+  ("java/nio/channels/spi", "java.base"),
+  ("java/nio/charset", "java.base"),
+  ("java/nio/charset/spi", "java.base"),
+  ("java/nio/file", "java.base"),
+  ("java/nio/file/attribute", "java.base"),
+  ("java/nio/file/spi", "java.base"),
+  ("java/rmi", "java.rmi"),
+  ("java/rmi/dgc", "java.rmi"),
+  ("java/rmi/registry", "java.rmi"),
+  ("java/rmi/server", "java.rmi"),
+  ("java/security", "java.base"),
+  ("java/security/cert", "java.base"),
+  ("java/security/interfaces", "java.base"),
+  ("java/security/spec", "java.base"),
+  ("java/sql", "java.sql"),
+  ("java/text", "java.base"),
+  ("java/text/spi", "java.base"),
+  ("java/time", "java.base"),
+  ("java/time/chrono", "java.base"),
+  ("java/time/format", "java.base"),
+  ("java/time/temporal", "java.base"),
+  ("java/time/zone", "java.base"),
+  ("java/util", "java.base"),
+  ("java/util/concurrent", "java.base"),
+  ("java/util/concurrent/atomic", "java.base"),
+  ("java/util/concurrent/locks", "java.base"),
+  ("java/util/function", "java.base"),
+  ("java/util/jar", "java.base"),
+  ("java/util/logging", "java.logging"),
+  ("java/util/prefs", "java.prefs"),
+  ("java/util/random", "java.base"),
+  ("java/util/regex", "java.base"),
+  ("java/util/spi", "java.base"),
+  ("java/util/stream", "java.base"),
+  ("java/util/zip", "java.base"),
+  ("javax/accessibility", "java.desktop"),
+  ("javax/annotation/processing", "java.compiler"),
+  ("javax/crypto", "java.base"),
+  ("javax/crypto/interfaces", "java.base"),
+  ("javax/crypto/spec", "java.base"),
+  ("javax/imageio", "java.desktop"),
+  ("javax/imageio/event", "java.desktop"),
+  ("javax/imageio/metadata", "java.desktop"),
+  ("javax/imageio/plugins/bmp", "java.desktop"),
+  ("javax/imageio/plugins/jpeg", "java.desktop"),
+  ("javax/imageio/plugins/tiff", "java.desktop"),
+  ("javax/imageio/spi", "java.desktop"),
+  ("javax/imageio/stream", "java.desktop"),
+  ("javax/lang/model", "java.compiler"),
+  ("javax/lang/model/element", "java.compiler"),
+  ("javax/lang/model/type", "java.compiler"),
+  ("javax/lang/model/util", "java.compiler"),
+  ("javax/management", "java.management"),
+  ("javax/management/loading", "java.management"),
+  ("javax/management/modelmbean", "java.management"),
+  ("javax/management/monitor", "java.management"),
+  ("javax/management/openmbean", "java.management"),
+  ("javax/management/relation", "java.management"),
+  ("javax/management/remote", "java.management"),
+  ("javax/management/remote/rmi", "java.management.rmi"),
+  ("javax/management/timer", "java.management"),
+  ("javax/naming", "java.naming"),
+  ("javax/naming/directory", "java.naming"),
+  ("javax/naming/event", "java.naming"),
+  ("javax/naming/ldap", "java.naming"),
+  ("javax/naming/ldap/spi", "java.naming"),
+  ("javax/naming/spi", "java.naming"),
+  ("javax/net", "java.base"),
+  ("javax/net/ssl", "java.base"),
+  ("javax/print", "java.desktop"),
+  ("javax/print/attribute", "java.desktop"),
+  ("javax/print/attribute/standard", "java.desktop"),
+  ("javax/print/event", "java.desktop"),
+  ("javax/rmi/ssl", "java.rmi"),
+  ("javax/script", "java.scripting"),
+  ("javax/security/auth", "java.base"),
+  ("javax/security/auth/callback", "java.base"),
+  ("javax/security/auth/kerberos", "java.security.jgss"),
+  ("javax/security/auth/login", "java.base"),
+  ("javax/security/auth/spi", "java.base"),
+  ("javax/security/auth/x500", "java.base"),
+  ("javax/security/cert", "java.base"),
+  ("javax/security/sasl", "java.security.sasl"),
+  ("javax/smartcardio", "java.smartcardio"),
+  ("javax/sound/midi", "java.desktop"),
+  ("javax/sound/midi/spi", "java.desktop"),
+  ("javax/sound/sampled", "java.desktop"),
+  ("javax/sound/sampled/spi", "java.desktop"),
+  ("javax/sql", "java.sql"),
+  ("javax/sql/rowset", "java.sql.rowset"),
+  ("javax/sql/rowset/serial", "java.sql.rowset"),
+  ("javax/sql/rowset/spi", "java.sql.rowset"),
+  ("javax/swing", "java.desktop"),
+  ("javax/swing/border", "java.desktop"),
+  ("javax/swing/colorchooser", "java.desktop"),
+  ("javax/swing/event", "java.desktop"),
+  ("javax/swing/filechooser", "java.desktop"),
+  ("javax/swing/plaf", "java.desktop"),
+  ("javax/swing/plaf/basic", "java.desktop"),
+  ("javax/swing/plaf/metal", "java.desktop"),
+  ("javax/swing/plaf/multi", "java.desktop"),
+  ("javax/swing/plaf/nimbus", "java.desktop"),
+  ("javax/swing/plaf/synth", "java.desktop"),
+  ("javax/swing/table", "java.desktop"),
+  ("javax/swing/text", "java.desktop"),
+  ("javax/swing/text/html", "java.desktop"),
+  ("javax/swing/text/html/parser", "java.desktop"),
+  ("javax/swing/text/rtf", "java.desktop"),
+  ("javax/swing/tree", "java.desktop"),
+  ("javax/swing/undo", "java.desktop"),
+  ("javax/tools", "java.compiler"),
+  ("javax/transaction/xa", "java.transaction.xa"),
+  ("javax/xml", "java.xml"),
+  ("javax/xml/catalog", "java.xml"),
+  ("javax/xml/crypto", "java.xml.crypto"),
+  ("javax/xml/crypto/dom", "java.xml.crypto"),
+  ("javax/xml/crypto/dsig", "java.xml.crypto"),
+  ("javax/xml/crypto/dsig/dom", "java.xml.crypto"),
+  ("javax/xml/crypto/dsig/keyinfo", "java.xml.crypto"),
+  ("javax/xml/crypto/dsig/spec", "java.xml.crypto"),
+  ("javax/xml/datatype", "java.xml"),
+  ("javax/xml/namespace", "java.xml"),
+  ("javax/xml/parsers", "java.xml"),
+  ("javax/xml/stream", "java.xml"),
+  ("javax/xml/stream/events", "java.xml"),
+  ("javax/xml/stream/util", "java.xml"),
+  ("javax/xml/transform", "java.xml"),
+  ("javax/xml/transform/dom", "java.xml"),
+  ("javax/xml/transform/sax", "java.xml"),
+  ("javax/xml/transform/stax", "java.xml"),
+  ("javax/xml/transform/stream", "java.xml"),
+  ("javax/xml/validation", "java.xml"),
+  ("javax/xml/xpath", "java.xml"),
+  ("jdk/dynalink", "jdk.dynalink"),
+  ("jdk/dynalink/beans", "jdk.dynalink"),
+  ("jdk/dynalink/linker", "jdk.dynalink"),
+  ("jdk/dynalink/linker/support", "jdk.dynalink"),
+  ("jdk/dynalink/support", "jdk.dynalink"),
+  ("jdk/javadoc/doclet", "jdk.javadoc"),
+  ("jdk/jfr", "jdk.jfr"),
+  ("jdk/jfr/consumer", "jdk.jfr"),
+  ("jdk/jshell", "jdk.jshell"),
+  ("jdk/jshell/execution", "jdk.jshell"),
+  ("jdk/jshell/spi", "jdk.jshell"),
+  ("jdk/jshell/tool", "jdk.jshell"),
+  ("jdk/management/jfr", "jdk.management.jfr"),
+  ("jdk/net", "jdk.net"),
+  ("jdk/nio", "jdk.net"),
+  ("jdk/nio/mapmode", "jdk.nio.mapmode"),
+  ("jdk/security/jarsigner", "jdk.jartool"),
+  ("jdk/swing/interop", "jdk.unsupported.desktop"),
+  ("netscape/javascript", "jdk.jsobject"),
+  ("org/ietf/jgss", "java.security.jgss"),
+  ("org/w3c/dom", "java.xml"),
+  ("org/w3c/dom/bootstrap", "java.xml"),
+  ("org/w3c/dom/css", "jdk.xml.dom"),
+  ("org/w3c/dom/events", "java.xml"),
+  ("org/w3c/dom/html", "jdk.xml.dom"),
+  ("org/w3c/dom/ls", "java.xml"),
+  ("org/w3c/dom/ranges", "java.xml"),
+  ("org/w3c/dom/stylesheets", "jdk.xml.dom"),
+  ("org/w3c/dom/traversal", "java.xml"),
+  ("org/w3c/dom/views", "java.xml"),
+  ("org/w3c/dom/xpath", "jdk.xml.dom"),
+  ("org/xml/sax", "java.xml"),
+  ("org/xml/sax/ext", "java.xml"),
+  ("org/xml/sax/helpers", "java.xml"),
+  ("sun/misc", "jdk.unsupported"),
+  ("sun/reflect", "jdk.unsupported"),
+];
+
+pub enum Jdk {
+  // jmodsに含まれるクラスと、そのモジュール名
+  Image(HashMap<String, String>),
+  // jmodsが見つからなければ、公開パッケージの表でモジュールを決める
+  Packages,
+}
+
+// JAVA_HOME、なければPATHにあるjavaの実体の2つ上のディレクトリ
+fn java_home() -> Option<PathBuf> {
+  if let Some(home) = env::var_os("JAVA_HOME") {
+    return Some(PathBuf::from(home));
+  }
+  let java = env::split_paths(&env::var_os("PATH")?).map(|dir| dir.join("java")).find(|java| java.is_file())?;
+  Some(fs::canonicalize(java).ok()?.parent()?.parent()?.to_path_buf())
+}
+
+pub fn package_module(package: &str) -> Option<&'static str> {
+  EXPORTED_PACKAGES.binary_search_by(|(name, _)| (*name).cmp(package)).ok().map(|index| EXPORTED_PACKAGES[index].1)
+}
+
+impl Jdk {
+  pub fn locate() -> Jdk {
+    java_home().and_then(|home| Jdk::from_jmods(&home.join("jmods")).ok()).unwrap_or(Jdk::Packages)
+  }
+
+  pub fn from_jmods(dir: &Path) -> io::Result<Jdk> {
+    let mut classes = HashMap::new();
+    for entry in fs::read_dir(dir)?.flatten() {
+      let path = entry.path();
+      let Some(module) = path.file_name().and_then(|name| name.to_str()).and_then(|name| name.strip_suffix(".jmod")) else {
+        continue;
+      };
+      // jmodは4バイトのヘッダ ("JM"とバージョン) の後にZIPが続く
+      let mut data = fs::read(&path)?;
+      if !data.starts_with(b"JM") {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}: not a jmod file", path.display())));
+      }
+      data.drain(..4);
+      let archive = ZipArchive::from_bytes(data)?;
+      for entry in &archive.entries {
+        if let Some(name) = entry.name.strip_prefix("classes/").and_then(|name| name.strip_suffix(".class"))
+          && name != "module-info"
+        {
+          classes.insert(name.to_string(), module.to_string());
+        }
+      }
+    }
+    if classes.is_empty() {
+      return Err(io::Error::new(io::ErrorKind::NotFound, format!("{}: no jmod files", dir.display())));
+    }
+    Ok(Jdk::Image(classes))
+  }
+
+  // クラスを含むモジュール
+  pub fn module(&self, class_name: &str) -> Option<&str> {
+    match self {
+      Jdk::Image(classes) => classes.get(class_name).map(String::as_str),
+      Jdk::Packages => package_module(package_name(class_name)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn exported_packages_are_sorted() {
+    assert!(EXPORTED_PACKAGES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+  }
+
+  #[test]
+  fn classes_are_mapped_to_modules() {
+    let packages = Jdk::Packages;
+    assert_eq!(packages.module("java/lang/String"), Some("java.base"));
+    assert_eq!(packages.module("javax/swing/JFrame"), Some("java.desktop"));
+    assert_eq!(packages.module("java/sql/Connection"), Some("java.sql"));
+    assert_eq!(packages.module("sun/misc/Unsafe"), Some("jdk.unsupported"));
+    assert_eq!(packages.module("jdk/jfr/Event"), Some("jdk.jfr"));
+    assert_eq!(packages.module("java/lang/reflect/Method"), Some("java.base"));
+    assert_eq!(packages.module("java/nonexistent/Foo"), None);
+    assert_eq!(packages.module("com/example/Foo"), None);
+
+    // jmodsがあればクラス単位で探すので、存在しないクラスはJDKのものにしない
+    let image = Jdk::Image(HashMap::from([("java/lang/String".to_string(), "java.base".to_string())]));
+    assert_eq!(image.module("java/lang/String"), Some("java.base"));
+    assert_eq!(image.module("java/lang/Strin"), None);
+  }
+}
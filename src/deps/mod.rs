@@ -0,0 +1,442 @@
+pub mod jdk;
+pub mod report;
+
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fs, io,
+  path::Path,
+};
+
+use crate::{
+  class_leader,
+  deps::jdk::Jdk,
+  structure::class::{
+    Annotation, ClassFile, ClassFileAttribute, CodeNestedAttribute, Constant, ConstantPool, ElementValue, ElementValueEnum,
+    FieldInfoAttribute, MethodInfoAttribute, RecordComponentInfoAttribute, TypeAnnotation,
+  },
+  util::{
+    class_path::{ClassPath, ClassPathEntry},
+    descriptor::{FieldType, MethodDescriptor},
+    signature::{ClassSignature, MethodSignature, TypeArgument, TypeParameter, TypeSignature},
+  },
+};
+
+// クラスファイルが参照するクラスの収集と、パッケージやモジュールごとの依存関係の集計 (jdeps相当)
+
+// 参照先のクラスが見つかった場所
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Location {
+  // 解析対象の入力 (ラベルはモジュール名かファイル名)
+  Input(String),
+  ClassPath(String),
+  // ブートクラスパスの指定がないときのJDKのモジュール
+  Jdk(String),
+  Missing,
+}
+
+impl Location {
+  pub fn label(&self) -> &str {
+    match self {
+      Location::Input(label) | Location::ClassPath(label) | Location::Jdk(label) => label,
+      Location::Missing => "not found",
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct ClassDependencies {
+  pub name: String,
+  // 入力のラベル
+  pub source: String,
+  pub references: BTreeSet<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Dependencies {
+  pub classes: Vec<ClassDependencies>,
+  pub locations: BTreeMap<String, Location>,
+}
+
+pub fn package_name(class_name: &str) -> &str {
+  class_name.rfind('/').map(|at| &class_name[..at]).unwrap_or("")
+}
+
+struct Collector<'a> {
+  pool: &'a ConstantPool,
+  references: BTreeSet<String>,
+}
+
+impl Collector<'_> {
+  fn add_field_type(&mut self, field_type: &FieldType) {
+    match field_type {
+      FieldType::Object(name) => {
+        self.references.insert(name.clone());
+      },
+      FieldType::Array(component) => self.add_field_type(component),
+      _ => {},
+    }
+  }
+
+  // CONSTANT_Classの名前 (配列なら要素の型)
+  fn add_class_name(&mut self, name: &str) {
+    if name.starts_with('[') {
+      if let Ok(field_type) = FieldType::parse(name) {
+        self.add_field_type(&field_type);
+      }
+    } else if !name.is_empty() {
+      self.references.insert(name.to_string());
+    }
+  }
+
+  fn add_class(&mut self, index: u16) {
+    if index != 0
+      && let Ok(name) = self.pool.get_class_name(index)
+    {
+      self.add_class_name(&name);
+    }
+  }
+
+  // フィールドとメソッドのどちらの記述子も受け付ける
+  fn add_descriptor(&mut self, descriptor: &str) {
+    if descriptor.starts_with('(') {
+      if let Ok(method) = MethodDescriptor::parse(descriptor) {
+        for parameter in &method.parameters {
+          self.add_field_type(parameter);
+        }
+        if let Some(return_type) = &method.return_type {
+          self.add_field_type(return_type);
+        }
+      }
+    } else if let Ok(field_type) = FieldType::parse(descriptor) {
+      self.add_field_type(&field_type);
+    }
+  }
+
+  fn add_descriptor_index(&mut self, index: u16) {
+    if let Ok(descriptor) = self.pool.get_utf8(index) {
+      self.add_descriptor(&descriptor);
+    }
+  }
+
+  fn add_type_signature(&mut self, signature: &TypeSignature) {
+    match signature {
+      TypeSignature::Class(segments) => {
+        // ネストしたクラスは'$'でつなぐ
+        let name: Vec<&str> = segments.iter().map(|segment| segment.name.as_str()).collect();
+        self.references.insert(name.join("$"));
+        for argument in segments.iter().flat_map(|segment| &segment.arguments) {
+          match argument {
+            TypeArgument::Any => {},
+            TypeArgument::Exact(signature) | TypeArgument::Extends(signature) | TypeArgument::Super(signature) => {
+              self.add_type_signature(signature)
+            },
+          }
+        }
+      },
+      TypeSignature::Array(component) => self.add_type_signature(component),
+      TypeSignature::Base(_) | TypeSignature::Variable(_) => {},
+    }
+  }
+
+  fn add_type_parameters(&mut self, parameters: &[TypeParameter]) {
+    for parameter in parameters {
+      for bound in parameter.class_bound.iter().chain(&parameter.interface_bounds) {
+        self.add_type_signature(bound);
+      }
+    }
+  }
+
+  // Signature属性 (クラス、メソッド、フィールドの順に試す)
+  fn add_signature(&mut self, index: u16) {
+    let Ok(signature) = self.pool.get_utf8(index) else {
+      return;
+    };
+    if signature.starts_with('(') || (signature.starts_with('<') && signature.contains('(')) {
+      if let Ok(method) = MethodSignature::parse(&signature) {
+        self.add_type_parameters(&method.type_parameters);
+        for signature in method.parameters.iter().chain(&method.return_type).chain(&method.throws) {
+          self.add_type_signature(signature);
+        }
+      }
+    } else if let Ok(field) = TypeSignature::parse(&signature) {
+      self.add_type_signature(&field);
+    } else if let Ok(class) = ClassSignature::parse(&signature) {
+      self.add_type_parameters(&class.type_parameters);
+      for signature in std::iter::once(&class.superclass).chain(&class.interfaces) {
+        self.add_type_signature(signature);
+      }
+    }
+  }
+
+  fn add_element_value(&mut self, value: &ElementValue) {
+    match &value.value {
+      ElementValueEnum::ConstValueIndex(_) => {},
+      ElementValueEnum::EnumConstValue { type_name_index, .. } => self.add_descriptor_index(*type_name_index),
+      // クラスリテラルは戻り値の記述子 (voidならV)
+      ElementValueEnum::ClassInfoIndex(index) => self.add_descriptor_index(*index),
+      ElementValueEnum::AnnotationValue(annotation) => self.add_annotation(annotation),
+      ElementValueEnum::ArrayValue { values, .. } => {
+        for value in values {
+          self.add_element_value(value);
+        }
+      },
+    }
+  }
+
+  fn add_annotation(&mut self, annotation: &Annotation) {
+    self.add_descriptor_index(annotation.type_index);
+    for pair in &annotation.element_value_pairs {
+      self.add_element_value(&pair.value);
+    }
+  }
+
+  fn add_annotations(&mut self, annotations: &[Annotation]) {
+    for annotation in annotations {
+      self.add_annotation(annotation);
+    }
+  }
+
+  fn add_type_annotations(&mut self, annotations: &[TypeAnnotation]) {
+    for annotation in annotations {
+      self.add_descriptor_index(annotation.type_index);
+      for pair in &annotation.element_value_pairs {
+        self.add_element_value(&pair.value);
+      }
+    }
+  }
+
+  fn add_constant_pool(&mut self) {
+    for constant in &self.pool.constants {
+      match constant {
+        Constant::Class { name_index } => {
+          if let Ok(name) = self.pool.get_utf8(*name_index) {
+            self.add_class_name(&name);
+          }
+        },
+        Constant::NameAndType { descriptor_index, .. } | Constant::MethodType { descriptor_index } => {
+          self.add_descriptor_index(*descriptor_index)
+        },
+        _ => {},
+      }
+    }
+  }
+
+  fn add_class_file(&mut self, class_file: &ClassFile) {
+    self.add_constant_pool();
+    for field in &class_file.fields.fields {
+      self.add_descriptor_index(field.descriptor_index);
+      for attribute in &field.attributes.attributes {
+        match attribute {
+          FieldInfoAttribute::Signature(signature) => self.add_signature(signature.signature_index),
+          FieldInfoAttribute::RuntimeVisibleAnnotations(attribute) => self.add_annotations(&attribute.annotations),
+          FieldInfoAttribute::RuntimeInvisibleAnnotations(attribute) => self.add_annotations(&attribute.annotations),
+          FieldInfoAttribute::RuntimeVisibleTypeAnnotations(attribute) => self.add_type_annotations(&attribute.annotations),
+          FieldInfoAttribute::RuntimeInvisibleTypeAnnotations(attribute) => self.add_type_annotations(&attribute.annotations),
+          _ => {},
+        }
+      }
+    }
+    for method in &class_file.methods.methods {
+      self.add_descriptor_index(method.descriptor_index);
+      for attribute in &method.attributes.attributes {
+        match attribute {
+          MethodInfoAttribute::Code(code) => {
+            for attribute in &code.attributes.attributes {
+              match attribute {
+                CodeNestedAttribute::LocalVariableTypeTable(table) => {
+                  for entry in &table.local_variable_type_table {
+                    self.add_signature(entry.signature_index);
+                  }
+                },
+                CodeNestedAttribute::RuntimeVisibleTypeAnnotations(attribute) => self.add_type_annotations(&attribute.annotations),
+                CodeNestedAttribute::RuntimeInvisibleTypeAnnotations(attribute) => self.add_type_annotations(&attribute.annotations),
+                _ => {},
+              }
+            }
+          },
+          MethodInfoAttribute::Exceptions(exceptions) => {
+            for &index in &exceptions.exception_index_table {
+              self.add_class(index);
+            }
+          },
+          MethodInfoAttribute::AnnotationDefault(attribute) => self.add_element_value(&attribute.default_value),
+          MethodInfoAttribute::Signature(signature) => self.add_signature(signature.signature_index),
+          MethodInfoAttribute::RuntimeVisibleAnnotations(attribute) => self.add_annotations(&attribute.annotations),
+          MethodInfoAttribute::RuntimeInvisibleAnnotations(attribute) => self.add_annotations(&attribute.annotations),
+          MethodInfoAttribute::RuntimeVisibleTypeAnnotations(attribute) => self.add_type_annotations(&attribute.annotations),
+          MethodInfoAttribute::RuntimeInvisibleTypeAnnotations(attribute) => self.add_type_annotations(&attribute.annotations),
+          MethodInfoAttribute::RuntimeVisibleParameterAnnotations(attribute) => {
+            for parameter in &attribute.parameter_annotations {
+              self.add_annotations(&parameter.annotations);
+            }
+          },
+          MethodInfoAttribute::RuntimeInvisibleParameterAnnotations(attribute) => {
+            for parameter in &attribute.parameter_annotations {
+              self.add_annotations(&parameter.annotations);
+            }
+          },
+          _ => {},
+        }
+      }
+    }
+    for attribute in &class_file.attributes.attributes {
+      match attribute {
+        ClassFileAttribute::InnerClasses(inner_classes) => {
+          for class in &inner_classes.classes {
+            self.add_class(class.inner_class_info_index);
+            self.add_class(class.outer_class_info_index);
+          }
+        },
+        ClassFileAttribute::EnclosingMethod(enclosing) => self.add_class(enclosing.class_index),
+        ClassFileAttribute::NestHost(nest_host) => self.add_class(nest_host.nest_host_index),
+        ClassFileAttribute::NestMembers(nest_members) => {
+          for &index in &nest_members.classes {
+            self.add_class(index);
+          }
+        },
+        ClassFileAttribute::PermittedSubclasses(permitted) => {
+          for &index in &permitted.classes {
+            self.add_class(index);
+          }
+        },
+        ClassFileAttribute::Record(record) => {
+          for component in &record.record_components {
+            self.add_descriptor_index(component.descriptor_index);
+            for attribute in &component.attributes.attributes {
+              match attribute {
+                RecordComponentInfoAttribute::Signature(signature) => self.add_signature(signature.signature_index),
+                RecordComponentInfoAttribute::RuntimeVisibleAnnotations(attribute) => self.add_annotations(&attribute.annotations),
+                RecordComponentInfoAttribute::RuntimeInvisibleAnnotations(attribute) => self.add_annotations(&attribute.annotations),
+                RecordComponentInfoAttribute::RuntimeVisibleTypeAnnotations(attribute) => {
+                  self.add_type_annotations(&attribute.annotations)
+                },
+                RecordComponentInfoAttribute::RuntimeInvisibleTypeAnnotations(attribute) => {
+                  self.add_type_annotations(&attribute.annotations)
+                },
+              }
+            }
+          }
+        },
+        ClassFileAttribute::Signature(signature) => self.add_signature(signature.signature_index),
+        ClassFileAttribute::RuntimeVisibleAnnotations(attribute) => self.add_annotations(&attribute.annotations),
+        ClassFileAttribute::RuntimeInvisibleAnnotations(attribute) => self.add_annotations(&attribute.annotations),
+        ClassFileAttribute::RuntimeVisibleTypeAnnotations(attribute) => self.add_type_annotations(&attribute.annotations),
+        ClassFileAttribute::RuntimeInvisibleTypeAnnotations(attribute) => self.add_type_annotations(&attribute.annotations),
+        _ => {},
+      }
+    }
+  }
+}
+
+// クラスファイルが参照する全てのクラスの内部名 (自分自身は除く)
+pub fn class_references(class_file: &ClassFile) -> BTreeSet<String> {
+  let mut collector = Collector { pool: &class_file.constant_pool, references: BTreeSet::new() };
+  collector.add_class_file(class_file);
+  if let Ok(name) = class_file.constant_pool.get_class_name(class_file.this_class) {
+    collector.references.remove(&name);
+  }
+  collector.references
+}
+
+// 入力 (.classファイル、ディレクトリかjar) に含まれるクラスをラベルと一緒に読み込む
+pub fn read_input(path: &Path) -> io::Result<(String, Vec<ClassFile>)> {
+  if path.is_file() && path.extension().is_some_and(|extension| extension == "class") {
+    let class_file = class_leader::parse_bytes(&fs::read(path)?)?;
+    let label = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    return Ok((label, vec![class_file]));
+  }
+  let entry = ClassPathEntry::open(path)?;
+  let mut classes = Vec::new();
+  for name in entry.class_names() {
+    let bytes = entry.read(&name).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{}.class", name)))?;
+    let class_file = class_leader::parse_bytes(&bytes).map_err(|e| io::Error::new(e.kind(), format!("{}.class: {}", name, e)))?;
+    classes.push(class_file);
+  }
+  Ok((entry.label(), classes))
+}
+
+impl Dependencies {
+  // 入力ごとの (ラベル, クラス) から依存関係を集め、参照先をブートクラスパス (指定がなければJDK)、クラスパスの順に探す
+  pub fn analyze(inputs: &[(String, Vec<ClassFile>)], class_path: &ClassPath, boot_class_path: Option<&ClassPath>, jdk: &Jdk) -> Dependencies {
+    let mut dependencies = Dependencies::default();
+    for (label, classes) in inputs {
+      for class_file in classes {
+        let Ok(name) = class_file.constant_pool.get_class_name(class_file.this_class) else {
+          continue;
+        };
+        dependencies.locations.insert(name.clone(), Location::Input(label.clone()));
+        dependencies.classes.push(ClassDependencies { name, source: label.clone(), references: class_references(class_file) });
+      }
+    }
+    let boot_labels: Vec<String> = boot_class_path.map(|path| path.entries.iter().map(ClassPathEntry::label).collect()).unwrap_or_default();
+    let labels: Vec<String> = class_path.entries.iter().map(ClassPathEntry::label).collect();
+    let references: BTreeSet<String> = dependencies.classes.iter().flat_map(|class| class.references.iter().cloned()).collect();
+    for reference in references {
+      if dependencies.locations.contains_key(&reference) {
+        continue;
+      }
+      let location = if let Some(index) = boot_class_path.and_then(|path| path.find(&reference)) {
+        Location::ClassPath(boot_labels[index].clone())
+      } else if boot_class_path.is_none()
+        && let Some(module) = jdk.module(&reference)
+      {
+        Location::Jdk(module.to_string())
+      } else if let Some(index) = class_path.find(&reference) {
+        Location::ClassPath(labels[index].clone())
+      } else {
+        Location::Missing
+      };
+      dependencies.locations.insert(reference, location);
+    }
+    dependencies.classes.sort_by(|a, b| a.name.cmp(&b.name));
+    dependencies
+  }
+
+  pub fn location(&self, class_name: &str) -> &Location {
+    self.locations.get(class_name).unwrap_or(&Location::Missing)
+  }
+
+  // パッケージ間の依存 ((入力のラベル, パッケージ) -> 参照先パッケージと場所)
+  pub fn package_dependencies(&self) -> BTreeMap<(String, String), BTreeSet<(String, Location)>> {
+    let mut packages: BTreeMap<(String, String), BTreeSet<(String, Location)>> = BTreeMap::new();
+    for class in &self.classes {
+      let package = package_name(&class.name);
+      let targets = packages.entry((class.source.clone(), package.to_string())).or_default();
+      for reference in &class.references {
+        let target = package_name(reference);
+        if target != package {
+          targets.insert((target.to_string(), self.location(reference).clone()));
+        }
+      }
+    }
+    packages
+  }
+
+  // 入力 (モジュールかアーカイブ) 間の依存
+  pub fn module_dependencies(&self) -> BTreeMap<String, BTreeSet<Location>> {
+    let mut modules: BTreeMap<String, BTreeSet<Location>> = BTreeMap::new();
+    for class in &self.classes {
+      let targets = modules.entry(class.source.clone()).or_default();
+      for reference in &class.references {
+        let location = self.location(reference);
+        if *location != Location::Input(class.source.clone()) {
+          targets.insert(location.clone());
+        }
+      }
+    }
+    modules
+  }
+
+  // 見つからなかったクラスと、それを参照するクラス
+  pub fn missing(&self) -> BTreeMap<String, BTreeSet<String>> {
+    let mut missing: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for class in &self.classes {
+      for reference in &class.references {
+        if *self.location(reference) == Location::Missing {
+          missing.entry(reference.clone()).or_default().insert(class.name.clone());
+        }
+      }
+    }
+    missing
+  }
+}
@@ -0,0 +1,229 @@
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fmt::Write as _,
+  path::Path,
+};
+
+use crate::{
+  deps::{jdk::Jdk, Dependencies, Location, read_input},
+  util::{class::java_name, class_path::ClassPathOptions, dot::escape, json},
+};
+
+enum Format {
+  Text,
+  Dot,
+  Json,
+}
+
+// jdepsと同じく、入力ごとの依存先の一覧とパッケージ (またはクラス) ごとの依存を並べる
+pub fn to_text(dependencies: &Dependencies, classes: bool) -> String {
+  let mut text = String::new();
+  for (source, targets) in dependencies.module_dependencies() {
+    for target in targets {
+      let _ = writeln!(text, "{} -> {}", source, target.label());
+    }
+  }
+  if classes {
+    for class in &dependencies.classes {
+      let _ = writeln!(text, "   {} ({})", java_name(&class.name), class.source);
+      let width = class.references.iter().map(|name| name.len()).max().unwrap_or(0);
+      for reference in &class.references {
+        let _ = writeln!(text, "      -> {:width$}  {}", java_name(reference), dependencies.location(reference).label(), width = width);
+      }
+    }
+  } else {
+    for ((source, package), targets) in dependencies.package_dependencies() {
+      let _ = writeln!(text, "   {} ({})", java_name(&package), source);
+      let width = targets.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+      for (target, location) in targets {
+        let _ = writeln!(text, "      -> {:width$}  {}", java_name(&target), location.label(), width = width);
+      }
+    }
+  }
+  let missing = dependencies.missing();
+  if !missing.is_empty() {
+    let _ = writeln!(text, "\nMissing classes ({}):", missing.len());
+    for (name, users) in missing {
+      let users: Vec<String> = users.iter().map(|user| java_name(user)).collect();
+      let _ = writeln!(text, "   {} <- {}", java_name(&name), users.join(", "));
+    }
+  }
+  text
+}
+
+// 入力や依存先のラベルごとにクラスタにまとめたDOT (見つからない参照先は赤い破線)
+pub fn to_dot(dependencies: &Dependencies, classes: bool) -> String {
+  let mut edges: BTreeSet<(String, String)> = BTreeSet::new();
+  let mut nodes: BTreeMap<String, Location> = BTreeMap::new();
+  if classes {
+    for class in &dependencies.classes {
+      nodes.insert(class.name.clone(), Location::Input(class.source.clone()));
+      for reference in &class.references {
+        nodes.entry(reference.clone()).or_insert_with(|| dependencies.location(reference).clone());
+        edges.insert((class.name.clone(), reference.clone()));
+      }
+    }
+  } else {
+    for ((source, package), targets) in dependencies.package_dependencies() {
+      nodes.insert(package.clone(), Location::Input(source));
+      for (target, location) in targets {
+        nodes.entry(target.clone()).or_insert(location);
+        edges.insert((package.clone(), target));
+      }
+    }
+  }
+  let mut clusters: BTreeMap<&Location, Vec<&String>> = BTreeMap::new();
+  for (name, location) in &nodes {
+    clusters.entry(location).or_default().push(name);
+  }
+  let mut dot = String::from("digraph \"dependencies\" {\n");
+  dot.push_str("  node [shape=box, fontname=\"monospace\"];\n");
+  for (i, (location, names)) in clusters.iter().enumerate() {
+    let _ = writeln!(dot, "  subgraph \"cluster_{}\" {{", i);
+    let _ = writeln!(dot, "    label=\"{}\";", escape(location.label()));
+    let style = if **location == Location::Missing { ", color=red, fontcolor=red, style=dashed" } else { "" };
+    for name in names {
+      let _ = writeln!(dot, "    \"{}\" [label=\"{}\"{}];", escape(name), escape(&java_name(name)), style);
+    }
+    dot.push_str("  }\n");
+  }
+  for (from, to) in &edges {
+    let _ = writeln!(dot, "  \"{}\" -> \"{}\";", escape(from), escape(to));
+  }
+  dot.push_str("}\n");
+  dot
+}
+
+pub fn to_json(dependencies: &Dependencies) -> String {
+  let mut json = String::from("{\n  \"modules\": [");
+  for (i, (source, targets)) in dependencies.module_dependencies().iter().enumerate() {
+    let _ = write!(
+      json,
+      "{}\n    {{\"name\": {}, \"requires\": {}}}",
+      if i == 0 { "" } else { "," },
+      json::string(source),
+      json::string_array(targets.iter().map(Location::label)),
+    );
+  }
+  json.push_str("\n  ],\n  \"packages\": [");
+  for (i, ((source, package), targets)) in dependencies.package_dependencies().iter().enumerate() {
+    let targets: Vec<String> = targets
+      .iter()
+      .map(|(target, location)| format!("{{\"name\": {}, \"location\": {}}}", json::string(&java_name(target)), json::string(location.label())))
+      .collect();
+    let _ = write!(
+      json,
+      "{}\n    {{\"name\": {}, \"module\": {}, \"dependencies\": [{}]}}",
+      if i == 0 { "" } else { "," },
+      json::string(&java_name(package)),
+      json::string(source),
+      targets.join(", "),
+    );
+  }
+  json.push_str("\n  ],\n  \"classes\": [");
+  for (i, class) in dependencies.classes.iter().enumerate() {
+    let references: Vec<String> = class
+      .references
+      .iter()
+      .map(|reference| {
+        format!(
+          "{{\"name\": {}, \"location\": {}}}",
+          json::string(&java_name(reference)),
+          json::string(dependencies.location(reference).label())
+        )
+      })
+      .collect();
+    let _ = write!(
+      json,
+      "{}\n    {{\"name\": {}, \"module\": {}, \"dependencies\": [{}]}}",
+      if i == 0 { "" } else { "," },
+      json::string(&java_name(&class.name)),
+      json::string(&class.source),
+      references.join(", "),
+    );
+  }
+  json.push_str("\n  ],\n  \"missing\": [");
+  for (i, (name, users)) in dependencies.missing().iter().enumerate() {
+    let users: Vec<String> = users.iter().map(|user| java_name(user)).collect();
+    let _ = write!(
+      json,
+      "{}\n    {{\"name\": {}, \"referencedBy\": {}}}",
+      if i == 0 { "" } else { "," },
+      json::string(&java_name(name)),
+      json::string_array(users.iter().map(String::as_str)),
+    );
+  }
+  json.push_str("\n  ]\n}\n");
+  json
+}
+
+// クラスファイル、ディレクトリ、jarの依存関係を表示する
+pub fn print_command(program: &str, args: &[String]) -> i32 {
+  let usage = || {
+    eprintln!("Usage: {} deps [-cp <path>] [-Xbootclasspath:<path>] [--classes] [--dot | --json] <class file | directory | jar>...", program);
+    2
+  };
+  let mut class_path_options = ClassPathOptions::default();
+  let mut classes = false;
+  let mut format = Format::Text;
+  let mut paths = Vec::new();
+  let mut i = 0;
+  while i < args.len() {
+    match class_path_options.parse_arg(&args[i..]) {
+      Ok(0) => {},
+      Ok(used) => {
+        i += used;
+        continue;
+      },
+      Err(_) => return usage(),
+    }
+    let arg = &args[i];
+    if arg == "--classes" {
+      classes = true;
+    } else if arg == "--dot" {
+      format = Format::Dot;
+    } else if arg == "--json" {
+      format = Format::Json;
+    } else if arg.starts_with('-') {
+      return usage();
+    } else {
+      paths.push(arg);
+    }
+    i += 1;
+  }
+  if paths.is_empty() {
+    return usage();
+  }
+  let class_path = match class_path_options.class_path("") {
+    Ok(class_path) => class_path,
+    Err(e) => {
+      eprintln!("Error: {}", e);
+      return 1;
+    },
+  };
+  let boot_class_path = match class_path_options.boot_class_path() {
+    Ok(boot_class_path) => boot_class_path,
+    Err(e) => {
+      eprintln!("Error: {}", e);
+      return 1;
+    },
+  };
+  let mut inputs = Vec::new();
+  for path in paths {
+    match read_input(Path::new(path)) {
+      Ok(input) => inputs.push(input),
+      Err(e) => {
+        eprintln!("Error: {}: {}", path, e);
+        return 1;
+      },
+    }
+  }
+  let jdk = if boot_class_path.is_some() { Jdk::Packages } else { Jdk::locate() };
+  let dependencies = Dependencies::analyze(&inputs, &class_path, boot_class_path.as_ref(), &jdk);
+  match format {
+    Format::Text => print!("{}", to_text(&dependencies, classes)),
+    Format::Dot => print!("{}", to_dot(&dependencies, classes)),
+    Format::Json => print!("{}", to_json(&dependencies)),
+  }
+  0
+}
@@ -0,0 +1,191 @@
+use std::{
+  fs,
+  io::{self, BufWriter, Write},
+  path::{Path, PathBuf},
+  time::UNIX_EPOCH,
+};
+
+use crate::{
+  hierarchy::{ClassInfo, entry_classes},
+  util::class_path::{ClassPath, ClassPathEntry},
+};
+
+// 継承関係のキャッシュファイル
+// クラスパスのエントリごとに、変更を見分ける値と含まれるクラスをタブ区切りのテキストで保存する
+//   entry <path> <fingerprint>
+//   class <name> <access_flags> <super_class> <interfaces> <permitted_subclasses>
+// 複数のクラス名は','で区切り、sealedでなければpermitted_subclassesは"-"にする
+
+const MAGIC: &str = "rust-jvm class hierarchy cache 1";
+
+pub struct CachedEntry {
+  pub path: String,
+  pub fingerprint: String,
+  pub classes: Vec<(String, ClassInfo)>,
+}
+
+fn modified(path: &Path) -> Option<u128> {
+  let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?;
+  Some(modified.duration_since(UNIX_EPOCH).ok()?.as_nanos())
+}
+
+// FNV-1a (キャッシュファイルに保存するので、実行ごとに変わらないハッシュを使う)
+fn fnv1a(bytes: &[u8]) -> u64 {
+  bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+// エントリが変わったかを見分ける値 (jarは大きさと更新時刻、ディレクトリはクラスファイルごとの名前、大きさ、更新時刻のハッシュ)
+// ファイルの置き換えや削除で数や最新の更新時刻が変わらなくても、どれかのファイルが変われば値が変わる
+pub fn fingerprint(entry: &ClassPathEntry) -> String {
+  match entry {
+    ClassPathEntry::Jar(path, _) => {
+      let size = fs::metadata(path).map_or(0, |metadata| metadata.len());
+      format!("jar {} {}", size, modified(path).unwrap_or(0))
+    },
+    ClassPathEntry::Directory(path) => {
+      let mut names = entry.class_names();
+      names.sort();
+      let mut files = String::new();
+      for name in &names {
+        let file = path.join(format!("{}.class", name));
+        let size = fs::metadata(&file).map_or(0, |metadata| metadata.len());
+        files.push_str(&format!("{}\t{}\t{}\n", name, size, modified(&file).unwrap_or(0)));
+      }
+      format!("dir {} {:016x}", names.len(), fnv1a(files.as_bytes()))
+    },
+  }
+}
+
+fn parse_class(fields: &[&str]) -> Option<(String, ClassInfo)> {
+  let [name, flags, super_class, interfaces, permitted_subclasses] = fields else {
+    return None;
+  };
+  let list = |text: &str| text.split(',').filter(|name| !name.is_empty()).map(str::to_string).collect::<Vec<String>>();
+  let info = ClassInfo {
+    access_flags: u16::from_str_radix(flags, 16).ok()?,
+    super_class: if super_class.is_empty() { None } else { Some(super_class.to_string()) },
+    interfaces: list(interfaces),
+    permitted_subclasses: if *permitted_subclasses == "-" { None } else { Some(list(permitted_subclasses)) },
+  };
+  Some((name.to_string(), info))
+}
+
+// 形式が違うか壊れたキャッシュは無いものとして扱う
+pub fn read_cache(path: &Path) -> Vec<CachedEntry> {
+  let Ok(text) = fs::read_to_string(path) else {
+    return Vec::new();
+  };
+  let mut lines = text.lines();
+  if lines.next() != Some(MAGIC) {
+    return Vec::new();
+  }
+  let mut entries: Vec<CachedEntry> = Vec::new();
+  for line in lines {
+    let fields: Vec<&str> = line.split('\t').collect();
+    match fields.as_slice() {
+      ["entry", path, fingerprint] => {
+        entries.push(CachedEntry { path: path.to_string(), fingerprint: fingerprint.to_string(), classes: Vec::new() });
+      },
+      ["class", rest @ ..] => match (entries.last_mut(), parse_class(rest)) {
+        (Some(entry), Some(class)) => entry.classes.push(class),
+        _ => return Vec::new(),
+      },
+      _ => return Vec::new(),
+    }
+  }
+  entries
+}
+
+pub fn write_cache(path: &Path, entries: &[CachedEntry]) -> io::Result<()> {
+  // 途中で失敗しても壊れたキャッシュが残らないよう、一時ファイルに書いてから置き換える
+  // with_extensionでは拡張子を置き換えてしまい、a.cacheとa.dbが同じa.tmpを使う
+  let temporary = PathBuf::from(format!("{}.tmp", path.display()));
+  let mut writer = BufWriter::new(fs::File::create(&temporary)?);
+  writeln!(writer, "{}", MAGIC)?;
+  for entry in entries {
+    writeln!(writer, "entry\t{}\t{}", entry.path, entry.fingerprint)?;
+    for (name, info) in &entry.classes {
+      let permitted_subclasses = match &info.permitted_subclasses {
+        Some(names) => names.join(","),
+        None => "-".to_string(),
+      };
+      writeln!(
+        writer,
+        "class\t{}\t{:x}\t{}\t{}\t{}",
+        name,
+        info.access_flags,
+        info.super_class.as_deref().unwrap_or(""),
+        info.interfaces.join(","),
+        permitted_subclasses
+      )?;
+    }
+  }
+  writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+  fs::rename(&temporary, path)
+}
+
+// クラスパスの各エントリのクラスを、変更がなければキャッシュから、あればクラスファイルから読む
+// 読み直したエントリがあればキャッシュを書き直す
+pub fn load_entries(class_path: &ClassPath, cache_path: &Path) -> io::Result<Vec<CachedEntry>> {
+  let mut cached = read_cache(cache_path);
+  let mut changed = false;
+  let mut entries = Vec::new();
+  for entry in &class_path.entries {
+    let path = entry.path().display().to_string();
+    let fingerprint = fingerprint(entry);
+    match cached.iter().position(|cached| cached.path == path && cached.fingerprint == fingerprint) {
+      Some(position) => entries.push(cached.swap_remove(position)),
+      None => {
+        changed = true;
+        entries.push(CachedEntry { path, fingerprint, classes: entry_classes(entry) });
+      },
+    }
+  }
+  if changed || !cached.is_empty() {
+    write_cache(cache_path, &entries)?;
+  }
+  Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn work_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rust-jvm-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+  }
+
+  #[test]
+  fn directory_fingerprint_covers_every_file() {
+    let dir = work_dir("fingerprint");
+    fs::write(dir.join("A.class"), b"a").unwrap();
+    fs::write(dir.join("B.class"), b"b").unwrap();
+    let entry = ClassPathEntry::Directory(dir.clone());
+    let before = fingerprint(&entry);
+    assert_eq!(fingerprint(&entry), before);
+    // 数も最新の更新時刻も変えずに、古い方のファイルの中身だけを変える
+    let modified = fs::metadata(dir.join("A.class")).unwrap().modified().unwrap();
+    fs::write(dir.join("A.class"), b"aa").unwrap();
+    fs::File::options().write(true).open(dir.join("A.class")).unwrap().set_modified(modified).unwrap();
+    assert_ne!(fingerprint(&entry), before);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn temporary_file_keeps_the_cache_extension() {
+    let dir = work_dir("cache");
+    let path = dir.join("hierarchy.cache");
+    fs::write(dir.join("hierarchy.tmp"), b"other").unwrap();
+    let entries = [CachedEntry { path: "lib".to_string(), fingerprint: "dir 0 0".to_string(), classes: Vec::new() }];
+    write_cache(&path, &entries).unwrap();
+    assert_eq!(fs::read(dir.join("hierarchy.tmp")).unwrap(), b"other");
+    assert!(!dir.join("hierarchy.cache.tmp").exists());
+    let read = read_cache(&path);
+    assert_eq!(read.len(), 1);
+    assert_eq!((read[0].path.as_str(), read[0].fingerprint.as_str()), ("lib", "dir 0 0"));
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
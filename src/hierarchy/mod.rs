@@ -0,0 +1,323 @@
+pub mod cache;
+pub mod report;
+
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap},
+  io,
+  path::Path,
+};
+
+use crate::{
+  class_leader,
+  structure::class::{ClassFile, ClassFileAttribute},
+  util::class_path::{ClassPath, ClassPathEntry},
+};
+
+// クラスパス上の全クラスのthis_class、super_class、interfacesから作る継承関係の索引
+
+const ACC_FINAL: u16 = 0x0010;
+const ACC_INTERFACE: u16 = 0x0200;
+
+const OBJECT: &str = "java/lang/Object";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClassInfo {
+  pub access_flags: u16,
+  pub super_class: Option<String>,
+  pub interfaces: Vec<String>,
+  // PermittedSubclasses属性 (sealedでなければNone)
+  pub permitted_subclasses: Option<Vec<String>>,
+}
+
+impl ClassInfo {
+  pub fn from_class_file(class_file: &ClassFile) -> Option<(String, ClassInfo)> {
+    let pool = &class_file.constant_pool;
+    let name = pool.get_class_name(class_file.this_class).ok()?;
+    let super_class = if class_file.super_class == 0 { None } else { pool.get_class_name(class_file.super_class).ok() };
+    let interfaces = class_file.interfaces.interfaces.iter().filter_map(|&index| pool.get_class_name(index).ok()).collect();
+    let permitted_subclasses = class_file.attributes.attributes.iter().find_map(|attribute| match attribute {
+      ClassFileAttribute::PermittedSubclasses(attribute) => {
+        Some(attribute.classes.iter().filter_map(|&index| pool.get_class_name(index).ok()).collect())
+      },
+      _ => None,
+    });
+    Some((name, ClassInfo { access_flags: class_file.access_flags, super_class, interfaces, permitted_subclasses }))
+  }
+
+  pub fn is_interface(&self) -> bool {
+    self.access_flags & ACC_INTERFACE != 0
+  }
+
+  pub fn is_final(&self) -> bool {
+    self.access_flags & ACC_FINAL != 0
+  }
+
+  // 直接の親 (スーパークラスとインターフェース)
+  pub fn parents(&self) -> impl Iterator<Item = &String> {
+    self.super_class.iter().chain(&self.interfaces)
+  }
+}
+
+// 1つのクラスパスのエントリに含まれる全クラス
+pub fn entry_classes(entry: &ClassPathEntry) -> Vec<(String, ClassInfo)> {
+  entry.class_names().iter()
+    .filter_map(|name| entry.read(name))
+    .filter_map(|bytes| class_leader::parse_bytes(&bytes).ok())
+    .filter_map(|class_file| ClassInfo::from_class_file(&class_file))
+    .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SealedViolationKind {
+  // sealedなクラスを継承しているが、PermittedSubclassesに含まれていない
+  NotPermitted,
+  // PermittedSubclassesに含まれているが、sealedなクラスを直接継承していない
+  NotDirectSubtype,
+  // PermittedSubclassesに含まれるクラスがクラスパスにない
+  Missing,
+}
+
+impl SealedViolationKind {
+  pub fn name(&self) -> &'static str {
+    match self {
+      SealedViolationKind::NotPermitted => "not permitted",
+      SealedViolationKind::NotDirectSubtype => "not a direct subtype",
+      SealedViolationKind::Missing => "missing",
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SealedViolation {
+  pub sealed: String,
+  pub class: String,
+  pub kind: SealedViolationKind,
+}
+
+#[derive(Debug, Default)]
+pub struct ClassHierarchy {
+  pub classes: BTreeMap<String, ClassInfo>,
+  // 直接のサブクラス、サブインターフェース、実装クラス
+  children: HashMap<String, Vec<String>>,
+}
+
+impl ClassHierarchy {
+  // 同じ名前のクラスは先に現れたものを使う (クラスパスの前のエントリが優先)
+  pub fn from_classes(classes: impl IntoIterator<Item = (String, ClassInfo)>) -> ClassHierarchy {
+    let mut hierarchy = ClassHierarchy::default();
+    for (name, info) in classes {
+      hierarchy.classes.entry(name).or_insert(info);
+    }
+    for (name, info) in &hierarchy.classes {
+      for parent in info.parents() {
+        hierarchy.children.entry(parent.clone()).or_default().push(name.clone());
+      }
+    }
+    hierarchy
+  }
+
+  pub fn build(class_path: &ClassPath) -> ClassHierarchy {
+    ClassHierarchy::from_classes(class_path.entries.iter().flat_map(entry_classes))
+  }
+
+  // キャッシュファイルがあれば、変更のないエントリはクラスファイルを読まずにキャッシュから作る
+  pub fn load(class_path: &ClassPath, cache_path: &Path) -> io::Result<ClassHierarchy> {
+    let entries = cache::load_entries(class_path, cache_path)?;
+    Ok(ClassHierarchy::from_classes(entries.into_iter().flat_map(|entry| entry.classes)))
+  }
+
+  pub fn get(&self, class: &str) -> Option<&ClassInfo> {
+    self.classes.get(class)
+  }
+
+  // クラスパスにないクラスも、クラスパスのクラスの親として現れれば含める (JDKのインターフェースなど)
+  pub fn contains(&self, class: &str) -> bool {
+    self.classes.contains_key(class) || self.children.contains_key(class)
+  }
+
+  // クラスパスにないクラスは、サブタイプのinterfacesに現れればインターフェースとする
+  pub fn is_interface(&self, class: &str) -> bool {
+    match self.get(class) {
+      Some(info) => info.is_interface(),
+      None => self.direct_subtypes(class).iter().any(|child| self.get(child).is_some_and(|info| info.interfaces.iter().any(|name| name == class))),
+    }
+  }
+
+  // 配列は扱わない
+  // クラスパスにないクラスの先は辿れないので、java/lang/Objectだけは常に上位とする
+  pub fn is_subclass_of(&self, class: &str, supertype: &str) -> bool {
+    class == supertype || supertype == OBJECT || self.supertypes(class).contains(supertype)
+  }
+
+  // スーパークラスを近い順に並べたもの (クラスパスにないクラスで止まる)
+  pub fn superclasses(&self, class: &str) -> Vec<String> {
+    let mut superclasses = Vec::new();
+    let mut current = self.get(class).and_then(|info| info.super_class.as_ref());
+    while let Some(name) = current {
+      if superclasses.contains(name) {
+        break;
+      }
+      superclasses.push(name.clone());
+      current = self.get(name).and_then(|info| info.super_class.as_ref());
+    }
+    superclasses
+  }
+
+  // 全てのスーパークラスとスーパーインターフェース (自身を除く)
+  pub fn supertypes(&self, class: &str) -> BTreeSet<String> {
+    let mut supertypes = BTreeSet::new();
+    let mut stack = vec![class.to_string()];
+    while let Some(name) = stack.pop() {
+      let Some(info) = self.get(&name) else {
+        continue;
+      };
+      for parent in info.parents() {
+        if parent != class && supertypes.insert(parent.clone()) {
+          stack.push(parent.clone());
+        }
+      }
+    }
+    supertypes
+  }
+
+  pub fn direct_subtypes(&self, class: &str) -> &[String] {
+    self.children.get(class).map(Vec::as_slice).unwrap_or_default()
+  }
+
+  // 全てのサブクラスとサブインターフェース、実装クラス (自身を除く)
+  pub fn subtypes(&self, class: &str) -> BTreeSet<String> {
+    let mut subtypes = BTreeSet::new();
+    let mut stack = vec![class.to_string()];
+    while let Some(name) = stack.pop() {
+      for child in self.direct_subtypes(&name) {
+        if child != class && subtypes.insert(child.clone()) {
+          stack.push(child.clone());
+        }
+      }
+    }
+    subtypes
+  }
+
+  // インターフェースを直接か間接に実装するクラス (インターフェースを除く)
+  pub fn implementors(&self, interface: &str) -> BTreeSet<String> {
+    let mut implementors = self.subtypes(interface);
+    implementors.retain(|name| !self.is_interface(name));
+    implementors
+  }
+
+  // 両方を代入できる最も近いスーパークラス (StackMapTableのフレームの計算で型を合わせるのに使う)
+  // 一方が他方に代入できなければ、インターフェースが含まれるときはjava/lang/Objectにする
+  pub fn common_superclass(&self, a: &str, b: &str) -> String {
+    if self.is_subclass_of(b, a) {
+      return a.to_string();
+    }
+    if self.is_subclass_of(a, b) {
+      return b.to_string();
+    }
+    if self.is_interface(a) || self.is_interface(b) {
+      return OBJECT.to_string();
+    }
+    let b_superclasses = self.superclasses(b);
+    self.superclasses(a).into_iter().find(|name| b_superclasses.contains(name)).unwrap_or_else(|| OBJECT.to_string())
+  }
+
+  pub fn is_sealed(&self, class: &str) -> bool {
+    self.get(class).is_some_and(|info| info.permitted_subclasses.is_some())
+  }
+
+  pub fn permitted_subclasses(&self, class: &str) -> Option<&[String]> {
+    self.get(class).and_then(|info| info.permitted_subclasses.as_deref())
+  }
+
+  // sealedなクラスを継承するクラスの修飾子 (クラスファイルにはnon-sealedの印が無いので、finalでもsealedでもなければnon-sealed)
+  pub fn subclass_modifier(&self, class: &str) -> Option<&'static str> {
+    let info = self.get(class)?;
+    Some(if self.is_sealed(class) {
+      "sealed"
+    } else if info.is_final() {
+      "final"
+    } else {
+      "non-sealed"
+    })
+  }
+
+  // classがsupertypeを直接継承してよいか (supertypeがsealedならPermittedSubclassesに含まれる必要がある)
+  pub fn may_extend(&self, class: &str, supertype: &str) -> bool {
+    self.permitted_subclasses(supertype).is_none_or(|permitted| permitted.iter().any(|name| name == class))
+  }
+
+  // クラスパス上の全てのsealedなクラスとインターフェースについて、継承の制約を確かめる
+  // permittedなサブクラスは必ずfinal、sealed、non-sealedのどれかになるので、修飾子は確かめない
+  pub fn check_sealed(&self) -> Vec<SealedViolation> {
+    let mut violations = Vec::new();
+    for (name, info) in &self.classes {
+      for parent in info.parents() {
+        if !self.may_extend(name, parent) {
+          violations.push(SealedViolation { sealed: parent.clone(), class: name.clone(), kind: SealedViolationKind::NotPermitted });
+        }
+      }
+      for permitted in info.permitted_subclasses.iter().flatten() {
+        let kind = match self.get(permitted) {
+          None => SealedViolationKind::Missing,
+          Some(subclass) if !subclass.parents().any(|parent| parent == name) => SealedViolationKind::NotDirectSubtype,
+          Some(_) => continue,
+        };
+        violations.push(SealedViolation { sealed: name.clone(), class: permitted.clone(), kind });
+      }
+    }
+    violations.sort();
+    violations
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn class(access_flags: u16, super_class: &str, permitted_subclasses: Option<&[&str]>) -> ClassInfo {
+    ClassInfo {
+      access_flags,
+      super_class: Some(super_class.to_string()),
+      interfaces: Vec::new(),
+      permitted_subclasses: permitted_subclasses.map(|names| names.iter().map(|name| name.to_string()).collect()),
+    }
+  }
+
+  #[test]
+  fn sealed_hierarchy_modifiers_and_violations() {
+    let hierarchy = ClassHierarchy::from_classes([
+      ("a/Shape".to_string(), class(0, OBJECT, Some(&["a/Circle", "a/Polygon", "a/Blob", "a/Gone"]))),
+      ("a/Circle".to_string(), class(ACC_FINAL, "a/Shape", None)),
+      ("a/Polygon".to_string(), class(0, "a/Shape", Some(&["a/Square"]))),
+      ("a/Square".to_string(), class(ACC_FINAL, "a/Polygon", None)),
+      ("a/Blob".to_string(), class(0, OBJECT, None)),
+      ("a/Other".to_string(), class(0, "a/Polygon", None)),
+    ]);
+    assert_eq!(hierarchy.subclass_modifier("a/Circle"), Some("final"));
+    assert_eq!(hierarchy.subclass_modifier("a/Polygon"), Some("sealed"));
+    assert_eq!(hierarchy.subclass_modifier("a/Blob"), Some("non-sealed"));
+    assert_eq!(hierarchy.subclass_modifier("a/Gone"), None);
+    let violation = |sealed: &str, class: &str, kind| SealedViolation { sealed: sealed.to_string(), class: class.to_string(), kind };
+    assert_eq!(hierarchy.check_sealed(), [
+      violation("a/Polygon", "a/Other", SealedViolationKind::NotPermitted),
+      violation("a/Shape", "a/Blob", SealedViolationKind::NotDirectSubtype),
+      violation("a/Shape", "a/Gone", SealedViolationKind::Missing),
+    ]);
+  }
+
+  #[test]
+  fn parents_missing_from_the_class_path_are_known() {
+    let mut runnable = class(0, OBJECT, None);
+    runnable.interfaces.push("java/lang/Runnable".to_string());
+    let hierarchy = ClassHierarchy::from_classes([
+      ("a/Task".to_string(), runnable),
+      ("a/Job".to_string(), class(ACC_FINAL, "a/Task", None)),
+    ]);
+    assert!(hierarchy.contains("java/lang/Runnable"));
+    assert!(hierarchy.is_interface("java/lang/Runnable"));
+    assert!(hierarchy.contains(OBJECT));
+    assert!(!hierarchy.is_interface(OBJECT));
+    assert!(!hierarchy.contains("java/util/List"));
+    assert_eq!(hierarchy.implementors("java/lang/Runnable"), BTreeSet::from(["a/Job".to_string(), "a/Task".to_string()]));
+  }
+}
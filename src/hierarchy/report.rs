@@ -0,0 +1,148 @@
+use std::{fmt::Write as _, path::Path};
+
+use crate::{
+  hierarchy::{ClassHierarchy, SealedViolation},
+  util::{class::java_name, class_path::ClassPathOptions},
+};
+
+// 名前は内部名で受け取り、ドット区切りで表示する
+fn list(text: &mut String, title: &str, names: impl IntoIterator<Item = impl AsRef<str>>) {
+  let names: Vec<_> = names.into_iter().collect();
+  let _ = writeln!(text, "{} ({}):", title, names.len());
+  for name in names {
+    let _ = writeln!(text, "   {}", java_name(name.as_ref()));
+  }
+}
+
+// クラスのスーパータイプとサブタイプ、sealedならPermittedSubclasses
+pub fn class_text(hierarchy: &ClassHierarchy, class: &str) -> String {
+  let mut text = String::new();
+  let interface = hierarchy.is_interface(class);
+  let _ = writeln!(text, "{} {}", if interface { "interface" } else { "class" }, java_name(class));
+  list(&mut text, "Superclasses", hierarchy.superclasses(class));
+  list(&mut text, "Supertypes", hierarchy.supertypes(class));
+  list(&mut text, "Subtypes", hierarchy.subtypes(class));
+  if interface {
+    list(&mut text, "Implementors", hierarchy.implementors(class));
+  }
+  if let Some(permitted) = hierarchy.permitted_subclasses(class) {
+    let permitted = permitted.iter().map(|name| format!("{} ({})", name, hierarchy.subclass_modifier(name).unwrap_or("missing")));
+    list(&mut text, "Permitted subclasses", permitted);
+  }
+  text
+}
+
+// 2つのクラスの関係
+pub fn pair_text(hierarchy: &ClassHierarchy, a: &str, b: &str) -> String {
+  let mut text = String::new();
+  let (a_name, b_name) = (java_name(a), java_name(b));
+  let _ = writeln!(text, "{} is a subtype of {}: {}", a_name, b_name, hierarchy.is_subclass_of(a, b));
+  let _ = writeln!(text, "{} is a subtype of {}: {}", b_name, a_name, hierarchy.is_subclass_of(b, a));
+  let _ = writeln!(text, "Common superclass: {}", java_name(&hierarchy.common_superclass(a, b)));
+  text
+}
+
+pub fn summary_text(hierarchy: &ClassHierarchy) -> String {
+  let interfaces = hierarchy.classes.values().filter(|info| info.is_interface()).count();
+  let sealed = hierarchy.classes.values().filter(|info| info.permitted_subclasses.is_some()).count();
+  format!(
+    "{} classes and interfaces: {} classes, {} interfaces, {} sealed\n",
+    hierarchy.classes.len(),
+    hierarchy.classes.len() - interfaces,
+    interfaces,
+    sealed
+  )
+}
+
+pub fn violations_text(violations: &[SealedViolation]) -> String {
+  let mut text = String::new();
+  for violation in violations {
+    let _ = writeln!(text, "sealed {}: {} {}", java_name(&violation.sealed), java_name(&violation.class), violation.kind.name());
+  }
+  let _ = writeln!(text, "{} sealed hierarchy violations", violations.len());
+  text
+}
+
+// クラスパスの継承関係を作り、指定したクラスについて問い合わせる
+// sealedの制約の確認で違反があれば終了コード1を返す
+pub fn print_command(program: &str, args: &[String]) -> i32 {
+  let usage = || {
+    eprintln!(
+      "Usage: {} hierarchy [-cp <path>] [-Xbootclasspath:<path>] [--cache <file>] [--check-sealed] [<class> [<class>]]",
+      program
+    );
+    2
+  };
+  let mut class_path_options = ClassPathOptions::default();
+  let mut cache = None;
+  let mut check_sealed = false;
+  let mut classes = Vec::new();
+  let mut i = 0;
+  while i < args.len() {
+    match class_path_options.parse_arg(&args[i..]) {
+      Ok(0) => {},
+      Ok(used) => {
+        i += used;
+        continue;
+      },
+      Err(_) => return usage(),
+    }
+    let arg = &args[i];
+    if arg == "--cache" {
+      i += 1;
+      let Some(value) = args.get(i) else {
+        return usage();
+      };
+      cache = Some(value.clone());
+    } else if arg == "--check-sealed" {
+      check_sealed = true;
+    } else if arg.starts_with('-') {
+      return usage();
+    } else {
+      classes.push(arg.replace('.', "/"));
+    }
+    i += 1;
+  }
+  if classes.len() > 2 {
+    return usage();
+  }
+  // ブートクラスパスのクラスを優先する
+  let class_path = match (class_path_options.boot_class_path(), class_path_options.class_path(".")) {
+    (Ok(Some(mut boot_class_path)), Ok(class_path)) => {
+      boot_class_path.entries.extend(class_path.entries);
+      boot_class_path
+    },
+    (Ok(None), Ok(class_path)) => class_path,
+    (Err(e), _) | (_, Err(e)) => {
+      eprintln!("Error: {}", e);
+      return 1;
+    },
+  };
+  let hierarchy = match cache {
+    Some(cache) => match ClassHierarchy::load(&class_path, Path::new(&cache)) {
+      Ok(hierarchy) => hierarchy,
+      Err(e) => {
+        eprintln!("Error: {}: {}", cache, e);
+        return 1;
+      },
+    },
+    None => ClassHierarchy::build(&class_path),
+  };
+  if let Some(class) = classes.iter().find(|class| !hierarchy.contains(class)) {
+    eprintln!("Error: {}: class not found", java_name(class));
+    return 1;
+  }
+  match classes.as_slice() {
+    [class] => print!("{}", class_text(&hierarchy, class)),
+    [a, b] => print!("{}", pair_text(&hierarchy, a, b)),
+    _ => print!("{}", summary_text(&hierarchy)),
+  }
+  if check_sealed {
+    let violations = hierarchy.check_sealed();
+    print!("{}", violations_text(&violations));
+    if !violations.is_empty() {
+      return 1;
+    }
+  }
+  0
+}
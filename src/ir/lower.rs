@@ -0,0 +1,743 @@
+use std::collections::HashMap;
+
+use crate::{
+  cfg::{ControlFlowGraph, EdgeKind},
+  ir::{
+    passes::propagate_copies, BinaryOp, Block, BlockId, CompareOp, Condition, Constant, Function, Handler, Inst, InvokeKind,
+    MemberRef, Op, Terminator, Type, UnaryOp, ValueId,
+  },
+  structure::{
+    class::{ClassFile, CodeAttribute, Constant as PoolConstant, ConstantPool, Method, MethodInfoAttribute},
+    code::{CodeByte, StackShuffle},
+  },
+  util::{
+    descriptor::{FieldType, MethodDescriptor},
+    graph::{predecessors, reverse_postorder},
+  },
+};
+
+// バイトコードの基本ブロック
+struct CodeBlock {
+  pc: usize,
+  // instructionsの範囲
+  start: usize,
+  end: usize,
+  // 通常の後続 (ブロックの番号)
+  successors: Vec<usize>,
+  // 例外ハンドラ (catch_typeとブロックの番号)
+  handlers: Vec<(Option<String>, usize)>,
+}
+
+// ブロックの境界でのローカル変数とオペランドスタックの型
+#[derive(Clone, PartialEq)]
+struct TypeState {
+  locals: Vec<Option<Type>>,
+  stack: Vec<Type>,
+}
+
+// 命令を実行する時点のローカル変数とオペランドスタックの値
+#[derive(Clone)]
+struct FrameState {
+  locals: Vec<Option<ValueId>>,
+  stack: Vec<ValueId>,
+}
+
+// phiを置いた場所
+enum Slot {
+  Local(usize),
+  Stack(usize),
+}
+
+pub fn lower_method(class_file: &ClassFile, method: &Method) -> Result<Function, String> {
+  let pool = &class_file.constant_pool;
+  let class_name = pool.get_class_name(class_file.this_class)?;
+  let name = pool.get_utf8(method.name_index)?;
+  let descriptor = pool.get_utf8(method.descriptor_index)?;
+  let code = method.attributes.attributes.iter().find_map(|attribute| match attribute {
+    MethodInfoAttribute::Code(code) => Some(code),
+    _ => None,
+  });
+  let Some(code) = code else {
+    return Err("no bytecode".to_string());
+  };
+  lower(&class_name, &name, &descriptor, method.access_flags & 0x0008 != 0, code, pool)
+}
+
+pub fn lower(class_name: &str, name: &str, descriptor: &str, is_static: bool, code: &CodeAttribute, pool: &ConstantPool) -> Result<Function, String> {
+  let signature = MethodDescriptor::parse(descriptor)?;
+  let mut instructions = Vec::new();
+  let mut pc = 0;
+  for code_byte in &code.code {
+    instructions.push((pc, code_byte));
+    pc += 1 + code_byte.data.len();
+  }
+  if instructions.is_empty() {
+    return Err("empty bytecode".to_string());
+  }
+  let mut lowering = Lowering {
+    pool,
+    instructions,
+    function: Function {
+      class_name: class_name.to_string(),
+      name: name.to_string(),
+      descriptor: descriptor.to_string(),
+      is_static,
+      params: Vec::new(),
+      types: Vec::new(),
+      blocks: Vec::new(),
+    },
+    insts: Vec::new(),
+    block_ids: Vec::new(),
+    block_of_pc: HashMap::new(),
+  };
+  let blocks = lowering.split_blocks(code)?;
+
+  // 引数の値
+  let mut locals = vec![None; code.max_locals as usize];
+  let mut slot = 0;
+  let receiver = (!is_static).then_some(Type::Ref);
+  for value_type in receiver.into_iter().chain(signature.parameters.iter().map(Type::of)) {
+    let value = lowering.function.new_value(value_type);
+    lowering.function.params.push(value);
+    *locals.get_mut(slot).ok_or("max_locals is too small for the parameters")? = Some(value);
+    slot += if value_type.is_wide() { 2 } else { 1 };
+  }
+  let params = FrameState { locals, stack: Vec::new() };
+
+  // 入口のブロックに戻る分岐があれば、引数を渡すだけのブロックを前に置く
+  let successors: Vec<Vec<usize>> =
+    blocks.iter().map(|block| block.successors.iter().copied().chain(block.handlers.iter().map(|(_, target)| *target)).collect()).collect();
+  let predecessors = predecessors(&successors);
+  let order = reverse_postorder(&successors, 0);
+  let offset = if predecessors[0].is_empty() { 0 } else { 1 };
+  lowering.block_ids = vec![None; blocks.len()];
+  for (i, &block) in order.iter().enumerate() {
+    lowering.block_ids[block] = Some(BlockId(i + offset));
+  }
+  let is_handler: Vec<bool> = (0..blocks.len()).map(|i| blocks.iter().any(|block| block.handlers.iter().any(|(_, target)| *target == i))).collect();
+  for &block in &order {
+    if is_handler[block] && blocks.iter().any(|b| b.successors.contains(&block)) {
+      return Err(format!("exception handler at pc {} is also reached by normal control flow", blocks[block].pc));
+    }
+  }
+
+  let entry_types = lowering.infer_types(&blocks, &order, &is_handler, &params)?;
+
+  // 値を割り当てる (逆後順なので、先行ブロックが1つならその出口の値をそのまま使える)
+  let mut exits: Vec<Option<FrameState>> = vec![None; blocks.len()];
+  let mut ir_blocks: Vec<Option<Block>> = vec![None; blocks.len()];
+  let mut phis = Vec::new();
+  for &block in &order {
+    let code_block = &blocks[block];
+    let types = entry_types[block].as_ref().ok_or_else(|| format!("unreachable block at pc {}", code_block.pc))?;
+    let single = predecessors[block].len() == 1 && (block != 0 || offset == 0);
+    let mut state = if block == 0 && offset == 0 {
+      params.clone()
+    } else if single && !is_handler[block] {
+      exits[predecessors[block][0]].clone().ok_or_else(|| format!("block at pc {} is visited before its predecessor", code_block.pc))?
+    } else {
+      let inherited = single.then(|| exits[predecessors[block][0]].clone()).flatten();
+      let mut locals = Vec::new();
+      for (i, local) in types.locals.iter().enumerate() {
+        locals.push(match (local, &inherited) {
+          (None, _) => None,
+          (Some(_), Some(inherited)) => inherited.locals[i],
+          (Some(local), None) => {
+            let value = lowering.phi(*local, code_block.pc);
+            phis.push((block, Slot::Local(i), value));
+            Some(value)
+          },
+        });
+      }
+      let mut stack = Vec::new();
+      if !is_handler[block] {
+        for (i, value_type) in types.stack.iter().enumerate() {
+          let value = lowering.phi(*value_type, code_block.pc);
+          phis.push((block, Slot::Stack(i), value));
+          stack.push(value);
+        }
+      }
+      FrameState { locals, stack }
+    };
+    if is_handler[block] {
+      let catch_types = lowering.catch_types(code, code_block.pc)?;
+      let exception = lowering.function.new_value(Type::Ref);
+      lowering.insts.push(Inst { dest: Some(exception), op: Op::Catch(catch_types), pc: code_block.pc });
+      state.stack = vec![exception];
+    }
+    let terminator = lowering.simulate(&blocks, block, &mut state)?;
+    let handlers = code_block
+      .handlers
+      .iter()
+      .map(|(catch_type, target)| Handler { catch_type: catch_type.clone(), block: lowering.block_id(*target) })
+      .collect();
+    ir_blocks[block] = Some(Block { pc: code_block.pc, insts: std::mem::take(&mut lowering.insts), terminator, handlers });
+    exits[block] = Some(state);
+  }
+
+  // phiの入力を先行ブロックの出口の値で埋める
+  for (block, slot, value) in phis {
+    let mut inputs = Vec::new();
+    if block == 0 && offset == 1 {
+      let Slot::Local(i) = slot else {
+        return Err("operand stack is not empty at method entry".to_string());
+      };
+      inputs.push((BlockId(0), params.locals[i].ok_or("undefined local variable at method entry")?));
+    }
+    for &predecessor in &predecessors[block] {
+      let exit = exits[predecessor].as_ref().ok_or("predecessor is not lowered")?;
+      let input = match slot {
+        Slot::Local(i) => exit.locals[i],
+        Slot::Stack(i) => exit.stack.get(i).copied(),
+      };
+      let input = input.ok_or_else(|| format!("undefined value flows into pc {}", blocks[block].pc))?;
+      inputs.push((lowering.block_id(predecessor), input));
+    }
+    let ir_block = ir_blocks[block].as_mut().ok_or("phi in an unlowered block")?;
+    if let Some(inst) = ir_block.insts.iter_mut().find(|inst| inst.dest == Some(value)) {
+      inst.op = Op::Phi(inputs);
+    }
+  }
+
+  let mut function = lowering.function;
+  if offset == 1 {
+    function.blocks.push(Block { pc: 0, insts: Vec::new(), terminator: Terminator::Goto(BlockId(1)), handlers: Vec::new() });
+  }
+  function.blocks.extend(order.iter().filter_map(|&block| ir_blocks[block].take()));
+  propagate_copies(&mut function);
+  Ok(function)
+}
+
+struct Lowering<'a> {
+  pool: &'a ConstantPool,
+  // 命令の位置と命令
+  instructions: Vec<(usize, &'a CodeByte)>,
+  function: Function,
+  // 変換中のブロックの命令
+  insts: Vec<Inst>,
+  // バイトコードのブロックに対応するIRのブロック (到達できなければNone)
+  block_ids: Vec<Option<BlockId>>,
+  // ブロックの先頭の位置とブロックの番号
+  block_of_pc: HashMap<usize, usize>,
+}
+
+impl Lowering<'_> {
+  // 制御フローグラフのブロックを変換に使う形にする
+  // 例外の範囲では例外を投げる命令の後でもブロックを分け、ハンドラへの辺はその命令を含むブロックからだけ張る
+  fn split_blocks(&mut self, code: &CodeAttribute) -> Result<Vec<CodeBlock>, String> {
+    let index_of_pc: HashMap<usize, usize> = self.instructions.iter().enumerate().map(|(i, &(pc, _))| (pc, i)).collect();
+    let in_try = |pc: usize| code.exception_table.iter().any(|entry| (entry.start_pc as usize..entry.end_pc as usize).contains(&pc));
+    let split_after = |pc: usize| in_try(pc) && self.may_throw(self.instructions[index_of_pc[&pc]].1);
+    let graph = ControlFlowGraph::build_split(code, self.pool, &split_after)?;
+
+    let mut code_blocks = Vec::new();
+    for (index, block) in graph.blocks.iter().enumerate() {
+      self.block_of_pc.insert(block.start_pc, index);
+      let start = index_of_pc[&block.start_pc];
+      let end = start + block.instructions.len();
+      let throws = self.instructions[start..end].iter().any(|(_, code_byte)| self.may_throw(code_byte));
+      let mut successors = Vec::new();
+      let mut handlers = Vec::new();
+      for edge in &block.successors {
+        match &edge.kind {
+          EdgeKind::Jsr | EdgeKind::Ret => return Err("jsr/ret subroutines are not supported".to_string()),
+          EdgeKind::Exception(catch_type) => {
+            if throws {
+              handlers.push((catch_type.clone(), edge.target));
+            }
+          },
+          _ => {
+            if !successors.contains(&edge.target) {
+              successors.push(edge.target);
+            }
+          },
+        }
+      }
+      code_blocks.push(CodeBlock { pc: block.start_pc, start, end, successors, handlers });
+    }
+    Ok(code_blocks)
+  }
+
+  // 例外を投げることがある命令
+  fn may_throw(&self, code_byte: &CodeByte) -> bool {
+    match code_byte.opcode {
+      0x12..=0x14 => {
+        let index = if code_byte.opcode == 0x12 { code_byte.data[0] as u16 } else { u16::from_be_bytes([code_byte.data[0], code_byte.data[1]]) };
+        !matches!(
+          self.pool.get_class(index),
+          Ok(PoolConstant::Integer { .. } | PoolConstant::Float { .. } | PoolConstant::Long { .. } | PoolConstant::Double { .. } | PoolConstant::String { .. })
+        )
+      },
+      0x2e..=0x35 | 0x4f..=0x56 | 0x6c | 0x6d | 0x70 | 0x71 | 0xb2..=0xbf | 0xc0..=0xc3 | 0xc5 => true,
+      _ => false,
+    }
+  }
+
+  fn block_id(&self, block: usize) -> BlockId {
+    self.block_ids[block].expect("successor of a reachable block is reachable")
+  }
+
+  fn phi(&mut self, value_type: Type, pc: usize) -> ValueId {
+    let value = self.function.new_value(value_type);
+    self.insts.push(Inst { dest: Some(value), op: Op::Phi(Vec::new()), pc });
+    value
+  }
+
+  // 例外テーブルでこのハンドラを指す型 (1つでも全ての例外を受けるものがあれば空)
+  fn catch_types(&self, code: &CodeAttribute, pc: usize) -> Result<Vec<String>, String> {
+    let mut catch_types = Vec::new();
+    for entry in code.exception_table.iter().filter(|entry| entry.handler_pc as usize == pc) {
+      if entry.catch_type == 0 {
+        return Ok(Vec::new());
+      }
+      let name = self.pool.get_class_name(entry.catch_type)?;
+      if !catch_types.contains(&name) {
+        catch_types.push(name);
+      }
+    }
+    Ok(catch_types)
+  }
+
+  // ブロックの入口の型を不動点まで求める (値は仮に割り当てて捨てる)
+  fn infer_types(
+    &mut self,
+    blocks: &[CodeBlock],
+    order: &[usize],
+    is_handler: &[bool],
+    params: &FrameState,
+  ) -> Result<Vec<Option<TypeState>>, String> {
+    let mut entry_types: Vec<Option<TypeState>> = vec![None; blocks.len()];
+    // 入口に戻る分岐があれば、引数の型と合流する
+    entry_types[0] = Some(self.types_of(params));
+    let mut changed = true;
+    while changed {
+      changed = false;
+      for &block in order {
+        let Some(types) = entry_types[block].clone() else {
+          continue;
+        };
+        let mark = self.function.types.len();
+        let mut state = FrameState {
+          locals: types.locals.iter().map(|local| local.map(|t| self.function.new_value(t))).collect(),
+          stack: types.stack.iter().map(|t| self.function.new_value(*t)).collect(),
+        };
+        if is_handler[block] {
+          state.stack = vec![self.function.new_value(Type::Ref)];
+        }
+        self.simulate(blocks, block, &mut state)?;
+        let exit = self.types_of(&state);
+        self.insts.clear();
+        self.function.types.truncate(mark);
+
+        let code_block = &blocks[block];
+        let handler_types = TypeState { locals: exit.locals.clone(), stack: vec![Type::Ref] };
+        let edges = code_block.successors.iter().map(|target| (*target, &exit)).chain(code_block.handlers.iter().map(|(_, target)| (*target, &handler_types)));
+        for (target, incoming) in edges {
+          let merged = match &entry_types[target] {
+            None => incoming.clone(),
+            Some(current) => merge_types(current, incoming, blocks[target].pc)?,
+          };
+          if entry_types[target].as_ref() != Some(&merged) {
+            entry_types[target] = Some(merged);
+            changed = true;
+          }
+        }
+      }
+    }
+    Ok(entry_types)
+  }
+
+  fn types_of(&self, state: &FrameState) -> TypeState {
+    TypeState {
+      locals: state.locals.iter().map(|local| local.map(|v| self.function.value_type(v))).collect(),
+      stack: state.stack.iter().map(|v| self.function.value_type(*v)).collect(),
+    }
+  }
+
+  // ブロックの命令を変換し、終端を返す
+  fn simulate(&mut self, blocks: &[CodeBlock], block: usize, state: &mut FrameState) -> Result<Terminator, String> {
+    let code_block = &blocks[block];
+    for i in code_block.start..code_block.end {
+      let (pc, code_byte) = self.instructions[i];
+      let next = self.instructions.get(i + 1).map(|(pc, _)| *pc);
+      if let Some(terminator) = self.instruction(pc, code_byte, next, state).map_err(|e| format!("{} at pc {}", e, pc))? {
+        return Ok(terminator);
+      }
+    }
+    let next = code_block.successors.first().ok_or("block without successor")?;
+    Ok(Terminator::Goto(self.block_id(*next)))
+  }
+
+  fn emit(&mut self, pc: usize, op: Op, value_type: Option<Type>) -> Option<ValueId> {
+    let dest = value_type.map(|t| self.function.new_value(t));
+    self.insts.push(Inst { dest, op, pc });
+    dest
+  }
+
+  fn push(&mut self, state: &mut FrameState, pc: usize, op: Op, value_type: Type) {
+    if let Some(value) = self.emit(pc, op, Some(value_type)) {
+      state.stack.push(value);
+    }
+  }
+
+  fn null_check(&mut self, pc: usize, value: ValueId) {
+    self.emit(pc, Op::NullCheck(value), None);
+  }
+
+  fn target(&self, pc: usize, offset: i32) -> Result<BlockId, String> {
+    let target = (pc as i64 + offset as i64) as usize;
+    let block = self.block_of_pc.get(&target).ok_or_else(|| format!("invalid branch target {}", target))?;
+    Ok(self.block_id(*block))
+  }
+
+  fn next_block(&self, next: Option<usize>) -> Result<BlockId, String> {
+    let next = next.ok_or("control flow falls off the end of the code")?;
+    Ok(self.block_id(self.block_of_pc[&next]))
+  }
+
+  fn member_ref(&self, index: u16) -> Result<MemberRef, String> {
+    let (class, name, descriptor) = self.pool.get_member_ref(index)?;
+    Ok(MemberRef { class, name, descriptor })
+  }
+
+  fn constant(&self, index: u16) -> Result<(Constant, Type), String> {
+    Ok(match self.pool.get_class(index)? {
+      PoolConstant::Integer { bytes } => (Constant::Int(*bytes as i32), Type::Int),
+      PoolConstant::Float { bytes } => (Constant::Float(f32::from_bits(*bytes)), Type::Float),
+      PoolConstant::Long { high_bytes, low_bytes } => (Constant::Long(((*high_bytes as u64) << 32 | *low_bytes as u64) as i64), Type::Long),
+      PoolConstant::Double { high_bytes, low_bytes } => (Constant::Double(f64::from_bits((*high_bytes as u64) << 32 | *low_bytes as u64)), Type::Double),
+      PoolConstant::String { string_index } => (Constant::String(self.pool.get_utf8(*string_index)?), Type::Ref),
+      PoolConstant::Class { name_index } => (Constant::Class(self.pool.get_utf8(*name_index)?), Type::Ref),
+      PoolConstant::MethodType { descriptor_index } => (Constant::Other(format!("MethodType {}", self.pool.get_utf8(*descriptor_index)?)), Type::Ref),
+      PoolConstant::MethodHandle { reference_kind, reference_index } => {
+        let (class, name, descriptor) = self.pool.get_member_ref(*reference_index)?;
+        (Constant::Other(format!("MethodHandle {} {}.{}:{}", reference_kind, class, name, descriptor)), Type::Ref)
+      },
+      PoolConstant::Dynamic { bootstrap_method_attr_index, name_and_type_index } => {
+        let (name, descriptor) = self.pool.get_name_and_type(*name_and_type_index)?;
+        let value_type = Type::of(&FieldType::parse(&descriptor)?);
+        (Constant::Other(format!("Dynamic #{}:{}:{}", bootstrap_method_attr_index, name, descriptor)), value_type)
+      },
+      c => return Err(format!("Unsupported ldc constant: {:?}", c)),
+    })
+  }
+
+  fn pop(state: &mut FrameState) -> Result<ValueId, String> {
+    state.stack.pop().ok_or_else(|| "operand stack underflow".to_string())
+  }
+
+  fn load(&self, state: &mut FrameState, index: usize) -> Result<(), String> {
+    let value = state.locals.get(index).copied().flatten().ok_or_else(|| format!("undefined local variable {}", index))?;
+    state.stack.push(value);
+    Ok(())
+  }
+
+  fn store(&self, state: &mut FrameState, index: usize) -> Result<(), String> {
+    let value = Self::pop(state)?;
+    let wide = self.function.value_type(value).is_wide();
+    if index + usize::from(wide) >= state.locals.len() {
+      return Err(format!("local variable {} is out of range", index));
+    }
+    // 2スロットの値の後半や、上書きされた2スロットの値は使えなくなる
+    if index > 0 && let Some(previous) = state.locals[index - 1] && self.function.value_type(previous).is_wide() {
+      state.locals[index - 1] = None;
+    }
+    state.locals[index] = Some(value);
+    if wide {
+      state.locals[index + 1] = None;
+    }
+    Ok(())
+  }
+
+  // pop, dupなどのスタック操作
+  fn shuffle(&self, state: &mut FrameState, opcode: u8) -> Result<(), String> {
+    let shuffle = StackShuffle::parse(opcode, |depth| state.stack.iter().rev().nth(depth).map(|value| self.function.value_type(*value).is_wide()))?;
+    let len = state.stack.len();
+    match shuffle {
+      StackShuffle::Pop(n) => state.stack.truncate(len - n),
+      StackShuffle::Swap => state.stack.swap(len - 1, len - 2),
+      StackShuffle::Dup { top, below } => {
+        let values: Vec<ValueId> = state.stack[len - top..].to_vec();
+        let at = len - top - below;
+        for (i, value) in values.into_iter().enumerate() {
+          state.stack.insert(at + i, value);
+        }
+      },
+    }
+    Ok(())
+  }
+
+  fn invoke_args(state: &mut FrameState, descriptor: &str, receiver: bool) -> Result<Vec<ValueId>, String> {
+    let signature = MethodDescriptor::parse(descriptor)?;
+    let count = signature.parameters.len() + usize::from(receiver);
+    if state.stack.len() < count {
+      return Err("operand stack underflow".to_string());
+    }
+    Ok(state.stack.split_off(state.stack.len() - count))
+  }
+
+  fn return_type(descriptor: &str) -> Result<Option<Type>, String> {
+    Ok(MethodDescriptor::parse(descriptor)?.return_type.as_ref().map(Type::of))
+  }
+
+  fn instruction(&mut self, pc: usize, code_byte: &CodeByte, next: Option<usize>, state: &mut FrameState) -> Result<Option<Terminator>, String> {
+    let data = &code_byte.data;
+    let u16_at = |at: usize| -> Result<u16, String> {
+      Ok(u16::from_be_bytes([*data.get(at).ok_or("truncated operand")?, *data.get(at + 1).ok_or("truncated operand")?]))
+    };
+    let i32_at = |at: usize| -> Result<i32, String> {
+      let bytes = data.get(at..at + 4).ok_or("truncated operand")?;
+      Ok(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    let opcode = code_byte.opcode;
+    match opcode {
+      0x00 => {},
+      0x01 => self.push(state, pc, Op::Const(Constant::Null), Type::Ref),
+      0x02..=0x08 => self.push(state, pc, Op::Const(Constant::Int(opcode as i32 - 3)), Type::Int),
+      0x09 | 0x0a => self.push(state, pc, Op::Const(Constant::Long(opcode as i64 - 9)), Type::Long),
+      0x0b..=0x0d => self.push(state, pc, Op::Const(Constant::Float((opcode - 0x0b) as f32)), Type::Float),
+      0x0e | 0x0f => self.push(state, pc, Op::Const(Constant::Double((opcode - 0x0e) as f64)), Type::Double),
+      0x10 => self.push(state, pc, Op::Const(Constant::Int(data[0] as i8 as i32)), Type::Int),
+      0x11 => self.push(state, pc, Op::Const(Constant::Int(u16_at(0)? as i16 as i32)), Type::Int),
+      0x12..=0x14 => {
+        let index = if opcode == 0x12 { data[0] as u16 } else { u16_at(0)? };
+        let (constant, value_type) = self.constant(index)?;
+        self.push(state, pc, Op::Const(constant), value_type);
+      },
+      0x15..=0x19 => self.load(state, data[0] as usize)?,
+      0x1a..=0x2d => self.load(state, ((opcode - 0x1a) % 4) as usize)?,
+      0x2e..=0x35 => {
+        let index = Self::pop(state)?;
+        let array = Self::pop(state)?;
+        self.null_check(pc, array);
+        let value_type = match opcode {
+          0x2f => Type::Long,
+          0x30 => Type::Float,
+          0x31 => Type::Double,
+          0x32 => Type::Ref,
+          _ => Type::Int,
+        };
+        self.push(state, pc, Op::ArrayLoad(array, index), value_type);
+      },
+      0x36..=0x3a => self.store(state, data[0] as usize)?,
+      0x3b..=0x4e => self.store(state, ((opcode - 0x3b) % 4) as usize)?,
+      0x4f..=0x56 => {
+        let value = Self::pop(state)?;
+        let index = Self::pop(state)?;
+        let array = Self::pop(state)?;
+        self.null_check(pc, array);
+        self.emit(pc, Op::ArrayStore(array, index, value), None);
+      },
+      0x57..=0x5f => self.shuffle(state, opcode)?,
+      0x60..=0x83 => {
+        const TYPES: [Type; 4] = [Type::Int, Type::Long, Type::Float, Type::Double];
+        let (op, value_type) = match opcode {
+          0x60..=0x73 => {
+            let ops = [BinaryOp::Add, BinaryOp::Sub, BinaryOp::Mul, BinaryOp::Div, BinaryOp::Rem];
+            (Some(ops[(opcode - 0x60) as usize / 4]), TYPES[(opcode - 0x60) as usize % 4])
+          },
+          0x74..=0x77 => (None, TYPES[(opcode - 0x74) as usize]),
+          0x78..=0x7d => ([BinaryOp::Shl, BinaryOp::Shr, BinaryOp::Ushr].get((opcode - 0x78) as usize / 2).copied(), TYPES[(opcode - 0x78) as usize % 2]),
+          _ => ([BinaryOp::And, BinaryOp::Or, BinaryOp::Xor].get((opcode - 0x7e) as usize / 2).copied(), TYPES[(opcode - 0x7e) as usize % 2]),
+        };
+        let right = Self::pop(state)?;
+        match op {
+          Some(op) => {
+            let left = Self::pop(state)?;
+            self.push(state, pc, Op::Binary(op, left, right), value_type);
+          },
+          None => self.push(state, pc, Op::Unary(UnaryOp::Neg, right), value_type),
+        }
+      },
+      0x84 => {
+        let (index, delta) = (data[0] as usize, data[1] as i8 as i32);
+        self.increment(pc, state, index, delta)?;
+      },
+      0x85..=0x90 => {
+        const TARGETS: [Type; 12] =
+          [Type::Long, Type::Float, Type::Double, Type::Int, Type::Float, Type::Double, Type::Int, Type::Long, Type::Double, Type::Int, Type::Long, Type::Float];
+        let target = TARGETS[(opcode - 0x85) as usize];
+        let value = Self::pop(state)?;
+        self.push(state, pc, Op::Unary(UnaryOp::Convert(target), value), target);
+      },
+      0x91..=0x93 => {
+        let op = [UnaryOp::ToByte, UnaryOp::ToChar, UnaryOp::ToShort][(opcode - 0x91) as usize];
+        let value = Self::pop(state)?;
+        self.push(state, pc, Op::Unary(op, value), Type::Int);
+      },
+      0x94..=0x98 => {
+        let op = match opcode {
+          0x94 => CompareOp::Compare,
+          0x95 | 0x97 => CompareOp::CompareLess,
+          _ => CompareOp::CompareGreater,
+        };
+        let right = Self::pop(state)?;
+        let left = Self::pop(state)?;
+        self.push(state, pc, Op::Compare(op, left, right), Type::Int);
+      },
+      0x99..=0xa6 | 0xc6 | 0xc7 => {
+        const CONDITIONS: [Condition; 6] =
+          [Condition::Equal, Condition::NotEqual, Condition::Less, Condition::GreaterEqual, Condition::Greater, Condition::LessEqual];
+        let (condition, binary) = match opcode {
+          0x99..=0x9e => (CONDITIONS[(opcode - 0x99) as usize], false),
+          0x9f..=0xa4 => (CONDITIONS[(opcode - 0x9f) as usize], true),
+          0xa5 | 0xa6 => (CONDITIONS[(opcode - 0xa5) as usize], true),
+          0xc6 => (Condition::Equal, false),
+          _ => (Condition::NotEqual, false),
+        };
+        let right = if binary { Some(Self::pop(state)?) } else { None };
+        let left = Self::pop(state)?;
+        let then_block = self.target(pc, u16_at(0)? as i16 as i32)?;
+        let else_block = self.next_block(next)?;
+        return Ok(Some(Terminator::If { condition, left, right, then_block, else_block }));
+      },
+      0xa7 => return Ok(Some(Terminator::Goto(self.target(pc, u16_at(0)? as i16 as i32)?))),
+      0xc8 => return Ok(Some(Terminator::Goto(self.target(pc, i32_at(0)?)?))),
+      0xaa | 0xab => {
+        let value = Self::pop(state)?;
+        let padding = (4 - (pc + 1) % 4) % 4;
+        let default = self.target(pc, i32_at(padding)?)?;
+        let mut cases = Vec::new();
+        if opcode == 0xaa {
+          let (low, high) = (i32_at(padding + 4)?, i32_at(padding + 8)?);
+          for (i, key) in (low..=high).enumerate() {
+            cases.push((key, self.target(pc, i32_at(padding + 12 + i * 4)?)?));
+          }
+        } else {
+          let pairs = i32_at(padding + 4)?;
+          for i in 0..pairs.max(0) as usize {
+            cases.push((i32_at(padding + 8 + i * 8)?, self.target(pc, i32_at(padding + 12 + i * 8)?)?));
+          }
+        }
+        return Ok(Some(Terminator::Switch { value, cases, default }));
+      },
+      0xac..=0xb0 => return Ok(Some(Terminator::Return(Some(Self::pop(state)?)))),
+      0xb1 => return Ok(Some(Terminator::Return(None))),
+      0xb2..=0xb5 => {
+        let member = self.member_ref(u16_at(0)?)?;
+        let value_type = Type::of(&FieldType::parse(&member.descriptor)?);
+        match opcode {
+          0xb2 => self.push(state, pc, Op::GetStatic(member), value_type),
+          0xb3 => {
+            let value = Self::pop(state)?;
+            self.emit(pc, Op::PutStatic(member, value), None);
+          },
+          0xb4 => {
+            let object = Self::pop(state)?;
+            self.null_check(pc, object);
+            self.push(state, pc, Op::GetField(member, object), value_type);
+          },
+          _ => {
+            let value = Self::pop(state)?;
+            let object = Self::pop(state)?;
+            self.null_check(pc, object);
+            self.emit(pc, Op::PutField(member, object, value), None);
+          },
+        }
+      },
+      0xb6..=0xb9 => {
+        let member = self.member_ref(u16_at(0)?)?;
+        let kind = [InvokeKind::Virtual, InvokeKind::Special, InvokeKind::Static, InvokeKind::Interface][(opcode - 0xb6) as usize];
+        let receiver = kind != InvokeKind::Static;
+        let args = Self::invoke_args(state, &member.descriptor, receiver)?;
+        if receiver {
+          self.null_check(pc, args[0]);
+        }
+        let return_type = Self::return_type(&member.descriptor)?;
+        if let Some(value) = self.emit(pc, Op::Invoke(kind, member, args), return_type) {
+          state.stack.push(value);
+        }
+      },
+      0xba => {
+        let PoolConstant::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index } = self.pool.get_class(u16_at(0)?)? else {
+          return Err("invokedynamic does not refer to an InvokeDynamic constant".to_string());
+        };
+        let (bootstrap, name_and_type_index) = (*bootstrap_method_attr_index, *name_and_type_index);
+        let (name, descriptor) = self.pool.get_name_and_type(name_and_type_index)?;
+        let args = Self::invoke_args(state, &descriptor, false)?;
+        let return_type = Self::return_type(&descriptor)?;
+        if let Some(value) = self.emit(pc, Op::InvokeDynamic { name, descriptor, bootstrap, args }, return_type) {
+          state.stack.push(value);
+        }
+      },
+      0xbb => {
+        let class = self.pool.get_class_name(u16_at(0)?)?;
+        self.push(state, pc, Op::New(class), Type::Ref);
+      },
+      0xbc => {
+        const ELEMENTS: [&str; 8] = ["Z", "C", "F", "D", "B", "S", "I", "J"];
+        let element = ELEMENTS.get((data[0] as usize).wrapping_sub(4)).ok_or_else(|| format!("invalid array type {}", data[0]))?;
+        let length = Self::pop(state)?;
+        self.push(state, pc, Op::NewArray(element.to_string(), length), Type::Ref);
+      },
+      0xbd => {
+        let class = self.pool.get_class_name(u16_at(0)?)?;
+        let element = if class.starts_with('[') { class } else { format!("L{};", class) };
+        let length = Self::pop(state)?;
+        self.push(state, pc, Op::NewArray(element, length), Type::Ref);
+      },
+      0xbe => {
+        let array = Self::pop(state)?;
+        self.null_check(pc, array);
+        self.push(state, pc, Op::ArrayLength(array), Type::Int);
+      },
+      0xbf => {
+        let exception = Self::pop(state)?;
+        self.null_check(pc, exception);
+        return Ok(Some(Terminator::Throw(exception)));
+      },
+      0xc0 => {
+        let class = self.pool.get_class_name(u16_at(0)?)?;
+        let value = Self::pop(state)?;
+        self.push(state, pc, Op::CheckCast(class, value), Type::Ref);
+      },
+      0xc1 => {
+        let class = self.pool.get_class_name(u16_at(0)?)?;
+        let value = Self::pop(state)?;
+        self.push(state, pc, Op::InstanceOf(class, value), Type::Int);
+      },
+      0xc2 | 0xc3 => {
+        let object = Self::pop(state)?;
+        self.null_check(pc, object);
+        let op = if opcode == 0xc2 { Op::MonitorEnter(object) } else { Op::MonitorExit(object) };
+        self.emit(pc, op, None);
+      },
+      0xc4 => {
+        let index = u16_at(1)? as usize;
+        match data[0] {
+          0x15..=0x19 => self.load(state, index)?,
+          0x36..=0x3a => self.store(state, index)?,
+          0x84 => self.increment(pc, state, index, u16_at(3)? as i16 as i32)?,
+          _ => return Err(format!("unsupported wide instruction {:#x}", data[0])),
+        }
+      },
+      0xc5 => {
+        let class = self.pool.get_class_name(u16_at(0)?)?;
+        let dimensions = data[2] as usize;
+        if state.stack.len() < dimensions {
+          return Err("operand stack underflow".to_string());
+        }
+        let lengths = state.stack.split_off(state.stack.len() - dimensions);
+        self.push(state, pc, Op::MultiNewArray(class, lengths), Type::Ref);
+      },
+      _ => return Err(format!("unsupported instruction {}", code_byte.name)),
+    }
+    Ok(None)
+  }
+
+  fn increment(&mut self, pc: usize, state: &mut FrameState, index: usize, delta: i32) -> Result<(), String> {
+    let value = state.locals.get(index).copied().flatten().ok_or_else(|| format!("undefined local variable {}", index))?;
+    let delta = self.emit(pc, Op::Const(Constant::Int(delta)), Some(Type::Int)).ok_or("constant without value")?;
+    let result = self.emit(pc, Op::Binary(BinaryOp::Add, value, delta), Some(Type::Int));
+    state.locals[index] = result;
+    Ok(())
+  }
+}
+
+fn merge_types(current: &TypeState, incoming: &TypeState, pc: usize) -> Result<TypeState, String> {
+  if current.stack != incoming.stack {
+    return Err(format!("inconsistent operand stack at pc {}", pc));
+  }
+  let locals = current.locals.iter().zip(&incoming.locals).map(|(a, b)| if a == b { *a } else { None }).collect();
+  Ok(TypeState { locals, stack: current.stack.clone() })
+}
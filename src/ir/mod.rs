@@ -0,0 +1,332 @@
+pub mod lower;
+pub mod passes;
+pub mod printer;
+
+use std::collections::HashMap;
+
+use crate::util::descriptor::FieldType;
+
+// メソッド本体のSSA形式の中間表現
+// 値は一度だけ定義され、ローカル変数やオペランドスタックの合流は基本ブロックの先頭のphiで表す
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ValueId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub usize);
+
+// 値の型 (boolean, byte, char, shortはintとして扱う)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+  Int,
+  Long,
+  Float,
+  Double,
+  Ref,
+}
+
+impl Type {
+  pub fn of(field_type: &FieldType) -> Type {
+    match field_type {
+      FieldType::Long => Type::Long,
+      FieldType::Float => Type::Float,
+      FieldType::Double => Type::Double,
+      FieldType::Object(_) | FieldType::Array(_) => Type::Ref,
+      _ => Type::Int,
+    }
+  }
+
+  // longとdoubleはローカル変数を2スロット使い、pop2などでは1つで2つ分になる
+  pub fn is_wide(self) -> bool {
+    matches!(self, Type::Long | Type::Double)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+  Int(i32),
+  Long(i64),
+  Float(f32),
+  Double(f64),
+  Null,
+  String(String),
+  Class(String),
+  // MethodType、MethodHandle、動的定数
+  Other(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+  Add,
+  Sub,
+  Mul,
+  Div,
+  Rem,
+  And,
+  Or,
+  Xor,
+  Shl,
+  Shr,
+  Ushr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+  Neg,
+  // i2l, f2d などの変換
+  Convert(Type),
+  // i2b, i2c, i2s
+  ToByte,
+  ToChar,
+  ToShort,
+}
+
+// lcmp, fcmpl/dcmpl (NaNなら-1), fcmpg/dcmpg (NaNなら1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+  Compare,
+  CompareLess,
+  CompareGreater,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+  Equal,
+  NotEqual,
+  Less,
+  GreaterEqual,
+  Greater,
+  LessEqual,
+}
+
+impl Condition {
+  pub fn evaluate<T: PartialOrd>(self, left: T, right: T) -> bool {
+    match self {
+      Condition::Equal => left == right,
+      Condition::NotEqual => left != right,
+      Condition::Less => left < right,
+      Condition::GreaterEqual => left >= right,
+      Condition::Greater => left > right,
+      Condition::LessEqual => left <= right,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvokeKind {
+  Virtual,
+  Special,
+  Static,
+  Interface,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberRef {
+  pub class: String,
+  pub name: String,
+  pub descriptor: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+  Const(Constant),
+  Copy(ValueId),
+  // 先行ブロックごとの値
+  Phi(Vec<(BlockId, ValueId)>),
+  // 例外ハンドラの先頭で受け取る例外 (空ならfinallyなど全ての例外)
+  Catch(Vec<String>),
+  Unary(UnaryOp, ValueId),
+  Binary(BinaryOp, ValueId, ValueId),
+  Compare(CompareOp, ValueId, ValueId),
+  // nullならNullPointerExceptionを投げる (フィールドや配列、メソッド呼び出しの前に置く)
+  NullCheck(ValueId),
+  GetField(MemberRef, ValueId),
+  PutField(MemberRef, ValueId, ValueId),
+  GetStatic(MemberRef),
+  PutStatic(MemberRef, ValueId),
+  ArrayLoad(ValueId, ValueId),
+  ArrayStore(ValueId, ValueId, ValueId),
+  ArrayLength(ValueId),
+  // レシーバがあれば引数の先頭に置く
+  Invoke(InvokeKind, MemberRef, Vec<ValueId>),
+  InvokeDynamic { name: String, descriptor: String, bootstrap: u16, args: Vec<ValueId> },
+  New(String),
+  // 要素の型の記述子と長さ
+  NewArray(String, ValueId),
+  MultiNewArray(String, Vec<ValueId>),
+  CheckCast(String, ValueId),
+  InstanceOf(String, ValueId),
+  MonitorEnter(ValueId),
+  MonitorExit(ValueId),
+}
+
+impl Op {
+  pub fn operands(&self) -> Vec<ValueId> {
+    match self {
+      Op::Const(_) | Op::Catch(_) | Op::GetStatic(_) | Op::New(_) => vec![],
+      Op::Copy(v) | Op::Unary(_, v) | Op::NullCheck(v) | Op::GetField(_, v) | Op::PutStatic(_, v) | Op::ArrayLength(v)
+      | Op::NewArray(_, v) | Op::CheckCast(_, v) | Op::InstanceOf(_, v) | Op::MonitorEnter(v) | Op::MonitorExit(v) => vec![*v],
+      Op::Phi(inputs) => inputs.iter().map(|(_, v)| *v).collect(),
+      Op::Binary(_, a, b) | Op::Compare(_, a, b) | Op::PutField(_, a, b) | Op::ArrayLoad(a, b) => vec![*a, *b],
+      Op::ArrayStore(a, b, c) => vec![*a, *b, *c],
+      Op::Invoke(_, _, args) | Op::InvokeDynamic { args, .. } | Op::MultiNewArray(_, args) => args.clone(),
+    }
+  }
+
+  pub fn operands_mut(&mut self) -> Vec<&mut ValueId> {
+    match self {
+      Op::Const(_) | Op::Catch(_) | Op::GetStatic(_) | Op::New(_) => vec![],
+      Op::Copy(v) | Op::Unary(_, v) | Op::NullCheck(v) | Op::GetField(_, v) | Op::PutStatic(_, v) | Op::ArrayLength(v)
+      | Op::NewArray(_, v) | Op::CheckCast(_, v) | Op::InstanceOf(_, v) | Op::MonitorEnter(v) | Op::MonitorExit(v) => vec![v],
+      Op::Phi(inputs) => inputs.iter_mut().map(|(_, v)| v).collect(),
+      Op::Binary(_, a, b) | Op::Compare(_, a, b) | Op::PutField(_, a, b) | Op::ArrayLoad(a, b) => vec![a, b],
+      Op::ArrayStore(a, b, c) => vec![a, b, c],
+      Op::Invoke(_, _, args) | Op::InvokeDynamic { args, .. } | Op::MultiNewArray(_, args) => args.iter_mut().collect(),
+    }
+  }
+
+  // 結果を使わなければ取り除いてよい命令 (例外を投げず、副作用もない)
+  pub fn is_pure(&self, function: &Function, constants: &HashMap<ValueId, Constant>) -> bool {
+    match self {
+      Op::Const(_) | Op::Copy(_) | Op::Phi(_) | Op::Unary(..) | Op::Compare(..) => true,
+      Op::Binary(BinaryOp::Div | BinaryOp::Rem, _, divisor) => match constants.get(divisor) {
+        Some(Constant::Int(v)) => *v != 0,
+        Some(Constant::Long(v)) => *v != 0,
+        _ => !matches!(function.value_type(*divisor), Type::Int | Type::Long),
+      },
+      Op::Binary(..) => true,
+      _ => false,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inst {
+  // 値を作らない命令ならNone
+  pub dest: Option<ValueId>,
+  pub op: Op,
+  // 元になった命令の位置
+  pub pc: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+  Goto(BlockId),
+  // rightがNoneなら0 (参照ならnull) と比べる
+  If { condition: Condition, left: ValueId, right: Option<ValueId>, then_block: BlockId, else_block: BlockId },
+  Switch { value: ValueId, cases: Vec<(i32, BlockId)>, default: BlockId },
+  Return(Option<ValueId>),
+  Throw(ValueId),
+}
+
+impl Terminator {
+  pub fn successors(&self) -> Vec<BlockId> {
+    let mut successors = match self {
+      Terminator::Goto(target) => vec![*target],
+      Terminator::If { then_block, else_block, .. } => vec![*then_block, *else_block],
+      Terminator::Switch { cases, default, .. } => cases.iter().map(|(_, target)| *target).chain([*default]).collect(),
+      Terminator::Return(_) | Terminator::Throw(_) => vec![],
+    };
+    let mut seen = Vec::new();
+    successors.retain(|target| {
+      let first = !seen.contains(target);
+      seen.push(*target);
+      first
+    });
+    successors
+  }
+
+  pub fn operands(&self) -> Vec<ValueId> {
+    match self {
+      Terminator::Goto(_) | Terminator::Return(None) => vec![],
+      Terminator::If { left, right, .. } => std::iter::once(*left).chain(*right).collect(),
+      Terminator::Switch { value, .. } | Terminator::Return(Some(value)) | Terminator::Throw(value) => vec![*value],
+    }
+  }
+
+  pub fn operands_mut(&mut self) -> Vec<&mut ValueId> {
+    match self {
+      Terminator::Goto(_) | Terminator::Return(None) => vec![],
+      Terminator::If { left, right, .. } => std::iter::once(left).chain(right.as_mut()).collect(),
+      Terminator::Switch { value, .. } | Terminator::Return(Some(value)) | Terminator::Throw(value) => vec![value],
+    }
+  }
+
+  pub fn targets_mut(&mut self) -> Vec<&mut BlockId> {
+    match self {
+      Terminator::Goto(target) => vec![target],
+      Terminator::If { then_block, else_block, .. } => vec![then_block, else_block],
+      Terminator::Switch { cases, default, .. } => cases.iter_mut().map(|(_, target)| target).chain([default]).collect(),
+      Terminator::Return(_) | Terminator::Throw(_) => vec![],
+    }
+  }
+}
+
+// ブロック内の命令が例外を投げた時の飛び先 (例外テーブルの順)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Handler {
+  pub catch_type: Option<String>,
+  pub block: BlockId,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+  // 先頭の命令の位置
+  pub pc: usize,
+  // phiは先頭にまとめて置く
+  pub insts: Vec<Inst>,
+  pub terminator: Terminator,
+  pub handlers: Vec<Handler>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Function {
+  pub class_name: String,
+  pub name: String,
+  pub descriptor: String,
+  pub is_static: bool,
+  // thisと引数の値
+  pub params: Vec<ValueId>,
+  // 値ごとの型
+  pub types: Vec<Type>,
+  // blocks[0]が入口
+  pub blocks: Vec<Block>,
+}
+
+impl Function {
+  pub fn new_value(&mut self, value_type: Type) -> ValueId {
+    self.types.push(value_type);
+    ValueId(self.types.len() - 1)
+  }
+
+  pub fn value_type(&self, value: ValueId) -> Type {
+    self.types[value.0]
+  }
+
+  // 通常の後続と例外ハンドラ
+  pub fn successors(&self, block: BlockId) -> Vec<BlockId> {
+    let block = &self.blocks[block.0];
+    let mut successors = block.terminator.successors();
+    for handler in &block.handlers {
+      if !successors.contains(&handler.block) {
+        successors.push(handler.block);
+      }
+    }
+    successors
+  }
+
+  pub fn successor_lists(&self) -> Vec<Vec<usize>> {
+    (0..self.blocks.len()).map(|i| self.successors(BlockId(i)).into_iter().map(|b| b.0).collect()).collect()
+  }
+
+  // 定数の命令で定義された値
+  pub fn constants(&self) -> HashMap<ValueId, Constant> {
+    let mut constants = HashMap::new();
+    for inst in self.blocks.iter().flat_map(|block| &block.insts) {
+      if let (Some(dest), Op::Const(constant)) = (inst.dest, &inst.op) {
+        constants.insert(dest, constant.clone());
+      }
+    }
+    constants
+  }
+}
@@ -0,0 +1,554 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+  ir::{BinaryOp, BlockId, CompareOp, Condition, Constant, Function, Op, Terminator, Type, UnaryOp, ValueId},
+  util::graph::{predecessors, reverse_postorder},
+};
+
+// 変化がなくなるまで最適化を繰り返す
+pub fn optimize(function: &mut Function) {
+  loop {
+    let mut changed = fold_constants(function);
+    changed |= propagate_copies(function);
+    changed |= eliminate_null_checks(function);
+    changed |= eliminate_dead_code(function);
+    if !changed {
+      break;
+    }
+  }
+}
+
+// 定数の演算を畳み込み、定数で決まる分岐をgotoにする
+pub fn fold_constants(function: &mut Function) -> bool {
+  let mut constants = function.constants();
+  let mut changed = false;
+  for block in 0..function.blocks.len() {
+    for i in 0..function.blocks[block].insts.len() {
+      let inst = &function.blocks[block].insts[i];
+      let Some(op) = fold(&inst.op, &constants) else {
+        continue;
+      };
+      if let (Some(dest), Op::Const(constant)) = (inst.dest, &op) {
+        constants.insert(dest, constant.clone());
+      }
+      function.blocks[block].insts[i].op = op;
+      changed = true;
+    }
+    if let Some(target) = fold_terminator(&function.blocks[block].terminator, function, &constants) {
+      function.blocks[block].terminator = Terminator::Goto(target);
+      changed = true;
+    }
+  }
+  changed
+}
+
+fn fold(op: &Op, constants: &HashMap<ValueId, Constant>) -> Option<Op> {
+  let constant = |value: &ValueId| constants.get(value);
+  match op {
+    Op::Unary(op, value) => fold_unary(*op, constant(value)?).map(Op::Const),
+    Op::Binary(op, left, right) => match (constant(left), constant(right)) {
+      (Some(a), Some(b)) => fold_binary(*op, a, b).map(Op::Const),
+      (a, b) => simplify_binary(*op, *left, a, *right, b),
+    },
+    Op::Compare(op, left, right) => fold_compare(*op, constant(left)?, constant(right)?).map(|v| Op::Const(Constant::Int(v))),
+    // nullはどの型にもキャストでき、どの型のインスタンスでもない
+    Op::CheckCast(_, value) if constant(value) == Some(&Constant::Null) => Some(Op::Copy(*value)),
+    Op::InstanceOf(_, value) if constant(value) == Some(&Constant::Null) => Some(Op::Const(Constant::Int(0))),
+    _ => None,
+  }
+}
+
+fn fold_unary(op: UnaryOp, value: &Constant) -> Option<Constant> {
+  Some(match (op, value) {
+    (UnaryOp::Neg, Constant::Int(v)) => Constant::Int(v.wrapping_neg()),
+    (UnaryOp::Neg, Constant::Long(v)) => Constant::Long(v.wrapping_neg()),
+    (UnaryOp::Neg, Constant::Float(v)) => Constant::Float(-v),
+    (UnaryOp::Neg, Constant::Double(v)) => Constant::Double(-v),
+    (UnaryOp::ToByte, Constant::Int(v)) => Constant::Int(*v as i8 as i32),
+    (UnaryOp::ToChar, Constant::Int(v)) => Constant::Int(*v as u16 as i32),
+    (UnaryOp::ToShort, Constant::Int(v)) => Constant::Int(*v as i16 as i32),
+    // 浮動小数点数から整数への変換は、Javaと同じく飽和してNaNは0になる
+    (UnaryOp::Convert(target), value) => {
+      let (integer, float) = match value {
+        Constant::Int(v) => (Some(*v as i64), *v as f64),
+        Constant::Long(v) => (Some(*v), *v as f64),
+        Constant::Float(v) => (None, *v as f64),
+        Constant::Double(v) => (None, *v),
+        _ => return None,
+      };
+      match (target, integer) {
+        (Type::Int, Some(v)) => Constant::Int(v as i32),
+        (Type::Int, None) => Constant::Int(float as i32),
+        (Type::Long, Some(v)) => Constant::Long(v),
+        (Type::Long, None) => Constant::Long(float as i64),
+        (Type::Float, Some(v)) => Constant::Float(v as f32),
+        (Type::Float, None) => Constant::Float(float as f32),
+        (Type::Double, _) => Constant::Double(float),
+        (Type::Ref, _) => return None,
+      }
+    },
+    _ => return None,
+  })
+}
+
+fn fold_binary(op: BinaryOp, left: &Constant, right: &Constant) -> Option<Constant> {
+  Some(match (left, right) {
+    (Constant::Int(a), Constant::Int(b)) => Constant::Int(match op {
+      BinaryOp::Add => a.wrapping_add(*b),
+      BinaryOp::Sub => a.wrapping_sub(*b),
+      BinaryOp::Mul => a.wrapping_mul(*b),
+      // 0での除算は実行時にArithmeticExceptionを投げるので残す
+      BinaryOp::Div if *b != 0 => a.wrapping_div(*b),
+      BinaryOp::Rem if *b != 0 => a.wrapping_rem(*b),
+      BinaryOp::Div | BinaryOp::Rem => return None,
+      BinaryOp::And => a & b,
+      BinaryOp::Or => a | b,
+      BinaryOp::Xor => a ^ b,
+      BinaryOp::Shl => a.wrapping_shl(*b as u32),
+      BinaryOp::Shr => a.wrapping_shr(*b as u32),
+      BinaryOp::Ushr => (*a as u32).wrapping_shr(*b as u32) as i32,
+    }),
+    // longのシフトの量はint
+    (Constant::Long(a), Constant::Int(b)) => Constant::Long(match op {
+      BinaryOp::Shl => a.wrapping_shl(*b as u32),
+      BinaryOp::Shr => a.wrapping_shr(*b as u32),
+      BinaryOp::Ushr => (*a as u64).wrapping_shr(*b as u32) as i64,
+      _ => return None,
+    }),
+    (Constant::Long(a), Constant::Long(b)) => Constant::Long(match op {
+      BinaryOp::Add => a.wrapping_add(*b),
+      BinaryOp::Sub => a.wrapping_sub(*b),
+      BinaryOp::Mul => a.wrapping_mul(*b),
+      BinaryOp::Div if *b != 0 => a.wrapping_div(*b),
+      BinaryOp::Rem if *b != 0 => a.wrapping_rem(*b),
+      BinaryOp::And => a & b,
+      BinaryOp::Or => a | b,
+      BinaryOp::Xor => a ^ b,
+      _ => return None,
+    }),
+    (Constant::Float(a), Constant::Float(b)) => Constant::Float(match op {
+      BinaryOp::Add => a + b,
+      BinaryOp::Sub => a - b,
+      BinaryOp::Mul => a * b,
+      BinaryOp::Div => a / b,
+      BinaryOp::Rem => a % b,
+      _ => return None,
+    }),
+    (Constant::Double(a), Constant::Double(b)) => Constant::Double(match op {
+      BinaryOp::Add => a + b,
+      BinaryOp::Sub => a - b,
+      BinaryOp::Mul => a * b,
+      BinaryOp::Div => a / b,
+      BinaryOp::Rem => a % b,
+      _ => return None,
+    }),
+    _ => return None,
+  })
+}
+
+// 片方だけが定数の整数演算 (x + 0、x * 1など)
+// 浮動小数点数は-0.0やNaNがあるので変えない
+fn simplify_binary(op: BinaryOp, left: ValueId, a: Option<&Constant>, right: ValueId, b: Option<&Constant>) -> Option<Op> {
+  let integer = |constant: Option<&Constant>| match constant {
+    Some(Constant::Int(v)) => Some(*v as i64),
+    Some(Constant::Long(v)) => Some(*v),
+    _ => None,
+  };
+  let (a, b) = (integer(a), integer(b));
+  match (op, a, b) {
+    (BinaryOp::Add | BinaryOp::Sub | BinaryOp::Or | BinaryOp::Xor | BinaryOp::Shl | BinaryOp::Shr | BinaryOp::Ushr, _, Some(0)) => Some(Op::Copy(left)),
+    (BinaryOp::Add | BinaryOp::Or | BinaryOp::Xor, Some(0), _) => Some(Op::Copy(right)),
+    (BinaryOp::Mul | BinaryOp::Div, _, Some(1)) | (BinaryOp::And, _, Some(-1)) => Some(Op::Copy(left)),
+    (BinaryOp::Mul, Some(1), _) | (BinaryOp::And, Some(-1), _) => Some(Op::Copy(right)),
+    _ => None,
+  }
+}
+
+fn fold_compare(op: CompareOp, left: &Constant, right: &Constant) -> Option<i32> {
+  let (a, b) = match (left, right) {
+    (Constant::Long(a), Constant::Long(b)) => return Some(a.cmp(b) as i32),
+    (Constant::Float(a), Constant::Float(b)) => (*a as f64, *b as f64),
+    (Constant::Double(a), Constant::Double(b)) => (*a, *b),
+    _ => return None,
+  };
+  Some(match a.partial_cmp(&b) {
+    Some(ordering) => ordering as i32,
+    None if op == CompareOp::CompareGreater => 1,
+    None => -1,
+  })
+}
+
+fn fold_terminator(terminator: &Terminator, function: &Function, constants: &HashMap<ValueId, Constant>) -> Option<BlockId> {
+  match terminator {
+    Terminator::If { condition, left, right, then_block, else_block } => {
+      let taken = if Some(*left) == *right {
+        condition.evaluate(0, 0)
+      } else {
+        let left_constant = constants.get(left)?;
+        let right_constant = match right {
+          Some(right) => constants.get(right)?,
+          None if function.value_type(*left) == Type::Ref => &Constant::Null,
+          None => &Constant::Int(0),
+        };
+        match (left_constant, right_constant) {
+          (Constant::Int(a), Constant::Int(b)) => condition.evaluate(a, b),
+          (Constant::Null, Constant::Null) => condition.evaluate(0, 0),
+          // 文字列やクラスの定数はnullではない
+          (Constant::String(_) | Constant::Class(_), Constant::Null) | (Constant::Null, Constant::String(_) | Constant::Class(_)) => {
+            !condition.evaluate(0, 0)
+          },
+          _ => return None,
+        }
+      };
+      Some(if taken { *then_block } else { *else_block })
+    },
+    Terminator::Switch { value, cases, default } => {
+      let Some(Constant::Int(key)) = constants.get(value) else {
+        return None;
+      };
+      Some(cases.iter().find(|(case, _)| case == key).map_or(*default, |(_, target)| *target))
+    },
+    _ => None,
+  }
+}
+
+// コピーと、入力が1つの値しかないphiを、元の値に置き換える
+pub fn propagate_copies(function: &mut Function) -> bool {
+  let mut changed = false;
+  loop {
+    let mut replacements = HashMap::new();
+    for inst in function.blocks.iter().flat_map(|block| &block.insts) {
+      let Some(dest) = inst.dest else {
+        continue;
+      };
+      match &inst.op {
+        Op::Copy(source) => {
+          replacements.insert(dest, *source);
+        },
+        Op::Phi(inputs) => {
+          let mut sources = inputs.iter().map(|(_, v)| *v).filter(|v| *v != dest);
+          if let Some(first) = sources.next()
+            && sources.all(|v| v == first)
+          {
+            replacements.insert(dest, first);
+          }
+        },
+        _ => {},
+      }
+    }
+    if replacements.is_empty() {
+      return changed;
+    }
+    // 到達できないループのphi同士が循環していることがあるので、たどる回数を制限する
+    let limit = replacements.len();
+    let resolve = |mut value: ValueId| {
+      for _ in 0..limit {
+        match replacements.get(&value) {
+          Some(next) => value = *next,
+          None => break,
+        }
+      }
+      value
+    };
+    for block in &mut function.blocks {
+      block.insts.retain(|inst| !inst.dest.is_some_and(|dest| replacements.contains_key(&dest)));
+      for inst in &mut block.insts {
+        for operand in inst.op.operands_mut() {
+          *operand = resolve(*operand);
+        }
+      }
+      for operand in block.terminator.operands_mut() {
+        *operand = resolve(*operand);
+      }
+    }
+    changed = true;
+  }
+}
+
+// 既にnullでないことが分かっている値のnullチェックを取り除く
+pub fn eliminate_null_checks(function: &mut Function) -> bool {
+  let mut non_null: HashSet<ValueId> = HashSet::new();
+  if !function.is_static
+    && let Some(this) = function.params.first()
+  {
+    non_null.insert(*this);
+  }
+  for inst in function.blocks.iter().flat_map(|block| &block.insts) {
+    let created = matches!(
+      inst.op,
+      Op::New(_) | Op::NewArray(..) | Op::MultiNewArray(..) | Op::Catch(_) | Op::Const(Constant::String(_) | Constant::Class(_) | Constant::Other(_))
+    );
+    if created && let Some(dest) = inst.dest {
+      non_null.insert(dest);
+    }
+  }
+
+  // ブロックの入口でチェック済みの値 (全ての先行ブロックから来る経路でチェック済みのもの)
+  let successors = function.successor_lists();
+  let order = reverse_postorder(&successors, 0);
+  let mut entries: Vec<Option<HashSet<ValueId>>> = vec![None; function.blocks.len()];
+  entries[0] = Some(HashSet::new());
+  let mut changed = true;
+  while changed {
+    changed = false;
+    for &block in &order {
+      let Some(entry) = entries[block].clone() else {
+        continue;
+      };
+      let ir_block = &function.blocks[block];
+      let mut exit = entry.clone();
+      exit.extend(ir_block.insts.iter().filter_map(|inst| match inst.op {
+        Op::NullCheck(value) => Some(value),
+        _ => None,
+      }));
+      for &target in &successors[block] {
+        // 例外の経路ではブロックのどの命令で投げたか分からないので、入口の状態を渡す
+        let mut incoming = if ir_block.handlers.iter().any(|handler| handler.block.0 == target) { entry.clone() } else { exit.clone() };
+        if let Terminator::If { condition, left, right: None, then_block, else_block } = &ir_block.terminator
+          && function.value_type(*left) == Type::Ref
+          && then_block != else_block
+        {
+          let non_null_target = if *condition == Condition::Equal { else_block } else { then_block };
+          if non_null_target.0 == target {
+            incoming.insert(*left);
+          }
+        }
+        let merged = match &entries[target] {
+          None => incoming,
+          Some(current) => current.intersection(&incoming).copied().collect(),
+        };
+        if entries[target].as_ref() != Some(&merged) {
+          entries[target] = Some(merged);
+          changed = true;
+        }
+      }
+    }
+  }
+
+  let mut removed = false;
+  for (block, entry) in function.blocks.iter_mut().zip(entries) {
+    let mut checked = entry.unwrap_or_default();
+    block.insts.retain(|inst| {
+      let Op::NullCheck(value) = inst.op else {
+        return true;
+      };
+      if non_null.contains(&value) || !checked.insert(value) {
+        removed = true;
+        return false;
+      }
+      true
+    });
+  }
+  removed
+}
+
+// 到達できないブロックと、結果が使われない副作用のない命令を取り除く
+pub fn eliminate_dead_code(function: &mut Function) -> bool {
+  let mut changed = false;
+  let constants = function.constants();
+
+  // 例外を投げる命令がなくなったブロックは、例外ハンドラに進まない
+  for i in 0..function.blocks.len() {
+    let block = &function.blocks[i];
+    let throws = matches!(block.terminator, Terminator::Throw(_)) || block.insts.iter().any(|inst| !inst.op.is_pure(function, &constants));
+    if !throws && !block.handlers.is_empty() {
+      function.blocks[i].handlers.clear();
+      changed = true;
+    }
+  }
+
+  // 到達できるブロックだけを残して番号を振り直す
+  let order = reverse_postorder(&function.successor_lists(), 0);
+  if order.len() != function.blocks.len() {
+    let mut numbers = vec![None; function.blocks.len()];
+    let mut reachable: Vec<usize> = order.clone();
+    reachable.sort_unstable();
+    for (i, &block) in reachable.iter().enumerate() {
+      numbers[block] = Some(BlockId(i));
+    }
+    let mut blocks = Vec::new();
+    for (i, mut block) in std::mem::take(&mut function.blocks).into_iter().enumerate() {
+      if numbers[i].is_none() {
+        continue;
+      }
+      for target in block.terminator.targets_mut() {
+        *target = numbers[target.0].expect("successor of a reachable block is reachable");
+      }
+      for handler in &mut block.handlers {
+        handler.block = numbers[handler.block.0].expect("handler of a reachable block is reachable");
+      }
+      for inst in &mut block.insts {
+        if let Op::Phi(inputs) = &mut inst.op {
+          inputs.retain(|(predecessor, _)| numbers[predecessor.0].is_some());
+          for (predecessor, _) in inputs.iter_mut() {
+            *predecessor = numbers[predecessor.0].unwrap_or(*predecessor);
+          }
+        }
+      }
+      blocks.push(block);
+    }
+    function.blocks = blocks;
+    changed = true;
+  }
+
+  // phiの入力を実際の先行ブロックだけにする
+  let predecessors = predecessors(&function.successor_lists());
+  for (i, block) in function.blocks.iter_mut().enumerate() {
+    for inst in &mut block.insts {
+      if let Op::Phi(inputs) = &mut inst.op {
+        let before = inputs.len();
+        inputs.retain(|(predecessor, _)| predecessors[i].contains(&predecessor.0));
+        changed |= inputs.len() != before;
+      }
+    }
+  }
+
+  // 副作用のある命令と終端から使われている値をたどる
+  let constants = function.constants();
+  let mut definitions = HashMap::new();
+  let mut live = HashSet::new();
+  let mut worklist = Vec::new();
+  for block in &function.blocks {
+    worklist.extend(block.terminator.operands());
+  }
+  for inst in function.blocks.iter().flat_map(|block| &block.insts) {
+    if let Some(dest) = inst.dest {
+      definitions.insert(dest, &inst.op);
+    }
+    if !inst.op.is_pure(function, &constants) {
+      worklist.extend(inst.op.operands());
+    }
+  }
+  while let Some(value) = worklist.pop() {
+    if live.insert(value)
+      && let Some(op) = definitions.get(&value)
+    {
+      worklist.extend(op.operands());
+    }
+  }
+  let dead: HashSet<ValueId> = function
+    .blocks
+    .iter()
+    .flat_map(|block| &block.insts)
+    .filter(|inst| inst.op.is_pure(function, &constants) && inst.dest.is_some_and(|dest| !live.contains(&dest)))
+    .filter_map(|inst| inst.dest)
+    .collect();
+  if !dead.is_empty() {
+    for block in &mut function.blocks {
+      block.insts.retain(|inst| !inst.dest.is_some_and(|dest| dead.contains(&dest)));
+    }
+    changed = true;
+  }
+  changed
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    ir::lower::lower,
+    structure::class::{CodeAttribute, ConstantPool, MethodInfoAttribute},
+  };
+
+  fn code_attribute(code: &[u8]) -> CodeAttribute {
+    let mut input = Vec::new();
+    input.extend((2 + 2 + 4 + code.len() as u32 + 2 + 2).to_be_bytes());
+    input.extend([0, 4, 0, 4]);
+    input.extend((code.len() as u32).to_be_bytes());
+    input.extend(code);
+    input.extend([0, 0, 0, 0]);
+    match MethodInfoAttribute::parse(&input, "Code", 1, &ConstantPool::default()) {
+      Ok((_, MethodInfoAttribute::Code(attribute))) => attribute,
+      _ => panic!("expected a Code attribute"),
+    }
+  }
+
+  // static int f(int)
+  fn lower_static(code: &[u8]) -> Function {
+    lower("T", "f", "(I)I", true, &code_attribute(code), &ConstantPool::default()).unwrap()
+  }
+
+  fn binary_ops(function: &Function) -> Vec<BinaryOp> {
+    function.blocks.iter().flat_map(|block| &block.insts)
+      .filter_map(|inst| match inst.op {
+        Op::Binary(op, ..) => Some(op),
+        _ => None,
+      })
+      .collect()
+  }
+
+  fn returned_constant(function: &Function) -> Option<Constant> {
+    let Terminator::Return(Some(value)) = function.blocks[0].terminator else {
+      panic!("expected a return in the entry block");
+    };
+    function.constants().get(&value).cloned()
+  }
+
+  #[test]
+  fn division_by_constant_zero_is_not_folded() {
+    // iload_0、iconst_0、idiv、ireturn
+    let mut function = lower_static(&[0x1a, 0x03, 0x6c, 0xac]);
+    optimize(&mut function);
+    assert_eq!(binary_ops(&function), [BinaryOp::Div]);
+    assert_eq!(returned_constant(&function), None);
+  }
+
+  #[test]
+  fn shift_distance_is_masked_when_folding() {
+    // iconst_1、bipush 33、ishl、ireturn
+    let mut function = lower_static(&[0x04, 0x10, 33, 0x78, 0xac]);
+    optimize(&mut function);
+    assert_eq!(returned_constant(&function), Some(Constant::Int(2)));
+    assert!(binary_ops(&function).is_empty());
+  }
+
+  #[test]
+  fn dead_code_elimination_keeps_instructions_that_can_throw() {
+    // iload_0、iconst_0、idiv、pop、iload_0、iconst_2、iadd、pop、iconst_1、ireturn
+    let mut function = lower_static(&[0x1a, 0x03, 0x6c, 0x57, 0x1a, 0x05, 0x60, 0x57, 0x04, 0xac]);
+    optimize(&mut function);
+    // 結果を使わないiaddは消えるが、ArithmeticExceptionを投げうるidivは残る
+    assert_eq!(binary_ops(&function), [BinaryOp::Div]);
+    assert_eq!(returned_constant(&function), Some(Constant::Int(1)));
+  }
+
+  #[test]
+  fn loop_header_gets_phis_for_values_changed_in_the_loop() {
+    // 0: iconst_0、1: istore_1、2: iload_0、3: ifle 16、6: iload_1、7: iload_0、8: iadd、9: istore_1、
+    // 10: iinc 0 -1、13: goto 2、16: iload_1、17: ireturn
+    let mut function = lower_static(&[
+      0x03, 0x3c, 0x1a, 0x9e, 0, 13, 0x1b, 0x1a, 0x60, 0x3c, 0x84, 0, 0xff, 0xa7, 0xff, 0xf5, 0x1b, 0xac,
+    ]);
+    optimize(&mut function);
+    let pcs: Vec<usize> = function.blocks.iter().map(|block| block.pc).collect();
+    assert_eq!(pcs, [0, 2, 6, 16]);
+    let (entry, header, body, exit) = (BlockId(0), BlockId(1), BlockId(2), BlockId(3));
+
+    // ヘッダにはnとsumのphiがあり、入口と本体からの値を受け取る
+    let phis: Vec<&Vec<(BlockId, ValueId)>> = function.blocks[header.0].insts.iter()
+      .filter_map(|inst| match &inst.op {
+        Op::Phi(inputs) => Some(inputs),
+        _ => None,
+      })
+      .collect();
+    assert_eq!(phis.len(), 2);
+    for inputs in &phis {
+      let predecessors: Vec<BlockId> = inputs.iter().map(|(block, _)| *block).collect();
+      assert_eq!(predecessors, [entry, body]);
+    }
+    let constants = function.constants();
+    let from_entry: Vec<Option<&Constant>> = phis.iter().map(|inputs| constants.get(&inputs[0].1)).collect();
+    assert!(phis.iter().any(|inputs| inputs[0].1 == function.params[0]));
+    assert!(from_entry.contains(&Some(&Constant::Int(0))));
+
+    // ループの外ではphiを置かず、ヘッダのsumを返す
+    for block in [entry, body, exit] {
+      assert!(!function.blocks[block.0].insts.iter().any(|inst| matches!(inst.op, Op::Phi(_))));
+    }
+    let Terminator::Return(Some(result)) = function.blocks[exit.0].terminator else {
+      panic!("expected a return");
+    };
+    let sum = function.blocks[header.0].insts.iter().find(|inst| inst.dest == Some(result));
+    assert!(matches!(sum.map(|inst| &inst.op), Some(Op::Phi(_))));
+  }
+}
@@ -0,0 +1,248 @@
+use std::{fmt, fs};
+
+use crate::{
+  class_leader,
+  ir::{lower::lower_method, passes::optimize, BinaryOp, CompareOp, Condition, Constant, Function, InvokeKind, MemberRef, Op, Terminator, Type, UnaryOp, ValueId},
+  structure::class::MethodInfoAttribute,
+  util::{
+    class::method_matches,
+    graph::predecessors,
+    number::{double_to_string, float_to_string},
+  },
+};
+
+impl fmt::Display for Type {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let name = match self {
+      Type::Int => "int",
+      Type::Long => "long",
+      Type::Float => "float",
+      Type::Double => "double",
+      Type::Ref => "ref",
+    };
+    write!(f, "{}", name)
+  }
+}
+
+impl fmt::Display for Constant {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Constant::Int(v) => write!(f, "{}", v),
+      Constant::Long(v) => write!(f, "{}L", v),
+      Constant::Float(v) => write!(f, "{}f", float_to_string(*v)),
+      Constant::Double(v) => write!(f, "{}", double_to_string(*v)),
+      Constant::Null => write!(f, "null"),
+      Constant::String(v) => write!(f, "{:?}", v),
+      Constant::Class(v) => write!(f, "class {}", v),
+      Constant::Other(v) => write!(f, "{}", v),
+    }
+  }
+}
+
+impl fmt::Display for MemberRef {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}.{}:{}", self.class, self.name, self.descriptor)
+  }
+}
+
+fn binary_name(op: BinaryOp) -> &'static str {
+  match op {
+    BinaryOp::Add => "add",
+    BinaryOp::Sub => "sub",
+    BinaryOp::Mul => "mul",
+    BinaryOp::Div => "div",
+    BinaryOp::Rem => "rem",
+    BinaryOp::And => "and",
+    BinaryOp::Or => "or",
+    BinaryOp::Xor => "xor",
+    BinaryOp::Shl => "shl",
+    BinaryOp::Shr => "shr",
+    BinaryOp::Ushr => "ushr",
+  }
+}
+
+fn condition_name(condition: Condition) -> &'static str {
+  match condition {
+    Condition::Equal => "eq",
+    Condition::NotEqual => "ne",
+    Condition::Less => "lt",
+    Condition::GreaterEqual => "ge",
+    Condition::Greater => "gt",
+    Condition::LessEqual => "le",
+  }
+}
+
+fn values(values: &[ValueId]) -> String {
+  values.iter().map(|v| format!("v{}", v.0)).collect::<Vec<_>>().join(", ")
+}
+
+impl fmt::Display for Op {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Op::Const(constant) => write!(f, "const {}", constant),
+      Op::Copy(v) => write!(f, "copy v{}", v.0),
+      Op::Phi(inputs) => {
+        let inputs: Vec<String> = inputs.iter().map(|(block, v)| format!("[B{}: v{}]", block.0, v.0)).collect();
+        write!(f, "phi {}", inputs.join(", "))
+      },
+      Op::Catch(types) if types.is_empty() => write!(f, "catch any"),
+      Op::Catch(types) => write!(f, "catch {}", types.join(" | ")),
+      Op::Unary(op, v) => {
+        let name = match op {
+          UnaryOp::Neg => "neg".to_string(),
+          UnaryOp::Convert(target) => format!("convert.{}", target),
+          UnaryOp::ToByte => "to_byte".to_string(),
+          UnaryOp::ToChar => "to_char".to_string(),
+          UnaryOp::ToShort => "to_short".to_string(),
+        };
+        write!(f, "{} v{}", name, v.0)
+      },
+      Op::Binary(op, a, b) => write!(f, "{} v{}, v{}", binary_name(*op), a.0, b.0),
+      Op::Compare(op, a, b) => {
+        let name = match op {
+          CompareOp::Compare => "cmp",
+          CompareOp::CompareLess => "cmpl",
+          CompareOp::CompareGreater => "cmpg",
+        };
+        write!(f, "{} v{}, v{}", name, a.0, b.0)
+      },
+      Op::NullCheck(v) => write!(f, "null_check v{}", v.0),
+      Op::GetField(member, object) => write!(f, "getfield {} v{}", member, object.0),
+      Op::PutField(member, object, v) => write!(f, "putfield {} v{}, v{}", member, object.0, v.0),
+      Op::GetStatic(member) => write!(f, "getstatic {}", member),
+      Op::PutStatic(member, v) => write!(f, "putstatic {} v{}", member, v.0),
+      Op::ArrayLoad(array, index) => write!(f, "array_load v{}[v{}]", array.0, index.0),
+      Op::ArrayStore(array, index, v) => write!(f, "array_store v{}[v{}], v{}", array.0, index.0, v.0),
+      Op::ArrayLength(array) => write!(f, "array_length v{}", array.0),
+      Op::Invoke(kind, member, args) => {
+        let kind = match kind {
+          InvokeKind::Virtual => "invokevirtual",
+          InvokeKind::Special => "invokespecial",
+          InvokeKind::Static => "invokestatic",
+          InvokeKind::Interface => "invokeinterface",
+        };
+        write!(f, "{} {}({})", kind, member, values(args))
+      },
+      Op::InvokeDynamic { name, descriptor, bootstrap, args } => write!(f, "invokedynamic #{} {}:{}({})", bootstrap, name, descriptor, values(args)),
+      Op::New(class) => write!(f, "new {}", class),
+      Op::NewArray(element, length) => write!(f, "new_array {}[v{}]", element, length.0),
+      Op::MultiNewArray(class, lengths) => write!(f, "multi_new_array {}({})", class, values(lengths)),
+      Op::CheckCast(class, v) => write!(f, "checkcast {} v{}", class, v.0),
+      Op::InstanceOf(class, v) => write!(f, "instanceof {} v{}", class, v.0),
+      Op::MonitorEnter(v) => write!(f, "monitorenter v{}", v.0),
+      Op::MonitorExit(v) => write!(f, "monitorexit v{}", v.0),
+    }
+  }
+}
+
+impl fmt::Display for Function {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let params: Vec<String> = self.params.iter().map(|v| format!("v{}: {}", v.0, self.value_type(*v))).collect();
+    let modifier = if self.is_static { "static " } else { "" };
+    writeln!(f, "{}function {}.{}{} ({})", modifier, self.class_name, self.name, self.descriptor, params.join(", "))?;
+    let predecessors = predecessors(&self.successor_lists());
+    for (i, block) in self.blocks.iter().enumerate() {
+      write!(f, "B{} (pc {})", i, block.pc)?;
+      if !predecessors[i].is_empty() {
+        let names: Vec<String> = predecessors[i].iter().map(|b| format!("B{}", b)).collect();
+        write!(f, " <- {}", names.join(", "))?;
+      }
+      writeln!(f, ":")?;
+      for inst in &block.insts {
+        match inst.dest {
+          Some(dest) => writeln!(f, "  v{}: {} = {}", dest.0, self.value_type(dest), inst.op)?,
+          None => writeln!(f, "  {}", inst.op)?,
+        }
+      }
+      let operand = |right: &Option<ValueId>, left: ValueId| match right {
+        Some(right) => format!("v{}", right.0),
+        None if self.value_type(left) == Type::Ref => "null".to_string(),
+        None => "0".to_string(),
+      };
+      match &block.terminator {
+        Terminator::Goto(target) => writeln!(f, "  goto B{}", target.0)?,
+        Terminator::If { condition, left, right, then_block, else_block } => writeln!(
+          f,
+          "  if {} v{}, {} then B{} else B{}",
+          condition_name(*condition),
+          left.0,
+          operand(right, *left),
+          then_block.0,
+          else_block.0
+        )?,
+        Terminator::Switch { value, cases, default } => {
+          let cases: Vec<String> = cases.iter().map(|(key, target)| format!("{}: B{}", key, target.0)).collect();
+          writeln!(f, "  switch v{} [{}] default B{}", value.0, cases.join(", "), default.0)?
+        },
+        Terminator::Return(Some(value)) => writeln!(f, "  return v{}", value.0)?,
+        Terminator::Return(None) => writeln!(f, "  return")?,
+        Terminator::Throw(value) => writeln!(f, "  throw v{}", value.0)?,
+      }
+      if !block.handlers.is_empty() {
+        let handlers: Vec<String> =
+          block.handlers.iter().map(|handler| format!("{} -> B{}", handler.catch_type.as_deref().unwrap_or("any"), handler.block.0)).collect();
+        writeln!(f, "  handlers: {}", handlers.join(", "))?;
+      }
+    }
+    Ok(())
+  }
+}
+
+// クラスファイルのメソッドを中間表現にして表示する
+pub fn print_command(program: &str, args: &[String]) -> i32 {
+  let (optimized, args) = match args.first() {
+    Some(flag) if flag == "-O" => (true, &args[1..]),
+    _ => (false, args),
+  };
+  let (path, filter) = match args {
+    [path] => (path, None),
+    [path, method] => (path, Some(method.as_str())),
+    _ => {
+      eprintln!("Usage: {} ir [-O] <class file> [<method>[<descriptor>]]", program);
+      return 2;
+    },
+  };
+  let class_file = match fs::read(path).and_then(|bytes| class_leader::parse_bytes(&bytes)) {
+    Ok(class_file) => class_file,
+    Err(e) => {
+      eprintln!("Error: {}: {}", path, e);
+      return 1;
+    },
+  };
+  let pool = &class_file.constant_pool;
+  let mut status = 0;
+  let mut found = false;
+  for method in &class_file.methods.methods {
+    if !method.attributes.attributes.iter().any(|attribute| matches!(attribute, MethodInfoAttribute::Code(_))) {
+      continue;
+    }
+    let name = pool.get_utf8(method.name_index).unwrap_or_default();
+    let descriptor = pool.get_utf8(method.descriptor_index).unwrap_or_default();
+    if filter.is_some_and(|filter| !method_matches(filter, &name, &descriptor)) {
+      continue;
+    }
+    if found {
+      println!();
+    }
+    found = true;
+    match lower_method(&class_file, method) {
+      Ok(mut function) => {
+        if optimized {
+          optimize(&mut function);
+        }
+        print!("{}", function);
+      },
+      Err(e) => {
+        eprintln!("Error: {}{}: {}", name, descriptor, e);
+        status = 1;
+      },
+    }
+  }
+  if let Some(filter) = filter
+    && !found
+  {
+    eprintln!("Error: method not found: {}", filter);
+    return 1;
+  }
+  status
+}
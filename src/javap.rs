@@ -16,7 +16,7 @@ pub fn javap_viewer(class_file: ClassFile) {
   println!("Super Class: #{}: {:?}", class_file.super_class, class_file.constant_pool.constants[class_file.super_class as usize - 1]);
 
   println!("\nInterfaces count: {}", class_file.interfaces.interfaces_count);
-  for (_, interface) in class_file.interfaces.interfaces.iter().enumerate() {
+  for interface in class_file.interfaces.interfaces.iter() {
     println!("Interface #{}: {:?}", interface, class_file.constant_pool.constants[*interface as usize - 1]);
   }
 
@@ -33,12 +33,12 @@ pub fn javap_viewer(class_file: ClassFile) {
     println!("Method #{}", i + 1);
     println!("Access Flags: {}", method_access_flags(method.access_flags));
     let method_name = match &class_file.constant_pool.constants[method.name_index as usize - 1] {
-      Constant::Utf8 { bytes, .. } => hex_utf8(&bytes),
+      Constant::Utf8 { bytes, .. } => hex_utf8(bytes),
       _ => format!("#{}", method.name_index),
     };
     println!("Name: #{}: {:?}", method.name_index, method_name);
     let method_descriptor = match &class_file.constant_pool.constants[method.descriptor_index as usize - 1] {
-      Constant::Utf8 { bytes, .. } => hex_utf8(&bytes),
+      Constant::Utf8 { bytes, .. } => hex_utf8(bytes),
       _ => format!("#{}", method.descriptor_index),
     };
     println!("Descriptor: #{}: {:?}", method.descriptor_index, method_descriptor);
@@ -68,7 +68,7 @@ pub fn javap_viewer(class_file: ClassFile) {
     match attr {
       ClassFileAttribute::SourceFile(source_file) => {
         let source_file_name = match &class_file.constant_pool.constants[source_file.source_file_index as usize - 1] {
-          Constant::Utf8 { bytes, .. } => hex_utf8(&bytes),
+          Constant::Utf8 { bytes, .. } => hex_utf8(bytes),
           _ => format!("#{}", source_file.source_file_index),
         };
         println!("Source File: {}", source_file_name);
@@ -1,22 +1,75 @@
-use std::env;
+use std::{env, process};
 
 mod util;
 mod structure;
+mod runtime;
+mod ir;
+mod cfg;
+mod decompiler;
+mod deps;
+mod callgraph;
+mod apidiff;
+mod classdiff;
+mod hierarchy;
 
 mod class_leader;
 mod javap;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        eprintln!("Usage: {} <class file path>", args[0]);
-        return;
-    }
-    let path = &args[1];
-    let class_file = class_leader::read_file(path);
+  let args: Vec<String> = env::args().collect();
+  if args.len() < 2 {
+    eprintln!("Usage: {} <class file path>", args[0]);
+    eprintln!("       {} run [-cp <path>] [-Xbootclasspath:<path>] [-D<name>=<value>] [-Xmx<size>] [-verbose:gc] [-Xint] [-XX:CompileThreshold=<n>] [-XX:+PrintCompilation] [--trace] [--trace-filter=<pattern>[,<pattern>...]] [--trace-file=<path>] [--profile] [--profile-collapsed=<path>] [--profile-json=<path>] [-agentlib:jdwp=transport=dt_socket,server=y,suspend=y,address=<port>] <main class | class file> [args...]", args[0]);
+    eprintln!("       {} debug [-cp <path>] [-Xbootclasspath:<path>] [-D<name>=<value>] [-Xmx<size>] <main class | class file> [args...]", args[0]);
+    eprintln!("       {} replay <trace file>", args[0]);
+    eprintln!("       {} ir [-O] <class file> [<method>[<descriptor>]]", args[0]);
+    eprintln!("       {} cfg [--dominators] [--loops] <class file> [<method>[<descriptor>]]", args[0]);
+    eprintln!("       {} decompile <class file>", args[0]);
+    eprintln!("       {} deps [-cp <path>] [-Xbootclasspath:<path>] [--classes] [--dot | --json] <class file | directory | jar>...", args[0]);
+    eprintln!("       {} callgraph [-cp <path>] [-Xbootclasspath:<path>] [--rta] [--entry <class>.<method>[<descriptor>]]... [--entry-annotation <annotation>]... [--dot | --json] <class file | directory | jar>...", args[0]);
+    eprintln!("       {} apidiff [--json] <old class file | directory | jar> <new class file | directory | jar>", args[0]);
+    eprintln!("       {} diff [--json] [--ignore <attribute>]... <old class file> <new class file>", args[0]);
+    eprintln!("       {} hierarchy [-cp <path>] [-Xbootclasspath:<path>] [--cache <file>] [--check-sealed] [<class> [<class>]]", args[0]);
+    return;
+  }
+  if args[1] == "run" {
+    process::exit(runtime::launcher::launch(&args[0], &args[2..]));
+  }
+  if args[1] == "debug" {
+    process::exit(runtime::launcher::debug(&args[0], &args[2..]));
+  }
+  if args[1] == "replay" {
+    process::exit(runtime::launcher::replay(&args[0], &args[2..]));
+  }
+  if args[1] == "ir" {
+    process::exit(ir::printer::print_command(&args[0], &args[2..]));
+  }
+  if args[1] == "cfg" {
+    process::exit(cfg::dot::print_command(&args[0], &args[2..]));
+  }
+  if args[1] == "decompile" {
+    process::exit(decompiler::print_command(&args[0], &args[2..]));
+  }
+  if args[1] == "deps" {
+    process::exit(deps::report::print_command(&args[0], &args[2..]));
+  }
+  if args[1] == "callgraph" {
+    process::exit(callgraph::report::print_command(&args[0], &args[2..]));
+  }
+  if args[1] == "apidiff" {
+    process::exit(apidiff::report::print_command(&args[0], &args[2..]));
+  }
+  if args[1] == "diff" {
+    process::exit(classdiff::report::print_command(&args[0], &args[2..]));
+  }
+  if args[1] == "hierarchy" {
+    process::exit(hierarchy::report::print_command(&args[0], &args[2..]));
+  }
+  let path = &args[1];
+  let class_file = class_leader::read_file(path);
 
-    match class_file {
-        Ok(cf) => javap::javap_viewer(cf),
-        Err(e) => eprintln!("Error reading class file: {}", e),
-    }
+  match class_file {
+    Ok(cf) => javap::javap_viewer(cf),
+    Err(e) => eprintln!("Error reading class file: {}", e),
+  }
 }
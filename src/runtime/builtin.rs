@@ -0,0 +1,387 @@
+use std::time::Duration;
+
+use crate::{
+  runtime::{
+    class::{ClassDefinition, ACC_ABSTRACT, ACC_FINAL, ACC_INTERFACE, ACC_PRIVATE, ACC_PROTECTED, ACC_PUBLIC, ACC_SUPER, ACC_TRANSIENT, ACC_VARARGS},
+    error::VmError,
+    library::{atomic, boxing, function, invoke, math, mirror, misc_unsafe, objects, string, string_builder, system, thread},
+    method_handle::method_type_string,
+    value::{ObjRef, Value},
+    vm::Vm,
+  },
+  util::descriptor::FieldType,
+};
+
+// VMが投げる例外とその親クラス (親を先に定義する、Throwableは別に定義する)
+const THROWABLES: &[(&str, &str)] = &[
+  ("java/lang/Exception", "java/lang/Throwable"),
+  ("java/lang/Error", "java/lang/Throwable"),
+  ("java/lang/RuntimeException", "java/lang/Exception"),
+  ("java/lang/CloneNotSupportedException", "java/lang/Exception"),
+  ("java/lang/InterruptedException", "java/lang/Exception"),
+  ("java/lang/ReflectiveOperationException", "java/lang/Exception"),
+  ("java/lang/InstantiationException", "java/lang/ReflectiveOperationException"),
+  ("java/lang/IllegalAccessException", "java/lang/ReflectiveOperationException"),
+  ("java/lang/NoSuchFieldException", "java/lang/ReflectiveOperationException"),
+  ("java/lang/ArithmeticException", "java/lang/RuntimeException"),
+  ("java/lang/ArrayStoreException", "java/lang/RuntimeException"),
+  ("java/lang/ClassCastException", "java/lang/RuntimeException"),
+  ("java/lang/IllegalArgumentException", "java/lang/RuntimeException"),
+  ("java/lang/NumberFormatException", "java/lang/IllegalArgumentException"),
+  ("java/lang/IllegalThreadStateException", "java/lang/IllegalArgumentException"),
+  ("java/lang/IllegalMonitorStateException", "java/lang/RuntimeException"),
+  ("java/lang/IllegalStateException", "java/lang/RuntimeException"),
+  ("java/lang/IndexOutOfBoundsException", "java/lang/RuntimeException"),
+  ("java/lang/ArrayIndexOutOfBoundsException", "java/lang/IndexOutOfBoundsException"),
+  ("java/lang/StringIndexOutOfBoundsException", "java/lang/IndexOutOfBoundsException"),
+  ("java/lang/NegativeArraySizeException", "java/lang/RuntimeException"),
+  ("java/lang/NullPointerException", "java/lang/RuntimeException"),
+  ("java/lang/UnsupportedOperationException", "java/lang/RuntimeException"),
+  ("java/lang/invoke/WrongMethodTypeException", "java/lang/RuntimeException"),
+  ("java/lang/LinkageError", "java/lang/Error"),
+  ("java/lang/BootstrapMethodError", "java/lang/LinkageError"),
+  ("java/lang/ClassCircularityError", "java/lang/LinkageError"),
+  ("java/lang/ClassFormatError", "java/lang/LinkageError"),
+  ("java/lang/ExceptionInInitializerError", "java/lang/LinkageError"),
+  ("java/lang/NoClassDefFoundError", "java/lang/LinkageError"),
+  ("java/lang/UnsatisfiedLinkError", "java/lang/LinkageError"),
+  ("java/lang/VerifyError", "java/lang/LinkageError"),
+  ("java/lang/IncompatibleClassChangeError", "java/lang/LinkageError"),
+  ("java/lang/AbstractMethodError", "java/lang/IncompatibleClassChangeError"),
+  ("java/lang/IllegalAccessError", "java/lang/IncompatibleClassChangeError"),
+  ("java/lang/InstantiationError", "java/lang/IncompatibleClassChangeError"),
+  ("java/lang/NoSuchFieldError", "java/lang/IncompatibleClassChangeError"),
+  ("java/lang/NoSuchMethodError", "java/lang/IncompatibleClassChangeError"),
+  ("java/lang/VirtualMachineError", "java/lang/Error"),
+  ("java/lang/InternalError", "java/lang/VirtualMachineError"),
+  ("java/lang/OutOfMemoryError", "java/lang/VirtualMachineError"),
+  ("java/lang/StackOverflowError", "java/lang/VirtualMachineError"),
+];
+
+// JDKが無くても最低限動かすために、Rustで定義するクラス
+pub fn define_builtin_classes(vm: &mut Vm) -> Result<(), VmError> {
+  vm.define_class(
+    ClassDefinition::new("java/lang/Object")
+      .super_class(None)
+      .native("<init>", "()V", ACC_PUBLIC, object_init)
+      .native("hashCode", "()I", ACC_PUBLIC, object_hash_code)
+      .native("equals", "(Ljava/lang/Object;)Z", ACC_PUBLIC, object_equals)
+      .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, object_to_string)
+      .native("clone", "()Ljava/lang/Object;", ACC_PROTECTED, object_clone)
+      .native("getClass", "()Ljava/lang/Class;", ACC_PUBLIC | ACC_FINAL, mirror::object_get_class)
+      .native("wait", "()V", ACC_PUBLIC | ACC_FINAL, object_wait)
+      .native("wait", "(J)V", ACC_PUBLIC | ACC_FINAL, object_wait_timeout)
+      .native("wait", "(JI)V", ACC_PUBLIC | ACC_FINAL, object_wait_nanos)
+      .native("notify", "()V", ACC_PUBLIC | ACC_FINAL, object_notify)
+      .native("notifyAll", "()V", ACC_PUBLIC | ACC_FINAL, object_notify_all)
+  )?;
+  for name in ["java/lang/Cloneable", "java/io/Serializable"] {
+    vm.define_class(
+      ClassDefinition::new(name).access_flags(ACC_PUBLIC | ACC_INTERFACE | ACC_ABSTRACT)
+    )?;
+  }
+  vm.define_class(
+    ClassDefinition::new("java/lang/Runnable")
+      .access_flags(ACC_PUBLIC | ACC_INTERFACE | ACC_ABSTRACT)
+      .abstract_method("run", "()V", ACC_PUBLIC)
+  )?;
+  string::define(vm)?;
+  string_builder::define(vm)?;
+  boxing::define(vm)?;
+  mirror::define(vm)?;
+  math::define(vm)?;
+  system::define(vm)?;
+  thread::define(vm)?;
+  atomic::define(vm)?;
+  objects::define(vm)?;
+  function::define(vm)?;
+  vm.define_class(
+    ClassDefinition::new("java/lang/Throwable")
+      .interface("java/io/Serializable")
+      .field("detailMessage", "Ljava/lang/String;", ACC_PRIVATE)
+      .field("cause", "Ljava/lang/Throwable;", ACC_PRIVATE)
+      .field("backtrace", "Ljava/lang/Object;", ACC_PRIVATE | ACC_TRANSIENT)
+      .native("<init>", "()V", ACC_PUBLIC, throwable_init)
+      .native("<init>", "(Ljava/lang/String;)V", ACC_PUBLIC, throwable_init)
+      .native("<init>", "(Ljava/lang/String;Ljava/lang/Throwable;)V", ACC_PUBLIC, throwable_init)
+      .native("<init>", "(Ljava/lang/Throwable;)V", ACC_PUBLIC, throwable_init_cause)
+      .native("getMessage", "()Ljava/lang/String;", ACC_PUBLIC, throwable_get_message)
+      .native("getLocalizedMessage", "()Ljava/lang/String;", ACC_PUBLIC, throwable_get_message)
+      .native("getCause", "()Ljava/lang/Throwable;", ACC_PUBLIC, throwable_get_cause)
+      .native("initCause", "(Ljava/lang/Throwable;)Ljava/lang/Throwable;", ACC_PUBLIC, throwable_set_cause)
+      .native("fillInStackTrace", "()Ljava/lang/Throwable;", ACC_PUBLIC, throwable_fill_in_stack_trace)
+      .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, throwable_to_string)
+      .native("printStackTrace", "()V", ACC_PUBLIC, throwable_print_stack_trace)
+  )?;
+  vm.define_class(
+    ClassDefinition::new("java/lang/Record")
+      .access_flags(ACC_PUBLIC | ACC_ABSTRACT | ACC_SUPER)
+      .native("<init>", "()V", ACC_PROTECTED, object_init)
+  )?;
+  // equals()とhashCode()はObjectの同一性のまま
+  vm.define_class(
+    ClassDefinition::new("java/lang/Enum")
+      .access_flags(ACC_PUBLIC | ACC_ABSTRACT | ACC_SUPER)
+      .interface("java/lang/Comparable")
+      .interface("java/io/Serializable")
+      .field("name", "Ljava/lang/String;", ACC_PRIVATE | ACC_FINAL)
+      .field("ordinal", "I", ACC_PRIVATE | ACC_FINAL)
+      .native("<init>", "(Ljava/lang/String;I)V", ACC_PROTECTED, enum_init)
+      .native("name", "()Ljava/lang/String;", ACC_PUBLIC | ACC_FINAL, enum_name)
+      .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, enum_name)
+      .native("ordinal", "()I", ACC_PUBLIC | ACC_FINAL, enum_ordinal)
+      .native("compareTo", "(Ljava/lang/Enum;)I", ACC_PUBLIC | ACC_FINAL, enum_compare_to)
+      .native("compareTo", "(Ljava/lang/Object;)I", ACC_PUBLIC, enum_compare_to)
+  )?;
+  vm.define_class(
+    ClassDefinition::new("java/lang/invoke/MethodType")
+      .access_flags(ACC_PUBLIC | ACC_FINAL | ACC_SUPER)
+      .interface("java/io/Serializable")
+      .field("descriptor", "Ljava/lang/String;", ACC_PRIVATE | ACC_FINAL)
+      .native("toMethodDescriptorString", "()Ljava/lang/String;", ACC_PUBLIC, method_type_descriptor)
+      .native("parameterCount", "()I", ACC_PUBLIC, method_type_parameter_count)
+      .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, method_type_to_string)
+  )?;
+  // invokeExactとinvokeはシグネチャ多相で、インタプリタが呼び出し側の記述子で実行する
+  vm.define_class(
+    ClassDefinition::new("java/lang/invoke/MethodHandle")
+      .access_flags(ACC_PUBLIC | ACC_ABSTRACT | ACC_SUPER)
+      .field("kind", "I", ACC_PRIVATE | ACC_FINAL)
+      .field("member", "J", ACC_PRIVATE | ACC_FINAL)
+      .field("type", "Ljava/lang/invoke/MethodType;", ACC_PRIVATE | ACC_FINAL)
+      .native("invokeExact", "([Ljava/lang/Object;)Ljava/lang/Object;", ACC_PUBLIC | ACC_FINAL | ACC_VARARGS, method_handle_invoke)
+      .native("invoke", "([Ljava/lang/Object;)Ljava/lang/Object;", ACC_PUBLIC | ACC_FINAL | ACC_VARARGS, method_handle_invoke)
+      .native("type", "()Ljava/lang/invoke/MethodType;", ACC_PUBLIC, method_handle_type)
+      .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, method_handle_to_string)
+  )?;
+  invoke::define(vm)?;
+  misc_unsafe::define(vm)?;
+  for (name, super_class) in THROWABLES {
+    vm.define_class(ClassDefinition::new(name).super_class(Some(super_class)))?;
+  }
+  Ok(())
+}
+
+fn object_init(_vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(None)
+}
+
+pub(crate) fn object_hash_code(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(vm.identity_hash(this(args)?))))
+}
+
+fn object_equals(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int((args[0] == args[1]) as i32)))
+}
+
+// getClass().getName() + "@" + Integer.toHexString(hashCode())
+fn object_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let class = vm.object_class(this)?;
+  let hash = match vm.find_virtual(class, "hashCode", "()I") {
+    Some(method) => vm.invoke(method, vec![args[0]])?.map_or(Ok(0), |v| v.as_int())?,
+    None => vm.identity_hash(this),
+  };
+  let text = format!("{}@{:x}", vm.classes[class].java_name(), hash);
+  Ok(Some(Value::Ref(vm.new_string(&text)?)))
+}
+
+// 配列とCloneableを実装したオブジェクトの浅いコピー
+pub(crate) fn object_clone(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let class = vm.object_class(this)?;
+  let cloneable = vm.load_class("java/lang/Cloneable")?;
+  if !vm.classes[class].is_array() && !vm.implements(class, cloneable) {
+    return Err(VmError::java("java/lang/CloneNotSupportedException", vm.classes[class].java_name()));
+  }
+  let copy = vm.heap.get(this)?.clone();
+  Ok(Some(Value::Ref(vm.allocate(copy)?)))
+}
+
+fn object_wait(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let lock = vm.object_lock(this(args)?);
+  vm.monitor_wait(lock, None)?;
+  Ok(None)
+}
+
+// 0ミリ秒ならnotify()されるまで待つ
+fn wait_millis(vm: &mut Vm, this: ObjRef, millis: i64) -> Result<Option<Value>, VmError> {
+  if millis < 0 {
+    return Err(VmError::java("java/lang/IllegalArgumentException", "timeout value is negative"));
+  }
+  let timeout = (millis > 0).then(|| Duration::from_millis(millis as u64));
+  vm.monitor_wait(vm.object_lock(this), timeout)?;
+  Ok(None)
+}
+
+pub(crate) fn object_wait_timeout(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  wait_millis(vm, this(args)?, args[1].as_long()?)
+}
+
+// ナノ秒は切り上げてミリ秒にする
+fn object_wait_nanos(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (millis, nanos) = (args[1].as_long()?, args[2].as_int()?);
+  if millis < 0 {
+    return Err(VmError::java("java/lang/IllegalArgumentException", "timeout value is negative"));
+  }
+  if !(0..=999_999).contains(&nanos) {
+    return Err(VmError::java("java/lang/IllegalArgumentException", "nanosecond timeout value out of range"));
+  }
+  let millis = if nanos > 0 { millis.saturating_add(1) } else { millis };
+  wait_millis(vm, this(args)?, millis)
+}
+
+pub(crate) fn object_notify(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  vm.monitor_notify(vm.object_lock(this(args)?), false)?;
+  Ok(None)
+}
+
+pub(crate) fn object_notify_all(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  vm.monitor_notify(vm.object_lock(this(args)?), true)?;
+  Ok(None)
+}
+
+pub(crate) fn this(args: &[Value]) -> Result<ObjRef, VmError> {
+  args[0].as_ref()?.ok_or_else(|| VmError::null_pointer("this is null"))
+}
+
+// StringやCharSequenceの引数の内容 (nullならNullPointerException)
+pub(crate) fn text_arg(vm: &mut Vm, value: Value) -> Result<Vec<u16>, VmError> {
+  match value {
+    Value::Null => Err(VmError::java_without_message("java/lang/NullPointerException")),
+    _ => vm.stringify(value, &FieldType::Object("java/lang/Object".to_string())),
+  }
+}
+
+pub(crate) fn string_result(vm: &mut Vm, chars: &[u16]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Ref(vm.new_string_utf16(chars)?)))
+}
+
+// Throwable(), Throwable(String), Throwable(String, Throwable)
+fn throwable_init(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let message = args.get(1).copied().unwrap_or(Value::Null);
+  let cause = args.get(2).copied().unwrap_or(Value::Null);
+  vm.set_field(this, "detailMessage", "Ljava/lang/String;", message)?;
+  vm.set_field(this, "cause", "Ljava/lang/Throwable;", cause)?;
+  vm.fill_in_stack_trace(this)?;
+  Ok(None)
+}
+
+// Throwable(Throwable): メッセージは原因のtoString()
+fn throwable_init_cause(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let cause = args[1].as_ref()?;
+  let message = match cause {
+    Some(cause) => {
+      let text = vm.throwable_string(cause)?;
+      Value::Ref(vm.new_string(&text)?)
+    },
+    None => Value::Null,
+  };
+  vm.set_field(this, "detailMessage", "Ljava/lang/String;", message)?;
+  vm.set_field(this, "cause", "Ljava/lang/Throwable;", args[1])?;
+  vm.fill_in_stack_trace(this)?;
+  Ok(None)
+}
+
+fn enum_init(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  vm.set_field(this, "name", "Ljava/lang/String;", args[1])?;
+  vm.set_field(this, "ordinal", "I", args[2])?;
+  Ok(None)
+}
+
+fn enum_name(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(vm.get_field(this(args)?, "name", "Ljava/lang/String;")?))
+}
+
+fn enum_ordinal(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(vm.get_field(this(args)?, "ordinal", "I")?))
+}
+
+// 同じ列挙型の定数どうしだけを比べられる
+fn enum_compare_to(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let other = args[1].as_ref()?.ok_or_else(|| VmError::java_without_message("java/lang/NullPointerException"))?;
+  // 本体を持つ定数は列挙型のサブクラスになるので、Enumの直下のクラスで比べる
+  let enum_class = vm.load_class("java/lang/Enum")?;
+  let mut declaring = [vm.object_class(this)?, vm.object_class(other)?];
+  for class in &mut declaring {
+    while let Some(super_class) = vm.classes[*class].super_class
+      && super_class != enum_class {
+      *class = super_class;
+    }
+  }
+  if declaring[0] != declaring[1] {
+    return Err(VmError::java_without_message("java/lang/ClassCastException"));
+  }
+  let a = vm.get_field(this, "ordinal", "I")?.as_int()?;
+  let b = vm.get_field(other, "ordinal", "I")?.as_int()?;
+  Ok(Some(Value::Int(a - b)))
+}
+
+fn throwable_get_message(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(vm.get_field(this(args)?, "detailMessage", "Ljava/lang/String;")?))
+}
+
+fn throwable_get_cause(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(vm.get_field(this(args)?, "cause", "Ljava/lang/Throwable;")?))
+}
+
+fn throwable_set_cause(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  if args[1].as_ref()? == Some(this) {
+    return Err(VmError::java("java/lang/IllegalArgumentException", "Self-causation not permitted"));
+  }
+  if vm.throwable_cause(this)?.is_some() {
+    return Err(VmError::java("java/lang/IllegalStateException", format!("Can't overwrite cause with {}", vm.throwable_string(this)?)));
+  }
+  vm.set_field(this, "cause", "Ljava/lang/Throwable;", args[1])?;
+  Ok(Some(Value::Ref(this)))
+}
+
+pub(crate) fn throwable_fill_in_stack_trace(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  vm.fill_in_stack_trace(this)?;
+  Ok(Some(Value::Ref(this)))
+}
+
+fn throwable_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let text = vm.throwable_string(this(args)?)?;
+  Ok(Some(Value::Ref(vm.new_string(&text)?)))
+}
+
+fn throwable_print_stack_trace(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  eprint!("{}", vm.stack_trace_text(this(args)?)?);
+  Ok(None)
+}
+
+fn method_type_descriptor(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(vm.get_field(this(args)?, "descriptor", "Ljava/lang/String;")?))
+}
+
+fn method_type_parameter_count(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let method_type = vm.method_type_descriptor(this(args)?)?;
+  Ok(Some(Value::Int(method_type.parameters.len() as i32)))
+}
+
+fn method_type_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let text = method_type_string(&vm.method_type_descriptor(this(args)?)?);
+  Ok(Some(Value::Ref(vm.new_string(&text)?)))
+}
+
+// リフレクションなどでシグネチャ多相メソッドを直接呼んだ場合
+fn method_handle_invoke(_vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  Err(VmError::java("java/lang/UnsupportedOperationException", "cannot reflectively invoke MethodHandle"))
+}
+
+fn method_handle_type(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(vm.get_field(this(args)?, "type", "Ljava/lang/invoke/MethodType;")?))
+}
+
+fn method_handle_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let target = vm.handle_target(this(args)?)?;
+  let text = format!("MethodHandle{}", method_type_string(&target.handle_type));
+  Ok(Some(Value::Ref(vm.new_string(&text)?)))
+}
@@ -0,0 +1,470 @@
+use std::rc::Rc;
+
+use crate::{
+  runtime::{error::VmError, invokedynamic::CallSite, scheduler::ThreadId, value::{ObjRef, Value}, vm::Vm},
+  structure::class::{ClassFile, ClassFileAttribute, CodeNestedAttribute, FieldInfoAttribute, MethodInfoAttribute},
+  util::descriptor::{FieldType, MethodDescriptor},
+};
+
+pub type ClassId = usize;
+
+pub type NativeFn = fn(&mut Vm, &[Value]) -> Result<Option<Value>, VmError>;
+
+pub const ACC_PUBLIC: u16 = 0x0001;
+pub const ACC_PRIVATE: u16 = 0x0002;
+pub const ACC_PROTECTED: u16 = 0x0004;
+pub const ACC_STATIC: u16 = 0x0008;
+pub const ACC_FINAL: u16 = 0x0010;
+pub const ACC_SUPER: u16 = 0x0020;
+pub const ACC_SYNCHRONIZED: u16 = 0x0020;
+pub const ACC_VOLATILE: u16 = 0x0040;
+pub const ACC_TRANSIENT: u16 = 0x0080;
+pub const ACC_VARARGS: u16 = 0x0080;
+pub const ACC_NATIVE: u16 = 0x0100;
+pub const ACC_INTERFACE: u16 = 0x0200;
+pub const ACC_ABSTRACT: u16 = 0x0400;
+pub const ACC_SYNTHETIC: u16 = 0x1000;
+
+#[derive(Debug, Clone)]
+pub struct ExceptionHandler {
+  pub start_pc: usize,
+  pub end_pc: usize,
+  pub handler_pc: usize,
+  pub catch_type: u16,
+}
+
+#[derive(Debug, Clone)]
+pub struct Code {
+  pub bytes: Vec<u8>,
+  pub max_stack: usize,
+  pub max_locals: usize,
+  pub exception_table: Vec<ExceptionHandler>,
+  // (start_pc, 行番号)
+  pub line_numbers: Vec<(usize, u16)>,
+  // LocalVariableTableの内容 (デバッガでローカル変数を名前で表示するのに使う)
+  pub local_variables: Vec<LocalVariable>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LocalVariable {
+  pub start_pc: usize,
+  pub length: usize,
+  pub name: String,
+  pub descriptor: String,
+  pub index: usize,
+}
+
+impl Code {
+  pub fn line_number(&self, pc: usize) -> Option<u16> {
+    self.line_numbers.iter()
+      .filter(|(start_pc, _)| *start_pc <= pc)
+      .max_by_key(|(start_pc, _)| *start_pc)
+      .map(|(_, line)| *line)
+  }
+
+  // pcの位置で有効なローカル変数
+  pub fn local_variables_at(&self, pc: usize) -> impl Iterator<Item = &LocalVariable> {
+    self.local_variables.iter()
+      .filter(move |variable| variable.start_pc <= pc && pc < variable.start_pc + variable.length)
+  }
+}
+
+#[derive(Debug, Clone)]
+pub enum MethodBody {
+  Bytecode(Code),
+  Native(Option<NativeFn>),
+  Abstract,
+}
+
+#[derive(Debug)]
+pub struct RuntimeMethod {
+  pub id: usize,
+  pub class: ClassId,
+  pub class_name: String,
+  pub name: String,
+  pub descriptor: String,
+  pub access_flags: u16,
+  pub signature: MethodDescriptor,
+  pub body: MethodBody,
+  // クラスのメソッドなら仮想メソッドテーブル、インターフェースのメソッドならインターフェースメソッドテーブルの位置
+  pub vtable_index: Option<usize>,
+  pub itable_index: Option<usize>,
+}
+
+impl RuntimeMethod {
+  pub fn is_static(&self) -> bool {
+    self.access_flags & ACC_STATIC != 0
+  }
+
+  pub fn is_private(&self) -> bool {
+    self.access_flags & ACC_PRIVATE != 0
+  }
+
+  pub fn is_abstract(&self) -> bool {
+    self.access_flags & ACC_ABSTRACT != 0
+  }
+
+  pub fn is_synchronized(&self) -> bool {
+    self.access_flags & ACC_SYNCHRONIZED != 0
+  }
+
+  pub fn is_public(&self) -> bool {
+    self.access_flags & ACC_PUBLIC != 0
+  }
+
+  pub fn is_protected(&self) -> bool {
+    self.access_flags & ACC_PROTECTED != 0
+  }
+
+  pub fn is_final(&self) -> bool {
+    self.access_flags & ACC_FINAL != 0
+  }
+
+  // MethodHandle.invokeのように、呼び出し側の記述子で呼び出すメソッド (JVMS 2.9.3)
+  pub fn is_signature_polymorphic(&self) -> bool {
+    matches!(self.class_name.as_str(), "java/lang/invoke/MethodHandle" | "java/lang/invoke/VarHandle")
+      && self.access_flags & (ACC_NATIVE | ACC_VARARGS) == ACC_NATIVE | ACC_VARARGS
+  }
+
+  // 仮想呼び出しの対象になるメソッド (static、private、コンストラクタ、クラス初期化以外)
+  pub fn is_virtual(&self) -> bool {
+    !self.is_static() && !self.is_private() && !self.name.starts_with('<')
+  }
+
+  pub fn package(&self) -> &str {
+    self.class_name.rsplit_once('/').map_or("", |(package, _)| package)
+  }
+
+  // selfがotherをオーバーライドできるか (JVMS 5.4.5)
+  pub fn can_override(&self, other: &RuntimeMethod) -> bool {
+    self.name == other.name
+      && self.descriptor == other.descriptor
+      && self.is_virtual()
+      && !other.is_private()
+      && (other.is_public() || other.is_protected() || self.package() == other.package())
+  }
+
+  pub fn code(&self) -> Option<&Code> {
+    match &self.body {
+      MethodBody::Bytecode(code) => Some(code),
+      _ => None,
+    }
+  }
+
+  // HotSpotのエラーメッセージでの表記 (例: int Foo.bar(int, java.lang.String))
+  pub fn external_name(&self) -> String {
+    let parameters: Vec<String> = self.signature.parameters.iter().map(|p| p.to_string()).collect();
+    let return_type = self.signature.return_type.as_ref().map_or("void".to_string(), |t| t.to_string());
+    format!("{} {}.{}({})", return_type, self.class_name.replace('/', "."), self.name, parameters.join(", "))
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct RuntimeField {
+  pub name: String,
+  pub descriptor: String,
+  pub field_type: FieldType,
+  pub access_flags: u16,
+  // インスタンスフィールドならオブジェクト内の位置、staticならクラス内の位置
+  pub slot: usize,
+  pub constant_value: Option<u16>,
+}
+
+impl RuntimeField {
+  pub fn is_static(&self) -> bool {
+    self.access_flags & ACC_STATIC != 0
+  }
+
+  pub fn is_final(&self) -> bool {
+    self.access_flags & ACC_FINAL != 0
+  }
+
+  pub fn is_volatile(&self) -> bool {
+    self.access_flags & ACC_VOLATILE != 0
+  }
+
+  pub fn is_private(&self) -> bool {
+    self.access_flags & ACC_PRIVATE != 0
+  }
+}
+
+// クラスの初期化状態 (JVMS 5.5)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitState {
+  Uninitialized,
+  // 初期化しているスレッド
+  Initializing(ThreadId),
+  Initialized,
+  Erroneous,
+}
+
+// 解決済みのシンボリック参照 (解決に失敗した場合は同じエラーを返し続ける)
+#[derive(Debug, Clone)]
+pub enum ResolvedRef {
+  Class(ClassId),
+  Field(ClassId, usize),
+  // 参照先として指定されたクラスと、解決したメソッド
+  Method(ClassId, Rc<RuntimeMethod>),
+  // invokedynamicの呼び出しサイト
+  CallSite(Rc<CallSite>),
+  // ldcで読み込むMethodHandle、MethodType、動的定数の値
+  Constant(Value),
+  Error(VmError),
+}
+
+#[derive(Debug)]
+pub struct Class {
+  pub id: ClassId,
+  pub name: String,
+  pub access_flags: u16,
+  pub super_class: Option<ClassId>,
+  pub interfaces: Vec<ClassId>,
+  pub fields: Vec<RuntimeField>,
+  pub methods: Vec<Rc<RuntimeMethod>>,
+  // 継承したフィールドを含むインスタンスフィールドの初期値
+  pub instance_defaults: Vec<Value>,
+  pub static_values: Vec<Value>,
+  // 配列クラスの要素型と、要素が参照型の場合はそのクラス
+  pub component: Option<FieldType>,
+  pub component_class: Option<ClassId>,
+  pub source_file: Option<String>,
+  pub class_file: Option<Rc<ClassFile>>,
+  pub init_state: InitState,
+  // 初期化に失敗した時の例外 (NoClassDefFoundErrorの原因にする)
+  pub init_error: Option<ObjRef>,
+  // コンスタントプールのインデックスごとの解決結果
+  pub resolved: Vec<Option<ResolvedRef>>,
+  // invokevirtual/invokeinterfaceの直前のレシーバのクラスと呼び出したメソッド (コンスタントプールのインデックスごと)
+  pub call_caches: Vec<Option<(ClassId, Rc<RuntimeMethod>)>>,
+  pub vtable: Vec<Rc<RuntimeMethod>>,
+  // 実装するインターフェースごとの、インターフェースのメソッドに対応する実装
+  pub itable: Vec<(ClassId, Vec<Option<Rc<RuntimeMethod>>>)>,
+  pub nest_host: Option<String>,
+  pub nest_members: Vec<String>,
+}
+
+impl Class {
+  pub fn is_interface(&self) -> bool {
+    self.access_flags & ACC_INTERFACE != 0
+  }
+
+  pub fn is_abstract(&self) -> bool {
+    self.access_flags & ACC_ABSTRACT != 0
+  }
+
+  pub fn is_array(&self) -> bool {
+    self.component.is_some()
+  }
+
+  pub fn find_method(&self, name: &str, descriptor: &str) -> Option<Rc<RuntimeMethod>> {
+    self.methods.iter()
+      .find(|m| m.name == name && m.descriptor == descriptor)
+      .cloned()
+  }
+
+  pub fn java_name(&self) -> String {
+    self.name.replace('/', ".")
+  }
+
+  pub fn package(&self) -> &str {
+    self.name.rsplit_once('/').map_or("", |(package, _)| package)
+  }
+}
+
+pub struct FieldDefinition {
+  pub name: String,
+  pub descriptor: String,
+  pub access_flags: u16,
+  pub constant_value: Option<u16>,
+}
+
+pub struct MethodDefinition {
+  pub name: String,
+  pub descriptor: String,
+  pub access_flags: u16,
+  pub body: MethodBody,
+}
+
+// クラスファイルと組み込みクラスの両方から作られる、リンク前のクラス定義
+pub struct ClassDefinition {
+  pub name: String,
+  pub access_flags: u16,
+  pub super_class: Option<String>,
+  pub interfaces: Vec<String>,
+  pub fields: Vec<FieldDefinition>,
+  pub methods: Vec<MethodDefinition>,
+  pub source_file: Option<String>,
+  pub nest_host: Option<String>,
+  pub nest_members: Vec<String>,
+  pub class_file: Option<Rc<ClassFile>>,
+}
+
+impl ClassDefinition {
+  pub fn new(name: &str) -> Self {
+    ClassDefinition {
+      name: name.to_string(),
+      access_flags: ACC_PUBLIC | ACC_SUPER,
+      super_class: Some("java/lang/Object".to_string()),
+      interfaces: Vec::new(),
+      fields: Vec::new(),
+      methods: Vec::new(),
+      source_file: None,
+      nest_host: None,
+      nest_members: Vec::new(),
+      class_file: None,
+    }
+  }
+
+  pub fn from_class_file(class_file: ClassFile) -> Result<Self, String> {
+    let constant_pool = &class_file.constant_pool;
+    let name = constant_pool.get_class_name(class_file.this_class)?;
+    let super_class = match class_file.super_class {
+      0 => None,
+      index => Some(constant_pool.get_class_name(index)?),
+    };
+    let interfaces = class_file.interfaces.interfaces.iter()
+      .map(|i| constant_pool.get_class_name(*i))
+      .collect::<Result<Vec<_>, _>>()?;
+
+    let mut fields = Vec::new();
+    for field in &class_file.fields.fields {
+      let constant_value = field.attributes.attributes.iter().find_map(|attr| match attr {
+        FieldInfoAttribute::ConstantValue(c) => Some(c.constant_value_index),
+        _ => None,
+      });
+      fields.push(FieldDefinition {
+        name: constant_pool.get_utf8(field.name_index)?,
+        descriptor: constant_pool.get_utf8(field.descriptor_index)?,
+        access_flags: field.access_flags,
+        constant_value,
+      });
+    }
+
+    let mut methods = Vec::new();
+    for method in &class_file.methods.methods {
+      let code = method.attributes.attributes.iter().find_map(|attr| match attr {
+        MethodInfoAttribute::Code(code) => Some(code),
+        _ => None,
+      });
+      let body = match code {
+        Some(code) => MethodBody::Bytecode(Code {
+          bytes: code.code.iter()
+            .flat_map(|c| std::iter::once(c.opcode).chain(c.data.iter().copied()))
+            .collect(),
+          max_stack: code.max_stack as usize,
+          max_locals: code.max_locals as usize,
+          exception_table: code.exception_table.iter().map(|e| ExceptionHandler {
+            start_pc: e.start_pc as usize,
+            end_pc: e.end_pc as usize,
+            handler_pc: e.handler_pc as usize,
+            catch_type: e.catch_type,
+          }).collect(),
+          line_numbers: code.attributes.attributes.iter()
+            .filter_map(|attr| match attr {
+              CodeNestedAttribute::LineNumberTable(table) => Some(&table.line_number_table),
+              _ => None,
+            })
+            .flatten()
+            .map(|entry| (entry.start_pc as usize, entry.line_number))
+            .collect(),
+          local_variables: code.attributes.attributes.iter()
+            .filter_map(|attr| match attr {
+              CodeNestedAttribute::LocalVariableTable(table) => Some(&table.local_variable_table),
+              _ => None,
+            })
+            .flatten()
+            .map(|entry| Ok(LocalVariable {
+              start_pc: entry.start_pc as usize,
+              length: entry.length as usize,
+              name: constant_pool.get_utf8(entry.name_index)?,
+              descriptor: constant_pool.get_utf8(entry.descriptor_index)?,
+              index: entry.index as usize,
+            }))
+            .collect::<Result<_, String>>()?,
+        }),
+        None if method.access_flags & ACC_NATIVE != 0 => MethodBody::Native(None),
+        None => MethodBody::Abstract,
+      };
+      methods.push(MethodDefinition {
+        name: constant_pool.get_utf8(method.name_index)?,
+        descriptor: constant_pool.get_utf8(method.descriptor_index)?,
+        access_flags: method.access_flags,
+        body,
+      });
+    }
+
+    let mut source_file = None;
+    let mut nest_host = None;
+    let mut nest_members = Vec::new();
+    for attribute in &class_file.attributes.attributes {
+      match attribute {
+        ClassFileAttribute::SourceFile(source) => source_file = Some(constant_pool.get_utf8(source.source_file_index)?),
+        ClassFileAttribute::NestHost(host) => nest_host = Some(constant_pool.get_class_name(host.nest_host_index)?),
+        ClassFileAttribute::NestMembers(members) => {
+          nest_members = members.classes.iter()
+            .map(|&index| constant_pool.get_class_name(index))
+            .collect::<Result<Vec<_>, _>>()?;
+        },
+        _ => {},
+      }
+    }
+
+    Ok(ClassDefinition {
+      name,
+      access_flags: class_file.access_flags,
+      super_class,
+      interfaces,
+      fields,
+      methods,
+      source_file,
+      nest_host,
+      nest_members,
+      class_file: Some(Rc::new(class_file)),
+    })
+  }
+
+  pub fn super_class(mut self, name: Option<&str>) -> Self {
+    self.super_class = name.map(|n| n.to_string());
+    self
+  }
+
+  pub fn access_flags(mut self, access_flags: u16) -> Self {
+    self.access_flags = access_flags;
+    self
+  }
+
+  pub fn interface(mut self, name: &str) -> Self {
+    self.interfaces.push(name.to_string());
+    self
+  }
+
+  pub fn field(mut self, name: &str, descriptor: &str, access_flags: u16) -> Self {
+    self.fields.push(FieldDefinition {
+      name: name.to_string(),
+      descriptor: descriptor.to_string(),
+      access_flags,
+      constant_value: None,
+    });
+    self
+  }
+
+  pub fn native(mut self, name: &str, descriptor: &str, access_flags: u16, function: NativeFn) -> Self {
+    self.methods.push(MethodDefinition {
+      name: name.to_string(),
+      descriptor: descriptor.to_string(),
+      access_flags: access_flags | ACC_NATIVE,
+      body: MethodBody::Native(Some(function)),
+    });
+    self
+  }
+
+  pub fn abstract_method(mut self, name: &str, descriptor: &str, access_flags: u16) -> Self {
+    self.methods.push(MethodDefinition {
+      name: name.to_string(),
+      descriptor: descriptor.to_string(),
+      access_flags: access_flags | ACC_ABSTRACT,
+      body: MethodBody::Abstract,
+    });
+    self
+  }
+}
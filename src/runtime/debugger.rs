@@ -0,0 +1,652 @@
+use std::{
+  collections::HashMap,
+  io::{self, BufRead, Write},
+  process,
+  rc::Rc,
+};
+
+use crate::{
+  runtime::{
+    class::{ClassId, RuntimeField, RuntimeMethod},
+    frame::Frame,
+    heap::ObjectKind,
+    scheduler::ThreadId,
+    trace::instruction_text,
+    value::{ObjRef, Value},
+    vm::Vm,
+  },
+  structure::code::instruction_length,
+  util::descriptor::FieldType,
+};
+
+// 配列の要素はこの数まで表示する
+const MAX_ELEMENTS: usize = 100;
+
+const HELP: &str = "\
+break <Class>.<method>[:<pc>]   stop at an instruction (pc 0 if omitted)
+break <Class>:<line>            stop at a source line
+delete [<n>]                    delete a breakpoint (all if omitted)
+watch <Class>.<field>           stop before the field is written
+unwatch <Class>.<field>         remove a watchpoint
+breakpoints                     list breakpoints and watchpoints
+continue | c | run              resume execution
+step | s                        execute one instruction
+next | n                        execute one instruction, stepping over calls
+finish                          run until the current method returns
+where | bt                      print the call stack
+frame <n> | up | down           select a frame for locals/print/code
+locals                          print local variables of the selected frame
+stack                           print the operand stack of the selected frame
+print <expr> | p <expr>         print a value (name, $slot, @id, Class.field, expr.field, expr[index])
+code                            disassemble the method of the selected frame
+help                            print this help
+quit | q                        exit the program";
+
+// ブレークポイントの位置 (クラスが読み込まれる前にも設定できるように名前で持つ)
+#[derive(Debug, Clone)]
+enum BreakLocation {
+  Method { class: String, method: String, pc: usize },
+  // LineNumberTableで行の先頭の命令に変換する (同じソースファイルの内部クラスも含める)
+  Line { class: String, line: u16 },
+}
+
+impl BreakLocation {
+  fn text(&self) -> String {
+    match self {
+      BreakLocation::Method { class, method, pc } => format!("{}.{}:{}", class.replace('/', "."), method, pc),
+      BreakLocation::Line { class, line } => format!("{}:{}", class.replace('/', "."), line),
+    }
+  }
+
+  fn pcs(&self, method: &RuntimeMethod) -> Vec<usize> {
+    let Some(code) = method.code() else {
+      return Vec::new();
+    };
+    match self {
+      BreakLocation::Method { class, method: name, pc } if *class == method.class_name && *name == method.name => {
+        // 命令の途中の位置には止まれない
+        let mut at = 0;
+        while at < *pc {
+          at += instruction_length(&code.bytes, at).unwrap_or(1);
+        }
+        if at == *pc && at < code.bytes.len() { vec![at] } else { Vec::new() }
+      },
+      BreakLocation::Line { class, line }
+        if *class == method.class_name || method.class_name.strip_prefix(class.as_str()).is_some_and(|rest| rest.starts_with('$')) => {
+        code.line_numbers.iter().filter(|(_, l)| l == line).map(|(pc, _)| *pc).collect()
+      },
+      _ => Vec::new(),
+    }
+  }
+}
+
+// 実行を再開した後にどこで止まるか
+#[derive(Debug, Clone, Copy)]
+enum Resume {
+  Continue,
+  Step(ThreadId),
+  // 呼び出し先では止まらない (実行前のフレーム数)
+  Next(ThreadId, usize),
+  // 現在のメソッドから戻ったら止まる
+  Finish(ThreadId, usize),
+}
+
+enum Access {
+  Field(String),
+  Index(i32),
+}
+
+pub struct Debugger {
+  breakpoints: Vec<(usize, BreakLocation)>,
+  next_id: usize,
+  // メソッドIDごとの、ブレークポイントを置いた(pc, 番号)
+  locations: HashMap<usize, Rc<[(usize, usize)]>>,
+  // 書き込みで止まるフィールド (クラス名, フィールド名)
+  watches: Vec<(String, String)>,
+  resume: Resume,
+  // locals/print/codeで使うフレーム (0が実行中のフレーム)
+  selected: usize,
+}
+
+impl Default for Debugger {
+  fn default() -> Self {
+    Debugger {
+      breakpoints: Vec::new(),
+      next_id: 1,
+      locations: HashMap::new(),
+      watches: Vec::new(),
+      resume: Resume::Continue,
+      selected: 0,
+    }
+  }
+}
+
+fn read_command() -> Option<String> {
+  print!("(rdb) ");
+  let _ = io::stdout().flush();
+  let mut line = String::new();
+  match io::stdin().lock().read_line(&mut line) {
+    Ok(0) | Err(_) => None,
+    Ok(_) => Some(line.trim().to_string()),
+  }
+}
+
+// "a.b[2].c"を先頭の名前とアクセスの並びに分ける
+fn parse_expression(expression: &str) -> Result<(String, Vec<Access>), String> {
+  let end = expression.find(['.', '[']).unwrap_or(expression.len());
+  let head = expression[..end].to_string();
+  if head.is_empty() {
+    return Err(format!("Invalid expression: {}", expression));
+  }
+  let mut accesses = Vec::new();
+  let mut rest = &expression[end..];
+  while !rest.is_empty() {
+    if let Some(after) = rest.strip_prefix('.') {
+      let end = after.find(['.', '[']).unwrap_or(after.len());
+      if end == 0 {
+        return Err(format!("Invalid expression: {}", expression));
+      }
+      accesses.push(Access::Field(after[..end].to_string()));
+      rest = &after[end..];
+    } else if let Some(after) = rest.strip_prefix('[') {
+      let (index, after) = after.split_once(']').ok_or_else(|| format!("Invalid expression: {}", expression))?;
+      accesses.push(Access::Index(index.trim().parse().map_err(|_| format!("Invalid index: {}", index))?));
+      rest = after;
+    } else {
+      return Err(format!("Invalid expression: {}", expression));
+    }
+  }
+  Ok((head, accesses))
+}
+
+impl Vm {
+  // debugサブコマンド: 実行を始める前にコマンドを受け付ける
+  pub fn attach_debugger(&mut self) {
+    // 命令ごとに止まれるように、コンパイルしたコードは使わない
+    self.jit = None;
+    let mut debugger = Debugger::default();
+    println!("Type 'help' for a list of commands. Use 'run' to start the program.");
+    if self.debug_prompt(&mut debugger) {
+      self.debugger = Some(debugger);
+    }
+  }
+
+  // 命令を実行する前に呼び、止まる条件を満たしていればコマンドを受け付ける
+  pub(crate) fn debug_before(&mut self) {
+    let Some(mut debugger) = self.debugger.take() else {
+      return;
+    };
+    let Some(reason) = self.stop_reason(&mut debugger) else {
+      self.debugger = Some(debugger);
+      return;
+    };
+    let _ = io::stdout().flush();
+    if !reason.is_empty() {
+      println!("{}", reason);
+    }
+    debugger.selected = 0;
+    self.print_location(0);
+    if self.debug_prompt(&mut debugger) {
+      self.debugger = Some(debugger);
+    }
+  }
+
+  fn stop_reason(&mut self, debugger: &mut Debugger) -> Option<String> {
+    let frame = self.frames.last()?;
+    let method = frame.method.clone();
+    let pc = frame.pc;
+    let depth = self.frames.len();
+    let thread = self.threads.current;
+    let locations = debugger.locations.entry(method.id).or_insert_with(|| {
+      debugger.breakpoints.iter()
+        .flat_map(|(id, location)| location.pcs(&method).into_iter().map(move |pc| (pc, *id)))
+        .collect()
+    }).clone();
+    if let Some((_, id)) = locations.iter().find(|(at, _)| *at == pc) {
+      return Some(format!("Breakpoint {}", id));
+    }
+    if !debugger.watches.is_empty()
+      && let Some(text) = self.watch_hit(debugger, &method, pc) {
+      return Some(text);
+    }
+    let stepped = match debugger.resume {
+      Resume::Continue => false,
+      Resume::Step(t) => t == thread,
+      Resume::Next(t, d) => t == thread && depth <= d,
+      Resume::Finish(t, d) => t == thread && depth < d,
+    };
+    stepped.then(String::new)
+  }
+
+  // putfield/putstaticで監視しているフィールドに書き込む直前なら、書き込む値を表示する
+  fn watch_hit(&mut self, debugger: &Debugger, method: &RuntimeMethod, pc: usize) -> Option<String> {
+    let code = &method.code()?.bytes;
+    let opcode = *code.get(pc)?;
+    if opcode != 0xb3 && opcode != 0xb5 {
+      return None;
+    }
+    let index = u16::from_be_bytes([*code.get(pc + 1)?, *code.get(pc + 2)?]);
+    let (owner, field_index) = self.resolve_field(method.class, index).ok()?;
+    let field = &self.classes[owner].fields[field_index];
+    let watched = debugger.watches.iter().any(|(class, name)| {
+      *name == field.name && (self.classes[owner].name == *class
+        || self.class_names.get(class).is_some_and(|&watched| self.is_subclass_of(watched, owner)))
+    });
+    if !watched {
+      return None;
+    }
+    let stack = &self.frames.last()?.stack;
+    let new = *stack.last()?;
+    let old = if opcode == 0xb3 {
+      Some(self.classes[owner].static_values[field.slot])
+    } else {
+      match stack.len().checked_sub(2).map(|i| stack[i]) {
+        Some(Value::Ref(object)) => self.heap.get(object).ok()?.fields().ok().map(|fields| fields[field.slot]),
+        _ => None,
+      }
+    };
+    let old = old.map_or("?".to_string(), |old| self.debug_value(old, Some(&field.field_type)));
+    Some(format!(
+      "Watchpoint {}.{}: {} -> {}",
+      self.classes[owner].java_name(), field.name, old, self.debug_value(new, Some(&field.field_type)),
+    ))
+  }
+
+  // nは内側から数えたフレームの番号
+  fn debug_frame(&self, n: usize) -> Option<&Frame> {
+    self.frames.len().checked_sub(n + 1).map(|i| &self.frames[i])
+  }
+
+  fn print_location(&self, n: usize) {
+    let Some(frame) = self.debug_frame(n) else {
+      return;
+    };
+    println!("{}, pc {} [{}]", self.stack_frame_text(&frame.method, frame.pc), frame.pc, self.thread_name(self.threads.current));
+    if let Some(code) = frame.method.code() {
+      let class_file = self.class_file(frame.method.class).ok();
+      println!("  {}: {}", frame.pc, instruction_text(&code.bytes, frame.pc, class_file.as_ref().map(|class_file| &class_file.constant_pool)));
+    }
+  }
+
+  // 実行を再開する時はtrue、入力が終わってデバッガを外す時はfalseを返す
+  fn debug_prompt(&mut self, debugger: &mut Debugger) -> bool {
+    loop {
+      let Some(line) = read_command() else {
+        println!();
+        return false;
+      };
+      let (command, argument) = line.split_once(char::is_whitespace).unwrap_or((&line, ""));
+      let argument = argument.trim();
+      let thread = self.threads.current;
+      let depth = self.frames.len();
+      let running = !self.frames.is_empty();
+      let resume = match command {
+        "" => None,
+        "continue" | "c" | "run" | "r" => Some(Resume::Continue),
+        "step" | "s" | "stepi" | "si" => Some(Resume::Step(thread)),
+        "next" | "n" | "nexti" | "ni" if running => Some(Resume::Next(thread, depth)),
+        "finish" | "out" if running => Some(Resume::Finish(thread, depth)),
+        "next" | "n" | "nexti" | "ni" | "finish" | "out" => {
+          println!("The program is not running");
+          None
+        },
+        "quit" | "q" | "exit" => {
+          self.finish_trace();
+          self.finish_jdwp();
+          self.finish_profile();
+          let _ = io::stdout().flush();
+          process::exit(0)
+        },
+        _ => {
+          if let Err(e) = self.debug_command(debugger, command, argument) {
+            println!("{}", e);
+          }
+          None
+        },
+      };
+      if let Some(resume) = resume {
+        debugger.resume = resume;
+        return true;
+      }
+    }
+  }
+
+  fn debug_command(&mut self, debugger: &mut Debugger, command: &str, argument: &str) -> Result<(), String> {
+    match command {
+      "break" | "b" => self.add_breakpoint(debugger, argument),
+      "delete" | "d" => {
+        if argument.is_empty() {
+          debugger.breakpoints.clear();
+        } else {
+          let id = argument.parse::<usize>().map_err(|_| format!("Invalid breakpoint number: {}", argument))?;
+          let count = debugger.breakpoints.len();
+          debugger.breakpoints.retain(|(n, _)| *n != id);
+          if debugger.breakpoints.len() == count {
+            return Err(format!("No breakpoint number {}", id));
+          }
+        }
+        debugger.locations.clear();
+        Ok(())
+      },
+      "watch" => {
+        let (class, field) = argument.rsplit_once('.').ok_or("Usage: watch <Class>.<field>")?;
+        let class = class.replace('.', "/");
+        if let Some(&id) = self.class_names.get(&class)
+          && self.find_field_by_name(id, field).is_none() {
+          return Err(format!("No field {} in {}", field, class.replace('/', ".")));
+        }
+        println!("Watchpoint set on {}", argument);
+        debugger.watches.push((class, field.to_string()));
+        Ok(())
+      },
+      "unwatch" => {
+        let (class, field) = argument.rsplit_once('.').ok_or("Usage: unwatch <Class>.<field>")?;
+        let class = class.replace('.', "/");
+        let count = debugger.watches.len();
+        debugger.watches.retain(|watch| *watch != (class.clone(), field.to_string()));
+        if debugger.watches.len() == count {
+          return Err(format!("No watchpoint on {}", argument));
+        }
+        Ok(())
+      },
+      "breakpoints" | "info" => {
+        if debugger.breakpoints.is_empty() && debugger.watches.is_empty() {
+          println!("No breakpoints or watchpoints");
+        }
+        for (id, location) in &debugger.breakpoints {
+          println!("{}: {}", id, location.text());
+        }
+        for (class, field) in &debugger.watches {
+          println!("watch: {}.{}", class.replace('/', "."), field);
+        }
+        Ok(())
+      },
+      "where" | "bt" | "backtrace" => {
+        self.require_running()?;
+        for n in 0..self.frames.len() {
+          let frame = self.debug_frame(n).ok_or("No frame")?;
+          let marker = if n == debugger.selected { "=>" } else { "  " };
+          println!("{} #{} {}, pc {}", marker, n, self.stack_frame_text(&frame.method, frame.pc), frame.pc);
+        }
+        Ok(())
+      },
+      "frame" | "f" | "up" | "down" => {
+        self.require_running()?;
+        let n = match command {
+          "up" => debugger.selected + 1,
+          "down" => debugger.selected.checked_sub(1).ok_or("Already at the innermost frame")?,
+          _ if argument.is_empty() => debugger.selected,
+          _ => argument.parse().map_err(|_| format!("Invalid frame number: {}", argument))?,
+        };
+        if n >= self.frames.len() {
+          return Err(format!("No frame #{}", n));
+        }
+        debugger.selected = n;
+        self.print_location(n);
+        Ok(())
+      },
+      "locals" => {
+        let frame = self.selected_frame(debugger)?;
+        let code = frame.method.code().ok_or("No bytecode in this frame")?;
+        let mut variables = code.local_variables_at(frame.pc).collect::<Vec<_>>();
+        if variables.is_empty() {
+          println!("No LocalVariableTable (compile with -g for names)");
+          for (slot, value) in frame.locals.iter().enumerate() {
+            println!("${} = {}", slot, self.debug_value(*value, None));
+          }
+        }
+        variables.sort_by_key(|variable| variable.index);
+        for variable in variables {
+          let field_type = FieldType::parse(&variable.descriptor).ok();
+          let value = frame.locals.get(variable.index).copied().unwrap_or(Value::Top);
+          println!("{} = {}", variable.name, self.debug_value(value, field_type.as_ref()));
+        }
+        Ok(())
+      },
+      "stack" => {
+        let frame = self.selected_frame(debugger)?;
+        if frame.stack.is_empty() {
+          println!("Operand stack is empty");
+        }
+        for (i, value) in frame.stack.iter().enumerate().rev() {
+          println!("[{}] {}", i, self.debug_value(*value, None));
+        }
+        Ok(())
+      },
+      "print" | "p" | "inspect" | "x" => {
+        if argument.is_empty() {
+          return Err("Usage: print <expr>".to_string());
+        }
+        let frame = self.debug_frame(debugger.selected);
+        let (value, field_type) = self.evaluate(frame, argument)?;
+        println!("{} = {}", argument, self.debug_value(value, field_type.as_ref()));
+        if let Value::Ref(object) = value {
+          self.print_object(object);
+        }
+        Ok(())
+      },
+      "code" | "list" | "l" => {
+        let frame = self.selected_frame(debugger)?;
+        let code = frame.method.code().ok_or("No bytecode in this frame")?;
+        let class_file = self.class_file(frame.method.class).ok();
+        let pool = class_file.as_ref().map(|class_file| &class_file.constant_pool);
+        let breakpoints = debugger.breakpoints.iter()
+          .flat_map(|(_, location)| location.pcs(&frame.method))
+          .collect::<Vec<_>>();
+        println!("{}.{}{}", self.classes[frame.method.class].java_name(), frame.method.name, frame.method.descriptor);
+        let mut pc = 0;
+        while pc < code.bytes.len() {
+          let marker = match (pc == frame.pc, breakpoints.contains(&pc)) {
+            (true, _) => "=>",
+            (false, true) => " *",
+            (false, false) => "  ",
+          };
+          println!("{} {:>4}: {}", marker, pc, instruction_text(&code.bytes, pc, pool));
+          pc += instruction_length(&code.bytes, pc).unwrap_or(1);
+        }
+        Ok(())
+      },
+      "help" | "h" | "?" => {
+        println!("{}", HELP);
+        Ok(())
+      },
+      _ => Err(format!("Unknown command: {} (type 'help' for a list of commands)", command)),
+    }
+  }
+
+  fn require_running(&self) -> Result<(), String> {
+    if self.frames.is_empty() {
+      return Err("The program is not running".to_string());
+    }
+    Ok(())
+  }
+
+  fn selected_frame(&self, debugger: &Debugger) -> Result<&Frame, String> {
+    self.require_running()?;
+    self.debug_frame(debugger.selected).ok_or_else(|| "No frame selected".to_string())
+  }
+
+  fn add_breakpoint(&mut self, debugger: &mut Debugger, argument: &str) -> Result<(), String> {
+    let usage = "Usage: break <Class>.<method>[:<pc>] | break <Class>:<line>";
+    if argument.is_empty() {
+      return Err(usage.to_string());
+    }
+    let (target, number) = match argument.rsplit_once(':') {
+      Some((target, number)) => (target, Some(number.parse::<usize>().map_err(|_| usage.to_string())?)),
+      None => (argument, None),
+    };
+    let class_name = target.replace('.', "/");
+    // "Foo:12"はクラスと行番号、"Foo.bar:3"はメソッドとpc
+    let is_class = number.is_some() && (self.class_names.contains_key(&class_name) || self.find_class_bytes(&class_name).is_some());
+    let location = match (target.rsplit_once('.'), number) {
+      (_, Some(line)) if is_class => BreakLocation::Line { class: class_name, line: u16::try_from(line).map_err(|_| usage.to_string())? },
+      (Some((class, method)), pc) => BreakLocation::Method { class: class.replace('.', "/"), method: method.to_string(), pc: pc.unwrap_or(0) },
+      (None, _) => return Err(usage.to_string()),
+    };
+    let class = match &location {
+      BreakLocation::Method { class, .. } | BreakLocation::Line { class, .. } => class.clone(),
+    };
+    // 読み込み済みのクラスなら、その位置に命令があるか確かめる
+    if let Some(&id) = self.class_names.get(&class) {
+      let nested = format!("{}$", class);
+      let found = self.classes.iter()
+        .filter(|c| c.id == id || (matches!(location, BreakLocation::Line { .. }) && c.name.starts_with(&nested)))
+        .flat_map(|c| &c.methods)
+        .any(|method| !location.pcs(method).is_empty());
+      if !found {
+        return Err(format!("No code at {}", location.text()));
+      }
+      println!("Breakpoint {} at {}", debugger.next_id, location.text());
+    } else {
+      println!("Breakpoint {} at {} (deferred until the class is loaded)", debugger.next_id, location.text());
+    }
+    debugger.breakpoints.push((debugger.next_id, location));
+    debugger.next_id += 1;
+    debugger.locations.clear();
+    Ok(())
+  }
+
+  // スーパークラスも含めて名前だけでフィールドを探す
+  fn find_field_by_name(&self, class: ClassId, name: &str) -> Option<(ClassId, &RuntimeField)> {
+    let mut current = Some(class);
+    while let Some(c) = current {
+      if let Some(field) = self.classes[c].fields.iter().find(|field| field.name == name) {
+        return Some((c, field));
+      }
+      current = self.classes[c].super_class;
+    }
+    None
+  }
+
+  fn evaluate(&self, frame: Option<&Frame>, expression: &str) -> Result<(Value, Option<FieldType>), String> {
+    let (head, accesses) = parse_expression(expression)?;
+    let mut accesses = accesses.into_iter().peekable();
+    let local = frame.and_then(|frame| {
+      let code = frame.method.code()?;
+      let variable = code.local_variables_at(frame.pc).find(|variable| variable.name == head)?;
+      Some((frame.locals.get(variable.index).copied()?, FieldType::parse(&variable.descriptor).ok()))
+    });
+    let (mut value, mut field_type) = if let Some(id) = head.strip_prefix('@') {
+      let object = ObjRef(u32::from_str_radix(id, 16).map_err(|_| format!("Invalid object id: {}", head))?);
+      self.heap.get(object).map_err(|_| format!("No object {}", head))?;
+      (Value::Ref(object), None)
+    } else if let Some(slot) = head.strip_prefix('$') {
+      let frame = frame.ok_or("The program is not running")?;
+      let slot = slot.parse::<usize>().map_err(|_| format!("Invalid local variable slot: {}", head))?;
+      (frame.locals.get(slot).copied().ok_or_else(|| format!("No local variable slot {}", slot))?, None)
+    } else if let Some(local) = local {
+      local
+    } else {
+      // ローカル変数でなければ、読み込み済みのクラスのstaticフィールドとして探す
+      let mut path = vec![head.clone()];
+      while let Some(Access::Field(name)) = accesses.peek() {
+        path.push(name.clone());
+        accesses.next();
+      }
+      let mut found = None;
+      for split in (1..path.len()).rev() {
+        if let Some(&class) = self.class_names.get(&path[..split].join("/"))
+          && let Some((owner, field)) = self.find_field_by_name(class, &path[split]).filter(|(_, field)| field.is_static()) {
+          found = Some((split, (self.classes[owner].static_values[field.slot], Some(field.field_type.clone()))));
+          break;
+        }
+      }
+      let (split, result) = found.ok_or_else(|| format!("No local variable or static field named {}", path.join(".")))?;
+      let mut result = result;
+      for name in &path[split + 1..] {
+        result = self.access_field(result.0, name)?;
+      }
+      result
+    };
+    for access in accesses {
+      (value, field_type) = match access {
+        Access::Field(name) => self.access_field(value, &name)?,
+        Access::Index(index) => self.access_element(value, index)?,
+      };
+    }
+    Ok((value, field_type))
+  }
+
+  fn access_field(&self, value: Value, name: &str) -> Result<(Value, Option<FieldType>), String> {
+    let object = match value {
+      Value::Ref(object) => object,
+      Value::Null => return Err(format!("Cannot read field {} of null", name)),
+      _ => return Err(format!("Cannot read field {} of a primitive value", name)),
+    };
+    let object = self.heap.get(object).map_err(|e| e.to_string())?;
+    match &object.kind {
+      ObjectKind::Array(data) if name == "length" => Ok((Value::Int(data.len() as i32), Some(FieldType::Int))),
+      ObjectKind::Instance(fields) => {
+        let (_, field) = self.find_field_by_name(object.class, name).filter(|(_, field)| !field.is_static())
+          .ok_or_else(|| format!("No field {} in {}", name, self.classes[object.class].java_name()))?;
+        Ok((fields[field.slot], Some(field.field_type.clone())))
+      },
+      _ => Err(format!("No field {} in {}", name, self.classes[object.class].java_name())),
+    }
+  }
+
+  fn access_element(&self, value: Value, index: i32) -> Result<(Value, Option<FieldType>), String> {
+    let Value::Ref(array) = value else {
+      return Err("Not an array".to_string());
+    };
+    let object = self.heap.get(array).map_err(|e| e.to_string())?;
+    let ObjectKind::Array(data) = &object.kind else {
+      return Err("Not an array".to_string());
+    };
+    if index < 0 || index as usize >= data.len() {
+      return Err(format!("Index {} out of bounds for length {}", index, data.len()));
+    }
+    Ok((data.get(index as usize), self.classes[object.class].component.clone()))
+  }
+
+  fn debug_value(&self, value: Value, field_type: Option<&FieldType>) -> String {
+    match (value, field_type) {
+      (Value::Int(value), Some(FieldType::Boolean)) => (value != 0).to_string(),
+      (Value::Int(value), Some(FieldType::Char)) => match char::from_u32(value as u32) {
+        Some(c) if !c.is_control() => format!("'{}'", c),
+        _ => format!("'\\u{:04x}'", value),
+      },
+      (Value::Int(value), _) => value.to_string(),
+      (Value::Long(value), _) => value.to_string(),
+      (Value::Float(value), _) => format!("{:?}", value),
+      (Value::Double(value), _) => format!("{:?}", value),
+      (Value::Ref(object), _) => self.reference_text(object),
+      (Value::Null, _) => "null".to_string(),
+      (Value::ReturnAddress(pc), _) => format!("ret:{}", pc),
+      (Value::Top, _) => "<unset>".to_string(),
+    }
+  }
+
+  // オブジェクトのフィールドや配列の要素を1段階だけ展開する (文字列とClassはそのまま)
+  fn print_object(&self, object: ObjRef) {
+    let Ok(heap_object) = self.heap.get(object) else {
+      return;
+    };
+    let class = &self.classes[heap_object.class];
+    if class.name == "java/lang/String" || self.mirror_target(object).is_some() {
+      return;
+    }
+    match &heap_object.kind {
+      ObjectKind::Array(data) => {
+        let elements = (0..data.len().min(MAX_ELEMENTS))
+          .map(|i| self.debug_value(data.get(i), class.component.as_ref()))
+          .collect::<Vec<_>>();
+        let more = if data.len() > MAX_ELEMENTS { ", ..." } else { "" };
+        println!("  length {}: [{}{}]", data.len(), elements.join(", "), more);
+      },
+      ObjectKind::Instance(values) => {
+        let mut chain = Vec::new();
+        let mut current = Some(heap_object.class);
+        while let Some(c) = current {
+          chain.push(c);
+          current = self.classes[c].super_class;
+        }
+        for &c in chain.iter().rev() {
+          for field in self.classes[c].fields.iter().filter(|field| !field.is_static()) {
+            println!("  {}: {}", field.name, self.debug_value(values[field.slot], Some(&field.field_type)));
+          }
+        }
+      },
+    }
+  }
+}
@@ -0,0 +1,113 @@
+use std::rc::Rc;
+
+use crate::runtime::{
+  class::{ClassId, RuntimeMethod},
+  error::VmError,
+  vm::Vm,
+};
+
+impl Vm {
+  // メソッドテーブルの位置を割り当て、仮想メソッドテーブルの長さを返す
+  // オーバーライドしたメソッドはスーパークラスのメソッドと同じ位置を使う
+  // ブリッジメソッド (ACC_BRIDGE) も普通のメソッドとしてオーバーライドし、選択される (消去後の記述子での呼び出しを実際のメソッドに渡す)
+  pub(crate) fn assign_table_indices(&self, super_class: Option<ClassId>, is_interface: bool, methods: &mut [RuntimeMethod]) -> Result<usize, VmError> {
+    if is_interface {
+      for (index, method) in methods.iter_mut().filter(|m| m.is_virtual()).enumerate() {
+        method.itable_index = Some(index);
+      }
+      return Ok(0);
+    }
+    let inherited = super_class.map_or(&[][..], |s| &self.classes[s].vtable[..]);
+    let mut length = inherited.len();
+    for method in methods.iter_mut().filter(|m| m.is_virtual()) {
+      let overridden = inherited.iter().position(|m| method.can_override(m));
+      if let Some(final_method) = inherited.iter().find(|m| method.can_override(m) && m.is_final()) {
+        return Err(VmError::java("java/lang/VerifyError", format!(
+          "class {} overrides final method {}.{}{}",
+          method.class_name.replace('/', "."), final_method.class_name.replace('/', "."), method.name, method.descriptor,
+        )));
+      }
+      method.vtable_index = Some(overridden.unwrap_or_else(|| {
+        length += 1;
+        length - 1
+      }));
+    }
+    Ok(length)
+  }
+
+  pub(crate) fn build_vtable(&self, super_class: Option<ClassId>, length: usize, methods: &[Rc<RuntimeMethod>]) -> Result<Vec<Rc<RuntimeMethod>>, VmError> {
+    let mut vtable: Vec<Option<Rc<RuntimeMethod>>> = match super_class {
+      Some(s) => self.classes[s].vtable.iter().cloned().map(Some).collect(),
+      None => Vec::new(),
+    };
+    vtable.resize(length, None);
+    for method in methods {
+      if let Some(index) = method.vtable_index {
+        // 別パッケージのクラスを挟むと、パッケージプライベートと公開のメソッドを両方オーバーライドすることがある
+        for entry in vtable.iter_mut().filter(|entry| entry.as_ref().is_some_and(|m| method.can_override(m))) {
+          *entry = Some(method.clone());
+        }
+        vtable[index] = Some(method.clone());
+      }
+    }
+    vtable.into_iter().collect::<Option<Vec<_>>>()
+      .ok_or_else(|| VmError::internal("Unassigned vtable entry"))
+  }
+
+  // インターフェースのメソッドごとに、このクラスで選択されるメソッドを求めておく
+  // 選択できない (抽象メソッドやデフォルトメソッドの衝突) 場合はNoneにして、呼び出し時にエラーにする
+  pub(crate) fn build_itable(&self, class: ClassId) -> Vec<(ClassId, Vec<Option<Rc<RuntimeMethod>>>)> {
+    if self.classes[class].is_interface() {
+      return Vec::new();
+    }
+    self.superinterfaces(class).into_iter()
+      .map(|interface| {
+        let entries = self.classes[interface].methods.iter()
+          .filter(|m| m.itable_index.is_some())
+          .map(|m| self.select_method(class, m).ok())
+          .collect();
+        (interface, entries)
+      })
+      .collect()
+  }
+
+  // メソッドテーブルから呼び出すメソッドを選ぶ。テーブルに無い場合はJVMS 5.4.6の手順で選ぶ
+  pub fn dispatch(&self, receiver: ClassId, resolved: &Rc<RuntimeMethod>) -> Result<Rc<RuntimeMethod>, VmError> {
+    // privateメソッド (ネストメイトからの呼び出しを含む) は選択を行わない
+    if resolved.is_private() {
+      return Ok(resolved.clone());
+    }
+    let class = &self.classes[receiver];
+    let found = match (resolved.vtable_index, resolved.itable_index) {
+      (Some(index), _) => class.vtable.get(index).cloned(),
+      (_, Some(index)) => class.itable.iter()
+        .find(|(interface, _)| *interface == resolved.class)
+        .and_then(|(_, entries)| entries.get(index).cloned().flatten()),
+      _ => None,
+    };
+    match found {
+      Some(method) if !method.is_abstract() => Ok(method),
+      _ => self.select_method(receiver, resolved),
+    }
+  }
+
+  // 仮想メソッドテーブルから名前と記述子でメソッドを探す (ネイティブ側からtoString()などを呼ぶ時に使う)
+  pub fn find_virtual(&self, class: ClassId, name: &str, descriptor: &str) -> Option<Rc<RuntimeMethod>> {
+    self.classes[class].vtable.iter()
+      .find(|m| m.name == name && m.descriptor == descriptor && !m.is_abstract())
+      .cloned()
+  }
+
+  // 呼び出し箇所 (コンスタントプールのインデックス) ごとに、直前のレシーバのクラスと選んだメソッドを覚えておく
+  pub(crate) fn dispatch_cached(&mut self, caller: ClassId, index: u16, receiver: ClassId, resolved: &Rc<RuntimeMethod>) -> Result<Rc<RuntimeMethod>, VmError> {
+    if let Some(Some((class, method))) = self.classes[caller].call_caches.get(index as usize)
+      && *class == receiver {
+      return Ok(method.clone());
+    }
+    let method = self.dispatch(receiver, resolved)?;
+    if let Some(cache) = self.classes[caller].call_caches.get_mut(index as usize) {
+      *cache = Some((receiver, method.clone()));
+    }
+    Ok(method)
+  }
+}
@@ -0,0 +1,47 @@
+use std::fmt;
+
+use crate::runtime::value::ObjRef;
+
+#[derive(Debug, Clone)]
+pub enum VmError {
+  // Javaの例外として投げるもの (クラス名は内部形式、投げる時点で例外オブジェクトを作る)
+  Java { class: String, message: Option<String> },
+  // 作成済みの例外オブジェクト
+  Thrown(ObjRef),
+  Internal(String),
+}
+
+impl VmError {
+  pub fn java(class: &str, message: impl Into<String>) -> Self {
+    VmError::Java { class: class.to_string(), message: Some(message.into()) }
+  }
+
+  pub fn java_without_message(class: &str) -> Self {
+    VmError::Java { class: class.to_string(), message: None }
+  }
+
+  pub fn internal(message: impl Into<String>) -> Self {
+    VmError::Internal(message.into())
+  }
+
+  pub fn null_pointer(message: impl Into<String>) -> Self {
+    Self::java("java/lang/NullPointerException", message)
+  }
+}
+
+impl fmt::Display for VmError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      VmError::Java { class, message: Some(message) } => write!(f, "{}: {}", class.replace('/', "."), message),
+      VmError::Java { class, message: None } => write!(f, "{}", class.replace('/', ".")),
+      VmError::Thrown(exception) => write!(f, "Exception object {:?}", exception),
+      VmError::Internal(message) => write!(f, "Internal error: {}", message),
+    }
+  }
+}
+
+impl From<String> for VmError {
+  fn from(message: String) -> Self {
+    VmError::Internal(message)
+  }
+}
@@ -0,0 +1,196 @@
+use crate::{
+  runtime::{
+    class::RuntimeMethod,
+    error::VmError,
+    heap::ArrayData,
+    value::{ObjRef, Value},
+    vm::Vm,
+  },
+  util::descriptor::FieldType,
+};
+
+impl Vm {
+  // エラーを例外オブジェクトにする (内部エラーはそのまま返す)
+  pub fn materialize(&mut self, error: VmError) -> Result<ObjRef, VmError> {
+    match error {
+      VmError::Thrown(exception) => Ok(exception),
+      VmError::Java { class, message } => {
+        match self.new_throwable(&class, message.as_deref(), None) {
+          // ヒープが足りない場合は、起動時に確保しておいたOutOfMemoryErrorを使う
+          Err(VmError::Java { class, .. }) if class == "java/lang/OutOfMemoryError" => {
+            self.out_of_memory.ok_or_else(|| VmError::internal("Out of memory while creating an exception"))
+          },
+          result => result,
+        }
+      },
+      VmError::Internal(_) => Err(error),
+    }
+  }
+
+  pub fn new_throwable(&mut self, class_name: &str, message: Option<&str>, cause: Option<ObjRef>) -> Result<ObjRef, VmError> {
+    let class = self.load_class(class_name)?;
+    self.initialize(class)?;
+    let exception = self.instantiate(class)?;
+    let mark = self.handles.mark();
+    self.handles.new_local(exception);
+    if let Some(cause) = cause {
+      self.handles.new_local(cause);
+    }
+    let result = self.init_throwable(exception, message, cause);
+    self.handles.release(mark);
+    result.map(|_| exception)
+  }
+
+  pub(crate) fn init_throwable(&mut self, exception: ObjRef, message: Option<&str>, cause: Option<ObjRef>) -> Result<(), VmError> {
+    if let Some(message) = message {
+      let message = self.new_string(message)?;
+      self.set_field(exception, "detailMessage", "Ljava/lang/String;", Value::Ref(message))?;
+    }
+    self.set_field(exception, "cause", "Ljava/lang/Throwable;", Value::from_ref(cause))?;
+    self.fill_in_stack_trace(exception)
+  }
+
+  // 現在のフレームを (メソッドID << 32 | pc) の配列として例外に記録する
+  pub fn fill_in_stack_trace(&mut self, exception: ObjRef) -> Result<(), VmError> {
+    let throwable = self.load_class("java/lang/Throwable")?;
+    // 例外自身のコンストラクタとfillInStackTraceのフレームは含めない
+    let trace: Vec<i64> = self.frames.iter().rev()
+      .skip_while(|frame| {
+        matches!(frame.method.name.as_str(), "<init>" | "fillInStackTrace")
+          && self.is_subclass_of(frame.method.class, throwable)
+      })
+      .map(|frame| ((frame.method.id as i64) << 32) | frame.pc as i64)
+      .collect();
+    let mark = self.handles.mark();
+    self.handles.new_local(exception);
+    let backtrace = self.new_array(&FieldType::Long, trace.len() as i32);
+    self.handles.release(mark);
+    let backtrace = backtrace?;
+    *self.heap.get_mut(backtrace)?.array_mut()? = ArrayData::Long(trace);
+    self.set_field(exception, "backtrace", "Ljava/lang/Object;", Value::Ref(backtrace))
+  }
+
+  // 例外テーブルから、現在のフレームで例外を捕まえるハンドラを探す
+  fn find_handler(&mut self, exception: ObjRef) -> Result<Option<usize>, VmError> {
+    let frame = self.frame()?;
+    let (method, pc) = (frame.method.clone(), frame.pc);
+    let Some(code) = method.code() else { return Ok(None) };
+    let exception_class = self.object_class(exception)?;
+    for handler in &code.exception_table {
+      if pc < handler.start_pc || pc >= handler.end_pc {
+        continue;
+      }
+      if handler.catch_type == 0 {
+        return Ok(Some(handler.handler_pc));
+      }
+      let catch_class = self.resolve_class(method.class, handler.catch_type)?;
+      if self.is_subclass_of(exception_class, catch_class) {
+        return Ok(Some(handler.handler_pc));
+      }
+    }
+    Ok(None)
+  }
+
+  // ハンドラが見つかるまでフレームを巻き戻す。base以下まで戻った場合は例外を返す
+  pub(crate) fn unwind(&mut self, base: usize, error: VmError) -> Result<(), VmError> {
+    let mut exception = self.materialize(error)?;
+    let mark = self.handles.mark();
+    self.handles.new_local(exception);
+    let result = loop {
+      if self.frames.len() <= base {
+        break Err(VmError::Thrown(exception));
+      }
+      match self.find_handler(exception) {
+        Ok(Some(handler_pc)) => {
+          let frame = self.frame_mut()?;
+          frame.stack.clear();
+          frame.push(Value::Ref(exception));
+          frame.pc = handler_pc;
+          break Ok(());
+        },
+        Ok(None) => {
+          self.pop_frame();
+        },
+        // catch_typeの解決に失敗した場合は、その例外に置き換えて続ける
+        Err(e) => match self.materialize(e) {
+          Ok(replacement) => exception = self.handles.new_local(replacement),
+          Err(e) => break Err(e),
+        },
+      }
+    };
+    self.handles.release(mark);
+    result
+  }
+
+  pub fn throwable_message(&self, exception: ObjRef) -> Result<Option<String>, VmError> {
+    match self.get_field(exception, "detailMessage", "Ljava/lang/String;")?.as_ref()? {
+      Some(message) => Ok(Some(self.string_value(message)?)),
+      None => Ok(None),
+    }
+  }
+
+  pub fn throwable_cause(&self, exception: ObjRef) -> Result<Option<ObjRef>, VmError> {
+    self.get_field(exception, "cause", "Ljava/lang/Throwable;")?.as_ref()
+  }
+
+  // Throwable.toString()と同じ形式 (クラス名: メッセージ)
+  pub fn throwable_string(&self, exception: ObjRef) -> Result<String, VmError> {
+    let name = self.classes[self.object_class(exception)?].java_name();
+    Ok(match self.throwable_message(exception)? {
+      Some(message) => format!("{}: {}", name, message),
+      None => name,
+    })
+  }
+
+  // at Foo.bar(Foo.java:12) の形式の各行
+  pub fn stack_trace(&self, exception: ObjRef) -> Result<Vec<String>, VmError> {
+    let Some(backtrace) = self.get_field(exception, "backtrace", "Ljava/lang/Object;")?.as_ref()? else {
+      return Ok(Vec::new());
+    };
+    let ArrayData::Long(entries) = self.heap.get(backtrace)?.array()? else {
+      return Err(VmError::internal("Backtrace is not a long array"));
+    };
+    Ok(entries.iter().map(|&entry| {
+      let method = &self.methods[(entry >> 32) as usize];
+      self.stack_frame_text(method, (entry & 0xffff_ffff) as usize)
+    }).collect())
+  }
+
+  pub(crate) fn stack_frame_text(&self, method: &RuntimeMethod, pc: usize) -> String {
+    let class = &self.classes[method.class];
+    let location = match (&class.source_file, method.code().and_then(|code| code.line_number(pc))) {
+      (Some(source), Some(line)) => format!("{}:{}", source, line),
+      (Some(source), None) => source.clone(),
+      (None, _) => "Unknown Source".to_string(),
+    };
+    format!("{}.{}({})", class.java_name(), method.name, location)
+  }
+
+  // printStackTrace()の出力 (原因の例外は共通するフレームを省略する)
+  pub fn stack_trace_text(&self, exception: ObjRef) -> Result<String, VmError> {
+    let mut text = format!("{}\n", self.throwable_string(exception)?);
+    let mut trace = self.stack_trace(exception)?;
+    for line in &trace {
+      text.push_str(&format!("\tat {}\n", line));
+    }
+    let mut seen = vec![exception];
+    let mut cause = self.throwable_cause(exception)?;
+    while let Some(current) = cause.filter(|c| !seen.contains(c)) {
+      let cause_trace = self.stack_trace(current)?;
+      let common = cause_trace.iter().rev().zip(trace.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+      text.push_str(&format!("Caused by: {}\n", self.throwable_string(current)?));
+      for line in &cause_trace[..cause_trace.len() - common] {
+        text.push_str(&format!("\tat {}\n", line));
+      }
+      if common > 0 {
+        text.push_str(&format!("\t... {} more\n", common));
+      }
+      seen.push(current);
+      trace = cause_trace;
+      cause = self.throwable_cause(current)?;
+    }
+    Ok(text)
+  }
+}
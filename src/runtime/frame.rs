@@ -0,0 +1,69 @@
+use std::rc::Rc;
+
+use crate::runtime::{class::RuntimeMethod, error::VmError, monitor::LockKey, value::Value};
+
+#[derive(Debug)]
+pub struct Frame {
+  pub method: Rc<RuntimeMethod>,
+  pub pc: usize,
+  pub locals: Vec<Value>,
+  pub stack: Vec<Value>,
+  // ネイティブ側から呼び出されたフレーム (戻り値は呼び出し元のスタックに積まない)
+  pub entry: bool,
+  // synchronizedメソッドが持っているロック
+  pub lock: Option<LockKey>,
+}
+
+impl Frame {
+  pub fn new(method: Rc<RuntimeMethod>, args: Vec<Value>, entry: bool) -> Frame {
+    let (max_locals, max_stack) = match method.code() {
+      Some(code) => (code.max_locals, code.max_stack),
+      None => (0, 0),
+    };
+    let mut locals = Vec::with_capacity(max_locals.max(args.len() * 2));
+    for arg in args {
+      let wide = arg.is_wide();
+      locals.push(arg);
+      if wide {
+        locals.push(Value::Top);
+      }
+    }
+    if locals.len() < max_locals {
+      locals.resize(max_locals, Value::Top);
+    }
+    Frame {
+      method,
+      pc: 0,
+      locals,
+      stack: Vec::with_capacity(max_stack),
+      entry,
+      lock: None,
+    }
+  }
+
+  pub fn push(&mut self, value: Value) {
+    self.stack.push(value);
+  }
+
+  pub fn pop(&mut self) -> Result<Value, VmError> {
+    self.stack.pop().ok_or_else(|| VmError::internal("Operand stack underflow"))
+  }
+
+  pub fn local(&self, index: usize) -> Result<Value, VmError> {
+    self.locals.get(index).copied()
+      .ok_or_else(|| VmError::internal(format!("Invalid local variable index: {}", index)))
+  }
+
+  pub fn set_local(&mut self, index: usize, value: Value) -> Result<(), VmError> {
+    let wide = value.is_wide();
+    let required = index + if wide { 2 } else { 1 };
+    if required > self.locals.len() {
+      return Err(VmError::internal(format!("Invalid local variable index: {}", index)));
+    }
+    self.locals[index] = value;
+    if wide {
+      self.locals[index + 1] = Value::Top;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,112 @@
+use std::time::{Duration, Instant};
+
+use crate::runtime::{
+  class::ResolvedRef,
+  error::VmError,
+  heap::Object,
+  value::{ObjRef, Value},
+  vm::Vm,
+};
+
+// 最初のGCを起動するヒープ使用量
+const INITIAL_THRESHOLD: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Default, Clone)]
+pub struct GcStats {
+  pub collections: usize,
+  pub freed_objects: usize,
+  pub freed_bytes: usize,
+  pub peak_used: usize,
+  pub total_pause: Duration,
+}
+
+impl Vm {
+  pub fn allocate(&mut self, object: Object) -> Result<ObjRef, VmError> {
+    let size = object.size();
+    let max_heap = self.options.max_heap;
+    if self.heap.used() + size > self.gc_threshold.min(max_heap) {
+      self.collect_garbage();
+      if self.heap.used() + size > max_heap {
+        return Err(VmError::java("java/lang/OutOfMemoryError", "Java heap space"));
+      }
+    }
+    if self.profiler.is_some() {
+      self.profile_allocation(object.class, size);
+    }
+    let reference = self.heap.alloc(object);
+    self.gc_stats.peak_used = self.gc_stats.peak_used.max(self.heap.used());
+    Ok(reference)
+  }
+
+  pub fn collect_garbage(&mut self) {
+    let start = Instant::now();
+    let before = self.heap.used();
+    let roots = self.gc_roots();
+    let (objects, bytes) = self.heap.collect(roots);
+    let pause = start.elapsed();
+
+    let after = self.heap.used();
+    // 生存オブジェクトの2倍まで使ったら次のGCを行う
+    self.gc_threshold = (after * 2).max(INITIAL_THRESHOLD);
+    self.gc_stats.collections += 1;
+    self.gc_stats.freed_objects += objects;
+    self.gc_stats.freed_bytes += bytes;
+    self.gc_stats.total_pause += pause;
+    if self.options.verbose_gc {
+      eprintln!(
+        "[gc] GC({}) Pause Full {}K->{}K({}K) {} objects freed {:.3}ms",
+        self.gc_stats.collections - 1,
+        before / 1024,
+        after / 1024,
+        self.options.max_heap / 1024,
+        objects,
+        pause.as_secs_f64() * 1000.0,
+      );
+    }
+  }
+
+  // フレームのローカル変数とオペランドスタック、staticフィールド、解決済みの定数、文字列リテラル、ネイティブの参照、確保済みの例外、保留中の例外、各スレッドの保存した状態とスレッドのオブジェクト、ロックされているオブジェクト、Classオブジェクト
+  fn gc_roots(&self) -> Vec<ObjRef> {
+    let frame_values = self.frames.iter()
+      .flat_map(|frame| frame.locals.iter().chain(frame.stack.iter()));
+    let static_values = self.classes.iter()
+      .flat_map(|class| class.static_values.iter());
+    let constants = self.classes.iter()
+      .flat_map(|class| class.resolved.iter())
+      .filter_map(|resolved| match resolved {
+        Some(ResolvedRef::Constant(value)) => Some(value),
+        _ => None,
+      });
+    frame_values
+      .chain(static_values)
+      .chain(constants)
+      .chain(self.entry_result.iter())
+      .filter_map(|value| match value {
+        Value::Ref(reference) => Some(*reference),
+        _ => None,
+      })
+      .chain(self.strings.values().copied())
+      .chain(self.classes.iter().filter_map(|class| class.init_error))
+      .chain(self.out_of_memory)
+      .chain(self.pending_exception)
+      .chain(self.handles.roots())
+      .chain(self.threads.roots())
+      .chain(self.monitor_roots())
+      .chain(self.mirrors.roots())
+      .collect()
+  }
+
+  pub fn print_gc_stats(&self) {
+    let stats = &self.gc_stats;
+    eprintln!(
+      "[gc] {} collections, {} objects ({}K) freed, total pause {:.3}ms, peak heap {}K, live {} objects ({}K)",
+      stats.collections,
+      stats.freed_objects,
+      stats.freed_bytes / 1024,
+      stats.total_pause.as_secs_f64() * 1000.0,
+      stats.peak_used / 1024,
+      self.heap.live_objects(),
+      self.heap.used() / 1024,
+    );
+  }
+}
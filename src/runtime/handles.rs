@@ -0,0 +1,74 @@
+use std::mem;
+
+use crate::runtime::value::ObjRef;
+
+// ネイティブコードが保持している参照 (GCのルートになる)
+#[derive(Debug, Default)]
+pub struct Handles {
+  locals: Vec<Option<ObjRef>>,
+  globals: Vec<Option<ObjRef>>,
+}
+
+impl Handles {
+  // ローカル参照はネイティブメソッドの呼び出し単位で解放する
+  pub fn mark(&self) -> usize {
+    self.locals.len()
+  }
+
+  pub fn release(&mut self, mark: usize) {
+    self.locals.truncate(mark);
+  }
+
+  pub fn new_local(&mut self, reference: ObjRef) -> ObjRef {
+    self.locals.push(Some(reference));
+    reference
+  }
+
+  // JNIのローカル参照として番号で参照する
+  pub fn push_local(&mut self, reference: ObjRef) -> usize {
+    self.locals.push(Some(reference));
+    self.locals.len() - 1
+  }
+
+  pub fn local(&self, index: usize) -> Option<ObjRef> {
+    self.locals.get(index).copied().flatten()
+  }
+
+  pub fn delete_local(&mut self, index: usize) {
+    if let Some(slot) = self.locals.get_mut(index) {
+      *slot = None;
+    }
+  }
+
+  // スレッドを切り替える時に、ローカル参照をスレッドごとに入れ替える
+  pub fn replace_locals(&mut self, locals: Vec<Option<ObjRef>>) -> Vec<Option<ObjRef>> {
+    mem::replace(&mut self.locals, locals)
+  }
+
+  pub fn new_global(&mut self, reference: ObjRef) -> usize {
+    match self.globals.iter().position(Option::is_none) {
+      Some(index) => {
+        self.globals[index] = Some(reference);
+        index
+      },
+      None => {
+        self.globals.push(Some(reference));
+        self.globals.len() - 1
+      },
+    }
+  }
+
+  pub fn global(&self, index: usize) -> Option<ObjRef> {
+    self.globals.get(index).copied().flatten()
+  }
+
+  pub fn delete_global(&mut self, index: usize) {
+    if let Some(slot) = self.globals.get_mut(index) {
+      *slot = None;
+    }
+  }
+
+  pub fn roots(&self) -> impl Iterator<Item = ObjRef> + '_ {
+    self.locals.iter().chain(&self.globals).flatten().copied()
+  }
+}
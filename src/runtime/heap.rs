@@ -0,0 +1,252 @@
+use crate::{runtime::{class::ClassId, error::VmError, value::{ObjRef, Value}}, util::descriptor::FieldType};
+
+#[derive(Debug, Clone)]
+pub enum ArrayData {
+  Boolean(Vec<i8>),
+  Byte(Vec<i8>),
+  Char(Vec<u16>),
+  Short(Vec<i16>),
+  Int(Vec<i32>),
+  Long(Vec<i64>),
+  Float(Vec<f32>),
+  Double(Vec<f64>),
+  Ref(Vec<Value>),
+}
+
+impl ArrayData {
+  pub fn new(component: &FieldType, length: usize) -> ArrayData {
+    match component {
+      FieldType::Boolean => ArrayData::Boolean(vec![0; length]),
+      FieldType::Byte => ArrayData::Byte(vec![0; length]),
+      FieldType::Char => ArrayData::Char(vec![0; length]),
+      FieldType::Short => ArrayData::Short(vec![0; length]),
+      FieldType::Int => ArrayData::Int(vec![0; length]),
+      FieldType::Long => ArrayData::Long(vec![0; length]),
+      FieldType::Float => ArrayData::Float(vec![0.0; length]),
+      FieldType::Double => ArrayData::Double(vec![0.0; length]),
+      FieldType::Object(_) | FieldType::Array(_) => ArrayData::Ref(vec![Value::Null; length]),
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    match self {
+      ArrayData::Boolean(v) | ArrayData::Byte(v) => v.len(),
+      ArrayData::Char(v) => v.len(),
+      ArrayData::Short(v) => v.len(),
+      ArrayData::Int(v) => v.len(),
+      ArrayData::Long(v) => v.len(),
+      ArrayData::Float(v) => v.len(),
+      ArrayData::Double(v) => v.len(),
+      ArrayData::Ref(v) => v.len(),
+    }
+  }
+
+  // 1要素あたりのバイト数 (ヒープ使用量の見積もりに使う)
+  pub fn element_size(&self) -> usize {
+    match self {
+      ArrayData::Boolean(_) | ArrayData::Byte(_) => 1,
+      ArrayData::Char(_) | ArrayData::Short(_) => 2,
+      ArrayData::Int(_) | ArrayData::Float(_) => 4,
+      ArrayData::Long(_) | ArrayData::Double(_) | ArrayData::Ref(_) => 8,
+    }
+  }
+
+  pub fn get(&self, index: usize) -> Value {
+    match self {
+      ArrayData::Boolean(v) | ArrayData::Byte(v) => Value::Int(v[index] as i32),
+      ArrayData::Char(v) => Value::Int(v[index] as i32),
+      ArrayData::Short(v) => Value::Int(v[index] as i32),
+      ArrayData::Int(v) => Value::Int(v[index]),
+      ArrayData::Long(v) => Value::Long(v[index]),
+      ArrayData::Float(v) => Value::Float(v[index]),
+      ArrayData::Double(v) => Value::Double(v[index]),
+      ArrayData::Ref(v) => v[index],
+    }
+  }
+
+  pub fn set(&mut self, index: usize, value: Value) -> Result<(), VmError> {
+    match self {
+      ArrayData::Boolean(v) => v[index] = (value.as_int()? & 1) as i8,
+      ArrayData::Byte(v) => v[index] = value.as_int()? as i8,
+      ArrayData::Char(v) => v[index] = value.as_int()? as u16,
+      ArrayData::Short(v) => v[index] = value.as_int()? as i16,
+      ArrayData::Int(v) => v[index] = value.as_int()?,
+      ArrayData::Long(v) => v[index] = value.as_long()?,
+      ArrayData::Float(v) => v[index] = value.as_float()?,
+      ArrayData::Double(v) => v[index] = value.as_double()?,
+      ArrayData::Ref(v) => {
+        value.as_ref()?;
+        v[index] = value;
+      },
+    }
+    Ok(())
+  }
+
+  // start..start + lengthの要素を取り出す
+  pub fn slice(&self, start: usize, length: usize) -> ArrayData {
+    let range = start..start + length;
+    match self {
+      ArrayData::Boolean(v) => ArrayData::Boolean(v[range].to_vec()),
+      ArrayData::Byte(v) => ArrayData::Byte(v[range].to_vec()),
+      ArrayData::Char(v) => ArrayData::Char(v[range].to_vec()),
+      ArrayData::Short(v) => ArrayData::Short(v[range].to_vec()),
+      ArrayData::Int(v) => ArrayData::Int(v[range].to_vec()),
+      ArrayData::Long(v) => ArrayData::Long(v[range].to_vec()),
+      ArrayData::Float(v) => ArrayData::Float(v[range].to_vec()),
+      ArrayData::Double(v) => ArrayData::Double(v[range].to_vec()),
+      ArrayData::Ref(v) => ArrayData::Ref(v[range].to_vec()),
+    }
+  }
+
+  // 同じ要素型のsourceの全要素をstartの位置へ書き込む (要素型が違えばfalse)
+  pub fn copy_from(&mut self, start: usize, source: &ArrayData) -> bool {
+    let range = start..start + source.len();
+    match (self, source) {
+      (ArrayData::Boolean(d), ArrayData::Boolean(s)) | (ArrayData::Byte(d), ArrayData::Byte(s)) => d[range].copy_from_slice(s),
+      (ArrayData::Char(d), ArrayData::Char(s)) => d[range].copy_from_slice(s),
+      (ArrayData::Short(d), ArrayData::Short(s)) => d[range].copy_from_slice(s),
+      (ArrayData::Int(d), ArrayData::Int(s)) => d[range].copy_from_slice(s),
+      (ArrayData::Long(d), ArrayData::Long(s)) => d[range].copy_from_slice(s),
+      (ArrayData::Float(d), ArrayData::Float(s)) => d[range].copy_from_slice(s),
+      (ArrayData::Double(d), ArrayData::Double(s)) => d[range].copy_from_slice(s),
+      (ArrayData::Ref(d), ArrayData::Ref(s)) => d[range].copy_from_slice(s),
+      _ => return false,
+    }
+    true
+  }
+}
+
+#[derive(Debug, Clone)]
+pub enum ObjectKind {
+  Instance(Vec<Value>),
+  Array(ArrayData),
+}
+
+#[derive(Debug, Clone)]
+pub struct Object {
+  pub class: ClassId,
+  pub kind: ObjectKind,
+}
+
+impl Object {
+  pub fn fields(&self) -> Result<&Vec<Value>, VmError> {
+    match &self.kind {
+      ObjectKind::Instance(fields) => Ok(fields),
+      ObjectKind::Array(_) => Err(VmError::internal("Expected instance, found array")),
+    }
+  }
+
+  pub fn fields_mut(&mut self) -> Result<&mut Vec<Value>, VmError> {
+    match &mut self.kind {
+      ObjectKind::Instance(fields) => Ok(fields),
+      ObjectKind::Array(_) => Err(VmError::internal("Expected instance, found array")),
+    }
+  }
+
+  pub fn array(&self) -> Result<&ArrayData, VmError> {
+    match &self.kind {
+      ObjectKind::Array(data) => Ok(data),
+      ObjectKind::Instance(_) => Err(VmError::internal("Expected array, found instance")),
+    }
+  }
+
+  pub fn array_mut(&mut self) -> Result<&mut ArrayData, VmError> {
+    match &mut self.kind {
+      ObjectKind::Array(data) => Ok(data),
+      ObjectKind::Instance(_) => Err(VmError::internal("Expected array, found instance")),
+    }
+  }
+
+  pub fn size(&self) -> usize {
+    16 + match &self.kind {
+      ObjectKind::Instance(fields) => fields.len() * 8,
+      ObjectKind::Array(data) => data.len() * data.element_size(),
+    }
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct Heap {
+  objects: Vec<Option<Object>>,
+  free: Vec<u32>,
+  used: usize,
+}
+
+impl Heap {
+  pub fn new() -> Self {
+    Heap::default()
+  }
+
+  pub fn alloc(&mut self, object: Object) -> ObjRef {
+    self.used += object.size();
+    match self.free.pop() {
+      Some(index) => {
+        self.objects[index as usize] = Some(object);
+        ObjRef(index)
+      },
+      None => {
+        self.objects.push(Some(object));
+        ObjRef(self.objects.len() as u32 - 1)
+      },
+    }
+  }
+
+  pub fn get(&self, reference: ObjRef) -> Result<&Object, VmError> {
+    self.objects.get(reference.index())
+      .and_then(|o| o.as_ref())
+      .ok_or_else(|| VmError::internal(format!("Dangling reference: {:?}", reference)))
+  }
+
+  pub fn get_mut(&mut self, reference: ObjRef) -> Result<&mut Object, VmError> {
+    self.objects.get_mut(reference.index())
+      .and_then(|o| o.as_mut())
+      .ok_or_else(|| VmError::internal(format!("Dangling reference: {:?}", reference)))
+  }
+
+  pub fn live_objects(&self) -> usize {
+    self.objects.len() - self.free.len()
+  }
+
+  pub fn used(&self) -> usize {
+    self.used
+  }
+
+  // ルートから到達できるオブジェクトに印を付け、残りを解放する
+  // 戻り値は (解放したオブジェクト数, 解放したバイト数)
+  pub fn collect(&mut self, roots: impl IntoIterator<Item = ObjRef>) -> (usize, usize) {
+    let mut marked = vec![false; self.objects.len()];
+    let mut pending: Vec<ObjRef> = roots.into_iter().collect();
+    while let Some(reference) = pending.pop() {
+      let index = reference.index();
+      if index >= marked.len() || marked[index] {
+        continue;
+      }
+      let Some(object) = &self.objects[index] else { continue };
+      marked[index] = true;
+      let children = match &object.kind {
+        ObjectKind::Instance(fields) => fields.as_slice(),
+        ObjectKind::Array(ArrayData::Ref(elements)) => elements.as_slice(),
+        ObjectKind::Array(_) => &[],
+      };
+      pending.extend(children.iter().filter_map(|v| match v {
+        Value::Ref(r) => Some(*r),
+        _ => None,
+      }));
+    }
+
+    let mut freed = (0, 0);
+    for (index, slot) in self.objects.iter_mut().enumerate() {
+      if marked[index] {
+        continue;
+      }
+      if let Some(object) = slot.take() {
+        let size = object.size();
+        self.used -= size;
+        self.free.push(index as u32);
+        freed.0 += 1;
+        freed.1 += size;
+      }
+    }
+    freed
+  }
+}
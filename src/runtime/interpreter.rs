@@ -0,0 +1,946 @@
+use std::rc::Rc;
+
+use crate::{
+  runtime::{
+    class::{ClassId, MethodBody, RuntimeMethod},
+    error::VmError,
+    frame::Frame,
+    memory::{self, Location, MemoryOrder},
+    value::{ObjRef, Value},
+    vm::Vm,
+  },
+  structure::code::CODE_BYTES,
+  util::descriptor::FieldType,
+};
+
+fn read_u8(code: &[u8], pc: usize) -> Result<u8, VmError> {
+  code.get(pc).copied().ok_or_else(|| VmError::internal(format!("pc out of range: {}", pc)))
+}
+
+fn read_u16(code: &[u8], pc: usize) -> Result<u16, VmError> {
+  Ok(((read_u8(code, pc)? as u16) << 8) | read_u8(code, pc + 1)? as u16)
+}
+
+fn read_i16(code: &[u8], pc: usize) -> Result<i16, VmError> {
+  Ok(read_u16(code, pc)? as i16)
+}
+
+fn read_i32(code: &[u8], pc: usize) -> Result<i32, VmError> {
+  Ok(((read_u16(code, pc)? as u32) << 16 | read_u16(code, pc + 2)? as u32) as i32)
+}
+
+fn branch(pc: usize, offset: i32) -> usize {
+  (pc as i64 + offset as i64) as usize
+}
+
+// invoke命令の長さ (呼び出し先から戻った時にpcを進めるのに使う)
+pub fn invoke_length(opcode: u8) -> usize {
+  match opcode {
+    0xb9 | 0xba => 5,
+    _ => 3,
+  }
+}
+
+fn array_type(atype: u8) -> Result<FieldType, VmError> {
+  match atype {
+    4 => Ok(FieldType::Boolean),
+    5 => Ok(FieldType::Char),
+    6 => Ok(FieldType::Float),
+    7 => Ok(FieldType::Double),
+    8 => Ok(FieldType::Byte),
+    9 => Ok(FieldType::Short),
+    10 => Ok(FieldType::Int),
+    11 => Ok(FieldType::Long),
+    _ => Err(VmError::internal(format!("Invalid newarray type: {}", atype))),
+  }
+}
+
+pub(crate) fn compare_float(a: f64, b: f64, nan: i32) -> i32 {
+  if a.is_nan() || b.is_nan() {
+    nan
+  } else if a > b {
+    1
+  } else if a < b {
+    -1
+  } else {
+    0
+  }
+}
+
+impl Vm {
+  pub fn frame(&self) -> Result<&Frame, VmError> {
+    self.frames.last().ok_or_else(|| VmError::internal("No active frame"))
+  }
+
+  pub fn frame_mut(&mut self) -> Result<&mut Frame, VmError> {
+    self.frames.last_mut().ok_or_else(|| VmError::internal("No active frame"))
+  }
+
+  fn push(&mut self, value: Value) -> Result<(), VmError> {
+    self.frame_mut()?.push(value);
+    Ok(())
+  }
+
+  fn pop(&mut self) -> Result<Value, VmError> {
+    self.frame_mut()?.pop()
+  }
+
+  fn pop_int(&mut self) -> Result<i32, VmError> {
+    self.pop()?.as_int()
+  }
+
+  fn pop_long(&mut self) -> Result<i64, VmError> {
+    self.pop()?.as_long()
+  }
+
+  fn pop_float(&mut self) -> Result<f32, VmError> {
+    self.pop()?.as_float()
+  }
+
+  fn pop_double(&mut self) -> Result<f64, VmError> {
+    self.pop()?.as_double()
+  }
+
+  fn pop_ref(&mut self) -> Result<Option<ObjRef>, VmError> {
+    self.pop()?.as_ref()
+  }
+
+  fn pop_args(&mut self, count: usize) -> Result<Vec<Value>, VmError> {
+    let frame = self.frame_mut()?;
+    if frame.stack.len() < count {
+      return Err(VmError::internal("Operand stack underflow"));
+    }
+    let at = frame.stack.len() - count;
+    Ok(frame.stack.split_off(at))
+  }
+
+  // 基準の深さより上のフレームが全て戻るまで実行する
+  pub(crate) fn execute(&mut self, base: usize) -> Result<Option<Value>, VmError> {
+    while self.frames.len() > base {
+      self.tick();
+      if self.debugger.is_some() {
+        self.debug_before();
+      }
+      if self.profiler.is_some() {
+        self.profile_before();
+      }
+      if self.jdwp.is_some() {
+        self.jdwp_before();
+      }
+      let depth = self.frames.len();
+      let traced = self.tracer.is_some() && self.trace_before();
+      let compiled = if self.jit.is_some() { self.run_compiled() } else { None };
+      let result = compiled.unwrap_or_else(|| self.step()).or_else(|e| self.unwind(base, e));
+      if traced {
+        self.trace_after(depth);
+      }
+      if let Err(e) = result {
+        while self.frames.len() > base {
+          self.pop_frame();
+        }
+        return Err(e);
+      }
+    }
+    Ok(self.entry_result.take())
+  }
+
+  fn current_class(&self) -> Result<ClassId, VmError> {
+    Ok(self.frame()?.method.class)
+  }
+
+  fn resolve_class_ref(&mut self, index: u16) -> Result<ClassId, VmError> {
+    let current = self.current_class()?;
+    self.resolve_class(current, index)
+  }
+
+  fn resolve_field_ref(&mut self, index: u16) -> Result<(ClassId, usize), VmError> {
+    let current = self.current_class()?;
+    self.resolve_field(current, index)
+  }
+
+  fn resolve_method_ref(&mut self, index: u16) -> Result<(ClassId, Rc<RuntimeMethod>), VmError> {
+    let current = self.current_class()?;
+    self.resolve_method(current, index)
+  }
+
+  fn null_check(reference: Option<ObjRef>, message: impl FnOnce() -> String) -> Result<ObjRef, VmError> {
+    reference.ok_or_else(|| VmError::null_pointer(message()))
+  }
+
+  pub(crate) fn array_index(&self, array: ObjRef, index: i32) -> Result<usize, VmError> {
+    let length = self.heap.get(array)?.array()?.len();
+    if index < 0 || index as usize >= length {
+      return Err(VmError::java(
+        "java/lang/ArrayIndexOutOfBoundsException",
+        format!("Index {} out of bounds for length {}", index, length),
+      ));
+    }
+    Ok(index as usize)
+  }
+
+  // 参照型の配列に入れられる値か (入れられなければArrayStoreException)
+  pub(crate) fn check_array_store(&self, array: ObjRef, value: Value) -> Result<(), VmError> {
+    if let Value::Ref(element) = value {
+      let array_class = self.object_class(array)?;
+      let element_class = self.object_class(element)?;
+      if let Some(component_class) = self.classes[array_class].component_class
+        && !self.is_assignable(element_class, component_class) {
+        return Err(VmError::java("java/lang/ArrayStoreException", self.classes[element_class].java_name()));
+      }
+    }
+    Ok(())
+  }
+
+  fn array_load(&mut self) -> Result<(), VmError> {
+    let index = self.pop_int()?;
+    let array = Self::null_check(self.pop_ref()?, || "Cannot load from array".to_string())?;
+    let index = self.array_index(array, index)?;
+    let value = self.heap.get(array)?.array()?.get(index);
+    self.push(value)
+  }
+
+  fn array_store(&mut self) -> Result<(), VmError> {
+    let value = self.pop()?;
+    let index = self.pop_int()?;
+    let array = Self::null_check(self.pop_ref()?, || "Cannot store to array".to_string())?;
+    let index = self.array_index(array, index)?;
+    self.check_array_store(array, value)?;
+    self.heap.get_mut(array)?.array_mut()?.set(index, value)
+  }
+
+  fn new_multi_array(&mut self, array_type: &FieldType, counts: &[i32]) -> Result<ObjRef, VmError> {
+    let component = match array_type {
+      FieldType::Array(component) => component.as_ref().clone(),
+      _ => return Err(VmError::internal("multianewarray requires an array type")),
+    };
+    let array = self.new_array(&component, counts[0])?;
+    if counts.len() > 1 {
+      // 内側の配列を確保する間に外側の配列が回収されないようにする
+      let mark = self.handles.mark();
+      self.handles.new_local(array);
+      let result = (0..counts[0] as usize).try_for_each(|i| {
+        let sub = self.new_multi_array(&component, &counts[1..])?;
+        self.heap.get_mut(array)?.array_mut()?.set(i, Value::Ref(sub))
+      });
+      self.handles.release(mark);
+      result?;
+    }
+    Ok(array)
+  }
+
+  fn invoke_from_frame(&mut self, method: Rc<RuntimeMethod>, args: Vec<Value>) -> Result<(), VmError> {
+    match &method.body {
+      MethodBody::Bytecode(_) => self.push_frame(Frame::new(method, args, false)),
+      _ => {
+        let result = self.invoke(method, args)?;
+        self.complete_invoke(result)
+      },
+    }
+  }
+
+  // 呼び出し元のフレームに戻り値を積み、invoke命令の次へ進める
+  fn complete_invoke(&mut self, result: Option<Value>) -> Result<(), VmError> {
+    let frame = self.frame_mut()?;
+    if let Some(value) = result {
+      frame.push(value);
+    }
+    let opcode = match frame.method.code() {
+      Some(code) => read_u8(&code.bytes, frame.pc)?,
+      None => return Err(VmError::internal("Caller frame has no code")),
+    };
+    frame.pc += invoke_length(opcode);
+    Ok(())
+  }
+
+  pub(crate) fn return_from_frame(&mut self, value: Option<Value>) -> Result<(), VmError> {
+    let frame = self.frames.pop().ok_or_else(|| VmError::internal("No active frame"))?;
+    if self.profiler.is_some() {
+      self.profile_exit();
+    }
+    if let Some(lock) = frame.lock {
+      self.monitor_exit(lock)?;
+    }
+    // コンストラクタで書き込んだfinalフィールドは、オブジェクトの参照より先に見えるようにする
+    if frame.method.name == "<init>" && self.classes[frame.method.class].fields.iter().any(|f| f.is_final() && !f.is_static()) {
+      memory::freeze();
+    }
+    if frame.entry {
+      self.entry_result = value;
+      return Ok(());
+    }
+    self.complete_invoke(value)
+  }
+
+  fn invoke_static(&mut self, index: u16) -> Result<(), VmError> {
+    let (_, method) = self.resolve_method_ref(index)?;
+    if !method.is_static() {
+      return Err(self.incompatible_member("Expected static method", &method.class_name, &method.name));
+    }
+    self.initialize(method.class)?;
+    let args = self.pop_args(method.signature.parameters.len())?;
+    self.invoke_from_frame(method, args)
+  }
+
+  fn invoke_special(&mut self, index: u16) -> Result<(), VmError> {
+    let (symbolic, resolved) = self.resolve_method_ref(index)?;
+    if resolved.is_static() {
+      return Err(self.incompatible_member("Expected non-static method", &resolved.class_name, &resolved.name));
+    }
+    let current = self.current_class()?;
+    let args = self.pop_args(resolved.signature.parameters.len() + 1)?;
+    Self::null_check(args[0].as_ref()?, || format!("Cannot invoke \"{}.{}()\"", resolved.class_name.replace('/', "."), resolved.name))?;
+    let method = self.select_special(current, symbolic, &resolved)?;
+    self.invoke_from_frame(method, args)
+  }
+
+  // invokevirtualとinvokeinterfaceで共通 (どちらの解決かはコンスタントの種類で決まる)
+  fn invoke_virtual(&mut self, index: u16) -> Result<(), VmError> {
+    let (_, resolved) = self.resolve_method_ref(index)?;
+    if resolved.is_static() {
+      return Err(self.incompatible_member("Expected non-static method", &resolved.class_name, &resolved.name));
+    }
+    let args = self.pop_args(resolved.signature.parameters.len() + 1)?;
+    let receiver = Self::null_check(args[0].as_ref()?, || format!("Cannot invoke \"{}.{}()\"", resolved.class_name.replace('/', "."), resolved.name))?;
+    if resolved.is_signature_polymorphic() {
+      let result = self.invoke_polymorphic(&resolved, args)?;
+      return self.complete_invoke(result);
+    }
+    let receiver_class = self.object_class(receiver)?;
+    let current = self.current_class()?;
+    let method = self.dispatch_cached(current, index, receiver_class, &resolved)?;
+    self.invoke_from_frame(method, args)
+  }
+
+  fn invoke_dynamic(&mut self, index: u16) -> Result<(), VmError> {
+    let current = self.current_class()?;
+    let site = self.resolve_call_site(current, index)?;
+    let args = self.pop_args(site.parameter_count())?;
+    let result = self.call_site(&site, args)?;
+    self.complete_invoke(Some(result))
+  }
+
+  fn incompatible_member(&self, message: &str, class_name: &str, name: &str) -> VmError {
+    VmError::java("java/lang/IncompatibleClassChangeError", format!("{} {}.{}", message, class_name.replace('/', "."), name))
+  }
+
+  // 解決したフィールドのスロットとアクセスの順序付けを返す (staticかどうかが命令と合わなければエラー)
+  fn field_slot(&mut self, index: u16, is_static: bool) -> Result<(ClassId, usize, String, MemoryOrder), VmError> {
+    let (owner, field_index) = self.resolve_field_ref(index)?;
+    let field = &self.classes[owner].fields[field_index];
+    if field.is_static() != is_static {
+      let message = if is_static { "Expected static field" } else { "Expected non-static field" };
+      return Err(self.incompatible_member(message, &self.classes[owner].name, &field.name));
+    }
+    let order = if field.is_volatile() { MemoryOrder::Volatile } else { MemoryOrder::Plain };
+    Ok((owner, field.slot, field.name.clone(), order))
+  }
+
+  fn get_field_value(&mut self, index: u16) -> Result<(), VmError> {
+    let (_, slot, name, order) = self.field_slot(index, false)?;
+    let object = Self::null_check(self.pop_ref()?, || format!("Cannot read field \"{}\"", name))?;
+    let value = self.load_ordered(Location::Field(object, slot), order)?;
+    self.push(value)
+  }
+
+  fn put_field_value(&mut self, index: u16) -> Result<(), VmError> {
+    let (_, slot, name, order) = self.field_slot(index, false)?;
+    let value = self.pop()?;
+    let object = Self::null_check(self.pop_ref()?, || format!("Cannot assign field \"{}\"", name))?;
+    self.store_ordered(Location::Field(object, slot), value, order)
+  }
+
+  fn get_static(&mut self, index: u16) -> Result<(), VmError> {
+    let (owner, slot, _, order) = self.field_slot(index, true)?;
+    self.initialize(owner)?;
+    let value = self.load_ordered(Location::Static(owner, slot), order)?;
+    self.push(value)
+  }
+
+  fn put_static(&mut self, index: u16) -> Result<(), VmError> {
+    let (owner, slot, _, order) = self.field_slot(index, true)?;
+    self.initialize(owner)?;
+    let value = self.pop()?;
+    self.store_ordered(Location::Static(owner, slot), value, order)
+  }
+
+  fn check_cast(&mut self, object: ObjRef, target: ClassId) -> Result<bool, VmError> {
+    let class = self.object_class(object)?;
+    Ok(self.is_assignable(class, target))
+  }
+
+  fn int_binary(&mut self, op: fn(i32, i32) -> i32) -> Result<(), VmError> {
+    let b = self.pop_int()?;
+    let a = self.pop_int()?;
+    self.push(Value::Int(op(a, b)))
+  }
+
+  fn long_binary(&mut self, op: fn(i64, i64) -> i64) -> Result<(), VmError> {
+    let b = self.pop_long()?;
+    let a = self.pop_long()?;
+    self.push(Value::Long(op(a, b)))
+  }
+
+  fn float_binary(&mut self, op: fn(f32, f32) -> f32) -> Result<(), VmError> {
+    let b = self.pop_float()?;
+    let a = self.pop_float()?;
+    self.push(Value::Float(op(a, b)))
+  }
+
+  fn double_binary(&mut self, op: fn(f64, f64) -> f64) -> Result<(), VmError> {
+    let b = self.pop_double()?;
+    let a = self.pop_double()?;
+    self.push(Value::Double(op(a, b)))
+  }
+
+  fn long_shift(&mut self, op: fn(i64, u32) -> i64) -> Result<(), VmError> {
+    let b = self.pop_int()?;
+    let a = self.pop_long()?;
+    self.push(Value::Long(op(a, (b & 0x3f) as u32)))
+  }
+
+  fn int_division(&mut self, op: fn(i32, i32) -> i32) -> Result<(), VmError> {
+    let b = self.pop_int()?;
+    let a = self.pop_int()?;
+    if b == 0 {
+      return Err(VmError::java("java/lang/ArithmeticException", "/ by zero"));
+    }
+    self.push(Value::Int(op(a, b)))
+  }
+
+  fn long_division(&mut self, op: fn(i64, i64) -> i64) -> Result<(), VmError> {
+    let b = self.pop_long()?;
+    let a = self.pop_long()?;
+    if b == 0 {
+      return Err(VmError::java("java/lang/ArithmeticException", "/ by zero"));
+    }
+    self.push(Value::Long(op(a, b)))
+  }
+
+  // 現在のフレームの命令を1つ実行する
+  pub(crate) fn step(&mut self) -> Result<(), VmError> {
+    let frame = self.frame()?;
+    let method = frame.method.clone();
+    let pc = frame.pc;
+    let code = match method.code() {
+      Some(code) => &code.bytes,
+      None => return Err(VmError::internal(format!("{}.{} has no code", method.class_name, method.name))),
+    };
+    let opcode = read_u8(code, pc)?;
+    let mut next = pc + 1;
+
+    match opcode {
+      0x00 => {},
+      0x01 => self.push(Value::Null)?,
+      0x02..=0x08 => self.push(Value::Int(opcode as i32 - 0x03))?,
+      0x09 | 0x0a => self.push(Value::Long(opcode as i64 - 0x09))?,
+      0x0b..=0x0d => self.push(Value::Float((opcode - 0x0b) as f32))?,
+      0x0e | 0x0f => self.push(Value::Double((opcode - 0x0e) as f64))?,
+      0x10 => {
+        self.push(Value::Int(read_u8(code, pc + 1)? as i8 as i32))?;
+        next = pc + 2;
+      },
+      0x11 => {
+        self.push(Value::Int(read_i16(code, pc + 1)? as i32))?;
+        next = pc + 3;
+      },
+      0x12 => {
+        let value = self.constant_value(method.class, read_u8(code, pc + 1)? as u16)?;
+        self.push(value)?;
+        next = pc + 2;
+      },
+      0x13 | 0x14 => {
+        let value = self.constant_value(method.class, read_u16(code, pc + 1)?)?;
+        self.push(value)?;
+        next = pc + 3;
+      },
+      // iload, lload, fload, dload, aload
+      0x15..=0x19 => {
+        let value = self.frame()?.local(read_u8(code, pc + 1)? as usize)?;
+        self.push(value)?;
+        next = pc + 2;
+      },
+      // xload_<n>
+      0x1a..=0x2d => {
+        let value = self.frame()?.local(((opcode - 0x1a) % 4) as usize)?;
+        self.push(value)?;
+      },
+      0x2e..=0x35 => self.array_load()?,
+      // istore, lstore, fstore, dstore, astore
+      0x36..=0x3a => {
+        let value = self.pop()?;
+        self.frame_mut()?.set_local(read_u8(code, pc + 1)? as usize, value)?;
+        next = pc + 2;
+      },
+      // xstore_<n>
+      0x3b..=0x4e => {
+        let value = self.pop()?;
+        self.frame_mut()?.set_local(((opcode - 0x3b) % 4) as usize, value)?;
+      },
+      0x4f..=0x56 => self.array_store()?,
+      0x57 => {
+        self.pop()?;
+      },
+      0x58 => {
+        if !self.pop()?.is_wide() {
+          self.pop()?;
+        }
+      },
+      0x59 => {
+        let value = self.pop()?;
+        self.push(value)?;
+        self.push(value)?;
+      },
+      0x5a => {
+        let v1 = self.pop()?;
+        let v2 = self.pop()?;
+        for v in [v1, v2, v1] {
+          self.push(v)?;
+        }
+      },
+      0x5b => {
+        let v1 = self.pop()?;
+        let v2 = self.pop()?;
+        if v2.is_wide() {
+          for v in [v1, v2, v1] {
+            self.push(v)?;
+          }
+        } else {
+          let v3 = self.pop()?;
+          for v in [v1, v3, v2, v1] {
+            self.push(v)?;
+          }
+        }
+      },
+      0x5c => {
+        let v1 = self.pop()?;
+        if v1.is_wide() {
+          self.push(v1)?;
+          self.push(v1)?;
+        } else {
+          let v2 = self.pop()?;
+          for v in [v2, v1, v2, v1] {
+            self.push(v)?;
+          }
+        }
+      },
+      0x5d => {
+        let v1 = self.pop()?;
+        if v1.is_wide() {
+          let v2 = self.pop()?;
+          for v in [v1, v2, v1] {
+            self.push(v)?;
+          }
+        } else {
+          let v2 = self.pop()?;
+          let v3 = self.pop()?;
+          for v in [v2, v1, v3, v2, v1] {
+            self.push(v)?;
+          }
+        }
+      },
+      0x5e => {
+        let v1 = self.pop()?;
+        if v1.is_wide() {
+          let v2 = self.pop()?;
+          if v2.is_wide() {
+            for v in [v1, v2, v1] {
+              self.push(v)?;
+            }
+          } else {
+            let v3 = self.pop()?;
+            for v in [v1, v3, v2, v1] {
+              self.push(v)?;
+            }
+          }
+        } else {
+          let v2 = self.pop()?;
+          let v3 = self.pop()?;
+          if v3.is_wide() {
+            for v in [v2, v1, v3, v2, v1] {
+              self.push(v)?;
+            }
+          } else {
+            let v4 = self.pop()?;
+            for v in [v2, v1, v4, v3, v2, v1] {
+              self.push(v)?;
+            }
+          }
+        }
+      },
+      0x5f => {
+        let v1 = self.pop()?;
+        let v2 = self.pop()?;
+        self.push(v1)?;
+        self.push(v2)?;
+      },
+      0x60 => self.int_binary(i32::wrapping_add)?,
+      0x61 => self.long_binary(i64::wrapping_add)?,
+      0x62 => self.float_binary(|a, b| a + b)?,
+      0x63 => self.double_binary(|a, b| a + b)?,
+      0x64 => self.int_binary(i32::wrapping_sub)?,
+      0x65 => self.long_binary(i64::wrapping_sub)?,
+      0x66 => self.float_binary(|a, b| a - b)?,
+      0x67 => self.double_binary(|a, b| a - b)?,
+      0x68 => self.int_binary(i32::wrapping_mul)?,
+      0x69 => self.long_binary(i64::wrapping_mul)?,
+      0x6a => self.float_binary(|a, b| a * b)?,
+      0x6b => self.double_binary(|a, b| a * b)?,
+      0x6c => self.int_division(i32::wrapping_div)?,
+      0x6d => self.long_division(i64::wrapping_div)?,
+      0x6e => self.float_binary(|a, b| a / b)?,
+      0x6f => self.double_binary(|a, b| a / b)?,
+      0x70 => self.int_division(i32::wrapping_rem)?,
+      0x71 => self.long_division(i64::wrapping_rem)?,
+      0x72 => self.float_binary(|a, b| a % b)?,
+      0x73 => self.double_binary(|a, b| a % b)?,
+      0x74 => {
+        let v = self.pop_int()?;
+        self.push(Value::Int(v.wrapping_neg()))?;
+      },
+      0x75 => {
+        let v = self.pop_long()?;
+        self.push(Value::Long(v.wrapping_neg()))?;
+      },
+      0x76 => {
+        let v = self.pop_float()?;
+        self.push(Value::Float(-v))?;
+      },
+      0x77 => {
+        let v = self.pop_double()?;
+        self.push(Value::Double(-v))?;
+      },
+      0x78 => self.int_binary(|a, b| a.wrapping_shl((b & 0x1f) as u32))?,
+      0x79 => self.long_shift(|a, b| a.wrapping_shl(b))?,
+      0x7a => self.int_binary(|a, b| a.wrapping_shr((b & 0x1f) as u32))?,
+      0x7b => self.long_shift(|a, b| a.wrapping_shr(b))?,
+      0x7c => self.int_binary(|a, b| ((a as u32) >> (b & 0x1f)) as i32)?,
+      0x7d => self.long_shift(|a, b| ((a as u64) >> b) as i64)?,
+      0x7e => self.int_binary(|a, b| a & b)?,
+      0x7f => self.long_binary(|a, b| a & b)?,
+      0x80 => self.int_binary(|a, b| a | b)?,
+      0x81 => self.long_binary(|a, b| a | b)?,
+      0x82 => self.int_binary(|a, b| a ^ b)?,
+      0x83 => self.long_binary(|a, b| a ^ b)?,
+      0x84 => {
+        let index = read_u8(code, pc + 1)? as usize;
+        let delta = read_u8(code, pc + 2)? as i8 as i32;
+        let frame = self.frame_mut()?;
+        let value = frame.local(index)?.as_int()?;
+        frame.set_local(index, Value::Int(value.wrapping_add(delta)))?;
+        next = pc + 3;
+      },
+      0x85 => {
+        let v = self.pop_int()?;
+        self.push(Value::Long(v as i64))?;
+      },
+      0x86 => {
+        let v = self.pop_int()?;
+        self.push(Value::Float(v as f32))?;
+      },
+      0x87 => {
+        let v = self.pop_int()?;
+        self.push(Value::Double(v as f64))?;
+      },
+      0x88 => {
+        let v = self.pop_long()?;
+        self.push(Value::Int(v as i32))?;
+      },
+      0x89 => {
+        let v = self.pop_long()?;
+        self.push(Value::Float(v as f32))?;
+      },
+      0x8a => {
+        let v = self.pop_long()?;
+        self.push(Value::Double(v as f64))?;
+      },
+      0x8b => {
+        let v = self.pop_float()?;
+        self.push(Value::Int(v as i32))?;
+      },
+      0x8c => {
+        let v = self.pop_float()?;
+        self.push(Value::Long(v as i64))?;
+      },
+      0x8d => {
+        let v = self.pop_float()?;
+        self.push(Value::Double(v as f64))?;
+      },
+      0x8e => {
+        let v = self.pop_double()?;
+        self.push(Value::Int(v as i32))?;
+      },
+      0x8f => {
+        let v = self.pop_double()?;
+        self.push(Value::Long(v as i64))?;
+      },
+      0x90 => {
+        let v = self.pop_double()?;
+        self.push(Value::Float(v as f32))?;
+      },
+      0x91 => {
+        let v = self.pop_int()?;
+        self.push(Value::Int(v as i8 as i32))?;
+      },
+      0x92 => {
+        let v = self.pop_int()?;
+        self.push(Value::Int(v as u16 as i32))?;
+      },
+      0x93 => {
+        let v = self.pop_int()?;
+        self.push(Value::Int(v as i16 as i32))?;
+      },
+      0x94 => {
+        let b = self.pop_long()?;
+        let a = self.pop_long()?;
+        self.push(Value::Int(a.cmp(&b) as i32))?;
+      },
+      0x95 | 0x96 => {
+        let b = self.pop_float()?;
+        let a = self.pop_float()?;
+        let nan = if opcode == 0x95 { -1 } else { 1 };
+        self.push(Value::Int(compare_float(a as f64, b as f64, nan)))?;
+      },
+      0x97 | 0x98 => {
+        let b = self.pop_double()?;
+        let a = self.pop_double()?;
+        let nan = if opcode == 0x97 { -1 } else { 1 };
+        self.push(Value::Int(compare_float(a, b, nan)))?;
+      },
+      // ifeq, ifne, iflt, ifge, ifgt, ifle
+      0x99..=0x9e => {
+        let v = self.pop_int()?;
+        let taken = match opcode {
+          0x99 => v == 0,
+          0x9a => v != 0,
+          0x9b => v < 0,
+          0x9c => v >= 0,
+          0x9d => v > 0,
+          _ => v <= 0,
+        };
+        next = if taken { branch(pc, read_i16(code, pc + 1)? as i32) } else { pc + 3 };
+      },
+      // if_icmpeq, if_icmpne, if_icmplt, if_icmpge, if_icmpgt, if_icmple
+      0x9f..=0xa4 => {
+        let b = self.pop_int()?;
+        let a = self.pop_int()?;
+        let taken = match opcode {
+          0x9f => a == b,
+          0xa0 => a != b,
+          0xa1 => a < b,
+          0xa2 => a >= b,
+          0xa3 => a > b,
+          _ => a <= b,
+        };
+        next = if taken { branch(pc, read_i16(code, pc + 1)? as i32) } else { pc + 3 };
+      },
+      // if_acmpeq, if_acmpne
+      0xa5 | 0xa6 => {
+        let b = self.pop_ref()?;
+        let a = self.pop_ref()?;
+        let taken = (a == b) == (opcode == 0xa5);
+        next = if taken { branch(pc, read_i16(code, pc + 1)? as i32) } else { pc + 3 };
+      },
+      0xa7 => next = branch(pc, read_i16(code, pc + 1)? as i32),
+      0xa8 => {
+        self.push(Value::ReturnAddress(pc + 3))?;
+        next = branch(pc, read_i16(code, pc + 1)? as i32);
+      },
+      0xa9 => {
+        next = match self.frame()?.local(read_u8(code, pc + 1)? as usize)? {
+          Value::ReturnAddress(address) => address,
+          v => return Err(VmError::internal(format!("ret expects a return address, found {:?}", v))),
+        };
+      },
+      0xaa => {
+        let base = pc + 1 + (4 - (pc + 1) % 4) % 4;
+        let default = read_i32(code, base)?;
+        let low = read_i32(code, base + 4)?;
+        let high = read_i32(code, base + 8)?;
+        let index = self.pop_int()?;
+        next = if index < low || index > high {
+          branch(pc, default)
+        } else {
+          branch(pc, read_i32(code, base + 12 + (index - low) as usize * 4)?)
+        };
+      },
+      0xab => {
+        let base = pc + 1 + (4 - (pc + 1) % 4) % 4;
+        let default = read_i32(code, base)?;
+        let npairs = read_i32(code, base + 4)? as usize;
+        let key = self.pop_int()?;
+        let mut offset = default;
+        for i in 0..npairs {
+          let entry = base + 8 + i * 8;
+          if read_i32(code, entry)? == key {
+            offset = read_i32(code, entry + 4)?;
+            break;
+          }
+        }
+        next = branch(pc, offset);
+      },
+      // ireturn, lreturn, freturn, dreturn, areturn
+      0xac..=0xb0 => {
+        let value = self.pop()?;
+        return self.return_from_frame(Some(value));
+      },
+      0xb1 => return self.return_from_frame(None),
+      0xb2 => {
+        self.get_static(read_u16(code, pc + 1)?)?;
+        next = pc + 3;
+      },
+      0xb3 => {
+        self.put_static(read_u16(code, pc + 1)?)?;
+        next = pc + 3;
+      },
+      0xb4 => {
+        self.get_field_value(read_u16(code, pc + 1)?)?;
+        next = pc + 3;
+      },
+      0xb5 => {
+        self.put_field_value(read_u16(code, pc + 1)?)?;
+        next = pc + 3;
+      },
+      0xb6 | 0xb9 => return self.invoke_virtual(read_u16(code, pc + 1)?),
+      0xb7 => return self.invoke_special(read_u16(code, pc + 1)?),
+      0xb8 => return self.invoke_static(read_u16(code, pc + 1)?),
+      0xba => return self.invoke_dynamic(read_u16(code, pc + 1)?),
+      0xbb => {
+        let class = self.resolve_class_ref(read_u16(code, pc + 1)?)?;
+        if self.classes[class].is_interface() || self.classes[class].is_abstract() {
+          return Err(VmError::java("java/lang/InstantiationError", self.classes[class].java_name()));
+        }
+        self.initialize(class)?;
+        let object = self.instantiate(class)?;
+        self.push(Value::Ref(object))?;
+        next = pc + 3;
+      },
+      0xbc => {
+        let component = array_type(read_u8(code, pc + 1)?)?;
+        let length = self.pop_int()?;
+        let array = self.new_array(&component, length)?;
+        self.push(Value::Ref(array))?;
+        next = pc + 2;
+      },
+      0xbd => {
+        let class = self.resolve_class_ref(read_u16(code, pc + 1)?)?;
+        let component = match &self.classes[class].component {
+          Some(_) => FieldType::parse(&self.classes[class].name)?,
+          None => FieldType::Object(self.classes[class].name.clone()),
+        };
+        let length = self.pop_int()?;
+        let array = self.new_array(&component, length)?;
+        self.push(Value::Ref(array))?;
+        next = pc + 3;
+      },
+      0xbe => {
+        let array = Self::null_check(self.pop_ref()?, || "Cannot read the array length".to_string())?;
+        let length = self.heap.get(array)?.array()?.len();
+        self.push(Value::Int(length as i32))?;
+      },
+      // athrow
+      0xbf => {
+        let exception = Self::null_check(self.pop_ref()?, || "Cannot throw exception".to_string())?;
+        return Err(VmError::Thrown(exception));
+      },
+      // checkcast
+      0xc0 => {
+        let target = self.resolve_class_ref(read_u16(code, pc + 1)?)?;
+        let top = self.pop_ref()?;
+        self.push(Value::from_ref(top))?;
+        if let Some(object) = top
+          && !self.check_cast(object, target)? {
+          let class = self.object_class(object)?;
+          return Err(VmError::java("java/lang/ClassCastException", format!(
+            "class {} cannot be cast to class {}",
+            self.classes[class].java_name(),
+            self.classes[target].java_name(),
+          )));
+        }
+        next = pc + 3;
+      },
+      // instanceof
+      0xc1 => {
+        let target = self.resolve_class_ref(read_u16(code, pc + 1)?)?;
+        let result = match self.pop_ref()? {
+          Some(object) => self.check_cast(object, target)?,
+          None => false,
+        };
+        self.push(Value::Int(result as i32))?;
+        next = pc + 3;
+      },
+      // monitorenter (ロックを待つ間もGCのルートに残すため、取れてからスタックから降ろす)
+      0xc2 => {
+        let object = Self::null_check(self.pop_ref()?, || "Cannot enter synchronized block".to_string())?;
+        self.push(Value::Ref(object))?;
+        self.monitor_enter(self.object_lock(object));
+        self.pop()?;
+      },
+      // monitorexit
+      0xc3 => {
+        let object = Self::null_check(self.pop_ref()?, || "Cannot exit synchronized block".to_string())?;
+        self.monitor_exit(self.object_lock(object))?;
+      },
+      0xc4 => {
+        let modified = read_u8(code, pc + 1)?;
+        let index = read_u16(code, pc + 2)? as usize;
+        match modified {
+          0x15..=0x19 => {
+            let value = self.frame()?.local(index)?;
+            self.push(value)?;
+            next = pc + 4;
+          },
+          0x36..=0x3a => {
+            let value = self.pop()?;
+            self.frame_mut()?.set_local(index, value)?;
+            next = pc + 4;
+          },
+          0x84 => {
+            let delta = read_i16(code, pc + 4)? as i32;
+            let frame = self.frame_mut()?;
+            let value = frame.local(index)?.as_int()?;
+            frame.set_local(index, Value::Int(value.wrapping_add(delta)))?;
+            next = pc + 6;
+          },
+          0xa9 => {
+            next = match self.frame()?.local(index)? {
+              Value::ReturnAddress(address) => address,
+              v => return Err(VmError::internal(format!("ret expects a return address, found {:?}", v))),
+            };
+          },
+          _ => return Err(VmError::internal(format!("Invalid wide opcode: 0x{:02x}", modified))),
+        }
+      },
+      0xc5 => {
+        let class = self.resolve_class_ref(read_u16(code, pc + 1)?)?;
+        let dimensions = read_u8(code, pc + 3)? as usize;
+        let counts = self.pop_args(dimensions)?.into_iter()
+          .map(Value::as_int)
+          .collect::<Result<Vec<_>, _>>()?;
+        if let Some(negative) = counts.iter().find(|c| **c < 0) {
+          return Err(VmError::java("java/lang/NegativeArraySizeException", negative.to_string()));
+        }
+        let array_type = FieldType::parse(&self.classes[class].name)?;
+        let array = self.new_multi_array(&array_type, &counts)?;
+        self.push(Value::Ref(array))?;
+        next = pc + 4;
+      },
+      0xc6 | 0xc7 => {
+        let is_null = self.pop_ref()?.is_none();
+        let taken = is_null == (opcode == 0xc6);
+        next = if taken { branch(pc, read_i16(code, pc + 1)? as i32) } else { pc + 3 };
+      },
+      0xc8 => next = branch(pc, read_i32(code, pc + 1)?),
+      0xc9 => {
+        self.push(Value::ReturnAddress(pc + 5))?;
+        next = branch(pc, read_i32(code, pc + 1)?);
+      },
+      _ => {
+        let name = CODE_BYTES.get(&opcode).map(|c| c.name).unwrap_or("unknown");
+        return Err(VmError::internal(format!("Unsupported opcode: {} (0x{:02x})", name, opcode)));
+      },
+    }
+
+    self.frame_mut()?.pc = next;
+    Ok(())
+  }
+}
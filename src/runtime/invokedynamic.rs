@@ -0,0 +1,402 @@
+use std::rc::Rc;
+
+use crate::{
+  runtime::{
+    class::{ClassDefinition, ClassId, ACC_FINAL, ACC_PRIVATE, ACC_PUBLIC, ACC_SUPER, ACC_SYNTHETIC},
+    error::VmError,
+    library::boxing::primitive_hash,
+    method_handle::{simple_name, HandleMember, HandleTarget},
+    value::{ObjRef, Value},
+    vm::Vm,
+  },
+  structure::class::{ClassFile, ClassFileAttribute, Constant, ConstantPool},
+  util::{descriptor::{FieldType, MethodDescriptor}, mutf8, number},
+};
+
+// LambdaMetafactory.altMetafactoryのフラグ
+const FLAG_SERIALIZABLE: i32 = 1;
+const FLAG_MARKERS: i32 = 2;
+const FLAG_BRIDGES: i32 = 4;
+
+// ブートストラップメソッドをVM内で実行した結果のリンク済み呼び出しサイト
+#[derive(Debug)]
+pub enum CallSite {
+  // StringConcatFactory: 引数を文字列にして定数部分と連結する
+  Concat { parameters: Vec<FieldType>, parts: Vec<ConcatPart> },
+  // LambdaMetafactory: 捕捉する値をフィールドに持つ、関数型インターフェースの実装クラス
+  Lambda { class: ClassId, captured: usize },
+  // ObjectMethods: レコードのtoString/equals/hashCodeをコンポーネントのフィールドから計算する
+  ObjectMethods { method: String, record: ClassId, names: Vec<String>, getters: Vec<HandleTarget> },
+}
+
+#[derive(Debug)]
+pub enum ConcatPart {
+  Text(Vec<u16>),
+  Argument(usize),
+}
+
+impl CallSite {
+  pub fn parameter_count(&self) -> usize {
+    match self {
+      CallSite::Concat { parameters, .. } => parameters.len(),
+      CallSite::Lambda { captured, .. } => *captured,
+      CallSite::ObjectMethods { method, .. } => if method == "equals" { 2 } else { 1 },
+    }
+  }
+}
+
+// ラムダのクラスのメソッドが呼び出す実装メソッド
+#[derive(Debug)]
+pub struct LambdaTarget {
+  pub target: HandleTarget,
+  // 捕捉した値のフィールドのスロット
+  pub fields: Vec<usize>,
+  // 引数の型 (捕捉した値、呼び出し時の引数の順)
+  pub parameters: Vec<FieldType>,
+  pub return_type: Option<FieldType>,
+}
+
+// BootstrapMethods属性から、ブートストラップメソッドのクラスと名前、静的引数を取り出す
+pub(crate) fn bootstrap_method(class_file: &ClassFile, index: u16) -> Result<(String, String, Vec<u16>), VmError> {
+  let constant_pool = &class_file.constant_pool;
+  let bootstrap = class_file.attributes.attributes.iter()
+    .find_map(|attr| match attr {
+      ClassFileAttribute::BootstrapMethods(methods) => methods.bootstrap_methods.get(index as usize),
+      _ => None,
+    })
+    .ok_or_else(|| VmError::java("java/lang/ClassFormatError", format!("Missing bootstrap method {}", index)))?;
+  match constant_pool.get_class(bootstrap.bootstrap_method_attr_index)? {
+    Constant::MethodHandle { reference_index, .. } => {
+      let (class_name, name, _) = constant_pool.get_member_ref(*reference_index)?;
+      Ok((class_name, name, bootstrap.bootstrap_arguments.clone()))
+    },
+    c => Err(VmError::internal(format!("Expected MethodHandle constant, found: {:?}", c))),
+  }
+}
+
+fn method_type(constant_pool: &ConstantPool, index: u16) -> Result<String, VmError> {
+  match constant_pool.get_class(index)? {
+    Constant::MethodType { descriptor_index } => Ok(constant_pool.get_utf8(*descriptor_index)?),
+    c => Err(VmError::internal(format!("Expected MethodType constant, found: {:?}", c))),
+  }
+}
+
+fn int_constant(constant_pool: &ConstantPool, index: u16) -> Result<i32, VmError> {
+  match constant_pool.get_class(index)? {
+    Constant::Integer { bytes } => Ok(*bytes as i32),
+    c => Err(VmError::internal(format!("Expected Integer constant, found: {:?}", c))),
+  }
+}
+
+// 連結のレシピに埋め込む定数の文字列表現
+fn constant_text(constant_pool: &ConstantPool, index: u16) -> Result<Vec<u16>, VmError> {
+  let text = match constant_pool.get_class(index)? {
+    Constant::String { string_index } => match constant_pool.get_class(*string_index)? {
+      Constant::Utf8 { bytes, .. } => return Ok(mutf8::decode(bytes)),
+      c => return Err(VmError::internal(format!("Expected Utf8 constant, found: {:?}", c))),
+    },
+    Constant::Integer { bytes } => (*bytes as i32).to_string(),
+    Constant::Float { bytes } => number::float_to_string(f32::from_bits(*bytes)),
+    Constant::Long { high_bytes, low_bytes } => ((((*high_bytes as u64) << 32) | *low_bytes as u64) as i64).to_string(),
+    Constant::Double { high_bytes, low_bytes } => {
+      number::double_to_string(f64::from_bits(((*high_bytes as u64) << 32) | *low_bytes as u64))
+    },
+    c => return Err(VmError::java("java/lang/BootstrapMethodError", format!("Unsupported concat constant: {:?}", c))),
+  };
+  Ok(text.encode_utf16().collect())
+}
+
+fn descriptor(descriptor: &str) -> Result<MethodDescriptor, VmError> {
+  MethodDescriptor::parse(descriptor).map_err(|e| VmError::java("java/lang/ClassFormatError", e))
+}
+
+impl Vm {
+  // invokedynamicの呼び出しサイト指定子をリンクする (JVMS 5.4.3.6)
+  // ブートストラップメソッドは実行せず、既知のものだけを同じ意味の処理に置き換える
+  pub(crate) fn link_call_site(&mut self, class: ClassId, index: u16) -> Result<CallSite, VmError> {
+    let class_file = self.class_file(class)?;
+    let constant_pool = &class_file.constant_pool;
+    let Constant::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index } = constant_pool.get_class(index)? else {
+      return Err(VmError::internal(format!("Expected InvokeDynamic constant at {}", index)));
+    };
+    let (name, invoked_type) = constant_pool.get_name_and_type(*name_and_type_index)?;
+    let (bootstrap_class, bootstrap_name, arguments) = bootstrap_method(&class_file, *bootstrap_method_attr_index)?;
+    let invoked = descriptor(&invoked_type)?;
+    match (bootstrap_class.as_str(), bootstrap_name.as_str()) {
+      ("java/lang/invoke/StringConcatFactory", "makeConcatWithConstants") => {
+        let recipe = constant_text(constant_pool, *arguments.first()
+          .ok_or_else(|| VmError::java("java/lang/BootstrapMethodError", "Missing concat recipe"))?)?;
+        let mut constants = arguments[1..].iter();
+        let mut parts = Vec::new();
+        let mut text = Vec::new();
+        let mut argument = 0;
+        // \1 は引数、\2 は定数に置き換える
+        for c in recipe {
+          match c {
+            1 => {
+              if !text.is_empty() {
+                parts.push(ConcatPart::Text(std::mem::take(&mut text)));
+              }
+              parts.push(ConcatPart::Argument(argument));
+              argument += 1;
+            },
+            2 => {
+              let constant = constants.next()
+                .ok_or_else(|| VmError::java("java/lang/BootstrapMethodError", "Missing concat constant"))?;
+              text.extend(constant_text(constant_pool, *constant)?);
+            },
+            c => text.push(c),
+          }
+        }
+        if !text.is_empty() {
+          parts.push(ConcatPart::Text(text));
+        }
+        if argument != invoked.parameters.len() {
+          return Err(VmError::java("java/lang/BootstrapMethodError", format!(
+            "Mismatched number of concat arguments: recipe wants {}, but signature provides {}", argument, invoked.parameters.len(),
+          )));
+        }
+        Ok(CallSite::Concat { parameters: invoked.parameters, parts })
+      },
+      ("java/lang/invoke/StringConcatFactory", "makeConcat") => {
+        let parts = (0..invoked.parameters.len()).map(ConcatPart::Argument).collect();
+        Ok(CallSite::Concat { parameters: invoked.parameters, parts })
+      },
+      ("java/lang/invoke/LambdaMetafactory", "metafactory" | "altMetafactory") => {
+        if arguments.len() < 3 {
+          return Err(VmError::java("java/lang/BootstrapMethodError", "Missing LambdaMetafactory arguments"));
+        }
+        let sam_type = method_type(constant_pool, arguments[0])?;
+        let instantiated = descriptor(&method_type(constant_pool, arguments[2])?)?;
+        let interface = match &invoked.return_type {
+          Some(FieldType::Object(name)) => name.clone(),
+          _ => return Err(VmError::java("java/lang/BootstrapMethodError", format!("Invalid lambda call site type: {}", invoked_type))),
+        };
+        let mut interfaces = vec![interface];
+        let mut method_types = vec![sam_type.clone()];
+        if bootstrap_name == "altMetafactory" {
+          let mut rest = arguments[3..].iter().copied();
+          let flags = rest.next().map_or(Ok(0), |i| int_constant(constant_pool, i))?;
+          if flags & FLAG_MARKERS != 0 {
+            let count = rest.next().map_or(Ok(0), |i| int_constant(constant_pool, i))?;
+            for index in rest.by_ref().take(count as usize) {
+              interfaces.push(constant_pool.get_class_name(index)?);
+            }
+          }
+          if flags & FLAG_BRIDGES != 0 {
+            let count = rest.next().map_or(Ok(0), |i| int_constant(constant_pool, i))?;
+            for index in rest.by_ref().take(count as usize) {
+              method_types.push(method_type(constant_pool, index)?);
+            }
+          }
+          if flags & FLAG_SERIALIZABLE != 0 {
+            interfaces.push("java/io/Serializable".to_string());
+          }
+        }
+        let target = self.resolve_method_handle(class, arguments[1])?;
+        if !matches!(target.member, HandleMember::Method(_)) {
+          return Err(VmError::java("java/lang/BootstrapMethodError", "Lambda implementation must be a method"));
+        }
+
+        // HotSpotと同じく 呼び出し元$$Lambda$n という名前のクラスを作る
+        let lambda_name = format!("{}$$Lambda${}", self.classes[class].name, self.lambdas.len() + 1);
+        let mut definition = ClassDefinition::new(&lambda_name)
+          .access_flags(ACC_FINAL | ACC_SUPER | ACC_SYNTHETIC);
+        definition.nest_host = Some(self.classes[class].name.clone());
+        for interface in &interfaces {
+          definition = definition.interface(interface);
+        }
+        for (i, parameter) in invoked.parameters.iter().enumerate() {
+          definition = definition.field(&format!("arg${}", i + 1), &parameter.descriptor(), ACC_PRIVATE | ACC_FINAL);
+        }
+        method_types.dedup();
+        for method_type in &method_types {
+          definition = definition.native(&name, method_type, ACC_PUBLIC, lambda_invoke);
+        }
+        let lambda = self.define_class(definition)?;
+        self.initialize(lambda)?;
+
+        let fields = self.classes[lambda].fields.iter().map(|f| f.slot).collect();
+        let parameters = invoked.parameters.iter().chain(instantiated.parameters.iter()).cloned().collect();
+        let return_type = descriptor(&sam_type)?.return_type;
+        self.lambdas.insert(lambda, Rc::new(LambdaTarget { target, fields, parameters, return_type }));
+        Ok(CallSite::Lambda { class: lambda, captured: invoked.parameters.len() })
+      },
+      ("java/lang/runtime/ObjectMethods", "bootstrap") => {
+        if !matches!(name.as_str(), "toString" | "equals" | "hashCode") || arguments.len() < 2 {
+          return Err(VmError::java("java/lang/BootstrapMethodError", format!("Unsupported record method {}", name)));
+        }
+        let record = self.resolve_class(class, arguments[0])?;
+        let names = String::from_utf16_lossy(&constant_text(constant_pool, arguments[1])?);
+        let names = names.split(';').filter(|n| !n.is_empty()).map(|n| n.to_string()).collect();
+        let getters = arguments[2..].iter()
+          .map(|&index| self.resolve_method_handle(class, index))
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(CallSite::ObjectMethods { method: name, record, names, getters })
+      },
+      _ => Err(VmError::java("java/lang/BootstrapMethodError", format!(
+        "Unsupported bootstrap method {}.{}", bootstrap_class.replace('/', "."), bootstrap_name,
+      ))),
+    }
+  }
+
+  // リンク済みの呼び出しサイトを、スタックから取り出した引数で実行する
+  pub(crate) fn call_site(&mut self, site: &CallSite, args: Vec<Value>) -> Result<Value, VmError> {
+    let mark = self.handles.mark();
+    for arg in &args {
+      if let Value::Ref(reference) = arg {
+        self.handles.new_local(*reference);
+      }
+    }
+    let result = match site {
+      CallSite::Concat { parameters, parts } => self.concat(parameters, parts, &args),
+      CallSite::Lambda { class, .. } => self.new_lambda(*class, &args),
+      CallSite::ObjectMethods { method, record, names, getters } => self.record_method(method, *record, names, getters, &args),
+    };
+    self.handles.release(mark);
+    result
+  }
+
+  fn concat(&mut self, parameters: &[FieldType], parts: &[ConcatPart], args: &[Value]) -> Result<Value, VmError> {
+    let mut chars = Vec::new();
+    for part in parts {
+      match part {
+        ConcatPart::Text(text) => chars.extend_from_slice(text),
+        ConcatPart::Argument(i) => chars.extend(self.stringify(args[*i], &parameters[*i])?),
+      }
+    }
+    Ok(Value::Ref(self.new_string_utf16(&chars)?))
+  }
+
+  fn new_lambda(&mut self, class: ClassId, captured: &[Value]) -> Result<Value, VmError> {
+    let lambda = self.instantiate(class)?;
+    let slots: Vec<usize> = self.classes[class].fields.iter().map(|f| f.slot).collect();
+    let fields = self.heap.get_mut(lambda)?.fields_mut()?;
+    for (slot, value) in slots.into_iter().zip(captured) {
+      fields[slot] = *value;
+    }
+    Ok(Value::Ref(lambda))
+  }
+
+  // String.valueOf()と同じ変換 (オブジェクトはtoString()を呼ぶ)
+  pub fn stringify(&mut self, value: Value, field_type: &FieldType) -> Result<Vec<u16>, VmError> {
+    let text = match (field_type, value) {
+      (FieldType::Boolean, Value::Int(v)) => (v != 0).to_string(),
+      (FieldType::Char, Value::Int(v)) => return Ok(vec![v as u16]),
+      (_, Value::Int(v)) => v.to_string(),
+      (_, Value::Long(v)) => v.to_string(),
+      (_, Value::Float(v)) => number::float_to_string(v),
+      (_, Value::Double(v)) => number::double_to_string(v),
+      (_, Value::Null) => "null".to_string(),
+      (_, Value::Ref(object)) => return self.object_to_string(object),
+      (_, v) => return Err(VmError::internal(format!("Cannot convert {:?} to string", v))),
+    };
+    Ok(text.encode_utf16().collect())
+  }
+
+  fn object_to_string(&mut self, object: ObjRef) -> Result<Vec<u16>, VmError> {
+    let class = self.object_class(object)?;
+    if self.classes[class].name == "java/lang/String" {
+      return self.string_chars(object);
+    }
+    let Some(method) = self.find_virtual(class, "toString", "()Ljava/lang/String;") else {
+      // Object.toString()と同じ形式
+      return Ok(format!("{}@{:x}", self.classes[class].java_name(), self.identity_hash(object)).encode_utf16().collect());
+    };
+    match self.invoke(method, vec![Value::Ref(object)])? {
+      Some(Value::Ref(string)) => self.string_chars(string),
+      _ => Ok("null".encode_utf16().collect()),
+    }
+  }
+
+  // レコードのtoString() (Name[x=1, y=2])、equals()、hashCode()
+  fn record_method(&mut self, method: &str, record: ClassId, names: &[String], getters: &[HandleTarget], args: &[Value]) -> Result<Value, VmError> {
+    let receiver = args[0];
+    match method {
+      "toString" => {
+        let mut chars: Vec<u16> = format!("{}[", simple_name(&self.classes[record].name)).encode_utf16().collect();
+        for (i, (name, getter)) in names.iter().zip(getters).enumerate() {
+          if i > 0 {
+            chars.extend(", ".encode_utf16());
+          }
+          chars.extend(format!("{}=", name).encode_utf16());
+          let value = self.component_value(getter, receiver)?;
+          chars.extend(self.stringify(value, &getter.handle_type.return_type.clone().unwrap_or(FieldType::Int))?);
+        }
+        chars.push(']' as u16);
+        Ok(Value::Ref(self.new_string_utf16(&chars)?))
+      },
+      "hashCode" => {
+        let mut result = 0i32;
+        for getter in getters {
+          let value = self.component_value(getter, receiver)?;
+          let hash = self.hash_value(value, getter.handle_type.return_type.as_ref())?;
+          result = result.wrapping_mul(31).wrapping_add(hash);
+        }
+        Ok(Value::Int(result))
+      },
+      _ => {
+        let other = match args[1] {
+          Value::Ref(other) if self.is_assignable(self.object_class(other)?, record) => Value::Ref(other),
+          _ => return Ok(Value::Int(0)),
+        };
+        for getter in getters {
+          let a = self.component_value(getter, receiver)?;
+          let b = self.component_value(getter, other)?;
+          if !self.values_equal(a, b)? {
+            return Ok(Value::Int(0));
+          }
+        }
+        Ok(Value::Int(1))
+      },
+    }
+  }
+
+  fn component_value(&mut self, getter: &HandleTarget, record: Value) -> Result<Value, VmError> {
+    self.invoke_member(getter, vec![record])?
+      .ok_or_else(|| VmError::internal("Record component getter returned no value"))
+  }
+
+  // 各ラッパークラスのhashCode()と同じ値 (参照はhashCode()を呼ぶ)
+  fn hash_value(&mut self, value: Value, field_type: Option<&FieldType>) -> Result<i32, VmError> {
+    Ok(match (value, field_type) {
+      (Value::Ref(object), _) => {
+        let class = self.object_class(object)?;
+        match self.find_virtual(class, "hashCode", "()I") {
+          Some(method) => self.invoke(method, vec![value])?.map_or(Ok(0), |v| v.as_int())?,
+          None => self.identity_hash(object),
+        }
+      },
+      (_, Some(field_type)) => primitive_hash(value, field_type),
+      _ => 0,
+    })
+  }
+
+  // 基本型は値 (浮動小数点数はビット列) で、参照はequals()で比較する
+  fn values_equal(&mut self, a: Value, b: Value) -> Result<bool, VmError> {
+    Ok(match (a, b) {
+      (Value::Float(x), Value::Float(y)) => x.to_bits() == y.to_bits() || (x.is_nan() && y.is_nan()),
+      (Value::Double(x), Value::Double(y)) => x.to_bits() == y.to_bits() || (x.is_nan() && y.is_nan()),
+      (Value::Ref(x), Value::Ref(_)) if Value::Ref(x) != b => {
+        let class = self.object_class(x)?;
+        match self.find_virtual(class, "equals", "(Ljava/lang/Object;)Z") {
+          Some(method) => self.invoke(method, vec![a, b])?.map_or(Ok(0), |v| v.as_int())? != 0,
+          None => false,
+        }
+      },
+      (a, b) => a == b,
+    })
+  }
+}
+
+// ラムダのクラスが実装するメソッド: 捕捉した値と引数を並べて実装メソッドを呼ぶ
+fn lambda_invoke(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = args[0].as_ref()?.ok_or_else(|| VmError::null_pointer("this is null"))?;
+  let class = vm.object_class(this)?;
+  let target = vm.lambdas.get(&class).cloned()
+    .ok_or_else(|| VmError::internal(format!("{} is not a lambda class", vm.classes[class].name)))?;
+  let fields = vm.heap.get(this)?.fields()?;
+  let mut values: Vec<Value> = target.fields.iter().map(|&slot| fields[slot]).collect();
+  values.extend_from_slice(&args[1..]);
+  vm.invoke_adapted(&target.target, values, &target.parameters, target.return_type.as_ref())
+}
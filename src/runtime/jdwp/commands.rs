@@ -0,0 +1,843 @@
+use std::rc::Rc;
+
+use crate::runtime::{
+  class::{ClassId, InitState, RuntimeField, RuntimeMethod},
+  error::VmError,
+  frame::Frame,
+  heap::ArrayData,
+  jdwp::{
+    events::EventRequest,
+    packet::{
+      self, JdwpResult, PacketReader, PacketWriter, ABSENT_INFORMATION, ILLEGAL_ARGUMENT, INVALID_CLASS, INVALID_FIELDID,
+      INVALID_FRAMEID, INVALID_INDEX, INVALID_LENGTH, INVALID_METHODID, INVALID_OBJECT, INVALID_SLOT, INVALID_THREAD,
+      INVALID_THREAD_GROUP, NOT_IMPLEMENTED, THREAD_GROUP_ID, THREAD_NOT_SUSPENDED,
+    },
+    Jdwp,
+  },
+  library::mirror::MirrorTarget,
+  scheduler::{ThreadId, ThreadState},
+  value::{ObjRef, Value},
+  vm::Vm,
+};
+
+const JDWP_MAJOR: i32 = 1;
+// 9以降はモジュールのコマンドが必要になるので、Java 8相当として応答する
+const JDWP_MINOR: i32 = 8;
+
+// ClassStatus
+const VERIFIED: i32 = 1;
+const PREPARED: i32 = 2;
+const INITIALIZED: i32 = 4;
+const ERROR: i32 = 8;
+
+// ThreadStatus
+const ZOMBIE: i32 = 0;
+const RUNNING: i32 = 1;
+const SLEEPING: i32 = 2;
+const MONITOR: i32 = 3;
+const WAIT: i32 = 4;
+
+const INVOKE_NONVIRTUAL: i32 = 2;
+const INTERNAL: u16 = 113;
+
+fn is_object_tag(tag: u8) -> bool {
+  matches!(tag, b'L' | b'[' | b's' | b't' | b'g' | b'l' | b'c')
+}
+
+fn array_tag(array: &ArrayData) -> u8 {
+  match array {
+    ArrayData::Boolean(_) => b'Z',
+    ArrayData::Byte(_) => b'B',
+    ArrayData::Char(_) => b'C',
+    ArrayData::Short(_) => b'S',
+    ArrayData::Int(_) => b'I',
+    ArrayData::Long(_) => b'J',
+    ArrayData::Float(_) => b'F',
+    ArrayData::Double(_) => b'D',
+    ArrayData::Ref(_) => b'L',
+  }
+}
+
+// CapabilitiesNewの順 (先頭の7つがCapabilities)
+fn capabilities() -> [bool; 32] {
+  let mut capabilities = [false; 32];
+  // canGetBytecodes
+  capabilities[2] = true;
+  // canRequestVMDeathEvent
+  capabilities[13] = true;
+  capabilities
+}
+
+// 戻り値の型の記述子の先頭
+fn return_tag(method: &RuntimeMethod) -> u8 {
+  method.descriptor.rsplit_once(')').and_then(|(_, ret)| ret.bytes().next()).unwrap_or(b'V')
+}
+
+impl Vm {
+  pub(crate) fn jdwp_command(&mut self, jdwp: &mut Jdwp, command_set: u8, command: u8, data: &[u8], reply: &mut PacketWriter) -> JdwpResult<()> {
+    let reader = &mut PacketReader::new(data);
+    match command_set {
+      1 => self.virtual_machine_command(jdwp, command, reader, reply),
+      2 => self.reference_type_command(command, reader, reply),
+      3 => self.class_type_command(command, reader, reply),
+      6 => self.method_command(command, reader, reply),
+      9 => self.object_reference_command(command, reader, reply),
+      10 if command == 1 => {
+        let object = self.jdwp_object(reader.id()?)?;
+        let value = self.string_value(object).map_err(|_| INVALID_OBJECT)?;
+        reply.string(&value);
+        Ok(())
+      },
+      11 => self.thread_command(jdwp, command, reader, reply),
+      12 => self.thread_group_command(command, reader, reply),
+      13 => self.array_command(command, reader, reply),
+      // ClassLoaderReference.VisibleClasses (クラスローダは1つだけ)
+      14 if command == 1 => {
+        reader.id()?;
+        reply.count(self.classes.len());
+        for class in 0..self.classes.len() {
+          reply.u8(self.type_tag(class)).id(packet::type_id(class));
+        }
+        Ok(())
+      },
+      15 => self.event_request_command(jdwp, command, reader, reply),
+      16 => self.stack_frame_command(command, reader, reply),
+      // ClassObjectReference.ReflectedType
+      17 if command == 1 => {
+        let object = self.jdwp_object(reader.id()?)?;
+        match self.mirror_target(object) {
+          Some(MirrorTarget::Class(class)) => {
+            let class = *class;
+            reply.u8(self.type_tag(class)).id(packet::type_id(class));
+          },
+          // 基本型のClassオブジェクトには型のIDがない
+          Some(MirrorTarget::Primitive(_)) => {
+            reply.u8(0).id(0);
+          },
+          None => return Err(INVALID_OBJECT),
+        }
+        Ok(())
+      },
+      _ => Err(NOT_IMPLEMENTED),
+    }
+  }
+
+  fn virtual_machine_command(&mut self, jdwp: &mut Jdwp, command: u8, reader: &mut PacketReader, reply: &mut PacketWriter) -> JdwpResult<()> {
+    match command {
+      // Version
+      1 => {
+        reply.string("rust-jvm JDWP agent").i32(JDWP_MAJOR).i32(JDWP_MINOR).string(env!("CARGO_PKG_VERSION")).string("rust-jvm");
+      },
+      // ClassesBySignature
+      2 => {
+        let signature = reader.string()?;
+        let classes: Vec<ClassId> = (0..self.classes.len()).filter(|&class| self.class_signature(class) == signature).collect();
+        reply.count(classes.len());
+        for class in classes {
+          reply.u8(self.type_tag(class)).id(packet::type_id(class)).i32(self.class_status(class));
+        }
+      },
+      // AllClasses, AllClassesWithGeneric
+      3 | 20 => {
+        reply.count(self.classes.len());
+        for class in 0..self.classes.len() {
+          reply.u8(self.type_tag(class)).id(packet::type_id(class)).string(&self.class_signature(class));
+          if command == 20 {
+            reply.string("");
+          }
+          reply.i32(self.class_status(class));
+        }
+      },
+      // AllThreads
+      4 => {
+        let threads = self.jdwp_threads();
+        reply.count(threads.len());
+        for thread in threads {
+          reply.id(thread);
+        }
+      },
+      // TopLevelThreadGroups
+      5 => {
+        reply.count(1).id(THREAD_GROUP_ID);
+      },
+      // Dispose (切断はserve_jdwpで行う)
+      6 => {},
+      // IDSizes
+      7 => {
+        for _ in 0..5 {
+          reply.i32(8);
+        }
+      },
+      // Suspend
+      8 => jdwp.suspend_count += 1,
+      // Resume
+      9 => jdwp.suspend_count = jdwp.suspend_count.saturating_sub(1),
+      // Exit (終了はserve_jdwpで応答を返してから行う)
+      10 => {
+        reader.i32()?;
+      },
+      // CreateString
+      11 => {
+        let value = reader.string()?;
+        let string = self.new_string(&value).map_err(|_| INTERNAL)?;
+        reply.id(packet::object_id(Some(string)));
+      },
+      // Capabilities
+      12 => {
+        for capability in capabilities().into_iter().take(7) {
+          reply.bool(capability);
+        }
+      },
+      // ClassPaths
+      13 => {
+        let base = std::env::current_dir().map(|dir| dir.display().to_string()).unwrap_or_default();
+        reply.string(&base).count(self.options.class_path.len());
+        for path in &self.options.class_path {
+          reply.string(&path.display().to_string());
+        }
+        let boot = self.options.boot_class_path.clone().unwrap_or_default();
+        reply.count(boot.len());
+        for path in boot {
+          reply.string(&path.display().to_string());
+        }
+      },
+      // DisposeObjects
+      14 => {},
+      // HoldEvents, ReleaseEvents
+      15 | 16 => {},
+      // CapabilitiesNew
+      17 => {
+        for capability in capabilities() {
+          reply.bool(capability);
+        }
+      },
+      _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+  }
+
+  fn reference_type_command(&mut self, command: u8, reader: &mut PacketReader, reply: &mut PacketWriter) -> JdwpResult<()> {
+    let class = self.jdwp_class(reader.id()?)?;
+    match command {
+      // Signature, SignatureWithGeneric
+      1 | 13 => {
+        reply.string(&self.class_signature(class));
+        if command == 13 {
+          reply.string("");
+        }
+      },
+      // ClassLoader (ブートストラップクラスローダとして扱う)
+      2 => {
+        reply.id(0);
+      },
+      // Modifiers
+      3 => {
+        reply.i32(self.classes[class].access_flags as i32);
+      },
+      // Fields, FieldsWithGeneric
+      4 | 14 => {
+        let fields = &self.classes[class].fields;
+        reply.count(fields.len());
+        for (index, field) in fields.iter().enumerate() {
+          reply.id(packet::field_id(class, index)).string(&field.name).string(&field.descriptor);
+          if command == 14 {
+            reply.string("");
+          }
+          reply.i32(field.access_flags as i32);
+        }
+      },
+      // Methods, MethodsWithGeneric
+      5 | 15 => {
+        let methods = &self.classes[class].methods;
+        reply.count(methods.len());
+        for method in methods {
+          reply.id(packet::method_id(method.id)).string(&method.name).string(&method.descriptor);
+          if command == 15 {
+            reply.string("");
+          }
+          reply.i32(method.access_flags as i32);
+        }
+      },
+      // GetValues (staticフィールド)
+      6 => {
+        let count = reader.count()?;
+        reply.count(count);
+        for _ in 0..count {
+          let (owner, field) = self.jdwp_field(reader.id()?)?;
+          let tag = field.descriptor.as_bytes()[0];
+          let value = self.classes[owner].static_values[field.slot];
+          self.write_tagged(reply, tag, value);
+        }
+      },
+      // SourceFile
+      7 => {
+        let source_file = self.classes[class].source_file.as_ref().ok_or(ABSENT_INFORMATION)?;
+        reply.string(source_file);
+      },
+      // NestedTypes
+      8 => {
+        let prefix = format!("{}$", self.classes[class].name);
+        let nested: Vec<ClassId> = (0..self.classes.len())
+          .filter(|&nested| self.classes[nested].name.strip_prefix(&prefix).is_some_and(|rest| !rest.contains('$')))
+          .collect();
+        reply.count(nested.len());
+        for nested in nested {
+          reply.u8(self.type_tag(nested)).id(packet::type_id(nested));
+        }
+      },
+      // Status
+      9 => {
+        reply.i32(self.class_status(class));
+      },
+      // Interfaces
+      10 => {
+        let interfaces = self.classes[class].interfaces.clone();
+        reply.count(interfaces.len());
+        for interface in interfaces {
+          reply.id(packet::type_id(interface));
+        }
+      },
+      // ClassObject
+      11 => {
+        let mirror = self.class_mirror(class).map_err(|_| INTERNAL)?;
+        reply.id(packet::object_id(Some(mirror)));
+      },
+      // SourceDebugExtension
+      12 => return Err(ABSENT_INFORMATION),
+      _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+  }
+
+  fn class_type_command(&mut self, command: u8, reader: &mut PacketReader, reply: &mut PacketWriter) -> JdwpResult<()> {
+    let class = self.jdwp_class(reader.id()?)?;
+    match command {
+      // Superclass
+      1 => {
+        reply.id(self.classes[class].super_class.map_or(0, packet::type_id));
+      },
+      // SetValues
+      2 => {
+        for _ in 0..reader.count()? {
+          let (owner, field) = self.jdwp_field(reader.id()?)?;
+          let slot = field.slot;
+          let value = self.read_value(reader, field.descriptor.as_bytes()[0])?;
+          self.classes[owner].static_values[slot] = value;
+        }
+      },
+      // InvokeMethod
+      3 => {
+        let thread = reader.id()?;
+        self.jdwp_thread(thread)?;
+        let method = self.jdwp_method(reader.id()?)?;
+        let args = self.jdwp_arguments(reader)?;
+        reader.i32()?;
+        if !method.is_static() {
+          return Err(INVALID_METHODID);
+        }
+        self.jdwp_invoke(method, args, reply)?;
+      },
+      _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+  }
+
+  fn method_command(&mut self, command: u8, reader: &mut PacketReader, reply: &mut PacketWriter) -> JdwpResult<()> {
+    self.jdwp_class(reader.id()?)?;
+    let method = self.jdwp_method(reader.id()?)?;
+    match command {
+      // LineTable (ネイティブメソッドは-1)
+      1 => match method.code() {
+        Some(code) => {
+          reply.i64(0).i64(code.bytes.len() as i64 - 1).count(code.line_numbers.len());
+          for &(pc, line) in &code.line_numbers {
+            reply.i64(pc as i64).i32(line as i32);
+          }
+        },
+        None => {
+          reply.i64(-1).i64(-1).count(0);
+        },
+      },
+      // VariableTable, VariableTableWithGeneric
+      2 | 5 => {
+        let code = method.code().ok_or(ABSENT_INFORMATION)?;
+        if code.local_variables.is_empty() {
+          return Err(ABSENT_INFORMATION);
+        }
+        let receiver = if method.is_static() { 0 } else { 1 };
+        reply.i32((receiver + method.signature.parameter_slots()) as i32).count(code.local_variables.len());
+        for variable in &code.local_variables {
+          reply.i64(variable.start_pc as i64).string(&variable.name).string(&variable.descriptor);
+          if command == 5 {
+            reply.string("");
+          }
+          reply.i32(variable.length as i32).i32(variable.index as i32);
+        }
+      },
+      // Bytecodes
+      3 => {
+        let bytes = method.code().map_or(&[][..], |code| &code.bytes[..]);
+        reply.count(bytes.len());
+        reply.data.extend_from_slice(bytes);
+      },
+      // IsObsolete
+      4 => {
+        reply.bool(false);
+      },
+      _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+  }
+
+  fn object_reference_command(&mut self, command: u8, reader: &mut PacketReader, reply: &mut PacketWriter) -> JdwpResult<()> {
+    let id = reader.id()?;
+    // 存在しなくなったかはIsCollectedで確かめる
+    if command == 9 {
+      reply.bool(packet::object_from_id(id).is_none_or(|object| self.heap.get(object).is_err()));
+      return Ok(());
+    }
+    let object = self.jdwp_object(id)?;
+    match command {
+      // ReferenceType
+      1 => {
+        let class = self.object_class(object).map_err(|_| INVALID_OBJECT)?;
+        reply.u8(self.type_tag(class)).id(packet::type_id(class));
+      },
+      // GetValues
+      2 => {
+        let count = reader.count()?;
+        reply.count(count);
+        for _ in 0..count {
+          let (_, field) = self.jdwp_field(reader.id()?)?;
+          let tag = field.descriptor.as_bytes()[0];
+          let value = self.heap.get(object).ok()
+            .and_then(|o| o.fields().ok()?.get(field.slot).copied())
+            .ok_or(INVALID_FIELDID)?;
+          self.write_tagged(reply, tag, value);
+        }
+      },
+      // SetValues
+      3 => {
+        for _ in 0..reader.count()? {
+          let (_, field) = self.jdwp_field(reader.id()?)?;
+          let slot = field.slot;
+          let value = self.read_value(reader, field.descriptor.as_bytes()[0])?;
+          let fields = self.heap.get_mut(object).and_then(|o| o.fields_mut()).map_err(|_| INVALID_OBJECT)?;
+          *fields.get_mut(slot).ok_or(INVALID_FIELDID)? = value;
+        }
+      },
+      // InvokeMethod
+      6 => {
+        self.jdwp_thread(reader.id()?)?;
+        self.jdwp_class(reader.id()?)?;
+        let method = self.jdwp_method(reader.id()?)?;
+        let mut args = self.jdwp_arguments(reader)?;
+        let options = reader.i32()?;
+        if method.is_static() {
+          return Err(INVALID_METHODID);
+        }
+        let method = match options & INVOKE_NONVIRTUAL {
+          0 => {
+            let class = self.object_class(object).map_err(|_| INVALID_OBJECT)?;
+            self.find_virtual(class, &method.name, &method.descriptor).unwrap_or(method)
+          },
+          _ => method,
+        };
+        args.insert(0, Value::Ref(object));
+        self.jdwp_invoke(method, args, reply)?;
+      },
+      // DisableCollection, EnableCollection
+      7 | 8 => {},
+      _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+  }
+
+  fn thread_command(&mut self, jdwp: &mut Jdwp, command: u8, reader: &mut PacketReader, reply: &mut PacketWriter) -> JdwpResult<()> {
+    let object = reader.id()?;
+    let thread = self.jdwp_thread(object)?;
+    match command {
+      // Name
+      1 => {
+        let name = match thread {
+          Some(thread) => self.thread_name(thread),
+          None => self.get_field(packet::object_from_id(object).ok_or(INVALID_THREAD)?, "name", "Ljava/lang/String;").ok()
+            .and_then(|name| name.as_ref().ok()?)
+            .and_then(|name| self.string_value(name).ok())
+            .unwrap_or_default(),
+        };
+        reply.string(&name);
+      },
+      // Suspend (VM全体を止める)
+      2 => jdwp.suspend_count += 1,
+      // Resume
+      3 => jdwp.suspend_count = jdwp.suspend_count.saturating_sub(1),
+      // Status
+      4 => {
+        let status = match thread.and_then(|thread| self.threads.table.get(&thread)) {
+          None => ZOMBIE,
+          Some(thread) => match thread.state {
+            ThreadState::Runnable => RUNNING,
+            ThreadState::Sleeping => SLEEPING,
+            ThreadState::Blocked(_) => MONITOR,
+            ThreadState::Waiting(_) | ThreadState::TimedWaiting(_) => WAIT,
+          },
+        };
+        reply.i32(status).i32((jdwp.suspend_count > 0) as i32);
+      },
+      // ThreadGroup
+      5 => {
+        reply.id(THREAD_GROUP_ID);
+      },
+      // Frames
+      6 => {
+        let start = reader.count()?;
+        let length = reader.i32()?;
+        let frames = self.suspended_frames(jdwp, thread)?;
+        if start > frames.len() {
+          return Err(INVALID_INDEX);
+        }
+        let length = if length < 0 { frames.len() - start } else { length as usize };
+        if start + length > frames.len() {
+          return Err(INVALID_LENGTH);
+        }
+        reply.count(length);
+        for depth in start..start + length {
+          let index = frames.len() - 1 - depth;
+          let frame = &frames[index];
+          let tag = self.type_tag(frame.method.class);
+          reply.id(index as u64 + 1).location(tag, frame.method.class, frame.method.id, frame.pc);
+        }
+      },
+      // FrameCount
+      7 => {
+        let count = self.suspended_frames(jdwp, thread)?.len();
+        reply.count(count);
+      },
+      // SuspendCount
+      12 => {
+        reply.i32(jdwp.suspend_count as i32);
+      },
+      // IsVirtual
+      15 => {
+        reply.bool(false);
+      },
+      _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+  }
+
+  fn thread_group_command(&mut self, command: u8, reader: &mut PacketReader, reply: &mut PacketWriter) -> JdwpResult<()> {
+    if reader.id()? != THREAD_GROUP_ID {
+      return Err(INVALID_THREAD_GROUP);
+    }
+    match command {
+      // Name
+      1 => {
+        reply.string("main");
+      },
+      // Parent
+      2 => {
+        reply.id(0);
+      },
+      // Children (スレッドと子のスレッドグループ)
+      3 => {
+        let threads = self.jdwp_threads();
+        reply.count(threads.len());
+        for thread in threads {
+          reply.id(thread);
+        }
+        reply.count(0);
+      },
+      _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+  }
+
+  fn array_command(&mut self, command: u8, reader: &mut PacketReader, reply: &mut PacketWriter) -> JdwpResult<()> {
+    let object = self.jdwp_object(reader.id()?)?;
+    let length = self.heap.get(object).and_then(|o| o.array()).map_err(|_| INVALID_OBJECT)?.len();
+    match command {
+      // Length
+      1 => {
+        reply.count(length);
+      },
+      // GetValues
+      2 => {
+        let first = reader.count()?;
+        let count = reader.count()?;
+        if first + count > length {
+          return Err(INVALID_LENGTH);
+        }
+        let array = self.heap.get(object).and_then(|o| o.array()).map_err(|_| INVALID_OBJECT)?;
+        let tag = array_tag(array);
+        let values: Vec<Value> = (first..first + count).map(|index| array.get(index)).collect();
+        reply.u8(tag).count(count);
+        for value in values {
+          if is_object_tag(tag) {
+            self.write_tagged(reply, tag, value);
+          } else {
+            self.write_value(reply, tag, value);
+          }
+        }
+      },
+      // SetValues
+      3 => {
+        let first = reader.count()?;
+        let count = reader.count()?;
+        if first + count > length {
+          return Err(INVALID_LENGTH);
+        }
+        let tag = array_tag(self.heap.get(object).and_then(|o| o.array()).map_err(|_| INVALID_OBJECT)?);
+        for index in first..first + count {
+          let value = self.read_value(reader, tag)?;
+          let array = self.heap.get_mut(object).and_then(|o| o.array_mut()).map_err(|_| INVALID_OBJECT)?;
+          array.set(index, value).map_err(|_| ILLEGAL_ARGUMENT)?;
+        }
+      },
+      _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+  }
+
+  fn event_request_command(&mut self, jdwp: &mut Jdwp, command: u8, reader: &mut PacketReader, reply: &mut PacketWriter) -> JdwpResult<()> {
+    match command {
+      // Set
+      1 => {
+        let request = EventRequest::read(0, reader, |thread| {
+          let thread = self.jdwp_thread(thread)?.ok_or(INVALID_THREAD)?;
+          let frames = self.thread_frames(thread);
+          let top = frames.last().map(|frame| (frame.method.id, frame.method.code().and_then(|code| code.line_number(frame.pc))));
+          Ok((frames.len(), top))
+        })?;
+        reply.i32(jdwp.add_request(request));
+      },
+      // Clear
+      2 => {
+        let kind = reader.u8()?;
+        let id = reader.i32()?;
+        jdwp.clear_request(kind, id);
+      },
+      // ClearAllBreakpoints
+      3 => jdwp.clear_breakpoints(),
+      _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+  }
+
+  fn stack_frame_command(&mut self, command: u8, reader: &mut PacketReader, reply: &mut PacketWriter) -> JdwpResult<()> {
+    let thread = self.jdwp_thread(reader.id()?)?.ok_or(INVALID_THREAD)?;
+    let index = (reader.id()? as usize).checked_sub(1).ok_or(INVALID_FRAMEID)?;
+    if index >= self.thread_frames(thread).len() {
+      return Err(INVALID_FRAMEID);
+    }
+    match command {
+      // GetValues
+      1 => {
+        let count = reader.count()?;
+        reply.count(count);
+        for _ in 0..count {
+          let slot = reader.count()?;
+          let tag = reader.u8()?;
+          let value = *self.thread_frames(thread)[index].locals.get(slot).ok_or(INVALID_SLOT)?;
+          self.write_tagged(reply, tag, value);
+        }
+      },
+      // SetValues
+      2 => {
+        for _ in 0..reader.count()? {
+          let slot = reader.count()?;
+          let tag = reader.u8()?;
+          let value = self.read_value(reader, tag)?;
+          let frame = &mut self.thread_frames_mut(thread)[index];
+          frame.set_local(slot, value).map_err(|_| INVALID_SLOT)?;
+        }
+      },
+      // ThisObject
+      3 => {
+        let frame = &self.thread_frames(thread)[index];
+        let this = match frame.method.is_static() {
+          true => Value::Null,
+          false => frame.locals.first().copied().unwrap_or(Value::Null),
+        };
+        self.write_tagged(reply, b'L', this);
+      },
+      _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+  }
+
+  // IDの変換
+
+  fn jdwp_class(&self, id: u64) -> JdwpResult<ClassId> {
+    packet::class_from_id(id).filter(|&class| class < self.classes.len()).ok_or(INVALID_CLASS)
+  }
+
+  fn jdwp_object(&self, id: u64) -> JdwpResult<ObjRef> {
+    packet::object_from_id(id).filter(|&object| self.heap.get(object).is_ok()).ok_or(INVALID_OBJECT)
+  }
+
+  fn jdwp_method(&self, id: u64) -> JdwpResult<Rc<RuntimeMethod>> {
+    (id as usize).checked_sub(1).and_then(|id| self.methods.get(id)).cloned().ok_or(INVALID_METHODID)
+  }
+
+  fn jdwp_field(&self, id: u64) -> JdwpResult<(ClassId, &RuntimeField)> {
+    let (class, index) = packet::field_from_id(id).ok_or(INVALID_FIELDID)?;
+    let field = self.classes.get(class).and_then(|c| c.fields.get(index)).ok_or(INVALID_FIELDID)?;
+    Ok((class, field))
+  }
+
+  // Threadオブジェクトが表す実行中のスレッド (終了したか開始前ならNone)
+  fn jdwp_thread(&self, id: u64) -> JdwpResult<Option<ThreadId>> {
+    let object = self.jdwp_object(id).map_err(|_| INVALID_THREAD)?;
+    let thread_class = self.class_names.get("java/lang/Thread").copied().ok_or(INVALID_THREAD)?;
+    let class = self.object_class(object).map_err(|_| INVALID_THREAD)?;
+    if !self.is_subclass_of(class, thread_class) {
+      return Err(INVALID_THREAD);
+    }
+    Ok(self.threads.table.iter().find(|(_, thread)| thread.object == Some(object)).map(|(&id, _)| id))
+  }
+
+  fn jdwp_threads(&self) -> Vec<u64> {
+    self.threads.table.values().filter_map(|thread| thread.object).map(|object| packet::object_id(Some(object))).collect()
+  }
+
+  fn suspended_frames(&self, jdwp: &Jdwp, thread: Option<ThreadId>) -> JdwpResult<&[Frame]> {
+    if jdwp.suspend_count == 0 {
+      return Err(THREAD_NOT_SUSPENDED);
+    }
+    Ok(thread.map_or(&[][..], |thread| self.thread_frames(thread)))
+  }
+
+  // 型の情報
+
+  pub(crate) fn type_tag(&self, class: ClassId) -> u8 {
+    match &self.classes[class] {
+      class if class.is_array() => 3,
+      class if class.is_interface() => 2,
+      _ => 1,
+    }
+  }
+
+  pub(crate) fn class_signature(&self, class: ClassId) -> String {
+    let name = &self.classes[class].name;
+    if name.starts_with('[') { name.clone() } else { format!("L{};", name) }
+  }
+
+  pub(crate) fn class_status(&self, class: ClassId) -> i32 {
+    match self.classes[class].init_state {
+      _ if self.classes[class].is_array() => VERIFIED | PREPARED | INITIALIZED,
+      InitState::Initialized => VERIFIED | PREPARED | INITIALIZED,
+      InitState::Erroneous => ERROR,
+      _ => VERIFIED | PREPARED,
+    }
+  }
+
+  fn object_tag(&self, object: ObjRef) -> u8 {
+    let Ok(class) = self.object_class(object) else {
+      return b'L';
+    };
+    let name = self.classes[class].name.as_str();
+    if name == "java/lang/String" {
+      b's'
+    } else if self.classes[class].is_array() {
+      b'['
+    } else if self.mirror_target(object).is_some() {
+      b'c'
+    } else if self.class_names.get("java/lang/Thread").is_some_and(|&thread| self.is_subclass_of(class, thread)) {
+      b't'
+    } else {
+      b'L'
+    }
+  }
+
+  // 値の読み書き (tagは型の記述子の先頭の文字)
+
+  fn write_value(&self, writer: &mut PacketWriter, tag: u8, value: Value) {
+    let int = match value {
+      Value::Int(value) => value,
+      _ => 0,
+    };
+    match tag {
+      b'Z' | b'B' => {
+        writer.u8(int as u8);
+      },
+      b'C' | b'S' => {
+        writer.i16(int as i16);
+      },
+      b'I' => {
+        writer.i32(int);
+      },
+      b'J' => {
+        writer.i64(if let Value::Long(value) = value { value } else { 0 });
+      },
+      b'F' => {
+        writer.i32(if let Value::Float(value) = value { value.to_bits() as i32 } else { 0 });
+      },
+      b'D' => {
+        writer.i64(if let Value::Double(value) = value { value.to_bits() as i64 } else { 0 });
+      },
+      b'V' => {},
+      _ => {
+        let object = if let Value::Ref(object) = value { Some(object) } else { None };
+        writer.id(packet::object_id(object));
+      },
+    }
+  }
+
+  fn write_tagged(&self, writer: &mut PacketWriter, tag: u8, value: Value) {
+    let tag = match value {
+      Value::Ref(object) if is_object_tag(tag) => self.object_tag(object),
+      _ => tag,
+    };
+    writer.u8(tag);
+    self.write_value(writer, tag, value);
+  }
+
+  fn read_value(&self, reader: &mut PacketReader, tag: u8) -> JdwpResult<Value> {
+    Ok(match tag {
+      b'Z' => Value::Int(reader.bool()? as i32),
+      b'B' => Value::Int(reader.u8()? as i8 as i32),
+      b'C' => Value::Int(reader.i16()? as u16 as i32),
+      b'S' => Value::Int(reader.i16()? as i32),
+      b'I' => Value::Int(reader.i32()?),
+      b'J' => Value::Long(reader.i64()?),
+      b'F' => Value::Float(f32::from_bits(reader.i32()? as u32)),
+      b'D' => Value::Double(f64::from_bits(reader.i64()? as u64)),
+      _ if is_object_tag(tag) => match reader.id()? {
+        0 => Value::Null,
+        id => Value::Ref(self.jdwp_object(id)?),
+      },
+      _ => return Err(ILLEGAL_ARGUMENT),
+    })
+  }
+
+  fn jdwp_arguments(&self, reader: &mut PacketReader) -> JdwpResult<Vec<Value>> {
+    let mut args = Vec::new();
+    for _ in 0..reader.count()? {
+      let tag = reader.u8()?;
+      args.push(self.read_value(reader, tag)?);
+    }
+    Ok(args)
+  }
+
+  // 中断しているスレッドでメソッドを呼び、戻り値と投げられた例外を返す
+  fn jdwp_invoke(&mut self, method: Rc<RuntimeMethod>, args: Vec<Value>, reply: &mut PacketWriter) -> JdwpResult<()> {
+    let tag = return_tag(&method);
+    let result = self.initialize(method.class).and_then(|()| self.invoke(method, args));
+    match result {
+      Ok(value) => {
+        self.write_tagged(reply, tag, value.unwrap_or(Value::Null));
+        reply.u8(b'L').id(0);
+      },
+      Err(error @ (VmError::Java { .. } | VmError::Thrown(_))) => {
+        let exception = self.materialize(error).map_err(|_| INTERNAL)?;
+        self.write_tagged(reply, tag, Value::Null);
+        self.write_tagged(reply, b'L', Value::Ref(exception));
+      },
+      Err(_) => return Err(INTERNAL),
+    }
+    Ok(())
+  }
+}
+
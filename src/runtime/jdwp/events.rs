@@ -0,0 +1,204 @@
+use crate::runtime::{
+  class::{ClassId, RuntimeMethod},
+  jdwp::packet::{self, JdwpResult, PacketReader, INVALID_EVENT_TYPE},
+};
+
+// イベントの種類
+pub const SINGLE_STEP: u8 = 1;
+pub const BREAKPOINT: u8 = 2;
+pub const THREAD_START: u8 = 6;
+pub const THREAD_DEATH: u8 = 7;
+pub const CLASS_PREPARE: u8 = 8;
+pub const VM_START: u8 = 90;
+pub const VM_DEATH: u8 = 99;
+
+// 中断の方針
+pub const SUSPEND_NONE: u8 = 0;
+pub const SUSPEND_ALL: u8 = 2;
+
+// ステップ実行の単位と深さ
+const STEP_LINE: i32 = 1;
+const STEP_INTO: i32 = 0;
+const STEP_OVER: i32 = 1;
+
+pub enum Modifier {
+  // n回目で報告し、以降は報告しない
+  Count(i32),
+  ThreadOnly(u64),
+  ClassOnly(ClassId),
+  ClassMatch(String),
+  ClassExclude(String),
+  LocationOnly { method: usize, pc: usize },
+  Step(Step),
+  // 報告しない条件は対応していないので、受け付けるだけにする
+  Ignored,
+}
+
+// ステップ実行を始めた時のスレッドの状態
+pub struct Step {
+  pub thread: u64,
+  line: bool,
+  depth: i32,
+  frames: usize,
+  // 開始したフレームのメソッドと行
+  method: Option<usize>,
+  start_line: Option<u16>,
+}
+
+pub struct EventRequest {
+  pub id: i32,
+  pub kind: u8,
+  pub suspend_policy: u8,
+  pub modifiers: Vec<Modifier>,
+}
+
+// イベントが起きたスレッドと場所
+pub struct EventContext<'a> {
+  pub thread: u64,
+  pub class: Option<(ClassId, &'a str)>,
+  pub location: Option<(&'a RuntimeMethod, usize)>,
+  pub frames: usize,
+}
+
+impl EventRequest {
+  // step_frames: スレッドのフレーム数と、実行中のメソッドIDと行
+  pub fn read(
+    id: i32,
+    reader: &mut PacketReader,
+    step_frames: impl Fn(u64) -> JdwpResult<(usize, Option<(usize, Option<u16>)>)>,
+  ) -> JdwpResult<EventRequest> {
+    let kind = reader.u8()?;
+    let suspend_policy = reader.u8()?;
+    let mut modifiers = Vec::new();
+    for _ in 0..reader.count()? {
+      let modifier = match reader.u8()? {
+        1 => Modifier::Count(reader.i32()?),
+        2 => {
+          reader.i32()?;
+          Modifier::Ignored
+        },
+        3 => Modifier::ThreadOnly(reader.id()?),
+        4 => Modifier::ClassOnly(packet::class_from_id(reader.id()?).ok_or(packet::INVALID_CLASS)?),
+        5 => Modifier::ClassMatch(reader.string()?),
+        6 => Modifier::ClassExclude(reader.string()?),
+        7 => {
+          let (_, method, pc) = reader.location()?;
+          let method = (method as usize).checked_sub(1).ok_or(packet::INVALID_METHODID)?;
+          Modifier::LocationOnly { method, pc: pc as usize }
+        },
+        8 => {
+          reader.id()?;
+          reader.bool()?;
+          reader.bool()?;
+          Modifier::Ignored
+        },
+        9 => {
+          reader.id()?;
+          reader.id()?;
+          Modifier::Ignored
+        },
+        10 => {
+          let thread = reader.id()?;
+          let size = reader.i32()?;
+          let depth = reader.i32()?;
+          let (frames, top) = step_frames(thread)?;
+          Modifier::Step(Step {
+            thread,
+            line: size == STEP_LINE,
+            depth,
+            frames,
+            method: top.map(|(method, _)| method),
+            start_line: top.and_then(|(_, line)| line),
+          })
+        },
+        11 => {
+          reader.id()?;
+          Modifier::Ignored
+        },
+        12 => {
+          reader.string()?;
+          Modifier::Ignored
+        },
+        _ => return Err(packet::ILLEGAL_ARGUMENT),
+      };
+      modifiers.push(modifier);
+    }
+    if !matches!(kind, 1..=9 | 20..=21 | 40..=46 | VM_DEATH) {
+      return Err(INVALID_EVENT_TYPE);
+    }
+    Ok(EventRequest { id, kind, suspend_policy, modifiers })
+  }
+
+  pub fn location(&self) -> Option<(usize, usize)> {
+    self.modifiers.iter().find_map(|modifier| match modifier {
+      Modifier::LocationOnly { method, pc } => Some((*method, *pc)),
+      _ => None,
+    })
+  }
+
+  // 条件を全て満たしたらCountを減らし、報告するならtrueを返す
+  pub fn accepts(&mut self, context: &EventContext) -> bool {
+    let matched = self.modifiers.iter().all(|modifier| match modifier {
+      Modifier::Count(_) | Modifier::Ignored => true,
+      Modifier::ThreadOnly(thread) => *thread == context.thread,
+      Modifier::ClassOnly(class) => context.class.is_some_and(|(id, _)| id == *class),
+      Modifier::ClassMatch(pattern) => context.class.is_some_and(|(_, name)| class_matches(pattern, name)),
+      Modifier::ClassExclude(pattern) => !context.class.is_some_and(|(_, name)| class_matches(pattern, name)),
+      Modifier::LocationOnly { method, pc } => context.location.is_some_and(|(m, p)| m.id == *method && p == *pc),
+      Modifier::Step(step) => step.thread == context.thread && context.location.is_some_and(|(method, pc)| step.stops_at(method, pc, context.frames)),
+    });
+    if !matched {
+      return false;
+    }
+    for modifier in &mut self.modifiers {
+      if let Modifier::Count(count) = modifier {
+        if *count <= 0 {
+          return false;
+        }
+        *count -= 1;
+        if *count > 0 {
+          return false;
+        }
+      }
+    }
+    true
+  }
+
+  // Countを使い切ったリクエスト
+  pub fn is_expired(&self) -> bool {
+    self.modifiers.iter().any(|modifier| matches!(modifier, Modifier::Count(0)))
+  }
+}
+
+impl Step {
+  // 命令を実行する前に、ステップ実行を止める位置かを判定する
+  fn stops_at(&self, method: &RuntimeMethod, pc: usize, frames: usize) -> bool {
+    let depth_ok = match self.depth {
+      STEP_INTO => true,
+      STEP_OVER => frames <= self.frames,
+      _ => frames < self.frames,
+    };
+    if !depth_ok {
+      return false;
+    }
+    if !self.line {
+      return true;
+    }
+    // 行単位なら行番号のある位置で、開始した行から変わった時だけ止まる
+    let Some(line) = method.code().and_then(|code| code.line_number(pc)) else {
+      return false;
+    };
+    frames != self.frames || self.method != Some(method.id) || self.start_line != Some(line)
+  }
+}
+
+// "java.*"や"*.Foo"のようなパターン (先頭か末尾の"*"だけ使える)
+fn class_matches(pattern: &str, name: &str) -> bool {
+  if let Some(prefix) = pattern.strip_suffix('*') {
+    name.starts_with(prefix)
+  } else if let Some(suffix) = pattern.strip_prefix('*') {
+    name.ends_with(suffix)
+  } else {
+    pattern == name
+  }
+}
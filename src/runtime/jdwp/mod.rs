@@ -0,0 +1,413 @@
+pub mod commands;
+pub mod events;
+pub mod packet;
+
+use std::{
+  collections::HashSet,
+  io::{self, Read, Write},
+  net::{Shutdown, TcpListener, TcpStream},
+  process,
+};
+
+use crate::runtime::{
+  class::ClassId,
+  jdwp::{
+    events::{
+      EventContext, EventRequest, BREAKPOINT, CLASS_PREPARE, SINGLE_STEP, SUSPEND_ALL, SUSPEND_NONE, THREAD_DEATH,
+      THREAD_START, VM_DEATH, VM_START,
+    },
+    packet::{PacketReader, PacketWriter, HANDSHAKE},
+  },
+  library::thread,
+  options::JdwpOptions,
+  vm::Vm,
+};
+
+// この命令数ごとに、デバッガからコマンドが届いていないかを確かめる
+const POLL_INTERVAL: u32 = 4096;
+
+const EVENT_COMMAND_SET: u8 = 64;
+const COMPOSITE: u8 = 100;
+
+pub struct Jdwp {
+  // suspend=nで、まだデバッガが接続していない
+  listener: Option<TcpListener>,
+  stream: Option<TcpStream>,
+  next_packet: u32,
+  pub(crate) requests: Vec<EventRequest>,
+  next_request: i32,
+  // VM全体を中断している回数 (スレッドの中断もVM全体の中断として扱う)
+  pub(crate) suspend_count: u32,
+  steps: u32,
+  // ブレークポイントを置いた(メソッドID, pc)
+  breakpoints: HashSet<(usize, usize)>,
+}
+
+// 報告するイベントの内容 (リクエストID以降)
+pub(crate) struct Event {
+  kind: u8,
+  request: i32,
+  data: Vec<u8>,
+}
+
+impl Jdwp {
+  fn new() -> Jdwp {
+    Jdwp {
+      listener: None,
+      stream: None,
+      next_packet: 1,
+      requests: Vec::new(),
+      next_request: 1,
+      suspend_count: 0,
+      steps: 0,
+      breakpoints: HashSet::new(),
+    }
+  }
+
+  pub(crate) fn add_request(&mut self, mut request: EventRequest) -> i32 {
+    request.id = self.next_request;
+    self.next_request += 1;
+    if request.kind == BREAKPOINT && let Some(location) = request.location() {
+      self.breakpoints.insert(location);
+    }
+    let id = request.id;
+    self.requests.push(request);
+    id
+  }
+
+  pub(crate) fn clear_request(&mut self, kind: u8, id: i32) {
+    self.requests.retain(|request| request.kind != kind || request.id != id);
+    self.update_breakpoints();
+  }
+
+  pub(crate) fn clear_breakpoints(&mut self) {
+    self.requests.retain(|request| request.kind != BREAKPOINT);
+    self.update_breakpoints();
+  }
+
+  fn update_breakpoints(&mut self) {
+    self.breakpoints = self.requests.iter()
+      .filter(|request| request.kind == BREAKPOINT)
+      .filter_map(EventRequest::location)
+      .collect();
+  }
+
+  // イベントを報告するリクエストと、一番強い中断の方針
+  fn collect_events(&mut self, kinds: &[u8], context: &EventContext, data: impl Fn(u8) -> Vec<u8>) -> (Vec<Event>, u8) {
+    let mut events = Vec::new();
+    let mut policy = SUSPEND_NONE;
+    for request in &mut self.requests {
+      if kinds.contains(&request.kind) && request.accepts(context) {
+        events.push(Event { kind: request.kind, request: request.id, data: data(request.kind) });
+        policy = policy.max(request.suspend_policy);
+      }
+    }
+    if !events.is_empty() {
+      self.requests.retain(|request| !request.is_expired());
+      self.update_breakpoints();
+    }
+    (events, policy)
+  }
+
+  // 実行中にデバッガからコマンドが届いているか (切断された時もtrue)
+  fn has_pending(&self) -> bool {
+    let Some(stream) = &self.stream else {
+      return false;
+    };
+    let mut byte = [0];
+    let _ = stream.set_nonblocking(true);
+    let available = stream.peek(&mut byte);
+    let _ = stream.set_nonblocking(false);
+    !matches!(available, Err(ref e) if e.kind() == io::ErrorKind::WouldBlock)
+  }
+
+  fn send_events(&mut self, policy: u8, events: &[Event]) -> io::Result<()> {
+    let mut writer = PacketWriter::default();
+    writer.u8(policy).count(events.len());
+    for event in events {
+      writer.u8(event.kind).i32(event.request);
+      writer.data.extend_from_slice(&event.data);
+    }
+    let id = self.next_packet;
+    self.next_packet += 1;
+    let stream = self.stream.as_mut().ok_or(io::ErrorKind::NotConnected)?;
+    packet::write_command(stream, id, EVENT_COMMAND_SET, COMPOSITE, &writer.data)
+  }
+}
+
+fn parse_address(address: &str, server: bool) -> String {
+  match address.rsplit_once(':') {
+    Some(("*", port)) => format!("0.0.0.0:{}", port),
+    Some(_) => address.to_string(),
+    // ホストを省略したらローカルホストだけで待つ
+    None if server => format!("127.0.0.1:{}", address),
+    None => format!("localhost:{}", address),
+  }
+}
+
+// デバッガが"JDWP-Handshake"を送り、同じ文字列を返す
+fn handshake(stream: &mut TcpStream) -> io::Result<()> {
+  let mut received = [0; HANDSHAKE.len()];
+  stream.read_exact(&mut received)?;
+  if received != *HANDSHAKE {
+    return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid handshake"));
+  }
+  stream.write_all(HANDSHAKE)
+}
+
+impl Vm {
+  // -agentlib:jdwp: 実行を始める前にデバッガと接続する (suspend=nなら接続を待たずに実行する)
+  pub fn attach_jdwp(&mut self, options: &JdwpOptions) -> Result<(), String> {
+    let address = parse_address(&options.address, options.server);
+    let mut jdwp = Jdwp::new();
+    let stream = if options.server {
+      let listener = TcpListener::bind(&address).map_err(|e| format!("failed to listen on {}: {}", address, e))?;
+      let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+      println!("Listening for transport dt_socket at address: {}", port);
+      let _ = io::stdout().flush();
+      if !options.suspend {
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        jdwp.listener = Some(listener);
+        self.jdwp = Some(jdwp);
+        return Ok(());
+      }
+      listener.accept().map_err(|e| format!("failed to accept a connection: {}", e))?.0
+    } else {
+      TcpStream::connect(&address).map_err(|e| format!("failed to connect to {}: {}", address, e))?
+    };
+    self.connect_jdwp(&mut jdwp, stream, options.suspend)?;
+    if jdwp.stream.is_some() {
+      self.jdwp = Some(jdwp);
+    }
+    Ok(())
+  }
+
+  // ハンドシェイクの後、VM_STARTを報告する (suspendなら再開されるまでコマンドを受け付ける)
+  fn connect_jdwp(&mut self, jdwp: &mut Jdwp, mut stream: TcpStream, suspend: bool) -> Result<(), String> {
+    let _ = stream.set_nodelay(true);
+    handshake(&mut stream).map_err(|e| format!("JDWP handshake failed: {}", e))?;
+    jdwp.stream = Some(stream);
+    // mainスレッドのIDとして使うThreadオブジェクトもここで作る
+    let mut data = PacketWriter::default();
+    data.id(self.current_thread_id());
+    let policy = if suspend { SUSPEND_ALL } else { SUSPEND_NONE };
+    self.report_events(jdwp, policy, &[Event { kind: VM_START, request: 0, data: data.data }]);
+    Ok(())
+  }
+
+  // 命令を実行する前に呼び、ブレークポイントとステップ実行のイベントを報告する
+  pub(crate) fn jdwp_before(&mut self) {
+    let Some(mut jdwp) = self.jdwp.take() else {
+      return;
+    };
+    jdwp.steps += 1;
+    if jdwp.steps >= POLL_INTERVAL {
+      jdwp.steps = 0;
+      self.poll_jdwp(&mut jdwp);
+    }
+    if jdwp.stream.is_some() && !jdwp.requests.is_empty() {
+      self.location_events(&mut jdwp);
+    }
+    if jdwp.stream.is_some() || jdwp.listener.is_some() {
+      self.jdwp = Some(jdwp);
+    }
+  }
+
+  fn location_events(&mut self, jdwp: &mut Jdwp) {
+    let Some(frame) = self.frames.last() else {
+      return;
+    };
+    let method = frame.method.clone();
+    let pc = frame.pc;
+    let stepping = jdwp.requests.iter().any(|request| request.kind == SINGLE_STEP);
+    if !stepping && !jdwp.breakpoints.contains(&(method.id, pc)) {
+      return;
+    }
+    let thread = self.current_thread_id();
+    let name = self.classes[method.class].java_name();
+    let context = EventContext {
+      thread,
+      class: Some((method.class, &name)),
+      location: Some((&method, pc)),
+      frames: self.frames.len(),
+    };
+    let tag = self.type_tag(method.class);
+    let (events, policy) = jdwp.collect_events(&[SINGLE_STEP, BREAKPOINT], &context, |_| {
+      let mut data = PacketWriter::default();
+      data.id(thread).location(tag, method.class, method.id, pc);
+      data.data
+    });
+    if !events.is_empty() {
+      self.report_events(jdwp, policy, &events);
+    }
+  }
+
+  // 実行中に届いたコマンドを処理する (suspend=nなら接続も受け付ける)
+  fn poll_jdwp(&mut self, jdwp: &mut Jdwp) {
+    let Some(listener) = &jdwp.listener else {
+      if jdwp.has_pending() {
+        self.serve_jdwp(jdwp);
+      }
+      return;
+    };
+    match listener.accept() {
+      Ok((stream, _)) => {
+        jdwp.listener = None;
+        let _ = stream.set_nonblocking(false);
+        if let Err(e) = self.connect_jdwp(jdwp, stream, false) {
+          eprintln!("Error: {}", e);
+        }
+      },
+      Err(e) if e.kind() == io::ErrorKind::WouldBlock => {},
+      Err(e) => {
+        eprintln!("Error: failed to accept a connection: {}", e);
+        jdwp.listener = None;
+      },
+    }
+  }
+
+  // イベントを送り、中断する方針なら再開されるまでコマンドを受け付ける
+  pub(crate) fn report_events(&mut self, jdwp: &mut Jdwp, policy: u8, events: &[Event]) {
+    if jdwp.send_events(policy, events).is_err() {
+      self.disconnect_jdwp(jdwp);
+      return;
+    }
+    if policy != SUSPEND_NONE {
+      jdwp.suspend_count += 1;
+      self.serve_jdwp(jdwp);
+    }
+  }
+
+  // 届いているコマンドを処理し、中断している間は再開されるまでコマンドを待つ
+  fn serve_jdwp(&mut self, jdwp: &mut Jdwp) {
+    while jdwp.suspend_count > 0 || jdwp.has_pending() {
+      let Some(stream) = jdwp.stream.as_mut() else {
+        return;
+      };
+      let packet = match packet::read_command(stream) {
+        Ok(Some(packet)) => packet,
+        Ok(None) | Err(_) => {
+          self.disconnect_jdwp(jdwp);
+          return;
+        },
+      };
+      let mut reply = PacketWriter::default();
+      let error = match self.jdwp_command(jdwp, packet.command_set, packet.command, &packet.data, &mut reply) {
+        Ok(()) => 0,
+        Err(error) => {
+          reply.data.clear();
+          error
+        },
+      };
+      let written = match jdwp.stream.as_mut() {
+        Some(stream) => packet::write_reply(stream, packet.id, error, &reply.data),
+        None => return,
+      };
+      if written.is_err() {
+        self.disconnect_jdwp(jdwp);
+        return;
+      }
+      match (packet.command_set, packet.command) {
+        // VirtualMachine.Dispose
+        (1, 6) => {
+          self.disconnect_jdwp(jdwp);
+          return;
+        },
+        // VirtualMachine.Exit
+        (1, 10) if error == 0 => {
+          let status = PacketReader::new(&packet.data).i32().unwrap_or(0);
+          self.finish_trace();
+          self.disconnect_jdwp(jdwp);
+          let _ = io::stdout().flush();
+          process::exit(status)
+        },
+        _ => {},
+      }
+    }
+  }
+
+  // デバッガが切断したら、リクエストを全て取り消して実行を続ける
+  fn disconnect_jdwp(&mut self, jdwp: &mut Jdwp) {
+    if let Some(stream) = jdwp.stream.take() {
+      let _ = stream.shutdown(Shutdown::Both);
+    }
+    jdwp.requests.clear();
+    jdwp.breakpoints.clear();
+    jdwp.suspend_count = 0;
+  }
+
+  // 実行中のスレッドのThreadオブジェクトのID
+  pub(crate) fn current_thread_id(&mut self) -> u64 {
+    packet::object_id(thread::current_thread(self).ok())
+  }
+
+  // スレッドの実行開始と終了 (新しいスレッドの中で呼ぶ)
+  fn jdwp_thread_event(&mut self, kind: u8) {
+    let Some(mut jdwp) = self.jdwp.take() else {
+      return;
+    };
+    if jdwp.stream.is_some() && jdwp.requests.iter().any(|request| request.kind == kind) {
+      let thread = self.current_thread_id();
+      let context = EventContext { thread, class: None, location: None, frames: self.frames.len() };
+      let (events, policy) = jdwp.collect_events(&[kind], &context, |_| thread.to_be_bytes().to_vec());
+      if !events.is_empty() {
+        self.report_events(&mut jdwp, policy, &events);
+      }
+    }
+    if jdwp.stream.is_some() || jdwp.listener.is_some() {
+      self.jdwp = Some(jdwp);
+    }
+  }
+
+  pub(crate) fn jdwp_thread_start(&mut self) {
+    self.jdwp_thread_event(THREAD_START);
+  }
+
+  pub(crate) fn jdwp_thread_death(&mut self) {
+    self.jdwp_thread_event(THREAD_DEATH);
+  }
+
+  // クラスを定義した直後に呼ぶ
+  pub(crate) fn jdwp_class_prepare(&mut self, class: ClassId) {
+    let Some(mut jdwp) = self.jdwp.take() else {
+      return;
+    };
+    if jdwp.stream.is_some() && jdwp.requests.iter().any(|request| request.kind == CLASS_PREPARE) {
+      let thread = self.current_thread_id();
+      let name = self.classes[class].java_name();
+      let context = EventContext { thread, class: Some((class, &name)), location: None, frames: self.frames.len() };
+      let tag = self.type_tag(class);
+      let signature = self.class_signature(class);
+      let status = self.class_status(class);
+      let (events, policy) = jdwp.collect_events(&[CLASS_PREPARE], &context, |_| {
+        let mut data = PacketWriter::default();
+        data.id(thread).u8(tag).id(packet::type_id(class)).string(&signature).i32(status);
+        data.data
+      });
+      if !events.is_empty() {
+        self.report_events(&mut jdwp, policy, &events);
+      }
+    }
+    if jdwp.stream.is_some() || jdwp.listener.is_some() {
+      self.jdwp = Some(jdwp);
+    }
+  }
+
+  // プロセスを終了する前に呼び、VM_DEATHを報告して切断する
+  pub fn finish_jdwp(&mut self) {
+    let Some(mut jdwp) = self.jdwp.take() else {
+      return;
+    };
+    if jdwp.stream.is_some() {
+      let mut events: Vec<Event> = jdwp.requests.iter()
+        .filter(|request| request.kind == VM_DEATH)
+        .map(|request| Event { kind: VM_DEATH, request: request.id, data: Vec::new() })
+        .collect();
+      if events.is_empty() {
+        events.push(Event { kind: VM_DEATH, request: 0, data: Vec::new() });
+      }
+      let _ = jdwp.send_events(SUSPEND_NONE, &events);
+    }
+    self.disconnect_jdwp(&mut jdwp);
+  }
+}
@@ -0,0 +1,221 @@
+use std::io::{self, Read, Write};
+
+use crate::runtime::{class::ClassId, value::ObjRef};
+
+pub const HANDSHAKE: &[u8; 14] = b"JDWP-Handshake";
+
+const HEADER_LENGTH: usize = 11;
+const FLAG_REPLY: u8 = 0x80;
+
+// エラーコード
+pub const INVALID_THREAD: u16 = 10;
+pub const INVALID_THREAD_GROUP: u16 = 11;
+pub const THREAD_NOT_SUSPENDED: u16 = 13;
+pub const INVALID_OBJECT: u16 = 20;
+pub const INVALID_CLASS: u16 = 21;
+pub const INVALID_METHODID: u16 = 23;
+pub const INVALID_FIELDID: u16 = 25;
+pub const INVALID_FRAMEID: u16 = 30;
+pub const INVALID_SLOT: u16 = 35;
+pub const NOT_IMPLEMENTED: u16 = 99;
+pub const ABSENT_INFORMATION: u16 = 101;
+pub const INVALID_EVENT_TYPE: u16 = 102;
+pub const ILLEGAL_ARGUMENT: u16 = 103;
+pub const INVALID_INDEX: u16 = 503;
+pub const INVALID_LENGTH: u16 = 504;
+
+pub type JdwpResult<T> = Result<T, u16>;
+
+// ID (全て8バイト)
+// スレッドはThreadオブジェクトのIDで表し、スレッドグループはオブジェクトを作らずに1つだけ用意する
+pub const THREAD_GROUP_ID: u64 = 1 << 40;
+
+pub fn object_id(object: Option<ObjRef>) -> u64 {
+  object.map_or(0, |object| object.0 as u64 + 1)
+}
+
+pub fn object_from_id(id: u64) -> Option<ObjRef> {
+  (id != 0 && id <= u32::MAX as u64 + 1).then(|| ObjRef((id - 1) as u32))
+}
+
+pub fn type_id(class: ClassId) -> u64 {
+  class as u64 + 1
+}
+
+pub fn class_from_id(id: u64) -> Option<ClassId> {
+  id.checked_sub(1).map(|class| class as ClassId)
+}
+
+pub fn method_id(method: usize) -> u64 {
+  method as u64 + 1
+}
+
+pub fn field_id(class: ClassId, index: usize) -> u64 {
+  ((class as u64) << 20 | index as u64) + 1
+}
+
+pub fn field_from_id(id: u64) -> Option<(ClassId, usize)> {
+  let id = id.checked_sub(1)?;
+  Some(((id >> 20) as ClassId, (id & 0xf_ffff) as usize))
+}
+
+pub struct Packet {
+  pub id: u32,
+  pub command_set: u8,
+  pub command: u8,
+  pub data: Vec<u8>,
+}
+
+// 応答以外のパケット (デバッガからのコマンド) を1つ読む
+pub fn read_command(input: &mut impl Read) -> io::Result<Option<Packet>> {
+  loop {
+    let mut header = [0; HEADER_LENGTH];
+    match input.read_exact(&mut header) {
+      Ok(()) => {},
+      Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+      Err(e) => return Err(e),
+    }
+    let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let mut data = vec![0; length.saturating_sub(HEADER_LENGTH)];
+    input.read_exact(&mut data)?;
+    // イベントには応答を求めないので、デバッガから応答が届くことはない
+    if header[8] & FLAG_REPLY != 0 {
+      continue;
+    }
+    return Ok(Some(Packet {
+      id: u32::from_be_bytes([header[4], header[5], header[6], header[7]]),
+      command_set: header[9],
+      command: header[10],
+      data,
+    }));
+  }
+}
+
+pub fn write_reply(output: &mut impl Write, id: u32, error: u16, data: &[u8]) -> io::Result<()> {
+  let mut packet = Vec::with_capacity(HEADER_LENGTH + data.len());
+  packet.extend_from_slice(&((HEADER_LENGTH + data.len()) as u32).to_be_bytes());
+  packet.extend_from_slice(&id.to_be_bytes());
+  packet.push(FLAG_REPLY);
+  packet.extend_from_slice(&error.to_be_bytes());
+  packet.extend_from_slice(data);
+  output.write_all(&packet)
+}
+
+pub fn write_command(output: &mut impl Write, id: u32, command_set: u8, command: u8, data: &[u8]) -> io::Result<()> {
+  let mut packet = Vec::with_capacity(HEADER_LENGTH + data.len());
+  packet.extend_from_slice(&((HEADER_LENGTH + data.len()) as u32).to_be_bytes());
+  packet.extend_from_slice(&id.to_be_bytes());
+  packet.push(0);
+  packet.push(command_set);
+  packet.push(command);
+  packet.extend_from_slice(data);
+  output.write_all(&packet)
+}
+
+pub struct PacketReader<'a> {
+  data: &'a [u8],
+  position: usize,
+}
+
+impl<'a> PacketReader<'a> {
+  pub fn new(data: &'a [u8]) -> Self {
+    PacketReader { data, position: 0 }
+  }
+
+  fn bytes<const N: usize>(&mut self) -> JdwpResult<[u8; N]> {
+    let bytes = self.data.get(self.position..self.position + N).ok_or(ILLEGAL_ARGUMENT)?;
+    self.position += N;
+    Ok(bytes.try_into().unwrap_or([0; N]))
+  }
+
+  pub fn u8(&mut self) -> JdwpResult<u8> {
+    Ok(self.bytes::<1>()?[0])
+  }
+
+  pub fn bool(&mut self) -> JdwpResult<bool> {
+    Ok(self.u8()? != 0)
+  }
+
+  pub fn i16(&mut self) -> JdwpResult<i16> {
+    Ok(i16::from_be_bytes(self.bytes()?))
+  }
+
+  pub fn i32(&mut self) -> JdwpResult<i32> {
+    Ok(i32::from_be_bytes(self.bytes()?))
+  }
+
+  pub fn i64(&mut self) -> JdwpResult<i64> {
+    Ok(i64::from_be_bytes(self.bytes()?))
+  }
+
+  pub fn id(&mut self) -> JdwpResult<u64> {
+    Ok(u64::from_be_bytes(self.bytes()?))
+  }
+
+  pub fn count(&mut self) -> JdwpResult<usize> {
+    usize::try_from(self.i32()?).map_err(|_| ILLEGAL_ARGUMENT)
+  }
+
+  pub fn string(&mut self) -> JdwpResult<String> {
+    let length = self.count()?;
+    let bytes = self.data.get(self.position..self.position + length).ok_or(ILLEGAL_ARGUMENT)?;
+    self.position += length;
+    String::from_utf8(bytes.to_vec()).map_err(|_| ILLEGAL_ARGUMENT)
+  }
+
+  // Location: 型のタグ、クラスID、メソッドID、位置
+  pub fn location(&mut self) -> JdwpResult<(u64, u64, u64)> {
+    self.u8()?;
+    Ok((self.id()?, self.id()?, self.id()?))
+  }
+}
+
+#[derive(Default)]
+pub struct PacketWriter {
+  pub data: Vec<u8>,
+}
+
+impl PacketWriter {
+  pub fn u8(&mut self, value: u8) -> &mut Self {
+    self.data.push(value);
+    self
+  }
+
+  pub fn bool(&mut self, value: bool) -> &mut Self {
+    self.u8(value as u8)
+  }
+
+  pub fn i16(&mut self, value: i16) -> &mut Self {
+    self.data.extend_from_slice(&value.to_be_bytes());
+    self
+  }
+
+  pub fn i32(&mut self, value: i32) -> &mut Self {
+    self.data.extend_from_slice(&value.to_be_bytes());
+    self
+  }
+
+  pub fn i64(&mut self, value: i64) -> &mut Self {
+    self.data.extend_from_slice(&value.to_be_bytes());
+    self
+  }
+
+  pub fn id(&mut self, value: u64) -> &mut Self {
+    self.data.extend_from_slice(&value.to_be_bytes());
+    self
+  }
+
+  pub fn count(&mut self, value: usize) -> &mut Self {
+    self.i32(value as i32)
+  }
+
+  pub fn string(&mut self, value: &str) -> &mut Self {
+    self.count(value.len());
+    self.data.extend_from_slice(value.as_bytes());
+    self
+  }
+
+  pub fn location(&mut self, tag: u8, class: ClassId, method: usize, pc: usize) -> &mut Self {
+    self.u8(tag).id(type_id(class)).id(method_id(method)).i64(pc as i64)
+  }
+}
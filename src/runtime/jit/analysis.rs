@@ -0,0 +1,382 @@
+use crate::{
+  runtime::value::{ObjRef, Value},
+  structure::{class::{Constant, ConstantPool}, code::instruction_length},
+  util::descriptor::{FieldType, MethodDescriptor},
+};
+
+// コンパイルしたコードのスロットに入っている値の型 (スロットは全て64ビットで、型はpcごとに決まる)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JitType {
+  Int,
+  Long,
+  Float,
+  Double,
+  Ref,
+  // 使われない値 (longやdoubleの上位スロット、合流で型が変わるローカル変数)
+  Top,
+}
+
+impl JitType {
+  pub fn of(field_type: &FieldType) -> JitType {
+    match field_type {
+      FieldType::Long => JitType::Long,
+      FieldType::Float => JitType::Float,
+      FieldType::Double => JitType::Double,
+      FieldType::Object(_) | FieldType::Array(_) => JitType::Ref,
+      _ => JitType::Int,
+    }
+  }
+
+  fn is_wide(self) -> bool {
+    matches!(self, JitType::Long | JitType::Double)
+  }
+
+  // インタプリタの値をスロットのビット列にする (参照はObjRef+1、nullは0)
+  pub fn bits(value: Value) -> u64 {
+    match value {
+      Value::Int(v) => v as i64 as u64,
+      Value::Long(v) => v as u64,
+      Value::Float(v) => v.to_bits() as u64,
+      Value::Double(v) => v.to_bits(),
+      Value::Ref(r) => r.0 as u64 + 1,
+      Value::Null | Value::ReturnAddress(_) | Value::Top => 0,
+    }
+  }
+
+  pub fn value(self, bits: u64) -> Value {
+    match self {
+      JitType::Int => Value::Int(bits as i32),
+      JitType::Long => Value::Long(bits as i64),
+      JitType::Float => Value::Float(f32::from_bits(bits as u32)),
+      JitType::Double => Value::Double(f64::from_bits(bits)),
+      JitType::Ref => match bits {
+        0 => Value::Null,
+        bits => Value::Ref(ObjRef((bits - 1) as u32)),
+      },
+      JitType::Top => Value::Top,
+    }
+  }
+}
+
+// 命令を実行する前のローカル変数とオペランドスタックの型
+#[derive(Debug, Clone, PartialEq)]
+pub struct State {
+  pub locals: Vec<JitType>,
+  pub stack: Vec<JitType>,
+}
+
+impl State {
+  fn pop(&mut self) -> Result<JitType, String> {
+    self.stack.pop().ok_or_else(|| "operand stack underflow".to_string())
+  }
+
+  fn pop_n(&mut self, count: usize) -> Result<(), String> {
+    for _ in 0..count {
+      self.pop()?;
+    }
+    Ok(())
+  }
+
+  fn set_local(&mut self, index: usize, value: JitType) -> Result<(), String> {
+    let end = index + if value.is_wide() { 2 } else { 1 };
+    if end > self.locals.len() {
+      return Err(format!("invalid local variable index {}", index));
+    }
+    // longやdoubleの上位スロットを上書きしたら、その値は使えなくなる
+    if index > 0 && self.locals[index - 1].is_wide() {
+      self.locals[index - 1] = JitType::Top;
+    }
+    self.locals[index] = value;
+    if value.is_wide() {
+      self.locals[index + 1] = JitType::Top;
+    }
+    Ok(())
+  }
+
+  // 合流する位置の型 (ローカル変数は違えば使えなくし、スタックは一致しなければコンパイルしない)
+  fn merge(&mut self, other: &State) -> Result<bool, String> {
+    if self.stack != other.stack {
+      return Err("operand stack types differ at a merge point".to_string());
+    }
+    let mut changed = false;
+    for (local, other) in self.locals.iter_mut().zip(&other.locals) {
+      if *local != *other && *local != JitType::Top {
+        *local = JitType::Top;
+        changed = true;
+      }
+    }
+    Ok(changed)
+  }
+}
+
+fn read_u8(code: &[u8], pc: usize) -> Result<u8, String> {
+  code.get(pc).copied().ok_or_else(|| format!("pc out of range: {}", pc))
+}
+
+fn read_u16(code: &[u8], pc: usize) -> Result<u16, String> {
+  Ok(((read_u8(code, pc)? as u16) << 8) | read_u8(code, pc + 1)? as u16)
+}
+
+pub fn read_i16(code: &[u8], pc: usize) -> Result<i16, String> {
+  Ok(read_u16(code, pc)? as i16)
+}
+
+pub fn read_i32(code: &[u8], pc: usize) -> Result<i32, String> {
+  Ok(((read_u16(code, pc)? as u32) << 16 | read_u16(code, pc + 2)? as u32) as i32)
+}
+
+pub fn branch_target(pc: usize, offset: i32) -> Result<usize, String> {
+  usize::try_from(pc as i64 + offset as i64).map_err(|_| format!("invalid branch target at {}", pc))
+}
+
+// tableswitchとlookupswitchの (default, [(キー, 飛び先)])
+pub fn switch_targets(code: &[u8], pc: usize) -> Result<(usize, Vec<(i32, usize)>), String> {
+  let base = pc + 1 + (4 - (pc + 1) % 4) % 4;
+  let default = branch_target(pc, read_i32(code, base)?)?;
+  let mut cases = Vec::new();
+  if read_u8(code, pc)? == 0xaa {
+    let low = read_i32(code, base + 4)?;
+    let high = read_i32(code, base + 8)?;
+    if high < low {
+      return Err(format!("invalid tableswitch at {}", pc));
+    }
+    for (i, key) in (low..=high).enumerate() {
+      cases.push((key, branch_target(pc, read_i32(code, base + 12 + i * 4)?)?));
+    }
+  } else {
+    let npairs = read_i32(code, base + 4)?.max(0) as usize;
+    for i in 0..npairs {
+      let entry = base + 8 + i * 8;
+      cases.push((read_i32(code, entry)?, branch_target(pc, read_i32(code, entry + 4)?)?));
+    }
+  }
+  Ok((default, cases))
+}
+
+// スタックを並べ替える命令 (pop, dup, swap系) が取り除く値の数と、代わりに積む値 (取り除いた値の深い方からの位置)
+pub fn shuffle(opcode: u8, stack: &[JitType]) -> Result<(usize, Vec<usize>), String> {
+  let wide = |depth: usize| -> Result<bool, String> {
+    stack.len().checked_sub(depth).map(|i| stack[i].is_wide()).ok_or_else(|| "operand stack underflow".to_string())
+  };
+  Ok(match opcode {
+    0x57 => (1, vec![]),
+    0x58 => if wide(1)? { (1, vec![]) } else { (2, vec![]) },
+    0x59 => (1, vec![0, 0]),
+    0x5a => (2, vec![1, 0, 1]),
+    0x5b => if wide(2)? { (2, vec![1, 0, 1]) } else { (3, vec![2, 0, 1, 2]) },
+    0x5c => if wide(1)? { (1, vec![0, 0]) } else { (2, vec![0, 1, 0, 1]) },
+    0x5d => if wide(1)? { (2, vec![1, 0, 1]) } else { (3, vec![1, 2, 0, 1, 2]) },
+    0x5e => match (wide(1)?, wide(2)?) {
+      (true, true) => (2, vec![1, 0, 1]),
+      (true, false) => (3, vec![2, 0, 1, 2]),
+      (false, _) if wide(3)? => (3, vec![1, 2, 0, 1, 2]),
+      (false, _) => (4, vec![2, 3, 0, 1, 2, 3]),
+    },
+    _ => (2, vec![1, 0]),
+  })
+}
+
+fn constant_type(pool: &ConstantPool, index: u16) -> Result<JitType, String> {
+  Ok(match pool.get_class(index)? {
+    Constant::Integer { .. } => JitType::Int,
+    Constant::Float { .. } => JitType::Float,
+    Constant::Long { .. } => JitType::Long,
+    Constant::Double { .. } => JitType::Double,
+    Constant::Dynamic { name_and_type_index, .. } => {
+      let (_, descriptor) = pool.get_name_and_type(*name_and_type_index)?;
+      JitType::of(&FieldType::parse(&descriptor)?)
+    },
+    _ => JitType::Ref,
+  })
+}
+
+fn field_type(pool: &ConstantPool, index: u16) -> Result<JitType, String> {
+  let (_, _, descriptor) = pool.get_member_ref(index)?;
+  Ok(JitType::of(&FieldType::parse(&descriptor)?))
+}
+
+fn invoke_effect(state: &mut State, descriptor: &str, receiver: bool) -> Result<(), String> {
+  let descriptor = MethodDescriptor::parse(descriptor)?;
+  state.pop_n(descriptor.parameters.len() + receiver as usize)?;
+  if let Some(return_type) = &descriptor.return_type {
+    state.stack.push(JitType::of(return_type));
+  }
+  Ok(())
+}
+
+// 命令を実行した後の型と、次に実行する位置
+fn transfer(code: &[u8], pc: usize, pool: &ConstantPool, state: &State) -> Result<(State, Vec<usize>), String> {
+  let opcode = read_u8(code, pc)?;
+  let length = instruction_length(code, pc).ok_or_else(|| format!("truncated instruction at {}", pc))?;
+  let mut state = state.clone();
+  let mut next = vec![pc + length];
+  let kinds = [JitType::Int, JitType::Long, JitType::Float, JitType::Double, JitType::Ref];
+  let s = &mut state;
+  match opcode {
+    0x00 => {},
+    0x01 => s.stack.push(JitType::Ref),
+    0x02..=0x08 | 0x10 | 0x11 => s.stack.push(JitType::Int),
+    0x09 | 0x0a => s.stack.push(JitType::Long),
+    0x0b..=0x0d => s.stack.push(JitType::Float),
+    0x0e | 0x0f => s.stack.push(JitType::Double),
+    0x12 => s.stack.push(constant_type(pool, read_u8(code, pc + 1)? as u16)?),
+    0x13 | 0x14 => s.stack.push(constant_type(pool, read_u16(code, pc + 1)?)?),
+    0x15..=0x19 => s.stack.push(kinds[(opcode - 0x15) as usize]),
+    0x1a..=0x2d => s.stack.push(kinds[((opcode - 0x1a) / 4) as usize]),
+    0x2e..=0x35 => {
+      s.pop_n(2)?;
+      s.stack.push(match opcode {
+        0x2f => JitType::Long,
+        0x30 => JitType::Float,
+        0x31 => JitType::Double,
+        0x32 => JitType::Ref,
+        _ => JitType::Int,
+      });
+    },
+    0x36..=0x3a => {
+      let value = s.pop()?;
+      s.set_local(read_u8(code, pc + 1)? as usize, value)?;
+    },
+    0x3b..=0x4e => {
+      let value = s.pop()?;
+      s.set_local(((opcode - 0x3b) % 4) as usize, value)?;
+    },
+    0x4f..=0x56 => s.pop_n(3)?,
+    0x57..=0x5f => {
+      let (consumed, pushed) = shuffle(opcode, &s.stack)?;
+      let at = s.stack.len().checked_sub(consumed).ok_or("operand stack underflow")?;
+      let taken = s.stack.split_off(at);
+      s.stack.extend(pushed.iter().map(|&i| taken[i]));
+    },
+    0x60..=0x73 => {
+      s.pop_n(2)?;
+      s.stack.push(kinds[((opcode - 0x60) % 4) as usize]);
+    },
+    0x74..=0x77 => {
+      s.pop()?;
+      s.stack.push(kinds[((opcode - 0x74) % 4) as usize]);
+    },
+    0x78..=0x83 => {
+      s.pop_n(2)?;
+      s.stack.push(kinds[((opcode - 0x78) % 2) as usize]);
+    },
+    0x84 => {},
+    0x85..=0x93 => {
+      s.pop()?;
+      s.stack.push(match opcode {
+        0x85 | 0x8c | 0x8f => JitType::Long,
+        0x86 | 0x89 | 0x90 => JitType::Float,
+        0x87 | 0x8a | 0x8d => JitType::Double,
+        _ => JitType::Int,
+      });
+    },
+    0x94..=0x98 => {
+      s.pop_n(2)?;
+      s.stack.push(JitType::Int);
+    },
+    0x99..=0x9e | 0xc6 | 0xc7 => {
+      s.pop()?;
+      next.push(branch_target(pc, read_i16(code, pc + 1)? as i32)?);
+    },
+    0x9f..=0xa6 => {
+      s.pop_n(2)?;
+      next.push(branch_target(pc, read_i16(code, pc + 1)? as i32)?);
+    },
+    0xa7 => next = vec![branch_target(pc, read_i16(code, pc + 1)? as i32)?],
+    0xc8 => next = vec![branch_target(pc, read_i32(code, pc + 1)?)?],
+    0xaa | 0xab => {
+      s.pop()?;
+      let (default, cases) = switch_targets(code, pc)?;
+      next = cases.into_iter().map(|(_, target)| target).chain([default]).collect();
+    },
+    0xac..=0xb1 | 0xbf => next.clear(),
+    0xb2 => s.stack.push(field_type(pool, read_u16(code, pc + 1)?)?),
+    0xb3 => s.pop_n(1)?,
+    0xb4 => {
+      s.pop()?;
+      s.stack.push(field_type(pool, read_u16(code, pc + 1)?)?);
+    },
+    0xb5 => s.pop_n(2)?,
+    0xb6..=0xb9 => {
+      let (_, _, descriptor) = pool.get_member_ref(read_u16(code, pc + 1)?)?;
+      invoke_effect(s, &descriptor, opcode != 0xb8)?;
+    },
+    0xba => {
+      let descriptor = match pool.get_class(read_u16(code, pc + 1)?)? {
+        Constant::InvokeDynamic { name_and_type_index, .. } => pool.get_name_and_type(*name_and_type_index)?.1,
+        c => return Err(format!("expected InvokeDynamic constant, found {:?}", c)),
+      };
+      invoke_effect(s, &descriptor, false)?;
+    },
+    0xbb => s.stack.push(JitType::Ref),
+    0xbc | 0xbd | 0xc0 => {
+      s.pop()?;
+      s.stack.push(JitType::Ref);
+    },
+    0xbe | 0xc1 => {
+      s.pop()?;
+      s.stack.push(JitType::Int);
+    },
+    0xc2 | 0xc3 => s.pop_n(1)?,
+    0xc4 => {
+      let modified = read_u8(code, pc + 1)?;
+      let index = read_u16(code, pc + 2)? as usize;
+      match modified {
+        0x15..=0x19 => s.stack.push(kinds[(modified - 0x15) as usize]),
+        0x36..=0x3a => {
+          let value = s.pop()?;
+          s.set_local(index, value)?;
+        },
+        0x84 => {},
+        _ => return Err(format!("unsupported wide opcode 0x{:02x}", modified)),
+      }
+    },
+    0xc5 => {
+      s.pop_n(read_u8(code, pc + 3)? as usize)?;
+      s.stack.push(JitType::Ref);
+    },
+    // jsrとretはサブルーチンの戻り先を値として扱うのでコンパイルしない
+    0xa8 | 0xa9 | 0xc9 => return Err("jsr/ret is not supported".to_string()),
+    _ => return Err(format!("unknown opcode 0x{:02x}", opcode)),
+  }
+  Ok((state, next))
+}
+
+// pc 0から通常の実行で到達する命令の型を求める (例外ハンドラはインタプリタで実行するので辿らない)
+pub fn analyze(code: &[u8], max_locals: usize, parameters: &[JitType], pool: &ConstantPool) -> Result<Vec<Option<State>>, String> {
+  let mut locals = vec![JitType::Top; max_locals];
+  let mut index = 0;
+  for &parameter in parameters {
+    if index >= max_locals {
+      return Err("parameters exceed max_locals".to_string());
+    }
+    locals[index] = parameter;
+    index += if parameter.is_wide() { 2 } else { 1 };
+  }
+  let mut states: Vec<Option<State>> = vec![None; code.len()];
+  states[0] = Some(State { locals, stack: Vec::new() });
+  let mut work = vec![0];
+  while let Some(pc) = work.pop() {
+    let Some(state) = states[pc].clone() else {
+      continue;
+    };
+    let (after, next) = transfer(code, pc, pool, &state)?;
+    for target in next {
+      if target >= code.len() {
+        return Err(format!("control falls off the code at {}", pc));
+      }
+      match &mut states[target] {
+        Some(existing) => {
+          if existing.merge(&after)? {
+            work.push(target);
+          }
+        },
+        slot => {
+          *slot = Some(after.clone());
+          work.push(target);
+        },
+      }
+    }
+  }
+  Ok(states)
+}
@@ -0,0 +1,365 @@
+use std::{ffi::{c_int, c_long, c_void}, ptr};
+
+// x86-64の汎用レジスタ (番号はModR/Mでの値)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reg {
+  Rax = 0,
+  Rcx = 1,
+  Rdx = 2,
+  Rbx = 3,
+  Rbp = 5,
+  Rsi = 6,
+  Rdi = 7,
+  R8 = 8,
+  R12 = 12,
+}
+
+impl Reg {
+  fn low(self) -> u8 {
+    self as u8 & 7
+  }
+
+  fn high(self) -> u8 {
+    (self as u8 >> 3) & 1
+  }
+}
+
+// 条件分岐の条件コード
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cond {
+  Equal = 0x4,
+  NotEqual = 0x5,
+  Less = 0xc,
+  GreaterEqual = 0xd,
+  LessEqual = 0xe,
+  Greater = 0xf,
+}
+
+// 2つのレジスタを取る演算 (オペコードは"op r/m, reg"の形)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AluOp {
+  Add = 0x01,
+  Or = 0x09,
+  And = 0x21,
+  Sub = 0x29,
+  Xor = 0x31,
+  Cmp = 0x39,
+}
+
+// シフトの種類 (D3 /n のn)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShiftOp {
+  Left = 4,
+  LogicalRight = 5,
+  ArithmeticRight = 7,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Label(usize);
+
+#[derive(Default)]
+pub struct Assembler {
+  code: Vec<u8>,
+  labels: Vec<Option<usize>>,
+  // (rel32を書き込む位置, 飛び先)
+  fixups: Vec<(usize, Label)>,
+}
+
+impl Assembler {
+  pub fn new_label(&mut self) -> Label {
+    self.labels.push(None);
+    Label(self.labels.len() - 1)
+  }
+
+  pub fn bind(&mut self, label: Label) {
+    self.labels[label.0] = Some(self.code.len());
+  }
+
+  fn rex(&mut self, wide: bool, reg: u8, base: u8) {
+    let rex = 0x40 | (wide as u8) << 3 | reg << 2 | base;
+    if rex != 0x40 {
+      self.code.push(rex);
+    }
+  }
+
+  fn imm32(&mut self, value: i32) {
+    self.code.extend_from_slice(&value.to_le_bytes());
+  }
+
+  // [base + disp32] のModR/M (r12はSIBが必要)
+  fn memory(&mut self, reg: u8, base: Reg, disp: i32) {
+    self.code.push(0x80 | (reg & 7) << 3 | base.low());
+    if base.low() == 4 {
+      self.code.push(0x24);
+    }
+    self.imm32(disp);
+  }
+
+  fn registers(&mut self, reg: u8, rm: Reg) {
+    self.code.push(0xc0 | (reg & 7) << 3 | rm.low());
+  }
+
+  // mov reg, [base + disp]
+  pub fn load(&mut self, reg: Reg, base: Reg, disp: i32) {
+    self.rex(true, reg.high(), base.high());
+    self.code.push(0x8b);
+    self.memory(reg.low(), base, disp);
+  }
+
+  // mov [base + disp], reg
+  pub fn store(&mut self, base: Reg, disp: i32, reg: Reg) {
+    self.rex(true, reg.high(), base.high());
+    self.code.push(0x89);
+    self.memory(reg.low(), base, disp);
+  }
+
+  // sub qword [base + disp], imm8
+  pub fn sub_memory(&mut self, base: Reg, disp: i32, value: i8) {
+    self.rex(true, 0, base.high());
+    self.code.push(0x83);
+    self.memory(5, base, disp);
+    self.code.push(value as u8);
+  }
+
+  pub fn mov_imm(&mut self, reg: Reg, value: u64) {
+    if value <= u32::MAX as u64 {
+      // 32ビットの書き込みは上位をゼロにする
+      self.rex(false, 0, reg.high());
+      self.code.push(0xb8 + reg.low());
+      self.imm32(value as u32 as i32);
+    } else {
+      self.rex(true, 0, reg.high());
+      self.code.push(0xb8 + reg.low());
+      self.code.extend_from_slice(&value.to_le_bytes());
+    }
+  }
+
+  pub fn mov(&mut self, dst: Reg, src: Reg) {
+    self.rex(true, src.high(), dst.high());
+    self.code.push(0x89);
+    self.registers(src.low(), dst);
+  }
+
+  pub fn alu(&mut self, op: AluOp, wide: bool, dst: Reg, src: Reg) {
+    self.rex(wide, src.high(), dst.high());
+    self.code.push(op as u8);
+    self.registers(src.low(), dst);
+  }
+
+  // op reg, imm32 (81 /n)
+  pub fn alu_imm(&mut self, op: AluOp, wide: bool, reg: Reg, value: i32) {
+    let extension = match op {
+      AluOp::Add => 0,
+      AluOp::Or => 1,
+      AluOp::And => 4,
+      AluOp::Sub => 5,
+      AluOp::Xor => 6,
+      AluOp::Cmp => 7,
+    };
+    self.rex(wide, 0, reg.high());
+    self.code.push(0x81);
+    self.registers(extension, reg);
+    self.imm32(value);
+  }
+
+  pub fn imul(&mut self, wide: bool, dst: Reg, src: Reg) {
+    self.rex(wide, dst.high(), src.high());
+    self.code.extend_from_slice(&[0x0f, 0xaf]);
+    self.registers(dst.low(), src);
+  }
+
+  pub fn test(&mut self, wide: bool, a: Reg, b: Reg) {
+    self.rex(wide, b.high(), a.high());
+    self.code.push(0x85);
+    self.registers(b.low(), a);
+  }
+
+  pub fn neg(&mut self, wide: bool, reg: Reg) {
+    self.rex(wide, 0, reg.high());
+    self.code.push(0xf7);
+    self.registers(3, reg);
+  }
+
+  // edx:eax (rdx:rax) をregで割る
+  pub fn idiv(&mut self, wide: bool, reg: Reg) {
+    self.rex(wide, 0, 0);
+    self.code.push(0x99);
+    self.rex(wide, 0, reg.high());
+    self.code.push(0xf7);
+    self.registers(7, reg);
+  }
+
+  // clの値でシフトする (32ビットなら下位5ビット、64ビットなら下位6ビットだけ使うのはJavaと同じ)
+  pub fn shift(&mut self, op: ShiftOp, wide: bool, reg: Reg) {
+    self.rex(wide, 0, reg.high());
+    self.code.push(0xd3);
+    self.registers(op as u8, reg);
+  }
+
+  // movsxd dst, src32
+  pub fn sign_extend_32(&mut self, dst: Reg, src: Reg) {
+    self.rex(true, dst.high(), src.high());
+    self.code.push(0x63);
+    self.registers(dst.low(), src);
+  }
+
+  // movsx/movzx dst32, src8/src16 (rax, rcx, rdx, rbxの下位だけ使う)
+  pub fn extend(&mut self, signed: bool, bits16: bool, dst: Reg, src: Reg) {
+    self.code.push(0x0f);
+    self.code.push(match (signed, bits16) {
+      (true, false) => 0xbe,
+      (true, true) => 0xbf,
+      (false, false) => 0xb6,
+      (false, true) => 0xb7,
+    });
+    self.registers(dst.low(), src);
+  }
+
+  // setcc reg8
+  pub fn set(&mut self, cond: Cond, reg: Reg) {
+    self.code.extend_from_slice(&[0x0f, 0x90 + cond as u8]);
+    self.registers(0, reg);
+  }
+
+  fn rel32(&mut self, label: Label) {
+    self.fixups.push((self.code.len(), label));
+    self.imm32(0);
+  }
+
+  pub fn jump(&mut self, label: Label) {
+    self.code.push(0xe9);
+    self.rel32(label);
+  }
+
+  pub fn jump_if(&mut self, cond: Cond, label: Label) {
+    self.code.extend_from_slice(&[0x0f, 0x80 + cond as u8]);
+    self.rel32(label);
+  }
+
+  pub fn call(&mut self, reg: Reg) {
+    self.rex(false, 0, reg.high());
+    self.code.push(0xff);
+    self.registers(2, reg);
+  }
+
+  pub fn push(&mut self, reg: Reg) {
+    self.rex(false, 0, reg.high());
+    self.code.push(0x50 + reg.low());
+  }
+
+  pub fn pop(&mut self, reg: Reg) {
+    self.rex(false, 0, reg.high());
+    self.code.push(0x58 + reg.low());
+  }
+
+  pub fn ret(&mut self) {
+    self.code.push(0xc3);
+  }
+
+  // 飛び先を埋めた機械語
+  pub fn finish(mut self) -> Result<Vec<u8>, String> {
+    for (at, label) in &self.fixups {
+      let target = self.labels[label.0].ok_or("unbound label")?;
+      let offset = target as i64 - (*at as i64 + 4);
+      self.code[*at..*at + 4].copy_from_slice(&(offset as i32).to_le_bytes());
+    }
+    Ok(self.code)
+  }
+}
+
+const PROT_READ: c_int = 0x1;
+const PROT_WRITE: c_int = 0x2;
+const PROT_EXEC: c_int = 0x4;
+const MAP_PRIVATE: c_int = 0x02;
+const MAP_ANONYMOUS: c_int = 0x20;
+
+unsafe extern "C" {
+  fn mmap(address: *mut c_void, length: usize, protection: c_int, flags: c_int, fd: c_int, offset: c_long) -> *mut c_void;
+  fn mprotect(address: *mut c_void, length: usize, protection: c_int) -> c_int;
+  fn munmap(address: *mut c_void, length: usize) -> c_int;
+}
+
+// 実行できるメモリに置いた機械語 (書き込みと実行を同時には許さない)
+pub struct ExecutableCode {
+  address: *mut c_void,
+  length: usize,
+}
+
+impl ExecutableCode {
+  pub fn new(code: &[u8]) -> Result<ExecutableCode, String> {
+    let length = code.len().max(1);
+    let address = unsafe { mmap(ptr::null_mut(), length, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0) };
+    if address as isize == -1 {
+      return Err("mmap failed".to_string());
+    }
+    let executable = ExecutableCode { address, length };
+    unsafe {
+      ptr::copy_nonoverlapping(code.as_ptr(), address as *mut u8, code.len());
+      if mprotect(address, length, PROT_READ | PROT_EXEC) != 0 {
+        return Err("mprotect failed".to_string());
+      }
+    }
+    Ok(executable)
+  }
+
+  pub fn address(&self) -> *const u8 {
+    self.address as *const u8
+  }
+}
+
+impl Drop for ExecutableCode {
+  fn drop(&mut self) {
+    unsafe { munmap(self.address, self.length) };
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn assemble(emit: impl FnOnce(&mut Assembler)) -> Vec<u8> {
+    let mut asm = Assembler::default();
+    emit(&mut asm);
+    asm.finish().unwrap()
+  }
+
+  #[test]
+  fn rex_prefix_is_emitted_only_when_needed() {
+    assert_eq!(assemble(|asm| asm.alu(AluOp::Add, false, Reg::Rax, Reg::Rcx)), [0x01, 0xc8]);
+    assert_eq!(assemble(|asm| asm.alu(AluOp::Add, true, Reg::Rax, Reg::Rcx)), [0x48, 0x01, 0xc8]);
+    // mov r8, rax (REX.W + REX.B)
+    assert_eq!(assemble(|asm| asm.mov(Reg::R8, Reg::Rax)), [0x49, 0x89, 0xc0]);
+    // imul r8, rcx (REX.W + REX.R)
+    assert_eq!(assemble(|asm| asm.imul(true, Reg::R8, Reg::Rcx)), [0x4c, 0x0f, 0xaf, 0xc1]);
+    assert_eq!(assemble(|asm| asm.push(Reg::R12)), [0x41, 0x54]);
+    assert_eq!(assemble(|asm| asm.pop(Reg::Rbx)), [0x5b]);
+    assert_eq!(assemble(|asm| asm.mov_imm(Reg::Rcx, 5)), [0xb9, 5, 0, 0, 0]);
+    assert_eq!(assemble(|asm| asm.mov_imm(Reg::R8, 1 << 32)), [0x49, 0xb8, 0, 0, 0, 0, 1, 0, 0, 0]);
+  }
+
+  #[test]
+  fn r12_base_needs_sib() {
+    // mov rax, [r12 + 16]
+    assert_eq!(assemble(|asm| asm.load(Reg::Rax, Reg::R12, 16)), [0x49, 0x8b, 0x84, 0x24, 16, 0, 0, 0]);
+    // mov [r12 - 8], r8
+    assert_eq!(assemble(|asm| asm.store(Reg::R12, -8, Reg::R8)), [0x4d, 0x89, 0x84, 0x24, 0xf8, 0xff, 0xff, 0xff]);
+    // sub qword [r12], 1
+    assert_eq!(assemble(|asm| asm.sub_memory(Reg::R12, 0, 1)), [0x49, 0x83, 0xac, 0x24, 0, 0, 0, 0, 1]);
+    // rbpはSIBを使わない
+    assert_eq!(assemble(|asm| asm.load(Reg::Rdx, Reg::Rbp, 8)), [0x48, 0x8b, 0x95, 8, 0, 0, 0]);
+  }
+
+  #[test]
+  fn labels_are_patched_relative_to_next_instruction() {
+    let code = assemble(|asm| {
+      let start = asm.new_label();
+      let end = asm.new_label();
+      asm.bind(start);
+      asm.jump_if(Cond::Equal, end);
+      asm.jump(start);
+      asm.bind(end);
+      asm.ret();
+    });
+    assert_eq!(code, [0x0f, 0x84, 5, 0, 0, 0, 0xe9, 0xf5, 0xff, 0xff, 0xff, 0xc3]);
+  }
+}
@@ -0,0 +1,508 @@
+use std::{collections::HashMap, mem};
+
+use crate::{
+  runtime::{
+    class::ClassId,
+    jit::{
+      analysis::{branch_target, read_i16, read_i32, shuffle, switch_targets, JitType, State},
+      assembler::{AluOp, Assembler, Cond, ExecutableCode, Label, Reg, ShiftOp},
+      helpers,
+      JitContext,
+    },
+    memory::MemoryOrder,
+  },
+  structure::class::{Constant, ConstantPool},
+};
+
+// コンパイルしたコードから戻る理由 (戻り値の下位2ビット、上位はpc)
+pub const EXIT_RETURN: u64 = 0;
+pub const EXIT_DEOPTIMIZE: u64 = 1;
+pub const EXIT_EXCEPTION: u64 = 2;
+
+pub fn exit_status(kind: u64, pc: usize) -> u64 {
+  (pc as u64) << 2 | kind
+}
+
+// 解決済みのフィールド (コンパイルした時点で解決と初期化が済んでいるものだけ直接読み書きする)
+#[derive(Debug, Clone, Copy)]
+pub enum FieldLocation {
+  Instance(usize),
+  Static(ClassId, usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FieldAccess {
+  pub location: FieldLocation,
+  pub order: MemoryOrder,
+}
+
+pub type CompiledEntry = unsafe extern "C" fn(*mut JitContext, *mut u64) -> u64;
+
+pub struct CompiledMethod {
+  code: ExecutableCode,
+  pub code_size: usize,
+  // 命令を実行する前の型 (インタプリタに戻る時にスロットを値に戻すのに使う)
+  pub states: Vec<Option<State>>,
+  pub max_locals: usize,
+  pub slots: usize,
+  pub return_type: Option<JitType>,
+  pub fields: HashMap<usize, FieldAccess>,
+}
+
+impl CompiledMethod {
+  pub fn entry(&self) -> CompiledEntry {
+    unsafe { mem::transmute::<*const u8, CompiledEntry>(self.code.address()) }
+  }
+}
+
+// 命令ごとにレジスタとスロットを使う機械語のテンプレートを並べる
+// rbxはスロットの先頭 (ローカル変数、オペランドスタックの順)、r12はJitContextを指す
+struct Compiler<'a> {
+  asm: Assembler,
+  code: &'a [u8],
+  pool: &'a ConstantPool,
+  fields: &'a HashMap<usize, FieldAccess>,
+  max_locals: usize,
+  labels: Vec<Option<Label>>,
+  // 分岐の先に置く、インタプリタへ戻るコード
+  exits: Vec<(Label, u64)>,
+  epilogue: Label,
+}
+
+impl Compiler<'_> {
+  fn local(&self, index: usize) -> i32 {
+    (index * 8) as i32
+  }
+
+  fn stack(&self, index: usize) -> i32 {
+    ((self.max_locals + index) * 8) as i32
+  }
+
+  fn label(&self, pc: usize) -> Result<Label, String> {
+    self.labels.get(pc).copied().flatten().ok_or_else(|| format!("branch to unreachable code at {}", pc))
+  }
+
+  fn exit(&mut self, kind: u64, pc: usize) -> Label {
+    let label = self.asm.new_label();
+    self.exits.push((label, exit_status(kind, pc)));
+    label
+  }
+
+  fn exit_now(&mut self, kind: u64, pc: usize) {
+    self.asm.mov_imm(Reg::Rax, exit_status(kind, pc));
+    self.asm.jump(self.epilogue);
+  }
+
+  fn load(&mut self, reg: Reg, disp: i32) {
+    self.asm.load(reg, Reg::Rbx, disp);
+  }
+
+  fn store(&mut self, disp: i32, reg: Reg) {
+    self.asm.store(Reg::Rbx, disp, reg);
+  }
+
+  fn call(&mut self, function: *const ()) {
+    self.asm.mov_imm(Reg::Rax, function as u64);
+    self.asm.call(Reg::Rax);
+  }
+
+  // 第1引数にJitContext、第2引数にpcを渡してランタイムの関数を呼ぶ
+  fn call_runtime(&mut self, function: *const (), pc: usize) {
+    self.asm.mov(Reg::Rdi, Reg::R12);
+    self.asm.mov_imm(Reg::Rsi, pc as u64);
+    self.call(function);
+  }
+
+  // 失敗 (戻り値が0以外) ならインタプリタに戻る
+  fn check(&mut self, kind: u64, pc: usize) {
+    let exit = self.exit(kind, pc);
+    self.asm.test(true, Reg::Rax, Reg::Rax);
+    self.asm.jump_if(Cond::NotEqual, exit);
+  }
+
+  fn load_result(&mut self, disp: i32) {
+    self.asm.load(Reg::Rax, Reg::R12, mem::offset_of!(JitContext, result) as i32);
+    self.store(disp, Reg::Rax);
+  }
+
+  // 後ろ向きの分岐で、一定の回数ごとに他のスレッドに実行権を譲る
+  fn safepoint(&mut self, pc: usize) {
+    let skip = self.asm.new_label();
+    self.asm.sub_memory(Reg::R12, mem::offset_of!(JitContext, budget) as i32, 1);
+    self.asm.jump_if(Cond::Greater, skip);
+    self.call_runtime(helpers::jit_safepoint as *const (), pc);
+    self.asm.bind(skip);
+  }
+
+  fn branch(&mut self, pc: usize, targets: &[usize]) {
+    if targets.iter().any(|target| *target <= pc) {
+      self.safepoint(pc);
+    }
+  }
+
+  // 浮動小数点数の演算と変換はRustの関数で行う (Javaの変換規則はasと同じ)
+  fn arithmetic(&mut self, opcode: u8, depth: usize, operands: usize) {
+    self.asm.mov_imm(Reg::Rdi, opcode as u64);
+    self.load(Reg::Rsi, self.stack(depth - operands));
+    if operands == 2 {
+      self.load(Reg::Rdx, self.stack(depth - 1));
+    }
+    self.call(helpers::jit_arithmetic as *const ());
+    self.store(self.stack(depth - operands), Reg::Rax);
+  }
+
+  fn division(&mut self, pc: usize, depth: usize, wide: bool, remainder: bool) {
+    let divide = self.asm.new_label();
+    let done = self.asm.new_label();
+    let zero = self.exit(EXIT_DEOPTIMIZE, pc);
+    self.load(Reg::Rax, self.stack(depth - 2));
+    self.load(Reg::Rcx, self.stack(depth - 1));
+    self.asm.test(wide, Reg::Rcx, Reg::Rcx);
+    self.asm.jump_if(Cond::Equal, zero);
+    // MIN / -1はx86では例外になるので、Javaの結果 (MINと0) を直接求める
+    self.asm.alu_imm(AluOp::Cmp, wide, Reg::Rcx, -1);
+    self.asm.jump_if(Cond::NotEqual, divide);
+    if remainder {
+      self.asm.mov_imm(Reg::Rax, 0);
+    } else {
+      self.asm.neg(wide, Reg::Rax);
+    }
+    self.asm.jump(done);
+    self.asm.bind(divide);
+    self.asm.idiv(wide, Reg::Rcx);
+    if remainder {
+      self.asm.mov(Reg::Rax, Reg::Rdx);
+    }
+    self.asm.bind(done);
+    self.store(self.stack(depth - 2), Reg::Rax);
+  }
+
+  fn binary(&mut self, op: AluOp, wide: bool, depth: usize) {
+    self.load(Reg::Rax, self.stack(depth - 2));
+    self.load(Reg::Rcx, self.stack(depth - 1));
+    self.asm.alu(op, wide, Reg::Rax, Reg::Rcx);
+    self.store(self.stack(depth - 2), Reg::Rax);
+  }
+
+  fn constant(&mut self, value: u64, depth: usize) {
+    self.asm.mov_imm(Reg::Rax, value);
+    self.store(self.stack(depth), Reg::Rax);
+  }
+
+  // ldcで読み込む数値の定数 (文字列やクラスはランタイムで解決する)
+  fn numeric_constant(&self, index: u16) -> Option<u64> {
+    Some(match self.pool.get_class(index).ok()? {
+      Constant::Integer { bytes } => *bytes as i32 as i64 as u64,
+      Constant::Float { bytes } => *bytes as u64,
+      Constant::Long { high_bytes, low_bytes } | Constant::Double { high_bytes, low_bytes } => {
+        (*high_bytes as u64) << 32 | *low_bytes as u64
+      },
+      _ => return None,
+    })
+  }
+
+  // 比較してから条件分岐する (セーフポイントのチェックはフラグを壊すので比較より前に置く)
+  fn compare_branch(&mut self, pc: usize, cond: Cond, compare: impl FnOnce(&mut Self)) -> Result<(), String> {
+    let target = branch_target(pc, read_i16(self.code, pc + 1)? as i32)?;
+    self.branch(pc, &[target]);
+    let label = self.label(target)?;
+    compare(self);
+    self.asm.jump_if(cond, label);
+    Ok(())
+  }
+
+  // インタプリタで1命令だけ実行する (呼び出しやオブジェクトの生成など)
+  fn interpret(&mut self, pc: usize) {
+    self.call_runtime(helpers::jit_interpret as *const (), pc);
+    self.check(EXIT_EXCEPTION, pc);
+  }
+
+  fn instruction(&mut self, pc: usize, state: &State) -> Result<(), String> {
+    let code = self.code;
+    let opcode = code[pc];
+    let depth = state.stack.len();
+    let conditions = [Cond::Equal, Cond::NotEqual, Cond::Less, Cond::GreaterEqual, Cond::Greater, Cond::LessEqual];
+    match opcode {
+      0x00 => {},
+      0x01 => self.constant(0, depth),
+      0x02..=0x08 => self.constant((opcode as i64 - 0x03) as u64, depth),
+      0x09 | 0x0a => self.constant((opcode - 0x09) as u64, depth),
+      0x0b..=0x0d => self.constant(((opcode - 0x0b) as f32).to_bits() as u64, depth),
+      0x0e | 0x0f => self.constant(((opcode - 0x0e) as f64).to_bits(), depth),
+      0x10 => self.constant(code[pc + 1] as i8 as i64 as u64, depth),
+      0x11 => self.constant(read_i16(code, pc + 1)? as i64 as u64, depth),
+      0x12..=0x14 => {
+        let index = if opcode == 0x12 { code[pc + 1] as u16 } else { (code[pc + 1] as u16) << 8 | code[pc + 2] as u16 };
+        match self.numeric_constant(index) {
+          Some(value) => self.constant(value, depth),
+          None => self.interpret(pc),
+        }
+      },
+      0x15..=0x19 => {
+        self.load(Reg::Rax, self.local(code[pc + 1] as usize));
+        self.store(self.stack(depth), Reg::Rax);
+      },
+      0x1a..=0x2d => {
+        self.load(Reg::Rax, self.local(((opcode - 0x1a) % 4) as usize));
+        self.store(self.stack(depth), Reg::Rax);
+      },
+      0x2e..=0x35 => {
+        self.load(Reg::Rdx, self.stack(depth - 2));
+        self.load(Reg::Rcx, self.stack(depth - 1));
+        self.call_runtime(helpers::jit_array_load as *const (), pc);
+        self.check(EXIT_DEOPTIMIZE, pc);
+        self.load_result(self.stack(depth - 2));
+      },
+      0x36..=0x3a => {
+        self.load(Reg::Rax, self.stack(depth - 1));
+        self.store(self.local(code[pc + 1] as usize), Reg::Rax);
+      },
+      0x3b..=0x4e => {
+        self.load(Reg::Rax, self.stack(depth - 1));
+        self.store(self.local(((opcode - 0x3b) % 4) as usize), Reg::Rax);
+      },
+      0x4f..=0x56 => {
+        self.load(Reg::Rdx, self.stack(depth - 3));
+        self.load(Reg::Rcx, self.stack(depth - 2));
+        self.load(Reg::R8, self.stack(depth - 1));
+        self.call_runtime(helpers::jit_array_store as *const (), pc);
+        self.check(EXIT_DEOPTIMIZE, pc);
+      },
+      0x57..=0x5f => {
+        let (consumed, pushed) = shuffle(opcode, &state.stack)?;
+        let registers = [Reg::Rax, Reg::Rcx, Reg::Rdx, Reg::Rsi];
+        let at = depth - consumed;
+        for (i, &reg) in registers.iter().enumerate().take(consumed) {
+          self.load(reg, self.stack(at + i));
+        }
+        for (i, &source) in pushed.iter().enumerate() {
+          self.store(self.stack(at + i), registers[source]);
+        }
+      },
+      0x60 | 0x61 => self.binary(AluOp::Add, opcode == 0x61, depth),
+      0x64 | 0x65 => self.binary(AluOp::Sub, opcode == 0x65, depth),
+      0x68 | 0x69 => {
+        let wide = opcode == 0x69;
+        self.load(Reg::Rax, self.stack(depth - 2));
+        self.load(Reg::Rcx, self.stack(depth - 1));
+        self.asm.imul(wide, Reg::Rax, Reg::Rcx);
+        self.store(self.stack(depth - 2), Reg::Rax);
+      },
+      0x6c | 0x6d => self.division(pc, depth, opcode == 0x6d, false),
+      0x70 | 0x71 => self.division(pc, depth, opcode == 0x71, true),
+      0x62 | 0x63 | 0x66 | 0x67 | 0x6a | 0x6b | 0x6e | 0x6f | 0x72 | 0x73 => self.arithmetic(opcode, depth, 2),
+      0x74 | 0x75 => {
+        self.load(Reg::Rax, self.stack(depth - 1));
+        self.asm.neg(opcode == 0x75, Reg::Rax);
+        self.store(self.stack(depth - 1), Reg::Rax);
+      },
+      // fneg, dneg (符号ビットを反転する)
+      0x76 | 0x77 => {
+        self.load(Reg::Rax, self.stack(depth - 1));
+        self.asm.mov_imm(Reg::Rcx, if opcode == 0x76 { 1 << 31 } else { 1 << 63 });
+        self.asm.alu(AluOp::Xor, true, Reg::Rax, Reg::Rcx);
+        self.store(self.stack(depth - 1), Reg::Rax);
+      },
+      0x78..=0x7d => {
+        let op = match (opcode - 0x78) / 2 {
+          0 => ShiftOp::Left,
+          1 => ShiftOp::ArithmeticRight,
+          _ => ShiftOp::LogicalRight,
+        };
+        self.load(Reg::Rax, self.stack(depth - 2));
+        self.load(Reg::Rcx, self.stack(depth - 1));
+        self.asm.shift(op, opcode % 2 == 1, Reg::Rax);
+        self.store(self.stack(depth - 2), Reg::Rax);
+      },
+      0x7e | 0x7f => self.binary(AluOp::And, opcode == 0x7f, depth),
+      0x80 | 0x81 => self.binary(AluOp::Or, opcode == 0x81, depth),
+      0x82 | 0x83 => self.binary(AluOp::Xor, opcode == 0x83, depth),
+      0x84 => {
+        let index = code[pc + 1] as usize;
+        self.load(Reg::Rax, self.local(index));
+        self.asm.alu_imm(AluOp::Add, false, Reg::Rax, code[pc + 2] as i8 as i32);
+        self.store(self.local(index), Reg::Rax);
+      },
+      0x85 => {
+        self.load(Reg::Rax, self.stack(depth - 1));
+        self.asm.sign_extend_32(Reg::Rax, Reg::Rax);
+        self.store(self.stack(depth - 1), Reg::Rax);
+      },
+      // l2iは下位32ビットをそのまま使う
+      0x88 => {},
+      0x86 | 0x87 | 0x89..=0x90 => self.arithmetic(opcode, depth, 1),
+      0x91..=0x93 => {
+        self.load(Reg::Rax, self.stack(depth - 1));
+        self.asm.extend(opcode != 0x92, opcode != 0x91, Reg::Rax, Reg::Rax);
+        self.store(self.stack(depth - 1), Reg::Rax);
+      },
+      0x94 => {
+        self.load(Reg::Rax, self.stack(depth - 2));
+        self.load(Reg::Rcx, self.stack(depth - 1));
+        self.asm.alu(AluOp::Cmp, true, Reg::Rax, Reg::Rcx);
+        self.asm.set(Cond::Greater, Reg::Rax);
+        self.asm.set(Cond::Less, Reg::Rcx);
+        self.asm.extend(false, false, Reg::Rax, Reg::Rax);
+        self.asm.extend(false, false, Reg::Rcx, Reg::Rcx);
+        self.asm.alu(AluOp::Sub, false, Reg::Rax, Reg::Rcx);
+        self.store(self.stack(depth - 2), Reg::Rax);
+      },
+      0x95..=0x98 => self.arithmetic(opcode, depth, 2),
+      0x99..=0x9e | 0xc6 | 0xc7 => {
+        let (cond, wide) = match opcode {
+          0xc6 => (Cond::Equal, true),
+          0xc7 => (Cond::NotEqual, true),
+          _ => (conditions[(opcode - 0x99) as usize], false),
+        };
+        self.compare_branch(pc, cond, |compiler| {
+          compiler.load(Reg::Rax, compiler.stack(depth - 1));
+          compiler.asm.test(wide, Reg::Rax, Reg::Rax);
+        })?;
+      },
+      0x9f..=0xa6 => {
+        let cond = match opcode {
+          0xa5 => Cond::Equal,
+          0xa6 => Cond::NotEqual,
+          _ => conditions[(opcode - 0x9f) as usize],
+        };
+        self.compare_branch(pc, cond, |compiler| {
+          compiler.load(Reg::Rax, compiler.stack(depth - 2));
+          compiler.load(Reg::Rcx, compiler.stack(depth - 1));
+          compiler.asm.alu(AluOp::Cmp, opcode >= 0xa5, Reg::Rax, Reg::Rcx);
+        })?;
+      },
+      0xa7 | 0xc8 => {
+        let offset = if opcode == 0xa7 { read_i16(code, pc + 1)? as i32 } else { read_i32(code, pc + 1)? };
+        let target = branch_target(pc, offset)?;
+        self.branch(pc, &[target]);
+        let label = self.label(target)?;
+        self.asm.jump(label);
+      },
+      0xaa | 0xab => {
+        let (default, cases) = switch_targets(code, pc)?;
+        let targets: Vec<usize> = cases.iter().map(|(_, target)| *target).chain([default]).collect();
+        self.branch(pc, &targets);
+        self.load(Reg::Rax, self.stack(depth - 1));
+        for (key, target) in cases {
+          self.asm.alu_imm(AluOp::Cmp, false, Reg::Rax, key);
+          let label = self.label(target)?;
+          self.asm.jump_if(Cond::Equal, label);
+        }
+        let label = self.label(default)?;
+        self.asm.jump(label);
+      },
+      // 戻り値はスタックの先頭のスロットに置く
+      0xac..=0xb0 => {
+        self.load(Reg::Rax, self.stack(depth - 1));
+        self.store(self.stack(0), Reg::Rax);
+        self.exit_now(EXIT_RETURN, pc);
+      },
+      0xb1 => self.exit_now(EXIT_RETURN, pc),
+      0xb2..=0xb5 if self.fields.contains_key(&pc) => {
+        match opcode {
+          0xb2 => {
+            self.call_runtime(helpers::jit_get_static as *const (), pc);
+            self.check(EXIT_DEOPTIMIZE, pc);
+            self.load_result(self.stack(depth));
+          },
+          0xb3 => {
+            self.load(Reg::Rdx, self.stack(depth - 1));
+            self.call_runtime(helpers::jit_put_static as *const (), pc);
+            self.check(EXIT_DEOPTIMIZE, pc);
+          },
+          0xb4 => {
+            self.load(Reg::Rdx, self.stack(depth - 1));
+            self.call_runtime(helpers::jit_get_field as *const (), pc);
+            self.check(EXIT_DEOPTIMIZE, pc);
+            self.load_result(self.stack(depth - 1));
+          },
+          _ => {
+            self.load(Reg::Rdx, self.stack(depth - 2));
+            self.load(Reg::Rcx, self.stack(depth - 1));
+            self.call_runtime(helpers::jit_put_field as *const (), pc);
+            self.check(EXIT_DEOPTIMIZE, pc);
+          },
+        }
+      },
+      0xbe => {
+        self.load(Reg::Rdx, self.stack(depth - 1));
+        self.call_runtime(helpers::jit_array_length as *const (), pc);
+        self.check(EXIT_DEOPTIMIZE, pc);
+        self.load_result(self.stack(depth - 1));
+      },
+      0xb2..=0xbd | 0xc0 | 0xc1 => self.interpret(pc),
+      0xc4 => {
+        let index = (code[pc + 2] as usize) << 8 | code[pc + 3] as usize;
+        match code[pc + 1] {
+          0x15..=0x19 => {
+            self.load(Reg::Rax, self.local(index));
+            self.store(self.stack(depth), Reg::Rax);
+          },
+          0x36..=0x3a => {
+            self.load(Reg::Rax, self.stack(depth - 1));
+            self.store(self.local(index), Reg::Rax);
+          },
+          _ => {
+            self.load(Reg::Rax, self.local(index));
+            self.asm.alu_imm(AluOp::Add, false, Reg::Rax, read_i16(code, pc + 4)? as i32);
+            self.store(self.local(index), Reg::Rax);
+          },
+        }
+      },
+      // athrow、monitorenter、monitorexit、multianewarrayはインタプリタに戻って実行する
+      _ => self.exit_now(EXIT_DEOPTIMIZE, pc),
+    }
+    Ok(())
+  }
+}
+
+pub fn compile(
+  code: &[u8],
+  pool: &ConstantPool,
+  states: Vec<Option<State>>,
+  fields: HashMap<usize, FieldAccess>,
+  max_locals: usize,
+  max_stack: usize,
+  return_type: Option<JitType>,
+) -> Result<CompiledMethod, String> {
+  let mut asm = Assembler::default();
+  let epilogue = asm.new_label();
+  let labels = states.iter().map(|state| state.as_ref().map(|_| asm.new_label())).collect();
+  let mut compiler = Compiler { asm, code, pool, fields: &fields, max_locals, labels, exits: Vec::new(), epilogue };
+
+  // rbx、r12、rbpを保存する (呼び出し時のスタックの位置もこれで16バイトに揃う)
+  compiler.asm.push(Reg::Rbx);
+  compiler.asm.push(Reg::R12);
+  compiler.asm.push(Reg::Rbp);
+  compiler.asm.mov(Reg::R12, Reg::Rdi);
+  compiler.asm.mov(Reg::Rbx, Reg::Rsi);
+  for (pc, state) in states.iter().enumerate() {
+    let Some(state) = state else {
+      continue;
+    };
+    let label = compiler.label(pc)?;
+    compiler.asm.bind(label);
+    compiler.instruction(pc, state)?;
+  }
+  for (label, status) in mem::take(&mut compiler.exits) {
+    compiler.asm.bind(label);
+    compiler.asm.mov_imm(Reg::Rax, status);
+    compiler.asm.jump(epilogue);
+  }
+  compiler.asm.bind(epilogue);
+  compiler.asm.pop(Reg::Rbp);
+  compiler.asm.pop(Reg::R12);
+  compiler.asm.pop(Reg::Rbx);
+  compiler.asm.ret();
+
+  let machine_code = compiler.asm.finish()?;
+  Ok(CompiledMethod {
+    code: ExecutableCode::new(&machine_code)?,
+    code_size: machine_code.len(),
+    states,
+    max_locals,
+    slots: max_locals + max_stack,
+    return_type,
+    fields,
+  })
+}
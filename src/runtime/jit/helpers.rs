@@ -0,0 +1,145 @@
+use crate::runtime::{
+  interpreter::compare_float,
+  jit::{analysis::JitType, compiler::FieldLocation, JitContext},
+  memory::{Location, MemoryOrder},
+  value::Value,
+  vm::Vm,
+};
+
+// コンパイルしたコードから呼ぶ関数 (成功なら0を返し、読み込んだ値はJitContext::resultに置く)
+
+const SUCCESS: u64 = 0;
+const FAILURE: u64 = 1;
+
+fn with_vm(context: *mut JitContext, f: impl FnOnce(&mut Vm, &mut JitContext) -> Option<u64>) -> u64 {
+  let context = unsafe { &mut *context };
+  let vm = unsafe { &mut *context.vm };
+  match f(vm, context) {
+    Some(result) => {
+      context.result = result;
+      SUCCESS
+    },
+    None => FAILURE,
+  }
+}
+
+// 失敗した場合は、インタプリタが同じ命令を実行し直して例外を投げる
+fn field_location(context: &JitContext, pc: usize, object: u64) -> Option<(Location, MemoryOrder)> {
+  let access = context.method().fields.get(&pc)?;
+  let location = match access.location {
+    FieldLocation::Instance(slot) => match JitType::Ref.value(object) {
+      Value::Ref(object) => Location::Field(object, slot),
+      _ => return None,
+    },
+    FieldLocation::Static(class, slot) => Location::Static(class, slot),
+  };
+  Some((location, access.order))
+}
+
+// 命令を実行する前のスタックの先頭の型
+fn top_type(context: &JitContext, pc: usize) -> JitType {
+  context.method().states[pc].as_ref().and_then(|state| state.stack.last().copied()).unwrap_or(JitType::Top)
+}
+
+pub extern "C" fn jit_get_field(context: *mut JitContext, pc: u64, object: u64) -> u64 {
+  with_vm(context, |vm, context| {
+    let (location, order) = field_location(context, pc as usize, object)?;
+    vm.load_ordered(location, order).ok().map(JitType::bits)
+  })
+}
+
+pub extern "C" fn jit_put_field(context: *mut JitContext, pc: u64, object: u64, value: u64) -> u64 {
+  with_vm(context, |vm, context| {
+    let (location, order) = field_location(context, pc as usize, object)?;
+    let value = top_type(context, pc as usize).value(value);
+    vm.store_ordered(location, value, order).ok().map(|_| 0)
+  })
+}
+
+pub extern "C" fn jit_get_static(context: *mut JitContext, pc: u64) -> u64 {
+  jit_get_field(context, pc, 0)
+}
+
+pub extern "C" fn jit_put_static(context: *mut JitContext, pc: u64, value: u64) -> u64 {
+  jit_put_field(context, pc, 0, value)
+}
+
+pub extern "C" fn jit_array_load(context: *mut JitContext, _pc: u64, array: u64, index: u64) -> u64 {
+  with_vm(context, |vm, _| {
+    let array = JitType::Ref.value(array).as_ref().ok()??;
+    let index = vm.array_index(array, index as i32).ok()?;
+    Some(JitType::bits(vm.heap.get(array).ok()?.array().ok()?.get(index)))
+  })
+}
+
+pub extern "C" fn jit_array_store(context: *mut JitContext, pc: u64, array: u64, index: u64, value: u64) -> u64 {
+  with_vm(context, |vm, context| {
+    let array = JitType::Ref.value(array).as_ref().ok()??;
+    let index = vm.array_index(array, index as i32).ok()?;
+    let value = top_type(context, pc as usize).value(value);
+    vm.check_array_store(array, value).ok()?;
+    vm.heap.get_mut(array).ok()?.array_mut().ok()?.set(index, value).ok().map(|_| 0)
+  })
+}
+
+pub extern "C" fn jit_array_length(context: *mut JitContext, _pc: u64, array: u64) -> u64 {
+  with_vm(context, |vm, _| {
+    let array = JitType::Ref.value(array).as_ref().ok()??;
+    Some(vm.heap.get(array).ok()?.array().ok()?.len() as u64)
+  })
+}
+
+// インタプリタで1命令を実行する (例外が起きたらJitContext::errorに置いて失敗を返す)
+pub extern "C" fn jit_interpret(context: *mut JitContext, pc: u64) -> u64 {
+  let context = unsafe { &mut *context };
+  let vm = unsafe { &mut *context.vm };
+  match vm.jit_interpret(context, pc as usize) {
+    Ok(()) => SUCCESS,
+    Err(error) => {
+      context.error = Some(error);
+      FAILURE
+    },
+  }
+}
+
+pub extern "C" fn jit_safepoint(context: *mut JitContext, pc: u64) {
+  let context = unsafe { &mut *context };
+  let vm = unsafe { &mut *context.vm };
+  vm.jit_safepoint(context, pc as usize);
+}
+
+// 浮動小数点数の演算と変換 (ビット列で受け取り、ビット列で返す)
+pub extern "C" fn jit_arithmetic(opcode: u64, a: u64, b: u64) -> u64 {
+  let (fa, fb) = (f32::from_bits(a as u32), f32::from_bits(b as u32));
+  let (da, db) = (f64::from_bits(a), f64::from_bits(b));
+  let float = |v: f32| v.to_bits() as u64;
+  let double = |v: f64| v.to_bits();
+  let int = |v: i32| v as i64 as u64;
+  match opcode {
+    0x62 => float(fa + fb),
+    0x63 => double(da + db),
+    0x66 => float(fa - fb),
+    0x67 => double(da - db),
+    0x6a => float(fa * fb),
+    0x6b => double(da * db),
+    0x6e => float(fa / fb),
+    0x6f => double(da / db),
+    0x72 => float(fa % fb),
+    0x73 => double(da % db),
+    0x86 => float(a as i32 as f32),
+    0x87 => double(a as i32 as f64),
+    0x89 => float(a as i64 as f32),
+    0x8a => double(a as i64 as f64),
+    0x8b => int(fa as i32),
+    0x8c => fa as i64 as u64,
+    0x8d => double(fa as f64),
+    0x8e => int(da as i32),
+    0x8f => da as i64 as u64,
+    0x90 => float(da as f32),
+    0x95 => int(compare_float(fa as f64, fb as f64, -1)),
+    0x96 => int(compare_float(fa as f64, fb as f64, 1)),
+    0x97 => int(compare_float(da, db, -1)),
+    0x98 => int(compare_float(da, db, 1)),
+    _ => 0,
+  }
+}
@@ -0,0 +1,273 @@
+pub mod analysis;
+pub mod assembler;
+pub mod compiler;
+pub mod helpers;
+
+use std::{collections::HashMap, rc::Rc, slice};
+
+use crate::runtime::{
+  class::{InitState, ResolvedRef, RuntimeMethod},
+  error::VmError,
+  jit::{
+    analysis::{analyze, JitType},
+    compiler::{compile, CompiledMethod, FieldAccess, FieldLocation, EXIT_DEOPTIMIZE, EXIT_RETURN},
+  },
+  memory::MemoryOrder,
+  options::JitOptions,
+  scheduler::ThreadId,
+  vm::Vm,
+};
+
+// コンパイルしたコードの中から呼び出せる深さ (Rustのスタックを使うので、超えたらインタプリタで実行する)
+const MAX_NESTING: usize = 64;
+// インタプリタに戻った回数がこれを超えたメソッドは、コンパイルしたコードを使わなくする
+const DEOPTIMIZE_LIMIT: u32 = 100;
+// セーフポイントで他のスレッドに実行権を譲るまでの後ろ向きの分岐の回数
+const SAFEPOINT_INTERVAL: i64 = 1000;
+
+enum MethodState {
+  // 呼び出された回数
+  Interpreted(u32),
+  // コンパイルしたコードと、インタプリタに戻った回数
+  Compiled(Rc<CompiledMethod>, u32),
+  NotCompilable,
+}
+
+pub struct Jit {
+  options: JitOptions,
+  // メソッドIDごとの状態
+  methods: HashMap<usize, MethodState>,
+  // スレッドごとの、実行中のコンパイルしたコードの深さ
+  nesting: HashMap<ThreadId, usize>,
+}
+
+impl Jit {
+  pub fn new(options: JitOptions) -> Jit {
+    Jit { options, methods: HashMap::new(), nesting: HashMap::new() }
+  }
+}
+
+// コンパイルしたコードとランタイムの関数が共有する状態 (budgetとresultの位置は機械語から参照する)
+#[repr(C)]
+pub struct JitContext {
+  budget: i64,
+  result: u64,
+  vm: *mut Vm,
+  method: *const CompiledMethod,
+  slots: *mut u64,
+  // 実行中のメソッドのフレームの位置
+  frame: usize,
+  error: Option<VmError>,
+}
+
+impl JitContext {
+  fn method(&self) -> &CompiledMethod {
+    unsafe { &*self.method }
+  }
+
+  fn slots(&mut self) -> &mut [u64] {
+    let length = self.method().slots;
+    unsafe { slice::from_raw_parts_mut(self.slots, length) }
+  }
+}
+
+fn method_name(method: &RuntimeMethod) -> String {
+  format!("{}.{}{}", method.class_name.replace('/', "."), method.name, method.descriptor)
+}
+
+impl Vm {
+  // メソッドを呼び出すごとに数え、閾値に達したらコンパイルする
+  pub(crate) fn jit_count(&mut self, method: &Rc<RuntimeMethod>) {
+    let Some(jit) = &mut self.jit else {
+      return;
+    };
+    let threshold = jit.options.threshold;
+    let MethodState::Interpreted(count) = jit.methods.entry(method.id).or_insert(MethodState::Interpreted(0)) else {
+      return;
+    };
+    *count += 1;
+    if *count < threshold {
+      return;
+    }
+    let state = match self.compile_method(method) {
+      Ok(compiled) => {
+        self.jit_print(|| format!("compiled {} ({} bytes -> {} bytes)", method_name(method), compiled.states.len(), compiled.code_size));
+        MethodState::Compiled(Rc::new(compiled), 0)
+      },
+      Err(reason) => {
+        self.jit_print(|| format!("not compiled {}: {}", method_name(method), reason));
+        MethodState::NotCompilable
+      },
+    };
+    if let Some(jit) = &mut self.jit {
+      jit.methods.insert(method.id, state);
+    }
+  }
+
+  fn jit_print(&self, message: impl FnOnce() -> String) {
+    if self.jit.as_ref().is_some_and(|jit| jit.options.print) {
+      eprintln!("[jit] {}", message());
+    }
+  }
+
+  fn compile_method(&self, method: &RuntimeMethod) -> Result<CompiledMethod, String> {
+    if !cfg!(all(target_arch = "x86_64", target_os = "linux")) {
+      return Err("unsupported platform".to_string());
+    }
+    let code = method.code().ok_or("no bytecode")?;
+    let class_file = self.class_file(method.class).map_err(|e| e.to_string())?;
+    let pool = &class_file.constant_pool;
+    let receiver = (!method.is_static()).then_some(JitType::Ref);
+    let parameters: Vec<JitType> = receiver.into_iter().chain(method.signature.parameters.iter().map(JitType::of)).collect();
+    let states = analyze(&code.bytes, code.max_locals, &parameters, pool)?;
+    let fields = self.field_accesses(method, &code.bytes, &states);
+    let return_type = method.signature.return_type.as_ref().map(JitType::of);
+    compile(&code.bytes, pool, states, fields, code.max_locals, code.max_stack, return_type)
+  }
+
+  // 解決済みのフィールドだけ直接読み書きする (staticフィールドはクラスの初期化が済んでいるものだけ)
+  fn field_accesses(&self, method: &RuntimeMethod, code: &[u8], states: &[Option<analysis::State>]) -> HashMap<usize, FieldAccess> {
+    let class = &self.classes[method.class];
+    let mut fields = HashMap::new();
+    for (pc, _) in states.iter().enumerate().filter(|(_, state)| state.is_some()) {
+      let opcode = code[pc];
+      if !(0xb2..=0xb5).contains(&opcode) {
+        continue;
+      }
+      let index = (code[pc + 1] as usize) << 8 | code[pc + 2] as usize;
+      let Some(Some(ResolvedRef::Field(owner, field_index))) = class.resolved.get(index) else {
+        continue;
+      };
+      let field = &self.classes[*owner].fields[*field_index];
+      let is_static = opcode <= 0xb3;
+      if field.is_static() != is_static {
+        continue;
+      }
+      let location = if is_static {
+        if !matches!(self.classes[*owner].init_state, InitState::Initialized) {
+          continue;
+        }
+        FieldLocation::Static(*owner, field.slot)
+      } else {
+        FieldLocation::Instance(field.slot)
+      };
+      let order = if field.is_volatile() { MemoryOrder::Volatile } else { MemoryOrder::Plain };
+      fields.insert(pc, FieldAccess { location, order });
+    }
+    fields
+  }
+
+  // 先頭の命令から実行するフレームのメソッドがコンパイル済みなら、コンパイルしたコードで実行する
+  pub(crate) fn run_compiled(&mut self) -> Option<Result<(), VmError>> {
+    let frame = self.frames.last()?;
+    if frame.pc != 0 || !frame.stack.is_empty() {
+      return None;
+    }
+    let jit = self.jit.as_mut()?;
+    let Some(MethodState::Compiled(compiled, _)) = jit.methods.get(&frame.method.id) else {
+      return None;
+    };
+    let compiled = compiled.clone();
+    let nesting = jit.nesting.entry(self.threads.current).or_default();
+    if *nesting >= MAX_NESTING {
+      return None;
+    }
+    *nesting += 1;
+    let id = frame.method.id;
+    let mut slots = vec![0; compiled.slots];
+    for (slot, value) in slots.iter_mut().zip(&frame.locals) {
+      *slot = JitType::bits(*value);
+    }
+    let mut context = JitContext {
+      budget: SAFEPOINT_INTERVAL,
+      result: 0,
+      vm: self,
+      method: Rc::as_ptr(&compiled),
+      slots: slots.as_mut_ptr(),
+      frame: self.frames.len() - 1,
+      error: None,
+    };
+    let status = unsafe { compiled.entry()(&mut context, slots.as_mut_ptr()) };
+    if let Some(nesting) = self.jit.as_mut().and_then(|jit| jit.nesting.get_mut(&self.threads.current)) {
+      *nesting -= 1;
+    }
+    let pc = (status >> 2) as usize;
+    Some(match status & 3 {
+      EXIT_RETURN => {
+        let value = compiled.return_type.map(|return_type| return_type.value(slots[compiled.max_locals]));
+        self.return_from_frame(value)
+      },
+      // 対応していない命令や例外を投げる命令は、インタプリタで実行し直す
+      EXIT_DEOPTIMIZE => {
+        self.jit_materialize(&mut context, pc);
+        self.jit_deoptimized(id);
+        self.step()
+      },
+      _ => Err(context.error.take().unwrap_or_else(|| VmError::internal("Compiled code exited without an exception"))),
+    })
+  }
+
+  fn jit_deoptimized(&mut self, id: usize) {
+    let Some(jit) = &mut self.jit else {
+      return;
+    };
+    let Some(MethodState::Compiled(_, count)) = jit.methods.get_mut(&id) else {
+      return;
+    };
+    *count += 1;
+    if *count >= DEOPTIMIZE_LIMIT {
+      jit.methods.insert(id, MethodState::NotCompilable);
+      let method = self.methods[id].clone();
+      self.jit_print(|| format!("made not entrant {} after {} deoptimizations", method_name(&method), DEOPTIMIZE_LIMIT));
+    }
+  }
+
+  // スロットの値をpcの型に従ってフレームに書き戻す
+  fn jit_materialize(&mut self, context: &mut JitContext, pc: usize) {
+    let slots = context.slots().to_vec();
+    let method = context.method();
+    let Some(state) = &method.states[pc] else {
+      return;
+    };
+    let frame = &mut self.frames[context.frame];
+    frame.pc = pc;
+    for (i, local) in state.locals.iter().enumerate() {
+      frame.locals[i] = local.value(slots[i]);
+    }
+    frame.stack.clear();
+    frame.stack.extend(state.stack.iter().enumerate().map(|(i, value)| value.value(slots[method.max_locals + i])));
+  }
+
+  // インタプリタで実行した後のフレームをスロットに読み込む
+  fn jit_reload(&mut self, context: &mut JitContext) {
+    let max_locals = context.method().max_locals;
+    let frame = &self.frames[context.frame];
+    let slots = context.slots();
+    for (i, value) in frame.locals.iter().enumerate() {
+      slots[i] = JitType::bits(*value);
+    }
+    for (i, value) in frame.stack.iter().enumerate() {
+      slots[max_locals + i] = JitType::bits(*value);
+    }
+  }
+
+  fn jit_interpret(&mut self, context: &mut JitContext, pc: usize) -> Result<(), VmError> {
+    self.jit_materialize(context, pc);
+    let depth = self.frames.len();
+    self.step()?;
+    if self.frames.len() > depth {
+      self.execute(depth)?;
+    }
+    self.jit_reload(context);
+    Ok(())
+  }
+
+  fn jit_safepoint(&mut self, context: &mut JitContext, pc: usize) {
+    context.budget = SAFEPOINT_INTERVAL;
+    if self.threads.table.len() > 1 {
+      self.jit_materialize(context, pc);
+      self.yield_thread();
+      self.jit_reload(context);
+    }
+  }
+}
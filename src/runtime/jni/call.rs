@@ -0,0 +1,282 @@
+use std::mem;
+
+use crate::{
+  runtime::{
+    class::RuntimeMethod,
+    error::VmError,
+    jni::reference::class_handle,
+    value::Value,
+    vm::Vm,
+  },
+  util::descriptor::FieldType,
+};
+
+// x86-64 System V ABIで引数を渡すレジスタの数
+pub const INTEGER_REGISTERS: usize = 6;
+pub const FLOAT_REGISTERS: usize = 8;
+// スタックで渡す引数の上限
+pub const STACK_SLOTS: usize = 16;
+
+// 16バイトより大きい構造体は値渡しでスタックに置かれるので、スタック上の引数の並びとして使える
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct StackArguments(pub [u64; STACK_SLOTS]);
+
+// 整数レジスタと浮動小数点数レジスタは独立に割り当てられるので、すべてのレジスタを埋める型で呼び出せば
+// 任意のシグネチャの関数を呼び出せる
+type IntegerFunction = unsafe extern "C" fn(u64, u64, u64, u64, u64, u64, f64, f64, f64, f64, f64, f64, f64, f64, StackArguments) -> u64;
+type FloatFunction = unsafe extern "C" fn(u64, u64, u64, u64, u64, u64, f64, f64, f64, f64, f64, f64, f64, f64, StackArguments) -> f64;
+
+#[derive(Default)]
+struct CallArguments {
+  integers: Vec<u64>,
+  floats: Vec<f64>,
+  stack: Vec<u64>,
+}
+
+impl CallArguments {
+  fn push_integer(&mut self, value: u64) {
+    match self.integers.len() < INTEGER_REGISTERS {
+      true => self.integers.push(value),
+      false => self.stack.push(value),
+    }
+  }
+
+  // floatはレジスタの下位32ビットに入れる
+  fn push_float(&mut self, bits: u64) {
+    match self.floats.len() < FLOAT_REGISTERS {
+      true => self.floats.push(f64::from_bits(bits)),
+      false => self.stack.push(bits),
+    }
+  }
+
+  // 戻り値のレジスタ (raxかxmm0) の値をそのまま返す
+  unsafe fn call(&self, function: usize, float_return: bool) -> u64 {
+    let mut integers = [0; INTEGER_REGISTERS];
+    let mut floats = [0.0; FLOAT_REGISTERS];
+    let mut stack = StackArguments::default();
+    integers[..self.integers.len()].copy_from_slice(&self.integers);
+    floats[..self.floats.len()].copy_from_slice(&self.floats);
+    stack.0[..self.stack.len()].copy_from_slice(&self.stack);
+    let [i0, i1, i2, i3, i4, i5] = integers;
+    let [f0, f1, f2, f3, f4, f5, f6, f7] = floats;
+    unsafe {
+      match float_return {
+        true => {
+          let function = mem::transmute::<usize, FloatFunction>(function);
+          function(i0, i1, i2, i3, i4, i5, f0, f1, f2, f3, f4, f5, f6, f7, stack).to_bits()
+        },
+        false => {
+          let function = mem::transmute::<usize, IntegerFunction>(function);
+          function(i0, i1, i2, i3, i4, i5, f0, f1, f2, f3, f4, f5, f6, f7, stack)
+        },
+      }
+    }
+  }
+}
+
+// JNI関数に渡された引数を、メソッドの記述子に従って順に読み出す
+pub trait ArgumentSource {
+  fn next_integer(&mut self) -> u64;
+  fn next_float(&mut self) -> f32;
+  fn next_double(&mut self) -> f64;
+}
+
+// 可変長引数 (...): レジスタに残った引数とスタックを受け取っておき、呼び出し規約と同じ順に取り出す
+pub struct VarArgs {
+  integers: Vec<u64>,
+  floats: [f64; FLOAT_REGISTERS],
+  stack: StackArguments,
+  next_float: usize,
+  next_stack: usize,
+}
+
+impl VarArgs {
+  pub fn new(integers: &[u64], floats: [f64; FLOAT_REGISTERS], stack: StackArguments) -> Self {
+    VarArgs { integers: integers.iter().rev().copied().collect(), floats, stack, next_float: 0, next_stack: 0 }
+  }
+
+  fn next_stack(&mut self) -> u64 {
+    let value = self.stack.0.get(self.next_stack).copied().unwrap_or(0);
+    self.next_stack += 1;
+    value
+  }
+}
+
+impl ArgumentSource for VarArgs {
+  fn next_integer(&mut self) -> u64 {
+    match self.integers.pop() {
+      Some(value) => value,
+      None => self.next_stack(),
+    }
+  }
+
+  // 可変長引数のfloatはdoubleに昇格している
+  fn next_float(&mut self) -> f32 {
+    self.next_double() as f32
+  }
+
+  fn next_double(&mut self) -> f64 {
+    match self.floats.get(self.next_float) {
+      Some(&value) => {
+        self.next_float += 1;
+        value
+      },
+      None => f64::from_bits(self.next_stack()),
+    }
+  }
+}
+
+// va_list (x86-64 System V ABIの__va_list_tag)
+#[repr(C)]
+pub struct VaList {
+  gp_offset: u32,
+  fp_offset: u32,
+  overflow_arg_area: *mut u64,
+  reg_save_area: *mut u8,
+}
+
+// レジスタ保存領域は整数レジスタ6個 (48バイト) の後に浮動小数点数レジスタ8個 (16バイトずつ) が並ぶ
+const GP_SAVE_END: u32 = (INTEGER_REGISTERS * 8) as u32;
+const FP_SAVE_END: u32 = GP_SAVE_END + (FLOAT_REGISTERS * 16) as u32;
+
+pub struct VaListArgs(pub *mut VaList);
+
+impl VaListArgs {
+  unsafe fn overflow(&mut self) -> u64 {
+    unsafe {
+      let list = &mut *self.0;
+      let value = list.overflow_arg_area.read_unaligned();
+      list.overflow_arg_area = list.overflow_arg_area.add(1);
+      value
+    }
+  }
+}
+
+impl ArgumentSource for VaListArgs {
+  fn next_integer(&mut self) -> u64 {
+    unsafe {
+      let list = &mut *self.0;
+      if list.gp_offset < GP_SAVE_END {
+        let value = (list.reg_save_area.add(list.gp_offset as usize) as *const u64).read_unaligned();
+        list.gp_offset += 8;
+        return value;
+      }
+      self.overflow()
+    }
+  }
+
+  fn next_float(&mut self) -> f32 {
+    self.next_double() as f32
+  }
+
+  fn next_double(&mut self) -> f64 {
+    unsafe {
+      let list = &mut *self.0;
+      if list.fp_offset < FP_SAVE_END {
+        let value = (list.reg_save_area.add(list.fp_offset as usize) as *const f64).read_unaligned();
+        list.fp_offset += 16;
+        return value;
+      }
+      f64::from_bits(self.overflow())
+    }
+  }
+}
+
+// jvalueの配列 (8バイトの共用体)
+pub struct JValueArgs(pub *const u64);
+
+impl JValueArgs {
+  fn next(&mut self) -> u64 {
+    unsafe {
+      let value = self.0.read_unaligned();
+      self.0 = self.0.add(1);
+      value
+    }
+  }
+}
+
+impl ArgumentSource for JValueArgs {
+  fn next_integer(&mut self) -> u64 {
+    self.next()
+  }
+
+  fn next_float(&mut self) -> f32 {
+    f32::from_bits(self.next() as u32)
+  }
+
+  fn next_double(&mut self) -> f64 {
+    f64::from_bits(self.next())
+  }
+}
+
+impl Vm {
+  // Cで書かれたネイティブメソッドを呼び出す (引数はJNIEnv*、thisまたはjclass、Javaの引数の順)
+  pub(crate) fn call_jni(&mut self, method: &RuntimeMethod, function: usize, args: &[Value]) -> Result<Option<Value>, VmError> {
+    let mark = self.handles.mark();
+    let local_frames = self.jni.local_frames.len();
+    let result = self.call_jni_function(method, function, args);
+    self.handles.release(mark);
+    self.jni.local_frames.truncate(local_frames);
+    match self.jni.error.take() {
+      Some(error) => Err(error),
+      None => result,
+    }
+  }
+
+  fn call_jni_function(&mut self, method: &RuntimeMethod, function: usize, args: &[Value]) -> Result<Option<Value>, VmError> {
+    let mut arguments = CallArguments::default();
+    let vm: *mut Vm = self;
+    arguments.push_integer(self.jni.env(vm) as u64);
+    let mut values = args.iter().copied();
+    match method.is_static() {
+      true => arguments.push_integer(class_handle(method.class) as u64),
+      false => {
+        let this = values.next().ok_or_else(|| VmError::internal("Missing receiver"))?;
+        arguments.push_integer(self.value_handle(this)? as u64);
+      },
+    }
+    for (parameter, value) in method.signature.parameters.iter().zip(values) {
+      match parameter {
+        FieldType::Float => arguments.push_float(value.as_float()?.to_bits() as u64),
+        FieldType::Double => arguments.push_float(value.as_double()?.to_bits()),
+        FieldType::Long => arguments.push_integer(value.as_long()? as u64),
+        FieldType::Object(_) | FieldType::Array(_) => arguments.push_integer(self.value_handle(value)? as u64),
+        _ => arguments.push_integer(value.as_int()? as i64 as u64),
+      }
+    }
+    if arguments.stack.len() > STACK_SLOTS {
+      return Err(VmError::java("java/lang/UnsatisfiedLinkError", format!("Too many arguments for '{}'", method.external_name())));
+    }
+    let float_return = matches!(method.signature.return_type, Some(FieldType::Float | FieldType::Double));
+    let raw = unsafe { arguments.call(function, float_return) };
+    let Some(return_type) = &method.signature.return_type else {
+      return Ok(None);
+    };
+    self.jni_value(return_type, raw).map(Some)
+  }
+
+  // Cの値 (レジスタの内容やjvalue) をJavaの値にする
+  pub(crate) fn jni_value(&self, field_type: &FieldType, raw: u64) -> Result<Value, VmError> {
+    Ok(match field_type {
+      FieldType::Boolean => Value::Int((raw as u8 != 0) as i32),
+      FieldType::Byte => Value::Int(raw as i8 as i32),
+      FieldType::Char => Value::Int(raw as u16 as i32),
+      FieldType::Short => Value::Int(raw as i16 as i32),
+      FieldType::Int => Value::Int(raw as i32),
+      FieldType::Long => Value::Long(raw as i64),
+      FieldType::Float => Value::Float(f32::from_bits(raw as u32)),
+      FieldType::Double => Value::Double(f64::from_bits(raw)),
+      FieldType::Object(_) | FieldType::Array(_) => Value::from_ref(self.resolve_handle(raw as usize)?),
+    })
+  }
+
+  // JNIのCall<Type>Method系の関数に渡された引数を読み出す
+  pub(crate) fn read_arguments(&self, method: &RuntimeMethod, source: &mut impl ArgumentSource) -> Result<Vec<Value>, VmError> {
+    method.signature.parameters.iter().map(|parameter| match parameter {
+      FieldType::Float => Ok(Value::Float(source.next_float())),
+      FieldType::Double => Ok(Value::Double(source.next_double())),
+      _ => self.jni_value(parameter, source.next_integer()),
+    }).collect()
+  }
+}
@@ -0,0 +1,1138 @@
+use std::{
+  ffi::{c_char, c_void, CStr},
+  mem,
+  ptr,
+  rc::Rc,
+  slice,
+  sync::OnceLock,
+};
+
+use crate::{
+  runtime::{
+    class::{ClassId, RuntimeMethod, ACC_NATIVE},
+    error::VmError,
+    heap::ArrayData,
+    jni::{
+      call::{ArgumentSource, JValueArgs, StackArguments, VaList, VaListArgs, VarArgs},
+      reference::{class_handle, ref_type, Handle},
+    },
+    native::JniEnv,
+    value::{ObjRef, Value},
+    vm::Vm,
+  },
+  util::{descriptor::FieldType, mutf8},
+};
+
+pub const JNI_OK: i32 = 0;
+pub const JNI_ERR: i32 = -1;
+pub const JNI_EVERSION: i32 = -3;
+
+// Release<Type>ArrayElementsのmode
+const JNI_COMMIT: i32 = 1;
+const JNI_ABORT: i32 = 2;
+
+const JNI_VERSION_10: i32 = 0x000a0000;
+const SUPPORTED_VERSIONS: [i32; 7] = [0x00010001, 0x00010002, 0x00010004, 0x00010006, 0x00010008, 0x00090000, JNI_VERSION_10];
+
+// JNINativeInterfaceの関数の数 (予約済みの4つを含む)
+const NATIVE_FUNCTIONS: usize = 234;
+
+type MethodId = usize;
+type FieldId = usize;
+
+// JNIEnv (関数テーブルへのポインタの後ろに、呼び出し元のVmを置く)
+#[repr(C)]
+pub struct RawEnv {
+  pub functions: *const usize,
+  pub vm: *mut Vm,
+}
+
+// JavaVM
+#[repr(C)]
+pub struct RawJavaVm {
+  pub functions: *const usize,
+  pub vm: *mut Vm,
+}
+
+// RegisterNativesに渡すJNINativeMethod
+#[repr(C)]
+struct NativeMethod {
+  name: *const c_char,
+  signature: *const c_char,
+  function: *mut c_void,
+}
+
+pub fn is_supported_version(version: i32) -> bool {
+  SUPPORTED_VERSIONS.contains(&version)
+}
+
+// Java側の値とJNIの型の変換
+trait JniValue: Copy + Default {
+  fn from_value(vm: &mut Vm, value: Value) -> Result<Self, VmError>;
+  fn into_value(self, vm: &Vm) -> Result<Value, VmError>;
+}
+
+impl JniValue for u8 {
+  fn from_value(_vm: &mut Vm, value: Value) -> Result<Self, VmError> {
+    Ok(value.as_int()? as u8)
+  }
+
+  fn into_value(self, _vm: &Vm) -> Result<Value, VmError> {
+    Ok(Value::Int((self != 0) as i32))
+  }
+}
+
+impl JniValue for i8 {
+  fn from_value(_vm: &mut Vm, value: Value) -> Result<Self, VmError> {
+    Ok(value.as_int()? as i8)
+  }
+
+  fn into_value(self, _vm: &Vm) -> Result<Value, VmError> {
+    Ok(Value::Int(self as i32))
+  }
+}
+
+impl JniValue for u16 {
+  fn from_value(_vm: &mut Vm, value: Value) -> Result<Self, VmError> {
+    Ok(value.as_int()? as u16)
+  }
+
+  fn into_value(self, _vm: &Vm) -> Result<Value, VmError> {
+    Ok(Value::Int(self as i32))
+  }
+}
+
+impl JniValue for i16 {
+  fn from_value(_vm: &mut Vm, value: Value) -> Result<Self, VmError> {
+    Ok(value.as_int()? as i16)
+  }
+
+  fn into_value(self, _vm: &Vm) -> Result<Value, VmError> {
+    Ok(Value::Int(self as i32))
+  }
+}
+
+impl JniValue for i32 {
+  fn from_value(_vm: &mut Vm, value: Value) -> Result<Self, VmError> {
+    value.as_int()
+  }
+
+  fn into_value(self, _vm: &Vm) -> Result<Value, VmError> {
+    Ok(Value::Int(self))
+  }
+}
+
+impl JniValue for i64 {
+  fn from_value(_vm: &mut Vm, value: Value) -> Result<Self, VmError> {
+    value.as_long()
+  }
+
+  fn into_value(self, _vm: &Vm) -> Result<Value, VmError> {
+    Ok(Value::Long(self))
+  }
+}
+
+impl JniValue for f32 {
+  fn from_value(_vm: &mut Vm, value: Value) -> Result<Self, VmError> {
+    value.as_float()
+  }
+
+  fn into_value(self, _vm: &Vm) -> Result<Value, VmError> {
+    Ok(Value::Float(self))
+  }
+}
+
+impl JniValue for f64 {
+  fn from_value(_vm: &mut Vm, value: Value) -> Result<Self, VmError> {
+    value.as_double()
+  }
+
+  fn into_value(self, _vm: &Vm) -> Result<Value, VmError> {
+    Ok(Value::Double(self))
+  }
+}
+
+// 参照はローカル参照にして返す
+impl JniValue for Handle {
+  fn from_value(vm: &mut Vm, value: Value) -> Result<Self, VmError> {
+    vm.value_handle(value)
+  }
+
+  fn into_value(self, vm: &Vm) -> Result<Value, VmError> {
+    Ok(Value::from_ref(vm.resolve_handle(self)?))
+  }
+}
+
+// Call<Type>Methodの戻り値がvoidの場合
+impl JniValue for () {
+  fn from_value(_vm: &mut Vm, _value: Value) -> Result<Self, VmError> {
+    Ok(())
+  }
+
+  fn into_value(self, _vm: &Vm) -> Result<Value, VmError> {
+    Err(VmError::internal("void has no value"))
+  }
+}
+
+// JNI関数の本体を実行する。エラーは保留中の例外にして、関数の戻り値は既定値 (0やNULL) にする
+fn with_vm<T: Default>(env: *mut RawEnv, f: impl FnOnce(&mut Vm) -> Result<T, VmError>) -> T {
+  let vm = unsafe { &mut *(*env).vm };
+  match f(vm) {
+    Ok(value) => value,
+    Err(error) => {
+      vm.jni_error(error);
+      T::default()
+    },
+  }
+}
+
+// 成功ならJNI_OK、失敗ならJNI_ERRを返すJNI関数
+fn with_status(env: *mut RawEnv, f: impl FnOnce(&mut Vm) -> Result<(), VmError>) -> i32 {
+  with_vm(env, |vm| f(vm).map(|_| JNI_OK).or_else(|error| {
+    vm.jni_error(error);
+    Ok(JNI_ERR)
+  }))
+}
+
+// NULL終端の修正UTF-8の文字列
+unsafe fn c_string(string: *const c_char) -> Result<String, VmError> {
+  if string.is_null() {
+    return Err(VmError::java_without_message("java/lang/NullPointerException"));
+  }
+  let bytes = unsafe { CStr::from_ptr(string) }.to_bytes();
+  Ok(String::from_utf16_lossy(&mutf8::decode(bytes)))
+}
+
+fn primitive_type(tag: u8) -> Result<FieldType, VmError> {
+  FieldType::parse(&(tag as char).to_string()).map_err(VmError::internal)
+}
+
+fn as_bytes<T>(values: &[T]) -> &[u8] {
+  unsafe { slice::from_raw_parts(values.as_ptr().cast(), mem::size_of_val(values)) }
+}
+
+fn as_bytes_mut<T>(values: &mut [T]) -> &mut [u8] {
+  unsafe { slice::from_raw_parts_mut(values.as_mut_ptr().cast(), mem::size_of_val(values)) }
+}
+
+// 基本型の配列の中身をCと同じメモリ表現のバイト列として扱う
+fn raw_bytes(data: &ArrayData) -> Result<&[u8], VmError> {
+  Ok(match data {
+    ArrayData::Boolean(v) | ArrayData::Byte(v) => as_bytes(v),
+    ArrayData::Char(v) => as_bytes(v),
+    ArrayData::Short(v) => as_bytes(v),
+    ArrayData::Int(v) => as_bytes(v),
+    ArrayData::Long(v) => as_bytes(v),
+    ArrayData::Float(v) => as_bytes(v),
+    ArrayData::Double(v) => as_bytes(v),
+    ArrayData::Ref(_) => return Err(VmError::internal("Not a primitive array")),
+  })
+}
+
+fn raw_bytes_mut(data: &mut ArrayData) -> Result<&mut [u8], VmError> {
+  Ok(match data {
+    ArrayData::Boolean(v) | ArrayData::Byte(v) => as_bytes_mut(v),
+    ArrayData::Char(v) => as_bytes_mut(v),
+    ArrayData::Short(v) => as_bytes_mut(v),
+    ArrayData::Int(v) => as_bytes_mut(v),
+    ArrayData::Long(v) => as_bytes_mut(v),
+    ArrayData::Float(v) => as_bytes_mut(v),
+    ArrayData::Double(v) => as_bytes_mut(v),
+    ArrayData::Ref(_) => return Err(VmError::internal("Not a primitive array")),
+  })
+}
+
+fn reflection_unsupported() -> VmError {
+  VmError::java("java/lang/UnsupportedOperationException", "Reflection is not supported")
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CallKind {
+  Virtual,
+  Nonvirtual,
+  Static,
+}
+
+impl Vm {
+  // JNI関数の中で起きたエラー: Javaの例外は保留し、内部エラーはネイティブメソッドから戻った時に報告する
+  fn jni_error(&mut self, error: VmError) {
+    match self.materialize(error) {
+      Ok(exception) => self.pending_exception = Some(exception),
+      Err(error) => self.jni.error = Some(error),
+    }
+  }
+
+  // jmethodIDはメソッドの番号 + 1
+  fn jni_method(&self, method: MethodId) -> Result<Rc<RuntimeMethod>, VmError> {
+    self.methods.get(method.wrapping_sub(1)).cloned()
+      .ok_or_else(|| VmError::internal(format!("Invalid jmethodID: {:#x}", method)))
+  }
+
+  // jfieldIDはクラスIDとフィールドの位置を詰めて + 1
+  fn jni_field(&self, field: FieldId) -> Result<(ClassId, usize), VmError> {
+    let raw = field.wrapping_sub(1);
+    let (class, index) = (raw >> 16, raw & 0xffff);
+    match self.classes.get(class).and_then(|c| c.fields.get(index)) {
+      Some(field) => Ok((class, field.slot)),
+      None => Err(VmError::internal(format!("Invalid jfieldID: {:#x}", field))),
+    }
+  }
+
+  fn find_jni_field(&mut self, class: Handle, name: *const c_char, signature: *const c_char, is_static: bool) -> Result<FieldId, VmError> {
+    let class = self.resolve_class_handle(class)?;
+    let (name, descriptor) = unsafe { (c_string(name)?, c_string(signature)?) };
+    self.initialize(class)?;
+    match self.find_field(class, &name, &descriptor) {
+      Some((owner, index)) if self.classes[owner].fields[index].is_static() == is_static => Ok(((owner << 16) | index) + 1),
+      _ => Err(VmError::java("java/lang/NoSuchFieldError", name)),
+    }
+  }
+
+  fn call_jni_method<T: JniValue>(&mut self, kind: CallKind, receiver: Handle, method: MethodId, source: &mut impl ArgumentSource) -> Result<T, VmError> {
+    let method = self.jni_method(method)?;
+    let args = self.read_arguments(&method, source)?;
+    let receiver = match kind {
+      CallKind::Static => None,
+      _ => Some(self.resolve_non_null(receiver)?),
+    };
+    let method = match receiver {
+      Some(object) if kind == CallKind::Virtual && !method.is_private() && method.name != "<init>" => {
+        let class = self.object_class(object)?;
+        self.select_method(class, &method)?
+      },
+      _ => method,
+    };
+    if kind == CallKind::Static {
+      self.initialize(method.class)?;
+    }
+    let args = receiver.map(Value::Ref).into_iter().chain(args).collect();
+    match self.invoke(method, args)? {
+      Some(value) => T::from_value(self, value),
+      None => Ok(T::default()),
+    }
+  }
+
+  fn new_jni_object(&mut self, class: Handle, method: MethodId, source: &mut impl ArgumentSource) -> Result<Handle, VmError> {
+    let class = self.resolve_class_handle(class)?;
+    let method = self.jni_method(method)?;
+    let args = self.read_arguments(&method, source)?;
+    let object = JniEnv::new(self).alloc_object(class)?;
+    let args = [Value::Ref(object)].into_iter().chain(args).collect();
+    self.invoke(method, args)?;
+    Ok(self.new_local_handle(Some(object)))
+  }
+
+  // 要素型がtag (Noneなら任意の基本型) の配列
+  fn primitive_array(&self, array: Handle, tag: Option<u8>) -> Result<ObjRef, VmError> {
+    let array = self.resolve_non_null(array)?;
+    let class = self.object_class(array)?;
+    match (&self.classes[class].component, tag) {
+      (Some(component), Some(tag)) if *component == primitive_type(tag)? => Ok(array),
+      (Some(component), None) if !component.is_reference() => Ok(array),
+      _ => Err(VmError::internal(format!("{} is not a primitive array of the expected type", self.classes[class].java_name()))),
+    }
+  }
+
+  fn array_elements(&mut self, array: Handle, tag: Option<u8>, is_copy: *mut u8) -> Result<*mut u8, VmError> {
+    let array = self.primitive_array(array, tag)?;
+    let bytes = raw_bytes(self.heap.get(array)?.array()?)?.to_vec();
+    if !is_copy.is_null() {
+      unsafe { *is_copy = 1 };
+    }
+    Ok(self.jni.new_buffer(&bytes))
+  }
+
+  // JNI_COMMITなら書き戻すだけ、JNI_ABORTなら解放するだけ、0なら両方
+  fn release_array_elements(&mut self, array: Handle, elements: *mut u8, mode: i32) -> Result<(), VmError> {
+    let array = self.primitive_array(array, None)?;
+    if mode != JNI_ABORT {
+      let data = raw_bytes_mut(self.heap.get_mut(array)?.array_mut()?)?;
+      unsafe { ptr::copy_nonoverlapping(elements, data.as_mut_ptr(), data.len()) };
+    }
+    if mode != JNI_COMMIT {
+      self.jni.free_buffer(elements);
+    }
+    Ok(())
+  }
+
+  // 文字列の一部 (StringIndexOutOfBoundsException)
+  fn string_region(&self, string: Handle, start: i32, length: i32) -> Result<Vec<u16>, VmError> {
+    let chars = self.string_chars(self.resolve_non_null(string)?)?;
+    if start < 0 || length < 0 || start as usize + length as usize > chars.len() {
+      return Err(VmError::java_without_message("java/lang/StringIndexOutOfBoundsException"));
+    }
+    Ok(chars[start as usize..(start + length) as usize].to_vec())
+  }
+}
+
+// バージョン情報
+
+unsafe extern "C" fn get_version(_env: *mut RawEnv) -> i32 {
+  JNI_VERSION_10
+}
+
+// クラス
+
+unsafe extern "C" fn define_class(env: *mut RawEnv, name: *const c_char, _loader: Handle, bytes: *const u8, length: i32) -> Handle {
+  with_vm(env, |vm| {
+    let name = match name.is_null() {
+      true => None,
+      false => Some(unsafe { c_string(name)? }),
+    };
+    let bytes = unsafe { slice::from_raw_parts(bytes, length.max(0) as usize) };
+    let class = vm.define_class_bytes(name.as_deref(), bytes)?;
+    Ok(class_handle(class))
+  })
+}
+
+unsafe extern "C" fn find_class(env: *mut RawEnv, name: *const c_char) -> Handle {
+  with_vm(env, |vm| {
+    let name = unsafe { c_string(name)? };
+    let class = JniEnv::new(vm).find_class(&name)?;
+    vm.initialize(class)?;
+    Ok(class_handle(class))
+  })
+}
+
+unsafe extern "C" fn from_reflected(env: *mut RawEnv, _object: Handle) -> usize {
+  with_vm(env, |_| Err(reflection_unsupported()))
+}
+
+unsafe extern "C" fn to_reflected(env: *mut RawEnv, _class: Handle, _id: usize, _is_static: u8) -> Handle {
+  with_vm(env, |_| Err(reflection_unsupported()))
+}
+
+unsafe extern "C" fn get_superclass(env: *mut RawEnv, class: Handle) -> Handle {
+  with_vm(env, |vm| {
+    let class = vm.resolve_class_handle(class)?;
+    Ok(JniEnv::new(vm).get_superclass(class).map_or(0, class_handle))
+  })
+}
+
+unsafe extern "C" fn is_assignable_from(env: *mut RawEnv, from: Handle, to: Handle) -> u8 {
+  with_vm(env, |vm| {
+    let (from, to) = (vm.resolve_class_handle(from)?, vm.resolve_class_handle(to)?);
+    Ok(JniEnv::new(vm).is_assignable_from(from, to) as u8)
+  })
+}
+
+// 例外
+
+unsafe extern "C" fn throw(env: *mut RawEnv, exception: Handle) -> i32 {
+  with_status(env, |vm| {
+    let exception = vm.resolve_non_null(exception)?;
+    JniEnv::new(vm).throw(exception);
+    Ok(())
+  })
+}
+
+unsafe extern "C" fn throw_new(env: *mut RawEnv, class: Handle, message: *const c_char) -> i32 {
+  with_status(env, |vm| {
+    let class = vm.resolve_class_handle(class)?;
+    let message = match message.is_null() {
+      true => None,
+      false => Some(unsafe { c_string(message)? }),
+    };
+    let name = vm.classes[class].name.clone();
+    JniEnv::new(vm).throw_new(&name, message.as_deref())
+  })
+}
+
+unsafe extern "C" fn exception_occurred(env: *mut RawEnv) -> Handle {
+  with_vm(env, |vm| {
+    let exception = JniEnv::new(vm).exception_occurred();
+    Ok(vm.new_local_handle(exception))
+  })
+}
+
+// 保留中の例外のスタックトレースを表示して、例外をクリアする
+unsafe extern "C" fn exception_describe(env: *mut RawEnv) {
+  with_vm(env, |vm| {
+    if let Some(exception) = vm.pending_exception.take() {
+      eprint!("Exception in thread \"main\" {}", vm.stack_trace_text(exception)?);
+    }
+    Ok(())
+  })
+}
+
+unsafe extern "C" fn exception_clear(env: *mut RawEnv) {
+  with_vm(env, |vm| {
+    JniEnv::new(vm).exception_clear();
+    Ok(())
+  })
+}
+
+unsafe extern "C" fn fatal_error(env: *mut RawEnv, message: *const c_char) {
+  let vm = unsafe { &mut *(*env).vm };
+  let message = unsafe { c_string(message) }.unwrap_or_default();
+  JniEnv::new(vm).fatal_error(&message)
+}
+
+unsafe extern "C" fn exception_check(env: *mut RawEnv) -> u8 {
+  with_vm(env, |vm| Ok(JniEnv::new(vm).exception_check() as u8))
+}
+
+// 参照
+
+unsafe extern "C" fn push_local_frame(env: *mut RawEnv, _capacity: i32) -> i32 {
+  with_status(env, |vm| {
+    let mark = vm.handles.mark();
+    vm.jni.local_frames.push(mark);
+    Ok(())
+  })
+}
+
+// フレーム内のローカル参照を解放し、resultだけを外側のフレームの参照として返す
+unsafe extern "C" fn pop_local_frame(env: *mut RawEnv, result: Handle) -> Handle {
+  with_vm(env, |vm| {
+    let object = match vm.resolve_class_handle(result) {
+      Ok(_) => None,
+      Err(_) => Some(vm.resolve_handle(result)?),
+    };
+    if let Some(mark) = vm.jni.local_frames.pop() {
+      vm.handles.release(mark);
+    }
+    Ok(match object {
+      Some(object) => vm.new_local_handle(object),
+      None => result,
+    })
+  })
+}
+
+unsafe extern "C" fn new_global_ref(env: *mut RawEnv, object: Handle) -> Handle {
+  with_vm(env, |vm| vm.new_global_handle(object))
+}
+
+unsafe extern "C" fn delete_ref(env: *mut RawEnv, object: Handle) {
+  with_vm(env, |vm| {
+    vm.delete_handle(object);
+    Ok(())
+  })
+}
+
+unsafe extern "C" fn is_same_object(env: *mut RawEnv, a: Handle, b: Handle) -> u8 {
+  with_vm(env, |vm| Ok(vm.same_handle(a, b)? as u8))
+}
+
+unsafe extern "C" fn new_local_ref(env: *mut RawEnv, object: Handle) -> Handle {
+  with_vm(env, |vm| {
+    if vm.resolve_class_handle(object).is_ok() {
+      return Ok(object);
+    }
+    let object = vm.resolve_handle(object)?;
+    Ok(vm.new_local_handle(object))
+  })
+}
+
+// ローカル参照の数に上限はない
+unsafe extern "C" fn ensure_local_capacity(_env: *mut RawEnv, _capacity: i32) -> i32 {
+  JNI_OK
+}
+
+unsafe extern "C" fn get_object_ref_type(_env: *mut RawEnv, object: Handle) -> i32 {
+  ref_type(object)
+}
+
+// オブジェクト
+
+unsafe extern "C" fn alloc_object(env: *mut RawEnv, class: Handle) -> Handle {
+  with_vm(env, |vm| {
+    let class = vm.resolve_class_handle(class)?;
+    let object = JniEnv::new(vm).alloc_object(class)?;
+    Ok(vm.new_local_handle(Some(object)))
+  })
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn new_object(
+  env: *mut RawEnv, class: Handle, method: MethodId, a3: u64, a4: u64, a5: u64,
+  f0: f64, f1: f64, f2: f64, f3: f64, f4: f64, f5: f64, f6: f64, f7: f64, stack: StackArguments,
+) -> Handle {
+  let mut args = VarArgs::new(&[a3, a4, a5], [f0, f1, f2, f3, f4, f5, f6, f7], stack);
+  with_vm(env, |vm| vm.new_jni_object(class, method, &mut args))
+}
+
+unsafe extern "C" fn new_object_v(env: *mut RawEnv, class: Handle, method: MethodId, args: *mut VaList) -> Handle {
+  with_vm(env, |vm| vm.new_jni_object(class, method, &mut VaListArgs(args)))
+}
+
+unsafe extern "C" fn new_object_a(env: *mut RawEnv, class: Handle, method: MethodId, args: *const u64) -> Handle {
+  with_vm(env, |vm| vm.new_jni_object(class, method, &mut JValueArgs(args)))
+}
+
+unsafe extern "C" fn get_object_class(env: *mut RawEnv, object: Handle) -> Handle {
+  with_vm(env, |vm| {
+    let object = vm.resolve_non_null(object)?;
+    Ok(class_handle(JniEnv::new(vm).get_object_class(object)?))
+  })
+}
+
+unsafe extern "C" fn is_instance_of(env: *mut RawEnv, object: Handle, class: Handle) -> u8 {
+  with_vm(env, |vm| {
+    let (object, class) = (vm.resolve_handle(object)?, vm.resolve_class_handle(class)?);
+    Ok(JniEnv::new(vm).is_instance_of(object, class)? as u8)
+  })
+}
+
+// メソッド呼び出し
+
+unsafe extern "C" fn get_method_id(env: *mut RawEnv, class: Handle, name: *const c_char, signature: *const c_char) -> MethodId {
+  with_vm(env, |vm| {
+    let class = vm.resolve_class_handle(class)?;
+    let (name, descriptor) = unsafe { (c_string(name)?, c_string(signature)?) };
+    Ok(JniEnv::new(vm).get_method(class, &name, &descriptor)?.id + 1)
+  })
+}
+
+unsafe extern "C" fn get_static_method_id(env: *mut RawEnv, class: Handle, name: *const c_char, signature: *const c_char) -> MethodId {
+  with_vm(env, |vm| {
+    let class = vm.resolve_class_handle(class)?;
+    let (name, descriptor) = unsafe { (c_string(name)?, c_string(signature)?) };
+    Ok(JniEnv::new(vm).get_static_method(class, &name, &descriptor)?.id + 1)
+  })
+}
+
+// 可変長引数の関数は、残りの整数レジスタと浮動小数点数レジスタ、スタックをすべて受け取る
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn call_method<T: JniValue>(
+  env: *mut RawEnv, object: Handle, method: MethodId, a3: u64, a4: u64, a5: u64,
+  f0: f64, f1: f64, f2: f64, f3: f64, f4: f64, f5: f64, f6: f64, f7: f64, stack: StackArguments,
+) -> T {
+  let mut args = VarArgs::new(&[a3, a4, a5], [f0, f1, f2, f3, f4, f5, f6, f7], stack);
+  with_vm(env, |vm| vm.call_jni_method(CallKind::Virtual, object, method, &mut args))
+}
+
+unsafe extern "C" fn call_method_v<T: JniValue>(env: *mut RawEnv, object: Handle, method: MethodId, args: *mut VaList) -> T {
+  with_vm(env, |vm| vm.call_jni_method(CallKind::Virtual, object, method, &mut VaListArgs(args)))
+}
+
+unsafe extern "C" fn call_method_a<T: JniValue>(env: *mut RawEnv, object: Handle, method: MethodId, args: *const u64) -> T {
+  with_vm(env, |vm| vm.call_jni_method(CallKind::Virtual, object, method, &mut JValueArgs(args)))
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn call_nonvirtual_method<T: JniValue>(
+  env: *mut RawEnv, object: Handle, _class: Handle, method: MethodId, a4: u64, a5: u64,
+  f0: f64, f1: f64, f2: f64, f3: f64, f4: f64, f5: f64, f6: f64, f7: f64, stack: StackArguments,
+) -> T {
+  let mut args = VarArgs::new(&[a4, a5], [f0, f1, f2, f3, f4, f5, f6, f7], stack);
+  with_vm(env, |vm| vm.call_jni_method(CallKind::Nonvirtual, object, method, &mut args))
+}
+
+unsafe extern "C" fn call_nonvirtual_method_v<T: JniValue>(env: *mut RawEnv, object: Handle, _class: Handle, method: MethodId, args: *mut VaList) -> T {
+  with_vm(env, |vm| vm.call_jni_method(CallKind::Nonvirtual, object, method, &mut VaListArgs(args)))
+}
+
+unsafe extern "C" fn call_nonvirtual_method_a<T: JniValue>(env: *mut RawEnv, object: Handle, _class: Handle, method: MethodId, args: *const u64) -> T {
+  with_vm(env, |vm| vm.call_jni_method(CallKind::Nonvirtual, object, method, &mut JValueArgs(args)))
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn call_static_method<T: JniValue>(
+  env: *mut RawEnv, _class: Handle, method: MethodId, a3: u64, a4: u64, a5: u64,
+  f0: f64, f1: f64, f2: f64, f3: f64, f4: f64, f5: f64, f6: f64, f7: f64, stack: StackArguments,
+) -> T {
+  let mut args = VarArgs::new(&[a3, a4, a5], [f0, f1, f2, f3, f4, f5, f6, f7], stack);
+  with_vm(env, |vm| vm.call_jni_method(CallKind::Static, 0, method, &mut args))
+}
+
+unsafe extern "C" fn call_static_method_v<T: JniValue>(env: *mut RawEnv, _class: Handle, method: MethodId, args: *mut VaList) -> T {
+  with_vm(env, |vm| vm.call_jni_method(CallKind::Static, 0, method, &mut VaListArgs(args)))
+}
+
+unsafe extern "C" fn call_static_method_a<T: JniValue>(env: *mut RawEnv, _class: Handle, method: MethodId, args: *const u64) -> T {
+  with_vm(env, |vm| vm.call_jni_method(CallKind::Static, 0, method, &mut JValueArgs(args)))
+}
+
+// フィールド
+
+unsafe extern "C" fn get_field_id(env: *mut RawEnv, class: Handle, name: *const c_char, signature: *const c_char) -> FieldId {
+  with_vm(env, |vm| vm.find_jni_field(class, name, signature, false))
+}
+
+unsafe extern "C" fn get_static_field_id(env: *mut RawEnv, class: Handle, name: *const c_char, signature: *const c_char) -> FieldId {
+  with_vm(env, |vm| vm.find_jni_field(class, name, signature, true))
+}
+
+unsafe extern "C" fn get_field<T: JniValue>(env: *mut RawEnv, object: Handle, field: FieldId) -> T {
+  with_vm(env, |vm| {
+    let object = vm.resolve_non_null(object)?;
+    let (_, slot) = vm.jni_field(field)?;
+    let value = vm.heap.get(object)?.fields()?.get(slot).copied()
+      .ok_or_else(|| VmError::internal(format!("Invalid jfieldID: {:#x}", field)))?;
+    T::from_value(vm, value)
+  })
+}
+
+unsafe extern "C" fn set_field<T: JniValue>(env: *mut RawEnv, object: Handle, field: FieldId, value: T) {
+  with_vm(env, |vm| {
+    let object = vm.resolve_non_null(object)?;
+    let (_, slot) = vm.jni_field(field)?;
+    let value = value.into_value(vm)?;
+    match vm.heap.get_mut(object)?.fields_mut()?.get_mut(slot) {
+      Some(target) => *target = value,
+      None => return Err(VmError::internal(format!("Invalid jfieldID: {:#x}", field))),
+    }
+    Ok(())
+  })
+}
+
+unsafe extern "C" fn get_static_field<T: JniValue>(env: *mut RawEnv, _class: Handle, field: FieldId) -> T {
+  with_vm(env, |vm| {
+    let (class, slot) = vm.jni_field(field)?;
+    vm.initialize(class)?;
+    let value = vm.classes[class].static_values[slot];
+    T::from_value(vm, value)
+  })
+}
+
+unsafe extern "C" fn set_static_field<T: JniValue>(env: *mut RawEnv, _class: Handle, field: FieldId, value: T) {
+  with_vm(env, |vm| {
+    let (class, slot) = vm.jni_field(field)?;
+    vm.initialize(class)?;
+    vm.classes[class].static_values[slot] = value.into_value(vm)?;
+    Ok(())
+  })
+}
+
+// 文字列
+
+unsafe extern "C" fn new_string(env: *mut RawEnv, chars: *const u16, length: i32) -> Handle {
+  with_vm(env, |vm| {
+    let chars = match length {
+      0 => &[][..],
+      _ => unsafe { slice::from_raw_parts(chars, length.max(0) as usize) },
+    };
+    let string = vm.new_string_utf16(chars)?;
+    Ok(vm.new_local_handle(Some(string)))
+  })
+}
+
+unsafe extern "C" fn get_string_length(env: *mut RawEnv, string: Handle) -> i32 {
+  with_vm(env, |vm| {
+    let string = vm.resolve_non_null(string)?;
+    Ok(JniEnv::new(vm).get_string_length(string)? as i32)
+  })
+}
+
+unsafe extern "C" fn get_string_chars(env: *mut RawEnv, string: Handle, is_copy: *mut u8) -> *const u16 {
+  with_vm(env, |vm| {
+    let chars = vm.string_chars(vm.resolve_non_null(string)?)?;
+    if !is_copy.is_null() {
+      unsafe { *is_copy = 1 };
+    }
+    Ok(vm.jni.new_buffer(as_bytes(&chars)) as usize)
+  }) as *const u16
+}
+
+unsafe extern "C" fn release_string_chars(env: *mut RawEnv, _string: Handle, chars: *const u16) {
+  with_vm(env, |vm| {
+    vm.jni.free_buffer(chars as *const u8);
+    Ok(())
+  })
+}
+
+unsafe extern "C" fn new_string_utf(env: *mut RawEnv, bytes: *const c_char) -> Handle {
+  with_vm(env, |vm| {
+    if bytes.is_null() {
+      return Ok(0);
+    }
+    let chars = mutf8::decode(unsafe { CStr::from_ptr(bytes) }.to_bytes());
+    let string = vm.new_string_utf16(&chars)?;
+    Ok(vm.new_local_handle(Some(string)))
+  })
+}
+
+unsafe extern "C" fn get_string_utf_length(env: *mut RawEnv, string: Handle) -> i32 {
+  with_vm(env, |vm| {
+    let chars = vm.string_chars(vm.resolve_non_null(string)?)?;
+    Ok(mutf8::encode(&chars).len() as i32)
+  })
+}
+
+unsafe extern "C" fn get_string_utf_chars(env: *mut RawEnv, string: Handle, is_copy: *mut u8) -> *const c_char {
+  with_vm(env, |vm| {
+    let chars = vm.string_chars(vm.resolve_non_null(string)?)?;
+    if !is_copy.is_null() {
+      unsafe { *is_copy = 1 };
+    }
+    Ok(vm.jni.new_buffer(&mutf8::encode(&chars)) as usize)
+  }) as *const c_char
+}
+
+unsafe extern "C" fn release_string_utf_chars(env: *mut RawEnv, _string: Handle, chars: *const c_char) {
+  with_vm(env, |vm| {
+    vm.jni.free_buffer(chars as *const u8);
+    Ok(())
+  })
+}
+
+unsafe extern "C" fn get_string_region(env: *mut RawEnv, string: Handle, start: i32, length: i32, buffer: *mut u16) {
+  with_vm(env, |vm| {
+    let chars = vm.string_region(string, start, length)?;
+    unsafe { ptr::copy_nonoverlapping(chars.as_ptr(), buffer, chars.len()) };
+    Ok(())
+  })
+}
+
+// 修正UTF-8に変換して、NULL終端まで書き込む
+unsafe extern "C" fn get_string_utf_region(env: *mut RawEnv, string: Handle, start: i32, length: i32, buffer: *mut c_char) {
+  with_vm(env, |vm| {
+    let bytes = mutf8::encode(&vm.string_region(string, start, length)?);
+    unsafe {
+      ptr::copy_nonoverlapping(bytes.as_ptr(), buffer as *mut u8, bytes.len());
+      *buffer.add(bytes.len()) = 0;
+    }
+    Ok(())
+  })
+}
+
+// 配列
+
+unsafe extern "C" fn get_array_length(env: *mut RawEnv, array: Handle) -> i32 {
+  with_vm(env, |vm| {
+    let array = vm.resolve_non_null(array)?;
+    Ok(JniEnv::new(vm).get_array_length(array)? as i32)
+  })
+}
+
+unsafe extern "C" fn new_object_array(env: *mut RawEnv, length: i32, class: Handle, initial: Handle) -> Handle {
+  with_vm(env, |vm| {
+    let (class, initial) = (vm.resolve_class_handle(class)?, vm.resolve_handle(initial)?);
+    let array = JniEnv::new(vm).new_object_array(length, class, initial)?;
+    Ok(vm.new_local_handle(Some(array)))
+  })
+}
+
+unsafe extern "C" fn get_object_array_element(env: *mut RawEnv, array: Handle, index: i32) -> Handle {
+  with_vm(env, |vm| {
+    let array = vm.resolve_non_null(array)?;
+    let element = JniEnv::new(vm).get_object_array_element(array, index)?;
+    Ok(vm.new_local_handle(element))
+  })
+}
+
+unsafe extern "C" fn set_object_array_element(env: *mut RawEnv, array: Handle, index: i32, value: Handle) {
+  with_vm(env, |vm| {
+    let (array, value) = (vm.resolve_non_null(array)?, vm.resolve_handle(value)?);
+    JniEnv::new(vm).set_object_array_element(array, index, value)
+  })
+}
+
+// 基本型の配列の関数は、要素型を記述子の文字 (b'I'など) で区別する
+unsafe extern "C" fn new_primitive_array<const TYPE: u8>(env: *mut RawEnv, length: i32) -> Handle {
+  with_vm(env, |vm| {
+    let array = JniEnv::new(vm).new_array(&primitive_type(TYPE)?, length)?;
+    Ok(vm.new_local_handle(Some(array)))
+  })
+}
+
+unsafe extern "C" fn get_array_elements<const TYPE: u8>(env: *mut RawEnv, array: Handle, is_copy: *mut u8) -> *mut u8 {
+  with_vm(env, |vm| vm.array_elements(array, Some(TYPE), is_copy).map(|p| p as usize)) as *mut u8
+}
+
+unsafe extern "C" fn release_array_elements<const TYPE: u8>(env: *mut RawEnv, array: Handle, elements: *mut u8, mode: i32) {
+  with_vm(env, |vm| vm.release_array_elements(array, elements, mode))
+}
+
+unsafe extern "C" fn get_array_region<const TYPE: u8>(env: *mut RawEnv, array: Handle, start: i32, length: i32, buffer: *mut u8) {
+  with_vm(env, |vm| {
+    let array = vm.primitive_array(array, Some(TYPE))?;
+    let region = JniEnv::new(vm).get_array_region(array, start, length)?;
+    let bytes = raw_bytes(&region)?;
+    unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), buffer, bytes.len()) };
+    Ok(())
+  })
+}
+
+unsafe extern "C" fn set_array_region<const TYPE: u8>(env: *mut RawEnv, array: Handle, start: i32, length: i32, buffer: *const u8) {
+  with_vm(env, |vm| {
+    let array = vm.primitive_array(array, Some(TYPE))?;
+    let mut region = ArrayData::new(&primitive_type(TYPE)?, length.max(0) as usize);
+    let bytes = raw_bytes_mut(&mut region)?;
+    unsafe { ptr::copy_nonoverlapping(buffer, bytes.as_mut_ptr(), bytes.len()) };
+    match length < 0 {
+      true => Err(VmError::java("java/lang/ArrayIndexOutOfBoundsException", format!(
+        "Array region {}..{} out of bounds for length {}", start, start as i64 + length as i64, JniEnv::new(vm).get_array_length(array)?,
+      ))),
+      false => JniEnv::new(vm).set_array_region(array, start, &region),
+    }
+  })
+}
+
+// GCでオブジェクトが動かないので、Criticalもコピーを渡すだけでよい
+unsafe extern "C" fn get_primitive_array_critical(env: *mut RawEnv, array: Handle, is_copy: *mut u8) -> *mut u8 {
+  with_vm(env, |vm| vm.array_elements(array, None, is_copy).map(|p| p as usize)) as *mut u8
+}
+
+unsafe extern "C" fn release_primitive_array_critical(env: *mut RawEnv, array: Handle, elements: *mut u8, mode: i32) {
+  with_vm(env, |vm| vm.release_array_elements(array, elements, mode))
+}
+
+// ネイティブメソッドの登録
+
+unsafe extern "C" fn register_natives(env: *mut RawEnv, class: Handle, methods: *const NativeMethod, count: i32) -> i32 {
+  with_status(env, |vm| {
+    let class = vm.resolve_class_handle(class)?;
+    let methods = unsafe { slice::from_raw_parts(methods, count.max(0) as usize) };
+    for native in methods {
+      let (name, descriptor) = unsafe { (c_string(native.name)?, c_string(native.signature)?) };
+      let method = vm.classes[class].find_method(&name, &descriptor)
+        .filter(|method| method.access_flags & ACC_NATIVE != 0)
+        .ok_or_else(|| VmError::java("java/lang/NoSuchMethodError", format!(
+          "Method '{}' name or signature does not match", external_name(&vm.classes[class].java_name(), &name, &descriptor),
+        )))?;
+      vm.jni.bound.insert(method.id, native.function as usize);
+    }
+    Ok(())
+  })
+}
+
+unsafe extern "C" fn unregister_natives(env: *mut RawEnv, class: Handle) -> i32 {
+  with_status(env, |vm| {
+    let class = vm.resolve_class_handle(class)?;
+    for method in &vm.classes[class].methods {
+      vm.jni.bound.remove(&method.id);
+    }
+    Ok(())
+  })
+}
+
+// "void Foo.bar(int)"の形式 (記述子が読めなければ名前と記述子をそのまま並べる)
+fn external_name(class: &str, name: &str, descriptor: &str) -> String {
+  match crate::util::descriptor::MethodDescriptor::parse(descriptor) {
+    Ok(signature) => {
+      let return_type = signature.return_type.map_or("void".to_string(), |t| t.to_string());
+      let parameters = signature.parameters.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ");
+      format!("{} {}.{}({})", return_type, class, name, parameters)
+    },
+    Err(_) => format!("{}.{}{}", class, name, descriptor),
+  }
+}
+
+// モニタ (synchronizedブロックと同じロック)
+
+unsafe extern "C" fn monitor_enter(env: *mut RawEnv, object: Handle) -> i32 {
+  with_status(env, |vm| {
+    let object = vm.resolve_non_null(object)?;
+    vm.monitor_enter(vm.object_lock(object));
+    Ok(())
+  })
+}
+
+unsafe extern "C" fn monitor_exit(env: *mut RawEnv, object: Handle) -> i32 {
+  with_status(env, |vm| {
+    let object = vm.resolve_non_null(object)?;
+    vm.monitor_exit(vm.object_lock(object))
+  })
+}
+
+unsafe extern "C" fn get_java_vm(env: *mut RawEnv, java_vm: *mut *mut RawJavaVm) -> i32 {
+  with_status(env, |vm| {
+    let pointer: *mut Vm = vm;
+    unsafe { *java_vm = vm.jni.java_vm(pointer) };
+    Ok(())
+  })
+}
+
+// ダイレクトバッファとモジュールには対応しない
+
+unsafe extern "C" fn new_direct_byte_buffer(_env: *mut RawEnv, _address: *mut c_void, _capacity: i64) -> Handle {
+  0
+}
+
+unsafe extern "C" fn get_direct_buffer_address(_env: *mut RawEnv, _buffer: Handle) -> *mut c_void {
+  ptr::null_mut()
+}
+
+unsafe extern "C" fn get_direct_buffer_capacity(_env: *mut RawEnv, _buffer: Handle) -> i64 {
+  -1
+}
+
+unsafe extern "C" fn get_module(_env: *mut RawEnv, _class: Handle) -> Handle {
+  0
+}
+
+// JavaVMの関数 (スレッドはmainだけなので、どのスレッドにも同じJNIEnvを渡す)
+
+unsafe fn attach(java_vm: *mut RawJavaVm, env: *mut *mut RawEnv) -> i32 {
+  unsafe {
+    let vm = (*java_vm).vm;
+    *env = (*vm).jni.env(vm);
+  }
+  JNI_OK
+}
+
+unsafe extern "C" fn destroy_java_vm(_java_vm: *mut RawJavaVm) -> i32 {
+  JNI_ERR
+}
+
+unsafe extern "C" fn attach_current_thread(java_vm: *mut RawJavaVm, env: *mut *mut RawEnv, _args: *mut c_void) -> i32 {
+  unsafe { attach(java_vm, env) }
+}
+
+unsafe extern "C" fn detach_current_thread(_java_vm: *mut RawJavaVm) -> i32 {
+  JNI_OK
+}
+
+unsafe extern "C" fn get_env(java_vm: *mut RawJavaVm, env: *mut *mut RawEnv, version: i32) -> i32 {
+  if !is_supported_version(version) {
+    unsafe { *env = ptr::null_mut() };
+    return JNI_EVERSION;
+  }
+  unsafe { attach(java_vm, env) }
+}
+
+// 関数テーブル (JNINativeInterfaceの並び順)
+
+fn call_functions<T: JniValue>() -> [*const (); 3] {
+  [call_method::<T> as *const (), call_method_v::<T> as *const (), call_method_a::<T> as *const ()]
+}
+
+fn call_nonvirtual_functions<T: JniValue>() -> [*const (); 3] {
+  [call_nonvirtual_method::<T> as *const (), call_nonvirtual_method_v::<T> as *const (), call_nonvirtual_method_a::<T> as *const ()]
+}
+
+fn call_static_functions<T: JniValue>() -> [*const (); 3] {
+  [call_static_method::<T> as *const (), call_static_method_v::<T> as *const (), call_static_method_a::<T> as *const ()]
+}
+
+fn build_native_table() -> Vec<usize> {
+  let mut table = vec![ptr::null(); 4];
+  table.extend([
+    get_version as *const (),
+    define_class as *const (),
+    find_class as *const (),
+    from_reflected as *const (),
+    from_reflected as *const (),
+    to_reflected as *const (),
+    get_superclass as *const (),
+    is_assignable_from as *const (),
+    to_reflected as *const (),
+    throw as *const (),
+    throw_new as *const (),
+    exception_occurred as *const (),
+    exception_describe as *const (),
+    exception_clear as *const (),
+    fatal_error as *const (),
+    push_local_frame as *const (),
+    pop_local_frame as *const (),
+    new_global_ref as *const (),
+    delete_ref as *const (),
+    delete_ref as *const (),
+    is_same_object as *const (),
+    new_local_ref as *const (),
+    ensure_local_capacity as *const (),
+    alloc_object as *const (),
+    new_object as *const (),
+    new_object_v as *const (),
+    new_object_a as *const (),
+    get_object_class as *const (),
+    is_instance_of as *const (),
+    get_method_id as *const (),
+  ]);
+  for functions in [
+    call_functions::<Handle>(), call_functions::<u8>(), call_functions::<i8>(), call_functions::<u16>(), call_functions::<i16>(),
+    call_functions::<i32>(), call_functions::<i64>(), call_functions::<f32>(), call_functions::<f64>(), call_functions::<()>(),
+    call_nonvirtual_functions::<Handle>(), call_nonvirtual_functions::<u8>(), call_nonvirtual_functions::<i8>(),
+    call_nonvirtual_functions::<u16>(), call_nonvirtual_functions::<i16>(), call_nonvirtual_functions::<i32>(),
+    call_nonvirtual_functions::<i64>(), call_nonvirtual_functions::<f32>(), call_nonvirtual_functions::<f64>(),
+    call_nonvirtual_functions::<()>(),
+  ] {
+    table.extend(functions);
+  }
+  table.extend([
+    get_field_id as *const (),
+    get_field::<Handle> as *const (), get_field::<u8> as *const (), get_field::<i8> as *const (), get_field::<u16> as *const (),
+    get_field::<i16> as *const (), get_field::<i32> as *const (), get_field::<i64> as *const (), get_field::<f32> as *const (),
+    get_field::<f64> as *const (),
+    set_field::<Handle> as *const (), set_field::<u8> as *const (), set_field::<i8> as *const (), set_field::<u16> as *const (),
+    set_field::<i16> as *const (), set_field::<i32> as *const (), set_field::<i64> as *const (), set_field::<f32> as *const (),
+    set_field::<f64> as *const (),
+    get_static_method_id as *const (),
+  ]);
+  for functions in [
+    call_static_functions::<Handle>(), call_static_functions::<u8>(), call_static_functions::<i8>(),
+    call_static_functions::<u16>(), call_static_functions::<i16>(), call_static_functions::<i32>(),
+    call_static_functions::<i64>(), call_static_functions::<f32>(), call_static_functions::<f64>(),
+    call_static_functions::<()>(),
+  ] {
+    table.extend(functions);
+  }
+  table.extend([
+    get_static_field_id as *const (),
+    get_static_field::<Handle> as *const (), get_static_field::<u8> as *const (), get_static_field::<i8> as *const (),
+    get_static_field::<u16> as *const (), get_static_field::<i16> as *const (), get_static_field::<i32> as *const (),
+    get_static_field::<i64> as *const (), get_static_field::<f32> as *const (), get_static_field::<f64> as *const (),
+    set_static_field::<Handle> as *const (), set_static_field::<u8> as *const (), set_static_field::<i8> as *const (),
+    set_static_field::<u16> as *const (), set_static_field::<i16> as *const (), set_static_field::<i32> as *const (),
+    set_static_field::<i64> as *const (), set_static_field::<f32> as *const (), set_static_field::<f64> as *const (),
+    new_string as *const (),
+    get_string_length as *const (),
+    get_string_chars as *const (),
+    release_string_chars as *const (),
+    new_string_utf as *const (),
+    get_string_utf_length as *const (),
+    get_string_utf_chars as *const (),
+    release_string_utf_chars as *const (),
+    get_array_length as *const (),
+    new_object_array as *const (),
+    get_object_array_element as *const (),
+    set_object_array_element as *const (),
+    new_primitive_array::<b'Z'> as *const (), new_primitive_array::<b'B'> as *const (), new_primitive_array::<b'C'> as *const (),
+    new_primitive_array::<b'S'> as *const (), new_primitive_array::<b'I'> as *const (), new_primitive_array::<b'J'> as *const (),
+    new_primitive_array::<b'F'> as *const (), new_primitive_array::<b'D'> as *const (),
+    get_array_elements::<b'Z'> as *const (), get_array_elements::<b'B'> as *const (), get_array_elements::<b'C'> as *const (),
+    get_array_elements::<b'S'> as *const (), get_array_elements::<b'I'> as *const (), get_array_elements::<b'J'> as *const (),
+    get_array_elements::<b'F'> as *const (), get_array_elements::<b'D'> as *const (),
+    release_array_elements::<b'Z'> as *const (), release_array_elements::<b'B'> as *const (), release_array_elements::<b'C'> as *const (),
+    release_array_elements::<b'S'> as *const (), release_array_elements::<b'I'> as *const (), release_array_elements::<b'J'> as *const (),
+    release_array_elements::<b'F'> as *const (), release_array_elements::<b'D'> as *const (),
+    get_array_region::<b'Z'> as *const (), get_array_region::<b'B'> as *const (), get_array_region::<b'C'> as *const (),
+    get_array_region::<b'S'> as *const (), get_array_region::<b'I'> as *const (), get_array_region::<b'J'> as *const (),
+    get_array_region::<b'F'> as *const (), get_array_region::<b'D'> as *const (),
+    set_array_region::<b'Z'> as *const (), set_array_region::<b'B'> as *const (), set_array_region::<b'C'> as *const (),
+    set_array_region::<b'S'> as *const (), set_array_region::<b'I'> as *const (), set_array_region::<b'J'> as *const (),
+    set_array_region::<b'F'> as *const (), set_array_region::<b'D'> as *const (),
+    register_natives as *const (),
+    unregister_natives as *const (),
+    monitor_enter as *const (),
+    monitor_exit as *const (),
+    get_java_vm as *const (),
+    get_string_region as *const (),
+    get_string_utf_region as *const (),
+    get_primitive_array_critical as *const (),
+    release_primitive_array_critical as *const (),
+    get_string_chars as *const (),
+    release_string_chars as *const (),
+    new_global_ref as *const (),
+    delete_ref as *const (),
+    exception_check as *const (),
+    new_direct_byte_buffer as *const (),
+    get_direct_buffer_address as *const (),
+    get_direct_buffer_capacity as *const (),
+    get_object_ref_type as *const (),
+    get_module as *const (),
+  ]);
+  assert_eq!(table.len(), NATIVE_FUNCTIONS);
+  table.into_iter().map(|function| function as usize).collect()
+}
+
+pub fn native_table() -> *const usize {
+  static TABLE: OnceLock<Vec<usize>> = OnceLock::new();
+  TABLE.get_or_init(build_native_table).as_ptr()
+}
+
+pub fn invoke_table() -> *const usize {
+  static TABLE: OnceLock<Vec<usize>> = OnceLock::new();
+  TABLE.get_or_init(|| [
+    ptr::null(),
+    ptr::null(),
+    ptr::null(),
+    destroy_java_vm as *const (),
+    attach_current_thread as *const (),
+    detach_current_thread as *const (),
+    get_env as *const (),
+    attach_current_thread as *const (),
+  ].into_iter().map(|function| function as usize).collect()).as_ptr()
+}
@@ -0,0 +1,128 @@
+use std::{
+  ffi::{c_char, c_int, c_void, CStr, CString},
+  mem,
+  path::{Path, PathBuf},
+  ptr,
+};
+
+use crate::runtime::{
+  class::RuntimeMethod,
+  error::VmError,
+  jni::functions::{is_supported_version, RawJavaVm},
+  vm::Vm,
+};
+
+const RTLD_LAZY: c_int = 0x0001;
+
+unsafe extern "C" {
+  fn dlopen(filename: *const c_char, flags: c_int) -> *mut c_void;
+  fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+  fn dlerror() -> *mut c_char;
+}
+
+type OnLoad = unsafe extern "C" fn(*mut RawJavaVm, *mut c_void) -> i32;
+
+// dlopenで読み込んだ共有ライブラリ (JVMと同じく、一度読み込んだら解放しない)
+pub struct NativeLibrary {
+  pub path: PathBuf,
+  handle: *mut c_void,
+}
+
+impl NativeLibrary {
+  fn symbol(&self, name: &str) -> Option<usize> {
+    let name = CString::new(name).ok()?;
+    let address = unsafe { dlsym(self.handle, name.as_ptr()) };
+    (!address.is_null()).then_some(address as usize)
+  }
+}
+
+fn link_error(message: String) -> VmError {
+  VmError::java("java/lang/UnsatisfiedLinkError", message)
+}
+
+// JNIの名前の符号化: '/'は'_'、'_'は"_1"、';'は"_2"、'['は"_3"、ASCII以外は"_0xxxx"
+fn mangle(name: &str) -> String {
+  let mut mangled = String::new();
+  for c in name.encode_utf16() {
+    match char::from_u32(c as u32) {
+      Some('/') => mangled.push('_'),
+      Some('_') => mangled.push_str("_1"),
+      Some(';') => mangled.push_str("_2"),
+      Some('[') => mangled.push_str("_3"),
+      Some(c) if c.is_ascii_alphanumeric() => mangled.push(c),
+      _ => mangled.push_str(&format!("_0{:04x}", c)),
+    }
+  }
+  mangled
+}
+
+// 短い名前 (Java_クラス_メソッド) と、オーバーロード用の長い名前 (__引数の記述子を付ける)
+fn symbol_names(method: &RuntimeMethod) -> [String; 2] {
+  let short = format!("Java_{}_{}", mangle(&method.class_name), mangle(&method.name));
+  let arguments = method.descriptor.split_once(')').map_or("", |(arguments, _)| arguments.trim_start_matches('('));
+  let long = format!("{}__{}", short, mangle(arguments));
+  [short, long]
+}
+
+impl Vm {
+  // System.load(): 絶対パスで指定された共有ライブラリを読み込み、JNI_OnLoadを呼ぶ
+  pub fn load_library(&mut self, path: &Path) -> Result<(), VmError> {
+    if !cfg!(all(target_arch = "x86_64", target_os = "linux")) {
+      return Err(link_error("Native libraries are only supported on x86-64 Linux".to_string()));
+    }
+    let Ok(path) = path.canonicalize() else {
+      return Err(link_error(format!("Can't load library: {}", path.display())));
+    };
+    if self.jni.libraries.iter().any(|library| library.path == path) {
+      return Ok(());
+    }
+    let name = CString::new(path.to_string_lossy().as_bytes())
+      .map_err(|_| link_error(format!("Can't load library: {}", path.display())))?;
+    let handle = unsafe { dlopen(name.as_ptr(), RTLD_LAZY) };
+    if handle.is_null() {
+      let error = unsafe { dlerror() };
+      let message = match error.is_null() {
+        true => "unknown error".to_string(),
+        false => unsafe { CStr::from_ptr(error) }.to_string_lossy().into_owned(),
+      };
+      return Err(link_error(format!("{}: {}", path.display(), message)));
+    }
+    let library = NativeLibrary { path: path.clone(), handle };
+    let on_load = library.symbol("JNI_OnLoad");
+    self.jni.libraries.push(library);
+    match on_load {
+      Some(on_load) => self.call_on_load(on_load, &path),
+      None => Ok(()),
+    }
+  }
+
+  fn call_on_load(&mut self, on_load: usize, path: &Path) -> Result<(), VmError> {
+    let mark = self.handles.mark();
+    let vm: *mut Vm = self;
+    let java_vm = self.jni.java_vm(vm);
+    let version = unsafe { mem::transmute::<usize, OnLoad>(on_load)(java_vm, ptr::null_mut()) };
+    self.handles.release(mark);
+    if let Some(error) = self.jni.error.take() {
+      return Err(error);
+    }
+    if let Some(exception) = self.pending_exception.take() {
+      return Err(VmError::Thrown(exception));
+    }
+    if !is_supported_version(version) {
+      return Err(link_error(format!("unsupported JNI version 0x{:08x} required by {}", version, path.display())));
+    }
+    Ok(())
+  }
+
+  // RegisterNativesで登録した関数か、読み込んだライブラリのJava_で始まるシンボルを探す
+  pub(crate) fn find_jni_function(&mut self, method: &RuntimeMethod) -> Option<usize> {
+    if let Some(&address) = self.jni.bound.get(&method.id) {
+      return Some(address);
+    }
+    let names = symbol_names(method);
+    let address = self.jni.libraries.iter()
+      .find_map(|library| names.iter().find_map(|name| library.symbol(name)))?;
+    self.jni.bound.insert(method.id, address);
+    Some(address)
+  }
+}
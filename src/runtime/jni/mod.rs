@@ -0,0 +1,5 @@
+pub mod call;
+pub mod functions;
+pub mod library;
+pub mod reference;
+pub mod state;
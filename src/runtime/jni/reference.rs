@@ -0,0 +1,97 @@
+use crate::runtime::{
+  class::ClassId,
+  error::VmError,
+  value::{ObjRef, Value},
+  vm::Vm,
+};
+
+// jobjectの下位2ビットで参照の種類を表し、残りのビットにハンドルの番号を入れる
+// (クラスはオブジェクトを持たないので、jclassはクラスIDをそのまま入れる)
+const TAG_BITS: usize = 2;
+const TAG_MASK: usize = (1 << TAG_BITS) - 1;
+const LOCAL: usize = 1;
+const GLOBAL: usize = 2;
+const CLASS: usize = 3;
+
+// GetObjectRefTypeの戻り値
+pub const JNI_INVALID_REF_TYPE: i32 = 0;
+pub const JNI_LOCAL_REF_TYPE: i32 = 1;
+pub const JNI_GLOBAL_REF_TYPE: i32 = 2;
+
+pub type Handle = usize;
+
+pub fn class_handle(class: ClassId) -> Handle {
+  (class << TAG_BITS) | CLASS
+}
+
+pub fn ref_type(handle: Handle) -> i32 {
+  match handle & TAG_MASK {
+    LOCAL => JNI_LOCAL_REF_TYPE,
+    GLOBAL | CLASS => JNI_GLOBAL_REF_TYPE,
+    _ => JNI_INVALID_REF_TYPE,
+  }
+}
+
+impl Vm {
+  pub(crate) fn new_local_handle(&mut self, object: Option<ObjRef>) -> Handle {
+    match object {
+      Some(object) => (self.handles.push_local(object) << TAG_BITS) | LOCAL,
+      None => 0,
+    }
+  }
+
+  pub(crate) fn value_handle(&mut self, value: Value) -> Result<Handle, VmError> {
+    Ok(self.new_local_handle(value.as_ref()?))
+  }
+
+  pub(crate) fn new_global_handle(&mut self, handle: Handle) -> Result<Handle, VmError> {
+    if handle & TAG_MASK == CLASS {
+      return Ok(handle);
+    }
+    Ok(match self.resolve_handle(handle)? {
+      Some(object) => (self.handles.new_global(object) << TAG_BITS) | GLOBAL,
+      None => 0,
+    })
+  }
+
+  pub(crate) fn delete_handle(&mut self, handle: Handle) {
+    match handle & TAG_MASK {
+      LOCAL => self.handles.delete_local(handle >> TAG_BITS),
+      GLOBAL => self.handles.delete_global(handle >> TAG_BITS),
+      _ => {},
+    }
+  }
+
+  pub(crate) fn resolve_handle(&self, handle: Handle) -> Result<Option<ObjRef>, VmError> {
+    let object = match handle & TAG_MASK {
+      _ if handle == 0 => return Ok(None),
+      LOCAL => self.handles.local(handle >> TAG_BITS),
+      GLOBAL => self.handles.global(handle >> TAG_BITS),
+      CLASS => return Err(VmError::internal("java.lang.Class objects are not supported")),
+      _ => None,
+    };
+    object.map(Some).ok_or_else(|| VmError::internal(format!("Invalid JNI reference: {:#x}", handle)))
+  }
+
+  // 参照先がnullならNullPointerException
+  pub(crate) fn resolve_non_null(&self, handle: Handle) -> Result<ObjRef, VmError> {
+    self.resolve_handle(handle)?.ok_or_else(|| VmError::java_without_message("java/lang/NullPointerException"))
+  }
+
+  pub(crate) fn resolve_class_handle(&self, handle: Handle) -> Result<ClassId, VmError> {
+    match handle & TAG_MASK {
+      CLASS if handle >> TAG_BITS < self.classes.len() => Ok(handle >> TAG_BITS),
+      _ if handle == 0 => Err(VmError::java_without_message("java/lang/NullPointerException")),
+      _ => Err(VmError::internal(format!("Invalid JNI class reference: {:#x}", handle))),
+    }
+  }
+
+  // IsSameObject: クラス同士はクラスIDで比べる
+  pub(crate) fn same_handle(&self, a: Handle, b: Handle) -> Result<bool, VmError> {
+    match (a & TAG_MASK == CLASS, b & TAG_MASK == CLASS) {
+      (true, true) => Ok(a == b),
+      (false, false) => Ok(self.resolve_handle(a)? == self.resolve_handle(b)?),
+      _ => Ok(false),
+    }
+  }
+}
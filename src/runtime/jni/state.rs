@@ -0,0 +1,65 @@
+use std::{collections::HashMap, ptr};
+
+use crate::runtime::{
+  error::VmError,
+  jni::{
+    functions::{invoke_table, native_table, RawEnv, RawJavaVm},
+    library::NativeLibrary,
+  },
+  vm::Vm,
+};
+
+// Cのネイティブメソッドとやり取りするための状態
+pub struct JniState {
+  pub(crate) libraries: Vec<NativeLibrary>,
+  // メソッドIDと、dlsymやRegisterNativesで結び付けた関数のアドレス
+  pub(crate) bound: HashMap<usize, usize>,
+  // GetStringCharsやGet<Type>ArrayElementsで渡したバッファ (Release時に解放する)
+  pub(crate) buffers: HashMap<usize, Vec<u64>>,
+  // PushLocalFrameで積んだローカル参照の位置
+  pub(crate) local_frames: Vec<usize>,
+  // JNI関数の中で起きた内部エラー (ネイティブメソッドから戻った時に報告する)
+  pub(crate) error: Option<VmError>,
+  env: Box<RawEnv>,
+  java_vm: Box<RawJavaVm>,
+}
+
+impl Default for JniState {
+  fn default() -> Self {
+    JniState {
+      libraries: Vec::new(),
+      bound: HashMap::new(),
+      buffers: HashMap::new(),
+      local_frames: Vec::new(),
+      error: None,
+      env: Box::new(RawEnv { functions: native_table(), vm: ptr::null_mut() }),
+      java_vm: Box::new(RawJavaVm { functions: invoke_table(), vm: ptr::null_mut() }),
+    }
+  }
+}
+
+impl JniState {
+  // Vmは移動することがあるので、Cに渡す直前にポインタを更新する
+  pub(crate) fn env(&mut self, vm: *mut Vm) -> *mut RawEnv {
+    self.env.vm = vm;
+    &mut *self.env
+  }
+
+  pub(crate) fn java_vm(&mut self, vm: *mut Vm) -> *mut RawJavaVm {
+    self.java_vm.vm = vm;
+    &mut *self.java_vm
+  }
+
+  // バッファを確保し、Release時まで保持する
+  pub(crate) fn new_buffer(&mut self, bytes: &[u8]) -> *mut u8 {
+    let mut buffer = vec![0u64; bytes.len() / 8 + 1];
+    let pointer = buffer.as_mut_ptr() as *mut u8;
+    unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), pointer, bytes.len()) };
+    self.buffers.insert(pointer as usize, buffer);
+    pointer
+  }
+
+  pub(crate) fn free_buffer(&mut self, pointer: *const u8) {
+    self.buffers.remove(&(pointer as usize));
+  }
+}
@@ -0,0 +1,73 @@
+use std::{io::{self, Write}, path::Path};
+
+use crate::runtime::{options::parse_launch_options, trace, vm::Vm};
+
+pub fn launch(program: &str, args: &[String]) -> i32 {
+  start(program, "run", args)
+}
+
+// debugサブコマンド: runと同じオプションで起動し、デバッガのコマンドを受け付ける
+pub fn debug(program: &str, args: &[String]) -> i32 {
+  start(program, "debug", args)
+}
+
+fn start(program: &str, command: &str, args: &[String]) -> i32 {
+  let options = match parse_launch_options(args) {
+    Ok(options) => options,
+    Err(e) => {
+      eprintln!("Error: {}", e);
+      eprintln!("Usage: {} {} [-cp <path>] [-Xbootclasspath:<path>] [-D<name>=<value>] [-Xmx<size>] [-verbose:gc] [-Xint] [-XX:CompileThreshold=<n>] [-XX:+PrintCompilation] [--trace] [--trace-filter=<pattern>[,<pattern>...]] [--trace-file=<path>] [--profile] [--profile-collapsed=<path>] [--profile-json=<path>] [-agentlib:jdwp=transport=dt_socket,server=y,suspend=y,address=<port>] <main class | class file> [args...]", program, command);
+      return 2;
+    },
+  };
+  let mut vm = match Vm::new(options.vm) {
+    Ok(vm) => vm,
+    Err(e) => {
+      eprintln!("Error: failed to initialize VM: {}", e);
+      return 1;
+    },
+  };
+  if let Some(jdwp) = vm.options.jdwp.clone()
+    && let Err(e) = vm.attach_jdwp(&jdwp) {
+    eprintln!("Error: {}", e);
+    return 1;
+  }
+  if command == "debug" {
+    vm.attach_debugger();
+  }
+  let status = match vm.run_main(&options.main_class, &options.args) {
+    Ok(()) => 0,
+    Err(e) => {
+      vm.report_uncaught(e);
+      1
+    },
+  };
+  // デーモンでないスレッドが全て終了するまで待つ
+  vm.wait_for_threads();
+  vm.finish_trace();
+  vm.finish_jdwp();
+  vm.finish_profile();
+  let _ = io::stdout().flush();
+  if vm.options.verbose_gc {
+    vm.print_gc_stats();
+  }
+  if command == "debug" {
+    println!("The program exited with status {}", status);
+  }
+  status
+}
+
+// --trace-fileで書き出したトレースファイルをテキストで表示する
+pub fn replay(program: &str, args: &[String]) -> i32 {
+  let [path] = args else {
+    eprintln!("Usage: {} replay <trace file>", program);
+    return 2;
+  };
+  match trace::replay(Path::new(path), io::stdout().lock()) {
+    Ok(()) => 0,
+    Err(e) => {
+      eprintln!("Error: {}: {}", path, e);
+      1
+    },
+  }
+}
@@ -0,0 +1,363 @@
+use crate::{
+  runtime::{
+    builtin::{object_clone, string_result, this},
+    class::{ClassDefinition, NativeFn, ACC_FINAL, ACC_PRIVATE, ACC_PUBLIC, ACC_SUPER, ACC_VOLATILE},
+    error::VmError,
+    memory::{add_values, Location, MemoryOrder},
+    value::{ObjRef, Value},
+    vm::Vm,
+  },
+  util::descriptor::FieldType,
+};
+
+// java.util.concurrent.atomicのクラス (値のフィールドの型)
+const SCALARS: &[(&str, &str)] = &[
+  ("java/util/concurrent/atomic/AtomicInteger", "I"),
+  ("java/util/concurrent/atomic/AtomicLong", "J"),
+  ("java/util/concurrent/atomic/AtomicBoolean", "Z"),
+  ("java/util/concurrent/atomic/AtomicReference", "Ljava/lang/Object;"),
+];
+
+const ARRAYS: &[(&str, &str)] = &[
+  ("java/util/concurrent/atomic/AtomicIntegerArray", "I"),
+  ("java/util/concurrent/atomic/AtomicLongArray", "J"),
+  ("java/util/concurrent/atomic/AtomicReferenceArray", "Ljava/lang/Object;"),
+];
+
+// 値の型に関係なく使えるメソッド ({V}は値の型に置き換える)
+const ACCESS_METHODS: &[(&str, &str, NativeFn)] = &[
+  ("get", "(){V}", atomic_get),
+  ("set", "({V})V", atomic_set),
+  ("lazySet", "({V})V", atomic_set_release),
+  ("getPlain", "(){V}", atomic_get_plain),
+  ("setPlain", "({V})V", atomic_set_plain),
+  ("getOpaque", "(){V}", atomic_get_opaque),
+  ("setOpaque", "({V})V", atomic_set_opaque),
+  ("getAcquire", "(){V}", atomic_get_acquire),
+  ("setRelease", "({V})V", atomic_set_release),
+  ("getAndSet", "({V}){V}", atomic_get_and_set),
+  ("compareAndSet", "({V}{V})Z", atomic_compare_and_set),
+  ("weakCompareAndSet", "({V}{V})Z", atomic_weak_compare_and_set_plain),
+  ("weakCompareAndSetPlain", "({V}{V})Z", atomic_weak_compare_and_set_plain),
+  ("weakCompareAndSetVolatile", "({V}{V})Z", atomic_compare_and_set),
+  ("weakCompareAndSetAcquire", "({V}{V})Z", atomic_weak_compare_and_set_acquire),
+  ("weakCompareAndSetRelease", "({V}{V})Z", atomic_weak_compare_and_set_release),
+  ("compareAndExchange", "({V}{V}){V}", atomic_compare_and_exchange),
+  ("compareAndExchangeAcquire", "({V}{V}){V}", atomic_compare_and_exchange_acquire),
+  ("compareAndExchangeRelease", "({V}{V}){V}", atomic_compare_and_exchange_release),
+  ("toString", "()Ljava/lang/String;", atomic_to_string),
+];
+
+// int、longの値だけが持つメソッド
+const ARITHMETIC_METHODS: &[(&str, &str, NativeFn)] = &[
+  ("getAndIncrement", "(){V}", atomic_get_and_increment),
+  ("getAndDecrement", "(){V}", atomic_get_and_decrement),
+  ("getAndAdd", "({V}){V}", atomic_get_and_add),
+  ("incrementAndGet", "(){V}", atomic_increment_and_get),
+  ("decrementAndGet", "(){V}", atomic_decrement_and_get),
+  ("addAndGet", "({V}){V}", atomic_add_and_get),
+];
+
+const NUMBER_VALUES: &[(&str, &str, NativeFn)] = &[
+  ("intValue", "()I", atomic_int_value),
+  ("longValue", "()J", atomic_long_value),
+  ("floatValue", "()F", atomic_float_value),
+  ("doubleValue", "()D", atomic_double_value),
+];
+
+pub fn define(vm: &mut Vm) -> Result<(), VmError> {
+  for &(name, value_type) in SCALARS {
+    let mut definition = ClassDefinition::new(name)
+      .access_flags(ACC_PUBLIC | ACC_SUPER)
+      .interface("java/io/Serializable")
+      .field("value", value_type, ACC_PRIVATE | ACC_VOLATILE)
+      .native("<init>", "()V", ACC_PUBLIC, atomic_init)
+      .native("<init>", &format!("({})V", value_type), ACC_PUBLIC, atomic_set);
+    definition = methods(definition, ACCESS_METHODS, "", value_type);
+    if matches!(value_type, "I" | "J") {
+      definition = methods(definition.super_class(Some("java/lang/Number")), ARITHMETIC_METHODS, "", value_type);
+      definition = methods(definition, NUMBER_VALUES, "", value_type);
+    }
+    vm.define_class(definition)?;
+  }
+  for &(name, value_type) in ARRAYS {
+    let mut definition = ClassDefinition::new(name)
+      .access_flags(ACC_PUBLIC | ACC_SUPER)
+      .interface("java/io/Serializable")
+      .field("array", &format!("[{}", value_type), ACC_PRIVATE | ACC_FINAL)
+      .native("<init>", "(I)V", ACC_PUBLIC, atomic_array_init_length)
+      .native("<init>", &format!("([{})V", value_type), ACC_PUBLIC, atomic_array_init_copy)
+      .native("length", "()I", ACC_PUBLIC | ACC_FINAL, atomic_array_length);
+    definition = methods(definition, ACCESS_METHODS, "I", value_type);
+    if matches!(value_type, "I" | "J") {
+      definition = methods(definition, ARITHMETIC_METHODS, "I", value_type);
+    }
+    vm.define_class(definition)?;
+  }
+  Ok(())
+}
+
+// 配列版は添字を最初の引数として受け取る
+fn methods(mut definition: ClassDefinition, table: &[(&str, &str, NativeFn)], index: &str, value_type: &str) -> ClassDefinition {
+  for &(name, descriptor, function) in table {
+    let descriptor = descriptor.replace("{V}", value_type);
+    let descriptor = match name {
+      "toString" | "intValue" | "longValue" | "floatValue" | "doubleValue" => descriptor,
+      _ => descriptor.replacen('(', &format!("({}", index), 1),
+    };
+    definition = definition.native(name, &descriptor, ACC_PUBLIC | ACC_FINAL, function);
+  }
+  definition
+}
+
+// 名前だけでインスタンスフィールドを探す (位置と型)
+fn own_field(vm: &Vm, object: ObjRef, name: &str) -> Result<Option<(usize, FieldType)>, VmError> {
+  let mut current = Some(vm.object_class(object)?);
+  while let Some(class) = current {
+    if let Some(field) = vm.classes[class].fields.iter().find(|field| field.name == name && !field.is_static()) {
+      let field_type = FieldType::parse(&field.descriptor).map_err(VmError::internal)?;
+      return Ok(Some((field.slot, field_type)));
+    }
+    current = vm.classes[class].super_class;
+  }
+  Ok(None)
+}
+
+fn backing_array(vm: &Vm, object: ObjRef) -> Result<Option<(ObjRef, FieldType)>, VmError> {
+  let Some((slot, array_type)) = own_field(vm, object, "array")? else { return Ok(None) };
+  let array = vm.load(Location::Field(object, slot))?.as_ref()?
+    .ok_or_else(|| VmError::internal("Atomic array without array"))?;
+  Ok(Some((array, array_type)))
+}
+
+// 値を持つ変数の位置と、残りの引数
+fn cell<'a>(vm: &Vm, args: &'a [Value]) -> Result<(Location, &'a [Value]), VmError> {
+  let this = this(args)?;
+  if let Some((array, _)) = backing_array(vm, this)? {
+    return Ok((vm.element_location(array, args[1].as_int()?)?, &args[2..]));
+  }
+  let (slot, _) = own_field(vm, this, "value")?
+    .ok_or_else(|| VmError::internal("Atomic class without value"))?;
+  Ok((Location::Field(this, slot), &args[1..]))
+}
+
+fn get(vm: &mut Vm, args: &[Value], order: MemoryOrder) -> Result<Option<Value>, VmError> {
+  let (location, _) = cell(vm, args)?;
+  Ok(Some(vm.load_ordered(location, order)?))
+}
+
+fn set(vm: &mut Vm, args: &[Value], order: MemoryOrder) -> Result<Option<Value>, VmError> {
+  let (location, values) = cell(vm, args)?;
+  vm.store_ordered(location, values[0], order)?;
+  Ok(None)
+}
+
+fn compare_and_set(vm: &mut Vm, args: &[Value], order: MemoryOrder) -> Result<Option<Value>, VmError> {
+  let (location, values) = cell(vm, args)?;
+  let result = vm.compare_and_set(location, values[0], values[1], order)?;
+  Ok(Some(Value::Int(result as i32)))
+}
+
+fn compare_and_exchange(vm: &mut Vm, args: &[Value], order: MemoryOrder) -> Result<Option<Value>, VmError> {
+  let (location, values) = cell(vm, args)?;
+  Ok(Some(vm.compare_and_exchange(location, values[0], values[1], order)?))
+}
+
+// 変数の型に合わせた整数
+fn integer_like(current: Value, n: i64) -> Value {
+  match current {
+    Value::Long(_) => Value::Long(n),
+    _ => Value::Int(n as i32),
+  }
+}
+
+// 加算して、加算前か加算後の値を返す
+fn add(vm: &mut Vm, location: Location, delta: impl FnOnce(Value) -> Value, updated: bool) -> Result<Option<Value>, VmError> {
+  let delta = delta(vm.load(location)?);
+  let previous = vm.get_and_add(location, delta, MemoryOrder::Volatile)?;
+  Ok(Some(if updated { add_values(previous, delta)? } else { previous }))
+}
+
+fn atomic_init(_vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(None)
+}
+
+fn atomic_get(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  get(vm, args, MemoryOrder::Volatile)
+}
+
+fn atomic_set(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  set(vm, args, MemoryOrder::Volatile)
+}
+
+fn atomic_get_plain(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  get(vm, args, MemoryOrder::Plain)
+}
+
+fn atomic_set_plain(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  set(vm, args, MemoryOrder::Plain)
+}
+
+fn atomic_get_opaque(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  get(vm, args, MemoryOrder::Opaque)
+}
+
+fn atomic_set_opaque(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  set(vm, args, MemoryOrder::Opaque)
+}
+
+fn atomic_get_acquire(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  get(vm, args, MemoryOrder::Acquire)
+}
+
+// lazySet()もsetRelease()と同じ
+fn atomic_set_release(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  set(vm, args, MemoryOrder::Release)
+}
+
+fn atomic_get_and_set(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (location, values) = cell(vm, args)?;
+  Ok(Some(vm.get_and_set(location, values[0], MemoryOrder::Volatile)?))
+}
+
+fn atomic_compare_and_set(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  compare_and_set(vm, args, MemoryOrder::Volatile)
+}
+
+// 弱いcompareAndSetも見かけ上の失敗はしない
+fn atomic_weak_compare_and_set_plain(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  compare_and_set(vm, args, MemoryOrder::Plain)
+}
+
+fn atomic_weak_compare_and_set_acquire(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  compare_and_set(vm, args, MemoryOrder::Acquire)
+}
+
+fn atomic_weak_compare_and_set_release(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  compare_and_set(vm, args, MemoryOrder::Release)
+}
+
+fn atomic_compare_and_exchange(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  compare_and_exchange(vm, args, MemoryOrder::Volatile)
+}
+
+fn atomic_compare_and_exchange_acquire(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  compare_and_exchange(vm, args, MemoryOrder::Acquire)
+}
+
+fn atomic_compare_and_exchange_release(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  compare_and_exchange(vm, args, MemoryOrder::Release)
+}
+
+fn atomic_get_and_increment(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (location, _) = cell(vm, args)?;
+  add(vm, location, |current| integer_like(current, 1), false)
+}
+
+fn atomic_get_and_decrement(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (location, _) = cell(vm, args)?;
+  add(vm, location, |current| integer_like(current, -1), false)
+}
+
+fn atomic_get_and_add(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (location, values) = cell(vm, args)?;
+  let delta = values[0];
+  add(vm, location, |_| delta, false)
+}
+
+fn atomic_increment_and_get(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (location, _) = cell(vm, args)?;
+  add(vm, location, |current| integer_like(current, 1), true)
+}
+
+fn atomic_decrement_and_get(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (location, _) = cell(vm, args)?;
+  add(vm, location, |current| integer_like(current, -1), true)
+}
+
+fn atomic_add_and_get(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (location, values) = cell(vm, args)?;
+  let delta = values[0];
+  add(vm, location, |_| delta, true)
+}
+
+fn number_value(vm: &mut Vm, args: &[Value]) -> Result<i64, VmError> {
+  match atomic_get(vm, args)? {
+    Some(Value::Long(v)) => Ok(v),
+    Some(value) => Ok(value.as_int()? as i64),
+    None => Err(VmError::internal("Atomic value missing")),
+  }
+}
+
+fn atomic_int_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(number_value(vm, args)? as i32)))
+}
+
+fn atomic_long_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Long(number_value(vm, args)?)))
+}
+
+fn atomic_float_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Float(number_value(vm, args)? as f32)))
+}
+
+fn atomic_double_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Double(number_value(vm, args)? as f64)))
+}
+
+// 値の文字列表現、配列版は [1, 2, 3]
+fn atomic_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let Some((array, array_type)) = backing_array(vm, this)? else {
+    let (slot, value_type) = own_field(vm, this, "value")?
+      .ok_or_else(|| VmError::internal("Atomic class without value"))?;
+    let value = vm.load_ordered(Location::Field(this, slot), MemoryOrder::Volatile)?;
+    let text = vm.stringify(value, &value_type)?;
+    return string_result(vm, &text);
+  };
+  let FieldType::Array(component) = array_type else {
+    return Err(VmError::internal("Atomic array is not an array"));
+  };
+  let length = vm.heap.get(array)?.array()?.len();
+  let mut text: Vec<u16> = "[".encode_utf16().collect();
+  for index in 0..length {
+    if index > 0 {
+      text.extend(", ".encode_utf16());
+    }
+    let value = vm.load_ordered(Location::Element(array, index), MemoryOrder::Volatile)?;
+    text.extend(vm.stringify(value, &component)?);
+  }
+  text.push(']' as u16);
+  string_result(vm, &text)
+}
+
+fn atomic_array_init_length(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let (slot, array_type) = own_field(vm, this, "array")?
+    .ok_or_else(|| VmError::internal("Atomic array without array"))?;
+  let FieldType::Array(component) = array_type else {
+    return Err(VmError::internal("Atomic array is not an array"));
+  };
+  let array = vm.new_array(&component, args[1].as_int()?)?;
+  vm.store(Location::Field(this, slot), Value::Ref(array))?;
+  Ok(None)
+}
+
+// 渡された配列のコピーを持つ
+fn atomic_array_init_copy(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  if args[1] == Value::Null {
+    return Err(VmError::null_pointer("Cannot invoke \"Object.clone()\" because \"array\" is null"));
+  }
+  let (slot, _) = own_field(vm, this, "array")?
+    .ok_or_else(|| VmError::internal("Atomic array without array"))?;
+  let copy = object_clone(vm, &args[1..])?.unwrap_or(Value::Null);
+  vm.store(Location::Field(this, slot), copy)?;
+  Ok(None)
+}
+
+fn atomic_array_length(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (array, _) = backing_array(vm, this(args)?)?
+    .ok_or_else(|| VmError::internal("Atomic array without array"))?;
+  Ok(Some(Value::Int(vm.heap.get(array)?.array()?.len() as i32)))
+}
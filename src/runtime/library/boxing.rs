@@ -0,0 +1,1030 @@
+use std::cmp::Ordering;
+
+use crate::{
+  runtime::{
+    builtin::{string_result, this},
+    class::{ClassDefinition, NativeFn, ACC_ABSTRACT, ACC_FINAL, ACC_PRIVATE, ACC_PUBLIC, ACC_STATIC, ACC_SUPER},
+    error::VmError,
+    heap::ArrayData,
+    library::string::code_point_chars,
+    method_handle::{wrapped_type, wrapper_class},
+    value::{ObjRef, Value},
+    vm::Vm,
+  },
+  util::descriptor::FieldType,
+};
+
+// valueOf()が同じオブジェクトを返す範囲 (Character以外は-128..=127)
+const CACHE_LOW: i64 = -128;
+const CACHE_HIGH: i64 = 127;
+
+// 数値のラッパークラスのxxxValue()
+const NUMBER_VALUES: &[(&str, &str, NativeFn)] = &[
+  ("byteValue", "()B", wrapper_byte_value),
+  ("shortValue", "()S", wrapper_short_value),
+  ("intValue", "()I", wrapper_int_value),
+  ("longValue", "()J", wrapper_long_value),
+  ("floatValue", "()F", wrapper_float_value),
+  ("doubleValue", "()D", wrapper_double_value),
+];
+
+pub fn define(vm: &mut Vm) -> Result<(), VmError> {
+  vm.define_class(
+    ClassDefinition::new("java/lang/Number")
+      .access_flags(ACC_PUBLIC | ACC_ABSTRACT | ACC_SUPER)
+      .interface("java/io/Serializable")
+      .native("<init>", "()V", ACC_PUBLIC, number_init)
+      .abstract_method("intValue", "()I", ACC_PUBLIC)
+      .abstract_method("longValue", "()J", ACC_PUBLIC)
+      .abstract_method("floatValue", "()F", ACC_PUBLIC)
+      .abstract_method("doubleValue", "()D", ACC_PUBLIC)
+      .native("byteValue", "()B", ACC_PUBLIC, number_byte_value)
+      .native("shortValue", "()S", ACC_PUBLIC, number_short_value)
+  )?;
+
+  vm.define_class(
+    wrapper("java/lang/Integer", "I")
+      .native("valueOf", "(I)Ljava/lang/Integer;", ACC_PUBLIC | ACC_STATIC, integer_value_of)
+      .native("valueOf", "(Ljava/lang/String;)Ljava/lang/Integer;", ACC_PUBLIC | ACC_STATIC, integer_value_of_string)
+      .native("parseInt", "(Ljava/lang/String;)I", ACC_PUBLIC | ACC_STATIC, integer_parse)
+      .native("parseInt", "(Ljava/lang/String;I)I", ACC_PUBLIC | ACC_STATIC, integer_parse)
+      .native("toString", "(I)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, number_to_string)
+      .native("toString", "(II)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, integer_to_string_radix)
+      .native("toHexString", "(I)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, integer_to_hex_string)
+      .native("toOctalString", "(I)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, integer_to_octal_string)
+      .native("toBinaryString", "(I)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, integer_to_binary_string)
+      .native("hashCode", "(I)I", ACC_PUBLIC | ACC_STATIC, integer_hash)
+      .native("compare", "(II)I", ACC_PUBLIC | ACC_STATIC, integer_compare)
+      .native("signum", "(I)I", ACC_PUBLIC | ACC_STATIC, integer_signum)
+      .native("max", "(II)I", ACC_PUBLIC | ACC_STATIC, integer_max)
+      .native("min", "(II)I", ACC_PUBLIC | ACC_STATIC, integer_min)
+      .native("sum", "(II)I", ACC_PUBLIC | ACC_STATIC, integer_sum)
+      .native("bitCount", "(I)I", ACC_PUBLIC | ACC_STATIC, integer_bit_count)
+      .native("numberOfLeadingZeros", "(I)I", ACC_PUBLIC | ACC_STATIC, integer_leading_zeros)
+      .native("numberOfTrailingZeros", "(I)I", ACC_PUBLIC | ACC_STATIC, integer_trailing_zeros)
+      .native("reverse", "(I)I", ACC_PUBLIC | ACC_STATIC, integer_reverse)
+  )?;
+  vm.define_class(
+    wrapper("java/lang/Long", "J")
+      .native("valueOf", "(J)Ljava/lang/Long;", ACC_PUBLIC | ACC_STATIC, long_value_of)
+      .native("valueOf", "(Ljava/lang/String;)Ljava/lang/Long;", ACC_PUBLIC | ACC_STATIC, long_value_of_string)
+      .native("parseLong", "(Ljava/lang/String;)J", ACC_PUBLIC | ACC_STATIC, long_parse)
+      .native("parseLong", "(Ljava/lang/String;I)J", ACC_PUBLIC | ACC_STATIC, long_parse)
+      .native("toString", "(J)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, number_to_string)
+      .native("toString", "(JI)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, long_to_string_radix)
+      .native("toHexString", "(J)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, long_to_hex_string)
+      .native("toOctalString", "(J)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, long_to_octal_string)
+      .native("toBinaryString", "(J)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, long_to_binary_string)
+      .native("hashCode", "(J)I", ACC_PUBLIC | ACC_STATIC, long_hash)
+      .native("compare", "(JJ)I", ACC_PUBLIC | ACC_STATIC, long_compare)
+      .native("signum", "(J)I", ACC_PUBLIC | ACC_STATIC, long_signum)
+      .native("max", "(JJ)J", ACC_PUBLIC | ACC_STATIC, long_max)
+      .native("min", "(JJ)J", ACC_PUBLIC | ACC_STATIC, long_min)
+      .native("sum", "(JJ)J", ACC_PUBLIC | ACC_STATIC, long_sum)
+      .native("bitCount", "(J)I", ACC_PUBLIC | ACC_STATIC, long_bit_count)
+      .native("numberOfLeadingZeros", "(J)I", ACC_PUBLIC | ACC_STATIC, long_leading_zeros)
+      .native("numberOfTrailingZeros", "(J)I", ACC_PUBLIC | ACC_STATIC, long_trailing_zeros)
+  )?;
+  vm.define_class(
+    wrapper("java/lang/Short", "S")
+      .native("valueOf", "(S)Ljava/lang/Short;", ACC_PUBLIC | ACC_STATIC, short_value_of)
+      .native("parseShort", "(Ljava/lang/String;)S", ACC_PUBLIC | ACC_STATIC, short_parse)
+      .native("toString", "(S)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, number_to_string)
+      .native("hashCode", "(S)I", ACC_PUBLIC | ACC_STATIC, integer_hash)
+      .native("compare", "(SS)I", ACC_PUBLIC | ACC_STATIC, difference_compare)
+  )?;
+  vm.define_class(
+    wrapper("java/lang/Byte", "B")
+      .native("valueOf", "(B)Ljava/lang/Byte;", ACC_PUBLIC | ACC_STATIC, byte_value_of)
+      .native("parseByte", "(Ljava/lang/String;)B", ACC_PUBLIC | ACC_STATIC, byte_parse)
+      .native("toString", "(B)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, number_to_string)
+      .native("hashCode", "(B)I", ACC_PUBLIC | ACC_STATIC, integer_hash)
+      .native("compare", "(BB)I", ACC_PUBLIC | ACC_STATIC, difference_compare)
+  )?;
+  vm.define_class(
+    wrapper("java/lang/Double", "D")
+      .native("valueOf", "(D)Ljava/lang/Double;", ACC_PUBLIC | ACC_STATIC, double_value_of)
+      .native("valueOf", "(Ljava/lang/String;)Ljava/lang/Double;", ACC_PUBLIC | ACC_STATIC, double_value_of_string)
+      .native("parseDouble", "(Ljava/lang/String;)D", ACC_PUBLIC | ACC_STATIC, double_parse)
+      .native("toString", "(D)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, number_to_string)
+      .native("hashCode", "(D)I", ACC_PUBLIC | ACC_STATIC, double_hash)
+      .native("compare", "(DD)I", ACC_PUBLIC | ACC_STATIC, double_compare)
+      .native("isNaN", "(D)Z", ACC_PUBLIC | ACC_STATIC, double_is_nan)
+      .native("isInfinite", "(D)Z", ACC_PUBLIC | ACC_STATIC, double_is_infinite)
+      .native("isFinite", "(D)Z", ACC_PUBLIC | ACC_STATIC, double_is_finite)
+      .native("isNaN", "()Z", ACC_PUBLIC, wrapper_is_nan)
+      .native("isInfinite", "()Z", ACC_PUBLIC, wrapper_is_infinite)
+      .native("doubleToLongBits", "(D)J", ACC_PUBLIC | ACC_STATIC, double_to_long_bits)
+      .native("doubleToRawLongBits", "(D)J", ACC_PUBLIC | ACC_STATIC, double_to_raw_long_bits)
+      .native("longBitsToDouble", "(J)D", ACC_PUBLIC | ACC_STATIC, long_bits_to_double)
+      .native("max", "(DD)D", ACC_PUBLIC | ACC_STATIC, double_max)
+      .native("min", "(DD)D", ACC_PUBLIC | ACC_STATIC, double_min)
+      .native("sum", "(DD)D", ACC_PUBLIC | ACC_STATIC, double_sum)
+  )?;
+  vm.define_class(
+    wrapper("java/lang/Float", "F")
+      .native("valueOf", "(F)Ljava/lang/Float;", ACC_PUBLIC | ACC_STATIC, float_value_of)
+      .native("valueOf", "(Ljava/lang/String;)Ljava/lang/Float;", ACC_PUBLIC | ACC_STATIC, float_value_of_string)
+      .native("parseFloat", "(Ljava/lang/String;)F", ACC_PUBLIC | ACC_STATIC, float_parse)
+      .native("toString", "(F)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, number_to_string)
+      .native("hashCode", "(F)I", ACC_PUBLIC | ACC_STATIC, float_hash)
+      .native("compare", "(FF)I", ACC_PUBLIC | ACC_STATIC, float_compare)
+      .native("isNaN", "(F)Z", ACC_PUBLIC | ACC_STATIC, float_is_nan)
+      .native("isInfinite", "(F)Z", ACC_PUBLIC | ACC_STATIC, float_is_infinite)
+      .native("isFinite", "(F)Z", ACC_PUBLIC | ACC_STATIC, float_is_finite)
+      .native("isNaN", "()Z", ACC_PUBLIC, wrapper_is_nan)
+      .native("isInfinite", "()Z", ACC_PUBLIC, wrapper_is_infinite)
+      .native("floatToIntBits", "(F)I", ACC_PUBLIC | ACC_STATIC, float_to_int_bits)
+      .native("floatToRawIntBits", "(F)I", ACC_PUBLIC | ACC_STATIC, float_to_raw_int_bits)
+      .native("intBitsToFloat", "(I)F", ACC_PUBLIC | ACC_STATIC, int_bits_to_float)
+      .native("max", "(FF)F", ACC_PUBLIC | ACC_STATIC, float_max)
+      .native("min", "(FF)F", ACC_PUBLIC | ACC_STATIC, float_min)
+      .native("sum", "(FF)F", ACC_PUBLIC | ACC_STATIC, float_sum)
+  )?;
+  vm.define_class(
+    wrapper("java/lang/Character", "C")
+      .super_class(Some("java/lang/Object"))
+      .native("valueOf", "(C)Ljava/lang/Character;", ACC_PUBLIC | ACC_STATIC, character_value_of)
+      .native("toString", "(C)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, character_to_string)
+      .native("hashCode", "(C)I", ACC_PUBLIC | ACC_STATIC, integer_hash)
+      .native("compare", "(CC)I", ACC_PUBLIC | ACC_STATIC, difference_compare)
+      .native("isDigit", "(C)Z", ACC_PUBLIC | ACC_STATIC, character_is_digit)
+      .native("isLetter", "(C)Z", ACC_PUBLIC | ACC_STATIC, character_is_letter)
+      .native("isLetterOrDigit", "(C)Z", ACC_PUBLIC | ACC_STATIC, character_is_letter_or_digit)
+      .native("isAlphabetic", "(I)Z", ACC_PUBLIC | ACC_STATIC, character_is_letter)
+      .native("isWhitespace", "(C)Z", ACC_PUBLIC | ACC_STATIC, character_is_whitespace)
+      .native("isUpperCase", "(C)Z", ACC_PUBLIC | ACC_STATIC, character_is_upper_case)
+      .native("isLowerCase", "(C)Z", ACC_PUBLIC | ACC_STATIC, character_is_lower_case)
+      .native("toUpperCase", "(C)C", ACC_PUBLIC | ACC_STATIC, character_to_upper_case)
+      .native("toLowerCase", "(C)C", ACC_PUBLIC | ACC_STATIC, character_to_lower_case)
+      .native("digit", "(CI)I", ACC_PUBLIC | ACC_STATIC, character_digit)
+      .native("forDigit", "(II)C", ACC_PUBLIC | ACC_STATIC, character_for_digit)
+      .native("getNumericValue", "(C)I", ACC_PUBLIC | ACC_STATIC, character_numeric_value)
+      .native("toChars", "(I)[C", ACC_PUBLIC | ACC_STATIC, character_to_chars)
+  )?;
+  vm.define_class(
+    wrapper("java/lang/Boolean", "Z")
+      .super_class(Some("java/lang/Object"))
+      .field("TRUE", "Ljava/lang/Boolean;", ACC_PUBLIC | ACC_STATIC | ACC_FINAL)
+      .field("FALSE", "Ljava/lang/Boolean;", ACC_PUBLIC | ACC_STATIC | ACC_FINAL)
+      .native("<clinit>", "()V", ACC_STATIC, boolean_clinit)
+      .native("valueOf", "(Z)Ljava/lang/Boolean;", ACC_PUBLIC | ACC_STATIC, boolean_value_of)
+      .native("valueOf", "(Ljava/lang/String;)Ljava/lang/Boolean;", ACC_PUBLIC | ACC_STATIC, boolean_value_of_string)
+      .native("parseBoolean", "(Ljava/lang/String;)Z", ACC_PUBLIC | ACC_STATIC, boolean_parse)
+      .native("toString", "(Z)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, boolean_to_string)
+      .native("hashCode", "(Z)I", ACC_PUBLIC | ACC_STATIC, boolean_hash)
+      .native("compare", "(ZZ)I", ACC_PUBLIC | ACC_STATIC, integer_compare)
+      .native("logicalAnd", "(ZZ)Z", ACC_PUBLIC | ACC_STATIC, boolean_and)
+      .native("logicalOr", "(ZZ)Z", ACC_PUBLIC | ACC_STATIC, boolean_or)
+      .native("logicalXor", "(ZZ)Z", ACC_PUBLIC | ACC_STATIC, boolean_xor)
+  )?;
+  Ok(())
+}
+
+// ラッパークラスに共通するフィールドとメソッド
+fn wrapper(name: &str, primitive: &str) -> ClassDefinition {
+  let object = format!("L{};", name);
+  let mut definition = ClassDefinition::new(name)
+    .access_flags(ACC_PUBLIC | ACC_FINAL | ACC_SUPER)
+    .super_class(Some("java/lang/Number"))
+    .interface("java/lang/Comparable")
+    .field("value", primitive, ACC_PRIVATE | ACC_FINAL)
+    .field("cache", &format!("[{}", object), ACC_PRIVATE | ACC_STATIC)
+    .field("TYPE", "Ljava/lang/Class;", ACC_PUBLIC | ACC_STATIC | ACC_FINAL)
+    .native("<init>", &format!("({})V", primitive), ACC_PUBLIC, wrapper_init)
+    .native("equals", "(Ljava/lang/Object;)Z", ACC_PUBLIC, wrapper_equals)
+    .native("hashCode", "()I", ACC_PUBLIC, wrapper_hash_code)
+    .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, wrapper_to_string)
+    .native("compareTo", &format!("({})I", object), ACC_PUBLIC, wrapper_compare_to)
+    .native("compareTo", "(Ljava/lang/Object;)I", ACC_PUBLIC, wrapper_compare_to);
+  match primitive {
+    "Z" => definition.native("booleanValue", "()Z", ACC_PUBLIC, wrapper_primitive_value),
+    "C" => definition.native("charValue", "()C", ACC_PUBLIC, wrapper_primitive_value),
+    _ => {
+      for (method, descriptor, function) in NUMBER_VALUES {
+        definition = definition.native(method, descriptor, ACC_PUBLIC, *function);
+      }
+      definition
+    },
+  }
+}
+
+// 基本型の値の変換 (i2l、d2iなどと同じ、浮動小数点数から整数へは飽和する)
+pub fn convert(value: Value, to: &FieldType) -> Value {
+  match to {
+    FieldType::Long => Value::Long(match value {
+      Value::Int(v) => v as i64,
+      Value::Long(v) => v,
+      Value::Float(v) => v as i64,
+      Value::Double(v) => v as i64,
+      _ => 0,
+    }),
+    FieldType::Float => Value::Float(match value {
+      Value::Int(v) => v as f32,
+      Value::Long(v) => v as f32,
+      Value::Float(v) => v,
+      Value::Double(v) => v as f32,
+      _ => 0.0,
+    }),
+    FieldType::Double => Value::Double(match value {
+      Value::Int(v) => v as f64,
+      Value::Long(v) => v as f64,
+      Value::Float(v) => v as f64,
+      Value::Double(v) => v,
+      _ => 0.0,
+    }),
+    _ => {
+      let v = match value {
+        Value::Int(v) => v,
+        Value::Long(v) => v as i32,
+        Value::Float(v) => v as i32,
+        Value::Double(v) => v as i32,
+        _ => 0,
+      };
+      Value::Int(match to {
+        FieldType::Byte => v as i8 as i32,
+        FieldType::Short => v as i16 as i32,
+        FieldType::Char => v as u16 as i32,
+        FieldType::Boolean => v & 1,
+        _ => v,
+      })
+    },
+  }
+}
+
+// 各ラッパークラスのhashCode()の値
+pub fn primitive_hash(value: Value, field_type: &FieldType) -> i32 {
+  match (field_type, value) {
+    (FieldType::Boolean, Value::Int(v)) => if v != 0 { 1231 } else { 1237 },
+    (_, Value::Int(v)) => v,
+    (_, Value::Long(v)) => (v ^ ((v as u64) >> 32) as i64) as i32,
+    (_, Value::Float(v)) => float_bits(v),
+    (_, Value::Double(v)) => {
+      let bits = double_bits(v);
+      (bits ^ ((bits as u64) >> 32) as i64) as i32
+    },
+    _ => 0,
+  }
+}
+
+// Float.floatToIntBits(): NaNは1つの値にまとめる
+fn float_bits(value: f32) -> i32 {
+  if value.is_nan() { 0x7fc00000 } else { value.to_bits() as i32 }
+}
+
+fn double_bits(value: f64) -> i64 {
+  if value.is_nan() { 0x7ff8000000000000 } else { value.to_bits() as i64 }
+}
+
+// Float.compare()、Double.compare(): -0.0 < 0.0、NaNは最大
+fn compare_floats(a: f64, b: f64) -> i32 {
+  match a.partial_cmp(&b) {
+    Some(Ordering::Less) => -1,
+    Some(Ordering::Greater) => 1,
+    _ => double_bits(a).cmp(&double_bits(b)) as i32,
+  }
+}
+
+// ラッパークラスのcompareTo()と同じ比較 (Character、Short、Byteは差を返す)
+pub fn compare_primitives(a: Value, b: Value, field_type: &FieldType) -> i32 {
+  match (field_type, a, b) {
+    (FieldType::Char | FieldType::Short | FieldType::Byte, Value::Int(x), Value::Int(y)) => x - y,
+    (_, Value::Int(x), Value::Int(y)) => x.cmp(&y) as i32,
+    (_, Value::Long(x), Value::Long(y)) => x.cmp(&y) as i32,
+    (_, Value::Float(x), Value::Float(y)) => compare_floats(x as f64, y as f64),
+    (_, Value::Double(x), Value::Double(y)) => compare_floats(x, y),
+    _ => 0,
+  }
+}
+
+// Character.isWhitespace(): 改行しない空白 (U+00A0など) は含めない
+pub fn is_java_whitespace(c: u16) -> bool {
+  match char::from_u32(c as u32) {
+    Some('\t'..='\r' | '\u{1c}'..='\u{1f}') => true,
+    Some('\u{85}' | '\u{a0}' | '\u{2007}' | '\u{202f}') => false,
+    Some(c) => c.is_whitespace(),
+    None => false,
+  }
+}
+
+fn number_format_error(text: &str) -> VmError {
+  VmError::java("java/lang/NumberFormatException", format!("For input string: \"{}\"", text))
+}
+
+fn text_or_null(vm: &Vm, value: Value) -> Result<Option<String>, VmError> {
+  match value.as_ref()? {
+    Some(string) => Ok(Some(vm.string_value(string)?)),
+    None => Ok(None),
+  }
+}
+
+// Integer.parseInt()、Long.parseLong(): 符号と指定した基数の数字だけを受け付ける
+fn parse_integer(text: Option<String>, radix: i32, min: i64, max: i64) -> Result<i64, VmError> {
+  let text = text.ok_or_else(|| VmError::java("java/lang/NumberFormatException", "Cannot parse null string"))?;
+  if !(2..=36).contains(&radix) {
+    let bound = if radix < 2 { "less than Character.MIN_RADIX" } else { "greater than Character.MAX_RADIX" };
+    return Err(VmError::java("java/lang/NumberFormatException", format!("radix {} {}", radix, bound)));
+  }
+  let error = || match radix {
+    10 => number_format_error(&text),
+    _ => VmError::java("java/lang/NumberFormatException", format!("For input string: \"{}\" under radix {}", text, radix)),
+  };
+  let (negative, digits) = match text.strip_prefix('-') {
+    Some(digits) => (true, digits),
+    None => (false, text.strip_prefix('+').unwrap_or(&text)),
+  };
+  if digits.is_empty() {
+    return Err(error());
+  }
+  let mut result: i128 = 0;
+  for c in digits.chars() {
+    let digit = c.to_digit(radix as u32).ok_or_else(error)?;
+    result = result * radix as i128 + digit as i128;
+    if result > max as i128 + 1 {
+      return Err(error());
+    }
+  }
+  let result = if negative { -result } else { result };
+  if result < min as i128 || result > max as i128 {
+    return Err(error());
+  }
+  Ok(result as i64)
+}
+
+// Double.parseDouble(): 前後の空白を除き、10進数、NaN、Infinityと型の接尾辞 (f、d) を受け付ける
+fn parse_floating(vm: &Vm, value: Value) -> Result<f64, VmError> {
+  let text = text_or_null(vm, value)?
+    .ok_or_else(|| VmError::null_pointer("Cannot invoke \"String.trim()\" because \"in\" is null"))?;
+  let trimmed = text.trim_matches(|c: char| c <= ' ');
+  if trimmed.is_empty() {
+    return Err(VmError::java("java/lang/NumberFormatException", "empty String"));
+  }
+  let unsigned = trimmed.strip_prefix(['+', '-']).unwrap_or(trimmed);
+  let negative = trimmed.starts_with('-');
+  match unsigned {
+    "NaN" => return Ok(f64::NAN),
+    "Infinity" => return Ok(if negative { f64::NEG_INFINITY } else { f64::INFINITY }),
+    _ => {},
+  }
+  let number = trimmed.strip_suffix(['f', 'F', 'd', 'D']).unwrap_or(trimmed);
+  let valid = number.strip_prefix(['+', '-']).unwrap_or(number)
+    .chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'));
+  match number.parse::<f64>() {
+    Ok(parsed) if valid => Ok(parsed),
+    _ => Err(number_format_error(&text)),
+  }
+}
+
+fn box_primitive(vm: &mut Vm, class_name: &str, value: Value) -> Result<ObjRef, VmError> {
+  let field_type = wrapped_type(class_name)
+    .ok_or_else(|| VmError::internal(format!("{} is not a primitive wrapper", class_name)))?;
+  let class = vm.load_class(class_name)?;
+  let object = vm.instantiate(class)?;
+  vm.set_field(object, "value", &field_type.descriptor(), value)?;
+  Ok(object)
+}
+
+// 小さい値は同じオブジェクトを返す (Integer.valueOf()のキャッシュ)
+fn box_cached(vm: &mut Vm, class_name: &str, value: Value) -> Result<Option<Value>, VmError> {
+  let key = match value {
+    Value::Int(v) => v as i64,
+    Value::Long(v) => v,
+    _ => return Err(VmError::internal(format!("Cannot cache {:?}", value))),
+  };
+  if !(CACHE_LOW..=CACHE_HIGH).contains(&key) {
+    return Ok(Some(Value::Ref(box_primitive(vm, class_name, value)?)));
+  }
+  let class = vm.load_class(class_name)?;
+  let descriptor = format!("[L{};", class_name);
+  let cache = match vm.get_static_field(class, "cache", &descriptor)?.as_ref()? {
+    Some(cache) => cache,
+    None => {
+      let cache = vm.new_array(&FieldType::Object(class_name.to_string()), (CACHE_HIGH - CACHE_LOW + 1) as i32)?;
+      vm.set_static_field(class, "cache", &descriptor, Value::Ref(cache))?;
+      cache
+    },
+  };
+  let index = (key - CACHE_LOW) as usize;
+  if let cached @ Value::Ref(_) = vm.heap.get(cache)?.array()?.get(index) {
+    return Ok(Some(cached));
+  }
+  let boxed = box_primitive(vm, class_name, value)?;
+  vm.heap.get_mut(cache)?.array_mut()?.set(index, Value::Ref(boxed))?;
+  Ok(Some(Value::Ref(boxed)))
+}
+
+fn new_box(vm: &mut Vm, class_name: &str, value: Value) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Ref(box_primitive(vm, class_name, value)?)))
+}
+
+fn bool_result(value: bool) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(value as i32)))
+}
+
+fn text_result(vm: &mut Vm, text: &str) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Ref(vm.new_string(text)?)))
+}
+
+fn number_init(_vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(None)
+}
+
+// Number.byteValue()、shortValue(): intValue()の結果を縮小する
+fn number_byte_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  number_narrow(vm, args, FieldType::Byte)
+}
+
+fn number_short_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  number_narrow(vm, args, FieldType::Short)
+}
+
+fn number_narrow(vm: &mut Vm, args: &[Value], to: FieldType) -> Result<Option<Value>, VmError> {
+  let class = vm.object_class(this(args)?)?;
+  let method = vm.find_virtual(class, "intValue", "()I")
+    .ok_or_else(|| VmError::java("java/lang/AbstractMethodError", format!("{}.intValue()I", vm.classes[class].java_name())))?;
+  let value = vm.invoke(method, vec![args[0]])?.unwrap_or(Value::Int(0));
+  Ok(Some(convert(value, &to)))
+}
+
+fn wrapper_init(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let class = vm.object_class(this)?;
+  let field_type = wrapped_type(&vm.classes[class].name)
+    .ok_or_else(|| VmError::internal(format!("{} is not a primitive wrapper", vm.classes[class].name)))?;
+  vm.set_field(this, "value", &field_type.descriptor(), args[1])?;
+  Ok(None)
+}
+
+fn wrapper_value(vm: &mut Vm, args: &[Value]) -> Result<(Value, FieldType), VmError> {
+  vm.unbox_value(Some(this(args)?))
+}
+
+fn wrapper_primitive_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(wrapper_value(vm, args)?.0))
+}
+
+fn wrapper_converted(vm: &mut Vm, args: &[Value], to: FieldType) -> Result<Option<Value>, VmError> {
+  Ok(Some(convert(wrapper_value(vm, args)?.0, &to)))
+}
+
+fn wrapper_byte_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  wrapper_converted(vm, args, FieldType::Byte)
+}
+
+fn wrapper_short_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  wrapper_converted(vm, args, FieldType::Short)
+}
+
+fn wrapper_int_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  wrapper_converted(vm, args, FieldType::Int)
+}
+
+fn wrapper_long_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  wrapper_converted(vm, args, FieldType::Long)
+}
+
+fn wrapper_float_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  wrapper_converted(vm, args, FieldType::Float)
+}
+
+fn wrapper_double_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  wrapper_converted(vm, args, FieldType::Double)
+}
+
+// 同じクラスで値が等しい (浮動小数点数はfloatToIntBits()が等しい)
+fn wrapper_equals(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let Some(other) = args[1].as_ref()? else { return bool_result(false) };
+  if vm.object_class(this)? != vm.object_class(other)? {
+    return bool_result(false);
+  }
+  let (a, field_type) = vm.unbox_value(Some(this))?;
+  let (b, _) = vm.unbox_value(Some(other))?;
+  bool_result(primitive_equals(a, b, &field_type))
+}
+
+fn primitive_equals(a: Value, b: Value, field_type: &FieldType) -> bool {
+  match (a, b) {
+    (Value::Float(x), Value::Float(y)) => float_bits(x) == float_bits(y),
+    (Value::Double(x), Value::Double(y)) => double_bits(x) == double_bits(y),
+    _ => compare_primitives(a, b, field_type) == 0,
+  }
+}
+
+fn wrapper_hash_code(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (value, field_type) = wrapper_value(vm, args)?;
+  Ok(Some(Value::Int(primitive_hash(value, &field_type))))
+}
+
+fn wrapper_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (value, field_type) = wrapper_value(vm, args)?;
+  let chars = vm.stringify(value, &field_type)?;
+  string_result(vm, &chars)
+}
+
+fn wrapper_compare_to(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let other = args[1].as_ref()?.ok_or_else(|| VmError::java_without_message("java/lang/NullPointerException"))?;
+  let (a, field_type) = vm.unbox_value(Some(this))?;
+  if vm.object_class(this)? != vm.object_class(other)? {
+    let other_class = vm.object_class(other)?;
+    return Err(VmError::java("java/lang/ClassCastException", format!(
+      "class {} cannot be cast to class {}", vm.classes[other_class].java_name(), wrapper_class(&field_type).unwrap_or_default().replace('/', "."),
+    )));
+  }
+  let (b, _) = vm.unbox_value(Some(other))?;
+  Ok(Some(Value::Int(compare_primitives(a, b, &field_type))))
+}
+
+fn wrapper_is_nan(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (value, _) = wrapper_value(vm, args)?;
+  bool_result(matches!(convert(value, &FieldType::Double), Value::Double(v) if v.is_nan()))
+}
+
+fn wrapper_is_infinite(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (value, _) = wrapper_value(vm, args)?;
+  bool_result(matches!(convert(value, &FieldType::Double), Value::Double(v) if v.is_infinite()))
+}
+
+// Integer/Long/Short/Byte/Float/Double.toString(値)
+fn number_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = vm.stringify(args[0], &FieldType::Int)?;
+  string_result(vm, &chars)
+}
+
+// 符号付きで指定した基数の文字列にする (基数が範囲外なら10進数)
+fn radix_string(value: i64, radix: i32) -> String {
+  let radix = if (2..=36).contains(&radix) { radix as u32 } else { 10 };
+  let mut digits = Vec::new();
+  let mut rest = (value as i128).unsigned_abs();
+  loop {
+    digits.push(std::char::from_digit((rest % radix as u128) as u32, radix).unwrap_or('0'));
+    rest /= radix as u128;
+    if rest == 0 {
+      break;
+    }
+  }
+  if value < 0 {
+    digits.push('-');
+  }
+  digits.iter().rev().collect()
+}
+
+fn integer_value_of(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  box_cached(vm, "java/lang/Integer", args[0])
+}
+
+fn integer_value_of_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let value = parse_integer(text_or_null(vm, args[0])?, 10, i32::MIN as i64, i32::MAX as i64)?;
+  box_cached(vm, "java/lang/Integer", Value::Int(value as i32))
+}
+
+// parseInt(String), parseInt(String, int radix)
+fn integer_parse(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let radix = match args.get(1) {
+    Some(radix) => radix.as_int()?,
+    None => 10,
+  };
+  let value = parse_integer(text_or_null(vm, args[0])?, radix, i32::MIN as i64, i32::MAX as i64)?;
+  Ok(Some(Value::Int(value as i32)))
+}
+
+fn integer_to_string_radix(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  text_result(vm, &radix_string(args[0].as_int()? as i64, args[1].as_int()?))
+}
+
+fn integer_to_hex_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  text_result(vm, &format!("{:x}", args[0].as_int()? as u32))
+}
+
+fn integer_to_octal_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  text_result(vm, &format!("{:o}", args[0].as_int()? as u32))
+}
+
+fn integer_to_binary_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  text_result(vm, &format!("{:b}", args[0].as_int()? as u32))
+}
+
+fn integer_hash(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_int()?)))
+}
+
+// Integer.compare()、Boolean.compare()
+fn integer_compare(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_int()?.cmp(&args[1].as_int()?) as i32)))
+}
+
+// Short/Byte/Character.compare(): x - y
+fn difference_compare(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_int()? - args[1].as_int()?)))
+}
+
+fn integer_signum(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_int()?.signum())))
+}
+
+fn integer_max(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_int()?.max(args[1].as_int()?))))
+}
+
+fn integer_min(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_int()?.min(args[1].as_int()?))))
+}
+
+fn integer_sum(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_int()?.wrapping_add(args[1].as_int()?))))
+}
+
+fn integer_bit_count(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_int()?.count_ones() as i32)))
+}
+
+fn integer_leading_zeros(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_int()?.leading_zeros() as i32)))
+}
+
+fn integer_trailing_zeros(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_int()?.trailing_zeros() as i32)))
+}
+
+fn integer_reverse(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_int()?.reverse_bits())))
+}
+
+fn long_value_of(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  box_cached(vm, "java/lang/Long", args[0])
+}
+
+fn long_value_of_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let value = parse_integer(text_or_null(vm, args[0])?, 10, i64::MIN, i64::MAX)?;
+  box_cached(vm, "java/lang/Long", Value::Long(value))
+}
+
+fn long_parse(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let radix = match args.get(1) {
+    Some(radix) => radix.as_int()?,
+    None => 10,
+  };
+  let value = parse_integer(text_or_null(vm, args[0])?, radix, i64::MIN, i64::MAX)?;
+  Ok(Some(Value::Long(value)))
+}
+
+fn long_to_string_radix(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  text_result(vm, &radix_string(args[0].as_long()?, args[1].as_int()?))
+}
+
+fn long_to_hex_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  text_result(vm, &format!("{:x}", args[0].as_long()? as u64))
+}
+
+fn long_to_octal_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  text_result(vm, &format!("{:o}", args[0].as_long()? as u64))
+}
+
+fn long_to_binary_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  text_result(vm, &format!("{:b}", args[0].as_long()? as u64))
+}
+
+fn long_hash(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(primitive_hash(args[0], &FieldType::Long))))
+}
+
+fn long_compare(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_long()?.cmp(&args[1].as_long()?) as i32)))
+}
+
+fn long_signum(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_long()?.signum() as i32)))
+}
+
+fn long_max(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Long(args[0].as_long()?.max(args[1].as_long()?))))
+}
+
+fn long_min(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Long(args[0].as_long()?.min(args[1].as_long()?))))
+}
+
+fn long_sum(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Long(args[0].as_long()?.wrapping_add(args[1].as_long()?))))
+}
+
+fn long_bit_count(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_long()?.count_ones() as i32)))
+}
+
+fn long_leading_zeros(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_long()?.leading_zeros() as i32)))
+}
+
+fn long_trailing_zeros(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_long()?.trailing_zeros() as i32)))
+}
+
+fn short_value_of(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  box_cached(vm, "java/lang/Short", args[0])
+}
+
+// Short.parseShort()、Byte.parseByte(): 範囲外ならValue out of range
+fn parse_narrow(vm: &Vm, value: Value, min: i64, max: i64) -> Result<i32, VmError> {
+  let text = text_or_null(vm, value)?;
+  let parsed = parse_integer(text.clone(), 10, i32::MIN as i64, i32::MAX as i64)?;
+  if parsed < min || parsed > max {
+    return Err(VmError::java("java/lang/NumberFormatException", format!(
+      "Value out of range. Value:\"{}\" Radix:10", text.unwrap_or_default(),
+    )));
+  }
+  Ok(parsed as i32)
+}
+
+fn short_parse(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(parse_narrow(vm, args[0], i16::MIN as i64, i16::MAX as i64)?)))
+}
+
+fn byte_value_of(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  box_cached(vm, "java/lang/Byte", args[0])
+}
+
+fn byte_parse(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(parse_narrow(vm, args[0], i8::MIN as i64, i8::MAX as i64)?)))
+}
+
+fn double_value_of(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  new_box(vm, "java/lang/Double", args[0])
+}
+
+fn double_value_of_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let value = parse_floating(vm, args[0])?;
+  new_box(vm, "java/lang/Double", Value::Double(value))
+}
+
+fn double_parse(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Double(parse_floating(vm, args[0])?)))
+}
+
+fn double_hash(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(primitive_hash(args[0], &FieldType::Double))))
+}
+
+fn double_compare(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(compare_floats(args[0].as_double()?, args[1].as_double()?))))
+}
+
+fn double_is_nan(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  bool_result(args[0].as_double()?.is_nan())
+}
+
+fn double_is_infinite(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  bool_result(args[0].as_double()?.is_infinite())
+}
+
+fn double_is_finite(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  bool_result(args[0].as_double()?.is_finite())
+}
+
+fn double_to_long_bits(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Long(double_bits(args[0].as_double()?))))
+}
+
+pub(crate) fn double_to_raw_long_bits(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Long(args[0].as_double()?.to_bits() as i64)))
+}
+
+pub(crate) fn long_bits_to_double(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Double(f64::from_bits(args[0].as_long()? as u64))))
+}
+
+// Math.max()、Math.min()と同じ (NaNを優先し、-0.0 < 0.0とする)
+pub fn max_double(a: f64, b: f64) -> f64 {
+  if a.is_nan() || b.is_nan() { f64::NAN } else if compare_floats(a, b) >= 0 { a } else { b }
+}
+
+pub fn min_double(a: f64, b: f64) -> f64 {
+  if a.is_nan() || b.is_nan() { f64::NAN } else if compare_floats(a, b) <= 0 { a } else { b }
+}
+
+fn double_max(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Double(max_double(args[0].as_double()?, args[1].as_double()?))))
+}
+
+fn double_min(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Double(min_double(args[0].as_double()?, args[1].as_double()?))))
+}
+
+fn double_sum(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Double(args[0].as_double()? + args[1].as_double()?)))
+}
+
+fn float_value_of(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  new_box(vm, "java/lang/Float", args[0])
+}
+
+fn float_value_of_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let value = parse_float(vm, args[0])?;
+  new_box(vm, "java/lang/Float", Value::Float(value))
+}
+
+// 倍精度を経由すると丸めが2回になるので、単精度で直接読み直す
+fn parse_float(vm: &Vm, value: Value) -> Result<f32, VmError> {
+  let parsed = parse_floating(vm, value)?;
+  if !parsed.is_finite() {
+    return Ok(parsed as f32);
+  }
+  let text = text_or_null(vm, value)?.unwrap_or_default();
+  let text = text.trim_matches(|c: char| c <= ' ');
+  Ok(text.strip_suffix(['f', 'F', 'd', 'D']).unwrap_or(text).parse::<f32>().unwrap_or(parsed as f32))
+}
+
+fn float_parse(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Float(parse_float(vm, args[0])?)))
+}
+
+fn float_hash(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(float_bits(args[0].as_float()?))))
+}
+
+fn float_compare(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(compare_floats(args[0].as_float()? as f64, args[1].as_float()? as f64))))
+}
+
+fn float_is_nan(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  bool_result(args[0].as_float()?.is_nan())
+}
+
+fn float_is_infinite(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  bool_result(args[0].as_float()?.is_infinite())
+}
+
+fn float_is_finite(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  bool_result(args[0].as_float()?.is_finite())
+}
+
+fn float_to_int_bits(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(float_bits(args[0].as_float()?))))
+}
+
+pub(crate) fn float_to_raw_int_bits(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_float()?.to_bits() as i32)))
+}
+
+pub(crate) fn int_bits_to_float(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Float(f32::from_bits(args[0].as_int()? as u32))))
+}
+
+fn float_max(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Float(max_double(args[0].as_float()? as f64, args[1].as_float()? as f64) as f32)))
+}
+
+fn float_min(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Float(min_double(args[0].as_float()? as f64, args[1].as_float()? as f64) as f32)))
+}
+
+fn float_sum(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Float(args[0].as_float()? + args[1].as_float()?)))
+}
+
+fn character_value_of(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  box_cached(vm, "java/lang/Character", args[0])
+}
+
+fn character_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  string_result(vm, &[args[0].as_int()? as u16])
+}
+
+fn character_to_chars(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let code_point = args[0].as_int()?;
+  if !(0..=0x10ffff).contains(&code_point) {
+    return Err(VmError::java(
+      "java/lang/IllegalArgumentException",
+      format!("Not a valid Unicode code point: 0x{:X}", code_point),
+    ));
+  }
+  let chars = code_point_chars(code_point);
+  let array = vm.new_array(&FieldType::Char, chars.len() as i32)?;
+  *vm.heap.get_mut(array)?.array_mut()? = ArrayData::Char(chars);
+  Ok(Some(Value::Ref(array)))
+}
+
+fn char_arg(value: Value) -> Result<Option<char>, VmError> {
+  Ok(char::from_u32(value.as_int()? as u32))
+}
+
+fn character_is_digit(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  bool_result(char_arg(args[0])?.is_some_and(|c| c.is_ascii_digit() || !c.is_ascii() && c.is_numeric()))
+}
+
+fn character_is_letter(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  bool_result(char_arg(args[0])?.is_some_and(char::is_alphabetic))
+}
+
+fn character_is_letter_or_digit(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  bool_result(char_arg(args[0])?.is_some_and(char::is_alphanumeric))
+}
+
+fn character_is_whitespace(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  bool_result(is_java_whitespace(args[0].as_int()? as u16))
+}
+
+fn character_is_upper_case(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  bool_result(char_arg(args[0])?.is_some_and(char::is_uppercase))
+}
+
+fn character_is_lower_case(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  bool_result(char_arg(args[0])?.is_some_and(char::is_lowercase))
+}
+
+// 1文字に対応する変換だけを行う (ßのように複数文字になる場合はそのまま)
+fn map_single(value: Value, f: fn(char) -> Vec<char>) -> Result<Option<Value>, VmError> {
+  let c = value.as_int()?;
+  let mapped = match char_arg(value)?.map(f).as_deref() {
+    Some(&[m]) if (m as u32) < 0x10000 => m as i32,
+    _ => c,
+  };
+  Ok(Some(Value::Int(mapped)))
+}
+
+fn character_to_upper_case(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  map_single(args[0], |c| c.to_uppercase().collect())
+}
+
+fn character_to_lower_case(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  map_single(args[0], |c| c.to_lowercase().collect())
+}
+
+fn character_digit(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let radix = args[1].as_int()?;
+  let digit = match char_arg(args[0])? {
+    Some(c) if (2..=36).contains(&radix) => c.to_digit(radix as u32).map_or(-1, |d| d as i32),
+    _ => -1,
+  };
+  Ok(Some(Value::Int(digit)))
+}
+
+fn character_for_digit(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (digit, radix) = (args[0].as_int()?, args[1].as_int()?);
+  let c = match (u32::try_from(digit), u32::try_from(radix)) {
+    (Ok(digit), Ok(radix)) if (2..=36).contains(&radix) => std::char::from_digit(digit, radix).map_or(0, |c| c as i32),
+    _ => 0,
+  };
+  Ok(Some(Value::Int(c)))
+}
+
+fn character_numeric_value(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let value = char_arg(args[0])?.and_then(|c| c.to_digit(36)).map_or(-1, |d| d as i32);
+  Ok(Some(Value::Int(value)))
+}
+
+// Boolean.TRUEとBoolean.FALSEを作る
+fn boolean_clinit(vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  let class = vm.load_class("java/lang/Boolean")?;
+  for (name, value) in [("TRUE", 1), ("FALSE", 0)] {
+    let boxed = box_primitive(vm, "java/lang/Boolean", Value::Int(value))?;
+    vm.set_static_field(class, name, "Ljava/lang/Boolean;", Value::Ref(boxed))?;
+  }
+  Ok(None)
+}
+
+fn boolean_constant(vm: &mut Vm, value: bool) -> Result<Option<Value>, VmError> {
+  let class = vm.load_class("java/lang/Boolean")?;
+  vm.initialize(class)?;
+  let name = if value { "TRUE" } else { "FALSE" };
+  Ok(Some(vm.get_static_field(class, name, "Ljava/lang/Boolean;")?))
+}
+
+fn boolean_value_of(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  boolean_constant(vm, args[0].as_int()? != 0)
+}
+
+// 大文字小文字を区別せず"true"ならtrue (nullはfalse)
+fn parse_boolean(vm: &Vm, value: Value) -> Result<bool, VmError> {
+  Ok(text_or_null(vm, value)?.is_some_and(|text| text.eq_ignore_ascii_case("true")))
+}
+
+fn boolean_value_of_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let value = parse_boolean(vm, args[0])?;
+  boolean_constant(vm, value)
+}
+
+fn boolean_parse(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  bool_result(parse_boolean(vm, args[0])?)
+}
+
+fn boolean_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  text_result(vm, if args[0].as_int()? != 0 { "true" } else { "false" })
+}
+
+fn boolean_hash(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(primitive_hash(args[0], &FieldType::Boolean))))
+}
+
+fn boolean_and(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_int()? & args[1].as_int()?)))
+}
+
+fn boolean_or(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_int()? | args[1].as_int()?)))
+}
+
+fn boolean_xor(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_int()? ^ args[1].as_int()?)))
+}
@@ -0,0 +1,75 @@
+use crate::runtime::{
+  class::{ClassDefinition, ACC_ABSTRACT, ACC_INTERFACE, ACC_PUBLIC},
+  error::VmError,
+  vm::Vm,
+};
+
+// java.util.functionの関数型インターフェース (名前、抽象メソッドの名前と記述子)
+const FUNCTIONAL_INTERFACES: &[(&str, &str, &str)] = &[
+  ("BiConsumer", "accept", "(Ljava/lang/Object;Ljava/lang/Object;)V"),
+  ("BiFunction", "apply", "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;"),
+  ("BiPredicate", "test", "(Ljava/lang/Object;Ljava/lang/Object;)Z"),
+  ("BooleanSupplier", "getAsBoolean", "()Z"),
+  ("Consumer", "accept", "(Ljava/lang/Object;)V"),
+  ("DoubleBinaryOperator", "applyAsDouble", "(DD)D"),
+  ("DoubleConsumer", "accept", "(D)V"),
+  ("DoubleFunction", "apply", "(D)Ljava/lang/Object;"),
+  ("DoublePredicate", "test", "(D)Z"),
+  ("DoubleSupplier", "getAsDouble", "()D"),
+  ("DoubleToIntFunction", "applyAsInt", "(D)I"),
+  ("DoubleToLongFunction", "applyAsLong", "(D)J"),
+  ("DoubleUnaryOperator", "applyAsDouble", "(D)D"),
+  ("Function", "apply", "(Ljava/lang/Object;)Ljava/lang/Object;"),
+  ("IntBinaryOperator", "applyAsInt", "(II)I"),
+  ("IntConsumer", "accept", "(I)V"),
+  ("IntFunction", "apply", "(I)Ljava/lang/Object;"),
+  ("IntPredicate", "test", "(I)Z"),
+  ("IntSupplier", "getAsInt", "()I"),
+  ("IntToDoubleFunction", "applyAsDouble", "(I)D"),
+  ("IntToLongFunction", "applyAsLong", "(I)J"),
+  ("IntUnaryOperator", "applyAsInt", "(I)I"),
+  ("LongBinaryOperator", "applyAsLong", "(JJ)J"),
+  ("LongConsumer", "accept", "(J)V"),
+  ("LongFunction", "apply", "(J)Ljava/lang/Object;"),
+  ("LongPredicate", "test", "(J)Z"),
+  ("LongSupplier", "getAsLong", "()J"),
+  ("LongToDoubleFunction", "applyAsDouble", "(J)D"),
+  ("LongToIntFunction", "applyAsInt", "(J)I"),
+  ("LongUnaryOperator", "applyAsLong", "(J)J"),
+  ("ObjDoubleConsumer", "accept", "(Ljava/lang/Object;D)V"),
+  ("ObjIntConsumer", "accept", "(Ljava/lang/Object;I)V"),
+  ("ObjLongConsumer", "accept", "(Ljava/lang/Object;J)V"),
+  ("Predicate", "test", "(Ljava/lang/Object;)Z"),
+  ("Supplier", "get", "()Ljava/lang/Object;"),
+  ("ToDoubleBiFunction", "applyAsDouble", "(Ljava/lang/Object;Ljava/lang/Object;)D"),
+  ("ToDoubleFunction", "applyAsDouble", "(Ljava/lang/Object;)D"),
+  ("ToIntBiFunction", "applyAsInt", "(Ljava/lang/Object;Ljava/lang/Object;)I"),
+  ("ToIntFunction", "applyAsInt", "(Ljava/lang/Object;)I"),
+  ("ToLongBiFunction", "applyAsLong", "(Ljava/lang/Object;Ljava/lang/Object;)J"),
+  ("ToLongFunction", "applyAsLong", "(Ljava/lang/Object;)J"),
+];
+
+// 抽象メソッドを継承するだけのインターフェース
+const SUBINTERFACES: &[(&str, &str)] = &[
+  ("BinaryOperator", "BiFunction"),
+  ("UnaryOperator", "Function"),
+];
+
+// デフォルトメソッド (andThenなど) と静的メソッドは持たない
+pub fn define(vm: &mut Vm) -> Result<(), VmError> {
+  for (name, method, descriptor) in FUNCTIONAL_INTERFACES {
+    vm.define_class(
+      ClassDefinition::new(&format!("java/util/function/{}", name))
+        .access_flags(ACC_PUBLIC | ACC_INTERFACE | ACC_ABSTRACT)
+        .abstract_method(method, descriptor, ACC_PUBLIC)
+    )?;
+  }
+  for (name, super_interface) in SUBINTERFACES {
+    vm.define_class(
+      ClassDefinition::new(&format!("java/util/function/{}", name))
+        .access_flags(ACC_PUBLIC | ACC_INTERFACE | ACC_ABSTRACT)
+        .interface(&format!("java/util/function/{}", super_interface))
+    )?;
+  }
+  Ok(())
+}
@@ -0,0 +1,161 @@
+use crate::{
+  runtime::{
+    builtin::{string_result, text_arg, this},
+    class::{ClassDefinition, ACC_FINAL, ACC_PRIVATE, ACC_PUBLIC, ACC_STATIC, ACC_SUPER, ACC_VARARGS},
+    error::VmError,
+    library::mirror::MirrorTarget,
+    memory,
+    value::{ObjRef, Value},
+    var_handle::{type_name, AccessOp, VarTarget, ACCESS_MODES},
+    vm::Vm,
+  },
+  util::descriptor::FieldType,
+};
+
+pub fn define(vm: &mut Vm) -> Result<(), VmError> {
+  // アクセスモードのメソッドはシグネチャ多相で、インタプリタが呼び出し側の記述子で実行する
+  let mut var_handle = ClassDefinition::new("java/lang/invoke/VarHandle")
+    .access_flags(ACC_PUBLIC | ACC_SUPER)
+    .field("kind", "I", ACC_PRIVATE | ACC_FINAL)
+    .field("member", "J", ACC_PRIVATE | ACC_FINAL)
+    .native("varType", "()Ljava/lang/Class;", ACC_PUBLIC, var_handle_var_type)
+    .native("toString", "()Ljava/lang/String;", ACC_PUBLIC | ACC_FINAL, var_handle_to_string)
+    .native("fullFence", "()V", ACC_PUBLIC | ACC_STATIC, var_handle_full_fence)
+    .native("acquireFence", "()V", ACC_PUBLIC | ACC_STATIC, var_handle_acquire_fence)
+    .native("releaseFence", "()V", ACC_PUBLIC | ACC_STATIC, var_handle_release_fence)
+    .native("loadLoadFence", "()V", ACC_PUBLIC | ACC_STATIC, var_handle_acquire_fence)
+    .native("storeStoreFence", "()V", ACC_PUBLIC | ACC_STATIC, var_handle_release_fence);
+  for &(name, op, _) in ACCESS_MODES {
+    let descriptor = match op {
+      AccessOp::Set => "([Ljava/lang/Object;)V",
+      AccessOp::CompareAndSet => "([Ljava/lang/Object;)Z",
+      _ => "([Ljava/lang/Object;)Ljava/lang/Object;",
+    };
+    var_handle = var_handle.native(name, descriptor, ACC_PUBLIC | ACC_FINAL | ACC_VARARGS, var_handle_access);
+  }
+  vm.define_class(var_handle)?;
+  vm.define_class(
+    ClassDefinition::new("java/lang/invoke/MethodHandles")
+      .access_flags(ACC_PUBLIC | ACC_FINAL | ACC_SUPER)
+      .native("lookup", "()Ljava/lang/invoke/MethodHandles$Lookup;", ACC_PUBLIC | ACC_STATIC, method_handles_lookup)
+      .native("arrayElementVarHandle", "(Ljava/lang/Class;)Ljava/lang/invoke/VarHandle;", ACC_PUBLIC | ACC_STATIC, method_handles_array_element_var_handle)
+  )?;
+  vm.define_class(
+    ClassDefinition::new("java/lang/invoke/MethodHandles$Lookup")
+      .access_flags(ACC_PUBLIC | ACC_FINAL | ACC_SUPER)
+      .field("lookupClass", "Ljava/lang/Class;", ACC_PRIVATE | ACC_FINAL)
+      .native("lookupClass", "()Ljava/lang/Class;", ACC_PUBLIC, lookup_lookup_class)
+      .native("findVarHandle", "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/VarHandle;", ACC_PUBLIC, lookup_find_var_handle)
+      .native("findStaticVarHandle", "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/VarHandle;", ACC_PUBLIC, lookup_find_static_var_handle)
+      .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, lookup_to_string)
+  )?;
+  Ok(())
+}
+
+// リフレクションなどでシグネチャ多相メソッドを直接呼んだ場合
+fn var_handle_access(_vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  Err(VmError::java("java/lang/UnsupportedOperationException", "cannot reflectively invoke VarHandle"))
+}
+
+fn var_handle_var_type(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let var_type = vm.var_type(vm.var_target(this(args)?)?)?;
+  Ok(Some(Value::Ref(vm.type_mirror(&var_type)?)))
+}
+
+fn var_handle_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let text = vm.var_handle_text(vm.var_target(this(args)?)?)?;
+  string_result(vm, &text.encode_utf16().collect::<Vec<_>>())
+}
+
+fn var_handle_full_fence(_vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  memory::full_fence();
+  Ok(None)
+}
+
+// loadLoadFence()はacquireFence()、storeStoreFence()はreleaseFence()で代用する
+fn var_handle_acquire_fence(_vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  memory::acquire_fence();
+  Ok(None)
+}
+
+fn var_handle_release_fence(_vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  memory::release_fence();
+  Ok(None)
+}
+
+// 呼び出し元のクラスを検索の基準にする
+fn method_handles_lookup(vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  let caller = vm.frames.last().map(|frame| frame.method.class)
+    .ok_or_else(|| VmError::internal("MethodHandles.lookup() without caller"))?;
+  let mirror = vm.class_mirror(caller)?;
+  let class = vm.load_class("java/lang/invoke/MethodHandles$Lookup")?;
+  let lookup = vm.instantiate(class)?;
+  vm.set_field(lookup, "lookupClass", "Ljava/lang/Class;", Value::Ref(mirror))?;
+  Ok(Some(Value::Ref(lookup)))
+}
+
+fn method_handles_array_element_var_handle(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let mirror = mirror_arg(args[0])?;
+  match vm.mirror_target(mirror).cloned() {
+    Some(MirrorTarget::Class(class)) if vm.classes[class].is_array() => {
+      Ok(Some(Value::Ref(vm.new_var_handle(VarTarget::Array(class))?)))
+    },
+    Some(target) => Err(VmError::java("java/lang/IllegalArgumentException", format!("not an array: {}", vm.mirror_text(&target)))),
+    None => Err(VmError::internal("Not a Class object")),
+  }
+}
+
+fn mirror_arg(value: Value) -> Result<ObjRef, VmError> {
+  value.as_ref()?.ok_or_else(|| VmError::java_without_message("java/lang/NullPointerException"))
+}
+
+fn lookup_class(vm: &Vm, lookup: ObjRef) -> Result<ObjRef, VmError> {
+  vm.get_field(lookup, "lookupClass", "Ljava/lang/Class;")?.as_ref()?
+    .ok_or_else(|| VmError::internal("Lookup without lookup class"))
+}
+
+fn lookup_lookup_class(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Ref(lookup_class(vm, this(args)?)?)))
+}
+
+fn lookup_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let name = type_name(&vm.mirror_type(lookup_class(vm, this(args)?)?)?);
+  string_result(vm, &name.encode_utf16().collect::<Vec<_>>())
+}
+
+fn lookup_find_var_handle(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  find_var_handle(vm, args, false)
+}
+
+fn lookup_find_static_var_handle(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  find_var_handle(vm, args, true)
+}
+
+// Lookup.findVarHandle()とfindStaticVarHandle() (フィールドの解決と同じ順序で探す)
+fn find_var_handle(vm: &mut Vm, args: &[Value], is_static: bool) -> Result<Option<Value>, VmError> {
+  let lookup = this(args)?;
+  let receiver = vm.mirror_type(mirror_arg(args[1])?)?;
+  let name = String::from_utf16_lossy(&text_arg(vm, args[2])?);
+  let field_type = vm.mirror_type(mirror_arg(args[3])?)?;
+  let reference_kind = if is_static { "getStatic" } else { "getField" };
+  let member = format!("{}.{}/{}/{}", type_name(&receiver), name, type_name(&field_type), reference_kind);
+  let found = match &receiver {
+    FieldType::Object(_) | FieldType::Array(_) => {
+      let class = vm.load_class(&receiver.class_name())?;
+      vm.find_field(class, &name, &field_type.descriptor())
+    },
+    _ => None,
+  };
+  let Some((owner, index)) = found else {
+    return Err(VmError::java("java/lang/NoSuchFieldException", format!("no such field: {}", member)));
+  };
+  let field = &vm.classes[owner].fields[index];
+  if field.is_static() != is_static {
+    let expected = if is_static { "a static field" } else { "a non-static field" };
+    let member = format!("{}.{}/{}/{}", type_name(&receiver), name, type_name(&field_type), if field.is_static() { "getStatic" } else { "getField" });
+    let caller = type_name(&vm.mirror_type(lookup_class(vm, lookup)?)?);
+    return Err(VmError::java("java/lang/IllegalAccessException", format!("expected {}: {}, from class {}", expected, member, caller)));
+  }
+  let target = if is_static { VarTarget::Static(owner, index) } else { VarTarget::Field(owner, index) };
+  Ok(Some(Value::Ref(vm.new_var_handle(target)?)))
+}
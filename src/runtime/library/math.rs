@@ -0,0 +1,391 @@
+use std::{
+  sync::atomic::{AtomicU64, Ordering},
+  time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::runtime::{
+  class::{ClassDefinition, NativeFn, ACC_FINAL, ACC_PUBLIC, ACC_STATIC, ACC_SUPER},
+  error::VmError,
+  library::boxing::{max_double, min_double},
+  value::Value,
+  vm::Vm,
+};
+
+// Math.random()の状態 (xorshift64*)
+static RANDOM_STATE: AtomicU64 = AtomicU64::new(0);
+
+// 引数と戻り値がdoubleのメソッド
+pub(crate) const DOUBLE_FUNCTIONS: &[(&str, NativeFn)] = &[
+  ("sqrt", math_sqrt),
+  ("cbrt", math_cbrt),
+  ("exp", math_exp),
+  ("expm1", math_expm1),
+  ("log", math_log),
+  ("log10", math_log10),
+  ("log1p", math_log1p),
+  ("sin", math_sin),
+  ("cos", math_cos),
+  ("tan", math_tan),
+  ("asin", math_asin),
+  ("acos", math_acos),
+  ("atan", math_atan),
+  ("sinh", math_sinh),
+  ("cosh", math_cosh),
+  ("tanh", math_tanh),
+  ("floor", math_floor),
+  ("ceil", math_ceil),
+  ("rint", math_rint),
+  ("abs", math_abs),
+  ("signum", math_signum),
+  ("toRadians", math_to_radians),
+  ("toDegrees", math_to_degrees),
+];
+
+const STATIC_METHODS: &[(&str, &str, NativeFn)] = &[
+  ("abs", "(I)I", math_abs_int),
+  ("abs", "(J)J", math_abs_long),
+  ("abs", "(F)F", math_abs_float),
+  ("max", "(II)I", math_max_int),
+  ("max", "(JJ)J", math_max_long),
+  ("max", "(FF)F", math_max_float),
+  ("max", "(DD)D", math_max_double),
+  ("min", "(II)I", math_min_int),
+  ("min", "(JJ)J", math_min_long),
+  ("min", "(FF)F", math_min_float),
+  ("min", "(DD)D", math_min_double),
+  ("pow", "(DD)D", math_pow),
+  ("atan2", "(DD)D", math_atan2),
+  ("hypot", "(DD)D", math_hypot),
+  ("round", "(F)I", math_round_float),
+  ("round", "(D)J", math_round_double),
+  ("signum", "(F)F", math_signum_float),
+  ("random", "()D", math_random),
+  ("floorDiv", "(II)I", math_floor_div_int),
+  ("floorDiv", "(JJ)J", math_floor_div_long),
+  ("floorMod", "(II)I", math_floor_mod_int),
+  ("floorMod", "(JJ)J", math_floor_mod_long),
+  ("addExact", "(II)I", math_add_exact_int),
+  ("addExact", "(JJ)J", math_add_exact_long),
+  ("subtractExact", "(II)I", math_subtract_exact_int),
+  ("subtractExact", "(JJ)J", math_subtract_exact_long),
+  ("multiplyExact", "(II)I", math_multiply_exact_int),
+  ("multiplyExact", "(JJ)J", math_multiply_exact_long),
+  ("negateExact", "(I)I", math_negate_exact_int),
+  ("negateExact", "(J)J", math_negate_exact_long),
+  ("toIntExact", "(J)I", math_to_int_exact),
+];
+
+pub fn define(vm: &mut Vm) -> Result<(), VmError> {
+  let mut definition = ClassDefinition::new("java/lang/Math")
+    .access_flags(ACC_PUBLIC | ACC_FINAL | ACC_SUPER)
+    .field("PI", "D", ACC_PUBLIC | ACC_STATIC | ACC_FINAL)
+    .field("E", "D", ACC_PUBLIC | ACC_STATIC | ACC_FINAL)
+    .native("<clinit>", "()V", ACC_STATIC, math_clinit);
+  for (name, function) in DOUBLE_FUNCTIONS {
+    definition = definition.native(name, "(D)D", ACC_PUBLIC | ACC_STATIC, *function);
+  }
+  for (name, descriptor, function) in STATIC_METHODS {
+    definition = definition.native(name, descriptor, ACC_PUBLIC | ACC_STATIC, *function);
+  }
+  vm.define_class(definition)?;
+  Ok(())
+}
+
+fn math_clinit(vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  let class = vm.load_class("java/lang/Math")?;
+  vm.set_static_field(class, "PI", "D", Value::Double(std::f64::consts::PI))?;
+  vm.set_static_field(class, "E", "D", Value::Double(std::f64::consts::E))?;
+  Ok(None)
+}
+
+fn signum(value: f64) -> f64 {
+  if value == 0.0 || value.is_nan() { value } else { value.signum() }
+}
+
+fn double_function(args: &[Value], function: fn(f64) -> f64) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Double(function(args[0].as_double()?))))
+}
+
+fn math_sqrt(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::sqrt)
+}
+
+fn math_cbrt(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::cbrt)
+}
+
+fn math_exp(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::exp)
+}
+
+fn math_expm1(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::exp_m1)
+}
+
+fn math_log(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::ln)
+}
+
+fn math_log10(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::log10)
+}
+
+fn math_log1p(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::ln_1p)
+}
+
+fn math_sin(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::sin)
+}
+
+fn math_cos(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::cos)
+}
+
+fn math_tan(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::tan)
+}
+
+fn math_asin(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::asin)
+}
+
+fn math_acos(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::acos)
+}
+
+fn math_atan(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::atan)
+}
+
+fn math_sinh(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::sinh)
+}
+
+fn math_cosh(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::cosh)
+}
+
+fn math_tanh(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::tanh)
+}
+
+fn math_floor(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::floor)
+}
+
+fn math_ceil(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::ceil)
+}
+
+fn math_rint(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::round_ties_even)
+}
+
+fn math_abs(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::abs)
+}
+
+fn math_signum(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, signum)
+}
+
+fn math_to_radians(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::to_radians)
+}
+
+fn math_to_degrees(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  double_function(args, f64::to_degrees)
+}
+
+fn overflow(name: &str) -> VmError {
+  VmError::java("java/lang/ArithmeticException", format!("{} overflow", name))
+}
+
+fn divide_by_zero() -> VmError {
+  VmError::java("java/lang/ArithmeticException", "/ by zero")
+}
+
+fn int_args(args: &[Value]) -> Result<(i32, i32), VmError> {
+  Ok((args[0].as_int()?, args[1].as_int()?))
+}
+
+fn long_args(args: &[Value]) -> Result<(i64, i64), VmError> {
+  Ok((args[0].as_long()?, args[1].as_long()?))
+}
+
+fn math_abs_int(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_int()?.wrapping_abs())))
+}
+
+fn math_abs_long(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Long(args[0].as_long()?.wrapping_abs())))
+}
+
+fn math_abs_float(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Float(args[0].as_float()?.abs())))
+}
+
+fn math_max_int(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (a, b) = int_args(args)?;
+  Ok(Some(Value::Int(a.max(b))))
+}
+
+fn math_max_long(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (a, b) = long_args(args)?;
+  Ok(Some(Value::Long(a.max(b))))
+}
+
+fn math_max_float(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Float(max_double(args[0].as_float()? as f64, args[1].as_float()? as f64) as f32)))
+}
+
+fn math_max_double(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Double(max_double(args[0].as_double()?, args[1].as_double()?))))
+}
+
+fn math_min_int(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (a, b) = int_args(args)?;
+  Ok(Some(Value::Int(a.min(b))))
+}
+
+fn math_min_long(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (a, b) = long_args(args)?;
+  Ok(Some(Value::Long(a.min(b))))
+}
+
+fn math_min_float(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Float(min_double(args[0].as_float()? as f64, args[1].as_float()? as f64) as f32)))
+}
+
+fn math_min_double(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Double(min_double(args[0].as_double()?, args[1].as_double()?))))
+}
+
+// Rustのpowfと違い、指数がNaNなら常にNaN、|x| == 1で指数が無限大ならNaN
+fn math_pow(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (x, y) = (args[0].as_double()?, args[1].as_double()?);
+  let result = if y.is_nan() || (x.abs() == 1.0 && y.is_infinite()) { f64::NAN } else { x.powf(y) };
+  Ok(Some(Value::Double(result)))
+}
+
+fn math_atan2(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Double(args[0].as_double()?.atan2(args[1].as_double()?))))
+}
+
+fn math_hypot(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Double(args[0].as_double()?.hypot(args[1].as_double()?))))
+}
+
+// 0.5を足して切り捨てた値 (-2.5は-2)、NaNは0で範囲外は飽和する
+fn round_half_up(value: f64) -> f64 {
+  let floor = value.floor();
+  if value - floor >= 0.5 { floor + 1.0 } else { floor }
+}
+
+fn math_round_float(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(round_half_up(args[0].as_float()? as f64) as i32)))
+}
+
+fn math_round_double(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Long(round_half_up(args[0].as_double()?) as i64)))
+}
+
+fn math_signum_float(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Float(signum(args[0].as_float()? as f64) as f32)))
+}
+
+// 0.0以上1.0未満の一様乱数
+fn math_random(_vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  let mut state = RANDOM_STATE.load(Ordering::Relaxed);
+  if state == 0 {
+    state = SystemTime::now().duration_since(UNIX_EPOCH).map_or(1, |d| d.as_nanos() as u64) | 1;
+  }
+  state ^= state >> 12;
+  state ^= state << 25;
+  state ^= state >> 27;
+  RANDOM_STATE.store(state, Ordering::Relaxed);
+  let bits = state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11;
+  Ok(Some(Value::Double(bits as f64 / (1u64 << 53) as f64)))
+}
+
+fn math_floor_div_int(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (a, b) = int_args(args)?;
+  if b == 0 {
+    return Err(divide_by_zero());
+  }
+  let quotient = a.wrapping_div(b);
+  let adjust = (a % b != 0 && (a < 0) != (b < 0)) as i32;
+  Ok(Some(Value::Int(quotient - adjust)))
+}
+
+fn math_floor_div_long(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (a, b) = long_args(args)?;
+  if b == 0 {
+    return Err(divide_by_zero());
+  }
+  let quotient = a.wrapping_div(b);
+  let adjust = (a % b != 0 && (a < 0) != (b < 0)) as i64;
+  Ok(Some(Value::Long(quotient - adjust)))
+}
+
+fn math_floor_mod_int(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (a, b) = int_args(args)?;
+  if b == 0 {
+    return Err(divide_by_zero());
+  }
+  let remainder = a.wrapping_rem(b);
+  Ok(Some(Value::Int(if remainder != 0 && (remainder < 0) != (b < 0) { remainder + b } else { remainder })))
+}
+
+fn math_floor_mod_long(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (a, b) = long_args(args)?;
+  if b == 0 {
+    return Err(divide_by_zero());
+  }
+  let remainder = a.wrapping_rem(b);
+  Ok(Some(Value::Long(if remainder != 0 && (remainder < 0) != (b < 0) { remainder + b } else { remainder })))
+}
+
+fn math_add_exact_int(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (a, b) = int_args(args)?;
+  Ok(Some(Value::Int(a.checked_add(b).ok_or_else(|| overflow("integer"))?)))
+}
+
+fn math_add_exact_long(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (a, b) = long_args(args)?;
+  Ok(Some(Value::Long(a.checked_add(b).ok_or_else(|| overflow("long"))?)))
+}
+
+fn math_subtract_exact_int(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (a, b) = int_args(args)?;
+  Ok(Some(Value::Int(a.checked_sub(b).ok_or_else(|| overflow("integer"))?)))
+}
+
+fn math_subtract_exact_long(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (a, b) = long_args(args)?;
+  Ok(Some(Value::Long(a.checked_sub(b).ok_or_else(|| overflow("long"))?)))
+}
+
+fn math_multiply_exact_int(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (a, b) = int_args(args)?;
+  Ok(Some(Value::Int(a.checked_mul(b).ok_or_else(|| overflow("integer"))?)))
+}
+
+fn math_multiply_exact_long(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (a, b) = long_args(args)?;
+  Ok(Some(Value::Long(a.checked_mul(b).ok_or_else(|| overflow("long"))?)))
+}
+
+fn math_negate_exact_int(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(args[0].as_int()?.checked_neg().ok_or_else(|| overflow("integer"))?)))
+}
+
+fn math_negate_exact_long(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Long(args[0].as_long()?.checked_neg().ok_or_else(|| overflow("long"))?)))
+}
+
+fn math_to_int_exact(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let value = args[0].as_long()?;
+  Ok(Some(Value::Int(i32::try_from(value).map_err(|_| overflow("integer"))?)))
+}
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+
+use crate::{
+  runtime::{
+    builtin::{string_result, this},
+    class::{ClassDefinition, ClassId, ACC_FINAL, ACC_PUBLIC, ACC_SUPER},
+    error::VmError,
+    method_handle::{simple_name, wrapper_class},
+    value::{ObjRef, Value},
+    vm::Vm,
+  },
+  util::descriptor::FieldType,
+};
+
+const PRIMITIVES: &[FieldType] = &[
+  FieldType::Boolean,
+  FieldType::Byte,
+  FieldType::Char,
+  FieldType::Short,
+  FieldType::Int,
+  FieldType::Long,
+  FieldType::Float,
+  FieldType::Double,
+];
+
+// Classオブジェクトが表す型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MirrorTarget {
+  Class(ClassId),
+  Primitive(FieldType),
+}
+
+// クラスと基本型ごとに1つだけ作るClassオブジェクト (一度作ったら回収しない)
+#[derive(Debug, Default)]
+pub struct Mirrors {
+  classes: HashMap<ClassId, ObjRef>,
+  primitives: HashMap<FieldType, ObjRef>,
+  targets: HashMap<ObjRef, MirrorTarget>,
+}
+
+impl Mirrors {
+  pub fn class_mirror(&self, class: ClassId) -> Option<ObjRef> {
+    self.classes.get(&class).copied()
+  }
+
+  pub fn roots(&self) -> impl Iterator<Item = ObjRef> + '_ {
+    self.targets.keys().copied()
+  }
+}
+
+pub fn define(vm: &mut Vm) -> Result<(), VmError> {
+  vm.define_class(
+    ClassDefinition::new("java/lang/Class")
+      .access_flags(ACC_PUBLIC | ACC_FINAL | ACC_SUPER)
+      .interface("java/io/Serializable")
+      .native("getName", "()Ljava/lang/String;", ACC_PUBLIC, class_get_name)
+      .native("getSimpleName", "()Ljava/lang/String;", ACC_PUBLIC, class_get_simple_name)
+      .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, class_to_string)
+      .native("isPrimitive", "()Z", ACC_PUBLIC, class_is_primitive)
+      .native("isArray", "()Z", ACC_PUBLIC, class_is_array)
+      .native("isInterface", "()Z", ACC_PUBLIC, class_is_interface)
+      .native("isInstance", "(Ljava/lang/Object;)Z", ACC_PUBLIC, class_is_instance)
+      .native("getSuperclass", "()Ljava/lang/Class;", ACC_PUBLIC, class_get_superclass)
+      .native("getComponentType", "()Ljava/lang/Class;", ACC_PUBLIC, class_get_component_type)
+      .native("desiredAssertionStatus", "()Z", ACC_PUBLIC, class_desired_assertion_status)
+  )?;
+  // int.classなどはラッパークラスのTYPEフィールドとしてコンパイルされる
+  for primitive in PRIMITIVES {
+    let Some(wrapper) = wrapper_class(primitive) else { continue };
+    let class = vm.load_class(wrapper)?;
+    let mirror = vm.primitive_mirror(primitive)?;
+    vm.set_static_field(class, "TYPE", "Ljava/lang/Class;", Value::Ref(mirror))?;
+  }
+  Ok(())
+}
+
+impl Vm {
+  pub fn class_mirror(&mut self, class: ClassId) -> Result<ObjRef, VmError> {
+    if let Some(&mirror) = self.mirrors.classes.get(&class) {
+      return Ok(mirror);
+    }
+    let mirror = self.new_mirror(MirrorTarget::Class(class))?;
+    self.mirrors.classes.insert(class, mirror);
+    Ok(mirror)
+  }
+
+  pub fn primitive_mirror(&mut self, primitive: &FieldType) -> Result<ObjRef, VmError> {
+    if let Some(&mirror) = self.mirrors.primitives.get(primitive) {
+      return Ok(mirror);
+    }
+    let mirror = self.new_mirror(MirrorTarget::Primitive(primitive.clone()))?;
+    self.mirrors.primitives.insert(primitive.clone(), mirror);
+    Ok(mirror)
+  }
+
+  // 型を表すClassオブジェクト (参照型ならクラスを読み込む)
+  pub fn type_mirror(&mut self, field_type: &FieldType) -> Result<ObjRef, VmError> {
+    match field_type {
+      FieldType::Object(_) | FieldType::Array(_) => {
+        let class = self.load_class(&field_type.class_name())?;
+        self.class_mirror(class)
+      },
+      primitive => self.primitive_mirror(primitive),
+    }
+  }
+
+  fn new_mirror(&mut self, target: MirrorTarget) -> Result<ObjRef, VmError> {
+    let class = self.load_class("java/lang/Class")?;
+    let mirror = self.instantiate(class)?;
+    self.mirrors.targets.insert(mirror, target);
+    Ok(mirror)
+  }
+
+  pub fn mirror_target(&self, mirror: ObjRef) -> Option<&MirrorTarget> {
+    self.mirrors.targets.get(&mirror)
+  }
+
+  // Classオブジェクトが表す型 (記述子の形)
+  pub fn mirror_type(&self, mirror: ObjRef) -> Result<FieldType, VmError> {
+    match self.mirror_target(mirror) {
+      Some(MirrorTarget::Class(class)) => Ok(self.class_type(*class)),
+      Some(MirrorTarget::Primitive(primitive)) => Ok(primitive.clone()),
+      None => Err(VmError::internal("Not a Class object")),
+    }
+  }
+
+  // Class.toString()の形式 ("class Foo"、"interface Bar"、基本型は名前だけ)
+  pub fn mirror_text(&self, target: &MirrorTarget) -> String {
+    match target {
+      MirrorTarget::Class(class) if self.classes[*class].is_interface() => format!("interface {}", self.classes[*class].java_name()),
+      MirrorTarget::Class(class) => format!("class {}", self.classes[*class].java_name()),
+      MirrorTarget::Primitive(primitive) => primitive.to_string(),
+    }
+  }
+
+  pub fn class_type(&self, class: ClassId) -> FieldType {
+    let name = &self.classes[class].name;
+    match name.starts_with('[') {
+      true => FieldType::parse(name).unwrap_or_else(|_| FieldType::Object(name.clone())),
+      false => FieldType::Object(name.clone()),
+    }
+  }
+}
+
+fn target(vm: &Vm, args: &[Value]) -> Result<MirrorTarget, VmError> {
+  vm.mirror_target(this(args)?).cloned().ok_or_else(|| VmError::internal("Not a Class object"))
+}
+
+fn class_get_name(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let name = match target(vm, args)? {
+    MirrorTarget::Class(class) => vm.classes[class].java_name(),
+    MirrorTarget::Primitive(primitive) => primitive.to_string(),
+  };
+  string_result(vm, &name.encode_utf16().collect::<Vec<_>>())
+}
+
+fn type_simple_name(field_type: &FieldType) -> String {
+  match field_type {
+    FieldType::Object(name) => {
+      let name = simple_name(name);
+      // 匿名クラスは空文字列
+      if name.chars().all(|c| c.is_ascii_digit()) { String::new() } else { name.to_string() }
+    },
+    FieldType::Array(component) => format!("{}[]", type_simple_name(component)),
+    primitive => primitive.to_string(),
+  }
+}
+
+fn class_get_simple_name(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let name = type_simple_name(&vm.mirror_type(this(args)?)?);
+  string_result(vm, &name.encode_utf16().collect::<Vec<_>>())
+}
+
+fn class_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let text = vm.mirror_text(&target(vm, args)?);
+  string_result(vm, &text.encode_utf16().collect::<Vec<_>>())
+}
+
+fn class_is_primitive(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(matches!(target(vm, args)?, MirrorTarget::Primitive(_)) as i32)))
+}
+
+fn class_is_array(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let is_array = matches!(target(vm, args)?, MirrorTarget::Class(class) if vm.classes[class].is_array());
+  Ok(Some(Value::Int(is_array as i32)))
+}
+
+fn class_is_interface(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let is_interface = matches!(target(vm, args)?, MirrorTarget::Class(class) if vm.classes[class].is_interface());
+  Ok(Some(Value::Int(is_interface as i32)))
+}
+
+fn class_is_instance(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let result = match (target(vm, args)?, args[1].as_ref()?) {
+    (MirrorTarget::Class(class), Some(object)) => vm.is_assignable(vm.object_class(object)?, class),
+    _ => false,
+  };
+  Ok(Some(Value::Int(result as i32)))
+}
+
+// インターフェースと基本型、Objectはnull
+fn class_get_superclass(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let super_class = match target(vm, args)? {
+    MirrorTarget::Class(class) if !vm.classes[class].is_interface() => vm.classes[class].super_class,
+    _ => None,
+  };
+  match super_class {
+    Some(super_class) => Ok(Some(Value::Ref(vm.class_mirror(super_class)?))),
+    None => Ok(Some(Value::Null)),
+  }
+}
+
+fn class_get_component_type(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let component = match target(vm, args)? {
+    MirrorTarget::Class(class) => vm.classes[class].component.clone(),
+    MirrorTarget::Primitive(_) => None,
+  };
+  match component {
+    Some(component) => Ok(Some(Value::Ref(vm.type_mirror(&component)?))),
+    None => Ok(Some(Value::Null)),
+  }
+}
+
+// assert文は常に無効
+fn class_desired_assertion_status(_vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(0)))
+}
+
+pub(crate) fn object_get_class(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let class = vm.object_class(this(args)?)?;
+  Ok(Some(Value::Ref(vm.class_mirror(class)?)))
+}
@@ -0,0 +1,246 @@
+use crate::{
+  runtime::{
+    builtin::{text_arg, this},
+    class::{ClassDefinition, NativeFn, ACC_FINAL, ACC_PRIVATE, ACC_PUBLIC, ACC_STATIC, ACC_SUPER},
+    error::VmError,
+    memory::{self, Location, MemoryOrder},
+    value::{ObjRef, Value},
+    vm::Vm,
+  },
+  util::descriptor::FieldType,
+};
+
+// Unsafeのオフセットはフィールドならスロットの位置、配列なら添字そのもの (ベース0、スケール1)
+const ARRAY_BASE_OFFSET: i32 = 0;
+const ARRAY_INDEX_SCALE: i32 = 1;
+
+// jdk.internal.misc.Unsafeのメソッド ({T}はInt、Long、Referenceに、{V}はその記述子に置き換える)
+const TYPED_METHODS: &[(&str, &str, NativeFn)] = &[
+  ("get{T}", "(Ljava/lang/Object;J){V}", unsafe_get),
+  ("put{T}", "(Ljava/lang/Object;J{V})V", unsafe_put),
+  ("get{T}Volatile", "(Ljava/lang/Object;J){V}", unsafe_get_volatile),
+  ("put{T}Volatile", "(Ljava/lang/Object;J{V})V", unsafe_put_volatile),
+  ("get{T}Acquire", "(Ljava/lang/Object;J){V}", unsafe_get_acquire),
+  ("put{T}Release", "(Ljava/lang/Object;J{V})V", unsafe_put_release),
+  ("get{T}Opaque", "(Ljava/lang/Object;J){V}", unsafe_get_opaque),
+  ("put{T}Opaque", "(Ljava/lang/Object;J{V})V", unsafe_put_opaque),
+  ("compareAndSet{T}", "(Ljava/lang/Object;J{V}{V})Z", unsafe_compare_and_set),
+  ("weakCompareAndSet{T}", "(Ljava/lang/Object;J{V}{V})Z", unsafe_compare_and_set),
+  ("weakCompareAndSet{T}Plain", "(Ljava/lang/Object;J{V}{V})Z", unsafe_weak_compare_and_set_plain),
+  ("compareAndExchange{T}", "(Ljava/lang/Object;J{V}{V}){V}", unsafe_compare_and_exchange),
+  ("getAndSet{T}", "(Ljava/lang/Object;J{V}){V}", unsafe_get_and_set),
+];
+
+const TYPES: &[(&str, &str)] = &[
+  ("Int", "I"),
+  ("Long", "J"),
+  ("Reference", "Ljava/lang/Object;"),
+];
+
+// JDKのクラスファイルのネイティブメソッドにも結び付ける実装
+pub(crate) const NATIVES: &[(&str, &str, NativeFn)] = &[
+  ("compareAndSetInt", "(Ljava/lang/Object;JII)Z", unsafe_compare_and_set),
+  ("compareAndSetLong", "(Ljava/lang/Object;JJJ)Z", unsafe_compare_and_set),
+  ("compareAndSetReference", "(Ljava/lang/Object;JLjava/lang/Object;Ljava/lang/Object;)Z", unsafe_compare_and_set),
+  ("compareAndExchangeInt", "(Ljava/lang/Object;JII)I", unsafe_compare_and_exchange),
+  ("compareAndExchangeLong", "(Ljava/lang/Object;JJJ)J", unsafe_compare_and_exchange),
+  ("compareAndExchangeReference", "(Ljava/lang/Object;JLjava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;", unsafe_compare_and_exchange),
+  ("getInt", "(Ljava/lang/Object;J)I", unsafe_get),
+  ("putInt", "(Ljava/lang/Object;JI)V", unsafe_put),
+  ("getLong", "(Ljava/lang/Object;J)J", unsafe_get),
+  ("putLong", "(Ljava/lang/Object;JJ)V", unsafe_put),
+  ("getReference", "(Ljava/lang/Object;J)Ljava/lang/Object;", unsafe_get),
+  ("putReference", "(Ljava/lang/Object;JLjava/lang/Object;)V", unsafe_put),
+  ("getIntVolatile", "(Ljava/lang/Object;J)I", unsafe_get_volatile),
+  ("putIntVolatile", "(Ljava/lang/Object;JI)V", unsafe_put_volatile),
+  ("getLongVolatile", "(Ljava/lang/Object;J)J", unsafe_get_volatile),
+  ("putLongVolatile", "(Ljava/lang/Object;JJ)V", unsafe_put_volatile),
+  ("getReferenceVolatile", "(Ljava/lang/Object;J)Ljava/lang/Object;", unsafe_get_volatile),
+  ("putReferenceVolatile", "(Ljava/lang/Object;JLjava/lang/Object;)V", unsafe_put_volatile),
+  ("objectFieldOffset1", "(Ljava/lang/Class;Ljava/lang/String;)J", unsafe_object_field_offset),
+  ("arrayBaseOffset0", "(Ljava/lang/Class;)I", unsafe_array_base_offset),
+  ("arrayIndexScale0", "(Ljava/lang/Class;)I", unsafe_array_index_scale),
+  ("fullFence", "()V", unsafe_full_fence),
+  ("loadFence", "()V", unsafe_load_fence),
+  ("storeFence", "()V", unsafe_store_fence),
+];
+
+pub fn define(vm: &mut Vm) -> Result<(), VmError> {
+  let mut definition = ClassDefinition::new("jdk/internal/misc/Unsafe")
+    .access_flags(ACC_PUBLIC | ACC_FINAL | ACC_SUPER)
+    .field("theUnsafe", "Ljdk/internal/misc/Unsafe;", ACC_PRIVATE | ACC_STATIC | ACC_FINAL)
+    .native("<clinit>", "()V", ACC_STATIC, unsafe_clinit)
+    .native("getUnsafe", "()Ljdk/internal/misc/Unsafe;", ACC_PUBLIC | ACC_STATIC, unsafe_get_unsafe)
+    .native("objectFieldOffset", "(Ljava/lang/Class;Ljava/lang/String;)J", ACC_PUBLIC, unsafe_object_field_offset)
+    .native("arrayBaseOffset", "(Ljava/lang/Class;)I", ACC_PUBLIC, unsafe_array_base_offset)
+    .native("arrayIndexScale", "(Ljava/lang/Class;)I", ACC_PUBLIC, unsafe_array_index_scale)
+    .native("getAndAddInt", "(Ljava/lang/Object;JI)I", ACC_PUBLIC | ACC_FINAL, unsafe_get_and_add)
+    .native("getAndAddLong", "(Ljava/lang/Object;JJ)J", ACC_PUBLIC | ACC_FINAL, unsafe_get_and_add)
+    .native("fullFence", "()V", ACC_PUBLIC, unsafe_full_fence)
+    .native("loadFence", "()V", ACC_PUBLIC, unsafe_load_fence)
+    .native("storeFence", "()V", ACC_PUBLIC, unsafe_store_fence)
+    .native("loadLoadFence", "()V", ACC_PUBLIC | ACC_FINAL, unsafe_load_fence)
+    .native("storeStoreFence", "()V", ACC_PUBLIC | ACC_FINAL, unsafe_store_fence);
+  for &(name, descriptor, function) in TYPED_METHODS {
+    for &(type_name, value_type) in TYPES {
+      let name = name.replace("{T}", type_name);
+      definition = definition.native(&name, &descriptor.replace("{V}", value_type), ACC_PUBLIC | ACC_FINAL, function);
+    }
+  }
+  vm.define_class(definition)?;
+  Ok(())
+}
+
+fn unsafe_clinit(vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  let class = vm.load_class("jdk/internal/misc/Unsafe")?;
+  let the_unsafe = vm.instantiate(class)?;
+  vm.set_static_field(class, "theUnsafe", "Ljdk/internal/misc/Unsafe;", Value::Ref(the_unsafe))?;
+  Ok(None)
+}
+
+fn unsafe_get_unsafe(vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  let class = vm.load_class("jdk/internal/misc/Unsafe")?;
+  Ok(Some(vm.get_static_field(class, "theUnsafe", "Ljdk/internal/misc/Unsafe;")?))
+}
+
+// (オブジェクト, オフセット) が指す変数 (範囲外のオフセットはJVMを壊さないようにエラーにする)
+fn location(vm: &Vm, base: Value, offset: Value) -> Result<Location, VmError> {
+  let Some(object) = base.as_ref()? else {
+    return Err(VmError::java("java/lang/UnsupportedOperationException", "off-heap memory access is not supported"));
+  };
+  let offset = offset.as_long()?;
+  let target = vm.heap.get(object)?;
+  let length = match target.array() {
+    Ok(array) => array.len(),
+    Err(_) => target.fields()?.len(),
+  };
+  if offset < 0 || offset as usize >= length {
+    return Err(VmError::internal(format!("Invalid Unsafe offset: {}", offset)));
+  }
+  match target.array() {
+    Ok(_) => Ok(Location::Element(object, offset as usize)),
+    Err(_) => Ok(Location::Field(object, offset as usize)),
+  }
+}
+
+// 引数はthis、オブジェクト、オフセットの後に値が続く
+fn get(vm: &mut Vm, args: &[Value], order: MemoryOrder) -> Result<Option<Value>, VmError> {
+  let location = location(vm, args[1], args[2])?;
+  Ok(Some(vm.load_ordered(location, order)?))
+}
+
+fn put(vm: &mut Vm, args: &[Value], order: MemoryOrder) -> Result<Option<Value>, VmError> {
+  let location = location(vm, args[1], args[2])?;
+  vm.store_ordered(location, args[3], order)?;
+  Ok(None)
+}
+
+fn unsafe_get(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  get(vm, args, MemoryOrder::Plain)
+}
+
+fn unsafe_put(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  put(vm, args, MemoryOrder::Plain)
+}
+
+fn unsafe_get_volatile(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  get(vm, args, MemoryOrder::Volatile)
+}
+
+fn unsafe_put_volatile(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  put(vm, args, MemoryOrder::Volatile)
+}
+
+fn unsafe_get_acquire(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  get(vm, args, MemoryOrder::Acquire)
+}
+
+fn unsafe_put_release(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  put(vm, args, MemoryOrder::Release)
+}
+
+fn unsafe_get_opaque(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  get(vm, args, MemoryOrder::Opaque)
+}
+
+fn unsafe_put_opaque(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  put(vm, args, MemoryOrder::Opaque)
+}
+
+fn unsafe_compare_and_set(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let location = location(vm, args[1], args[2])?;
+  let result = vm.compare_and_set(location, args[3], args[4], MemoryOrder::Volatile)?;
+  Ok(Some(Value::Int(result as i32)))
+}
+
+fn unsafe_weak_compare_and_set_plain(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let location = location(vm, args[1], args[2])?;
+  let result = vm.compare_and_set(location, args[3], args[4], MemoryOrder::Plain)?;
+  Ok(Some(Value::Int(result as i32)))
+}
+
+fn unsafe_compare_and_exchange(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let location = location(vm, args[1], args[2])?;
+  Ok(Some(vm.compare_and_exchange(location, args[3], args[4], MemoryOrder::Volatile)?))
+}
+
+fn unsafe_get_and_set(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let location = location(vm, args[1], args[2])?;
+  Ok(Some(vm.get_and_set(location, args[3], MemoryOrder::Volatile)?))
+}
+
+fn unsafe_get_and_add(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let location = location(vm, args[1], args[2])?;
+  Ok(Some(vm.get_and_add(location, args[3], MemoryOrder::Volatile)?))
+}
+
+// 宣言したクラスだけから名前でインスタンスフィールドを探す (見つからなければInternalError)
+fn unsafe_object_field_offset(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  this(args)?;
+  let mirror = args[1].as_ref()?.ok_or_else(|| VmError::java_without_message("java/lang/NullPointerException"))?;
+  let name = String::from_utf16_lossy(&text_arg(vm, args[2])?);
+  let class = match vm.mirror_type(mirror)? {
+    field_type @ (FieldType::Object(_) | FieldType::Array(_)) => vm.load_class(&field_type.class_name())?,
+    _ => return Err(VmError::java("java/lang/InternalError", name)),
+  };
+  let slot = vm.classes[class].fields.iter()
+    .find(|field| field.name == name && !field.is_static())
+    .map(|field| field.slot);
+  match slot {
+    Some(slot) => Ok(Some(Value::Long(slot as i64))),
+    None => Err(VmError::java("java/lang/InternalError", name)),
+  }
+}
+
+fn array_class_arg(vm: &Vm, args: &[Value]) -> Result<ObjRef, VmError> {
+  let mirror = args[1].as_ref()?.ok_or_else(|| VmError::java_without_message("java/lang/NullPointerException"))?;
+  match vm.mirror_type(mirror)? {
+    FieldType::Array(_) => Ok(mirror),
+    _ => Err(VmError::java_without_message("java/lang/IllegalArgumentException")),
+  }
+}
+
+fn unsafe_array_base_offset(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  array_class_arg(vm, args)?;
+  Ok(Some(Value::Int(ARRAY_BASE_OFFSET)))
+}
+
+fn unsafe_array_index_scale(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  array_class_arg(vm, args)?;
+  Ok(Some(Value::Int(ARRAY_INDEX_SCALE)))
+}
+
+fn unsafe_full_fence(_vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  memory::full_fence();
+  Ok(None)
+}
+
+fn unsafe_load_fence(_vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  memory::acquire_fence();
+  Ok(None)
+}
+
+fn unsafe_store_fence(_vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  memory::release_fence();
+  Ok(None)
+}
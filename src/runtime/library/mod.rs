@@ -0,0 +1,12 @@
+pub mod atomic;
+pub mod boxing;
+pub mod function;
+pub mod invoke;
+pub mod math;
+pub mod mirror;
+pub mod misc_unsafe;
+pub mod objects;
+pub mod string;
+pub mod string_builder;
+pub mod system;
+pub mod thread;
@@ -0,0 +1,131 @@
+use crate::{
+  runtime::{
+    builtin::string_result,
+    class::{ClassDefinition, NativeFn, ACC_FINAL, ACC_PUBLIC, ACC_STATIC, ACC_SUPER, ACC_VARARGS},
+    error::VmError,
+    heap::ArrayData,
+    value::Value,
+    vm::Vm,
+  },
+  util::descriptor::FieldType,
+};
+
+// java.util.Objectsの静的メソッド (メソッド参照や内部クラスのnewでjavacがrequireNonNullを呼ぶ)
+const STATIC_METHODS: &[(&str, &str, NativeFn)] = &[
+  ("requireNonNull", "(Ljava/lang/Object;)Ljava/lang/Object;", objects_require_non_null),
+  ("requireNonNull", "(Ljava/lang/Object;Ljava/lang/String;)Ljava/lang/Object;", objects_require_non_null),
+  ("requireNonNullElse", "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;", objects_require_non_null_else),
+  ("isNull", "(Ljava/lang/Object;)Z", objects_is_null),
+  ("nonNull", "(Ljava/lang/Object;)Z", objects_non_null),
+  ("equals", "(Ljava/lang/Object;Ljava/lang/Object;)Z", objects_equals),
+  ("hashCode", "(Ljava/lang/Object;)I", objects_hash_code),
+  ("toString", "(Ljava/lang/Object;)Ljava/lang/String;", objects_to_string),
+  ("toString", "(Ljava/lang/Object;Ljava/lang/String;)Ljava/lang/String;", objects_to_string_or_default),
+  ("checkIndex", "(II)I", objects_check_index),
+];
+
+pub fn define(vm: &mut Vm) -> Result<(), VmError> {
+  let mut definition = ClassDefinition::new("java/util/Objects")
+    .access_flags(ACC_PUBLIC | ACC_FINAL | ACC_SUPER)
+    .native("hash", "([Ljava/lang/Object;)I", ACC_PUBLIC | ACC_STATIC | ACC_VARARGS, objects_hash);
+  for (name, descriptor, function) in STATIC_METHODS {
+    definition = definition.native(name, descriptor, ACC_PUBLIC | ACC_STATIC, *function);
+  }
+  vm.define_class(definition)?;
+  Ok(())
+}
+
+// メッセージ付きの場合は2番目の引数をメッセージにする
+fn objects_require_non_null(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  if args[0] != Value::Null {
+    return Ok(Some(args[0]));
+  }
+  match args.get(1).map(|message| message.as_ref()).transpose()?.flatten() {
+    Some(message) => Err(VmError::null_pointer(vm.string_value(message)?)),
+    None => Err(VmError::java_without_message("java/lang/NullPointerException")),
+  }
+}
+
+fn objects_require_non_null_else(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  match (args[0], args[1]) {
+    (Value::Null, Value::Null) => Err(VmError::null_pointer("defaultObj")),
+    (Value::Null, default) => Ok(Some(default)),
+    (value, _) => Ok(Some(value)),
+  }
+}
+
+fn objects_is_null(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int((args[0] == Value::Null) as i32)))
+}
+
+fn objects_non_null(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int((args[0] != Value::Null) as i32)))
+}
+
+// a == b || a != null && a.equals(b)
+fn objects_equals(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let equal = match args[0].as_ref()? {
+    _ if args[0] == args[1] => true,
+    None => false,
+    Some(object) => {
+      let class = vm.object_class(object)?;
+      match vm.find_virtual(class, "equals", "(Ljava/lang/Object;)Z") {
+        Some(method) => vm.invoke(method, vec![args[0], args[1]])?.map_or(Ok(0), |v| v.as_int())? != 0,
+        None => false,
+      }
+    },
+  };
+  Ok(Some(Value::Int(equal as i32)))
+}
+
+// nullは0
+fn hash_code(vm: &mut Vm, value: Value) -> Result<i32, VmError> {
+  let Some(object) = value.as_ref()? else {
+    return Ok(0);
+  };
+  let class = vm.object_class(object)?;
+  match vm.find_virtual(class, "hashCode", "()I") {
+    Some(method) => vm.invoke(method, vec![value])?.map_or(Ok(0), |v| v.as_int()),
+    None => Ok(vm.identity_hash(object)),
+  }
+}
+
+fn objects_hash_code(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(hash_code(vm, args[0])?)))
+}
+
+// Arrays.hashCode(values)と同じく31倍して足していく (配列がnullなら0)
+fn objects_hash(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let Some(array) = args[0].as_ref()? else {
+    return Ok(Some(Value::Int(0)));
+  };
+  let elements = match vm.heap.get(array)?.array()? {
+    ArrayData::Ref(elements) => elements.clone(),
+    _ => return Err(VmError::internal("Expected reference array")),
+  };
+  let mut hash = 1i32;
+  for element in elements {
+    hash = hash.wrapping_mul(31).wrapping_add(hash_code(vm, element)?);
+  }
+  Ok(Some(Value::Int(hash)))
+}
+
+fn objects_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = vm.stringify(args[0], &FieldType::Object("java/lang/Object".to_string()))?;
+  string_result(vm, &chars)
+}
+
+fn objects_to_string_or_default(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  match args[0] {
+    Value::Null => Ok(Some(args[1])),
+    _ => objects_to_string(vm, args),
+  }
+}
+
+fn objects_check_index(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (index, length) = (args[0].as_int()?, args[1].as_int()?);
+  if index < 0 || index >= length {
+    return Err(VmError::java("java/lang/IndexOutOfBoundsException", format!("Index {} out of bounds for length {}", index, length)));
+  }
+  Ok(Some(Value::Int(index)))
+}
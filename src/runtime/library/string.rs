@@ -0,0 +1,575 @@
+use crate::{
+  runtime::{
+    builtin::{string_result, text_arg, this},
+    class::{ClassDefinition, ACC_ABSTRACT, ACC_FINAL, ACC_INTERFACE, ACC_PRIVATE, ACC_PUBLIC, ACC_STATIC, ACC_SUPER, ACC_VARARGS},
+    error::VmError,
+    heap::ArrayData,
+    library::boxing::is_java_whitespace,
+    value::{ObjRef, Value},
+    vm::Vm,
+  },
+  util::descriptor::FieldType,
+};
+
+pub fn define(vm: &mut Vm) -> Result<(), VmError> {
+  vm.define_class(
+    ClassDefinition::new("java/lang/Comparable")
+      .access_flags(ACC_PUBLIC | ACC_INTERFACE | ACC_ABSTRACT)
+      .abstract_method("compareTo", "(Ljava/lang/Object;)I", ACC_PUBLIC)
+  )?;
+  vm.define_class(
+    ClassDefinition::new("java/lang/CharSequence")
+      .access_flags(ACC_PUBLIC | ACC_INTERFACE | ACC_ABSTRACT)
+      .abstract_method("length", "()I", ACC_PUBLIC)
+      .abstract_method("charAt", "(I)C", ACC_PUBLIC)
+      .abstract_method("subSequence", "(II)Ljava/lang/CharSequence;", ACC_PUBLIC)
+  )?;
+  vm.define_class(
+    ClassDefinition::new("java/lang/String")
+      .access_flags(ACC_PUBLIC | ACC_FINAL | ACC_SUPER)
+      .interface("java/io/Serializable")
+      .interface("java/lang/Comparable")
+      .interface("java/lang/CharSequence")
+      .field("value", "[C", ACC_PRIVATE | ACC_FINAL)
+      .native("<init>", "()V", ACC_PUBLIC, string_init)
+      .native("<init>", "(Ljava/lang/String;)V", ACC_PUBLIC, string_init)
+      .native("<init>", "(Ljava/lang/StringBuilder;)V", ACC_PUBLIC, string_init)
+      .native("<init>", "([C)V", ACC_PUBLIC, string_init_chars)
+      .native("<init>", "([CII)V", ACC_PUBLIC, string_init_chars)
+      .native("<init>", "([B)V", ACC_PUBLIC, string_init_bytes)
+      .native("length", "()I", ACC_PUBLIC, string_length)
+      .native("isEmpty", "()Z", ACC_PUBLIC, string_is_empty)
+      .native("charAt", "(I)C", ACC_PUBLIC, string_char_at)
+      .native("equals", "(Ljava/lang/Object;)Z", ACC_PUBLIC, string_equals)
+      .native("equalsIgnoreCase", "(Ljava/lang/String;)Z", ACC_PUBLIC, string_equals_ignore_case)
+      .native("contentEquals", "(Ljava/lang/CharSequence;)Z", ACC_PUBLIC, string_content_equals)
+      .native("hashCode", "()I", ACC_PUBLIC, string_hash_code)
+      .native("compareTo", "(Ljava/lang/String;)I", ACC_PUBLIC, string_compare_to)
+      .native("compareTo", "(Ljava/lang/Object;)I", ACC_PUBLIC, string_compare_to)
+      .native("compareToIgnoreCase", "(Ljava/lang/String;)I", ACC_PUBLIC, string_compare_to_ignore_case)
+      .native("indexOf", "(I)I", ACC_PUBLIC, string_index_of_char)
+      .native("indexOf", "(II)I", ACC_PUBLIC, string_index_of_char)
+      .native("indexOf", "(Ljava/lang/String;)I", ACC_PUBLIC, string_index_of)
+      .native("indexOf", "(Ljava/lang/String;I)I", ACC_PUBLIC, string_index_of)
+      .native("lastIndexOf", "(I)I", ACC_PUBLIC, string_last_index_of_char)
+      .native("lastIndexOf", "(Ljava/lang/String;)I", ACC_PUBLIC, string_last_index_of)
+      .native("contains", "(Ljava/lang/CharSequence;)Z", ACC_PUBLIC, string_contains)
+      .native("startsWith", "(Ljava/lang/String;)Z", ACC_PUBLIC, string_starts_with)
+      .native("startsWith", "(Ljava/lang/String;I)Z", ACC_PUBLIC, string_starts_with)
+      .native("endsWith", "(Ljava/lang/String;)Z", ACC_PUBLIC, string_ends_with)
+      .native("substring", "(I)Ljava/lang/String;", ACC_PUBLIC, string_substring)
+      .native("substring", "(II)Ljava/lang/String;", ACC_PUBLIC, string_substring)
+      .native("subSequence", "(II)Ljava/lang/CharSequence;", ACC_PUBLIC, string_substring)
+      .native("concat", "(Ljava/lang/String;)Ljava/lang/String;", ACC_PUBLIC, string_concat)
+      .native("replace", "(CC)Ljava/lang/String;", ACC_PUBLIC, string_replace_char)
+      .native("replace", "(Ljava/lang/CharSequence;Ljava/lang/CharSequence;)Ljava/lang/String;", ACC_PUBLIC, string_replace)
+      .native("toUpperCase", "()Ljava/lang/String;", ACC_PUBLIC, string_to_upper_case)
+      .native("toLowerCase", "()Ljava/lang/String;", ACC_PUBLIC, string_to_lower_case)
+      .native("trim", "()Ljava/lang/String;", ACC_PUBLIC, string_trim)
+      .native("strip", "()Ljava/lang/String;", ACC_PUBLIC, string_strip)
+      .native("isBlank", "()Z", ACC_PUBLIC, string_is_blank)
+      .native("repeat", "(I)Ljava/lang/String;", ACC_PUBLIC, string_repeat)
+      .native("split", "(Ljava/lang/String;)[Ljava/lang/String;", ACC_PUBLIC, string_split)
+      .native("toCharArray", "()[C", ACC_PUBLIC, string_to_char_array)
+      .native("getBytes", "()[B", ACC_PUBLIC, string_get_bytes)
+      .native("intern", "()Ljava/lang/String;", ACC_PUBLIC, string_intern)
+      .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, string_to_string)
+      .native("valueOf", "(Ljava/lang/Object;)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, string_value_of_object)
+      .native("valueOf", "(Z)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, string_value_of_boolean)
+      .native("valueOf", "(C)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, string_value_of_char)
+      .native("valueOf", "(I)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, string_value_of)
+      .native("valueOf", "(J)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, string_value_of)
+      .native("valueOf", "(F)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, string_value_of)
+      .native("valueOf", "(D)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, string_value_of)
+      .native("valueOf", "([C)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, string_value_of_chars)
+      .native("valueOf", "([CII)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, string_value_of_chars)
+      .native("copyValueOf", "([C)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, string_value_of_chars)
+      .native("copyValueOf", "([CII)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, string_value_of_chars)
+      .native("join", "(Ljava/lang/CharSequence;[Ljava/lang/CharSequence;)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC | ACC_VARARGS, string_join)
+  )?;
+  Ok(())
+}
+
+fn chars_of(vm: &Vm, args: &[Value]) -> Result<Vec<u16>, VmError> {
+  vm.string_chars(this(args)?)
+}
+
+fn bool_result(value: bool) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(value as i32)))
+}
+
+fn int_result(value: usize) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(value as i32)))
+}
+
+fn char_array(vm: &Vm, array: ObjRef) -> Result<Vec<u16>, VmError> {
+  match vm.heap.get(array)?.array()? {
+    ArrayData::Char(chars) => Ok(chars.clone()),
+    _ => Err(VmError::internal("Expected char array")),
+  }
+}
+
+fn out_of_range(index: i32) -> VmError {
+  VmError::java("java/lang/StringIndexOutOfBoundsException", format!("String index out of range: {}", index))
+}
+
+// 範囲の検査 (String.checkBoundsBeginEnd)
+fn check_begin_end(begin: i32, end: i32, length: usize) -> Result<(usize, usize), VmError> {
+  if begin < 0 || begin > end || end > length as i32 {
+    return Err(VmError::java(
+      "java/lang/StringIndexOutOfBoundsException",
+      format!("begin {}, end {}, length {}", begin, end, length),
+    ));
+  }
+  Ok((begin as usize, end as usize))
+}
+
+fn find(haystack: &[u16], needle: &[u16], from: usize) -> Option<usize> {
+  if needle.is_empty() {
+    return (from <= haystack.len()).then_some(from);
+  }
+  (from..haystack.len().saturating_sub(needle.len() - 1)).find(|&i| haystack[i..].starts_with(needle))
+}
+
+fn rfind(haystack: &[u16], needle: &[u16]) -> Option<usize> {
+  (0..=haystack.len().checked_sub(needle.len())?).rev().find(|&i| haystack[i..].starts_with(needle))
+}
+
+fn index_result(index: Option<usize>) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(index.map_or(-1, |i| i as i32))))
+}
+
+// 補助文字 (サロゲートペア) を含むコードポイントをUTF-16にする
+pub fn code_point_chars(code_point: i32) -> Vec<u16> {
+  match char::from_u32(code_point as u32) {
+    Some(c) => c.encode_utf16(&mut [0; 2]).to_vec(),
+    None => vec![code_point as u16],
+  }
+}
+
+fn map_chars(chars: &[u16], f: fn(char) -> String) -> Vec<u16> {
+  char::decode_utf16(chars.iter().copied())
+    .flat_map(|c| match c {
+      Ok(c) => f(c).encode_utf16().collect::<Vec<_>>(),
+      Err(e) => vec![e.unpaired_surrogate()],
+    })
+    .collect()
+}
+
+fn to_upper(chars: &[u16]) -> Vec<u16> {
+  map_chars(chars, |c| c.to_uppercase().collect())
+}
+
+fn to_lower(chars: &[u16]) -> Vec<u16> {
+  map_chars(chars, |c| c.to_lowercase().collect())
+}
+
+// 1文字ずつ大文字、小文字の順に比べる (String.CASE_INSENSITIVE_ORDER)
+fn fold_case(c: u16) -> u16 {
+  let upper = to_upper(&[c]);
+  let upper = if upper.len() == 1 { upper[0] } else { c };
+  let lower = to_lower(&[upper]);
+  if lower.len() == 1 { lower[0] } else { upper }
+}
+
+fn compare(a: &[u16], b: &[u16], fold: bool) -> i32 {
+  for (&x, &y) in a.iter().zip(b) {
+    let (x, y) = if fold { (fold_case(x), fold_case(y)) } else { (x, y) };
+    if x != y {
+      return x as i32 - y as i32;
+    }
+  }
+  a.len() as i32 - b.len() as i32
+}
+
+// String(), String(String), String(StringBuilder)
+fn string_init(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let chars = match args.get(1) {
+    Some(&value) => text_arg(vm, value)?,
+    None => Vec::new(),
+  };
+  vm.init_string(this, &chars)?;
+  Ok(None)
+}
+
+// String(char[]), String(char[], int offset, int count)
+fn string_init_chars(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let chars = char_range(vm, &args[1..])?;
+  vm.init_string(this, &chars)?;
+  Ok(None)
+}
+
+// (char[] value) または (char[] value, int offset, int count) の範囲の文字
+fn char_range(vm: &Vm, args: &[Value]) -> Result<Vec<u16>, VmError> {
+  let array = args[0].as_ref()?.ok_or_else(|| VmError::java_without_message("java/lang/NullPointerException"))?;
+  let chars = char_array(vm, array)?;
+  match args.get(1..3) {
+    Some(&[offset, count]) => {
+      let (offset, count) = (offset.as_int()?, count.as_int()?);
+      if offset < 0 || count < 0 || offset as i64 + count as i64 > chars.len() as i64 {
+        return Err(VmError::java(
+          "java/lang/StringIndexOutOfBoundsException",
+          format!("offset {}, count {}, length {}", offset, count, chars.len()),
+        ));
+      }
+      Ok(chars[offset as usize..(offset + count) as usize].to_vec())
+    },
+    _ => Ok(chars),
+  }
+}
+
+// String(byte[]): UTF-8として読む
+fn string_init_bytes(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let array = args[1].as_ref()?.ok_or_else(|| VmError::java_without_message("java/lang/NullPointerException"))?;
+  let bytes: Vec<u8> = match vm.heap.get(array)?.array()? {
+    ArrayData::Byte(bytes) => bytes.iter().map(|&b| b as u8).collect(),
+    _ => return Err(VmError::internal("Expected byte array")),
+  };
+  let chars: Vec<u16> = String::from_utf8_lossy(&bytes).encode_utf16().collect();
+  vm.init_string(this, &chars)?;
+  Ok(None)
+}
+
+fn string_length(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  int_result(chars_of(vm, args)?.len())
+}
+
+fn string_is_empty(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  bool_result(chars_of(vm, args)?.is_empty())
+}
+
+fn string_char_at(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  let index = args[1].as_int()?;
+  match usize::try_from(index).ok().and_then(|i| chars.get(i)) {
+    Some(&c) => Ok(Some(Value::Int(c as i32))),
+    None => Err(out_of_range(index)),
+  }
+}
+
+fn string_equals(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let Value::Ref(other) = args[1] else { return bool_result(false) };
+  let string = vm.object_class(this(args)?)?;
+  if vm.object_class(other)? != string {
+    return bool_result(false);
+  }
+  bool_result(chars_of(vm, args)? == vm.string_chars(other)?)
+}
+
+fn string_equals_ignore_case(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  let other = match args[1] {
+    Value::Null => return bool_result(false),
+    value => text_arg(vm, value)?,
+  };
+  bool_result(chars.len() == other.len() && compare(&chars, &other, true) == 0)
+}
+
+fn string_content_equals(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  bool_result(chars == text_arg(vm, args[1])?)
+}
+
+// s[0]*31^(n-1) + s[1]*31^(n-2) + ... + s[n-1]
+fn string_hash_code(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let hash = chars_of(vm, args)?.iter().fold(0i32, |h, &c| h.wrapping_mul(31).wrapping_add(c as i32));
+  Ok(Some(Value::Int(hash)))
+}
+
+fn string_compare_to(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  let other = args[1].as_ref()?.ok_or_else(|| VmError::java_without_message("java/lang/NullPointerException"))?;
+  let string = vm.load_class("java/lang/String")?;
+  if vm.object_class(other)? != string {
+    return Err(VmError::java("java/lang/ClassCastException", format!(
+      "class {} cannot be cast to class java.lang.String", vm.classes[vm.object_class(other)?].java_name(),
+    )));
+  }
+  Ok(Some(Value::Int(compare(&chars, &vm.string_chars(other)?, false))))
+}
+
+fn string_compare_to_ignore_case(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  let other = text_arg(vm, args[1])?;
+  Ok(Some(Value::Int(compare(&chars, &other, true))))
+}
+
+// indexOf(int ch), indexOf(int ch, int fromIndex)
+fn string_index_of_char(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  let from = match args.get(2) {
+    Some(from) => from.as_int()?.max(0) as usize,
+    None => 0,
+  };
+  index_result(find(&chars, &code_point_chars(args[1].as_int()?), from))
+}
+
+// indexOf(String), indexOf(String, int fromIndex)
+fn string_index_of(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  let target = text_arg(vm, args[1])?;
+  let from = match args.get(2) {
+    Some(from) => from.as_int()?.max(0) as usize,
+    None => 0,
+  };
+  index_result(find(&chars, &target, from.min(chars.len())))
+}
+
+fn string_last_index_of_char(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  index_result(rfind(&chars, &code_point_chars(args[1].as_int()?)))
+}
+
+fn string_last_index_of(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  let target = text_arg(vm, args[1])?;
+  index_result(rfind(&chars, &target))
+}
+
+fn string_contains(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  let target = text_arg(vm, args[1])?;
+  bool_result(find(&chars, &target, 0).is_some())
+}
+
+// startsWith(String prefix), startsWith(String prefix, int offset)
+fn string_starts_with(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  let prefix = text_arg(vm, args[1])?;
+  let offset = match args.get(2) {
+    Some(offset) => offset.as_int()?,
+    None => 0,
+  };
+  if offset < 0 || offset as usize > chars.len() {
+    return bool_result(false);
+  }
+  bool_result(chars[offset as usize..].starts_with(&prefix))
+}
+
+fn string_ends_with(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  let suffix = text_arg(vm, args[1])?;
+  bool_result(chars.ends_with(&suffix))
+}
+
+// substring(int begin), substring(int begin, int end), subSequence(int begin, int end)
+fn string_substring(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  let end = match args.get(2) {
+    Some(end) => end.as_int()?,
+    None => chars.len() as i32,
+  };
+  let (begin, end) = check_begin_end(args[1].as_int()?, end, chars.len())?;
+  if begin == 0 && end == chars.len() {
+    return Ok(Some(args[0]));
+  }
+  string_result(vm, &chars[begin..end])
+}
+
+fn string_concat(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let mut chars = chars_of(vm, args)?;
+  let other = text_arg(vm, args[1])?;
+  if other.is_empty() {
+    return Ok(Some(args[0]));
+  }
+  chars.extend(other);
+  string_result(vm, &chars)
+}
+
+fn string_replace_char(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  let (from, to) = (args[1].as_int()? as u16, args[2].as_int()? as u16);
+  if !chars.contains(&from) {
+    return Ok(Some(args[0]));
+  }
+  let replaced: Vec<u16> = chars.iter().map(|&c| if c == from { to } else { c }).collect();
+  string_result(vm, &replaced)
+}
+
+// replace(CharSequence target, CharSequence replacement): 空文字列なら各文字の間に入れる
+fn string_replace(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  let target = text_arg(vm, args[1])?;
+  let replacement = text_arg(vm, args[2])?;
+  let mut result = Vec::new();
+  if target.is_empty() {
+    for &c in &chars {
+      result.extend(&replacement);
+      result.push(c);
+    }
+    result.extend(&replacement);
+    return string_result(vm, &result);
+  }
+  let mut start = 0;
+  while let Some(i) = find(&chars, &target, start) {
+    result.extend(&chars[start..i]);
+    result.extend(&replacement);
+    start = i + target.len();
+  }
+  if start == 0 {
+    return Ok(Some(args[0]));
+  }
+  result.extend(&chars[start..]);
+  string_result(vm, &result)
+}
+
+fn string_to_upper_case(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  string_result(vm, &to_upper(&chars))
+}
+
+fn string_to_lower_case(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  string_result(vm, &to_lower(&chars))
+}
+
+// 空白と制御文字 (' '以下) を両端から取り除く
+fn string_trim(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  let start = chars.iter().position(|&c| c > ' ' as u16).unwrap_or(chars.len());
+  let end = chars.iter().rposition(|&c| c > ' ' as u16).map_or(start, |i| i + 1);
+  if start == 0 && end == chars.len() {
+    return Ok(Some(args[0]));
+  }
+  string_result(vm, &chars[start..end])
+}
+
+// Character.isWhitespace()に当てはまる文字を両端から取り除く
+fn string_strip(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  let start = chars.iter().position(|&c| !is_java_whitespace(c)).unwrap_or(chars.len());
+  let end = chars.iter().rposition(|&c| !is_java_whitespace(c)).map_or(start, |i| i + 1);
+  string_result(vm, &chars[start..end])
+}
+
+fn string_is_blank(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  bool_result(chars_of(vm, args)?.iter().all(|&c| is_java_whitespace(c)))
+}
+
+fn string_repeat(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  let count = args[1].as_int()?;
+  if count < 0 {
+    return Err(VmError::java("java/lang/IllegalArgumentException", format!("count is negative: {}", count)));
+  }
+  if count == 1 {
+    return Ok(Some(args[0]));
+  }
+  if chars.len() as u64 * count as u64 > i32::MAX as u64 {
+    return Err(VmError::java("java/lang/OutOfMemoryError", "Required length exceeds implementation limit"));
+  }
+  string_result(vm, &chars.repeat(count as usize))
+}
+
+// 正規表現は扱わず、区切りを文字列としてそのまま探す (末尾の空文字列は除く)
+fn string_split(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  let separator = text_arg(vm, args[1])?;
+  let mut parts: Vec<Vec<u16>> = Vec::new();
+  if separator.is_empty() {
+    parts.extend(chars.chunks(1).map(|c| c.to_vec()));
+  } else {
+    let mut start = 0;
+    while let Some(i) = find(&chars, &separator, start) {
+      parts.push(chars[start..i].to_vec());
+      start = i + separator.len();
+    }
+    if !parts.is_empty() {
+      parts.push(chars[start..].to_vec());
+    }
+  }
+  if parts.is_empty() {
+    parts.push(chars);
+  } else {
+    while parts.last().is_some_and(|p| p.is_empty()) {
+      parts.pop();
+    }
+  }
+  let array = vm.new_array(&FieldType::Object("java/lang/String".to_string()), parts.len() as i32)?;
+  let mark = vm.handles.mark();
+  vm.handles.new_local(array);
+  let result = (|| {
+    for (i, part) in parts.iter().enumerate() {
+      let string = vm.new_string_utf16(part)?;
+      vm.heap.get_mut(array)?.array_mut()?.set(i, Value::Ref(string))?;
+    }
+    Ok(Some(Value::Ref(array)))
+  })();
+  vm.handles.release(mark);
+  result
+}
+
+fn string_to_char_array(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  let array = vm.new_array(&FieldType::Char, chars.len() as i32)?;
+  *vm.heap.get_mut(array)?.array_mut()? = ArrayData::Char(chars);
+  Ok(Some(Value::Ref(array)))
+}
+
+// UTF-8で符号化する
+fn string_get_bytes(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let bytes: Vec<i8> = vm.string_value(this(args)?)?.bytes().map(|b| b as i8).collect();
+  let array = vm.new_array(&FieldType::Byte, bytes.len() as i32)?;
+  *vm.heap.get_mut(array)?.array_mut()? = ArrayData::Byte(bytes);
+  Ok(Some(Value::Ref(array)))
+}
+
+pub(crate) fn string_intern(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = chars_of(vm, args)?;
+  if let Some(&string) = vm.strings.get(&chars) {
+    return Ok(Some(Value::Ref(string)));
+  }
+  vm.strings.insert(chars, this(args)?);
+  Ok(Some(args[0]))
+}
+
+fn string_to_string(_vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(args[0]))
+}
+
+fn string_value_of_object(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = vm.stringify(args[0], &FieldType::Object("java/lang/Object".to_string()))?;
+  string_result(vm, &chars)
+}
+
+fn string_value_of_boolean(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = vm.stringify(args[0], &FieldType::Boolean)?;
+  string_result(vm, &chars)
+}
+
+fn string_value_of_char(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = vm.stringify(args[0], &FieldType::Char)?;
+  string_result(vm, &chars)
+}
+
+// valueOf(int), valueOf(long), valueOf(float), valueOf(double)
+fn string_value_of(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = vm.stringify(args[0], &FieldType::Int)?;
+  string_result(vm, &chars)
+}
+
+// valueOf(char[])、valueOf(char[], int, int)、copyValueOf()
+fn string_value_of_chars(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let chars = char_range(vm, args)?;
+  string_result(vm, &chars)
+}
+
+fn string_join(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let delimiter = text_arg(vm, args[0])?;
+  let array = args[1].as_ref()?.ok_or_else(|| VmError::java_without_message("java/lang/NullPointerException"))?;
+  let elements = match vm.heap.get(array)?.array()? {
+    ArrayData::Ref(elements) => elements.clone(),
+    _ => return Err(VmError::internal("Expected reference array")),
+  };
+  let mut chars = Vec::new();
+  for (i, element) in elements.into_iter().enumerate() {
+    if i > 0 {
+      chars.extend(&delimiter);
+    }
+    chars.extend(vm.stringify(element, &FieldType::Object("java/lang/Object".to_string()))?);
+  }
+  string_result(vm, &chars)
+}
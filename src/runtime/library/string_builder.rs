@@ -0,0 +1,352 @@
+use crate::{
+  runtime::{
+    builtin::{string_result, text_arg, this},
+    class::{ClassDefinition, ACC_FINAL, ACC_PRIVATE, ACC_PUBLIC, ACC_SUPER},
+    error::VmError,
+    heap::ArrayData,
+    value::{ObjRef, Value},
+    vm::Vm,
+  },
+  util::descriptor::FieldType,
+};
+
+// 初期容量 (new StringBuilder()と同じ)
+const DEFAULT_CAPACITY: i32 = 16;
+
+// 値をString.valueOf()と同じ形で追加するappend (booleanとcharは別に定義する)
+const APPENDS: &[&str] = &[
+  "Ljava/lang/String;", "Ljava/lang/Object;", "Ljava/lang/CharSequence;", "I", "J", "F", "D",
+];
+
+pub fn define(vm: &mut Vm) -> Result<(), VmError> {
+  let mut definition = ClassDefinition::new("java/lang/StringBuilder")
+    .access_flags(ACC_PUBLIC | ACC_FINAL | ACC_SUPER)
+    .interface("java/io/Serializable")
+    .interface("java/lang/CharSequence")
+    .field("value", "[C", ACC_PRIVATE)
+    .field("count", "I", ACC_PRIVATE)
+    .native("<init>", "()V", ACC_PUBLIC, builder_init)
+    .native("<init>", "(I)V", ACC_PUBLIC, builder_init_capacity)
+    .native("<init>", "(Ljava/lang/String;)V", ACC_PUBLIC, builder_init_text)
+    .native("<init>", "(Ljava/lang/CharSequence;)V", ACC_PUBLIC, builder_init_text);
+  for descriptor in APPENDS {
+    definition = definition.native("append", &format!("({})Ljava/lang/StringBuilder;", descriptor), ACC_PUBLIC, builder_append);
+  }
+  vm.define_class(
+    definition
+      .native("append", "(Z)Ljava/lang/StringBuilder;", ACC_PUBLIC, builder_append_boolean)
+      .native("append", "(C)Ljava/lang/StringBuilder;", ACC_PUBLIC, builder_append_char)
+      .native("append", "([C)Ljava/lang/StringBuilder;", ACC_PUBLIC, builder_append_chars)
+      .native("appendCodePoint", "(I)Ljava/lang/StringBuilder;", ACC_PUBLIC, builder_append_code_point)
+      .native("insert", "(ILjava/lang/String;)Ljava/lang/StringBuilder;", ACC_PUBLIC, builder_insert)
+      .native("insert", "(ILjava/lang/Object;)Ljava/lang/StringBuilder;", ACC_PUBLIC, builder_insert)
+      .native("insert", "(II)Ljava/lang/StringBuilder;", ACC_PUBLIC, builder_insert)
+      .native("insert", "(IJ)Ljava/lang/StringBuilder;", ACC_PUBLIC, builder_insert)
+      .native("insert", "(IC)Ljava/lang/StringBuilder;", ACC_PUBLIC, builder_insert_char)
+      .native("insert", "(IZ)Ljava/lang/StringBuilder;", ACC_PUBLIC, builder_insert_boolean)
+      .native("delete", "(II)Ljava/lang/StringBuilder;", ACC_PUBLIC, builder_delete)
+      .native("deleteCharAt", "(I)Ljava/lang/StringBuilder;", ACC_PUBLIC, builder_delete_char_at)
+      .native("replace", "(IILjava/lang/String;)Ljava/lang/StringBuilder;", ACC_PUBLIC, builder_replace)
+      .native("reverse", "()Ljava/lang/StringBuilder;", ACC_PUBLIC, builder_reverse)
+      .native("length", "()I", ACC_PUBLIC, builder_length)
+      .native("capacity", "()I", ACC_PUBLIC, builder_capacity)
+      .native("charAt", "(I)C", ACC_PUBLIC, builder_char_at)
+      .native("setCharAt", "(IC)V", ACC_PUBLIC, builder_set_char_at)
+      .native("setLength", "(I)V", ACC_PUBLIC, builder_set_length)
+      .native("indexOf", "(Ljava/lang/String;)I", ACC_PUBLIC, builder_index_of)
+      .native("isEmpty", "()Z", ACC_PUBLIC, builder_is_empty)
+      .native("substring", "(I)Ljava/lang/String;", ACC_PUBLIC, builder_substring)
+      .native("substring", "(II)Ljava/lang/String;", ACC_PUBLIC, builder_substring)
+      .native("subSequence", "(II)Ljava/lang/CharSequence;", ACC_PUBLIC, builder_substring)
+      .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, builder_to_string)
+  )?;
+  Ok(())
+}
+
+fn index_error(name: &str, index: i32, length: usize) -> VmError {
+  VmError::java("java/lang/StringIndexOutOfBoundsException", format!("{} {}, length {}", name, index, length))
+}
+
+fn buffer(vm: &Vm, builder: ObjRef) -> Result<ObjRef, VmError> {
+  vm.get_field(builder, "value", "[C")?.as_ref()?
+    .ok_or_else(|| VmError::internal("StringBuilder without value"))
+}
+
+// 使用中の部分 (value[0..count]) の内容
+fn contents(vm: &Vm, builder: ObjRef) -> Result<Vec<u16>, VmError> {
+  let count = vm.get_field(builder, "count", "I")?.as_int()? as usize;
+  match vm.heap.get(buffer(vm, builder)?)?.array()? {
+    ArrayData::Char(chars) => Ok(chars[..count].to_vec()),
+    _ => Err(VmError::internal("StringBuilder value is not a char array")),
+  }
+}
+
+fn new_buffer(vm: &mut Vm, builder: ObjRef, capacity: i32) -> Result<(), VmError> {
+  let mark = vm.handles.mark();
+  vm.handles.new_local(builder);
+  let array = vm.new_array(&FieldType::Char, capacity);
+  vm.handles.release(mark);
+  vm.set_field(builder, "value", "[C", Value::Ref(array?))
+}
+
+// 容量が足りなければ (現在の容量 * 2 + 2) か必要な長さの大きい方へ広げる
+fn ensure_capacity(vm: &mut Vm, builder: ObjRef, minimum: usize) -> Result<(), VmError> {
+  let old = buffer(vm, builder)?;
+  let capacity = vm.heap.get(old)?.array()?.len();
+  if minimum <= capacity {
+    return Ok(());
+  }
+  if minimum > i32::MAX as usize {
+    return Err(VmError::java_without_message("java/lang/OutOfMemoryError"));
+  }
+  let new_capacity = (capacity * 2 + 2).max(minimum).min(i32::MAX as usize);
+  let ArrayData::Char(chars) = vm.heap.get(old)?.array()?.clone() else {
+    return Err(VmError::internal("StringBuilder value is not a char array"));
+  };
+  new_buffer(vm, builder, new_capacity as i32)?;
+  let new = buffer(vm, builder)?;
+  if let ArrayData::Char(buffer) = vm.heap.get_mut(new)?.array_mut()? {
+    buffer[..chars.len()].copy_from_slice(&chars);
+  }
+  Ok(())
+}
+
+fn set_contents(vm: &mut Vm, builder: ObjRef, contents: &[u16]) -> Result<(), VmError> {
+  ensure_capacity(vm, builder, contents.len())?;
+  let array = buffer(vm, builder)?;
+  if let ArrayData::Char(buffer) = vm.heap.get_mut(array)?.array_mut()? {
+    buffer[..contents.len()].copy_from_slice(contents);
+  }
+  vm.set_field(builder, "count", "I", Value::Int(contents.len() as i32))
+}
+
+fn append_chars(vm: &mut Vm, builder: ObjRef, chars: &[u16]) -> Result<Option<Value>, VmError> {
+  let count = vm.get_field(builder, "count", "I")?.as_int()? as usize;
+  ensure_capacity(vm, builder, count + chars.len())?;
+  let array = buffer(vm, builder)?;
+  if let ArrayData::Char(buffer) = vm.heap.get_mut(array)?.array_mut()? {
+    buffer[count..count + chars.len()].copy_from_slice(chars);
+  }
+  vm.set_field(builder, "count", "I", Value::Int((count + chars.len()) as i32))?;
+  Ok(Some(Value::Ref(builder)))
+}
+
+fn builder_init(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  new_buffer(vm, this(args)?, DEFAULT_CAPACITY)?;
+  Ok(None)
+}
+
+fn builder_init_capacity(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  new_buffer(vm, this(args)?, args[1].as_int()?)?;
+  Ok(None)
+}
+
+// StringBuilder(String), StringBuilder(CharSequence): 容量は長さ + 16
+fn builder_init_text(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let chars = text_arg(vm, args[1])?;
+  new_buffer(vm, this, chars.len() as i32 + DEFAULT_CAPACITY)?;
+  append_chars(vm, this, &chars)?;
+  Ok(None)
+}
+
+// append(String/Object/CharSequence/StringBuffer/int/long/float/double)
+fn builder_append(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  append_value(vm, args, FieldType::Object("java/lang/Object".to_string()))
+}
+
+fn builder_append_boolean(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  append_value(vm, args, FieldType::Boolean)
+}
+
+fn builder_append_char(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  append_value(vm, args, FieldType::Char)
+}
+
+fn append_value(vm: &mut Vm, args: &[Value], field_type: FieldType) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let chars = vm.stringify(args[1], &field_type)?;
+  append_chars(vm, this, &chars)
+}
+
+fn builder_append_chars(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let array = args[1].as_ref()?.ok_or_else(|| VmError::java_without_message("java/lang/NullPointerException"))?;
+  let ArrayData::Char(chars) = vm.heap.get(array)?.array()?.clone() else {
+    return Err(VmError::internal("Expected char array"));
+  };
+  append_chars(vm, this, &chars)
+}
+
+fn builder_append_code_point(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let code_point = args[1].as_int()?;
+  let c = char::from_u32(code_point as u32).ok_or_else(|| VmError::java(
+    "java/lang/IllegalArgumentException",
+    format!("Not a valid Unicode code point: 0x{:X}", code_point),
+  ))?;
+  append_chars(vm, this, c.encode_utf16(&mut [0; 2]))
+}
+
+// insert(int offset, String/Object/int/long)
+fn builder_insert(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  insert_value(vm, args, FieldType::Object("java/lang/Object".to_string()))
+}
+
+fn builder_insert_boolean(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  insert_value(vm, args, FieldType::Boolean)
+}
+
+fn builder_insert_char(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  insert_value(vm, args, FieldType::Char)
+}
+
+fn insert_value(vm: &mut Vm, args: &[Value], field_type: FieldType) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let mut contents = contents(vm, this)?;
+  let offset = args[1].as_int()?;
+  if offset < 0 || offset as usize > contents.len() {
+    return Err(index_error("offset", offset, contents.len()));
+  }
+  let chars = vm.stringify(args[2], &field_type)?;
+  contents.splice(offset as usize..offset as usize, chars);
+  set_contents(vm, this, &contents)?;
+  Ok(Some(Value::Ref(this)))
+}
+
+fn builder_delete(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let mut contents = contents(vm, this)?;
+  let (start, end) = (args[1].as_int()?, args[2].as_int()?);
+  let end = end.min(contents.len() as i32);
+  if start < 0 || start > end {
+    return Err(VmError::java(
+      "java/lang/StringIndexOutOfBoundsException",
+      format!("start {}, end {}, length {}", start, end, contents.len()),
+    ));
+  }
+  contents.drain(start as usize..end as usize);
+  set_contents(vm, this, &contents)?;
+  Ok(Some(Value::Ref(this)))
+}
+
+fn builder_delete_char_at(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let mut contents = contents(vm, this)?;
+  let index = args[1].as_int()?;
+  if index < 0 || index as usize >= contents.len() {
+    return Err(index_error("index", index, contents.len()));
+  }
+  contents.remove(index as usize);
+  set_contents(vm, this, &contents)?;
+  Ok(Some(Value::Ref(this)))
+}
+
+fn builder_replace(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let mut contents = contents(vm, this)?;
+  let (start, end) = (args[1].as_int()?, args[2].as_int()?);
+  let replacement = text_arg(vm, args[3])?;
+  let end = end.min(contents.len() as i32);
+  if start < 0 || start > contents.len() as i32 || start > end {
+    return Err(VmError::java(
+      "java/lang/StringIndexOutOfBoundsException",
+      format!("start {}, end {}, length {}", start, end, contents.len()),
+    ));
+  }
+  contents.splice(start as usize..end as usize, replacement);
+  set_contents(vm, this, &contents)?;
+  Ok(Some(Value::Ref(this)))
+}
+
+// サロゲートペアは順序を保ったまま反転する
+fn builder_reverse(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let contents = contents(vm, this)?;
+  let mut reversed: Vec<u16> = Vec::with_capacity(contents.len());
+  for c in char::decode_utf16(contents.iter().copied()).collect::<Vec<_>>().into_iter().rev() {
+    match c {
+      Ok(c) => reversed.extend(c.encode_utf16(&mut [0; 2]).iter()),
+      Err(e) => reversed.push(e.unpaired_surrogate()),
+    }
+  }
+  set_contents(vm, this, &reversed)?;
+  Ok(Some(Value::Ref(this)))
+}
+
+fn builder_length(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(vm.get_field(this(args)?, "count", "I")?))
+}
+
+fn builder_capacity(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let array = buffer(vm, this(args)?)?;
+  Ok(Some(Value::Int(vm.heap.get(array)?.array()?.len() as i32)))
+}
+
+fn builder_char_at(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let contents = contents(vm, this(args)?)?;
+  let index = args[1].as_int()?;
+  match usize::try_from(index).ok().and_then(|i| contents.get(i)) {
+    Some(&c) => Ok(Some(Value::Int(c as i32))),
+    None => Err(index_error("index", index, contents.len())),
+  }
+}
+
+fn builder_set_char_at(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let mut contents = contents(vm, this)?;
+  let index = args[1].as_int()?;
+  if index < 0 || index as usize >= contents.len() {
+    return Err(index_error("index", index, contents.len()));
+  }
+  contents[index as usize] = args[2].as_int()? as u16;
+  set_contents(vm, this, &contents)?;
+  Ok(None)
+}
+
+fn builder_set_length(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let mut contents = contents(vm, this)?;
+  let length = args[1].as_int()?;
+  if length < 0 {
+    return Err(VmError::java("java/lang/StringIndexOutOfBoundsException", format!("String index out of range: {}", length)));
+  }
+  contents.resize(length as usize, 0);
+  set_contents(vm, this, &contents)?;
+  Ok(None)
+}
+
+fn builder_index_of(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let contents = contents(vm, this(args)?)?;
+  let target = text_arg(vm, args[1])?;
+  let index = (0..=contents.len().saturating_sub(target.len()))
+    .find(|&i| contents[i..].starts_with(&target))
+    .filter(|_| target.len() <= contents.len());
+  Ok(Some(Value::Int(index.map_or(-1, |i| i as i32))))
+}
+
+fn builder_is_empty(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let count = vm.get_field(this(args)?, "count", "I")?.as_int()?;
+  Ok(Some(Value::Int((count == 0) as i32)))
+}
+
+fn builder_substring(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let contents = contents(vm, this(args)?)?;
+  let start = args[1].as_int()?;
+  let end = match args.get(2) {
+    Some(end) => end.as_int()?,
+    None => contents.len() as i32,
+  };
+  if start < 0 || start > end || end > contents.len() as i32 {
+    return Err(VmError::java(
+      "java/lang/StringIndexOutOfBoundsException",
+      format!("start {}, end {}, length {}", start, end, contents.len()),
+    ));
+  }
+  string_result(vm, &contents[start as usize..end as usize])
+}
+
+fn builder_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let contents = contents(vm, this(args)?)?;
+  string_result(vm, &contents)
+}
@@ -0,0 +1,405 @@
+use std::{
+  env,
+  io::{self, Write},
+  path::Path,
+  process,
+  sync::OnceLock,
+  time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+  runtime::{
+    builtin::{text_arg, this},
+    class::{ClassDefinition, NativeFn, ACC_FINAL, ACC_PRIVATE, ACC_PUBLIC, ACC_STATIC, ACC_SUPER},
+    error::VmError,
+    heap::ArrayData,
+    value::{ObjRef, Value},
+    vm::Vm,
+  },
+  util::descriptor::FieldType,
+};
+
+// 標準出力と標準エラー出力のファイル記述子
+const STDOUT: i32 = 1;
+const STDERR: i32 = 2;
+
+// print/printlnの引数 (String.valueOf()と同じ形で出力する)
+const PRINTS: &[(&str, NativeFn, NativeFn)] = &[
+  ("Z", print_boolean, println_boolean),
+  ("C", print_char, println_char),
+  ("I", print_value, println_value),
+  ("J", print_value, println_value),
+  ("F", print_value, println_value),
+  ("D", print_value, println_value),
+  ("[C", print_chars, println_chars),
+  ("Ljava/lang/String;", print_value, println_value),
+  ("Ljava/lang/Object;", print_value, println_value),
+];
+
+const DEFAULT_LIBRARY_PATH: &str = "/usr/java/packages/lib:/usr/lib64:/lib64:/lib:/usr/lib";
+
+// nanoTime()の起点
+static START: OnceLock<Instant> = OnceLock::new();
+
+pub fn define(vm: &mut Vm) -> Result<(), VmError> {
+  let mut print_stream = ClassDefinition::new("java/io/PrintStream")
+    .field("fd", "I", ACC_PRIVATE | ACC_FINAL)
+    .native("println", "()V", ACC_PUBLIC, println)
+    .native("write", "(I)V", ACC_PUBLIC, print_stream_write)
+    .native("flush", "()V", ACC_PUBLIC, print_stream_flush);
+  for (descriptor, print, println) in PRINTS {
+    print_stream = print_stream
+      .native("print", &format!("({})V", descriptor), ACC_PUBLIC, *print)
+      .native("println", &format!("({})V", descriptor), ACC_PUBLIC, *println);
+  }
+  vm.define_class(print_stream)?;
+  vm.define_class(
+    ClassDefinition::new("java/lang/System")
+      .access_flags(ACC_PUBLIC | ACC_FINAL | ACC_SUPER)
+      .field("out", "Ljava/io/PrintStream;", ACC_PUBLIC | ACC_STATIC | ACC_FINAL)
+      .field("err", "Ljava/io/PrintStream;", ACC_PUBLIC | ACC_STATIC | ACC_FINAL)
+      .native("<clinit>", "()V", ACC_STATIC, system_clinit)
+      .native("arraycopy", "(Ljava/lang/Object;ILjava/lang/Object;II)V", ACC_PUBLIC | ACC_STATIC, system_arraycopy)
+      .native("currentTimeMillis", "()J", ACC_PUBLIC | ACC_STATIC, system_current_time_millis)
+      .native("nanoTime", "()J", ACC_PUBLIC | ACC_STATIC, system_nano_time)
+      .native("identityHashCode", "(Ljava/lang/Object;)I", ACC_PUBLIC | ACC_STATIC, system_identity_hash_code)
+      .native("exit", "(I)V", ACC_PUBLIC | ACC_STATIC, system_exit)
+      .native("gc", "()V", ACC_PUBLIC | ACC_STATIC, system_gc)
+      .native("lineSeparator", "()Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, system_line_separator)
+      .native("getProperty", "(Ljava/lang/String;)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, system_get_property)
+      .native("getProperty", "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, system_get_property)
+      .native("getenv", "(Ljava/lang/String;)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, system_getenv)
+      .native("load", "(Ljava/lang/String;)V", ACC_PUBLIC | ACC_STATIC, system_load)
+      .native("loadLibrary", "(Ljava/lang/String;)V", ACC_PUBLIC | ACC_STATIC, system_load_library)
+      .native("mapLibraryName", "(Ljava/lang/String;)Ljava/lang/String;", ACC_PUBLIC | ACC_STATIC, system_map_library_name)
+  )?;
+  Ok(())
+}
+
+// System.outとSystem.errを作る
+fn system_clinit(vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  let system = vm.load_class("java/lang/System")?;
+  let print_stream = vm.load_class("java/io/PrintStream")?;
+  for (name, fd) in [("out", STDOUT), ("err", STDERR)] {
+    let stream = vm.instantiate(print_stream)?;
+    vm.set_field(stream, "fd", "I", Value::Int(fd))?;
+    vm.set_static_field(system, name, "Ljava/io/PrintStream;", Value::Ref(stream))?;
+  }
+  Ok(None)
+}
+
+fn write_chars(vm: &Vm, stream: ObjRef, chars: &[u16], newline: bool) -> Result<(), VmError> {
+  let mut text = String::from_utf16_lossy(chars);
+  if newline {
+    text.push('\n');
+  }
+  let result = match vm.get_field(stream, "fd", "I")?.as_int()? {
+    STDERR => {
+      // 標準出力と順序が入れ替わらないようにする
+      let _ = io::stdout().flush();
+      io::stderr().write_all(text.as_bytes())
+    },
+    _ => io::stdout().write_all(text.as_bytes()),
+  };
+  result.map_err(|e| VmError::internal(format!("Failed to write output: {}", e)))
+}
+
+fn print(vm: &mut Vm, args: &[Value], field_type: FieldType, newline: bool) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let chars = vm.stringify(args[1], &field_type)?;
+  write_chars(vm, this, &chars, newline)?;
+  Ok(None)
+}
+
+fn print_chars_array(vm: &mut Vm, args: &[Value], newline: bool) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let array = args[1].as_ref()?.ok_or_else(|| VmError::java_without_message("java/lang/NullPointerException"))?;
+  let ArrayData::Char(chars) = vm.heap.get(array)?.array()?.clone() else {
+    return Err(VmError::internal("Expected char array"));
+  };
+  write_chars(vm, this, &chars, newline)?;
+  Ok(None)
+}
+
+fn object_type() -> FieldType {
+  FieldType::Object("java/lang/Object".to_string())
+}
+
+// print(int/long/float/double/String/Object)
+fn print_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  print(vm, args, object_type(), false)
+}
+
+fn println_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  print(vm, args, object_type(), true)
+}
+
+fn print_boolean(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  print(vm, args, FieldType::Boolean, false)
+}
+
+fn println_boolean(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  print(vm, args, FieldType::Boolean, true)
+}
+
+fn print_char(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  print(vm, args, FieldType::Char, false)
+}
+
+fn println_char(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  print(vm, args, FieldType::Char, true)
+}
+
+fn print_chars(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  print_chars_array(vm, args, false)
+}
+
+fn println_chars(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  print_chars_array(vm, args, true)
+}
+
+fn println(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  write_chars(vm, this(args)?, &[], true)?;
+  Ok(None)
+}
+
+// write(int): 下位8ビットを1バイトとして出力する
+fn print_stream_write(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let byte = [args[1].as_int()? as u8];
+  let result = match vm.get_field(this(args)?, "fd", "I")?.as_int()? {
+    STDERR => io::stderr().write_all(&byte),
+    _ => io::stdout().write_all(&byte),
+  };
+  result.map_err(|e| VmError::internal(format!("Failed to write output: {}", e)))?;
+  Ok(None)
+}
+
+fn print_stream_flush(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let result = match vm.get_field(this(args)?, "fd", "I")?.as_int()? {
+    STDERR => io::stderr().flush(),
+    _ => io::stdout().flush(),
+  };
+  result.map_err(|e| VmError::internal(format!("Failed to flush output: {}", e)))?;
+  Ok(None)
+}
+
+// 配列の型名 (HotSpotのメッセージと同じく、参照型の配列はobject array)
+fn array_type_name(component: &FieldType) -> String {
+  if component.is_reference() { "object array".to_string() } else { component.to_string() }
+}
+
+fn array_store_error(message: String) -> VmError {
+  VmError::java("java/lang/ArrayStoreException", format!("arraycopy: {}", message))
+}
+
+fn bounds_error(message: String) -> VmError {
+  VmError::java("java/lang/ArrayIndexOutOfBoundsException", format!("arraycopy: {}", message))
+}
+
+// System.arraycopy(): 範囲が重なっていても、一度コピーしてから書き込むので正しく動く
+pub(crate) fn system_arraycopy(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let null_pointer = || VmError::java_without_message("java/lang/NullPointerException");
+  let source = args[0].as_ref()?.ok_or_else(null_pointer)?;
+  let destination = args[2].as_ref()?.ok_or_else(null_pointer)?;
+  let (source_pos, destination_pos, length) = (args[1].as_int()?, args[3].as_int()?, args[4].as_int()?);
+  let source_class = vm.object_class(source)?;
+  let destination_class = vm.object_class(destination)?;
+  let Some(source_component) = vm.classes[source_class].component.clone() else {
+    return Err(array_store_error(format!("source type {} is not an array", vm.classes[source_class].java_name())));
+  };
+  let Some(destination_component) = vm.classes[destination_class].component.clone() else {
+    return Err(array_store_error(format!("destination type {} is not an array", vm.classes[destination_class].java_name())));
+  };
+  if (source_component.is_reference() || destination_component.is_reference() || source_component != destination_component)
+    && !(source_component.is_reference() && destination_component.is_reference())
+  {
+    return Err(array_store_error(format!(
+      "type mismatch: can not copy {}[] into {}[]", array_type_name(&source_component), array_type_name(&destination_component),
+    )));
+  }
+
+  let source_length = vm.heap.get(source)?.array()?.len();
+  let destination_length = vm.heap.get(destination)?.array()?.len();
+  let source_name = format!("{}[{}]", array_type_name(&source_component), source_length);
+  let destination_name = format!("{}[{}]", array_type_name(&destination_component), destination_length);
+  if source_pos < 0 {
+    return Err(bounds_error(format!("source index {} out of bounds for {}", source_pos, source_name)));
+  }
+  if destination_pos < 0 {
+    return Err(bounds_error(format!("destination index {} out of bounds for {}", destination_pos, destination_name)));
+  }
+  if length < 0 {
+    return Err(bounds_error(format!("length {} is negative", length)));
+  }
+  if source_pos as i64 + length as i64 > source_length as i64 {
+    return Err(bounds_error(format!("last source index {} out of bounds for {}", source_pos as i64 + length as i64, source_name)));
+  }
+  if destination_pos as i64 + length as i64 > destination_length as i64 {
+    return Err(bounds_error(format!(
+      "last destination index {} out of bounds for {}", destination_pos as i64 + length as i64, destination_name,
+    )));
+  }
+
+  let (source_pos, destination_pos, length) = (source_pos as usize, destination_pos as usize, length as usize);
+  let mut elements = vm.heap.get(source)?.array()?.slice(source_pos, length);
+  // 要素型が代入できない参照型の配列は、各要素を検査して失敗した所まで書き込む
+  let mut error = None;
+  if let (ArrayData::Ref(values), Some(target)) = (&mut elements, vm.classes[destination_class].component_class)
+    && !vm.classes[source_class].component_class.is_some_and(|s| vm.is_assignable(s, target))
+  {
+    for (i, value) in values.iter().enumerate() {
+      if let Value::Ref(element) = value
+        && !vm.is_assignable(vm.object_class(*element)?, target)
+      {
+        error = Some(array_store_error(format!(
+          "element type mismatch: can not cast one of the elements of {}[] to the type of the destination array, {}",
+          source_component, destination_component,
+        )));
+        values.truncate(i);
+        break;
+      }
+    }
+  }
+  if !vm.heap.get_mut(destination)?.array_mut()?.copy_from(destination_pos, &elements) {
+    return Err(VmError::internal("arraycopy between different element types"));
+  }
+  match error {
+    Some(error) => Err(error),
+    None => Ok(None),
+  }
+}
+
+pub(crate) fn system_current_time_millis(_vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  let millis = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as i64);
+  Ok(Some(Value::Long(millis)))
+}
+
+pub(crate) fn system_nano_time(_vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  let start = START.get_or_init(Instant::now);
+  Ok(Some(Value::Long(start.elapsed().as_nanos() as i64)))
+}
+
+pub(crate) fn system_identity_hash_code(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let hash = match args[0].as_ref()? {
+    Some(object) => vm.identity_hash(object),
+    None => 0,
+  };
+  Ok(Some(Value::Int(hash)))
+}
+
+fn system_exit(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  vm.finish_trace();
+  vm.finish_jdwp();
+  vm.finish_profile();
+  let _ = io::stdout().flush();
+  if vm.options.verbose_gc {
+    vm.print_gc_stats();
+  }
+  process::exit(args[0].as_int()?)
+}
+
+fn system_gc(vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  vm.collect_garbage();
+  Ok(None)
+}
+
+fn system_line_separator(vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Ref(vm.new_string("\n")?)))
+}
+
+pub(crate) fn property(vm: &Vm, key: &str) -> Option<String> {
+  if let Some(value) = vm.options.properties.get(key) {
+    return Some(value.clone());
+  }
+  Some(match key {
+    "java.version" | "java.specification.version" => "17".to_string(),
+    "java.vendor" | "java.vm.name" => "rust-jvm".to_string(),
+    "java.class.path" => vm.options.class_path.iter()
+      .map(|path| path.display().to_string())
+      .collect::<Vec<_>>()
+      .join(":"),
+    "os.name" => match env::consts::OS {
+      "linux" => "Linux".to_string(),
+      "macos" => "Mac OS X".to_string(),
+      "windows" => "Windows".to_string(),
+      os => os.to_string(),
+    },
+    "os.arch" => match env::consts::ARCH {
+      "x86_64" => "amd64".to_string(),
+      arch => arch.to_string(),
+    },
+    // HotSpotと同じく、LD_LIBRARY_PATHの後にシステムのディレクトリを探す
+    "java.library.path" => env::var("LD_LIBRARY_PATH").ok()
+      .filter(|path| !path.is_empty())
+      .into_iter()
+      .chain([DEFAULT_LIBRARY_PATH.to_string()])
+      .collect::<Vec<_>>()
+      .join(":"),
+    "file.separator" => "/".to_string(),
+    "path.separator" => ":".to_string(),
+    "line.separator" => "\n".to_string(),
+    "user.dir" => env::current_dir().ok()?.display().to_string(),
+    "user.home" => env::var("HOME").ok()?,
+    "user.name" => env::var("USER").ok()?,
+    _ => return None,
+  })
+}
+
+// getProperty(String key), getProperty(String key, String default)
+fn system_get_property(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let key = String::from_utf16_lossy(&text_arg(vm, args[0])?);
+  match property(vm, &key) {
+    Some(value) => Ok(Some(Value::Ref(vm.new_string(&value)?))),
+    None => Ok(Some(args.get(1).copied().unwrap_or(Value::Null))),
+  }
+}
+
+fn system_getenv(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let name = String::from_utf16_lossy(&text_arg(vm, args[0])?);
+  match env::var(name) {
+    Ok(value) => Ok(Some(Value::Ref(vm.new_string(&value)?))),
+    Err(_) => Ok(Some(Value::Null)),
+  }
+}
+
+fn link_error(message: String) -> VmError {
+  VmError::java("java/lang/UnsatisfiedLinkError", message)
+}
+
+fn library_file_name(name: &str) -> String {
+  format!("lib{}.so", name)
+}
+
+// load(String filename): 絶対パスで指定された共有ライブラリを読み込む
+fn system_load(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let file_name = String::from_utf16_lossy(&text_arg(vm, args[0])?);
+  let path = Path::new(&file_name);
+  if !path.is_absolute() {
+    return Err(link_error(format!("Expecting an absolute path of the library: {}", file_name)));
+  }
+  vm.load_library(path)?;
+  Ok(None)
+}
+
+// loadLibrary(String libname): java.library.pathのディレクトリからlib<名前>.soを探して読み込む
+fn system_load_library(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let name = String::from_utf16_lossy(&text_arg(vm, args[0])?);
+  if name.contains('/') {
+    return Err(link_error(format!("Directory separator should not appear in library name: {}", name)));
+  }
+  let search_path = property(vm, "java.library.path").unwrap_or_default();
+  let found = search_path.split(':')
+    .filter(|directory| !directory.is_empty())
+    .map(|directory| Path::new(directory).join(library_file_name(&name)))
+    .find(|path| path.is_file());
+  match found {
+    Some(path) => vm.load_library(&path)?,
+    None => return Err(link_error(format!("no {} in java.library.path: {}", name, search_path))),
+  }
+  Ok(None)
+}
+
+fn system_map_library_name(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let name = String::from_utf16_lossy(&text_arg(vm, args[0])?);
+  Ok(Some(Value::Ref(vm.new_string(&library_file_name(&name))?)))
+}
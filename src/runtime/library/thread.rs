@@ -0,0 +1,283 @@
+use std::time::{Duration, Instant};
+
+use crate::runtime::{
+  builtin::{string_result, this},
+  class::{ClassDefinition, ACC_FINAL, ACC_PRIVATE, ACC_PUBLIC, ACC_STATIC, ACC_SUPER, ACC_VOLATILE},
+  error::VmError,
+  scheduler::MAIN_THREAD_ID,
+  value::{ObjRef, Value},
+  vm::Vm,
+};
+
+const MIN_PRIORITY: i32 = 1;
+const NORM_PRIORITY: i32 = 5;
+const MAX_PRIORITY: i32 = 10;
+
+pub fn define(vm: &mut Vm) -> Result<(), VmError> {
+  vm.define_class(
+    ClassDefinition::new("java/lang/Thread")
+      .access_flags(ACC_PUBLIC | ACC_SUPER)
+      .interface("java/lang/Runnable")
+      .field("name", "Ljava/lang/String;", ACC_PRIVATE | ACC_VOLATILE)
+      .field("priority", "I", ACC_PRIVATE)
+      .field("daemon", "Z", ACC_PRIVATE)
+      .field("target", "Ljava/lang/Runnable;", ACC_PRIVATE)
+      .field("tid", "J", ACC_PRIVATE)
+      .field("started", "Z", ACC_PRIVATE)
+      .field("interrupted", "Z", ACC_PRIVATE | ACC_VOLATILE)
+      .field("MIN_PRIORITY", "I", ACC_PUBLIC | ACC_STATIC | ACC_FINAL)
+      .field("NORM_PRIORITY", "I", ACC_PUBLIC | ACC_STATIC | ACC_FINAL)
+      .field("MAX_PRIORITY", "I", ACC_PUBLIC | ACC_STATIC | ACC_FINAL)
+      .native("<clinit>", "()V", ACC_STATIC, thread_clinit)
+      .native("<init>", "()V", ACC_PUBLIC, thread_init)
+      .native("<init>", "(Ljava/lang/Runnable;)V", ACC_PUBLIC, thread_init_target)
+      .native("<init>", "(Ljava/lang/Runnable;Ljava/lang/String;)V", ACC_PUBLIC, thread_init_target_name)
+      .native("<init>", "(Ljava/lang/String;)V", ACC_PUBLIC, thread_init_name)
+      .native("currentThread", "()Ljava/lang/Thread;", ACC_PUBLIC | ACC_STATIC, thread_current_thread)
+      .native("getName", "()Ljava/lang/String;", ACC_PUBLIC | ACC_FINAL, thread_get_name)
+      .native("setName", "(Ljava/lang/String;)V", ACC_PUBLIC | ACC_FINAL, thread_set_name)
+      .native("getId", "()J", ACC_PUBLIC, thread_get_id)
+      .native("getPriority", "()I", ACC_PUBLIC | ACC_FINAL, thread_get_priority)
+      .native("isDaemon", "()Z", ACC_PUBLIC | ACC_FINAL, thread_is_daemon)
+      .native("setDaemon", "(Z)V", ACC_PUBLIC | ACC_FINAL, thread_set_daemon)
+      .native("setPriority", "(I)V", ACC_PUBLIC | ACC_FINAL, thread_set_priority)
+      .native("run", "()V", ACC_PUBLIC, thread_run)
+      .native("start", "()V", ACC_PUBLIC, thread_start)
+      .native("isAlive", "()Z", ACC_PUBLIC | ACC_FINAL, thread_is_alive)
+      .native("join", "()V", ACC_PUBLIC | ACC_FINAL, thread_join)
+      .native("join", "(J)V", ACC_PUBLIC | ACC_FINAL, thread_join_timeout)
+      .native("interrupt", "()V", ACC_PUBLIC, thread_interrupt)
+      .native("isInterrupted", "()Z", ACC_PUBLIC, thread_is_interrupted)
+      .native("interrupted", "()Z", ACC_PUBLIC | ACC_STATIC, thread_interrupted)
+      .native("sleep", "(J)V", ACC_PUBLIC | ACC_STATIC, thread_sleep)
+      .native("sleep", "(JI)V", ACC_PUBLIC | ACC_STATIC, thread_sleep_nanos)
+      .native("yield", "()V", ACC_PUBLIC | ACC_STATIC, thread_yield)
+      .native("onSpinWait", "()V", ACC_PUBLIC | ACC_STATIC, thread_yield)
+      .native("holdsLock", "(Ljava/lang/Object;)Z", ACC_PUBLIC | ACC_STATIC, thread_holds_lock)
+      .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, thread_to_string)
+  )?;
+  Ok(())
+}
+
+fn thread_clinit(vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  let class = vm.load_class("java/lang/Thread")?;
+  vm.set_static_field(class, "MIN_PRIORITY", "I", Value::Int(MIN_PRIORITY))?;
+  vm.set_static_field(class, "NORM_PRIORITY", "I", Value::Int(NORM_PRIORITY))?;
+  vm.set_static_field(class, "MAX_PRIORITY", "I", Value::Int(MAX_PRIORITY))?;
+  Ok(None)
+}
+
+// 最初に呼ばれた時にmainスレッドのオブジェクトを作る
+pub(crate) fn thread_current_thread(vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Ref(current_thread(vm)?)))
+}
+
+pub(crate) fn current_thread(vm: &mut Vm) -> Result<ObjRef, VmError> {
+  let current = vm.threads.current;
+  if let Some(thread) = vm.threads.table.get(&current).and_then(|thread| thread.object) {
+    return Ok(thread);
+  }
+  let class = vm.load_class("java/lang/Thread")?;
+  vm.initialize(class)?;
+  let thread = vm.instantiate(class)?;
+  if let Some(entry) = vm.threads.table.get_mut(&current) {
+    entry.object = Some(thread);
+  }
+  let name = vm.new_string("main")?;
+  vm.set_field(thread, "name", "Ljava/lang/String;", Value::Ref(name))?;
+  vm.set_field(thread, "priority", "I", Value::Int(NORM_PRIORITY))?;
+  vm.set_field(thread, "tid", "J", Value::Long(MAIN_THREAD_ID))?;
+  vm.set_field(thread, "started", "Z", Value::Int(1))?;
+  Ok(thread)
+}
+
+fn thread_init(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  init_thread(vm, this(args)?, Value::Null, None)
+}
+
+fn thread_init_target(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  init_thread(vm, this(args)?, args[1], None)
+}
+
+fn thread_init_target_name(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  init_thread(vm, this(args)?, args[1], Some(args[2]))
+}
+
+fn thread_init_name(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  init_thread(vm, this(args)?, Value::Null, Some(args[1]))
+}
+
+// 名前を指定しなければThread-Nにし、優先度とデーモンかどうかは作成したスレッドから引き継ぐ
+fn init_thread(vm: &mut Vm, this: ObjRef, target: Value, name: Option<Value>) -> Result<Option<Value>, VmError> {
+  let name = match name {
+    Some(Value::Null) => return Err(VmError::java("java/lang/NullPointerException", "name cannot be null")),
+    Some(name) => name,
+    None => {
+      let name = vm.threads.new_name();
+      Value::Ref(vm.new_string(&name)?)
+    },
+  };
+  vm.set_field(this, "name", "Ljava/lang/String;", name)?;
+  let parent = current_thread(vm)?;
+  let priority = vm.get_field(parent, "priority", "I")?;
+  let daemon = vm.get_field(parent, "daemon", "Z")?;
+  vm.set_field(this, "priority", "I", priority)?;
+  vm.set_field(this, "daemon", "Z", daemon)?;
+  vm.set_field(this, "target", "Ljava/lang/Runnable;", target)?;
+  let id = vm.threads.new_id();
+  vm.set_field(this, "tid", "J", Value::Long(id))?;
+  Ok(None)
+}
+
+fn thread_get_name(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(vm.get_field(this(args)?, "name", "Ljava/lang/String;")?))
+}
+
+fn thread_set_name(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  if args[1] == Value::Null {
+    return Err(VmError::java("java/lang/NullPointerException", "name cannot be null"));
+  }
+  vm.set_field(this(args)?, "name", "Ljava/lang/String;", args[1])?;
+  Ok(None)
+}
+
+fn thread_get_id(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(vm.get_field(this(args)?, "tid", "J")?))
+}
+
+fn thread_get_priority(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(vm.get_field(this(args)?, "priority", "I")?))
+}
+
+fn thread_is_daemon(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(vm.get_field(this(args)?, "daemon", "Z")?))
+}
+
+fn is_alive(vm: &Vm, thread: ObjRef) -> Result<bool, VmError> {
+  Ok(vm.get_field(thread, "started", "Z")?.as_int()? != 0 && vm.is_thread_alive(vm.get_field(thread, "tid", "J")?.as_long()?))
+}
+
+fn thread_set_daemon(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  if is_alive(vm, this)? {
+    return Err(VmError::java_without_message("java/lang/IllegalThreadStateException"));
+  }
+  vm.set_field(this, "daemon", "Z", args[1])?;
+  Ok(None)
+}
+
+fn thread_set_priority(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let priority = args[1].as_int()?;
+  if !(MIN_PRIORITY..=MAX_PRIORITY).contains(&priority) {
+    return Err(VmError::java_without_message("java/lang/IllegalArgumentException"));
+  }
+  vm.set_field(this(args)?, "priority", "I", Value::Int(priority))?;
+  Ok(None)
+}
+
+// targetが指定されていればそのrun()を呼ぶ
+fn thread_run(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let Some(target) = vm.get_field(this(args)?, "target", "Ljava/lang/Runnable;")?.as_ref()? else {
+    return Ok(None);
+  };
+  let class = vm.object_class(target)?;
+  let method = vm.find_virtual(class, "run", "()V")
+    .ok_or_else(|| VmError::java("java/lang/AbstractMethodError", "java.lang.Runnable.run()V"))?;
+  vm.invoke(method, vec![Value::Ref(target)])?;
+  Ok(None)
+}
+
+// 2回目の呼び出しはIllegalThreadStateException
+fn thread_start(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  if vm.get_field(this, "started", "Z")?.as_int()? != 0 {
+    return Err(VmError::java_without_message("java/lang/IllegalThreadStateException"));
+  }
+  vm.set_field(this, "started", "Z", Value::Int(1))?;
+  vm.start_thread(this)?;
+  Ok(None)
+}
+
+fn thread_is_alive(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(is_alive(vm, this(args)?)? as i32)))
+}
+
+fn thread_join(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  join_millis(vm, this(args)?, 0)
+}
+
+fn thread_join_timeout(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  join_millis(vm, this(args)?, args[1].as_long()?)
+}
+
+// 0ミリ秒ならスレッドが終了するまで待つ
+fn join_millis(vm: &mut Vm, thread: ObjRef, millis: i64) -> Result<Option<Value>, VmError> {
+  if millis < 0 {
+    return Err(VmError::java("java/lang/IllegalArgumentException", "timeout value is negative"));
+  }
+  let deadline = (millis > 0).then(|| Instant::now() + Duration::from_millis(millis as u64));
+  let id = vm.get_field(thread, "tid", "J")?.as_long()?;
+  vm.join_thread(id, deadline, true)?;
+  Ok(None)
+}
+
+fn thread_interrupt(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  vm.interrupt_thread(this(args)?)?;
+  Ok(None)
+}
+
+fn thread_is_interrupted(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(vm.get_field(this(args)?, "interrupted", "Z")?))
+}
+
+// 現在のスレッドの割り込みフラグを返して下ろす
+fn thread_interrupted(vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(Some(Value::Int(vm.take_interrupted()? as i32)))
+}
+
+pub(crate) fn thread_sleep(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  sleep_millis(vm, args[0].as_long()?)
+}
+
+// 0.5ミリ秒以上なら切り上げる
+fn thread_sleep_nanos(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let (millis, nanos) = (args[0].as_long()?, args[1].as_int()?);
+  if millis < 0 {
+    return Err(VmError::java("java/lang/IllegalArgumentException", "timeout value is negative"));
+  }
+  if !(0..=999_999).contains(&nanos) {
+    return Err(VmError::java("java/lang/IllegalArgumentException", "nanosecond timeout value out of range"));
+  }
+  let round_up = nanos >= 500_000 || (nanos != 0 && millis == 0);
+  sleep_millis(vm, if round_up { millis.saturating_add(1) } else { millis })
+}
+
+fn sleep_millis(vm: &mut Vm, millis: i64) -> Result<Option<Value>, VmError> {
+  if millis < 0 {
+    return Err(VmError::java("java/lang/IllegalArgumentException", "timeout value is negative"));
+  }
+  vm.sleep(Instant::now() + Duration::from_millis(millis as u64))?;
+  Ok(None)
+}
+
+pub(crate) fn thread_yield(vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  vm.yield_thread();
+  Ok(None)
+}
+
+pub(crate) fn thread_holds_lock(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let object = args[0].as_ref()?.ok_or_else(|| VmError::java_without_message("java/lang/NullPointerException"))?;
+  Ok(Some(Value::Int(vm.holds_lock(vm.object_lock(object)) as i32)))
+}
+
+// Thread[名前,優先度,スレッドグループ]
+fn thread_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, VmError> {
+  let this = this(args)?;
+  let name = match vm.get_field(this, "name", "Ljava/lang/String;")?.as_ref()? {
+    Some(name) => vm.string_value(name)?,
+    None => "null".to_string(),
+  };
+  let priority = vm.get_field(this, "priority", "I")?.as_int()?;
+  let text = format!("Thread[{},{},main]", name, priority);
+  string_result(vm, &text.encode_utf16().collect::<Vec<_>>())
+}
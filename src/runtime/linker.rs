@@ -0,0 +1,426 @@
+use std::rc::Rc;
+
+use crate::{
+  runtime::{
+    class::{ClassId, InitState, ResolvedRef, RuntimeMethod, ACC_SUPER},
+    error::VmError,
+    invokedynamic::CallSite,
+    monitor::LockKey,
+    value::Value,
+    vm::Vm,
+  },
+  structure::class::Constant,
+  util::descriptor::MethodDescriptor,
+};
+
+impl Vm {
+  // コンスタントプールのシンボリック参照を初回の使用時に解決し、結果をクラスに保存する (JVMS 5.4.3)
+  fn resolve_constant(&mut self, class: ClassId, index: u16) -> Result<ResolvedRef, VmError> {
+    if let Some(resolved) = self.classes[class].resolved.get(index as usize).cloned().flatten() {
+      return match resolved {
+        ResolvedRef::Error(e) => Err(e),
+        resolved => Ok(resolved),
+      };
+    }
+    let class_file = self.class_file(class)?;
+    let constant_pool = &class_file.constant_pool;
+    let result = match constant_pool.get_class(index)? {
+      Constant::Class { .. } => {
+        let name = constant_pool.get_class_name(index)?;
+        self.load_class(&name).map(ResolvedRef::Class)
+      },
+      Constant::Fieldref { class_index, .. } => {
+        let (_, name, descriptor) = constant_pool.get_member_ref(index)?;
+        self.resolve_class(class, *class_index).and_then(|c| {
+          let (owner, field) = self.find_field(c, &name, &descriptor)
+            .ok_or_else(|| VmError::java("java/lang/NoSuchFieldError", name.clone()))?;
+          if self.classes[owner].fields[field].is_private() {
+            self.check_private_access(class, owner, &format!("field {}.{}", self.classes[owner].java_name(), name))?;
+          }
+          Ok(ResolvedRef::Field(owner, field))
+        })
+      },
+      Constant::Methodref { class_index, .. } => {
+        let (_, name, descriptor) = constant_pool.get_member_ref(index)?;
+        self.resolve_class(class, *class_index).and_then(|c| {
+          let method = self.resolve_class_method(c, &name, &descriptor)?;
+          self.check_method_access(class, &method)?;
+          Ok(ResolvedRef::Method(c, method))
+        })
+      },
+      Constant::InterfaceMethodref { class_index, .. } => {
+        let (_, name, descriptor) = constant_pool.get_member_ref(index)?;
+        self.resolve_class(class, *class_index).and_then(|c| {
+          let method = self.resolve_interface_method(c, &name, &descriptor)?;
+          self.check_method_access(class, &method)?;
+          Ok(ResolvedRef::Method(c, method))
+        })
+      },
+      Constant::MethodHandle { .. } => {
+        self.resolve_method_handle(class, index)
+          .and_then(|target| self.new_method_handle(&target))
+          .map(|handle| ResolvedRef::Constant(Value::Ref(handle)))
+      },
+      Constant::MethodType { descriptor_index } => {
+        let descriptor = constant_pool.get_utf8(*descriptor_index)?;
+        self.resolve_method_type(&descriptor).map(|method_type| ResolvedRef::Constant(Value::Ref(method_type)))
+      },
+      Constant::Dynamic { .. } => self.resolve_dynamic_constant(class, index).map(ResolvedRef::Constant),
+      Constant::InvokeDynamic { .. } => {
+        self.link_call_site(class, index).map(|site| ResolvedRef::CallSite(Rc::new(site)))
+      },
+      c => return Err(VmError::internal(format!("Expected symbolic reference, found: {:?}", c))),
+    };
+    // 内部エラーは記録せず、Javaの例外になるものだけ記録する
+    let entry = match &result {
+      Ok(resolved) => Some(resolved.clone()),
+      Err(e @ VmError::Java { .. }) => Some(ResolvedRef::Error(e.clone())),
+      Err(VmError::Thrown(_) | VmError::Internal(_)) => None,
+    };
+    if let Some(slot) = self.classes[class].resolved.get_mut(index as usize) {
+      *slot = entry;
+    }
+    result
+  }
+
+  pub fn resolve_class(&mut self, class: ClassId, index: u16) -> Result<ClassId, VmError> {
+    match self.resolve_constant(class, index)? {
+      ResolvedRef::Class(c) => Ok(c),
+      r => Err(VmError::internal(format!("Expected class reference, found: {:?}", r))),
+    }
+  }
+
+  pub fn resolve_field(&mut self, class: ClassId, index: u16) -> Result<(ClassId, usize), VmError> {
+    match self.resolve_constant(class, index)? {
+      ResolvedRef::Field(owner, field) => Ok((owner, field)),
+      r => Err(VmError::internal(format!("Expected field reference, found: {:?}", r))),
+    }
+  }
+
+  // 参照先として指定されたクラスと、解決したメソッドを返す
+  pub fn resolve_method(&mut self, class: ClassId, index: u16) -> Result<(ClassId, Rc<RuntimeMethod>), VmError> {
+    match self.resolve_constant(class, index)? {
+      ResolvedRef::Method(c, method) => Ok((c, method)),
+      r => Err(VmError::internal(format!("Expected method reference, found: {:?}", r))),
+    }
+  }
+
+  pub fn resolve_call_site(&mut self, class: ClassId, index: u16) -> Result<Rc<CallSite>, VmError> {
+    match self.resolve_constant(class, index)? {
+      ResolvedRef::CallSite(site) => Ok(site),
+      r => Err(VmError::internal(format!("Expected call site, found: {:?}", r))),
+    }
+  }
+
+  pub fn resolve_loadable(&mut self, class: ClassId, index: u16) -> Result<Value, VmError> {
+    match self.resolve_constant(class, index)? {
+      ResolvedRef::Constant(value) => Ok(value),
+      r => Err(VmError::internal(format!("Expected loadable constant, found: {:?}", r))),
+    }
+  }
+
+  // 名前と記述子でメソッドを探す (ネイティブ側のGetMethodIDで使う)
+  pub(crate) fn lookup_method(&mut self, class: ClassId, name: &str, descriptor: &str) -> Result<Rc<RuntimeMethod>, VmError> {
+    match self.classes[class].is_interface() {
+      true => self.resolve_interface_method(class, name, descriptor),
+      false => self.resolve_class_method(class, name, descriptor),
+    }
+  }
+
+  // クラスのメソッドの解決 (JVMS 5.4.3.3)
+  fn resolve_class_method(&self, class: ClassId, name: &str, descriptor: &str) -> Result<Rc<RuntimeMethod>, VmError> {
+    if self.classes[class].is_interface() {
+      return Err(VmError::java("java/lang/IncompatibleClassChangeError", format!(
+        "Found interface {}, but class was expected", self.classes[class].java_name(),
+      )));
+    }
+    let mut current = Some(class);
+    while let Some(c) = current {
+      if let Some(method) = self.classes[c].find_method(name, descriptor) {
+        return Ok(method);
+      }
+      if let Some(method) = self.signature_polymorphic_method(c, name, descriptor) {
+        return Ok(method);
+      }
+      current = self.classes[c].super_class;
+    }
+    self.superinterface_method(class, name, descriptor)
+      .ok_or_else(|| self.no_such_method(class, name, descriptor))
+  }
+
+  // その名前のメソッドが1つだけで、シグネチャ多相なら呼び出し側の記述子のメソッドとして解決する
+  fn signature_polymorphic_method(&self, class: ClassId, name: &str, descriptor: &str) -> Option<Rc<RuntimeMethod>> {
+    let mut candidates = self.classes[class].methods.iter().filter(|m| m.name == name);
+    let method = candidates.next()?;
+    if candidates.next().is_some() || !method.is_signature_polymorphic() {
+      return None;
+    }
+    Some(Rc::new(RuntimeMethod {
+      id: method.id,
+      class: method.class,
+      class_name: method.class_name.clone(),
+      name: method.name.clone(),
+      descriptor: descriptor.to_string(),
+      access_flags: method.access_flags,
+      signature: MethodDescriptor::parse(descriptor).ok()?,
+      body: method.body.clone(),
+      vtable_index: None,
+      itable_index: None,
+    }))
+  }
+
+  // インターフェースのメソッドの解決 (JVMS 5.4.3.4)
+  fn resolve_interface_method(&mut self, class: ClassId, name: &str, descriptor: &str) -> Result<Rc<RuntimeMethod>, VmError> {
+    if !self.classes[class].is_interface() {
+      return Err(VmError::java("java/lang/IncompatibleClassChangeError", format!(
+        "Found class {}, but interface was expected", self.classes[class].java_name(),
+      )));
+    }
+    if let Some(method) = self.classes[class].find_method(name, descriptor) {
+      return Ok(method);
+    }
+    let object = self.load_class("java/lang/Object")?;
+    if let Some(method) = self.classes[object].find_method(name, descriptor)
+      .filter(|m| m.is_public() && !m.is_static()) {
+      return Ok(method);
+    }
+    self.superinterface_method(class, name, descriptor)
+      .ok_or_else(|| self.no_such_method(class, name, descriptor))
+  }
+
+  fn check_method_access(&mut self, accessor: ClassId, method: &RuntimeMethod) -> Result<(), VmError> {
+    if !method.is_private() {
+      return Ok(());
+    }
+    self.check_private_access(accessor, method.class, &format!("method {}.{}{}", method.class_name.replace('/', "."), method.name, method.descriptor))
+  }
+
+  // privateメンバーには同じクラスか、同じネストに属するクラスからのみアクセスできる
+  fn check_private_access(&mut self, accessor: ClassId, owner: ClassId, member: &str) -> Result<(), VmError> {
+    if accessor == owner || self.nest_host(accessor) == self.nest_host(owner) {
+      return Ok(());
+    }
+    Err(VmError::java("java/lang/IllegalAccessError", format!(
+      "class {} tried to access private {}", self.classes[accessor].java_name(), member,
+    )))
+  }
+
+  // NestHost属性が指すクラスが、NestMembers属性でこのクラスを認めている場合だけそのクラスがネストホストになる (JVMS 5.4.4)
+  pub fn nest_host(&mut self, class: ClassId) -> ClassId {
+    let Some(host_name) = self.classes[class].nest_host.clone() else { return class };
+    match self.load_class(&host_name) {
+      Ok(host) if self.classes[host].package() == self.classes[class].package()
+        && self.classes[host].nest_members.contains(&self.classes[class].name) => host,
+      _ => class,
+    }
+  }
+
+  fn no_such_method(&self, class: ClassId, name: &str, descriptor: &str) -> VmError {
+    VmError::java("java/lang/NoSuchMethodError", format!("{}.{}{}", self.classes[class].java_name(), name, descriptor))
+  }
+
+  // 最も特定的なスーパーインターフェースのメソッドのうち、抽象でないものが1つならそれを選び、
+  // 無ければスーパーインターフェースのいずれかのメソッドを選ぶ
+  fn superinterface_method(&self, class: ClassId, name: &str, descriptor: &str) -> Option<Rc<RuntimeMethod>> {
+    let mut concrete = self.maximally_specific_methods(class, name, descriptor);
+    concrete.retain(|m| !m.is_abstract());
+    if concrete.len() == 1 {
+      return concrete.pop();
+    }
+    self.superinterfaces(class).into_iter()
+      .filter_map(|i| self.classes[i].find_method(name, descriptor))
+      .find(|m| !m.is_private() && !m.is_static())
+  }
+
+  // スーパークラスのものも含めた、全てのスーパーインターフェース
+  pub(crate) fn superinterfaces(&self, class: ClassId) -> Vec<ClassId> {
+    let mut result = Vec::new();
+    let mut pending: Vec<ClassId> = Vec::new();
+    let mut current = Some(class);
+    while let Some(c) = current {
+      pending.extend(self.classes[c].interfaces.iter().rev());
+      while let Some(interface) = pending.pop() {
+        if !result.contains(&interface) {
+          result.push(interface);
+          pending.extend(self.classes[interface].interfaces.iter().rev());
+        }
+      }
+      current = self.classes[c].super_class;
+    }
+    result
+  }
+
+  // 他の候補を宣言したインターフェースのスーパーインターフェースで宣言されていないメソッド (JVMS 5.4.3.3)
+  fn maximally_specific_methods(&self, class: ClassId, name: &str, descriptor: &str) -> Vec<Rc<RuntimeMethod>> {
+    let candidates: Vec<Rc<RuntimeMethod>> = self.superinterfaces(class).into_iter()
+      .filter_map(|i| self.classes[i].find_method(name, descriptor))
+      .filter(|m| !m.is_private() && !m.is_static())
+      .collect();
+    candidates.iter()
+      .filter(|m| !candidates.iter().any(|other| other.class != m.class && self.implements(other.class, m.class)))
+      .cloned()
+      .collect()
+  }
+
+  // invokevirtual/invokeinterfaceで呼び出すメソッドの選択 (JVMS 5.4.6)
+  pub fn select_method(&self, receiver: ClassId, resolved: &Rc<RuntimeMethod>) -> Result<Rc<RuntimeMethod>, VmError> {
+    if resolved.is_private() {
+      return Ok(resolved.clone());
+    }
+    let mut current = Some(receiver);
+    while let Some(c) = current {
+      if let Some(method) = self.classes[c].find_method(&resolved.name, &resolved.descriptor)
+        && (Rc::ptr_eq(&method, resolved) || method.can_override(resolved)) {
+        return self.check_concrete(receiver, method);
+      }
+      current = self.classes[c].super_class;
+    }
+    self.select_default_method(receiver, &resolved.name, &resolved.descriptor)
+  }
+
+  // invokespecialで呼び出すメソッドの選択 (JVMS 6.5 invokespecial)
+  pub fn select_special(&self, current: ClassId, symbolic: ClassId, resolved: &Rc<RuntimeMethod>) -> Result<Rc<RuntimeMethod>, VmError> {
+    // ACC_SUPERのクラスからスーパークラスのメソッドを呼ぶ場合は、直近のスーパークラスから選び直す
+    let class = match self.classes[current].super_class {
+      Some(super_class) if resolved.name != "<init>"
+        && !self.classes[symbolic].is_interface()
+        && symbolic != current
+        && self.is_subclass_of(current, symbolic)
+        && self.classes[current].access_flags & ACC_SUPER != 0 => super_class,
+      _ => symbolic,
+    };
+    let mut candidate = Some(class);
+    while let Some(c) = candidate {
+      if let Some(method) = self.classes[c].find_method(&resolved.name, &resolved.descriptor)
+        && !method.is_static() {
+        return self.check_concrete(class, method);
+      }
+      candidate = if self.classes[c].is_interface() { None } else { self.classes[c].super_class };
+    }
+    if self.classes[class].is_interface()
+      && let Some(&object) = self.class_names.get("java/lang/Object")
+      && let Some(method) = self.classes[object].find_method(&resolved.name, &resolved.descriptor)
+      && method.is_public() && !method.is_static() {
+      return self.check_concrete(class, method);
+    }
+    self.select_default_method(class, &resolved.name, &resolved.descriptor)
+  }
+
+  fn select_default_method(&self, class: ClassId, name: &str, descriptor: &str) -> Result<Rc<RuntimeMethod>, VmError> {
+    let mut candidates = self.maximally_specific_methods(class, name, descriptor);
+    candidates.retain(|m| !m.is_abstract());
+    match candidates.len() {
+      0 => Err(self.abstract_method_error(class, name, descriptor)),
+      1 => Ok(candidates.remove(0)),
+      _ => Err(VmError::java("java/lang/IncompatibleClassChangeError", format!(
+        "Conflicting default methods: {}",
+        candidates.iter()
+          .map(|m| format!("{}.{}", self.classes[m.class].java_name(), m.name))
+          .collect::<Vec<_>>()
+          .join(" "),
+      ))),
+    }
+  }
+
+  fn check_concrete(&self, class: ClassId, method: Rc<RuntimeMethod>) -> Result<Rc<RuntimeMethod>, VmError> {
+    if method.is_abstract() {
+      return Err(self.abstract_method_error(class, &method.name, &method.descriptor));
+    }
+    Ok(method)
+  }
+
+  fn abstract_method_error(&self, class: ClassId, name: &str, descriptor: &str) -> VmError {
+    VmError::java("java/lang/AbstractMethodError", format!("{}.{}{}", self.classes[class].java_name(), name, descriptor))
+  }
+
+  // クラスの初期化 (JVMS 5.5)
+  pub fn initialize(&mut self, class: ClassId) -> Result<(), VmError> {
+    if self.classes[class].init_state == InitState::Initialized {
+      return Ok(());
+    }
+    // 他のスレッドが初期化中なら、初期化ロックで終わるまで待つ
+    let me = self.threads.current;
+    let lock = LockKey::Initialization(class);
+    self.monitor_enter(lock);
+    while let InitState::Initializing(owner) = self.classes[class].init_state && owner != me {
+      self.monitor_wait_uninterruptibly(lock);
+    }
+    let state = self.classes[class].init_state;
+    if state == InitState::Uninitialized {
+      self.classes[class].init_state = InitState::Initializing(me);
+    }
+    self.monitor_exit(lock)?;
+    match state {
+      // 初期化中のクラスへの要求は、同じスレッドからの再帰的な要求なのでそのまま返す
+      InitState::Initialized | InitState::Initializing(_) => return Ok(()),
+      InitState::Erroneous => {
+        let message = format!("Could not initialize class {}", self.classes[class].java_name());
+        let cause = self.classes[class].init_error;
+        return Err(self.new_throwable("java/lang/NoClassDefFoundError", Some(&message), cause).map_or_else(|e| e, VmError::Thrown));
+      },
+      InitState::Uninitialized => {},
+    }
+
+    let result = match self.initialize_supertypes(class) {
+      Err(e) => Err(e),
+      Ok(()) => match self.classes[class].find_method("<clinit>", "()V") {
+        Some(clinit) => self.invoke(clinit, Vec::new()).map(|_| ()).map_err(|e| self.initializer_error(e)),
+        None => Ok(()),
+      },
+    };
+    // 失敗した時の例外は、後の初期化要求で投げるNoClassDefFoundErrorの原因として残す
+    let result = result.map_err(|e| match self.materialize(e) {
+      Ok(exception) => {
+        self.classes[class].init_error = Some(exception);
+        VmError::Thrown(exception)
+      },
+      Err(e) => e,
+    });
+    let state = match result {
+      Ok(()) => InitState::Initialized,
+      Err(_) => InitState::Erroneous,
+    };
+    self.monitor_enter(lock);
+    self.classes[class].init_state = state;
+    self.monitor_notify(lock, true)?;
+    self.monitor_exit(lock)?;
+    result
+  }
+
+  // クラスならスーパークラスと、デフォルトメソッドを持つスーパーインターフェースを先に初期化する
+  fn initialize_supertypes(&mut self, class: ClassId) -> Result<(), VmError> {
+    if self.classes[class].is_interface() {
+      return Ok(());
+    }
+    if let Some(super_class) = self.classes[class].super_class {
+      self.initialize(super_class)?;
+    }
+    let interfaces: Vec<ClassId> = self.classes[class].interfaces.iter()
+      .flat_map(|&i| std::iter::once(i).chain(self.superinterfaces(i)))
+      .filter(|&i| self.classes[i].methods.iter().any(|m| !m.is_abstract() && !m.is_static()))
+      .collect();
+    for interface in interfaces {
+      self.initialize(interface)?;
+    }
+    Ok(())
+  }
+
+  // Errorのサブクラス以外の例外はExceptionInInitializerErrorで包む
+  fn initializer_error(&mut self, error: VmError) -> VmError {
+    let exception = match self.materialize(error) {
+      Ok(exception) => exception,
+      Err(e) => return e,
+    };
+    let is_error = match (self.object_class(exception), self.load_class("java/lang/Error")) {
+      (Ok(thrown), Ok(error_class)) => self.is_subclass_of(thrown, error_class),
+      _ => false,
+    };
+    if is_error {
+      return VmError::Thrown(exception);
+    }
+    match self.new_throwable("java/lang/ExceptionInInitializerError", None, Some(exception)) {
+      Ok(wrapper) => VmError::Thrown(wrapper),
+      Err(e) => e,
+    }
+  }
+}
@@ -0,0 +1,186 @@
+use std::sync::atomic::{fence, Ordering};
+
+use crate::runtime::{
+  class::ClassId,
+  error::VmError,
+  value::{ObjRef, Value},
+  vm::Vm,
+};
+
+// Javaメモリモデル (JLS 17.4)
+// Vmを操作できるのは実行権を持つ1つのスレッドだけで、実行権は命令の境界でMutexを通して受け渡すので、
+// 全てのアクセスは1つの全順序に並び、longやdoubleの読み書きが分断されることもない。
+// その上でvolatileとfinalが要求する順序をJSR-133のバリアに対応するフェンスとして明示する
+
+// アクセスの順序付けの強さ (VarHandleのアクセスモード)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryOrder {
+  Plain,
+  Opaque,
+  Acquire,
+  Release,
+  Volatile,
+}
+
+impl MemoryOrder {
+  // 書き込みの前 (StoreStore|LoadStore)
+  pub fn before_write(self) {
+    if matches!(self, MemoryOrder::Release | MemoryOrder::Volatile) {
+      fence(Ordering::Release);
+    }
+  }
+
+  // 書き込みの後 (StoreLoad)
+  pub fn after_write(self) {
+    if self == MemoryOrder::Volatile {
+      fence(Ordering::SeqCst);
+    }
+  }
+
+  // 読み込みの後 (LoadLoad|LoadStore)
+  pub fn after_read(self) {
+    if matches!(self, MemoryOrder::Acquire | MemoryOrder::Volatile) {
+      fence(Ordering::Acquire);
+    }
+  }
+
+  // 読み込みと書き込みを不可分に行う命令の前後
+  pub fn before_update(self) {
+    self.before_write();
+  }
+
+  pub fn after_update(self) {
+    self.after_read();
+    self.after_write();
+  }
+}
+
+// finalフィールドを持つオブジェクトのコンストラクタの終わり (フィールドの凍結)
+pub fn freeze() {
+  fence(Ordering::Release);
+}
+
+pub fn full_fence() {
+  fence(Ordering::SeqCst);
+}
+
+pub fn acquire_fence() {
+  fence(Ordering::Acquire);
+}
+
+pub fn release_fence() {
+  fence(Ordering::Release);
+}
+
+// 不可分に読み書きする変数の位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+  Field(ObjRef, usize),
+  Static(ClassId, usize),
+  Element(ObjRef, usize),
+}
+
+// compareAndSetの比較 (浮動小数点数はビット列で、参照は同一性で比べる)
+pub fn same_value(a: Value, b: Value) -> bool {
+  match (a, b) {
+    (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+    (Value::Double(a), Value::Double(b)) => a.to_bits() == b.to_bits(),
+    (a, b) => a == b,
+  }
+}
+
+// getAndAddの加算 (整数はラップアラウンドする)
+pub fn add_values(a: Value, b: Value) -> Result<Value, VmError> {
+  match (a, b) {
+    (Value::Int(a), Value::Int(b)) => Ok(Value::Int(a.wrapping_add(b))),
+    (Value::Long(a), Value::Long(b)) => Ok(Value::Long(a.wrapping_add(b))),
+    (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a + b)),
+    (Value::Double(a), Value::Double(b)) => Ok(Value::Double(a + b)),
+    _ => Err(VmError::java_without_message("java/lang/UnsupportedOperationException")),
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitwiseOp {
+  Or,
+  And,
+  Xor,
+}
+
+pub fn bitwise_values(op: BitwiseOp, a: Value, b: Value) -> Result<Value, VmError> {
+  let apply = |a: i64, b: i64| match op {
+    BitwiseOp::Or => a | b,
+    BitwiseOp::And => a & b,
+    BitwiseOp::Xor => a ^ b,
+  };
+  match (a, b) {
+    (Value::Int(a), Value::Int(b)) => Ok(Value::Int(apply(a as i64, b as i64) as i32)),
+    (Value::Long(a), Value::Long(b)) => Ok(Value::Long(apply(a, b))),
+    _ => Err(VmError::java_without_message("java/lang/UnsupportedOperationException")),
+  }
+}
+
+impl Vm {
+  // 配列の要素の位置 (範囲外ならArrayIndexOutOfBoundsException)
+  pub fn element_location(&self, array: ObjRef, index: i32) -> Result<Location, VmError> {
+    Ok(Location::Element(array, self.array_index(array, index)?))
+  }
+
+  pub fn load(&self, location: Location) -> Result<Value, VmError> {
+    match location {
+      Location::Field(object, slot) => Ok(self.heap.get(object)?.fields()?[slot]),
+      Location::Static(class, slot) => Ok(self.classes[class].static_values[slot]),
+      Location::Element(array, index) => Ok(self.heap.get(array)?.array()?.get(index)),
+    }
+  }
+
+  pub fn store(&mut self, location: Location, value: Value) -> Result<(), VmError> {
+    match location {
+      Location::Field(object, slot) => self.heap.get_mut(object)?.fields_mut()?[slot] = value,
+      Location::Static(class, slot) => self.classes[class].static_values[slot] = value,
+      Location::Element(array, index) => self.heap.get_mut(array)?.array_mut()?.set(index, value)?,
+    }
+    Ok(())
+  }
+
+  pub fn load_ordered(&self, location: Location, order: MemoryOrder) -> Result<Value, VmError> {
+    let value = self.load(location)?;
+    order.after_read();
+    Ok(value)
+  }
+
+  pub fn store_ordered(&mut self, location: Location, value: Value, order: MemoryOrder) -> Result<(), VmError> {
+    order.before_write();
+    self.store(location, value)?;
+    order.after_write();
+    Ok(())
+  }
+
+  // 現在の値から新しい値を計算して書き込み、元の値を返す (ネイティブの処理中はスレッドが切り替わらないので不可分になる)
+  pub fn update(&mut self, location: Location, order: MemoryOrder, f: impl FnOnce(Value) -> Result<Option<Value>, VmError>) -> Result<Value, VmError> {
+    order.before_update();
+    let current = self.load(location)?;
+    if let Some(value) = f(current)? {
+      self.store(location, value)?;
+    }
+    order.after_update();
+    Ok(current)
+  }
+
+  // 期待した値なら書き込み、書き込む前の値を返す
+  pub fn compare_and_exchange(&mut self, location: Location, expected: Value, value: Value, order: MemoryOrder) -> Result<Value, VmError> {
+    self.update(location, order, |current| Ok(same_value(current, expected).then_some(value)))
+  }
+
+  pub fn compare_and_set(&mut self, location: Location, expected: Value, value: Value, order: MemoryOrder) -> Result<bool, VmError> {
+    Ok(same_value(self.compare_and_exchange(location, expected, value, order)?, expected))
+  }
+
+  pub fn get_and_set(&mut self, location: Location, value: Value, order: MemoryOrder) -> Result<Value, VmError> {
+    self.update(location, order, |_| Ok(Some(value)))
+  }
+
+  pub fn get_and_add(&mut self, location: Location, delta: Value, order: MemoryOrder) -> Result<Value, VmError> {
+    self.update(location, order, |current| add_values(current, delta).map(Some))
+  }
+}
@@ -0,0 +1,480 @@
+use std::rc::Rc;
+
+use crate::{
+  runtime::{
+    class::{ClassId, RuntimeMethod},
+    error::VmError,
+    invokedynamic::bootstrap_method,
+    library::boxing::convert,
+    value::{ObjRef, Value},
+    vm::Vm,
+  },
+  structure::class::Constant,
+  util::descriptor::{FieldType, MethodDescriptor},
+};
+
+// MethodHandleの参照の種類 (JVMS 5.4.3.5)
+pub const REF_GET_FIELD: u8 = 1;
+pub const REF_GET_STATIC: u8 = 2;
+pub const REF_PUT_FIELD: u8 = 3;
+pub const REF_PUT_STATIC: u8 = 4;
+pub const REF_INVOKE_VIRTUAL: u8 = 5;
+pub const REF_INVOKE_STATIC: u8 = 6;
+pub const REF_INVOKE_SPECIAL: u8 = 7;
+pub const REF_NEW_INVOKE_SPECIAL: u8 = 8;
+pub const REF_INVOKE_INTERFACE: u8 = 9;
+
+// 基本型とラッパークラス
+const WRAPPERS: &[(FieldType, &str)] = &[
+  (FieldType::Boolean, "java/lang/Boolean"),
+  (FieldType::Byte, "java/lang/Byte"),
+  (FieldType::Char, "java/lang/Character"),
+  (FieldType::Short, "java/lang/Short"),
+  (FieldType::Int, "java/lang/Integer"),
+  (FieldType::Long, "java/lang/Long"),
+  (FieldType::Float, "java/lang/Float"),
+  (FieldType::Double, "java/lang/Double"),
+];
+
+// MethodHandleが指すフィールドまたはメソッド
+#[derive(Debug, Clone)]
+pub enum HandleMember {
+  Field(ClassId, usize),
+  Method(Rc<RuntimeMethod>),
+}
+
+// 解決済みのMethodHandle (参照の種類、参照先、呼び出す時の型)
+#[derive(Debug, Clone)]
+pub struct HandleTarget {
+  pub kind: u8,
+  pub member: HandleMember,
+  pub handle_type: MethodDescriptor,
+}
+
+pub fn wrapper_class(field_type: &FieldType) -> Option<&'static str> {
+  WRAPPERS.iter().find(|(t, _)| t == field_type).map(|(_, name)| *name)
+}
+
+pub fn wrapped_type(class_name: &str) -> Option<FieldType> {
+  WRAPPERS.iter().find(|(_, name)| *name == class_name).map(|(t, _)| t.clone())
+}
+
+// パッケージと外側のクラスを除いたクラス名 (Class.getSimpleName()の代わり)
+pub fn simple_name(class_name: &str) -> &str {
+  let name = class_name.rsplit('/').next().unwrap_or(class_name);
+  name.rsplit('$').next().filter(|n| !n.is_empty()).unwrap_or(name)
+}
+
+fn simple_type_name(field_type: &FieldType) -> String {
+  match field_type {
+    FieldType::Object(name) => simple_name(name).to_string(),
+    FieldType::Array(component) => format!("{}[]", simple_type_name(component)),
+    primitive => primitive.to_string(),
+  }
+}
+
+// MethodType.toString()と同じ形式 ((int,String)void)
+pub fn method_type_string(method_type: &MethodDescriptor) -> String {
+  let parameters: Vec<String> = method_type.parameters.iter().map(simple_type_name).collect();
+  let return_type = method_type.return_type.as_ref().map_or("void".to_string(), simple_type_name);
+  format!("({}){}", parameters.join(","), return_type)
+}
+
+// 参照先のクラスを型として表す (配列クラスは記述子がそのまま内部名になる)
+fn class_type(class_name: &str) -> FieldType {
+  match class_name.starts_with('[') {
+    true => FieldType::parse(class_name).unwrap_or(FieldType::Object(class_name.to_string())),
+    false => FieldType::Object(class_name.to_string()),
+  }
+}
+
+// 定数の値から引数の型を決める (参照はObjectとして扱う)
+fn value_type(value: Value) -> FieldType {
+  match value {
+    Value::Long(_) => FieldType::Long,
+    Value::Float(_) => FieldType::Float,
+    Value::Double(_) => FieldType::Double,
+    Value::Int(_) => FieldType::Int,
+    _ => FieldType::Object("java/lang/Object".to_string()),
+  }
+}
+
+impl Vm {
+  // MethodHandle定数の解決 (JVMS 5.4.3.5)
+  pub(crate) fn resolve_method_handle(&mut self, class: ClassId, index: u16) -> Result<HandleTarget, VmError> {
+    let class_file = self.class_file(class)?;
+    let constant_pool = &class_file.constant_pool;
+    let (kind, reference) = match constant_pool.get_class(index)? {
+      Constant::MethodHandle { reference_kind, reference_index } => (*reference_kind, *reference_index),
+      c => return Err(VmError::internal(format!("Expected MethodHandle constant, found: {:?}", c))),
+    };
+    let (class_name, name, _) = constant_pool.get_member_ref(reference)?;
+    let receiver = class_type(&class_name);
+    match kind {
+      REF_GET_FIELD..=REF_PUT_STATIC => {
+        let (owner, index) = self.resolve_field(class, reference)?;
+        let field = &self.classes[owner].fields[index];
+        let is_static = matches!(kind, REF_GET_STATIC | REF_PUT_STATIC);
+        if field.is_static() != is_static {
+          return Err(VmError::java("java/lang/IncompatibleClassChangeError", format!(
+            "Expected {} field {}.{}", if is_static { "static" } else { "non-static" }, class_name.replace('/', "."), name,
+          )));
+        }
+        let field_type = field.field_type.clone();
+        let (parameters, return_type) = match kind {
+          REF_GET_FIELD => (vec![receiver], Some(field_type)),
+          REF_GET_STATIC => (vec![], Some(field_type)),
+          REF_PUT_FIELD => (vec![receiver, field_type], None),
+          _ => (vec![field_type], None),
+        };
+        Ok(HandleTarget { kind, member: HandleMember::Field(owner, index), handle_type: MethodDescriptor { parameters, return_type } })
+      },
+      REF_INVOKE_VIRTUAL..=REF_INVOKE_INTERFACE => {
+        let (_, method) = self.resolve_method(class, reference)?;
+        if (kind == REF_INVOKE_STATIC) != method.is_static() || (kind == REF_NEW_INVOKE_SPECIAL) != (method.name == "<init>") {
+          return Err(VmError::java("java/lang/IncompatibleClassChangeError", format!(
+            "Invalid method handle kind {} for {}.{}", kind, class_name.replace('/', "."), name,
+          )));
+        }
+        let mut handle_type = method.signature.clone();
+        match kind {
+          REF_INVOKE_STATIC => {},
+          REF_NEW_INVOKE_SPECIAL => handle_type.return_type = Some(receiver),
+          _ => handle_type.parameters.insert(0, receiver),
+        }
+        Ok(HandleTarget { kind, member: HandleMember::Method(method), handle_type })
+      },
+      _ => Err(VmError::java("java/lang/ClassFormatError", format!("Invalid reference kind {}", kind))),
+    }
+  }
+
+  // MethodType定数の解決: 記述子に現れるクラスを読み込む
+  pub(crate) fn resolve_method_type(&mut self, descriptor: &str) -> Result<ObjRef, VmError> {
+    let method_type = MethodDescriptor::parse(descriptor)
+      .map_err(|e| VmError::java("java/lang/ClassFormatError", e))?;
+    for field_type in method_type.parameters.iter().chain(method_type.return_type.iter()) {
+      if field_type.is_reference() {
+        self.load_class(&field_type.class_name())?;
+      }
+    }
+    self.new_method_type(&method_type)
+  }
+
+  pub fn new_method_type(&mut self, method_type: &MethodDescriptor) -> Result<ObjRef, VmError> {
+    let class = self.load_class("java/lang/invoke/MethodType")?;
+    let descriptor = self.new_string(&method_type.descriptor())?;
+    let mark = self.handles.mark();
+    self.handles.new_local(descriptor);
+    let object = self.instantiate(class);
+    self.handles.release(mark);
+    let object = object?;
+    self.set_field(object, "descriptor", "Ljava/lang/String;", Value::Ref(descriptor))?;
+    Ok(object)
+  }
+
+  pub fn method_type_descriptor(&self, method_type: ObjRef) -> Result<MethodDescriptor, VmError> {
+    let descriptor = self.get_field(method_type, "descriptor", "Ljava/lang/String;")?.as_ref()?
+      .ok_or_else(|| VmError::internal("MethodType without descriptor"))?;
+    MethodDescriptor::parse(&self.string_value(descriptor)?).map_err(VmError::internal)
+  }
+
+  // 参照先はメソッドならメソッドID、フィールドなら (クラスID << 32 | フィールドの位置) として持つ
+  pub fn new_method_handle(&mut self, target: &HandleTarget) -> Result<ObjRef, VmError> {
+    let class = self.load_class("java/lang/invoke/MethodHandle")?;
+    let method_type = self.new_method_type(&target.handle_type)?;
+    let mark = self.handles.mark();
+    self.handles.new_local(method_type);
+    let handle = self.instantiate(class);
+    self.handles.release(mark);
+    let handle = handle?;
+    let member = match &target.member {
+      HandleMember::Field(owner, index) => ((*owner as i64) << 32) | *index as i64,
+      HandleMember::Method(method) => method.id as i64,
+    };
+    self.set_field(handle, "kind", "I", Value::Int(target.kind as i32))?;
+    self.set_field(handle, "member", "J", Value::Long(member))?;
+    self.set_field(handle, "type", "Ljava/lang/invoke/MethodType;", Value::Ref(method_type))?;
+    Ok(handle)
+  }
+
+  pub fn handle_target(&self, handle: ObjRef) -> Result<HandleTarget, VmError> {
+    let kind = self.get_field(handle, "kind", "I")?.as_int()? as u8;
+    let member = self.get_field(handle, "member", "J")?.as_long()?;
+    let method_type = self.get_field(handle, "type", "Ljava/lang/invoke/MethodType;")?.as_ref()?
+      .ok_or_else(|| VmError::internal("MethodHandle without type"))?;
+    let member = match kind {
+      REF_GET_FIELD..=REF_PUT_STATIC => HandleMember::Field((member >> 32) as ClassId, (member & 0xffff_ffff) as usize),
+      _ => HandleMember::Method(self.methods.get(member as usize).cloned()
+        .ok_or_else(|| VmError::internal(format!("Invalid method handle target: {}", member)))?),
+    };
+    Ok(HandleTarget { kind, member, handle_type: self.method_type_descriptor(method_type)? })
+  }
+
+  // 動的定数をブートストラップメソッドで計算する (JVMS 5.4.3.6)
+  // ConstantBootstrapsのメソッドだけをVM内で実装している
+  pub(crate) fn resolve_dynamic_constant(&mut self, class: ClassId, index: u16) -> Result<Value, VmError> {
+    let class_file = self.class_file(class)?;
+    let constant_pool = &class_file.constant_pool;
+    let Constant::Dynamic { bootstrap_method_attr_index, name_and_type_index } = constant_pool.get_class(index)? else {
+      return Err(VmError::internal(format!("Expected Dynamic constant at {}", index)));
+    };
+    let (name, descriptor) = constant_pool.get_name_and_type(*name_and_type_index)?;
+    let field_type = FieldType::parse(&descriptor).map_err(|e| VmError::java("java/lang/ClassFormatError", e))?;
+    let (bootstrap_class, bootstrap_name, arguments) = bootstrap_method(&class_file, *bootstrap_method_attr_index)?;
+    if bootstrap_class != "java/lang/invoke/ConstantBootstraps" {
+      return Err(VmError::java("java/lang/BootstrapMethodError", format!(
+        "Unsupported bootstrap method {}.{}", bootstrap_class.replace('/', "."), bootstrap_name,
+      )));
+    }
+    match bootstrap_name.as_str() {
+      "nullConstant" => match field_type.is_reference() {
+        true => Ok(Value::Null),
+        false => Err(VmError::java("java/lang/IllegalArgumentException", format!("not reference type: {}", field_type))),
+      },
+      "getStaticFinal" | "enumConstant" => {
+        let declaring = match (bootstrap_name.as_str(), arguments.first()) {
+          ("getStaticFinal", Some(&declaring)) => self.resolve_class(class, declaring)?,
+          _ => {
+            let class_name = wrapper_class(&field_type).map_or_else(|| field_type.class_name(), |w| w.to_string());
+            self.load_class(&class_name)?
+          },
+        };
+        let (owner, field) = self.find_field(declaring, &name, &descriptor)
+          .ok_or_else(|| VmError::java("java/lang/NoSuchFieldError", name.clone()))?;
+        if !self.classes[owner].fields[field].is_static() {
+          return Err(VmError::java("java/lang/IncompatibleClassChangeError", format!(
+            "Expected static field {}.{}", self.classes[owner].java_name(), name,
+          )));
+        }
+        self.initialize(owner)?;
+        Ok(self.classes[owner].static_values[self.classes[owner].fields[field].slot])
+      },
+      "invoke" => {
+        let Some((&handle, rest)) = arguments.split_first() else {
+          return Err(VmError::java("java/lang/BootstrapMethodError", "Missing method handle"));
+        };
+        let target = self.resolve_method_handle(class, handle)?;
+        let mark = self.handles.mark();
+        let result = self.invoke_with_constants(&target, class, rest, &field_type);
+        self.handles.release(mark);
+        result.map_err(|e| self.bootstrap_error(e))
+      },
+      "explicitCast" => {
+        let value = match arguments.first() {
+          Some(&argument) => self.constant_value(class, argument)?,
+          None => return Err(VmError::java("java/lang/BootstrapMethodError", "Missing value")),
+        };
+        // explicitCastArguments()と同じく、基本型へは縮小変換もする (nullは0になる)
+        match (value, field_type.is_reference()) {
+          (_, true) => self.adapt_value(value, &value_type(value), &field_type),
+          (Value::Null, false) => Ok(convert(Value::Int(0), &field_type)),
+          (Value::Ref(object), false) => Ok(convert(self.unbox_value(Some(object))?.0, &field_type)),
+          (_, false) => Ok(convert(value, &field_type)),
+        }
+      },
+      _ => Err(VmError::java("java/lang/BootstrapMethodError", format!(
+        "Unsupported bootstrap method {}.{}", bootstrap_class.replace('/', "."), bootstrap_name,
+      ))),
+    }
+  }
+
+  fn invoke_with_constants(&mut self, target: &HandleTarget, class: ClassId, arguments: &[u16], field_type: &FieldType) -> Result<Value, VmError> {
+    let mut values = Vec::new();
+    for &argument in arguments {
+      let value = self.constant_value(class, argument)?;
+      if let Value::Ref(reference) = value {
+        self.handles.new_local(reference);
+      }
+      values.push(value);
+    }
+    let types: Vec<FieldType> = values.iter().map(|v| value_type(*v)).collect();
+    let result = self.invoke_adapted(target, values, &types, Some(field_type))?;
+    Ok(result.unwrap_or(Value::default_for(field_type)))
+  }
+
+  // ブートストラップメソッドが投げたError以外の例外はBootstrapMethodErrorで包む
+  fn bootstrap_error(&mut self, error: VmError) -> VmError {
+    let VmError::Thrown(exception) = error else { return error };
+    let Ok(error_class) = self.load_class("java/lang/Error") else { return error };
+    match self.object_class(exception) {
+      Ok(class) if !self.is_subclass_of(class, error_class) => {
+        match self.new_throwable("java/lang/BootstrapMethodError", Some("bootstrap method initialization exception"), Some(exception)) {
+          Ok(wrapped) => VmError::Thrown(wrapped),
+          Err(e) => e,
+        }
+      },
+      _ => error,
+    }
+  }
+
+  // MethodHandle.invokeExact/invoke: 呼び出し側の記述子 (レシーバを除く) で引数と戻り値を受け渡す
+  pub(crate) fn invoke_polymorphic(&mut self, method: &RuntimeMethod, args: Vec<Value>) -> Result<Option<Value>, VmError> {
+    if method.class_name == "java/lang/invoke/VarHandle" {
+      return self.invoke_var_handle(method, args);
+    }
+    let handle = args[0].as_ref()?.ok_or_else(|| VmError::null_pointer("Cannot invoke a null MethodHandle"))?;
+    let target = self.handle_target(handle)?;
+    let values = args[1..].to_vec();
+    match method.name.as_str() {
+      "invokeExact" => {
+        if method.signature != target.handle_type {
+          return Err(VmError::java("java/lang/invoke/WrongMethodTypeException", format!(
+            "expected {} but found {}", method_type_string(&target.handle_type), method_type_string(&method.signature),
+          )));
+        }
+        self.invoke_member(&target, values)
+      },
+      "invoke" => self.invoke_adapted(&target, values, &method.signature.parameters, method.signature.return_type.as_ref()),
+      _ => Err(VmError::java("java/lang/UnsupportedOperationException", format!(
+        "{}.{}", method.class_name.replace('/', "."), method.name,
+      ))),
+    }
+  }
+
+  // 引数と戻り値を型に合わせて変換してから呼び出す (MethodHandle.asType()相当)
+  pub(crate) fn invoke_adapted(&mut self, target: &HandleTarget, values: Vec<Value>, types: &[FieldType], return_type: Option<&FieldType>) -> Result<Option<Value>, VmError> {
+    let parameters = &target.handle_type.parameters;
+    if values.len() != parameters.len() {
+      let from = MethodDescriptor { parameters: types.to_vec(), return_type: return_type.cloned() };
+      return Err(VmError::java("java/lang/invoke/WrongMethodTypeException", format!(
+        "cannot convert {} to {}", method_type_string(&target.handle_type), method_type_string(&from),
+      )));
+    }
+    let mark = self.handles.mark();
+    let result = self.adapt_and_invoke(target, values, types, return_type);
+    self.handles.release(mark);
+    result
+  }
+
+  fn adapt_and_invoke(&mut self, target: &HandleTarget, values: Vec<Value>, types: &[FieldType], return_type: Option<&FieldType>) -> Result<Option<Value>, VmError> {
+    let mut args = Vec::new();
+    for ((value, from), to) in values.into_iter().zip(types).zip(&target.handle_type.parameters) {
+      let value = self.adapt_value(value, from, to)?;
+      if let Value::Ref(reference) = value {
+        self.handles.new_local(reference);
+      }
+      args.push(value);
+    }
+    let result = self.invoke_member(target, args)?;
+    match (result, &target.handle_type.return_type, return_type) {
+      (Some(value), Some(from), Some(to)) => self.adapt_value(value, from, to).map(Some),
+      // voidとして呼ばれた場合は戻り値を捨て、voidのメソッドの戻り値を求められた場合は既定値にする
+      (_, _, None) => Ok(None),
+      (None, _, Some(to)) => Ok(Some(Value::default_for(to))),
+      (result, None, _) => Ok(result),
+    }
+  }
+
+  // 参照の種類に応じてフィールドを読み書きするか、メソッドを呼び出す
+  pub(crate) fn invoke_member(&mut self, target: &HandleTarget, mut args: Vec<Value>) -> Result<Option<Value>, VmError> {
+    let method = match &target.member {
+      HandleMember::Field(owner, index) => {
+        let (owner, slot) = (*owner, self.classes[*owner].fields[*index].slot);
+        let name = &self.classes[owner].fields[*index].name;
+        return match target.kind {
+          REF_GET_FIELD | REF_PUT_FIELD => {
+            let object = args[0].as_ref()?
+              .ok_or_else(|| VmError::null_pointer(format!("Cannot access field \"{}\" of null", name)))?;
+            let fields = self.heap.get_mut(object)?.fields_mut()?;
+            if target.kind == REF_GET_FIELD {
+              Ok(Some(fields[slot]))
+            } else {
+              fields[slot] = args[1];
+              Ok(None)
+            }
+          },
+          REF_GET_STATIC => {
+            self.initialize(owner)?;
+            Ok(Some(self.classes[owner].static_values[slot]))
+          },
+          _ => {
+            self.initialize(owner)?;
+            self.classes[owner].static_values[slot] = args[0];
+            Ok(None)
+          },
+        };
+      },
+      HandleMember::Method(method) => method.clone(),
+    };
+    let receiver_required = || VmError::null_pointer(format!(
+      "Cannot invoke \"{}.{}()\"", method.class_name.replace('/', "."), method.name,
+    ));
+    match target.kind {
+      REF_INVOKE_STATIC => {
+        self.initialize(method.class)?;
+        self.invoke(method, args)
+      },
+      REF_NEW_INVOKE_SPECIAL => {
+        self.initialize(method.class)?;
+        let object = self.instantiate(method.class)?;
+        let mark = self.handles.mark();
+        self.handles.new_local(object);
+        args.insert(0, Value::Ref(object));
+        let result = self.invoke(method, args);
+        self.handles.release(mark);
+        result.map(|_| Some(Value::Ref(object)))
+      },
+      REF_INVOKE_SPECIAL => {
+        args[0].as_ref()?.ok_or_else(receiver_required)?;
+        self.invoke(method, args)
+      },
+      _ => {
+        let receiver = args[0].as_ref()?.ok_or_else(receiver_required)?;
+        let selected = self.dispatch(self.object_class(receiver)?, &method)?;
+        self.invoke(selected, args)
+      },
+    }
+  }
+
+  // 基本型の値をラッパークラスのvalueOf()で包む
+  pub fn box_value(&mut self, value: Value, field_type: &FieldType) -> Result<Value, VmError> {
+    let Some(wrapper) = wrapper_class(field_type) else {
+      return Err(VmError::internal(format!("Cannot box {:?} as {:?}", value, field_type)));
+    };
+    let class = self.load_class(wrapper)?;
+    let descriptor = format!("({}){}", field_type.descriptor(), FieldType::Object(wrapper.to_string()).descriptor());
+    let method = self.classes[class].find_method("valueOf", &descriptor)
+      .ok_or_else(|| VmError::java("java/lang/NoSuchMethodError", format!("{}.valueOf{}", wrapper.replace('/', "."), descriptor)))?;
+    self.initialize(class)?;
+    self.invoke(method, vec![value])?
+      .ok_or_else(|| VmError::internal("valueOf returned no value"))
+  }
+
+  // ラッパークラスのオブジェクトから基本型の値を取り出す
+  pub fn unbox_value(&mut self, object: Option<ObjRef>) -> Result<(Value, FieldType), VmError> {
+    let object = object.ok_or_else(|| VmError::null_pointer("Cannot unbox null value"))?;
+    let class = self.object_class(object)?;
+    let field_type = wrapped_type(&self.classes[class].name)
+      .ok_or_else(|| VmError::java("java/lang/ClassCastException", format!("{} is not a primitive wrapper", self.classes[class].java_name())))?;
+    Ok((self.get_field(object, "value", &field_type.descriptor())?, field_type))
+  }
+
+  // 型の違いをボクシング・アンボクシングと拡大変換で合わせる
+  pub(crate) fn adapt_value(&mut self, value: Value, from: &FieldType, to: &FieldType) -> Result<Value, VmError> {
+    match (value, to.is_reference()) {
+      (Value::Ref(_) | Value::Null, true) => Ok(value),
+      (Value::Ref(_) | Value::Null, false) => {
+        let (unboxed, unboxed_type) = self.unbox_value(value.as_ref()?)?;
+        self.adapt_value(unboxed, &unboxed_type, to)
+      },
+      (_, true) => {
+        let boxed_type = match from {
+          FieldType::Object(name) => wrapped_type(name),
+          primitive if !primitive.is_reference() => Some(primitive.clone()),
+          _ => None,
+        };
+        let boxed_type = boxed_type.unwrap_or(value_type(value));
+        self.box_value(value, &boxed_type)
+      },
+      (_, false) => Ok(match (value, to) {
+        (Value::Int(v), FieldType::Long) => Value::Long(v as i64),
+        (Value::Int(v), FieldType::Float) => Value::Float(v as f32),
+        (Value::Int(v), FieldType::Double) => Value::Double(v as f64),
+        (Value::Long(v), FieldType::Float) => Value::Float(v as f32),
+        (Value::Long(v), FieldType::Double) => Value::Double(v as f64),
+        (Value::Float(v), FieldType::Double) => Value::Double(v as f64),
+        (value, _) => value,
+      }),
+    }
+  }
+}
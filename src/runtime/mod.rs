@@ -0,0 +1,29 @@
+pub mod builtin;
+pub mod class;
+pub mod debugger;
+pub mod dispatch;
+pub mod error;
+pub mod exception;
+pub mod frame;
+pub mod gc;
+pub mod handles;
+pub mod heap;
+pub mod interpreter;
+pub mod invokedynamic;
+pub mod jdwp;
+pub mod jit;
+pub mod jni;
+pub mod launcher;
+pub mod library;
+pub mod linker;
+pub mod memory;
+pub mod method_handle;
+pub mod monitor;
+pub mod native;
+pub mod options;
+pub mod profile;
+pub mod scheduler;
+pub mod trace;
+pub mod value;
+pub mod var_handle;
+pub mod vm;
@@ -0,0 +1,301 @@
+use std::{
+  collections::BTreeSet,
+  time::{Duration, Instant},
+};
+
+use crate::runtime::{
+  class::{ClassId, RuntimeMethod},
+  error::VmError,
+  frame::Frame,
+  library::mirror::MirrorTarget,
+  scheduler::{ThreadId, ThreadState},
+  value::{ObjRef, Value},
+  vm::Vm,
+};
+
+// モニタを持つもの
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LockKey {
+  Object(ObjRef),
+  // staticなsynchronizedメソッドとClassオブジェクトのロック (Classオブジェクトを作る前でも使える)
+  Class(ClassId),
+  // クラスの初期化ロック (JVMS 5.5)
+  Initialization(ClassId),
+}
+
+#[derive(Debug, Default)]
+pub struct Monitor {
+  owner: Option<ThreadId>,
+  // 再入した回数
+  count: u32,
+  // ロックの解放を待っているスレッド
+  entrants: Vec<ThreadId>,
+  // wait()しているスレッド (notify()されると取り除く)
+  waiters: Vec<ThreadId>,
+}
+
+impl Vm {
+  // synchronizedメソッドが呼び出しの間に持つロック
+  pub(crate) fn method_lock(method: &RuntimeMethod, locals: &[Value]) -> Option<LockKey> {
+    if !method.is_synchronized() {
+      return None;
+    }
+    if method.is_static() {
+      return Some(LockKey::Class(method.class));
+    }
+    match locals.first() {
+      Some(Value::Ref(this)) => Some(LockKey::Object(*this)),
+      _ => None,
+    }
+  }
+
+  // Classオブジェクトのロックは、そのクラスのstaticなsynchronizedメソッドと共有する
+  pub(crate) fn object_lock(&self, object: ObjRef) -> LockKey {
+    match self.mirror_target(object) {
+      Some(MirrorTarget::Class(class)) => LockKey::Class(*class),
+      _ => LockKey::Object(object),
+    }
+  }
+
+  // ロックを取れるまで止まる (同じスレッドなら再入する)
+  pub(crate) fn monitor_enter(&mut self, key: LockKey) {
+    let me = self.threads.current;
+    loop {
+      let monitor = self.monitors.entry(key).or_default();
+      match monitor.owner {
+        None => {
+          monitor.owner = Some(me);
+          monitor.count = 1;
+          monitor.entrants.retain(|&entrant| entrant != me);
+          break;
+        },
+        Some(owner) if owner == me => {
+          monitor.count += 1;
+          break;
+        },
+        Some(_) => {
+          if !monitor.entrants.contains(&me) {
+            monitor.entrants.push(me);
+          }
+          self.set_thread_state(ThreadState::Blocked(key));
+          self.park(None);
+        },
+      }
+    }
+    self.set_thread_state(ThreadState::Runnable);
+  }
+
+  pub(crate) fn monitor_exit(&mut self, key: LockKey) -> Result<(), VmError> {
+    let me = self.threads.current;
+    let Some(monitor) = self.monitors.get_mut(&key).filter(|monitor| monitor.owner == Some(me)) else {
+      return Err(VmError::java_without_message("java/lang/IllegalMonitorStateException"));
+    };
+    monitor.count -= 1;
+    if monitor.count == 0 {
+      self.release_monitor(key);
+    }
+    Ok(())
+  }
+
+  // 所有者をなくし、ロックを待っているスレッドを起こす
+  fn release_monitor(&mut self, key: LockKey) {
+    let Some(monitor) = self.monitors.get_mut(&key) else { return };
+    monitor.owner = None;
+    monitor.count = 0;
+    let entrants = monitor.entrants.clone();
+    if entrants.is_empty() && monitor.waiters.is_empty() {
+      self.monitors.remove(&key);
+    }
+    for entrant in entrants {
+      self.unpark_thread(entrant);
+    }
+  }
+
+  pub(crate) fn holds_lock(&self, key: LockKey) -> bool {
+    self.monitors.get(&key).is_some_and(|monitor| monitor.owner == Some(self.threads.current))
+  }
+
+  fn check_owner(&self, key: LockKey) -> Result<(), VmError> {
+    if !self.holds_lock(key) {
+      return Err(VmError::java("java/lang/IllegalMonitorStateException", "current thread is not owner"));
+    }
+    Ok(())
+  }
+
+  // Object.wait(): ロックを全て手放してnotify()されるのを待ち、同じ回数だけ取り直す
+  pub(crate) fn monitor_wait(&mut self, key: LockKey, timeout: Option<Duration>) -> Result<(), VmError> {
+    self.check_owner(key)?;
+    if self.take_interrupted()? {
+      return Err(VmError::java_without_message("java/lang/InterruptedException"));
+    }
+    self.wait_notified(key, timeout, true);
+    if self.take_interrupted()? {
+      return Err(VmError::java_without_message("java/lang/InterruptedException"));
+    }
+    Ok(())
+  }
+
+  // 割り込まれても待ち続ける (クラスの初期化ロック)
+  pub(crate) fn monitor_wait_uninterruptibly(&mut self, key: LockKey) {
+    self.wait_notified(key, None, false);
+  }
+
+  fn wait_notified(&mut self, key: LockKey, timeout: Option<Duration>, interruptible: bool) {
+    let me = self.threads.current;
+    let count = self.monitors.get(&key).map_or(1, |monitor| monitor.count);
+    if let Some(monitor) = self.monitors.get_mut(&key) {
+      monitor.waiters.push(me);
+    }
+    self.release_monitor(key);
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    self.set_thread_state(match deadline {
+      Some(_) => ThreadState::TimedWaiting(Some(key)),
+      None => ThreadState::Waiting(Some(key)),
+    });
+    loop {
+      self.park(deadline);
+      let notified = !self.monitors.get(&key).is_some_and(|monitor| monitor.waiters.contains(&me));
+      let timed_out = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+      if notified || timed_out || (interruptible && self.is_interrupted().unwrap_or(false)) {
+        break;
+      }
+    }
+    if let Some(monitor) = self.monitors.get_mut(&key) {
+      monitor.waiters.retain(|&waiter| waiter != me);
+    }
+    self.monitor_enter(key);
+    if let Some(monitor) = self.monitors.get_mut(&key) {
+      monitor.count = count;
+    }
+  }
+
+  // Object.notify()とnotifyAll()
+  pub(crate) fn monitor_notify(&mut self, key: LockKey, all: bool) -> Result<(), VmError> {
+    self.check_owner(key)?;
+    let Some(monitor) = self.monitors.get_mut(&key) else { return Ok(()) };
+    let count = if all { monitor.waiters.len() } else { monitor.waiters.len().min(1) };
+    let woken: Vec<ThreadId> = monitor.waiters.drain(..count).collect();
+    for waiter in woken {
+      self.unpark_thread(waiter);
+    }
+    Ok(())
+  }
+
+  // ロックを持っていなくても、wait()しているスレッドを全て起こす (スレッドの終了時)
+  pub(crate) fn notify_all_waiters(&mut self, key: LockKey) {
+    let Some(monitor) = self.monitors.get_mut(&key) else { return };
+    let woken = std::mem::take(&mut monitor.waiters);
+    if monitor.owner.is_none() && monitor.entrants.is_empty() {
+      self.monitors.remove(&key);
+    }
+    for waiter in woken {
+      self.unpark_thread(waiter);
+    }
+  }
+
+  // ロックされているオブジェクト (GCのルートになる)
+  pub(crate) fn monitor_roots(&self) -> impl Iterator<Item = ObjRef> + '_ {
+    self.monitors.keys().filter_map(|key| match key {
+      LockKey::Object(object) => Some(*object),
+      _ => None,
+    })
+  }
+
+  fn lock_text(&self, key: LockKey) -> String {
+    match key {
+      LockKey::Object(object) => {
+        let class = match self.object_class(object) {
+          Ok(class) => self.classes[class].java_name(),
+          Err(_) => "java.lang.Object".to_string(),
+        };
+        format!("<0x{:08x}> (a {})", self.identity_hash(object), class)
+      },
+      LockKey::Class(class) => {
+        let hash = self.mirrors.class_mirror(class).map_or(0, |mirror| self.identity_hash(mirror));
+        format!("<0x{:08x}> (a java.lang.Class for {})", hash, self.classes[class].java_name())
+      },
+      LockKey::Initialization(class) => format!("<initialization lock> (for {})", self.classes[class].java_name()),
+    }
+  }
+
+  // 全スレッドのスタックと、ロックを待ち合っているスレッドの循環
+  pub(crate) fn deadlock_report(&self) -> String {
+    let mut text = String::from("Full thread dump:\n\n");
+    for (&id, thread) in &self.threads.table {
+      let daemon = if thread.daemon { " daemon" } else { "" };
+      text.push_str(&format!("\"{}\" #{}{} {}\n", self.thread_name(id), id, daemon, thread.state.description()));
+      let frames: &[Frame] = self.thread_frames(id);
+      for (depth, frame) in frames.iter().rev().enumerate() {
+        text.push_str(&format!("\tat {}\n", self.stack_frame_text(&frame.method, frame.pc)));
+        match thread.state {
+          ThreadState::Blocked(key) if depth == 0 => text.push_str(&format!("\t- waiting to lock {}\n", self.lock_text(key))),
+          ThreadState::Waiting(Some(key)) | ThreadState::TimedWaiting(Some(key)) if depth == 0 => {
+            text.push_str(&format!("\t- waiting on {}\n", self.lock_text(key)))
+          },
+          _ => {},
+        }
+      }
+      // monitorenterで取ったロックはフレームと結び付けていないので、まとめて表示する
+      let mut owned: Vec<LockKey> = self.monitors.iter()
+        .filter(|(_, monitor)| monitor.owner == Some(id))
+        .map(|(&key, _)| key)
+        .collect();
+      owned.sort_by_key(|key| self.lock_text(*key));
+      for key in owned {
+        text.push_str(&format!("\t- holding {}\n", self.lock_text(key)));
+      }
+      text.push('\n');
+    }
+
+    let cycles = self.lock_cycles();
+    if cycles.is_empty() {
+      text.push_str("No thread can make progress: all threads are waiting and none of them can be notified.\n");
+      return text;
+    }
+    for cycle in &cycles {
+      text.push_str("Found one Java-level deadlock:\n=============================\n");
+      for &id in cycle {
+        if let ThreadState::Blocked(key) = self.threads.table[&id].state {
+          let owner = self.monitors.get(&key).and_then(|monitor| monitor.owner).unwrap_or(id);
+          text.push_str(&format!(
+            "\"{}\":\n  waiting to lock {},\n  which is held by \"{}\"\n",
+            self.thread_name(id),
+            self.lock_text(key),
+            self.thread_name(owner),
+          ));
+        }
+      }
+      text.push('\n');
+    }
+    text.push_str(&format!("Found {} deadlock{}.\n", cycles.len(), if cycles.len() == 1 { "" } else { "s" }));
+    text
+  }
+
+  // ロックの所有者をたどって自分に戻ってくるスレッドの列
+  fn lock_cycles(&self) -> Vec<Vec<ThreadId>> {
+    let waiting_for = |id: ThreadId| match self.threads.table.get(&id)?.state {
+      ThreadState::Blocked(key) => self.monitors.get(&key)?.owner,
+      _ => None,
+    };
+    let mut seen = BTreeSet::new();
+    let mut cycles = Vec::new();
+    for &start in self.threads.table.keys() {
+      let mut path = Vec::new();
+      let mut current = start;
+      while !seen.contains(&current) && !path.contains(&current) {
+        path.push(current);
+        match waiting_for(current) {
+          Some(owner) => current = owner,
+          None => break,
+        }
+      }
+      if let Some(position) = path.iter().position(|&id| id == current)
+        && waiting_for(*path.last().unwrap_or(&current)).is_some()
+      {
+        cycles.push(path[position..].to_vec());
+      }
+      seen.extend(path);
+    }
+    cycles
+  }
+}
@@ -0,0 +1,416 @@
+use std::{collections::HashMap, process, rc::Rc};
+
+use crate::{
+  runtime::{
+    builtin,
+    class::{ClassId, NativeFn, RuntimeMethod},
+    error::VmError,
+    heap::ArrayData,
+    library::{boxing, math, misc_unsafe, string, system, thread},
+    value::{ObjRef, Value},
+    vm::Vm,
+  },
+  util::descriptor::FieldType,
+};
+
+// クラスファイルのネイティブメソッドに結び付ける組み込みの実装 (本物のjava.baseを使う場合に必要になる)
+const INTRINSICS: &[(&str, &str, &str, NativeFn)] = &[
+  ("java/lang/Object", "registerNatives", "()V", register_natives),
+  ("java/lang/Object", "hashCode", "()I", builtin::object_hash_code),
+  ("java/lang/Object", "clone", "()Ljava/lang/Object;", builtin::object_clone),
+  ("java/lang/Object", "wait", "(J)V", builtin::object_wait_timeout),
+  ("java/lang/Object", "notify", "()V", builtin::object_notify),
+  ("java/lang/Object", "notifyAll", "()V", builtin::object_notify_all),
+  ("java/lang/System", "registerNatives", "()V", register_natives),
+  ("java/lang/System", "arraycopy", "(Ljava/lang/Object;ILjava/lang/Object;II)V", system::system_arraycopy),
+  ("java/lang/System", "currentTimeMillis", "()J", system::system_current_time_millis),
+  ("java/lang/System", "nanoTime", "()J", system::system_nano_time),
+  ("java/lang/System", "identityHashCode", "(Ljava/lang/Object;)I", system::system_identity_hash_code),
+  ("java/lang/Thread", "registerNatives", "()V", register_natives),
+  ("java/lang/Thread", "currentThread", "()Ljava/lang/Thread;", thread::thread_current_thread),
+  ("java/lang/Thread", "yield", "()V", thread::thread_yield),
+  ("java/lang/Thread", "sleep", "(J)V", thread::thread_sleep),
+  ("java/lang/Thread", "holdsLock", "(Ljava/lang/Object;)Z", thread::thread_holds_lock),
+  ("java/lang/Throwable", "fillInStackTrace", "(I)Ljava/lang/Throwable;", builtin::throwable_fill_in_stack_trace),
+  ("java/lang/String", "intern", "()Ljava/lang/String;", string::string_intern),
+  ("java/lang/Float", "floatToRawIntBits", "(F)I", boxing::float_to_raw_int_bits),
+  ("java/lang/Float", "intBitsToFloat", "(I)F", boxing::int_bits_to_float),
+  ("java/lang/Double", "doubleToRawLongBits", "(D)J", boxing::double_to_raw_long_bits),
+  ("java/lang/Double", "longBitsToDouble", "(J)D", boxing::long_bits_to_double),
+];
+
+// (クラス名, メソッド名, 記述子) からRustで実装したネイティブメソッドを引く
+#[derive(Default)]
+pub struct NativeRegistry {
+  functions: HashMap<(String, String, String), NativeFn>,
+}
+
+impl NativeRegistry {
+  pub fn with_intrinsics() -> Self {
+    let mut registry = NativeRegistry::default();
+    for (class, name, descriptor, function) in INTRINSICS {
+      registry.register(class, name, descriptor, *function);
+    }
+    for (name, descriptor, function) in misc_unsafe::NATIVES {
+      registry.register("jdk/internal/misc/Unsafe", name, descriptor, *function);
+    }
+    // StrictMathの関数はMathと同じ実装を使う
+    for (name, function) in math::DOUBLE_FUNCTIONS {
+      registry.register("java/lang/StrictMath", name, "(D)D", *function);
+    }
+    registry
+  }
+
+  pub fn register(&mut self, class: &str, name: &str, descriptor: &str, function: NativeFn) {
+    self.functions.insert((class.to_string(), name.to_string(), descriptor.to_string()), function);
+  }
+
+  // クラスに登録した実装をすべて外す (JNIのUnregisterNatives)
+  pub fn unregister(&mut self, class: &str) {
+    self.functions.retain(|(c, _, _), _| c != class);
+  }
+
+  pub fn lookup(&self, class: &str, name: &str, descriptor: &str) -> Option<NativeFn> {
+    self.functions.get(&(class.to_string(), name.to_string(), descriptor.to_string())).copied()
+  }
+}
+
+fn register_natives(_vm: &mut Vm, _args: &[Value]) -> Result<Option<Value>, VmError> {
+  Ok(None)
+}
+
+impl Vm {
+  // ネイティブメソッドの実装を探して呼び出す (見つからなければUnsatisfiedLinkError)
+  pub(crate) fn invoke_native(&mut self, method: &RuntimeMethod, function: Option<NativeFn>, args: Vec<Value>) -> Result<Option<Value>, VmError> {
+    let function = function.or_else(|| self.natives.lookup(&method.class_name, &method.name, &method.descriptor));
+    // Rustの実装がなければ、読み込んだ共有ライブラリのJNI関数を探す
+    let address = match function {
+      Some(_) => None,
+      None => match self.find_jni_function(method) {
+        Some(address) => Some(address),
+        None => return Err(VmError::java("java/lang/UnsatisfiedLinkError", format!("'{}'", method.external_name()))),
+      },
+    };
+    let mark = self.handles.mark();
+    for arg in &args {
+      if let Value::Ref(reference) = arg {
+        self.handles.new_local(*reference);
+      }
+    }
+    let lock = Self::method_lock(method, &args);
+    if let Some(lock) = lock {
+      self.monitor_enter(lock);
+    }
+    if self.profiler.is_some() {
+      self.profile_enter(method);
+    }
+    let result = match (function, address) {
+      (Some(function), _) => function(self, &args),
+      (None, Some(address)) => self.call_jni(method, address, &args),
+      (None, None) => unreachable!(),
+    };
+    if self.profiler.is_some() {
+      self.profile_exit();
+    }
+    let result = match lock {
+      Some(lock) => self.monitor_exit(lock).and(result),
+      None => result,
+    };
+    self.handles.release(mark);
+    // JniEnv::throw()で保留した例外は、ネイティブメソッドから戻った時点で投げる
+    match (result, self.pending_exception.take()) {
+      (Ok(_), Some(exception)) => Err(VmError::Thrown(exception)),
+      (result, _) => result,
+    }
+  }
+}
+
+// ネイティブメソッドからVMを操作するためのJNIEnv相当のAPI
+pub struct JniEnv<'a> {
+  vm: &'a mut Vm,
+}
+
+// Rustで書くネイティブメソッド向けのAPIで、組み込みのネイティブメソッドとJNIの関数表が使わないものも残す
+#[allow(dead_code)]
+impl<'a> JniEnv<'a> {
+  pub fn new(vm: &'a mut Vm) -> Self {
+    JniEnv { vm }
+  }
+
+  pub fn vm(&mut self) -> &mut Vm {
+    self.vm
+  }
+
+  // クラス
+
+  pub fn find_class(&mut self, name: &str) -> Result<ClassId, VmError> {
+    self.vm.load_class(name)
+  }
+
+  pub fn get_object_class(&self, object: ObjRef) -> Result<ClassId, VmError> {
+    self.vm.object_class(object)
+  }
+
+  pub fn get_superclass(&self, class: ClassId) -> Option<ClassId> {
+    match self.vm.classes[class].is_interface() {
+      true => None,
+      false => self.vm.classes[class].super_class,
+    }
+  }
+
+  pub fn is_assignable_from(&self, from: ClassId, to: ClassId) -> bool {
+    self.vm.is_assignable(from, to)
+  }
+
+  // nullはどのクラスのインスタンスとしても扱う
+  pub fn is_instance_of(&self, object: Option<ObjRef>, class: ClassId) -> Result<bool, VmError> {
+    match object {
+      Some(object) => Ok(self.vm.is_assignable(self.vm.object_class(object)?, class)),
+      None => Ok(true),
+    }
+  }
+
+  pub fn is_same_object(&self, a: Option<ObjRef>, b: Option<ObjRef>) -> bool {
+    a == b
+  }
+
+  // オブジェクト
+
+  // コンストラクタを呼ばずにインスタンスを作る
+  pub fn alloc_object(&mut self, class: ClassId) -> Result<ObjRef, VmError> {
+    if self.vm.classes[class].is_interface() || self.vm.classes[class].is_abstract() {
+      return Err(VmError::java("java/lang/InstantiationException", self.vm.classes[class].java_name()));
+    }
+    self.vm.initialize(class)?;
+    let object = self.vm.instantiate(class)?;
+    Ok(self.vm.handles.new_local(object))
+  }
+
+  pub fn new_object(&mut self, class: ClassId, descriptor: &str, args: &[Value]) -> Result<ObjRef, VmError> {
+    let object = self.alloc_object(class)?;
+    self.call_nonvirtual_method(object, class, "<init>", descriptor, args)?;
+    Ok(object)
+  }
+
+  // フィールド
+
+  pub fn get_field(&self, object: ObjRef, name: &str, descriptor: &str) -> Result<Value, VmError> {
+    self.vm.get_field(object, name, descriptor)
+  }
+
+  pub fn set_field(&mut self, object: ObjRef, name: &str, descriptor: &str, value: Value) -> Result<(), VmError> {
+    self.vm.set_field(object, name, descriptor, value)
+  }
+
+  // staticフィールドの参照ではクラスを初期化する
+  pub fn get_static_field(&mut self, class: ClassId, name: &str, descriptor: &str) -> Result<Value, VmError> {
+    self.vm.initialize(class)?;
+    self.vm.get_static_field(class, name, descriptor)
+  }
+
+  pub fn set_static_field(&mut self, class: ClassId, name: &str, descriptor: &str, value: Value) -> Result<(), VmError> {
+    self.vm.initialize(class)?;
+    self.vm.set_static_field(class, name, descriptor, value)
+  }
+
+  // メソッド呼び出し
+
+  pub fn get_method(&mut self, class: ClassId, name: &str, descriptor: &str) -> Result<Rc<RuntimeMethod>, VmError> {
+    self.vm.initialize(class)?;
+    let method = self.vm.lookup_method(class, name, descriptor)?;
+    match method.is_static() {
+      true => Err(VmError::java("java/lang/NoSuchMethodError", name)),
+      false => Ok(method),
+    }
+  }
+
+  pub fn get_static_method(&mut self, class: ClassId, name: &str, descriptor: &str) -> Result<Rc<RuntimeMethod>, VmError> {
+    self.vm.initialize(class)?;
+    let method = self.vm.lookup_method(class, name, descriptor)?;
+    match method.is_static() {
+      true => Ok(method),
+      false => Err(VmError::java("java/lang/NoSuchMethodError", name)),
+    }
+  }
+
+  // レシーバのクラスでメソッドを選んで呼び出す
+  pub fn call_method(&mut self, object: ObjRef, name: &str, descriptor: &str, args: &[Value]) -> Result<Option<Value>, VmError> {
+    let class = self.vm.object_class(object)?;
+    let resolved = self.get_method(class, name, descriptor)?;
+    let method = self.vm.select_method(class, &resolved)?;
+    self.call(method, Some(object), args)
+  }
+
+  // オーバーライドを無視して、指定したクラスのメソッドを呼び出す
+  pub fn call_nonvirtual_method(&mut self, object: ObjRef, class: ClassId, name: &str, descriptor: &str, args: &[Value]) -> Result<Option<Value>, VmError> {
+    let method = self.get_method(class, name, descriptor)?;
+    self.call(method, Some(object), args)
+  }
+
+  pub fn call_static_method(&mut self, class: ClassId, name: &str, descriptor: &str, args: &[Value]) -> Result<Option<Value>, VmError> {
+    let method = self.get_static_method(class, name, descriptor)?;
+    self.call(method, None, args)
+  }
+
+  fn call(&mut self, method: Rc<RuntimeMethod>, receiver: Option<ObjRef>, args: &[Value]) -> Result<Option<Value>, VmError> {
+    if method.signature.parameters.len() != args.len() {
+      return Err(VmError::internal(format!(
+        "{} takes {} arguments, but {} were given", method.external_name(), method.signature.parameters.len(), args.len(),
+      )));
+    }
+    let args = receiver.map(Value::Ref).into_iter().chain(args.iter().copied()).collect();
+    self.vm.invoke(method, args)
+  }
+
+  // 文字列
+
+  pub fn new_string(&mut self, value: &str) -> Result<ObjRef, VmError> {
+    let string = self.vm.new_string(value)?;
+    Ok(self.vm.handles.new_local(string))
+  }
+
+  pub fn new_string_utf16(&mut self, chars: &[u16]) -> Result<ObjRef, VmError> {
+    let string = self.vm.new_string_utf16(chars)?;
+    Ok(self.vm.handles.new_local(string))
+  }
+
+  pub fn get_string_chars(&self, string: ObjRef) -> Result<Vec<u16>, VmError> {
+    self.vm.string_chars(string)
+  }
+
+  pub fn get_string_utf(&self, string: ObjRef) -> Result<String, VmError> {
+    self.vm.string_value(string)
+  }
+
+  pub fn get_string_length(&self, string: ObjRef) -> Result<usize, VmError> {
+    Ok(self.vm.string_chars(string)?.len())
+  }
+
+  // 配列
+
+  pub fn get_array_length(&self, array: ObjRef) -> Result<usize, VmError> {
+    Ok(self.vm.heap.get(array)?.array()?.len())
+  }
+
+  pub fn new_array(&mut self, component: &FieldType, length: i32) -> Result<ObjRef, VmError> {
+    let array = self.vm.new_array(component, length)?;
+    Ok(self.vm.handles.new_local(array))
+  }
+
+  // 要素をすべてinitialにした参照型の配列を作る
+  pub fn new_object_array(&mut self, length: i32, element_class: ClassId, initial: Option<ObjRef>) -> Result<ObjRef, VmError> {
+    let name = &self.vm.classes[element_class].name;
+    let component = match name.starts_with('[') {
+      true => FieldType::parse(name).map_err(VmError::internal)?,
+      false => FieldType::Object(name.clone()),
+    };
+    let array = self.new_array(&component, length)?;
+    if let Some(initial) = initial {
+      for i in 0..length {
+        self.set_object_array_element(array, i, Some(initial))?;
+      }
+    }
+    Ok(array)
+  }
+
+  pub fn get_object_array_element(&self, array: ObjRef, index: i32) -> Result<Option<ObjRef>, VmError> {
+    let index = self.vm.array_index(array, index)?;
+    self.vm.heap.get(array)?.array()?.get(index).as_ref()
+  }
+
+  pub fn set_object_array_element(&mut self, array: ObjRef, index: i32, value: Option<ObjRef>) -> Result<(), VmError> {
+    let index = self.vm.array_index(array, index)?;
+    self.vm.check_array_store(array, Value::from_ref(value))?;
+    self.vm.heap.get_mut(array)?.array_mut()?.set(index, Value::from_ref(value))
+  }
+
+  // 基本型の配列の一部を取り出す (Get<Type>ArrayRegion)
+  pub fn get_array_region(&self, array: ObjRef, start: i32, length: i32) -> Result<ArrayData, VmError> {
+    let data = self.vm.heap.get(array)?.array()?;
+    let (start, length) = region(data, start, length)?;
+    Ok(data.slice(start, length))
+  }
+
+  // 基本型の配列の一部を書き換える (Set<Type>ArrayRegion)
+  pub fn set_array_region(&mut self, array: ObjRef, start: i32, values: &ArrayData) -> Result<(), VmError> {
+    let data = self.vm.heap.get_mut(array)?.array_mut()?;
+    let (start, _) = region(data, start, values.len() as i32)?;
+    match data.copy_from(start, values) {
+      true => Ok(()),
+      false => Err(VmError::internal("Array region has a different element type")),
+    }
+  }
+
+  // 例外
+
+  // 例外を保留し、ネイティブメソッドから戻った時に投げる
+  pub fn throw(&mut self, exception: ObjRef) {
+    self.vm.pending_exception = Some(exception);
+  }
+
+  pub fn throw_new(&mut self, class_name: &str, message: Option<&str>) -> Result<(), VmError> {
+    let exception = self.vm.new_throwable(class_name, message, None)?;
+    self.throw(exception);
+    Ok(())
+  }
+
+  pub fn exception_occurred(&self) -> Option<ObjRef> {
+    self.vm.pending_exception
+  }
+
+  pub fn exception_check(&self) -> bool {
+    self.vm.pending_exception.is_some()
+  }
+
+  pub fn exception_clear(&mut self) {
+    self.vm.pending_exception = None;
+  }
+
+  pub fn fatal_error(&mut self, message: &str) -> ! {
+    eprintln!("FATAL ERROR in native method: {}", message);
+    self.vm.finish_trace();
+    self.vm.finish_jdwp();
+    self.vm.finish_profile();
+    process::exit(1)
+  }
+
+  // 参照
+
+  pub fn new_local_ref(&mut self, object: ObjRef) -> ObjRef {
+    self.vm.handles.new_local(object)
+  }
+
+  pub fn new_global_ref(&mut self, object: ObjRef) -> usize {
+    self.vm.handles.new_global(object)
+  }
+
+  pub fn global_ref(&self, index: usize) -> Option<ObjRef> {
+    self.vm.handles.global(index)
+  }
+
+  pub fn delete_global_ref(&mut self, index: usize) {
+    self.vm.handles.delete_global(index);
+  }
+
+  // ネイティブメソッドの登録 (RegisterNatives、UnregisterNatives)
+
+  pub fn register_natives(&mut self, class: &str, methods: &[(&str, &str, NativeFn)]) {
+    for (name, descriptor, function) in methods {
+      self.vm.natives.register(class, name, descriptor, *function);
+    }
+  }
+
+  pub fn unregister_natives(&mut self, class: &str) {
+    self.vm.natives.unregister(class);
+  }
+}
+
+fn region(data: &ArrayData, start: i32, length: i32) -> Result<(usize, usize), VmError> {
+  if start < 0 || length < 0 || start as i64 + length as i64 > data.len() as i64 {
+    return Err(VmError::java(
+      "java/lang/ArrayIndexOutOfBoundsException",
+      format!("Array region {}..{} out of bounds for length {}", start, start as i64 + length as i64, data.len()),
+    ));
+  }
+  Ok((start as usize, length as usize))
+}
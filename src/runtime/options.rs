@@ -0,0 +1,240 @@
+use std::{collections::HashMap, path::{Path, PathBuf}};
+
+use crate::{
+  class_leader,
+  util::class_path::{ClassPathOptions, split_paths},
+};
+
+#[derive(Debug, Clone)]
+pub struct VmOptions {
+  pub class_path: Vec<PathBuf>,
+  // 指定された場合は組み込みのクラスライブラリを使わず、ここからjava.baseのクラスを読み込む
+  pub boot_class_path: Option<Vec<PathBuf>>,
+  pub max_heap: usize,
+  pub verbose_gc: bool,
+  // -D<名前>=<値>で指定したシステムプロパティ
+  pub properties: HashMap<String, String>,
+  // --traceで実行した命令を記録する
+  pub trace: Option<TraceOptions>,
+  // -agentlib:jdwpでデバッガと接続する
+  pub jdwp: Option<JdwpOptions>,
+  // --profileで実行したメソッドや命令、確保したオブジェクトを集計する
+  pub profile: Option<ProfileOptions>,
+  // 呼び出し回数が閾値を超えたメソッドを機械語にコンパイルする (-Xintで無効にする)
+  pub jit: Option<JitOptions>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TraceOptions {
+  // --trace-filter=<パターン>で記録するクラスやメソッドを絞り込む ("*"は任意の文字列)
+  pub filters: Vec<String>,
+  // --trace-file=<パス>でテキストの代わりにバイナリのトレースファイルに書き出す
+  pub file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProfileOptions {
+  // --profile-collapsed=<パス>で折りたたみスタック (flamegraph形式) を書き出す
+  pub collapsed: Option<PathBuf>,
+  // --profile-json=<パス>でJSONの集計を書き出す
+  pub json: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct JitOptions {
+  // -XX:CompileThreshold=<回数>でコンパイルするまでの呼び出し回数を変える
+  pub threshold: u32,
+  // -XX:+PrintCompilationでコンパイルしたメソッドを表示する
+  pub print: bool,
+}
+
+impl Default for JitOptions {
+  fn default() -> Self {
+    JitOptions { threshold: 1000, print: false }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct JdwpOptions {
+  // [<ホスト>:]<ポート> (ポートが0なら空いているポートで待つ)
+  pub address: String,
+  // yならデバッガからの接続を待ち、nならデバッガに接続する
+  pub server: bool,
+  // yなら接続してデバッガが再開するまでmainを実行しない
+  pub suspend: bool,
+}
+
+impl Default for VmOptions {
+  fn default() -> Self {
+    VmOptions {
+      class_path: vec![PathBuf::from(".")],
+      boot_class_path: None,
+      max_heap: 256 * 1024 * 1024,
+      verbose_gc: false,
+      properties: HashMap::new(),
+      trace: None,
+      jdwp: None,
+      profile: None,
+      jit: Some(JitOptions::default()),
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct LaunchOptions {
+  pub vm: VmOptions,
+  pub main_class: String,
+  pub args: Vec<String>,
+}
+
+pub fn parse_launch_options(args: &[String]) -> Result<LaunchOptions, String> {
+  let mut vm = VmOptions::default();
+  let mut class_path_options = ClassPathOptions::default();
+  let mut i = 0;
+  while i < args.len() {
+    let used = class_path_options.parse_arg(&args[i..])?;
+    if used > 0 {
+      i += used;
+      continue;
+    }
+    let arg = &args[i];
+    match arg.as_str() {
+      "-verbose:gc" | "-Xlog:gc" => {
+        vm.verbose_gc = true;
+        i += 1;
+      },
+      "--trace" => {
+        vm.trace.get_or_insert_with(TraceOptions::default);
+        i += 1;
+      },
+      _ if arg.starts_with("--trace-filter=") => {
+        let trace = vm.trace.get_or_insert_with(TraceOptions::default);
+        trace.filters.extend(arg["--trace-filter=".len()..].split(',').filter(|filter| !filter.is_empty()).map(String::from));
+        i += 1;
+      },
+      _ if arg.starts_with("--trace-file=") => {
+        vm.trace.get_or_insert_with(TraceOptions::default).file = Some(PathBuf::from(&arg["--trace-file=".len()..]));
+        i += 1;
+      },
+      "--profile" => {
+        vm.profile.get_or_insert_with(ProfileOptions::default);
+        i += 1;
+      },
+      _ if arg.starts_with("--profile-collapsed=") => {
+        vm.profile.get_or_insert_with(ProfileOptions::default).collapsed = Some(PathBuf::from(&arg["--profile-collapsed=".len()..]));
+        i += 1;
+      },
+      _ if arg.starts_with("--profile-json=") => {
+        vm.profile.get_or_insert_with(ProfileOptions::default).json = Some(PathBuf::from(&arg["--profile-json=".len()..]));
+        i += 1;
+      },
+      "-Xint" => {
+        vm.jit = None;
+        i += 1;
+      },
+      "-XX:+PrintCompilation" => {
+        if let Some(jit) = &mut vm.jit {
+          jit.print = true;
+        }
+        i += 1;
+      },
+      _ if arg.starts_with("-XX:CompileThreshold=") => {
+        let threshold = arg["-XX:CompileThreshold=".len()..].parse().map_err(|_| format!("Invalid compile threshold: {}", arg))?;
+        if let Some(jit) = &mut vm.jit {
+          jit.threshold = threshold;
+        }
+        i += 1;
+      },
+      _ if arg.starts_with("-agentlib:jdwp=") => {
+        vm.jdwp = Some(parse_jdwp_options(&arg["-agentlib:jdwp=".len()..])?);
+        i += 1;
+      },
+      _ if arg.starts_with("-D") => {
+        let (key, value) = arg[2..].split_once('=').unwrap_or((&arg[2..], ""));
+        vm.properties.insert(key.to_string(), value.to_string());
+        i += 1;
+      },
+      _ if arg.starts_with("-Xmx") => {
+        vm.max_heap = parse_size(&arg[4..]).ok_or(format!("Invalid heap size: {}", arg))?;
+        i += 1;
+      },
+      _ if arg.starts_with('-') => return Err(format!("Unknown option: {}", arg)),
+      _ => break,
+    }
+  }
+  let target = args.get(i).ok_or("Main class is not specified")?;
+  if let Some(class_path) = &class_path_options.class_path {
+    vm.class_path = split_paths(class_path);
+  }
+  vm.boot_class_path = class_path_options.boot_class_path.as_deref().map(split_paths);
+
+  let main_class = if target.ends_with(".class") {
+    // クラスファイルのパスが指定された場合は、パッケージ階層を遡ってクラスパスに加える
+    let (name, root) = class_file_root(Path::new(target))?;
+    vm.class_path.insert(0, root);
+    name
+  } else {
+    target.replace('.', "/")
+  };
+
+  Ok(LaunchOptions {
+    vm,
+    main_class,
+    args: args[i + 1..].to_vec(),
+  })
+}
+
+// transport=dt_socket,server=y,suspend=n,address=5005
+fn parse_jdwp_options(value: &str) -> Result<JdwpOptions, String> {
+  let mut address = None;
+  let mut server = false;
+  let mut suspend = true;
+  for option in value.split(',') {
+    let (key, value) = option.split_once('=').ok_or(format!("Invalid JDWP option: {}", option))?;
+    let flag = || match value {
+      "y" => Ok(true),
+      "n" => Ok(false),
+      _ => Err(format!("Invalid JDWP option: {}", option)),
+    };
+    match key {
+      "transport" if value == "dt_socket" => {},
+      "transport" => return Err(format!("Unsupported JDWP transport: {}", value)),
+      "server" => server = flag()?,
+      "suspend" => suspend = flag()?,
+      "address" => address = Some(value.to_string()),
+      _ => return Err(format!("Unknown JDWP option: {}", key)),
+    }
+  }
+  let address = address.ok_or("JDWP address is not specified")?;
+  Ok(JdwpOptions { address, server, suspend })
+}
+
+// -Xmxの値 (例: 64m, 512k, 1g, 1048576)
+fn parse_size(value: &str) -> Option<usize> {
+  let (digits, unit) = match value.char_indices().last()? {
+    (i, c) if c.is_ascii_alphabetic() => (&value[..i], c.to_ascii_lowercase()),
+    _ => (value, 'b'),
+  };
+  let multiplier = match unit {
+    'b' => 1,
+    'k' => 1024,
+    'm' => 1024 * 1024,
+    'g' => 1024 * 1024 * 1024,
+    _ => return None,
+  };
+  digits.parse::<usize>().ok()?.checked_mul(multiplier).filter(|&size| size > 0)
+}
+
+fn class_file_root(path: &Path) -> Result<(String, PathBuf), String> {
+  let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+  let class_file = class_leader::parse_bytes(&bytes).map_err(|e| format!("{}: {}", path.display(), e))?;
+  let name = class_file.constant_pool.get_class_name(class_file.this_class)?;
+  let mut root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+  for _ in 1..name.split('/').count() {
+    root = root.parent().map(Path::to_path_buf).unwrap_or_default();
+  }
+  if root.as_os_str().is_empty() {
+    root = PathBuf::from(".");
+  }
+  Ok((name, root))
+}
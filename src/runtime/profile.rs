@@ -0,0 +1,445 @@
+use std::{
+  collections::HashMap,
+  fmt::Write as _,
+  fs::File,
+  io::{self, BufWriter, Write},
+  time::{Duration, Instant},
+};
+
+use crate::{
+  runtime::{
+    class::{ClassId, RuntimeMethod},
+    options::ProfileOptions,
+    scheduler::ThreadId,
+    trace::instruction_text,
+    vm::Vm,
+  },
+  util::json,
+};
+
+// この命令数ごとに実行中のスレッドの呼び出しスタックを記録する
+const SAMPLE_INTERVAL: u64 = 100;
+// テキストの報告に表示する件数
+const TOP_ENTRIES: usize = 20;
+
+#[derive(Debug, Default, Clone)]
+struct MethodStats {
+  invocations: u64,
+  // 再帰呼び出しの内側の時間は重ねて数えない
+  inclusive: Duration,
+  exclusive: Duration,
+}
+
+// 呼び出しスタックの木の節 (根からたどったメソッドIDの並びが1つの呼び出しスタック)
+struct StackNode {
+  parent: Option<usize>,
+  method: usize,
+  // このスタックで自身を実行していた時間
+  exclusive: Duration,
+}
+
+// 実行中の呼び出し (ネイティブメソッドも含む)
+struct Call {
+  method: usize,
+  // 呼び出しスタックの木の節
+  node: usize,
+  start: Instant,
+  children: Duration,
+  // 同じメソッドの呼び出しの中で一番外側
+  outermost: bool,
+}
+
+#[derive(Default)]
+struct ThreadCalls {
+  calls: Vec<Call>,
+  // メソッドIDごとの実行中の呼び出しの数
+  active: HashMap<usize, u32>,
+}
+
+pub struct Profiler {
+  options: ProfileOptions,
+  start: Instant,
+  bytecodes: u64,
+  // メソッドIDごとの統計と、pcごとの実行回数
+  methods: Vec<MethodStats>,
+  hits: Vec<Vec<u64>>,
+  // クラスごとの確保したオブジェクトの数とバイト数
+  allocations: HashMap<ClassId, (u64, u64)>,
+  // 呼び出しスタック (外側からのメソッドID) ごとのサンプル数
+  stacks: HashMap<Vec<usize>, u64>,
+  samples: u64,
+  // 全ての呼び出しスタックの木と、(親の節, メソッドID) から子の節への索引
+  nodes: Vec<StackNode>,
+  node_index: HashMap<(Option<usize>, usize), usize>,
+  threads: HashMap<ThreadId, ThreadCalls>,
+}
+
+impl Profiler {
+  pub fn new(options: &ProfileOptions) -> Profiler {
+    Profiler {
+      options: options.clone(),
+      start: Instant::now(),
+      bytecodes: 0,
+      methods: Vec::new(),
+      hits: Vec::new(),
+      allocations: HashMap::new(),
+      stacks: HashMap::new(),
+      samples: 0,
+      nodes: Vec::new(),
+      node_index: HashMap::new(),
+      threads: HashMap::new(),
+    }
+  }
+
+  fn stats(&mut self, method: usize) -> &mut MethodStats {
+    if self.methods.len() <= method {
+      self.methods.resize(method + 1, MethodStats::default());
+    }
+    &mut self.methods[method]
+  }
+
+  fn node(&mut self, parent: Option<usize>, method: usize) -> usize {
+    let nodes = &mut self.nodes;
+    *self.node_index.entry((parent, method)).or_insert_with(|| {
+      nodes.push(StackNode { parent, method, exclusive: Duration::ZERO });
+      nodes.len() - 1
+    })
+  }
+
+  fn enter(&mut self, thread: ThreadId, method: usize) {
+    self.stats(method).invocations += 1;
+    let parent = self.threads.get(&thread).and_then(|calls| calls.calls.last()).map(|call| call.node);
+    let node = self.node(parent, method);
+    let calls = self.threads.entry(thread).or_default();
+    let active = calls.active.entry(method).or_default();
+    *active += 1;
+    calls.calls.push(Call { method, node, start: Instant::now(), children: Duration::ZERO, outermost: *active == 1 });
+  }
+
+  fn exit(&mut self, thread: ThreadId, end: Instant) {
+    let Some(calls) = self.threads.get_mut(&thread) else {
+      return;
+    };
+    let Some(call) = calls.calls.pop() else {
+      return;
+    };
+    if let Some(active) = calls.active.get_mut(&call.method) {
+      *active -= 1;
+    }
+    let elapsed = end.saturating_duration_since(call.start);
+    if let Some(caller) = calls.calls.last_mut() {
+      caller.children += elapsed;
+    }
+    let exclusive = elapsed.saturating_sub(call.children);
+    self.nodes[call.node].exclusive += exclusive;
+    let stats = self.stats(call.method);
+    if call.outermost {
+      stats.inclusive += elapsed;
+    }
+    stats.exclusive += exclusive;
+  }
+
+  fn hit(&mut self, method: usize, pc: usize, code_length: usize) {
+    if self.hits.len() <= method {
+      self.hits.resize(method + 1, Vec::new());
+    }
+    let hits = &mut self.hits[method];
+    if hits.is_empty() {
+      hits.resize(code_length, 0);
+    }
+    if let Some(count) = hits.get_mut(pc) {
+      *count += 1;
+    }
+  }
+
+  fn sample(&mut self, thread: ThreadId) {
+    let Some(calls) = self.threads.get(&thread) else {
+      return;
+    };
+    let stack: Vec<usize> = calls.calls.iter().map(|call| call.method).collect();
+    *self.stacks.entry(stack).or_default() += 1;
+    self.samples += 1;
+  }
+
+  // 終了していない呼び出しを全て終わらせる
+  fn close(&mut self) {
+    let end = Instant::now();
+    let threads: Vec<ThreadId> = self.threads.keys().copied().collect();
+    for thread in threads {
+      while self.threads.get(&thread).is_some_and(|calls| !calls.calls.is_empty()) {
+        self.exit(thread, end);
+      }
+    }
+  }
+}
+
+// 折りたたみスタックのフレーム名 (";"を含まないように、記述子でなくJavaの型名で引数を表す)
+fn frame_name(method: &RuntimeMethod) -> String {
+  let parameters: Vec<String> = method.signature.parameters.iter().map(|p| p.to_string()).collect();
+  format!("{}.{}({})", method.class_name.replace('/', "."), method.name, parameters.join(", "))
+}
+
+fn method_label(method: &RuntimeMethod) -> String {
+  format!("{}.{}{}", method.class_name.replace('/', "."), method.name, method.descriptor)
+}
+
+fn millis(duration: Duration) -> f64 {
+  duration.as_secs_f64() * 1000.0
+}
+
+impl Vm {
+  // 呼び出しの開始と終了 (フレームを積んだ時と取り除いた時、ネイティブメソッドの前後で呼ぶ)
+  pub(crate) fn profile_enter(&mut self, method: &RuntimeMethod) {
+    let thread = self.threads.current;
+    if let Some(profiler) = &mut self.profiler {
+      profiler.enter(thread, method.id);
+    }
+  }
+
+  pub(crate) fn profile_exit(&mut self) {
+    let thread = self.threads.current;
+    if let Some(profiler) = &mut self.profiler {
+      profiler.exit(thread, Instant::now());
+    }
+  }
+
+  pub(crate) fn profile_allocation(&mut self, class: ClassId, size: usize) {
+    if let Some(profiler) = &mut self.profiler {
+      let entry = profiler.allocations.entry(class).or_default();
+      entry.0 += 1;
+      entry.1 += size as u64;
+    }
+  }
+
+  // 他のスレッドに実行権を譲っていた時間は、呼び出しの時間に含めない
+  pub(crate) fn profile_resume(&mut self, yielded: Instant) {
+    let thread = self.threads.current;
+    if let Some(profiler) = &mut self.profiler
+      && let Some(calls) = profiler.threads.get_mut(&thread)
+    {
+      let paused = yielded.elapsed();
+      for call in &mut calls.calls {
+        call.start += paused;
+      }
+    }
+  }
+
+  // スレッドが終了した時に、残っている呼び出しを終わらせる
+  pub(crate) fn profile_thread_end(&mut self) {
+    let thread = self.threads.current;
+    if let Some(profiler) = &mut self.profiler {
+      let end = Instant::now();
+      while profiler.threads.get(&thread).is_some_and(|calls| !calls.calls.is_empty()) {
+        profiler.exit(thread, end);
+      }
+      profiler.threads.remove(&thread);
+    }
+  }
+
+  // 命令を実行する前に呼ぶ
+  pub(crate) fn profile_before(&mut self) {
+    let thread = self.threads.current;
+    let (Some(profiler), Some(frame)) = (&mut self.profiler, self.frames.last()) else {
+      return;
+    };
+    let code_length = frame.method.code().map_or(0, |code| code.bytes.len());
+    profiler.hit(frame.method.id, frame.pc, code_length);
+    profiler.bytecodes += 1;
+    if profiler.bytecodes.is_multiple_of(SAMPLE_INTERVAL) {
+      profiler.sample(thread);
+    }
+  }
+
+  // プロセスを終了する前に呼び、プロファイルを書き出す
+  pub fn finish_profile(&mut self) {
+    let Some(mut profiler) = self.profiler.take() else {
+      return;
+    };
+    profiler.close();
+    let duration = profiler.start.elapsed();
+    let mut outputs = Vec::new();
+    if let Some(path) = &profiler.options.collapsed {
+      outputs.push((path.clone(), self.collapsed_stacks(&profiler)));
+    }
+    if let Some(path) = &profiler.options.json {
+      outputs.push((path.clone(), self.profile_json(&profiler, duration)));
+    }
+    if outputs.is_empty() {
+      let _ = io::stdout().flush();
+      eprint!("{}", self.profile_report(&profiler, duration));
+    }
+    for (path, text) in outputs {
+      let result = File::create(&path).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        writer.write_all(text.as_bytes())?;
+        writer.flush()
+      });
+      if let Err(e) = result {
+        eprintln!("Error: failed to write profile {}: {}", path.display(), e);
+      }
+    }
+  }
+
+  // 呼び出しがあったメソッドを、自身の時間の長い順に並べる
+  fn profiled_methods(&self, profiler: &Profiler) -> Vec<(usize, MethodStats)> {
+    let mut methods: Vec<(usize, MethodStats)> = profiler.methods.iter().cloned().enumerate()
+      .filter(|(id, stats)| stats.invocations > 0 && *id < self.methods.len())
+      .collect();
+    methods.sort_by(|a, b| b.1.exclusive.cmp(&a.1.exclusive).then(a.0.cmp(&b.0)));
+    methods
+  }
+
+  fn profiled_allocations(&self, profiler: &Profiler) -> Vec<(ClassId, u64, u64)> {
+    let mut allocations: Vec<(ClassId, u64, u64)> = profiler.allocations.iter().map(|(&class, &(count, bytes))| (class, count, bytes)).collect();
+    allocations.sort_by(|a, b| b.2.cmp(&a.2).then(b.1.cmp(&a.1)).then(a.0.cmp(&b.0)));
+    allocations
+  }
+
+  // 実行回数の多い命令 (メソッドID, pc, 回数)
+  fn hot_bytecodes(&self, profiler: &Profiler) -> Vec<(usize, usize, u64)> {
+    let mut hits: Vec<(usize, usize, u64)> = profiler.hits.iter().enumerate()
+      .flat_map(|(method, hits)| hits.iter().enumerate().filter(|(_, count)| **count > 0).map(move |(pc, &count)| (method, pc, count)))
+      .collect();
+    hits.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)).then(a.1.cmp(&b.1)));
+    hits
+  }
+
+  fn profile_instruction(&self, method: &RuntimeMethod, pc: usize) -> String {
+    let Some(code) = method.code() else {
+      return String::new();
+    };
+    let class_file = self.class_file(method.class).ok();
+    instruction_text(&code.bytes, pc, class_file.as_ref().map(|class_file| &class_file.constant_pool))
+  }
+
+  fn profile_report(&self, profiler: &Profiler, duration: Duration) -> String {
+    let mut text = String::new();
+    let _ = writeln!(
+      text,
+      "[profile] {:.3}ms, {} bytecodes executed, {} samples (every {} bytecodes)",
+      millis(duration), profiler.bytecodes, profiler.samples, SAMPLE_INTERVAL,
+    );
+    let _ = writeln!(text, "\n{:>12} {:>12} {:>10}  method", "self ms", "total ms", "calls");
+    for (id, stats) in self.profiled_methods(profiler).into_iter().take(TOP_ENTRIES) {
+      let _ = writeln!(
+        text,
+        "{:>12.3} {:>12.3} {:>10}  {}",
+        millis(stats.exclusive), millis(stats.inclusive), stats.invocations, method_label(&self.methods[id]),
+      );
+    }
+    let _ = writeln!(text, "\n{:>12}  bytecode", "hits");
+    for (id, pc, count) in self.hot_bytecodes(profiler).into_iter().take(TOP_ENTRIES) {
+      let method = &self.methods[id];
+      let _ = writeln!(text, "{:>12}  {} {}: {}", count, method_label(method), pc, self.profile_instruction(method, pc));
+    }
+    let _ = writeln!(text, "\n{:>12} {:>12}  class", "objects", "bytes");
+    for (class, count, bytes) in self.profiled_allocations(profiler).into_iter().take(TOP_ENTRIES) {
+      let _ = writeln!(text, "{:>12} {:>12}  {}", count, bytes, self.classes[class].java_name());
+    }
+    text
+  }
+
+  // flamegraph.plなどで読める形式 ("外側;...;内側 ナノ秒")
+  // サンプルではなく、呼び出しスタックごとに計った自身の時間を使うので、短いプログラムでも空にならない
+  fn collapsed_stacks(&self, profiler: &Profiler) -> String {
+    let mut lines: HashMap<String, u128> = HashMap::new();
+    for node in profiler.nodes.iter().filter(|node| !node.exclusive.is_zero()) {
+      let mut frames = vec![frame_name(&self.methods[node.method])];
+      let mut parent = node.parent;
+      while let Some(index) = parent {
+        frames.push(frame_name(&self.methods[profiler.nodes[index].method]));
+        parent = profiler.nodes[index].parent;
+      }
+      frames.reverse();
+      // 戻り値の型だけが違うメソッド (ブリッジメソッドなど) は同じ表記になるのでまとめる
+      *lines.entry(frames.join(";")).or_default() += node.exclusive.as_nanos();
+    }
+    let mut lines: Vec<(String, u128)> = lines.into_iter().collect();
+    lines.sort();
+    lines.into_iter().map(|(stack, nanos)| format!("{} {}\n", stack, nanos)).collect()
+  }
+
+  fn profile_json(&self, profiler: &Profiler, duration: Duration) -> String {
+    let mut json = String::from("{\n");
+    let _ = writeln!(json, "  \"durationNanos\": {},", duration.as_nanos());
+    let _ = writeln!(json, "  \"bytecodes\": {},", profiler.bytecodes);
+    let _ = writeln!(json, "  \"sampleInterval\": {},", SAMPLE_INTERVAL);
+    let _ = writeln!(json, "  \"samples\": {},", profiler.samples);
+    json.push_str("  \"methods\": [");
+    for (i, (id, stats)) in self.profiled_methods(profiler).into_iter().enumerate() {
+      let method = &self.methods[id];
+      let _ = write!(
+        json,
+        "{}\n    {{\"class\": {}, \"name\": {}, \"descriptor\": {}, \"invocations\": {}, \"inclusiveNanos\": {}, \"exclusiveNanos\": {}, \"bytecodes\": [",
+        if i == 0 { "" } else { "," },
+        json::string(&method.class_name.replace('/', ".")),
+        json::string(&method.name),
+        json::string(&method.descriptor),
+        stats.invocations,
+        stats.inclusive.as_nanos(),
+        stats.exclusive.as_nanos(),
+      );
+      let hits = profiler.hits.get(id).map_or(&[][..], |hits| &hits[..]);
+      let mut first = true;
+      for (pc, &count) in hits.iter().enumerate().filter(|(_, count)| **count > 0) {
+        let _ = write!(
+          json,
+          "{}{{\"pc\": {}, \"hits\": {}, \"instruction\": {}}}",
+          if first { "" } else { ", " },
+          pc, count, json::string(&self.profile_instruction(method, pc)),
+        );
+        first = false;
+      }
+      json.push_str("]}");
+    }
+    json.push_str("\n  ],\n  \"allocations\": [");
+    for (i, (class, count, bytes)) in self.profiled_allocations(profiler).into_iter().enumerate() {
+      let _ = write!(
+        json,
+        "{}\n    {{\"class\": {}, \"objects\": {}, \"bytes\": {}}}",
+        if i == 0 { "" } else { "," },
+        json::string(&self.classes[class].java_name()), count, bytes,
+      );
+    }
+    json.push_str("\n  ],\n  \"stackSamples\": [");
+    let mut stacks: Vec<(&Vec<usize>, u64)> = profiler.stacks.iter().map(|(stack, &count)| (stack, count)).collect();
+    stacks.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    for (i, (stack, count)) in stacks.into_iter().enumerate() {
+      // JFRと同じく内側のフレームから並べる
+      let frames: Vec<String> = stack.iter().rev().map(|&id| json::string(&method_label(&self.methods[id]))).collect();
+      let _ = write!(
+        json,
+        "{}\n    {{\"frames\": [{}], \"samples\": {}}}",
+        if i == 0 { "" } else { "," },
+        frames.join(", "), count,
+      );
+    }
+    json.push_str("\n  ]\n}\n");
+    json
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn every_call_stack_gets_its_own_node() {
+    let mut profiler = Profiler::new(&ProfileOptions::default());
+    // main -> a -> b、main -> b、別のスレッドでb
+    profiler.enter(1, 0);
+    profiler.enter(1, 1);
+    profiler.enter(1, 2);
+    profiler.exit(1, Instant::now());
+    profiler.exit(1, Instant::now());
+    profiler.enter(1, 2);
+    profiler.exit(1, Instant::now());
+    profiler.enter(2, 2);
+    profiler.close();
+    let stacks: Vec<(Option<usize>, usize)> = profiler.nodes.iter().map(|node| (node.parent, node.method)).collect();
+    assert_eq!(stacks, [(None, 0), (Some(0), 1), (Some(1), 2), (Some(0), 2), (None, 2)]);
+    assert_eq!(profiler.methods[2].invocations, 3);
+    let total: Duration = profiler.nodes.iter().map(|node| node.exclusive).sum();
+    assert_eq!(total, profiler.methods.iter().map(|stats| stats.exclusive).sum());
+  }
+}
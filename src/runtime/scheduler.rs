@@ -0,0 +1,518 @@
+use std::{
+  collections::{BTreeMap, HashMap, VecDeque},
+  io::{self, Write},
+  mem,
+  process,
+  sync::{Arc, Condvar, Mutex, MutexGuard},
+  thread,
+  time::Instant,
+};
+
+use crate::runtime::{
+  error::VmError,
+  frame::Frame,
+  monitor::LockKey,
+  value::{ObjRef, Value},
+  vm::Vm,
+};
+
+// スレッドID (JavaのThread.getId()と同じ値)
+pub type ThreadId = i64;
+
+pub const MAIN_THREAD_ID: ThreadId = 1;
+
+// この命令数ごとに、実行を待っているスレッドに実行権を譲る
+const TIME_SLICE: u32 = 1000;
+
+const THREAD_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+// 止まっているスレッドがどれも起こされる見込みがない
+pub struct Deadlock;
+
+#[derive(Default)]
+struct SchedulerState {
+  // 実行権を持っているスレッド
+  running: Option<ThreadId>,
+  ready: VecDeque<ThreadId>,
+  // 起こされるまで止まっているスレッドと、タイムアウトの時刻
+  parked: HashMap<ThreadId, Option<Instant>>,
+}
+
+// Javaのスレッドはそれぞれ別のOSスレッドで動かし、Vmを操作できるのは実行権を持つ1つのスレッドだけにする
+#[derive(Default)]
+pub struct Scheduler {
+  state: Mutex<SchedulerState>,
+  condvar: Condvar,
+}
+
+impl Scheduler {
+  fn lock(&self) -> MutexGuard<'_, SchedulerState> {
+    self.state.lock().unwrap_or_else(|e| e.into_inner())
+  }
+
+  // 実行権を次のスレッドに渡す
+  fn hand_off(&self, state: &mut SchedulerState) {
+    state.running = state.ready.pop_front();
+    self.condvar.notify_all();
+  }
+
+  fn wait_in(&self, mut state: MutexGuard<'_, SchedulerState>, me: ThreadId) {
+    while state.running != Some(me) {
+      state = match state.parked.get(&me).copied().flatten() {
+        Some(deadline) if Instant::now() >= deadline => {
+          state.parked.remove(&me);
+          match state.running {
+            None => state.running = Some(me),
+            Some(_) => state.ready.push_back(me),
+          }
+          continue;
+        },
+        Some(deadline) => {
+          let timeout = deadline - Instant::now();
+          self.condvar.wait_timeout(state, timeout).unwrap_or_else(|e| e.into_inner()).0
+        },
+        None => self.condvar.wait(state).unwrap_or_else(|e| e.into_inner()),
+      };
+    }
+  }
+
+  pub fn wait_for_turn(&self, me: ThreadId) {
+    self.wait_in(self.lock(), me);
+  }
+
+  pub fn has_ready(&self) -> bool {
+    !self.lock().ready.is_empty()
+  }
+
+  // 新しいスレッドを実行待ちにする
+  pub fn add(&self, id: ThreadId) {
+    self.lock().ready.push_back(id);
+  }
+
+  pub fn unpark(&self, id: ThreadId) {
+    let mut state = self.lock();
+    if state.parked.remove(&id).is_some() {
+      state.ready.push_back(id);
+    }
+  }
+
+  pub fn yield_now(&self, me: ThreadId) {
+    let mut state = self.lock();
+    if state.ready.is_empty() {
+      return;
+    }
+    state.ready.push_back(me);
+    self.hand_off(&mut state);
+    self.wait_in(state, me);
+  }
+
+  // unparkされるかdeadlineを過ぎるまで止まる
+  pub fn park(&self, me: ThreadId, deadline: Option<Instant>) -> Result<(), Deadlock> {
+    let mut state = self.lock();
+    state.parked.insert(me, deadline);
+    self.hand_off(&mut state);
+    if Self::deadlocked(&state) {
+      state.parked.remove(&me);
+      state.running = Some(me);
+      return Err(Deadlock);
+    }
+    self.wait_in(state, me);
+    Ok(())
+  }
+
+  // スレッドの終了 (デッドロックが見つかれば実行権を持ったまま返る)
+  pub fn exit(&self, me: ThreadId) -> Result<(), Deadlock> {
+    let mut state = self.lock();
+    self.hand_off(&mut state);
+    if Self::deadlocked(&state) {
+      state.running = Some(me);
+      return Err(Deadlock);
+    }
+    Ok(())
+  }
+
+  fn deadlocked(state: &SchedulerState) -> bool {
+    state.running.is_none() && !state.parked.is_empty() && state.parked.values().all(Option::is_none)
+  }
+}
+
+// スレッドの状態 (スレッドダンプに表示する)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadState {
+  Runnable,
+  Blocked(LockKey),
+  Waiting(Option<LockKey>),
+  TimedWaiting(Option<LockKey>),
+  Sleeping,
+}
+
+impl ThreadState {
+  pub fn description(self) -> &'static str {
+    match self {
+      ThreadState::Runnable => "RUNNABLE",
+      ThreadState::Blocked(_) => "BLOCKED (on object monitor)",
+      ThreadState::Waiting(_) => "WAITING (on object monitor)",
+      ThreadState::TimedWaiting(_) => "TIMED_WAITING (on object monitor)",
+      ThreadState::Sleeping => "TIMED_WAITING (sleeping)",
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct JavaThread {
+  // mainスレッドのオブジェクトはThread.currentThread()で初めて作る
+  pub object: Option<ObjRef>,
+  pub daemon: bool,
+  pub state: ThreadState,
+  // join()で終了を待っているスレッド
+  joiners: Vec<ThreadId>,
+}
+
+// 実行権を持っていないスレッドの実行状態
+#[derive(Default)]
+struct ThreadContext {
+  frames: Vec<Frame>,
+  locals: Vec<Option<ObjRef>>,
+  local_frames: Vec<usize>,
+  jni_error: Option<VmError>,
+  pending_exception: Option<ObjRef>,
+  entry_result: Option<Value>,
+}
+
+pub struct Threads {
+  scheduler: Arc<Scheduler>,
+  pub current: ThreadId,
+  pub table: BTreeMap<ThreadId, JavaThread>,
+  contexts: HashMap<ThreadId, ThreadContext>,
+  next_id: ThreadId,
+  // 名前を指定しなかったスレッドの番号 (Thread-0, Thread-1, ...)
+  next_number: i32,
+  steps: u32,
+}
+
+impl Default for Threads {
+  fn default() -> Self {
+    let scheduler = Scheduler::default();
+    scheduler.lock().running = Some(MAIN_THREAD_ID);
+    let main = JavaThread { object: None, daemon: false, state: ThreadState::Runnable, joiners: Vec::new() };
+    Threads {
+      scheduler: Arc::new(scheduler),
+      current: MAIN_THREAD_ID,
+      table: BTreeMap::from([(MAIN_THREAD_ID, main)]),
+      contexts: HashMap::new(),
+      next_id: MAIN_THREAD_ID + 1,
+      next_number: 0,
+      steps: 0,
+    }
+  }
+}
+
+impl Threads {
+  pub fn new_id(&mut self) -> ThreadId {
+    let id = self.next_id;
+    self.next_id += 1;
+    id
+  }
+
+  pub fn new_name(&mut self) -> String {
+    let name = format!("Thread-{}", self.next_number);
+    self.next_number += 1;
+    name
+  }
+
+  // 実行権を持っていないスレッドのフレームや参照 (GCのルートになる)
+  pub fn roots(&self) -> impl Iterator<Item = ObjRef> + '_ {
+    let contexts = self.contexts.values().flat_map(|context| {
+      context.frames.iter()
+        .flat_map(|frame| frame.locals.iter().chain(frame.stack.iter()))
+        .chain(context.entry_result.iter())
+        .filter_map(|value| match value {
+          Value::Ref(reference) => Some(*reference),
+          _ => None,
+        })
+        .chain(context.locals.iter().flatten().copied())
+        .chain(context.pending_exception)
+    });
+    contexts.chain(self.table.values().filter_map(|thread| thread.object))
+  }
+}
+
+// 別のOSスレッドにVmを渡す (実行権を持っている間だけ操作する)
+struct VmPointer(*mut Vm);
+
+unsafe impl Send for VmPointer {}
+
+impl VmPointer {
+  fn get(self) -> *mut Vm {
+    self.0
+  }
+}
+
+impl Vm {
+  fn save_context(&mut self) {
+    let context = ThreadContext {
+      frames: mem::take(&mut self.frames),
+      locals: self.handles.replace_locals(Vec::new()),
+      local_frames: mem::take(&mut self.jni.local_frames),
+      jni_error: self.jni.error.take(),
+      pending_exception: self.pending_exception.take(),
+      entry_result: self.entry_result.take(),
+    };
+    self.threads.contexts.insert(self.threads.current, context);
+  }
+
+  fn restore_context(&mut self, id: ThreadId) {
+    let context = self.threads.contexts.remove(&id).unwrap_or_default();
+    self.frames = context.frames;
+    self.handles.replace_locals(context.locals);
+    self.jni.local_frames = context.local_frames;
+    self.jni.error = context.jni_error;
+    self.pending_exception = context.pending_exception;
+    self.entry_result = context.entry_result;
+    self.threads.current = id;
+  }
+
+  // 命令を実行するごとに呼び、一定の命令数ごとに他のスレッドに実行権を譲る
+  pub(crate) fn tick(&mut self) {
+    self.threads.steps += 1;
+    if self.threads.steps >= TIME_SLICE {
+      self.threads.steps = 0;
+      self.yield_thread();
+    }
+  }
+
+  pub(crate) fn yield_thread(&mut self) {
+    if self.threads.table.len() <= 1 || !self.threads.scheduler.has_ready() {
+      return;
+    }
+    let me = self.threads.current;
+    let scheduler = self.threads.scheduler.clone();
+    let yielded = Instant::now();
+    self.save_context();
+    scheduler.yield_now(me);
+    self.restore_context(me);
+    if self.profiler.is_some() {
+      self.profile_resume(yielded);
+    }
+  }
+
+  // unpark_threadされるかdeadlineを過ぎるまで、実行権を手放して止まる
+  pub(crate) fn park(&mut self, deadline: Option<Instant>) {
+    let me = self.threads.current;
+    let scheduler = self.threads.scheduler.clone();
+    self.save_context();
+    let result = scheduler.park(me, deadline);
+    self.restore_context(me);
+    if result.is_err() {
+      self.abort_deadlock();
+    }
+  }
+
+  pub(crate) fn unpark_thread(&self, id: ThreadId) {
+    self.threads.scheduler.unpark(id);
+  }
+
+  fn abort_deadlock(&mut self) -> ! {
+    self.finish_trace();
+    self.finish_jdwp();
+    self.finish_profile();
+    let _ = io::stdout().flush();
+    eprint!("{}", self.deadlock_report());
+    process::exit(1)
+  }
+
+  pub(crate) fn set_thread_state(&mut self, state: ThreadState) {
+    if let Some(thread) = self.threads.table.get_mut(&self.threads.current) {
+      thread.state = state;
+    }
+  }
+
+  pub fn thread_name(&self, id: ThreadId) -> String {
+    let name = self.threads.table.get(&id)
+      .and_then(|thread| thread.object)
+      .and_then(|object| self.get_field(object, "name", "Ljava/lang/String;").ok()?.as_ref().ok()?)
+      .and_then(|name| self.string_value(name).ok());
+    name.unwrap_or_else(|| "main".to_string())
+  }
+
+  // 実行権を持っていなければ保存したフレーム (まだ実行を始めていなければ空)
+  pub(crate) fn thread_frames(&self, id: ThreadId) -> &[Frame] {
+    if id == self.threads.current {
+      return &self.frames;
+    }
+    self.threads.contexts.get(&id).map_or(&[], |context| &context.frames)
+  }
+
+  pub(crate) fn thread_frames_mut(&mut self, id: ThreadId) -> &mut [Frame] {
+    if id == self.threads.current {
+      return &mut self.frames;
+    }
+    self.threads.contexts.get_mut(&id).map_or(&mut [], |context| &mut context.frames)
+  }
+
+  pub(crate) fn is_thread_alive(&self, id: ThreadId) -> bool {
+    self.threads.table.contains_key(&id)
+  }
+
+  // Thread.start(): OSスレッドを作り、実行権が回ってきたらrun()を呼ぶ
+  pub(crate) fn start_thread(&mut self, object: ObjRef) -> Result<(), VmError> {
+    let id = self.get_field(object, "tid", "J")?.as_long()?;
+    let daemon = self.get_field(object, "daemon", "Z")?.as_int()? != 0;
+    let name = self.thread_name_of(object)?;
+    self.threads.table.insert(id, JavaThread { object: Some(object), daemon, state: ThreadState::Runnable, joiners: Vec::new() });
+    self.threads.scheduler.add(id);
+    let scheduler = self.threads.scheduler.clone();
+    let vm = VmPointer(self);
+    let spawned = thread::Builder::new()
+      .name(name)
+      .stack_size(THREAD_STACK_SIZE)
+      .spawn(move || {
+        let vm = vm.get();
+        scheduler.wait_for_turn(id);
+        unsafe { (*vm).run_thread(id) };
+      });
+    if let Err(e) = spawned {
+      self.threads.table.remove(&id);
+      return Err(VmError::java("java/lang/OutOfMemoryError", format!("unable to create native thread: {}", e)));
+    }
+    Ok(())
+  }
+
+  fn thread_name_of(&self, object: ObjRef) -> Result<String, VmError> {
+    match self.get_field(object, "name", "Ljava/lang/String;")?.as_ref()? {
+      Some(name) => self.string_value(name),
+      None => Ok(String::new()),
+    }
+  }
+
+  fn run_thread(&mut self, id: ThreadId) {
+    self.restore_context(id);
+    self.jdwp_thread_start();
+    if let Some(object) = self.threads.table.get(&id).and_then(|thread| thread.object) {
+      let result = self.object_class(object).and_then(|class| {
+        let method = self.find_virtual(class, "run", "()V")
+          .ok_or_else(|| VmError::java("java/lang/AbstractMethodError", "java.lang.Thread.run()V"))?;
+        self.invoke(method, vec![Value::Ref(object)])
+      });
+      if let Err(error) = result {
+        self.report_uncaught(error);
+      }
+    }
+    self.terminate_thread(id);
+  }
+
+  // 終了したスレッドをjoin()しているスレッドと、スレッドのオブジェクトでwait()しているスレッドを起こす
+  fn terminate_thread(&mut self, id: ThreadId) {
+    self.frames.clear();
+    self.profile_thread_end();
+    self.jdwp_thread_death();
+    if let Some(thread) = self.threads.table.remove(&id) {
+      for joiner in thread.joiners {
+        self.unpark_thread(joiner);
+      }
+      if let Some(object) = thread.object {
+        self.notify_all_waiters(LockKey::Object(object));
+      }
+    }
+    if self.threads.scheduler.exit(id).is_err() {
+      self.abort_deadlock();
+    }
+  }
+
+  // キャッチされなかった例外を、スレッド名を付けて表示する
+  pub fn report_uncaught(&mut self, error: VmError) {
+    let _ = io::stdout().flush();
+    let name = self.thread_name(self.threads.current);
+    match error {
+      VmError::Internal(message) => eprintln!("Error: {}", message),
+      error => match self.materialize(error.clone()).and_then(|exception| self.stack_trace_text(exception)) {
+        Ok(text) => eprint!("Exception in thread \"{}\" {}", name, text),
+        Err(_) => eprintln!("Exception in thread \"{}\" {}", name, error),
+      },
+    }
+  }
+
+  // join(): スレッドが終了するかdeadlineを過ぎるまで待つ
+  pub(crate) fn join_thread(&mut self, id: ThreadId, deadline: Option<Instant>, interruptible: bool) -> Result<(), VmError> {
+    let me = self.threads.current;
+    while self.threads.table.contains_key(&id) {
+      if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+        break;
+      }
+      if interruptible && self.take_interrupted()? {
+        self.set_thread_state(ThreadState::Runnable);
+        return Err(VmError::java_without_message("java/lang/InterruptedException"));
+      }
+      if let Some(thread) = self.threads.table.get_mut(&id).filter(|thread| !thread.joiners.contains(&me)) {
+        thread.joiners.push(me);
+      }
+      self.set_thread_state(match deadline {
+        Some(_) => ThreadState::TimedWaiting(None),
+        None => ThreadState::Waiting(None),
+      });
+      self.park(deadline);
+    }
+    if let Some(thread) = self.threads.table.get_mut(&id) {
+      thread.joiners.retain(|&joiner| joiner != me);
+    }
+    self.set_thread_state(ThreadState::Runnable);
+    Ok(())
+  }
+
+  // mainメソッドが終わった後、デーモンでないスレッドがすべて終了するまで待つ (DestroyJavaVM)
+  pub fn wait_for_threads(&mut self) {
+    let me = self.threads.current;
+    while let Some(&id) = self.threads.table.iter()
+      .find(|(id, thread)| **id != me && !thread.daemon)
+      .map(|(id, _)| id)
+    {
+      let _ = self.join_thread(id, None, false);
+    }
+  }
+
+  // Thread.sleep()
+  pub(crate) fn sleep(&mut self, deadline: Instant) -> Result<(), VmError> {
+    let result = loop {
+      if self.take_interrupted()? {
+        break Err(VmError::java("java/lang/InterruptedException", "sleep interrupted"));
+      }
+      if Instant::now() >= deadline {
+        break Ok(());
+      }
+      self.set_thread_state(ThreadState::Sleeping);
+      self.park(Some(deadline));
+    };
+    self.set_thread_state(ThreadState::Runnable);
+    result
+  }
+
+  // Thread.interrupt(): 割り込みのフラグを立て、止まっていれば起こす
+  pub(crate) fn interrupt_thread(&mut self, object: ObjRef) -> Result<(), VmError> {
+    self.set_field(object, "interrupted", "Z", Value::Int(1))?;
+    let id = self.get_field(object, "tid", "J")?.as_long()?;
+    if id != self.threads.current && self.threads.table.contains_key(&id) {
+      self.unpark_thread(id);
+    }
+    Ok(())
+  }
+
+  fn current_thread_object(&self) -> Option<ObjRef> {
+    self.threads.table.get(&self.threads.current).and_then(|thread| thread.object)
+  }
+
+  pub(crate) fn is_interrupted(&self) -> Result<bool, VmError> {
+    match self.current_thread_object() {
+      Some(object) => Ok(self.get_field(object, "interrupted", "Z")?.as_int()? != 0),
+      None => Ok(false),
+    }
+  }
+
+  // 割り込まれていればフラグを下ろしてtrueを返す
+  pub(crate) fn take_interrupted(&mut self) -> Result<bool, VmError> {
+    let interrupted = self.is_interrupted()?;
+    if interrupted && let Some(object) = self.current_thread_object() {
+      self.set_field(object, "interrupted", "Z", Value::Int(0))?;
+    }
+    Ok(interrupted)
+  }
+}
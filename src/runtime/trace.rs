@@ -0,0 +1,616 @@
+use std::{
+  collections::HashMap,
+  fs::File,
+  io::{self, BufReader, BufWriter, Read, Write},
+  path::Path,
+  rc::Rc,
+};
+
+use crate::{
+  runtime::{frame::Frame, options::TraceOptions, value::{ObjRef, Value}, vm::Vm},
+  structure::{class::{Constant, ConstantPool}, code::{operand_text, CODE_BYTES}},
+};
+
+// トレースファイルの形式: "RJTR"、バージョン、以降はタグ付きのレコードが続く
+const MAGIC: &[u8; 4] = b"RJTR";
+const VERSION: u8 = 1;
+const TAG_STRING: u8 = 1;
+const TAG_STEP: u8 = 2;
+const TAG_DONE: u8 = 3;
+
+// 文字列の値はこの文字数で切り詰めて表示する
+const MAX_STRING_CHARS: usize = 40;
+
+// 記録した値 (参照は表示用の文字列にしておく)
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceValue {
+  Int(i32),
+  Long(i64),
+  Float(f32),
+  Double(f64),
+  Null,
+  ReturnAddress(usize),
+  Top,
+  Ref(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FrameState {
+  pub stack: Vec<TraceValue>,
+  pub locals: Vec<TraceValue>,
+}
+
+// 命令を実行する前の状態
+#[derive(Debug, Clone)]
+pub struct TraceStep {
+  pub thread: String,
+  pub method: String,
+  pub pc: usize,
+  pub opcode: u8,
+  pub operands: String,
+  pub before: FrameState,
+}
+
+#[derive(Debug, Clone)]
+pub enum TraceEvent {
+  Step(TraceStep),
+  // 命令を実行した後の状態 (フレームから抜けた場合はNone)
+  Done { thread: String, after: Option<FrameState> },
+}
+
+fn value_text(value: &TraceValue) -> String {
+  match value {
+    TraceValue::Int(value) => value.to_string(),
+    TraceValue::Long(value) => format!("{}L", value),
+    TraceValue::Float(value) => format!("{:?}f", value),
+    TraceValue::Double(value) => format!("{:?}d", value),
+    TraceValue::Null => "null".to_string(),
+    TraceValue::ReturnAddress(pc) => format!("ret:{}", pc),
+    TraceValue::Top => "-".to_string(),
+    TraceValue::Ref(text) => text.clone(),
+  }
+}
+
+fn values_text(values: &[TraceValue]) -> String {
+  format!("[{}]", values.iter().map(value_text).collect::<Vec<_>>().join(", "))
+}
+
+fn step_text(step: &TraceStep) -> String {
+  let name = CODE_BYTES.get(&step.opcode).map_or("unknown", |code_byte| code_byte.name);
+  if step.operands.is_empty() {
+    format!("[{}] {} {}: {}", step.thread, step.method, step.pc, name)
+  } else {
+    format!("[{}] {} {}: {} {}", step.thread, step.method, step.pc, name, step.operands)
+  }
+}
+
+fn state_text(state: &FrameState) -> String {
+  format!("stack {} | locals {}", values_text(&state.stack), values_text(&state.locals))
+}
+
+// 実行前と実行後のイベントを1行にまとめて表示する
+// (実行中に別の命令が記録された場合は、実行前と実行後を別の行に分ける)
+pub struct TextRenderer<W: Write> {
+  out: W,
+  pending: Option<TraceStep>,
+  // 実行が終わっていない命令 (スレッドごと)
+  open: HashMap<String, Vec<TraceStep>>,
+}
+
+impl<W: Write> TextRenderer<W> {
+  pub fn new(out: W) -> Self {
+    TextRenderer { out, pending: None, open: HashMap::new() }
+  }
+
+  pub fn event(&mut self, event: TraceEvent) -> io::Result<()> {
+    match event {
+      TraceEvent::Step(step) => {
+        self.suspend_pending()?;
+        self.pending = Some(step);
+      },
+      TraceEvent::Done { thread, after } => {
+        if let Some(step) = self.pending.take_if(|step| step.thread == thread) {
+          let change = match after {
+            Some(after) => format!(
+              "stack {} -> {} | locals {} -> {}",
+              values_text(&step.before.stack), values_text(&after.stack),
+              values_text(&step.before.locals), values_text(&after.locals),
+            ),
+            None => format!("{} | exit", state_text(&step.before)),
+          };
+          return writeln!(self.out, "{} | {}", step_text(&step), change);
+        }
+        self.suspend_pending()?;
+        if let Some(step) = self.open.get_mut(&thread).and_then(Vec::pop) {
+          let after = after.as_ref().map_or("exit".to_string(), state_text);
+          writeln!(self.out, "{} done | {}", step_text(&step), after)?;
+        }
+      },
+    }
+    Ok(())
+  }
+
+  fn suspend_pending(&mut self) -> io::Result<()> {
+    if let Some(step) = self.pending.take() {
+      writeln!(self.out, "{} ... | {}", step_text(&step), state_text(&step.before))?;
+      self.open.entry(step.thread.clone()).or_default().push(step);
+    }
+    Ok(())
+  }
+
+  pub fn finish(&mut self) -> io::Result<()> {
+    self.suspend_pending()?;
+    self.out.flush()
+  }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+  while value >= 0x80 {
+    out.push(value as u8 | 0x80);
+    value >>= 7;
+  }
+  out.push(value as u8);
+}
+
+fn write_signed(out: &mut Vec<u8>, value: i64) {
+  write_varint(out, ((value << 1) ^ (value >> 63)) as u64);
+}
+
+// 文字列は最初に現れた時に定義レコードを書き、以降は番号で参照する
+pub struct TraceWriter<W: Write> {
+  out: W,
+  strings: HashMap<String, u64>,
+}
+
+impl<W: Write> TraceWriter<W> {
+  pub fn new(mut out: W) -> io::Result<Self> {
+    out.write_all(MAGIC)?;
+    out.write_all(&[VERSION])?;
+    Ok(TraceWriter { out, strings: HashMap::new() })
+  }
+
+  fn string(&mut self, record: &mut Vec<u8>, text: &str) -> io::Result<()> {
+    let id = match self.strings.get(text) {
+      Some(&id) => id,
+      None => {
+        let id = self.strings.len() as u64;
+        let mut definition = vec![TAG_STRING];
+        write_varint(&mut definition, text.len() as u64);
+        definition.extend_from_slice(text.as_bytes());
+        self.out.write_all(&definition)?;
+        self.strings.insert(text.to_string(), id);
+        id
+      },
+    };
+    write_varint(record, id);
+    Ok(())
+  }
+
+  fn values(&mut self, record: &mut Vec<u8>, values: &[TraceValue]) -> io::Result<()> {
+    write_varint(record, values.len() as u64);
+    for value in values {
+      match value {
+        TraceValue::Top => record.push(0),
+        TraceValue::Int(value) => {
+          record.push(1);
+          write_signed(record, *value as i64);
+        },
+        TraceValue::Long(value) => {
+          record.push(2);
+          write_signed(record, *value);
+        },
+        TraceValue::Float(value) => {
+          record.push(3);
+          record.extend_from_slice(&value.to_bits().to_le_bytes());
+        },
+        TraceValue::Double(value) => {
+          record.push(4);
+          record.extend_from_slice(&value.to_bits().to_le_bytes());
+        },
+        TraceValue::Null => record.push(5),
+        TraceValue::ReturnAddress(pc) => {
+          record.push(6);
+          write_varint(record, *pc as u64);
+        },
+        TraceValue::Ref(text) => {
+          record.push(7);
+          self.string(record, text)?;
+        },
+      }
+    }
+    Ok(())
+  }
+
+  fn state(&mut self, record: &mut Vec<u8>, state: &FrameState) -> io::Result<()> {
+    self.values(record, &state.stack)?;
+    self.values(record, &state.locals)
+  }
+
+  pub fn event(&mut self, event: TraceEvent) -> io::Result<()> {
+    let mut record = Vec::new();
+    match &event {
+      TraceEvent::Step(step) => {
+        record.push(TAG_STEP);
+        self.string(&mut record, &step.thread)?;
+        self.string(&mut record, &step.method)?;
+        write_varint(&mut record, step.pc as u64);
+        record.push(step.opcode);
+        self.string(&mut record, &step.operands)?;
+        self.state(&mut record, &step.before)?;
+      },
+      TraceEvent::Done { thread, after } => {
+        record.push(TAG_DONE);
+        self.string(&mut record, thread)?;
+        match after {
+          Some(after) => {
+            record.push(1);
+            self.state(&mut record, after)?;
+          },
+          None => record.push(0),
+        }
+      },
+    }
+    self.out.write_all(&record)
+  }
+
+  pub fn finish(&mut self) -> io::Result<()> {
+    self.out.flush()
+  }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+pub struct TraceReader<R: Read> {
+  input: R,
+  strings: Vec<String>,
+}
+
+impl<R: Read> TraceReader<R> {
+  pub fn new(mut input: R) -> io::Result<Self> {
+    let mut header = [0; 5];
+    input.read_exact(&mut header).map_err(|_| invalid_data("not a trace file"))?;
+    if &header[..4] != MAGIC {
+      return Err(invalid_data("not a trace file"));
+    }
+    if header[4] != VERSION {
+      return Err(invalid_data(&format!("unsupported trace file version: {}", header[4])));
+    }
+    Ok(TraceReader { input, strings: Vec::new() })
+  }
+
+  fn byte(&mut self) -> io::Result<u8> {
+    let mut byte = [0];
+    self.input.read_exact(&mut byte)?;
+    Ok(byte[0])
+  }
+
+  fn varint(&mut self) -> io::Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+      let byte = self.byte()?;
+      value |= ((byte & 0x7f) as u64) << shift;
+      if byte & 0x80 == 0 {
+        return Ok(value);
+      }
+    }
+    Err(invalid_data("varint is too long"))
+  }
+
+  fn signed(&mut self) -> io::Result<i64> {
+    let value = self.varint()?;
+    Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+  }
+
+  fn bytes<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    self.input.read_exact(&mut bytes)?;
+    Ok(bytes)
+  }
+
+  fn string(&mut self) -> io::Result<String> {
+    let id = self.varint()? as usize;
+    self.strings.get(id).cloned().ok_or_else(|| invalid_data(&format!("undefined string: {}", id)))
+  }
+
+  fn values(&mut self) -> io::Result<Vec<TraceValue>> {
+    let count = self.varint()? as usize;
+    let mut values = Vec::with_capacity(count.min(u16::MAX as usize));
+    for _ in 0..count {
+      values.push(match self.byte()? {
+        0 => TraceValue::Top,
+        1 => TraceValue::Int(self.signed()? as i32),
+        2 => TraceValue::Long(self.signed()?),
+        3 => TraceValue::Float(f32::from_bits(u32::from_le_bytes(self.bytes()?))),
+        4 => TraceValue::Double(f64::from_bits(u64::from_le_bytes(self.bytes()?))),
+        5 => TraceValue::Null,
+        6 => TraceValue::ReturnAddress(self.varint()? as usize),
+        7 => TraceValue::Ref(self.string()?),
+        tag => return Err(invalid_data(&format!("invalid value tag: {}", tag))),
+      });
+    }
+    Ok(values)
+  }
+
+  fn state(&mut self) -> io::Result<FrameState> {
+    Ok(FrameState { stack: self.values()?, locals: self.values()? })
+  }
+
+  // ファイルの終わりではNoneを返す
+  pub fn next_event(&mut self) -> io::Result<Option<TraceEvent>> {
+    loop {
+      let mut tag = [0];
+      if self.input.read(&mut tag)? == 0 {
+        return Ok(None);
+      }
+      match tag[0] {
+        TAG_STRING => {
+          let length = self.varint()? as usize;
+          let mut bytes = vec![0; length];
+          self.input.read_exact(&mut bytes)?;
+          self.strings.push(String::from_utf8(bytes).map_err(|_| invalid_data("invalid UTF-8 string"))?);
+        },
+        TAG_STEP => {
+          let thread = self.string()?;
+          let method = self.string()?;
+          let pc = self.varint()? as usize;
+          let opcode = self.byte()?;
+          let operands = self.string()?;
+          let before = self.state()?;
+          return Ok(Some(TraceEvent::Step(TraceStep { thread, method, pc, opcode, operands, before })));
+        },
+        TAG_DONE => {
+          let thread = self.string()?;
+          let after = match self.byte()? {
+            0 => None,
+            _ => Some(self.state()?),
+          };
+          return Ok(Some(TraceEvent::Done { thread, after }));
+        },
+        tag => return Err(invalid_data(&format!("invalid record tag: {}", tag))),
+      }
+    }
+  }
+}
+
+// トレースファイルを実行時と同じテキストに戻す
+pub fn replay(path: &Path, out: impl Write) -> io::Result<()> {
+  let mut reader = TraceReader::new(BufReader::new(File::open(path)?))?;
+  let mut renderer = TextRenderer::new(out);
+  while let Some(event) = reader.next_event()? {
+    renderer.event(event)?;
+  }
+  renderer.finish()
+}
+
+// '*'を任意の文字列として照合する
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+  match pattern.split_first() {
+    None => text.is_empty(),
+    Some((b'*', rest)) => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+    Some((c, rest)) => text.first() == Some(c) && glob_match(rest, &text[1..]),
+  }
+}
+
+pub(crate) fn constant_comment(pool: &ConstantPool, index: u16) -> Option<String> {
+  let name_and_type = |index: u16| pool.get_name_and_type(index).ok().map(|(name, descriptor)| format!("{}:{}", name, descriptor));
+  Some(match pool.get_class(index).ok()? {
+    Constant::Class { .. } => {
+      let name = pool.get_class_name(index).ok()?;
+      if name.starts_with('[') { format!("class \"{}\"", name) } else { format!("class {}", name) }
+    },
+    Constant::Fieldref { .. } | Constant::Methodref { .. } | Constant::InterfaceMethodref { .. } => {
+      let kind = match pool.get_class(index).ok()? {
+        Constant::Fieldref { .. } => "Field",
+        Constant::Methodref { .. } => "Method",
+        _ => "InterfaceMethod",
+      };
+      let (class_name, name, descriptor) = pool.get_member_ref(index).ok()?;
+      format!("{} {}.{}:{}", kind, class_name, name, descriptor)
+    },
+    Constant::String { string_index } => format!("String {}", pool.get_utf8(*string_index).ok()?),
+    Constant::Integer { bytes } => format!("int {}", *bytes as i32),
+    Constant::Float { bytes } => format!("float {:?}f", f32::from_bits(*bytes)),
+    Constant::Long { high_bytes, low_bytes } => format!("long {}l", (((*high_bytes as u64) << 32) | *low_bytes as u64) as i64),
+    Constant::Double { high_bytes, low_bytes } => format!("double {:?}d", f64::from_bits(((*high_bytes as u64) << 32) | *low_bytes as u64)),
+    Constant::MethodType { descriptor_index } => format!("MethodType {}", pool.get_utf8(*descriptor_index).ok()?),
+    Constant::MethodHandle { reference_kind, reference_index } => {
+      let (class_name, name, descriptor) = pool.get_member_ref(*reference_index).ok()?;
+      format!("MethodHandle {}:{}.{}:{}", reference_kind, class_name, name, descriptor)
+    },
+    Constant::Dynamic { bootstrap_method_attr_index, name_and_type_index } => {
+      format!("Dynamic #{}:{}", bootstrap_method_attr_index, name_and_type(*name_and_type_index)?)
+    },
+    Constant::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index } => {
+      format!("InvokeDynamic #{}:{}", bootstrap_method_attr_index, name_and_type(*name_and_type_index)?)
+    },
+    _ => return None,
+  })
+}
+
+// javap -cと同じ形式 ("#5 // Method Foo.bar:()V")
+fn reference_text(pool: Option<&ConstantPool>, index: u16, extra: &str) -> String {
+  match pool.and_then(|pool| constant_comment(pool, index)) {
+    Some(comment) => format!("#{}{} // {}", index, extra, comment),
+    None => format!("#{}{}", index, extra),
+  }
+}
+
+// 命令のオペランドを表示用に展開する (分岐先は絶対位置にする)
+fn operands(code: &[u8], pc: usize, pool: Option<&ConstantPool>) -> String {
+  operand_text(code, pc, &|index, extra| reference_text(pool, index, extra), &|target| target.to_string())
+}
+
+// "iinc 1, 1"のような命令の表示
+pub(crate) fn instruction_text(code: &[u8], pc: usize, pool: Option<&ConstantPool>) -> String {
+  let name = code.get(pc).and_then(|opcode| CODE_BYTES.get(opcode)).map_or("unknown", |code_byte| code_byte.name);
+  match operands(code, pc, pool) {
+    operands if operands.is_empty() => name.to_string(),
+    operands => format!("{} {}", name, operands),
+  }
+}
+
+enum TraceSink {
+  Text(TextRenderer<io::Stderr>),
+  Binary(TraceWriter<BufWriter<File>>),
+}
+
+pub struct Tracer {
+  // "Class.method"か"Class"に対するパターン (空なら全て)
+  filters: Vec<String>,
+  // メソッドIDごとの表示名 (フィルタに一致しなければNone)
+  methods: HashMap<usize, Option<Rc<str>>>,
+  // (メソッドID, pc)ごとのオペランドの表示
+  operands: HashMap<(usize, usize), Rc<str>>,
+  sink: TraceSink,
+}
+
+impl Tracer {
+  pub fn new(options: &TraceOptions) -> io::Result<Tracer> {
+    let sink = match &options.file {
+      Some(path) => TraceSink::Binary(TraceWriter::new(BufWriter::new(File::create(path)?))?),
+      None => TraceSink::Text(TextRenderer::new(io::stderr())),
+    };
+    Ok(Tracer {
+      filters: options.filters.iter().map(|filter| filter.replace('/', ".")).collect(),
+      methods: HashMap::new(),
+      operands: HashMap::new(),
+      sink,
+    })
+  }
+
+  fn matches(&self, class_name: &str, method_name: &str) -> bool {
+    let member = format!("{}.{}", class_name, method_name);
+    self.filters.is_empty() || self.filters.iter().any(|filter| {
+      glob_match(filter.as_bytes(), class_name.as_bytes()) || glob_match(filter.as_bytes(), member.as_bytes())
+    })
+  }
+
+  fn record(&mut self, event: TraceEvent) -> io::Result<()> {
+    match &mut self.sink {
+      TraceSink::Text(renderer) => renderer.event(event),
+      TraceSink::Binary(writer) => writer.event(event),
+    }
+  }
+
+  fn finish(&mut self) -> io::Result<()> {
+    match &mut self.sink {
+      TraceSink::Text(renderer) => renderer.finish(),
+      TraceSink::Binary(writer) => writer.finish(),
+    }
+  }
+}
+
+impl Vm {
+  pub(crate) fn reference_text(&self, object: ObjRef) -> String {
+    let Ok(class) = self.object_class(object) else {
+      return format!("<invalid {}>", object.0);
+    };
+    if let Some(target) = self.mirror_target(object) {
+      return self.mirror_text(target);
+    }
+    if self.classes[class].name == "java/lang/String"
+      && let Ok(text) = self.string_value(object) {
+      let mut chars = text.chars();
+      let head = chars.by_ref().take(MAX_STRING_CHARS).collect::<String>();
+      return if chars.next().is_some() { format!("{:?}...", head) } else { format!("{:?}", head) };
+    }
+    format!("{}@{:x}", self.classes[class].java_name(), self.identity_hash(object))
+  }
+
+  fn trace_value(&self, value: Value) -> TraceValue {
+    match value {
+      Value::Int(value) => TraceValue::Int(value),
+      Value::Long(value) => TraceValue::Long(value),
+      Value::Float(value) => TraceValue::Float(value),
+      Value::Double(value) => TraceValue::Double(value),
+      Value::Ref(object) => TraceValue::Ref(self.reference_text(object)),
+      Value::Null => TraceValue::Null,
+      Value::ReturnAddress(pc) => TraceValue::ReturnAddress(pc),
+      Value::Top => TraceValue::Top,
+    }
+  }
+
+  fn trace_state(&self, frame: &Frame) -> FrameState {
+    FrameState {
+      stack: frame.stack.iter().map(|&value| self.trace_value(value)).collect(),
+      locals: frame.locals.iter().map(|&value| self.trace_value(value)).collect(),
+    }
+  }
+
+  // 命令を実行する前に呼び、記録した場合はtrueを返す
+  pub(crate) fn trace_before(&mut self) -> bool {
+    let Some(mut tracer) = self.tracer.take() else {
+      return false;
+    };
+    let traced = self.record_step(&mut tracer);
+    self.tracer = Some(tracer);
+    traced
+  }
+
+  fn record_step(&self, tracer: &mut Tracer) -> bool {
+    let Some(frame) = self.frames.last() else {
+      return false;
+    };
+    let method = &frame.method;
+    let label = match tracer.methods.get(&method.id) {
+      Some(label) => label.clone(),
+      None => {
+        let class_name = method.class_name.replace('/', ".");
+        let label = tracer.matches(&class_name, &method.name)
+          .then(|| Rc::from(format!("{}.{}{}", class_name, method.name, method.descriptor)));
+        tracer.methods.insert(method.id, label.clone());
+        label
+      },
+    };
+    let (Some(label), Some(code)) = (label, method.code()) else {
+      return false;
+    };
+    let operands = tracer.operands.entry((method.id, frame.pc)).or_insert_with(|| {
+      let class_file = self.class_file(method.class).ok();
+      Rc::from(operands(&code.bytes, frame.pc, class_file.as_ref().map(|class_file| &class_file.constant_pool)))
+    }).clone();
+    let step = TraceStep {
+      thread: self.thread_name(self.threads.current),
+      method: label.to_string(),
+      pc: frame.pc,
+      opcode: code.bytes.get(frame.pc).copied().unwrap_or(0),
+      operands: operands.to_string(),
+      before: self.trace_state(frame),
+    };
+    self.write_trace(tracer, TraceEvent::Step(step))
+  }
+
+  // 命令を実行した後に呼ぶ (depthは実行前のフレーム数)
+  pub(crate) fn trace_after(&mut self, depth: usize) {
+    let Some(mut tracer) = self.tracer.take() else {
+      return;
+    };
+    let after = self.frames.get(depth - 1).filter(|_| self.frames.len() >= depth)
+      .map(|frame| self.trace_state(frame));
+    let thread = self.thread_name(self.threads.current);
+    if self.write_trace(&mut tracer, TraceEvent::Done { thread, after }) {
+      self.tracer = Some(tracer);
+    }
+  }
+
+  // 書き込みに失敗したらトレースをやめる
+  fn write_trace(&self, tracer: &mut Tracer, event: TraceEvent) -> bool {
+    match tracer.record(event) {
+      Ok(()) => true,
+      Err(e) => {
+        eprintln!("Error: failed to write trace: {}", e);
+        false
+      },
+    }
+  }
+
+  // プロセスを終了する前に呼び、書きかけのトレースを書き出す
+  pub fn finish_trace(&mut self) {
+    if let Some(mut tracer) = self.tracer.take()
+      && let Err(e) = tracer.finish() {
+      eprintln!("Error: failed to write trace: {}", e);
+    }
+  }
+}
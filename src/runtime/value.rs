@@ -0,0 +1,83 @@
+use crate::{runtime::error::VmError, util::descriptor::FieldType};
+
+// ヒープ上のオブジェクトを指すハンドル (ネイティブコードからもこの値で参照する)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjRef(pub u32);
+
+impl ObjRef {
+  pub fn index(self) -> usize {
+    self.0 as usize
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+  Int(i32),
+  Long(i64),
+  Float(f32),
+  Double(f64),
+  Ref(ObjRef),
+  Null,
+  ReturnAddress(usize),
+  // long/doubleの上位スロットや未初期化のローカル変数
+  Top,
+}
+
+impl Value {
+  pub fn default_for(field_type: &FieldType) -> Value {
+    match field_type {
+      FieldType::Long => Value::Long(0),
+      FieldType::Float => Value::Float(0.0),
+      FieldType::Double => Value::Double(0.0),
+      FieldType::Object(_) | FieldType::Array(_) => Value::Null,
+      _ => Value::Int(0),
+    }
+  }
+
+  pub fn from_ref(reference: Option<ObjRef>) -> Value {
+    match reference {
+      Some(r) => Value::Ref(r),
+      None => Value::Null,
+    }
+  }
+
+  pub fn is_wide(&self) -> bool {
+    matches!(self, Value::Long(_) | Value::Double(_))
+  }
+
+  pub fn as_int(self) -> Result<i32, VmError> {
+    match self {
+      Value::Int(v) => Ok(v),
+      v => Err(VmError::internal(format!("Expected int, found {:?}", v))),
+    }
+  }
+
+  pub fn as_long(self) -> Result<i64, VmError> {
+    match self {
+      Value::Long(v) => Ok(v),
+      v => Err(VmError::internal(format!("Expected long, found {:?}", v))),
+    }
+  }
+
+  pub fn as_float(self) -> Result<f32, VmError> {
+    match self {
+      Value::Float(v) => Ok(v),
+      v => Err(VmError::internal(format!("Expected float, found {:?}", v))),
+    }
+  }
+
+  pub fn as_double(self) -> Result<f64, VmError> {
+    match self {
+      Value::Double(v) => Ok(v),
+      v => Err(VmError::internal(format!("Expected double, found {:?}", v))),
+    }
+  }
+
+  pub fn as_ref(self) -> Result<Option<ObjRef>, VmError> {
+    match self {
+      Value::Ref(r) => Ok(Some(r)),
+      Value::Null => Ok(None),
+      v => Err(VmError::internal(format!("Expected reference, found {:?}", v))),
+    }
+  }
+}
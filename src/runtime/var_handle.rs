@@ -0,0 +1,311 @@
+use crate::{
+  runtime::{
+    class::{ClassId, RuntimeMethod},
+    error::VmError,
+    library::mirror::MirrorTarget,
+    memory::{add_values, bitwise_values, BitwiseOp, Location, MemoryOrder},
+    method_handle::method_type_string,
+    value::{ObjRef, Value},
+    vm::Vm,
+  },
+  util::descriptor::{FieldType, MethodDescriptor},
+};
+
+// VarHandleが指す変数の種類
+pub const VAR_FIELD: i32 = 0;
+pub const VAR_STATIC: i32 = 1;
+pub const VAR_ARRAY: i32 = 2;
+
+// VarHandleが指す変数 (フィールドはクラスとフィールドの位置、配列は配列クラス)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VarTarget {
+  Field(ClassId, usize),
+  Static(ClassId, usize),
+  Array(ClassId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessOp {
+  Get,
+  Set,
+  CompareAndSet,
+  CompareAndExchange,
+  GetAndSet,
+  GetAndAdd,
+  GetAndBitwise(BitwiseOp),
+}
+
+impl AccessOp {
+  // 座標の後に受け取る値の数
+  fn value_count(self) -> usize {
+    match self {
+      AccessOp::Get => 0,
+      AccessOp::CompareAndSet | AccessOp::CompareAndExchange => 2,
+      _ => 1,
+    }
+  }
+
+  fn return_type(self, var_type: &FieldType) -> Option<FieldType> {
+    match self {
+      AccessOp::Set => None,
+      AccessOp::CompareAndSet => Some(FieldType::Boolean),
+      _ => Some(var_type.clone()),
+    }
+  }
+}
+
+// VarHandleのアクセスモード (メソッド名、操作、順序付け)
+pub const ACCESS_MODES: &[(&str, AccessOp, MemoryOrder)] = &[
+  ("get", AccessOp::Get, MemoryOrder::Plain),
+  ("set", AccessOp::Set, MemoryOrder::Plain),
+  ("getVolatile", AccessOp::Get, MemoryOrder::Volatile),
+  ("setVolatile", AccessOp::Set, MemoryOrder::Volatile),
+  ("getAcquire", AccessOp::Get, MemoryOrder::Acquire),
+  ("setRelease", AccessOp::Set, MemoryOrder::Release),
+  ("getOpaque", AccessOp::Get, MemoryOrder::Opaque),
+  ("setOpaque", AccessOp::Set, MemoryOrder::Opaque),
+  ("compareAndSet", AccessOp::CompareAndSet, MemoryOrder::Volatile),
+  ("compareAndExchange", AccessOp::CompareAndExchange, MemoryOrder::Volatile),
+  ("compareAndExchangeAcquire", AccessOp::CompareAndExchange, MemoryOrder::Acquire),
+  ("compareAndExchangeRelease", AccessOp::CompareAndExchange, MemoryOrder::Release),
+  ("weakCompareAndSetPlain", AccessOp::CompareAndSet, MemoryOrder::Plain),
+  ("weakCompareAndSet", AccessOp::CompareAndSet, MemoryOrder::Volatile),
+  ("weakCompareAndSetAcquire", AccessOp::CompareAndSet, MemoryOrder::Acquire),
+  ("weakCompareAndSetRelease", AccessOp::CompareAndSet, MemoryOrder::Release),
+  ("getAndSet", AccessOp::GetAndSet, MemoryOrder::Volatile),
+  ("getAndSetAcquire", AccessOp::GetAndSet, MemoryOrder::Acquire),
+  ("getAndSetRelease", AccessOp::GetAndSet, MemoryOrder::Release),
+  ("getAndAdd", AccessOp::GetAndAdd, MemoryOrder::Volatile),
+  ("getAndAddAcquire", AccessOp::GetAndAdd, MemoryOrder::Acquire),
+  ("getAndAddRelease", AccessOp::GetAndAdd, MemoryOrder::Release),
+  ("getAndBitwiseOr", AccessOp::GetAndBitwise(BitwiseOp::Or), MemoryOrder::Volatile),
+  ("getAndBitwiseOrAcquire", AccessOp::GetAndBitwise(BitwiseOp::Or), MemoryOrder::Acquire),
+  ("getAndBitwiseOrRelease", AccessOp::GetAndBitwise(BitwiseOp::Or), MemoryOrder::Release),
+  ("getAndBitwiseAnd", AccessOp::GetAndBitwise(BitwiseOp::And), MemoryOrder::Volatile),
+  ("getAndBitwiseAndAcquire", AccessOp::GetAndBitwise(BitwiseOp::And), MemoryOrder::Acquire),
+  ("getAndBitwiseAndRelease", AccessOp::GetAndBitwise(BitwiseOp::And), MemoryOrder::Release),
+  ("getAndBitwiseXor", AccessOp::GetAndBitwise(BitwiseOp::Xor), MemoryOrder::Volatile),
+  ("getAndBitwiseXorAcquire", AccessOp::GetAndBitwise(BitwiseOp::Xor), MemoryOrder::Acquire),
+  ("getAndBitwiseXorRelease", AccessOp::GetAndBitwise(BitwiseOp::Xor), MemoryOrder::Release),
+];
+
+// int未満の整数型の変数に入るように切り詰める
+fn narrow(value: Value, var_type: &FieldType) -> Value {
+  match (value, var_type) {
+    (Value::Int(v), FieldType::Boolean) => Value::Int(v & 1),
+    (Value::Int(v), FieldType::Byte) => Value::Int(v as i8 as i32),
+    (Value::Int(v), FieldType::Char) => Value::Int(v as u16 as i32),
+    (Value::Int(v), FieldType::Short) => Value::Int(v as i16 as i32),
+    (value, _) => value,
+  }
+}
+
+// Class.getName()の形式 (配列は記述子をドット区切りにしたもの)
+pub fn type_name(field_type: &FieldType) -> String {
+  match field_type {
+    FieldType::Array(_) => field_type.descriptor().replace('/', "."),
+    _ => field_type.to_string(),
+  }
+}
+
+// 変数の型で使えない操作 (数値でない変数への加算、整数でない変数へのビット演算)
+fn is_supported(op: AccessOp, var_type: &FieldType) -> bool {
+  match op {
+    AccessOp::GetAndAdd => !var_type.is_reference() && *var_type != FieldType::Boolean,
+    AccessOp::GetAndBitwise(_) => !var_type.is_reference() && !matches!(var_type, FieldType::Float | FieldType::Double),
+    _ => true,
+  }
+}
+
+impl Vm {
+  // 参照先はフィールドなら (クラスID << 32 | フィールドの位置)、配列なら配列クラスのIDとして持つ
+  pub fn new_var_handle(&mut self, target: VarTarget) -> Result<ObjRef, VmError> {
+    let class = self.load_class("java/lang/invoke/VarHandle")?;
+    let handle = self.instantiate(class)?;
+    let (kind, member) = match target {
+      VarTarget::Field(owner, index) => (VAR_FIELD, ((owner as i64) << 32) | index as i64),
+      VarTarget::Static(owner, index) => (VAR_STATIC, ((owner as i64) << 32) | index as i64),
+      VarTarget::Array(array_class) => (VAR_ARRAY, array_class as i64),
+    };
+    self.set_field(handle, "kind", "I", Value::Int(kind))?;
+    self.set_field(handle, "member", "J", Value::Long(member))?;
+    Ok(handle)
+  }
+
+  pub fn var_target(&self, handle: ObjRef) -> Result<VarTarget, VmError> {
+    let kind = self.get_field(handle, "kind", "I")?.as_int()?;
+    let member = self.get_field(handle, "member", "J")?.as_long()?;
+    let (owner, index) = ((member >> 32) as ClassId, (member & 0xffff_ffff) as usize);
+    match kind {
+      VAR_FIELD => Ok(VarTarget::Field(owner, index)),
+      VAR_STATIC => Ok(VarTarget::Static(owner, index)),
+      VAR_ARRAY => Ok(VarTarget::Array(member as ClassId)),
+      _ => Err(VmError::internal(format!("Invalid VarHandle kind: {}", kind))),
+    }
+  }
+
+  // 変数の型
+  pub fn var_type(&self, target: VarTarget) -> Result<FieldType, VmError> {
+    match target {
+      VarTarget::Field(owner, index) | VarTarget::Static(owner, index) => {
+        FieldType::parse(&self.classes[owner].fields[index].descriptor).map_err(VmError::internal)
+      },
+      VarTarget::Array(array_class) => self.classes[array_class].component.clone()
+        .ok_or_else(|| VmError::internal("VarHandle for non-array class")),
+    }
+  }
+
+  // 変数を指定するための引数 (インスタンス、配列と添字) の型
+  pub fn coordinate_types(&self, target: VarTarget) -> Vec<MirrorTarget> {
+    match target {
+      VarTarget::Field(owner, _) => vec![MirrorTarget::Class(owner)],
+      VarTarget::Static(_, _) => vec![],
+      VarTarget::Array(array_class) => vec![MirrorTarget::Class(array_class), MirrorTarget::Primitive(FieldType::Int)],
+    }
+  }
+
+  // VarHandle[varType=int, coord=[class Foo]]
+  pub fn var_handle_text(&self, target: VarTarget) -> Result<String, VmError> {
+    let coordinates: Vec<String> = self.coordinate_types(target).iter().map(|c| self.mirror_text(c)).collect();
+    Ok(format!("VarHandle[varType={}, coord=[{}]]", type_name(&self.var_type(target)?), coordinates.join(", ")))
+  }
+
+  fn coordinate_field_types(&self, target: VarTarget) -> Vec<FieldType> {
+    self.coordinate_types(target).into_iter().map(|coordinate| match coordinate {
+      MirrorTarget::Class(class) => self.class_type(class),
+      MirrorTarget::Primitive(primitive) => primitive,
+    }).collect()
+  }
+
+  // VarHandleのアクセスモードのメソッド: 呼び出し側の記述子 (レシーバを除く) で座標と値を受け取る
+  pub(crate) fn invoke_var_handle(&mut self, method: &RuntimeMethod, args: Vec<Value>) -> Result<Option<Value>, VmError> {
+    let Some(&(_, op, order)) = ACCESS_MODES.iter().find(|(name, _, _)| *name == method.name) else {
+      return Err(VmError::java("java/lang/UnsupportedOperationException", format!(
+        "{}.{}", method.class_name.replace('/', "."), method.name,
+      )));
+    };
+    let handle = args[0].as_ref()?.ok_or_else(|| VmError::null_pointer("Cannot invoke a null VarHandle"))?;
+    let target = self.var_target(handle)?;
+    let var_type = self.var_type(target)?;
+    let mut parameters = self.coordinate_field_types(target);
+    let coordinate_count = parameters.len();
+    parameters.extend(std::iter::repeat_n(var_type.clone(), op.value_count()));
+    let return_type = op.return_type(&var_type);
+    if method.signature.parameters.len() != parameters.len() {
+      let handle_type = FieldType::Object("java/lang/invoke/VarHandle".to_string());
+      let expected = MethodDescriptor {
+        parameters: std::iter::once(handle_type.clone()).chain(parameters).collect(),
+        return_type,
+      };
+      let found = MethodDescriptor {
+        parameters: std::iter::once(handle_type).chain(method.signature.parameters.iter().cloned()).collect(),
+        return_type: method.signature.return_type.clone(),
+      };
+      return Err(VmError::java("java/lang/invoke/WrongMethodTypeException", format!(
+        "cannot convert MethodHandle{} to {}", method_type_string(&expected), method_type_string(&found),
+      )));
+    }
+    let mark = self.handles.mark();
+    let result = self.adapt_and_access(target, op, order, &args[1..], &method.signature, &parameters, coordinate_count, &var_type);
+    self.handles.release(mark);
+    result
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  fn adapt_and_access(
+    &mut self,
+    target: VarTarget,
+    op: AccessOp,
+    order: MemoryOrder,
+    args: &[Value],
+    signature: &MethodDescriptor,
+    parameters: &[FieldType],
+    coordinate_count: usize,
+    var_type: &FieldType,
+  ) -> Result<Option<Value>, VmError> {
+    let mut values = Vec::new();
+    for ((&value, from), to) in args.iter().zip(&signature.parameters).zip(parameters) {
+      let value = self.adapt_value(value, from, to)?;
+      if let Value::Ref(reference) = value {
+        self.handles.new_local(reference);
+      }
+      values.push(value);
+    }
+    let (coordinates, values) = values.split_at(coordinate_count);
+    let result = self.access_variable(target, op, order, coordinates, values, var_type)?;
+    match (result, op.return_type(var_type), signature.return_type.as_ref()) {
+      (_, _, None) => Ok(None),
+      (Some(value), Some(from), Some(to)) => self.adapt_value(value, &from, to).map(Some),
+      (_, _, Some(to)) => Ok(Some(Value::default_for(to))),
+    }
+  }
+
+  fn variable_location(&mut self, target: VarTarget, coordinates: &[Value]) -> Result<Location, VmError> {
+    match target {
+      VarTarget::Field(owner, index) => {
+        let object = coordinates[0].as_ref()?
+          .ok_or_else(|| VmError::java_without_message("java/lang/NullPointerException"))?;
+        let class = self.object_class(object)?;
+        if !self.is_assignable(class, owner) {
+          return Err(VmError::java("java/lang/ClassCastException", format!(
+            "Cannot cast {} to {}", self.classes[class].java_name(), self.classes[owner].java_name(),
+          )));
+        }
+        Ok(Location::Field(object, self.classes[owner].fields[index].slot))
+      },
+      VarTarget::Static(owner, index) => {
+        self.initialize(owner)?;
+        Ok(Location::Static(owner, self.classes[owner].fields[index].slot))
+      },
+      VarTarget::Array(_) => {
+        let array = coordinates[0].as_ref()?
+          .ok_or_else(|| VmError::java_without_message("java/lang/NullPointerException"))?;
+        self.element_location(array, coordinates[1].as_int()?)
+      },
+    }
+  }
+
+  // 変数を1つのアクセスモードで読み書きし、戻り値 (変数の型) を返す
+  fn access_variable(
+    &mut self,
+    target: VarTarget,
+    op: AccessOp,
+    order: MemoryOrder,
+    coordinates: &[Value],
+    values: &[Value],
+    var_type: &FieldType,
+  ) -> Result<Option<Value>, VmError> {
+    // finalフィールドは読み込みしかできない
+    let read_only = match target {
+      VarTarget::Field(owner, index) | VarTarget::Static(owner, index) => self.classes[owner].fields[index].is_final(),
+      VarTarget::Array(_) => false,
+    };
+    if (read_only && op != AccessOp::Get) || !is_supported(op, var_type) {
+      return Err(VmError::java_without_message("java/lang/UnsupportedOperationException"));
+    }
+    let location = self.variable_location(target, coordinates)?;
+    if let Location::Element(array, _) = location
+      && matches!(op, AccessOp::Set | AccessOp::CompareAndSet | AccessOp::CompareAndExchange | AccessOp::GetAndSet)
+    {
+      self.check_array_store(array, *values.last().unwrap_or(&Value::Null))?;
+    }
+    match op {
+      AccessOp::Get => self.load_ordered(location, order).map(Some),
+      AccessOp::Set => self.store_ordered(location, values[0], order).map(|_| None),
+      AccessOp::CompareAndSet => {
+        let result = self.compare_and_set(location, values[0], values[1], order)?;
+        Ok(Some(Value::Int(result as i32)))
+      },
+      AccessOp::CompareAndExchange => self.compare_and_exchange(location, values[0], values[1], order).map(Some),
+      AccessOp::GetAndSet => self.get_and_set(location, values[0], order).map(Some),
+      AccessOp::GetAndAdd => {
+        let delta = values[0];
+        self.update(location, order, |current| Ok(Some(narrow(add_values(current, delta)?, var_type)))).map(Some)
+      },
+      AccessOp::GetAndBitwise(bitwise) => {
+        let operand = values[0];
+        self.update(location, order, |current| Ok(Some(narrow(bitwise_values(bitwise, current, operand)?, var_type)))).map(Some)
+      },
+    }
+  }
+}
@@ -0,0 +1,614 @@
+use std::{collections::HashMap, fs, rc::Rc};
+
+use crate::{
+  class_leader,
+  runtime::{
+    builtin,
+    class::{Class, ClassDefinition, ClassId, InitState, MethodBody, RuntimeField, RuntimeMethod, ACC_ABSTRACT, ACC_FINAL, ACC_INTERFACE, ACC_PUBLIC},
+    error::VmError,
+    frame::Frame,
+    gc::GcStats,
+    handles::Handles,
+    heap::{ArrayData, Heap, Object, ObjectKind},
+    invokedynamic::LambdaTarget,
+    jni::state::JniState,
+    library::mirror::Mirrors,
+    monitor::{LockKey, Monitor},
+    native::NativeRegistry,
+    options::VmOptions,
+    scheduler::Threads,
+    debugger::Debugger,
+    jdwp::Jdwp,
+    jit::Jit,
+    profile::Profiler,
+    trace::Tracer,
+    value::{ObjRef, Value},
+  },
+  structure::class::{ClassFile, Constant},
+  util::{descriptor::{FieldType, MethodDescriptor}, mutf8},
+};
+
+const MAX_FRAMES: usize = 4096;
+
+pub struct Vm {
+  pub options: VmOptions,
+  pub classes: Vec<Class>,
+  pub class_names: HashMap<String, ClassId>,
+  pub methods: Vec<Rc<RuntimeMethod>>,
+  pub heap: Heap,
+  pub frames: Vec<Frame>,
+  pub handles: Handles,
+  pub gc_stats: GcStats,
+  pub(crate) gc_threshold: usize,
+  // 例外オブジェクトも作れないほどヒープが足りない時に投げる
+  pub(crate) out_of_memory: Option<ObjRef>,
+  // 読み込み中のクラス (循環継承の検出に使う)
+  loading: Vec<String>,
+  // ラムダ式のために作ったクラスと、その呼び出し先
+  pub(crate) lambdas: HashMap<ClassId, Rc<LambdaTarget>>,
+  // String.intern()と文字列リテラルで共有する文字列
+  pub(crate) strings: HashMap<Vec<u16>, ObjRef>,
+  // クラスファイルのネイティブメソッドの実装
+  pub natives: NativeRegistry,
+  // ネイティブメソッドが戻った時に投げる例外
+  pub(crate) pending_exception: Option<ObjRef>,
+  // 実行中のスレッドと、実行権を待っているスレッドの状態
+  pub(crate) threads: Threads,
+  // 所有者かロックを待っているスレッドがいるモニタ
+  pub(crate) monitors: HashMap<LockKey, Monitor>,
+  // ldcやgetClass()で作ったClassオブジェクト
+  pub(crate) mirrors: Mirrors,
+  // System.loadLibrary()で読み込んだ共有ライブラリとJNIの状態
+  pub(crate) jni: JniState,
+  pub(crate) entry_result: Option<Value>,
+  // --traceで実行した命令を記録する
+  pub(crate) tracer: Option<Tracer>,
+  // debugサブコマンドで付けたデバッガ
+  pub(crate) debugger: Option<Debugger>,
+  // -agentlib:jdwpで接続したデバッガ
+  pub(crate) jdwp: Option<Jdwp>,
+  // --profileで付けたプロファイラ
+  pub(crate) profiler: Option<Profiler>,
+  // 呼び出し回数の多いメソッドを機械語にコンパイルする (-Xintでは使わない)
+  pub(crate) jit: Option<Jit>,
+}
+
+impl Vm {
+  pub fn new(options: VmOptions) -> Result<Vm, VmError> {
+    let tracer = match &options.trace {
+      Some(trace) => Some(Tracer::new(trace).map_err(|e| VmError::internal(format!("failed to open trace file: {}", e)))?),
+      None => None,
+    };
+    let profiler = options.profile.as_ref().map(Profiler::new);
+    // トレースやプロファイル、デバッガは命令ごとに呼ぶので、インタプリタだけで実行する
+    let jit = match (&options.jit, &options.trace, &options.profile, &options.jdwp) {
+      (Some(jit), None, None, None) => Some(Jit::new(jit.clone())),
+      _ => None,
+    };
+    let mut vm = Vm {
+      options,
+      classes: Vec::new(),
+      class_names: HashMap::new(),
+      methods: Vec::new(),
+      heap: Heap::new(),
+      frames: Vec::new(),
+      handles: Handles::default(),
+      gc_stats: GcStats::default(),
+      gc_threshold: 0,
+      out_of_memory: None,
+      loading: Vec::new(),
+      lambdas: HashMap::new(),
+      strings: HashMap::new(),
+      natives: NativeRegistry::with_intrinsics(),
+      pending_exception: None,
+      threads: Threads::default(),
+      monitors: HashMap::new(),
+      mirrors: Mirrors::default(),
+      jni: JniState::default(),
+      entry_result: None,
+      tracer,
+      debugger: None,
+      jdwp: None,
+      profiler,
+      jit,
+    };
+    if vm.options.boot_class_path.is_none() {
+      builtin::define_builtin_classes(&mut vm)?;
+    }
+    // 実際のjava.baseでは起動直後に例外を作れないことがあるので、失敗しても続ける
+    vm.out_of_memory = vm.new_throwable("java/lang/OutOfMemoryError", Some("Java heap space"), None).ok();
+    Ok(vm)
+  }
+
+  pub fn class_file(&self, id: ClassId) -> Result<Rc<ClassFile>, VmError> {
+    self.classes[id].class_file.clone()
+      .ok_or_else(|| VmError::internal(format!("{} has no constant pool", self.classes[id].name)))
+  }
+
+  pub fn load_class(&mut self, name: &str) -> Result<ClassId, VmError> {
+    if let Some(&id) = self.class_names.get(name) {
+      return Ok(id);
+    }
+    if name.starts_with('[') {
+      return self.load_array_class(name);
+    }
+    if self.loading.iter().any(|n| n == name) {
+      return Err(VmError::java("java/lang/ClassCircularityError", name));
+    }
+    let bytes = self.find_class_bytes(name)
+      .ok_or_else(|| VmError::java("java/lang/NoClassDefFoundError", name))?;
+    self.define_class_bytes(Some(name), &bytes)
+  }
+
+  // クラスファイルのバイト列からクラスを定義する (名前が指定されていれば一致するか確かめる)
+  pub fn define_class_bytes(&mut self, name: Option<&str>, bytes: &[u8]) -> Result<ClassId, VmError> {
+    let label = name.unwrap_or("Unknown");
+    let class_file = class_leader::parse_bytes(bytes)
+      .map_err(|e| VmError::java("java/lang/ClassFormatError", format!("{}: {}", label, e)))?;
+    let definition = ClassDefinition::from_class_file(class_file)
+      .map_err(|e| VmError::java("java/lang/ClassFormatError", format!("{}: {}", label, e)))?;
+    if let Some(name) = name
+      && definition.name != name {
+      return Err(VmError::java("java/lang/NoClassDefFoundError", format!("{} (wrong name: {})", name, definition.name)));
+    }
+    if self.class_names.contains_key(&definition.name) {
+      return Err(VmError::java("java/lang/LinkageError", format!(
+        "attempted duplicate class definition for {}.", definition.name.replace('/', "."),
+      )));
+    }
+    self.loading.push(definition.name.clone());
+    let result = self.define_class(definition);
+    self.loading.pop();
+    result
+  }
+
+  pub(crate) fn find_class_bytes(&self, name: &str) -> Option<Vec<u8>> {
+    self.options.boot_class_path.iter().flatten()
+      .chain(&self.options.class_path)
+      .map(|dir| dir.join(format!("{}.class", name)))
+      .find_map(|path| fs::read(path).ok())
+  }
+
+  fn load_array_class(&mut self, name: &str) -> Result<ClassId, VmError> {
+    let component = match FieldType::parse(name)? {
+      FieldType::Array(component) => *component,
+      _ => return Err(VmError::java("java/lang/NoClassDefFoundError", name)),
+    };
+    let component_class = match &component {
+      FieldType::Object(_) | FieldType::Array(_) => Some(self.load_class(&component.class_name())?),
+      _ => None,
+    };
+    let object = self.load_class("java/lang/Object")?;
+    let interfaces = vec![self.load_class("java/lang/Cloneable")?, self.load_class("java/io/Serializable")?];
+    let id = self.classes.len();
+    let vtable = self.classes[object].vtable.clone();
+    self.classes.push(Class {
+      id,
+      name: name.to_string(),
+      access_flags: ACC_PUBLIC | ACC_FINAL | ACC_ABSTRACT,
+      super_class: Some(object),
+      interfaces,
+      fields: Vec::new(),
+      methods: Vec::new(),
+      instance_defaults: Vec::new(),
+      static_values: Vec::new(),
+      component: Some(component),
+      component_class,
+      source_file: None,
+      class_file: None,
+      init_state: InitState::Initialized,
+      init_error: None,
+      resolved: Vec::new(),
+      call_caches: Vec::new(),
+      vtable,
+      itable: Vec::new(),
+      nest_host: None,
+      nest_members: Vec::new(),
+    });
+    self.class_names.insert(name.to_string(), id);
+    Ok(id)
+  }
+
+  pub fn define_class(&mut self, definition: ClassDefinition) -> Result<ClassId, VmError> {
+    let super_class = match &definition.super_class {
+      Some(name) => Some(self.load_class(name)?),
+      None => None,
+    };
+    let interfaces = definition.interfaces.iter()
+      .map(|name| self.load_class(name))
+      .collect::<Result<Vec<_>, _>>()?;
+    if let Some(s) = super_class {
+      if self.classes[s].is_interface() {
+        return Err(VmError::java("java/lang/IncompatibleClassChangeError", format!(
+          "class {} has interface {} as super class", definition.name.replace('/', "."), self.classes[s].java_name(),
+        )));
+      }
+      if self.classes[s].access_flags & ACC_FINAL != 0 {
+        return Err(VmError::java("java/lang/VerifyError", format!(
+          "Cannot inherit from final class {}", self.classes[s].java_name(),
+        )));
+      }
+    }
+    if let Some(&i) = interfaces.iter().find(|&&i| !self.classes[i].is_interface()) {
+      return Err(VmError::java("java/lang/IncompatibleClassChangeError", format!(
+        "class {} can not implement {}, because it is not an interface", definition.name.replace('/', "."), self.classes[i].java_name(),
+      )));
+    }
+
+    let id = self.classes.len();
+    let mut instance_defaults = match super_class {
+      Some(s) => self.classes[s].instance_defaults.clone(),
+      None => Vec::new(),
+    };
+    let mut static_values = Vec::new();
+    let mut fields = Vec::new();
+    for field in definition.fields {
+      let field_type = FieldType::parse(&field.descriptor)
+        .map_err(|e| VmError::java("java/lang/ClassFormatError", e))?;
+      let default = Value::default_for(&field_type);
+      let mut runtime_field = RuntimeField {
+        name: field.name,
+        descriptor: field.descriptor,
+        field_type,
+        access_flags: field.access_flags,
+        slot: 0,
+        constant_value: field.constant_value,
+      };
+      if runtime_field.is_static() {
+        runtime_field.slot = static_values.len();
+        static_values.push(default);
+      } else {
+        runtime_field.slot = instance_defaults.len();
+        instance_defaults.push(default);
+      }
+      fields.push(runtime_field);
+    }
+
+    let mut methods = Vec::new();
+    for method in definition.methods {
+      let signature = MethodDescriptor::parse(&method.descriptor)
+        .map_err(|e| VmError::java("java/lang/ClassFormatError", e))?;
+      methods.push(RuntimeMethod {
+        id: self.methods.len() + methods.len(),
+        class: id,
+        class_name: definition.name.clone(),
+        name: method.name,
+        descriptor: method.descriptor,
+        access_flags: method.access_flags,
+        signature,
+        body: method.body,
+        vtable_index: None,
+        itable_index: None,
+      });
+    }
+    let is_interface = definition.access_flags & ACC_INTERFACE != 0;
+    let vtable_length = self.assign_table_indices(super_class, is_interface, &mut methods)?;
+    let methods: Vec<Rc<RuntimeMethod>> = methods.into_iter().map(Rc::new).collect();
+    self.methods.extend(methods.iter().cloned());
+    let vtable = self.build_vtable(super_class, vtable_length, &methods)?;
+
+    let constant_count = definition.class_file.as_ref().map_or(0, |cf| cf.constant_pool.count as usize + 1);
+    self.classes.push(Class {
+      id,
+      name: definition.name.clone(),
+      access_flags: definition.access_flags,
+      super_class,
+      interfaces,
+      fields,
+      methods,
+      instance_defaults,
+      static_values,
+      component: None,
+      component_class: None,
+      source_file: definition.source_file,
+      class_file: definition.class_file,
+      init_state: InitState::Uninitialized,
+      init_error: None,
+      resolved: vec![None; constant_count],
+      call_caches: vec![None; constant_count],
+      vtable,
+      itable: Vec::new(),
+      nest_host: definition.nest_host,
+      nest_members: definition.nest_members,
+    });
+    self.classes[id].itable = self.build_itable(id);
+    self.class_names.insert(definition.name, id);
+    self.assign_constant_values(id)?;
+    if self.jdwp.is_some() {
+      self.jdwp_class_prepare(id);
+    }
+    Ok(id)
+  }
+
+  // static finalフィールドのConstantValue属性を反映する
+  fn assign_constant_values(&mut self, id: ClassId) -> Result<(), VmError> {
+    let constants: Vec<(usize, u16)> = self.classes[id].fields.iter()
+      .filter(|f| f.is_static())
+      .filter_map(|f| f.constant_value.map(|c| (f.slot, c)))
+      .collect();
+    for (slot, index) in constants {
+      let value = self.constant_value(id, index)?;
+      self.classes[id].static_values[slot] = value;
+    }
+    Ok(())
+  }
+
+  pub fn constant_value(&mut self, class: ClassId, index: u16) -> Result<Value, VmError> {
+    let class_file = self.class_file(class)?;
+    match class_file.constant_pool.get_class(index)? {
+      Constant::Integer { bytes } => Ok(Value::Int(*bytes as i32)),
+      Constant::Float { bytes } => Ok(Value::Float(f32::from_bits(*bytes))),
+      Constant::Long { high_bytes, low_bytes } => {
+        Ok(Value::Long((((*high_bytes as u64) << 32) | *low_bytes as u64) as i64))
+      },
+      Constant::Double { high_bytes, low_bytes } => {
+        Ok(Value::Double(f64::from_bits(((*high_bytes as u64) << 32) | *low_bytes as u64)))
+      },
+      Constant::String { string_index } => {
+        let chars = match class_file.constant_pool.get_class(*string_index)? {
+          Constant::Utf8 { bytes, .. } => mutf8::decode(bytes),
+          c => return Err(VmError::internal(format!("Expected Utf8 constant, found: {:?}", c))),
+        };
+        Ok(Value::Ref(self.intern(&chars)?))
+      },
+      Constant::Class { .. } => {
+        let resolved = self.resolve_class(class, index)?;
+        Ok(Value::Ref(self.class_mirror(resolved)?))
+      },
+      Constant::MethodHandle { .. } | Constant::MethodType { .. } | Constant::Dynamic { .. } => {
+        self.resolve_loadable(class, index)
+      },
+      c => Err(VmError::internal(format!("Unsupported constant: {:?}", c))),
+    }
+  }
+
+  pub fn is_subclass_of(&self, class: ClassId, super_class: ClassId) -> bool {
+    let mut current = Some(class);
+    while let Some(c) = current {
+      if c == super_class {
+        return true;
+      }
+      current = self.classes[c].super_class;
+    }
+    false
+  }
+
+  pub fn implements(&self, class: ClassId, interface: ClassId) -> bool {
+    let mut current = Some(class);
+    while let Some(c) = current {
+      if c == interface {
+        return true;
+      }
+      if self.classes[c].interfaces.iter().any(|&i| self.implements(i, interface)) {
+        return true;
+      }
+      current = self.classes[c].super_class;
+    }
+    false
+  }
+
+  // fromの値をtoの型へ代入できるか (instanceof/checkcast/aastoreの判定)
+  pub fn is_assignable(&self, from: ClassId, to: ClassId) -> bool {
+    if from == to {
+      return true;
+    }
+    let source = &self.classes[from];
+    let target = &self.classes[to];
+    match (&source.component, &target.component) {
+      (Some(source_component), Some(target_component)) => {
+        match (source.component_class, target.component_class) {
+          (Some(s), Some(t)) => self.is_assignable(s, t),
+          _ => source_component == target_component,
+        }
+      },
+      (Some(_), None) => matches!(target.name.as_str(), "java/lang/Object" | "java/lang/Cloneable" | "java/io/Serializable"),
+      (None, Some(_)) => false,
+      (None, None) => {
+        if target.is_interface() {
+          self.implements(from, to)
+        } else {
+          self.is_subclass_of(from, to)
+        }
+      },
+    }
+  }
+
+  // フィールドの解決 (JVMS 5.4.3.2): 自クラス、スーパーインターフェース、スーパークラスの順に探す
+  pub fn find_field(&self, class: ClassId, name: &str, descriptor: &str) -> Option<(ClassId, usize)> {
+    let c = &self.classes[class];
+    if let Some(index) = c.fields.iter().position(|f| f.name == name && f.descriptor == descriptor) {
+      return Some((class, index));
+    }
+    for &interface in &c.interfaces {
+      if let Some(found) = self.find_field(interface, name, descriptor) {
+        return Some(found);
+      }
+    }
+    c.super_class.and_then(|s| self.find_field(s, name, descriptor))
+  }
+
+  pub fn object_class(&self, object: ObjRef) -> Result<ClassId, VmError> {
+    Ok(self.heap.get(object)?.class)
+  }
+
+  // Object.hashCode()の値 (オブジェクトは移動しないので位置を使う)
+  pub fn identity_hash(&self, object: ObjRef) -> i32 {
+    object.0 as i32
+  }
+
+  pub fn instantiate(&mut self, class: ClassId) -> Result<ObjRef, VmError> {
+    let fields = self.classes[class].instance_defaults.clone();
+    self.allocate(Object { class, kind: ObjectKind::Instance(fields) })
+  }
+
+  pub fn new_array(&mut self, component: &FieldType, length: i32) -> Result<ObjRef, VmError> {
+    if length < 0 {
+      return Err(VmError::java("java/lang/NegativeArraySizeException", length.to_string()));
+    }
+    let class = self.load_class(&format!("[{}", component.descriptor()))?;
+    let data = ArrayData::new(component, length as usize);
+    self.allocate(Object { class, kind: ObjectKind::Array(data) })
+  }
+
+  pub fn new_string(&mut self, value: &str) -> Result<ObjRef, VmError> {
+    self.new_string_utf16(&value.encode_utf16().collect::<Vec<_>>())
+  }
+
+  pub fn new_string_utf16(&mut self, chars: &[u16]) -> Result<ObjRef, VmError> {
+    let class = self.load_class("java/lang/String")?;
+    let string = self.instantiate(class)?;
+    let mark = self.handles.mark();
+    self.handles.new_local(string);
+    let result = self.init_string(string, chars);
+    self.handles.release(mark);
+    result.map(|_| string)
+  }
+
+  // 文字列リテラルとString.intern(): 同じ内容なら同じオブジェクトを返す
+  pub fn intern(&mut self, chars: &[u16]) -> Result<ObjRef, VmError> {
+    if let Some(&string) = self.strings.get(chars) {
+      return Ok(string);
+    }
+    let string = self.new_string_utf16(chars)?;
+    self.strings.insert(chars.to_vec(), string);
+    Ok(string)
+  }
+
+  // 組み込みのStringはchar[]、JDKのStringはbyte[]とcoder (0: LATIN1, 1: UTF16) で内容を持つ
+  pub(crate) fn init_string(&mut self, string: ObjRef, chars: &[u16]) -> Result<(), VmError> {
+    let class = self.object_class(string)?;
+    let compact = self.find_field(class, "value", "[B").is_some();
+    let latin1 = chars.iter().all(|&c| c < 0x100);
+    let (component, data) = match (compact, latin1) {
+      (false, _) => (FieldType::Char, ArrayData::Char(chars.to_vec())),
+      (true, true) => (FieldType::Byte, ArrayData::Byte(chars.iter().map(|&c| c as i8).collect())),
+      (true, false) => (FieldType::Byte, ArrayData::Byte(chars.iter().flat_map(|c| c.to_le_bytes()).map(|b| b as i8).collect())),
+    };
+    let array = self.new_array(&component, data.len() as i32)?;
+    *self.heap.get_mut(array)?.array_mut()? = data;
+    if compact {
+      self.set_field(string, "value", "[B", Value::Ref(array))?;
+      self.set_field(string, "coder", "B", Value::Int(if latin1 { 0 } else { 1 }))
+    } else {
+      self.set_field(string, "value", "[C", Value::Ref(array))
+    }
+  }
+
+  pub fn string_value(&self, string: ObjRef) -> Result<String, VmError> {
+    Ok(String::from_utf16_lossy(&self.string_chars(string)?))
+  }
+
+  pub fn string_chars(&self, string: ObjRef) -> Result<Vec<u16>, VmError> {
+    let class = self.object_class(string)?;
+    let descriptor = if self.find_field(class, "value", "[B").is_some() { "[B" } else { "[C" };
+    let array = self.get_field(string, "value", descriptor)?.as_ref()?
+      .ok_or_else(|| VmError::internal("String without value"))?;
+    match self.heap.get(array)?.array()? {
+      ArrayData::Char(chars) => Ok(chars.clone()),
+      ArrayData::Byte(bytes) if self.get_field(string, "coder", "B")?.as_int()? == 0 => {
+        Ok(bytes.iter().map(|&b| b as u8 as u16).collect())
+      },
+      ArrayData::Byte(bytes) => Ok(bytes.chunks(2).map(|pair| u16::from_le_bytes([pair[0] as u8, pair[1] as u8])).collect()),
+      _ => Err(VmError::internal("String value is not a char array")),
+    }
+  }
+
+  fn instance_field_slot(&self, object: ObjRef, name: &str, descriptor: &str) -> Result<usize, VmError> {
+    let class = self.object_class(object)?;
+    let (owner, index) = self.find_field(class, name, descriptor)
+      .ok_or_else(|| VmError::java("java/lang/NoSuchFieldError", name))?;
+    Ok(self.classes[owner].fields[index].slot)
+  }
+
+  pub fn get_field(&self, object: ObjRef, name: &str, descriptor: &str) -> Result<Value, VmError> {
+    let slot = self.instance_field_slot(object, name, descriptor)?;
+    Ok(self.heap.get(object)?.fields()?[slot])
+  }
+
+  pub fn set_field(&mut self, object: ObjRef, name: &str, descriptor: &str, value: Value) -> Result<(), VmError> {
+    let slot = self.instance_field_slot(object, name, descriptor)?;
+    self.heap.get_mut(object)?.fields_mut()?[slot] = value;
+    Ok(())
+  }
+
+  pub fn get_static_field(&self, class: ClassId, name: &str, descriptor: &str) -> Result<Value, VmError> {
+    let (owner, index) = self.find_field(class, name, descriptor)
+      .ok_or_else(|| VmError::java("java/lang/NoSuchFieldError", name))?;
+    Ok(self.classes[owner].static_values[self.classes[owner].fields[index].slot])
+  }
+
+  pub fn set_static_field(&mut self, class: ClassId, name: &str, descriptor: &str, value: Value) -> Result<(), VmError> {
+    let (owner, index) = self.find_field(class, name, descriptor)
+      .ok_or_else(|| VmError::java("java/lang/NoSuchFieldError", name))?;
+    let slot = self.classes[owner].fields[index].slot;
+    self.classes[owner].static_values[slot] = value;
+    Ok(())
+  }
+
+  // synchronizedメソッドなら、フレームを積んでからロックを取る
+  pub fn push_frame(&mut self, mut frame: Frame) -> Result<(), VmError> {
+    if self.frames.len() >= MAX_FRAMES {
+      return Err(VmError::java_without_message("java/lang/StackOverflowError"));
+    }
+    frame.lock = Self::method_lock(&frame.method, &frame.locals);
+    let lock = frame.lock;
+    if self.profiler.is_some() {
+      self.profile_enter(&frame.method);
+    }
+    if self.jit.is_some() {
+      self.jit_count(&frame.method);
+    }
+    self.frames.push(frame);
+    if let Some(lock) = lock {
+      self.monitor_enter(lock);
+    }
+    Ok(())
+  }
+
+  // フレームを取り除き、持っていたロックを解放する
+  pub(crate) fn pop_frame(&mut self) -> Option<Frame> {
+    let frame = self.frames.pop()?;
+    if self.profiler.is_some() {
+      self.profile_exit();
+    }
+    if let Some(lock) = frame.lock {
+      let _ = self.monitor_exit(lock);
+    }
+    Some(frame)
+  }
+
+  // ネイティブ側からJavaのメソッドを呼び出し、戻るまで実行する
+  pub fn invoke(&mut self, method: Rc<RuntimeMethod>, args: Vec<Value>) -> Result<Option<Value>, VmError> {
+    match &method.body {
+      MethodBody::Native(function) => self.invoke_native(&method, *function, args),
+      MethodBody::Abstract => Err(VmError::java(
+        "java/lang/AbstractMethodError",
+        format!("{}.{}{}", method.class_name.replace('/', "."), method.name, method.descriptor),
+      )),
+      MethodBody::Bytecode(_) => {
+        let base = self.frames.len();
+        self.push_frame(Frame::new(method, args, true))?;
+        self.execute(base)
+      },
+    }
+  }
+
+  pub fn run_main(&mut self, main_class: &str, args: &[String]) -> Result<(), VmError> {
+    let class = self.load_class(main_class)?;
+    self.initialize(class)?;
+    let main = self.classes[class].find_method("main", "([Ljava/lang/String;)V")
+      .filter(|m| m.is_static())
+      .ok_or_else(|| VmError::java("java/lang/NoSuchMethodError", format!("{}.main([Ljava/lang/String;)V", main_class)))?;
+    let string_type = FieldType::Object("java/lang/String".to_string());
+    let array = self.new_array(&string_type, args.len() as i32)?;
+    let mark = self.handles.mark();
+    self.handles.new_local(array);
+    for (i, arg) in args.iter().enumerate() {
+      let string = self.new_string(arg)?;
+      self.heap.get_mut(array)?.array_mut()?.set(i, Value::Ref(string))?;
+    }
+    self.handles.release(mark);
+    self.invoke(main, vec![Value::Ref(array)])?;
+    Ok(())
+  }
+}
@@ -2,9 +2,9 @@
 
 use std::{ mem::discriminant };
 
-use nom::{ bytes::complete::take, error::ErrorKind, multi::count, number::complete::{ be_u16, be_u32, be_u8 }, Err, IResult, Parser};
+use nom::{ bytes::complete::take, error::ErrorKind, multi::count, number::complete::{ be_u16, be_u32, be_u8 }, IResult, Parser};
 
-use crate::{structure::code::{CodeByte, CODE_BYTES}, util::{class::{method_access_flags, parse_constant_pool}, hex::{hex_utf8, hex_viewer}}};
+use crate::{structure::code::{instruction_length, CodeByte, CODE_BYTES}, util::{class::parse_constant_pool, hex::hex_utf8}};
 
 #[derive(Debug, Default)]
 pub struct Header {
@@ -26,7 +26,7 @@ impl ConstantPool {
       Ok(c) => {
         let index = discriminant(&Constant::Class { name_index: 0 });
         if index == discriminant(c) {
-          Ok(&c)
+          Ok(c)
         } else {
           Err(format!("Expected Class constant, found: {:?}", c))
         }
@@ -35,12 +35,46 @@ impl ConstantPool {
     }
   }
   pub fn get_class(&self, index: u16) -> Result<&Constant, String> {
-    if index == 0 || index >= self.count {
+    // countはエントリ数 (constant_pool_count - 1) なので、最後のインデックスはcountになる
+    if index == 0 || index > self.count {
       return Err("InvalidIndex".to_string());
     }
     self.constants.get(index as usize - 1)
       .ok_or("NotFound".to_string())
   }
+  pub fn get_utf8(&self, index: u16) -> Result<String, String> {
+    match self.get_class(index)? {
+      Constant::Utf8 { bytes, .. } => Ok(hex_utf8(bytes)),
+      c => Err(format!("Expected Utf8 constant, found: {:?}", c)),
+    }
+  }
+  pub fn get_class_name(&self, index: u16) -> Result<String, String> {
+    match self.get_class(index)? {
+      Constant::Class { name_index } => self.get_utf8(*name_index),
+      c => Err(format!("Expected Class constant, found: {:?}", c)),
+    }
+  }
+  pub fn get_name_and_type(&self, index: u16) -> Result<(String, String), String> {
+    match self.get_class(index)? {
+      Constant::NameAndType { name_index, descriptor_index } => {
+        Ok((self.get_utf8(*name_index)?, self.get_utf8(*descriptor_index)?))
+      },
+      c => Err(format!("Expected NameAndType constant, found: {:?}", c)),
+    }
+  }
+  // Fieldref/Methodref/InterfaceMethodrefを(クラス名, 名前, 記述子)に展開する
+  pub fn get_member_ref(&self, index: u16) -> Result<(String, String, String), String> {
+    match self.get_class(index)? {
+      Constant::Fieldref { class_index, name_and_type_index }
+      | Constant::Methodref { class_index, name_and_type_index }
+      | Constant::InterfaceMethodref { class_index, name_and_type_index } => {
+        let class_name = self.get_class_name(*class_index)?;
+        let (name, descriptor) = self.get_name_and_type(*name_and_type_index)?;
+        Ok((class_name, name, descriptor))
+      },
+      c => Err(format!("Expected member reference constant, found: {:?}", c)),
+    }
+  }
 }
 
 #[derive(Debug, Clone)]
@@ -606,38 +640,28 @@ impl MethodInfoAttribute {
         let (input, max_stack) = be_u16(input)?;
         let (input, max_locals) = be_u16(input)?;
         let (input, code_length) = be_u32(input)?;
+        let (input, code_bytes) = take(code_length as usize)(input)?;
         let mut code: Vec<CodeByte> = Vec::new();
-        let mut code_length_buffer = code_length as usize;
-        let mut input = input;
-
-        while code_length_buffer > 0 {
-            let (input_inner, byte) = be_u8(input)?;
-            code_length_buffer -= 1;
-            input = input_inner;
-
-            let code_byte: CodeByte = CODE_BYTES.get(&byte).cloned().unwrap_or(
+        let mut pc = 0;
+        while pc < code_bytes.len() {
+            let code_byte: CodeByte = CODE_BYTES.get(&code_bytes[pc]).cloned().unwrap_or(
                 CodeByte {
                     name: "Unknown",
-                    opcode: byte,
+                    opcode: code_bytes[pc],
                     length: 1,
                     stack_behavior: "Unknown bytecode",
                     data: Vec::new(),
                 }
             );
-            if code_byte.length == 1 {
-                code.push(code_byte);
-            } else {
-                let mut data = Vec::new();
-                for _ in 1..code_byte.length {
-                    let (input_inner, next_byte) = be_u8(input)?;
-                    code_length_buffer -= 1;
-                    input = input_inner;
-                    data.push(next_byte);
-                }
-                let mut full_code_byte = code_byte.clone();
-                full_code_byte.data = data;
-                code.push(full_code_byte);
-            }
+            // オペランドがcode_lengthを超える命令は読めない
+            let length = match instruction_length(code_bytes, pc) {
+              Some(length) if pc + length <= code_bytes.len() => length,
+              _ => return Err(nom::Err::Error(nom::error::Error::new(&code_bytes[pc..], ErrorKind::LengthValue))),
+            };
+            let mut full_code_byte = code_byte;
+            full_code_byte.data = code_bytes[pc + 1..pc + length].to_vec();
+            code.push(full_code_byte);
+            pc += length;
         }
         let (input, exception_table_length) = be_u16(input)?;
         fn exception_entry(input: &[u8]) -> IResult<&[u8], ExceptionTableEntry> {
@@ -827,8 +851,8 @@ impl MethodInfoAttribute {
 
 #[derive(Debug, Default)]
 pub struct CodeAttributes {
-  attributes_count: u16,
-  attributes: Vec<CodeNestedAttribute>,
+  pub attributes_count: u16,
+  pub attributes: Vec<CodeNestedAttribute>,
 }
 
 #[derive(Debug)]
@@ -1375,11 +1399,11 @@ pub enum StackMapFrame {
     frame_type: u8,
   },
   SameLocals1StackItemFrame {
-    frame_type: u8, // 0-63
+    frame_type: u8, // 64-127
     stack: Vec<VerificationTypeInfo>,
   },
   SameLocals1StackItemFrameExtended {
-    frame_type: u8, // 64-127
+    frame_type: u8, // 247
     offset_delta: u16,
     stack: Vec<VerificationTypeInfo>,
   },
@@ -1417,7 +1441,8 @@ impl StackMapFrame {
         let (input, stack) = VerificationTypeInfo::parse_vec(input)?;
         Ok((input, StackMapFrame::SameLocals1StackItemFrame { frame_type, stack }))
       },
-      128..=247 => {
+      128..=246 => Err(nom::Err::Error(nom::error::Error::new(input, ErrorKind::Tag))),
+      247 => {
         let (input, offset_delta) = be_u16(input)?;
         let (input, stack) = VerificationTypeInfo::parse_vec(input)?;
         Ok((input, StackMapFrame::SameLocals1StackItemFrameExtended { frame_type, offset_delta, stack }))
@@ -1458,6 +1483,7 @@ impl StackMapFrame {
 }
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum VerificationTypeInfo {
   TopVariableInfo { tag: u8 }, // 0
   IntegerVariableInfo { tag: u8 }, // 1
@@ -1471,43 +1497,31 @@ pub enum VerificationTypeInfo {
 }
 
 impl VerificationTypeInfo {
-  pub fn parse(input: &[u8]) -> IResult<&[u8], Vec<Self>> {
-    let mut input = input;
-    let mut vec = Vec::new();
-
-    while !input.is_empty() {
-      let (rest, tag) = be_u8(input)?;
-      input = rest;
-
-      let verification_type_info = match tag {
-        0 => VerificationTypeInfo::TopVariableInfo { tag },
-        1 => VerificationTypeInfo::IntegerVariableInfo { tag },
-        2 => VerificationTypeInfo::FloatVariableInfo { tag },
-        3 => VerificationTypeInfo::LongVariableInfo { tag },
-        4 => VerificationTypeInfo::DoubleVariableInfo { tag },
-        5 => VerificationTypeInfo::NullVariableInfo { tag },
-        6 => VerificationTypeInfo::UninitializedThisVariableInfo { tag },
-        7 => {
-          let (rest, cpool_index) = be_u16(input)?;
-          input = rest;
-          VerificationTypeInfo::ObjectVariableInfo { tag, cpool_index }
-        },
-        8 => {
-          let (rest, offset) = be_u16(input)?;
-          input = rest;
-          VerificationTypeInfo::UninitializedVariableInfo { tag, offset }
-        },
-        _ => return Err(nom::Err::Error(nom::error::Error::new(input, ErrorKind::Tag))),
-      };
+  pub fn parse(input: &[u8]) -> IResult<&[u8], Self> {
+    let (input, tag) = be_u8(input)?;
 
-      vec.push(verification_type_info);
+    match tag {
+      0 => Ok((input, VerificationTypeInfo::TopVariableInfo { tag })),
+      1 => Ok((input, VerificationTypeInfo::IntegerVariableInfo { tag })),
+      2 => Ok((input, VerificationTypeInfo::FloatVariableInfo { tag })),
+      3 => Ok((input, VerificationTypeInfo::LongVariableInfo { tag })),
+      4 => Ok((input, VerificationTypeInfo::DoubleVariableInfo { tag })),
+      5 => Ok((input, VerificationTypeInfo::NullVariableInfo { tag })),
+      6 => Ok((input, VerificationTypeInfo::UninitializedThisVariableInfo { tag })),
+      7 => {
+        let (input, cpool_index) = be_u16(input)?;
+        Ok((input, VerificationTypeInfo::ObjectVariableInfo { tag, cpool_index }))
+      },
+      8 => {
+        let (input, offset) = be_u16(input)?;
+        Ok((input, VerificationTypeInfo::UninitializedVariableInfo { tag, offset }))
+      },
+      _ => Err(nom::Err::Error(nom::error::Error::new(input, ErrorKind::Tag))),
     }
-
-    Ok((input, vec))
   }
 
   pub fn parse_vec(input: &[u8]) -> IResult<&[u8], Vec<Self>> {
-    Self::parse(input)
+    Self::parse_vec_with_count(input, 1)
   }
 
   pub fn parse_vec_with_count(input: &[u8], count: usize) -> IResult<&[u8], Vec<Self>> {
@@ -1517,7 +1531,7 @@ impl VerificationTypeInfo {
     for _ in 0..count {
       let (rest, verification_type_info) = Self::parse(input)?;
       input = rest;
-      vec = verification_type_info;
+      vec.push(verification_type_info);
     }
 
     Ok((input, vec))
@@ -1801,12 +1815,13 @@ impl ElementValue {
     };
 
     match tag {
-      0x42 => { // 'B' for byte
-        let (input, const_value_index) = be_u8(input)?;
+      // B C D F I J S Z s は定数プールの値
+      b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b's' => {
+        let (input, const_value_index) = be_u16(input)?;
         value.value = ElementValueEnum::ConstValueIndex(const_value_index);
         Ok((input, value))
       },
-      0x45 => { // 'E' for enum
+      0x65 => { // 'e' for enum
         let (input, type_name_index) = be_u16(input)?;
         let (input, const_name_index) = be_u16(input)?;
         value.value = ElementValueEnum::EnumConstValue {
@@ -1815,8 +1830,8 @@ impl ElementValue {
         };
         Ok((input, value))
       },
-      0x43 => { // 'C' for class
-        let (input, class_info_index) = be_u8(input)?;
+      0x63 => { // 'c' for class
+        let (input, class_info_index) = be_u16(input)?;
         value.value = ElementValueEnum::ClassInfoIndex(class_info_index);
         Ok((input, value))
       },
@@ -1846,14 +1861,14 @@ impl ElementValue {
 
 #[derive(Debug)]
 pub enum ElementValueEnum {
-  ConstValueIndex(u8),
+  ConstValueIndex(u16),
 
   EnumConstValue {
     type_name_index: u16,
     const_name_index: u16,
   },
 
-  ClassInfoIndex(u8),
+  ClassInfoIndex(u16),
 
   AnnotationValue(Annotation),
 
@@ -1928,7 +1943,7 @@ impl<'a> ClassFileParser {
 
     let class_file = ClassFile {
       header,
-      constant_pool: constant_pool,
+      constant_pool,
       access_flags,
       this_class,
       super_class,
@@ -2008,14 +2023,12 @@ impl<'a> ClassFileParser {
     let (input, name_index) = be_u16(input)?;
     let (input, descriptor_index) = be_u16(input)?;
     let (input, attributes_count) = be_u16(input)?;
-    let name = match self.constant_pool.get_class(name_index).unwrap() {
-      Constant::Utf8 { bytes, .. } => hex_utf8(bytes),
-      _ => return Err(nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Tag))),
-    };
-    let descriptor = match self.constant_pool.get_class(descriptor_index).unwrap() {
-      Constant::Utf8 { bytes, .. } => hex_utf8(bytes),
-      _ => return Err(nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Tag))),
-    };
+    if !matches!(self.constant_pool.get_class(name_index), Ok(Constant::Utf8 { .. })) {
+      return Err(nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Tag)));
+    }
+    if !matches!(self.constant_pool.get_class(descriptor_index), Ok(Constant::Utf8 { .. })) {
+      return Err(nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Tag)));
+    }
 
     let parse_method_info_attribute = |input: &'a [u8]| -> IResult<&'a [u8], MethodInfoAttribute> {
       let (input, index) = be_u16(input)?;
@@ -2067,4 +2080,104 @@ impl<'a> ClassFileParser {
       attributes,
     }))
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn i32s(values: &[i32]) -> Vec<u8> {
+    values.iter().flat_map(|value| value.to_be_bytes()).collect()
+  }
+
+  // 可変長の命令を含むCode属性を読み、命令の区切りが正しいことを確かめる
+  #[test]
+  fn code_attribute_splits_variable_length_instructions() {
+    let mut code = vec![0x03];
+    // pc 1: tableswitch (詰め物2バイト、low=0、high=1)
+    code.extend([0xaa, 0, 0]);
+    code.extend(i32s(&[59, 0, 1, 58, 58]));
+    // pc 24: lookupswitch (詰め物3バイト、1組)
+    code.extend([0xab, 0, 0, 0]);
+    code.extend(i32s(&[35, 1, 7, 35]));
+    // pc 44: wide iinc、pc 50: wide iload
+    code.extend([0xc4, 0x84, 0x01, 0x00, 0xff, 0xff]);
+    code.extend([0xc4, 0x15, 0x01, 0x00]);
+    // pc 54: lstore、pc 56: iinc、pc 59: return
+    code.extend([0x37, 0x02, 0x84, 0x01, 0x05, 0xb1]);
+    assert_eq!(code.len(), 60);
+
+    let mut input = Vec::new();
+    input.extend((2 + 2 + 4 + code.len() as u32 + 2 + 2).to_be_bytes());
+    input.extend([0, 2, 0, 4]);
+    input.extend((code.len() as u32).to_be_bytes());
+    input.extend(&code);
+    input.extend([0, 0, 0, 0]);
+    let (rest, attribute) = MethodInfoAttribute::parse(&input, "Code", 1, &ConstantPool::default()).unwrap();
+    assert!(rest.is_empty());
+    let MethodInfoAttribute::Code(attribute) = attribute else {
+      panic!("expected a Code attribute");
+    };
+    let instructions: Vec<(u8, usize)> = attribute.code.iter().map(|code| (code.opcode, code.data.len())).collect();
+    assert_eq!(
+      instructions,
+      [(0x03, 0), (0xaa, 22), (0xab, 19), (0xc4, 5), (0xc4, 3), (0x37, 1), (0x84, 2), (0xb1, 0)]
+    );
+  }
+
+  #[test]
+  fn code_attribute_rejects_operands_past_code_length() {
+    // code_lengthが2なのに、iincのオペランドは2バイト必要
+    let input = [0, 0, 0, 14, 0, 1, 0, 1, 0, 0, 0, 2, 0x84, 0x01, 0x05, 0, 0, 0, 0];
+    assert!(MethodInfoAttribute::parse(&input, "Code", 1, &ConstantPool::default()).is_err());
+  }
+
+  #[test]
+  fn same_locals_1_stack_item_frame_extended() {
+    let input = [247, 0x01, 0x00, 7, 0x00, 0x05, 0xaa];
+    let (rest, frame) = StackMapFrame::parse(&input).unwrap();
+    assert_eq!(rest, [0xaa]);
+    let StackMapFrame::SameLocals1StackItemFrameExtended { frame_type, offset_delta, stack } = frame else {
+      panic!("unexpected frame {:?}", frame);
+    };
+    assert_eq!((frame_type, offset_delta), (247, 256));
+    assert!(matches!(stack[..], [VerificationTypeInfo::ObjectVariableInfo { tag: 7, cpool_index: 5 }]));
+  }
+
+  #[test]
+  fn reserved_frame_types_are_rejected() {
+    assert!(StackMapFrame::parse(&[128, 0, 0]).is_err());
+    assert!(StackMapFrame::parse(&[246, 0, 0]).is_err());
+  }
+
+  #[test]
+  fn verification_type_info_reads_only_the_given_count() {
+    // append_frame (252 + 1 = 253): local 2つ、後続のバイトは読まない
+    let input = [253, 0, 3, 1, 8, 0, 9, 4];
+    let (rest, frame) = StackMapFrame::parse(&input).unwrap();
+    assert_eq!(rest, [4]);
+    let StackMapFrame::AppendFrame { locals, .. } = frame else {
+      panic!("unexpected frame {:?}", frame);
+    };
+    assert!(matches!(
+      locals[..],
+      [VerificationTypeInfo::IntegerVariableInfo { tag: 1 }, VerificationTypeInfo::UninitializedVariableInfo { tag: 8, offset: 9 }]
+    ));
+    assert!(VerificationTypeInfo::parse(&[9]).is_err());
+    assert!(VerificationTypeInfo::parse(&[7, 0]).is_err());
+  }
+
+  #[test]
+  fn get_class_checks_bounds() {
+    let pool = ConstantPool {
+      count: 2,
+      constants: vec![Constant::Utf8 { length: 1, bytes: b"A".to_vec() }, Constant::Class { name_index: 1 }],
+    };
+    assert!(pool.get_class(0).is_err());
+    assert!(matches!(pool.get_class(1), Ok(Constant::Utf8 { .. })));
+    assert!(matches!(pool.get_class(2), Ok(Constant::Class { name_index: 1 })));
+    assert!(pool.get_class(3).is_err());
+    assert_eq!(pool.get_class_name(2), Ok("A".to_string()));
+    assert!(pool.get_class_name(1).is_err());
+  }
+}
@@ -5,6 +5,8 @@ pub struct CodeByte {
   pub name: &'static str,
   pub opcode: u8,
   pub length: u8,
+  // オペランドスタックの変化 (JVMSの表記)。命令表の資料として持っていて、コードからは読まない
+  #[allow(dead_code)]
   pub stack_behavior: &'static str,
   pub data: Vec<u8>,
 }
@@ -398,7 +400,7 @@ pub static CODE_BYTES: phf::Map<u8, CodeByte> = phf_map! {
   0x37u8 => CodeByte {
     name: "lstore",
     opcode: 0x37,
-    length: 0x01,
+    length: 0x02,
     stack_behavior: "..., value -> ...",
     data: Vec::new()
   },
@@ -937,7 +939,7 @@ pub static CODE_BYTES: phf::Map<u8, CodeByte> = phf_map! {
   0x84u8 => CodeByte {
     name: "iinc",
     opcode: 0x84,
-    length: 0x01,
+    length: 0x03,
     stack_behavior: "No change",
     data: Vec::new()
   },
@@ -1445,4 +1447,183 @@ pub static CODE_BYTES: phf::Map<u8, CodeByte> = phf_map! {
     stack_behavior: "No change",
     data: Vec::new()
   },
-};
\ No newline at end of file
+};
+
+// 命令の長さ (tableswitch/lookupswitch/wideはオペランドから計算する、オペランドが読めなければNone)
+pub fn instruction_length(code: &[u8], pc: usize) -> Option<usize> {
+  let i32_at = |at: usize| code.get(at..at + 4).map(|b| i32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+  let base = pc + 1 + (4 - (pc + 1) % 4) % 4;
+  match *code.get(pc)? {
+    0xaa => {
+      let (low, high) = (i32_at(base + 4)?, i32_at(base + 8)?);
+      Some(base + 12 + (high as i64 - low as i64 + 1).max(0) as usize * 4 - pc)
+    },
+    0xab => Some(base + 8 + i32_at(base + 4)?.max(0) as usize * 8 - pc),
+    0xc4 => match code.get(pc + 1)? {
+      0x84 => Some(6),
+      _ => Some(4),
+    },
+    opcode => Some(CODE_BYTES.get(&opcode).map_or(1, |code_byte| code_byte.length as usize)),
+  }
+}
+
+// 命令のオペランドの表示 (constantはコンスタントプールの番号と後に続くオペランド、targetは分岐先の絶対位置を表示する)
+pub fn operand_text(code: &[u8], pc: usize, constant: &dyn Fn(u16, &str) -> String, target: &dyn Fn(usize) -> String) -> String {
+  let u8_at = |at: usize| code.get(at).copied().unwrap_or(0);
+  let u16_at = |at: usize| u16::from_be_bytes([u8_at(at), u8_at(at + 1)]);
+  let i32_at = |at: usize| i32::from_be_bytes([u8_at(at), u8_at(at + 1), u8_at(at + 2), u8_at(at + 3)]);
+  let branch = |offset: i32| target((pc as i64 + offset as i64) as usize);
+  match u8_at(pc) {
+    0x10 => (u8_at(pc + 1) as i8).to_string(),
+    0x11 => (u16_at(pc + 1) as i16).to_string(),
+    0x12 => constant(u8_at(pc + 1) as u16, ""),
+    0x13 | 0x14 | 0xb2..=0xb8 | 0xbb | 0xbd | 0xc0 | 0xc1 => constant(u16_at(pc + 1), ""),
+    0x15..=0x19 | 0x36..=0x3a | 0xa9 => u8_at(pc + 1).to_string(),
+    0x84 => format!("{}, {}", u8_at(pc + 1), u8_at(pc + 2) as i8),
+    0x99..=0xa8 | 0xc6 | 0xc7 => branch(u16_at(pc + 1) as i16 as i32),
+    0xc8 | 0xc9 => branch(i32_at(pc + 1)),
+    0xaa => {
+      let base = pc + 1 + (4 - (pc + 1) % 4) % 4;
+      let low = i32_at(base + 4);
+      let high = i32_at(base + 8);
+      let cases = (low..=high).enumerate()
+        .map(|(i, key)| format!("{}: {}, ", key, branch(i32_at(base + 12 + i * 4))));
+      format!("{{ {}default: {} }}", cases.collect::<String>(), branch(i32_at(base)))
+    },
+    0xab => {
+      let base = pc + 1 + (4 - (pc + 1) % 4) % 4;
+      let npairs = i32_at(base + 4).max(0) as usize;
+      let cases = (0..npairs)
+        .map(|i| format!("{}: {}, ", i32_at(base + 8 + i * 8), branch(i32_at(base + 12 + i * 8))));
+      format!("{{ {}default: {} }}", cases.collect::<String>(), branch(i32_at(base)))
+    },
+    0xb9 | 0xc5 => constant(u16_at(pc + 1), &format!(", {}", u8_at(pc + 3))),
+    0xba => constant(u16_at(pc + 1), ", 0"),
+    0xbc => match u8_at(pc + 1) {
+      4 => "boolean", 5 => "char", 6 => "float", 7 => "double",
+      8 => "byte", 9 => "short", 10 => "int", 11 => "long",
+      _ => "unknown",
+    }.to_string(),
+    0xc4 => {
+      let modified = u8_at(pc + 1);
+      let name = CODE_BYTES.get(&modified).map_or("unknown", |code_byte| code_byte.name);
+      match modified {
+        0x84 => format!("{} {}, {}", name, u16_at(pc + 2), u16_at(pc + 4) as i16),
+        _ => format!("{} {}", name, u16_at(pc + 2)),
+      }
+    },
+    _ => String::new(),
+  }
+}
+
+// pop, dupなどのスタック操作を、ワード数からスタックの値の個数に直したもの (longとdoubleは1つで2ワードを占める)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackShuffle {
+  Pop(usize),
+  Swap,
+  // 上からtop個の値を複製して、その下のbelow個の値の下に挿入する
+  Dup { top: usize, below: usize },
+}
+
+impl StackShuffle {
+  // wideは上からdepth番目の値が2ワードか (スタックが足りなければNone)
+  pub fn parse(opcode: u8, wide: impl Fn(usize) -> Option<bool>) -> Result<StackShuffle, String> {
+    let count = |skip: usize, words: usize| -> Result<usize, String> {
+      let mut taken = 0;
+      let mut counted = 0;
+      while counted < words {
+        counted += if wide(skip + taken).ok_or("operand stack underflow")? { 2 } else { 1 };
+        taken += 1;
+      }
+      if counted != words {
+        return Err("operand stack splits a category 2 value".to_string());
+      }
+      Ok(taken)
+    };
+    let (top, below) = match opcode {
+      0x57 => return Ok(StackShuffle::Pop(count(0, 1)?)),
+      0x58 => return Ok(StackShuffle::Pop(count(0, 2)?)),
+      0x5f => {
+        count(0, 2)?;
+        return Ok(StackShuffle::Swap);
+      },
+      // dup, dup_x1, dup_x2, dup2, dup2_x1, dup2_x2 (複製するワード数と、その下に挿入するワード数)
+      0x59 => (1, 0),
+      0x5a => (1, 1),
+      0x5b => (1, 2),
+      0x5c => (2, 0),
+      0x5d => (2, 1),
+      0x5e => (2, 2),
+      _ => return Err(format!("unknown stack instruction {:#x}", opcode)),
+    };
+    let top = count(0, top)?;
+    Ok(StackShuffle::Dup { top, below: count(top, below)? })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // lengthはオペコードを含む命令の長さ (可変長の命令は1)
+  #[test]
+  fn instruction_lengths() {
+    let length = |opcode: u8| CODE_BYTES.get(&opcode).unwrap().length;
+    assert_eq!(length(0x36), 2);
+    assert_eq!(length(0x37), 2);
+    assert_eq!(length(0x84), 3);
+    assert_eq!(length(0xb1), 1);
+    assert_eq!(length(0xb9), 5);
+    assert_eq!(length(0xc5), 4);
+  }
+
+  fn i32s(values: &[i32]) -> Vec<u8> {
+    values.iter().flat_map(|value| value.to_be_bytes()).collect()
+  }
+
+  #[test]
+  fn tableswitch_length_includes_padding_and_offsets() {
+    // pc 1のtableswitch: 2バイトの詰め物、default、low=0、high=2、3つのオフセット
+    let mut code = vec![0x03, 0xaa, 0, 0];
+    code.extend(i32s(&[20, 0, 2, 10, 11, 12]));
+    assert_eq!(instruction_length(&code, 1), Some(1 + 2 + 12 + 3 * 4));
+    // pc 3なら詰め物はない
+    let mut code = vec![0x03, 0x03, 0x03, 0xaa];
+    code.extend(i32s(&[20, 0, 2, 10, 11, 12]));
+    assert_eq!(instruction_length(&code, 3), Some(1 + 12 + 3 * 4));
+    // highとlowが読めなければNone
+    assert_eq!(instruction_length(&code[..10], 3), None);
+  }
+
+  #[test]
+  fn lookupswitch_length_counts_pairs() {
+    // pc 0のlookupswitch: 3バイトの詰め物、default、npairs=2、(match, offset)が2組
+    let mut code = vec![0xab, 0, 0, 0];
+    code.extend(i32s(&[30, 2, 1, 10, 5, 20]));
+    assert_eq!(instruction_length(&code, 0), Some(4 + 8 + 2 * 8));
+    // 負のnpairsは0組として扱う
+    let mut code = vec![0xab, 0, 0, 0];
+    code.extend(i32s(&[30, -1]));
+    assert_eq!(instruction_length(&code, 0), Some(4 + 8));
+  }
+
+  #[test]
+  fn wide_length_depends_on_modified_opcode() {
+    assert_eq!(instruction_length(&[0xc4, 0x84, 0, 1, 0, 2], 0), Some(6));
+    assert_eq!(instruction_length(&[0xc4, 0x15, 0, 1], 0), Some(4));
+    assert_eq!(instruction_length(&[0xc4], 0), None);
+    assert_eq!(instruction_length(&[], 0), None);
+  }
+
+  #[test]
+  fn stack_shuffles_count_values() {
+    // 上から long, int, int
+    let stack = [true, false, false];
+    let wide = |depth: usize| stack.get(depth).copied();
+    assert_eq!(StackShuffle::parse(0x58, wide), Ok(StackShuffle::Pop(1)));
+    assert_eq!(StackShuffle::parse(0x5c, wide), Ok(StackShuffle::Dup { top: 1, below: 0 }));
+    assert_eq!(StackShuffle::parse(0x5e, wide), Ok(StackShuffle::Dup { top: 1, below: 2 }));
+    assert_eq!(StackShuffle::parse(0x57, wide), Err("operand stack splits a category 2 value".to_string()));
+    assert_eq!(StackShuffle::parse(0x5b, |depth: usize| [false, false].get(depth).copied()), Err("operand stack underflow".to_string()));
+  }
+}
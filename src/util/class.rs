@@ -6,8 +6,6 @@ use nom::{
 
 use crate::{structure::class::{ Constant, ConstantPool }, util::hex::hex_utf8};
 
-use std::mem::discriminant;
-
 pub fn java_version_name(major: u16) -> &'static str {
   match major {
     69 => "Java SE 25",
@@ -40,11 +38,10 @@ pub fn java_version_name(major: u16) -> &'static str {
 }
 
 pub fn constant_pool_viewer(constant_pool: &[Constant]) {
-  let mut e = constant_pool.iter().enumerate();
-  while let Some((i, constant)) = e.next() {
+  for (i, constant) in constant_pool.iter().enumerate() {
     match constant {
       Constant::Utf8 { length: _, bytes } => {
-        let string = hex_utf8(&bytes);
+        let string = hex_utf8(bytes);
         println!("{:>4} = UTF8 \"{}\"", format!("#{}", i + 1), string);
       },
       _ => {
@@ -58,7 +55,7 @@ pub fn parse_constant_pool(count: u16, input: &[u8]) -> IResult<&[u8], ConstantP
   let mut constants = Vec::new();
   let mut remaining_input = input;
 
-  for _ in 0..count {
+  while constants.len() < count as usize {
     let (input, tag) = be_u8(remaining_input)?;
 
     let constant: Constant = match tag {
@@ -163,7 +160,12 @@ pub fn parse_constant_pool(count: u16, input: &[u8]) -> IResult<&[u8], ConstantP
         Constant::Unknown
       },
     };
+    let wide = matches!(constant, Constant::Long { .. } | Constant::Double { .. });
     constants.push(constant);
+    if wide {
+      // long/doubleはコンスタントプールの2エントリ分を占有する
+      constants.push(Constant::Unknown);
+    }
   }
 
   Ok((remaining_input, ConstantPool {
@@ -172,15 +174,6 @@ pub fn parse_constant_pool(count: u16, input: &[u8]) -> IResult<&[u8], ConstantP
   }))
 }
 
-pub fn check_constant_pool_class(constant: &Constant) -> Result<&Constant, String> {
-  let index = discriminant(&Constant::Class { name_index: 0 });
-  if index == discriminant(constant) {
-    Ok(&constant)
-  } else {
-    Err(format!("Expected Class constant, found: {:?}", constant))
-  }
-}
-
 pub fn class_access_flags(flags: u16) -> String {
   let mut result = String::new();
   if flags & 0x0001 != 0 {
@@ -337,4 +330,45 @@ pub fn inner_class_access_flags(flags: u16) -> String {
   }
   
   result
-}
\ No newline at end of file
+}
+
+// コマンドでのメソッドの指定 ("name"なら同じ名前の全て、"name(I)V"なら記述子まで一致するもの)
+pub fn method_matches(filter: &str, name: &str, descriptor: &str) -> bool {
+  filter == name || filter.strip_prefix(name) == Some(descriptor)
+}
+
+// 内部形式のクラス名やパッケージ名をJavaの表記にする (空なら無名パッケージ)
+pub fn java_name(name: &str) -> String {
+  if name.is_empty() {
+    return "<unnamed>".to_string();
+  }
+  name.replace('/', ".")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // long/doubleの次のインデックスは使われず、その次のエントリが続く
+  #[test]
+  fn long_and_double_take_two_slots() {
+    let mut input = vec![5, 0, 0, 0, 1, 0, 0, 0, 2];
+    input.extend([6, 0x3f, 0xf0, 0, 0, 0, 0, 0, 0]);
+    input.extend([1, 0, 1, b'x', 0xff]);
+    // constant_pool_countは6 (エントリ数5)
+    let (rest, pool) = parse_constant_pool(5, &input).unwrap();
+    assert_eq!(rest, [0xff]);
+    assert_eq!(pool.constants.len(), 5);
+    assert!(matches!(pool.get_class(1), Ok(Constant::Long { high_bytes: 1, low_bytes: 2 })));
+    assert!(matches!(pool.get_class(2), Ok(Constant::Unknown)));
+    assert!(matches!(pool.get_class(3), Ok(Constant::Double { high_bytes: 0x3ff00000, low_bytes: 0 })));
+    assert!(matches!(pool.get_class(4), Ok(Constant::Unknown)));
+    assert_eq!(pool.get_utf8(5), Ok("x".to_string()));
+    assert!(pool.get_class(6).is_err());
+  }
+
+  #[test]
+  fn truncated_constant_pool_is_an_error() {
+    assert!(parse_constant_pool(2, &[5, 0, 0, 0, 1]).is_err());
+  }
+}
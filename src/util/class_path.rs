@@ -0,0 +1,195 @@
+use std::{
+  fs, io,
+  path::{Path, PathBuf},
+};
+
+use crate::{class_leader, structure::class::ClassFileAttribute, util::zip::ZipArchive};
+
+// ディレクトリとjarを並べたクラスパス (実行時のVmとは別に、解析のためにクラスファイルを探す)
+
+pub enum ClassPathEntry {
+  Directory(PathBuf),
+  Jar(PathBuf, ZipArchive),
+}
+
+#[derive(Default)]
+pub struct ClassPath {
+  pub entries: Vec<ClassPathEntry>,
+}
+
+// ディレクトリ以下の.classファイルを内部名にする
+fn walk(root: &Path, dir: &Path, names: &mut Vec<String>) {
+  let Ok(read_dir) = fs::read_dir(dir) else {
+    return;
+  };
+  for entry in read_dir.flatten() {
+    let path = entry.path();
+    if path.is_dir() {
+      walk(root, &path, names);
+    } else if let Ok(relative) = path.strip_prefix(root)
+      && let Some(name) = relative.to_str().and_then(|name| name.strip_suffix(".class"))
+    {
+      names.push(name.replace(std::path::MAIN_SEPARATOR, "/"));
+    }
+  }
+}
+
+// module-info.classのModule属性のモジュール名
+fn module_info_name(bytes: &[u8]) -> Option<String> {
+  let class_file = class_leader::parse_bytes(bytes).ok()?;
+  class_file.attributes.attributes.iter().find_map(|attribute| match attribute {
+    ClassFileAttribute::Module(module) => match class_file.constant_pool.get_class(module.module_name_index).ok()? {
+      crate::structure::class::Constant::Module { name_index } => class_file.constant_pool.get_utf8(*name_index).ok(),
+      _ => None,
+    },
+    _ => None,
+  })
+}
+
+impl ClassPathEntry {
+  pub fn open(path: &Path) -> io::Result<ClassPathEntry> {
+    if path.is_dir() {
+      return Ok(ClassPathEntry::Directory(path.to_path_buf()));
+    }
+    Ok(ClassPathEntry::Jar(path.to_path_buf(), ZipArchive::open(path)?))
+  }
+
+  pub fn path(&self) -> &Path {
+    match self {
+      ClassPathEntry::Directory(path) | ClassPathEntry::Jar(path, _) => path,
+    }
+  }
+
+  // ファイルの読み込み (nameは'/'区切りの相対パス)
+  pub fn read_file(&self, name: &str) -> Option<Vec<u8>> {
+    match self {
+      ClassPathEntry::Directory(path) => fs::read(path.join(name)).ok(),
+      ClassPathEntry::Jar(_, archive) => archive.find(name).and_then(|entry| archive.read(entry).ok()),
+    }
+  }
+
+  pub fn read(&self, class_name: &str) -> Option<Vec<u8>> {
+    self.read_file(&format!("{}.class", class_name))
+  }
+
+  pub fn contains(&self, class_name: &str) -> bool {
+    match self {
+      ClassPathEntry::Directory(path) => path.join(format!("{}.class", class_name)).is_file(),
+      ClassPathEntry::Jar(_, archive) => archive.find(&format!("{}.class", class_name)).is_some(),
+    }
+  }
+
+  // 含まれる全てのクラスの内部名 (module-infoとMETA-INF以下を除く)
+  pub fn class_names(&self) -> Vec<String> {
+    let mut names = Vec::new();
+    match self {
+      ClassPathEntry::Directory(path) => walk(path, path, &mut names),
+      ClassPathEntry::Jar(_, archive) => {
+        names.extend(archive.entries.iter().filter_map(|entry| entry.name.strip_suffix(".class")).map(str::to_string));
+      },
+    }
+    names.retain(|name| !name.starts_with("META-INF/") && !name.ends_with("module-info") && !name.ends_with("package-info"));
+    names.sort();
+    names
+  }
+
+  // module-info.classのモジュール名、なければマニフェストのAutomatic-Module-Name
+  pub fn module_name(&self) -> Option<String> {
+    if let Some(name) = self.read("module-info").and_then(|bytes| module_info_name(&bytes)) {
+      return Some(name);
+    }
+    let manifest = String::from_utf8(self.read_file("META-INF/MANIFEST.MF")?).ok()?;
+    manifest.lines().find_map(|line| line.strip_prefix("Automatic-Module-Name:")).map(|name| name.trim().to_string())
+  }
+
+  // 表示用の名前 (モジュール名かファイル名)
+  pub fn label(&self) -> String {
+    self.module_name().unwrap_or_else(|| {
+      let path = self.path();
+      path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_else(|| path.display().to_string())
+    })
+  }
+}
+
+// コマンドラインの-cp (-classpath、--class-path) と-Xbootclasspath:の値
+#[derive(Debug, Default)]
+pub struct ClassPathOptions {
+  pub class_path: Option<String>,
+  pub boot_class_path: Option<String>,
+}
+
+impl ClassPathOptions {
+  // args[0]がクラスパスの指定なら読み、使った引数の数を返す (指定でなければ0)
+  pub fn parse_arg(&mut self, args: &[String]) -> Result<usize, String> {
+    let Some(arg) = args.first() else {
+      return Ok(0);
+    };
+    if arg == "-cp" || arg == "-classpath" || arg == "--class-path" {
+      let value = args.get(1).ok_or(format!("{} requires a path", arg))?;
+      self.class_path = Some(value.clone());
+      Ok(2)
+    } else if let Some(value) = arg.strip_prefix("-Xbootclasspath:") {
+      self.boot_class_path = Some(value.to_string());
+      Ok(1)
+    } else {
+      Ok(0)
+    }
+  }
+
+  // 指定が無ければdefaultを使う
+  pub fn class_path(&self, default: &str) -> io::Result<ClassPath> {
+    ClassPath::parse(self.class_path.as_deref().unwrap_or(default))
+  }
+
+  pub fn boot_class_path(&self) -> io::Result<Option<ClassPath>> {
+    self.boot_class_path.as_deref().map(ClassPath::parse).transpose()
+  }
+}
+
+// ':'区切りのパスを並べる (実行時のVmのクラスパス)
+pub fn split_paths(text: &str) -> Vec<PathBuf> {
+  text.split(':').filter(|part| !part.is_empty()).map(PathBuf::from).collect()
+}
+
+impl ClassPath {
+  // ':'区切りのパス
+  pub fn parse(text: &str) -> io::Result<ClassPath> {
+    let mut class_path = ClassPath::default();
+    for part in text.split(':').filter(|part| !part.is_empty()) {
+      let entry = ClassPathEntry::open(Path::new(part)).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", part, e)))?;
+      class_path.entries.push(entry);
+    }
+    Ok(class_path)
+  }
+
+  // クラスが見つかったエントリの番号
+  pub fn find(&self, class_name: &str) -> Option<usize> {
+    self.entries.iter().position(|entry| entry.contains(class_name))
+  }
+
+  pub fn read(&self, class_name: &str) -> Option<Vec<u8>> {
+    self.entries.iter().find_map(|entry| entry.read(class_name))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+  }
+
+  #[test]
+  fn class_path_options_consume_their_values() {
+    let mut options = ClassPathOptions::default();
+    assert_eq!(options.parse_arg(&args(&["-classpath", "a:b", "Main"])), Ok(2));
+    assert_eq!(options.parse_arg(&args(&["-Xbootclasspath:boot.jar", "Main"])), Ok(1));
+    assert_eq!(options.parse_arg(&args(&["--classes"])), Ok(0));
+    assert_eq!(options.parse_arg(&[]), Ok(0));
+    assert!(options.parse_arg(&args(&["-cp"])).is_err());
+    assert_eq!(options.class_path.as_deref(), Some("a:b"));
+    assert_eq!(options.boot_class_path.as_deref(), Some("boot.jar"));
+    assert_eq!(split_paths("a::b"), [PathBuf::from("a"), PathBuf::from("b")]);
+  }
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FieldType {
+  Byte,
+  Char,
+  Double,
+  Float,
+  Int,
+  Long,
+  Short,
+  Boolean,
+  Object(String),
+  Array(Box<FieldType>),
+}
+
+impl FieldType {
+  pub fn parse(descriptor: &str) -> Result<FieldType, String> {
+    let bytes = descriptor.as_bytes();
+    let mut pos = 0;
+    let field_type = Self::parse_at(bytes, &mut pos)?;
+    if pos != bytes.len() {
+      return Err(format!("Invalid field descriptor: {}", descriptor));
+    }
+    Ok(field_type)
+  }
+
+  fn parse_at(bytes: &[u8], pos: &mut usize) -> Result<FieldType, String> {
+    let tag = *bytes.get(*pos).ok_or("Unexpected end of descriptor")?;
+    *pos += 1;
+    match tag {
+      b'B' => Ok(FieldType::Byte),
+      b'C' => Ok(FieldType::Char),
+      b'D' => Ok(FieldType::Double),
+      b'F' => Ok(FieldType::Float),
+      b'I' => Ok(FieldType::Int),
+      b'J' => Ok(FieldType::Long),
+      b'S' => Ok(FieldType::Short),
+      b'Z' => Ok(FieldType::Boolean),
+      b'L' => {
+        let start = *pos;
+        while *pos < bytes.len() && bytes[*pos] != b';' {
+          *pos += 1;
+        }
+        if *pos >= bytes.len() {
+          return Err("Unterminated class name in descriptor".to_string());
+        }
+        let name = String::from_utf8_lossy(&bytes[start..*pos]).into_owned();
+        *pos += 1;
+        Ok(FieldType::Object(name))
+      },
+      b'[' => Ok(FieldType::Array(Box::new(Self::parse_at(bytes, pos)?))),
+      _ => Err(format!("Invalid descriptor tag: {}", tag as char)),
+    }
+  }
+
+  // long/doubleはローカル変数を2スロット使う
+  pub fn is_wide(&self) -> bool {
+    matches!(self, FieldType::Long | FieldType::Double)
+  }
+
+  pub fn is_reference(&self) -> bool {
+    matches!(self, FieldType::Object(_) | FieldType::Array(_))
+  }
+
+  pub fn descriptor(&self) -> String {
+    match self {
+      FieldType::Byte => "B".to_string(),
+      FieldType::Char => "C".to_string(),
+      FieldType::Double => "D".to_string(),
+      FieldType::Float => "F".to_string(),
+      FieldType::Int => "I".to_string(),
+      FieldType::Long => "J".to_string(),
+      FieldType::Short => "S".to_string(),
+      FieldType::Boolean => "Z".to_string(),
+      FieldType::Object(name) => format!("L{};", name),
+      FieldType::Array(component) => format!("[{}", component.descriptor()),
+    }
+  }
+
+  // クラスとして扱う時の内部名 (配列は記述子そのもの)
+  pub fn class_name(&self) -> String {
+    match self {
+      FieldType::Object(name) => name.clone(),
+      _ => self.descriptor(),
+    }
+  }
+}
+
+impl fmt::Display for FieldType {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      FieldType::Byte => write!(f, "byte"),
+      FieldType::Char => write!(f, "char"),
+      FieldType::Double => write!(f, "double"),
+      FieldType::Float => write!(f, "float"),
+      FieldType::Int => write!(f, "int"),
+      FieldType::Long => write!(f, "long"),
+      FieldType::Short => write!(f, "short"),
+      FieldType::Boolean => write!(f, "boolean"),
+      FieldType::Object(name) => write!(f, "{}", name.replace('/', ".")),
+      FieldType::Array(component) => write!(f, "{}[]", component),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MethodDescriptor {
+  pub parameters: Vec<FieldType>,
+  pub return_type: Option<FieldType>,
+}
+
+impl MethodDescriptor {
+  pub fn parse(descriptor: &str) -> Result<MethodDescriptor, String> {
+    let bytes = descriptor.as_bytes();
+    if bytes.first() != Some(&b'(') {
+      return Err(format!("Invalid method descriptor: {}", descriptor));
+    }
+    let mut pos = 1;
+    let mut parameters = Vec::new();
+    while bytes.get(pos) != Some(&b')') {
+      if pos >= bytes.len() {
+        return Err(format!("Invalid method descriptor: {}", descriptor));
+      }
+      parameters.push(FieldType::parse_at(bytes, &mut pos)?);
+    }
+    pos += 1;
+    let return_type = if bytes.get(pos) == Some(&b'V') {
+      pos += 1;
+      None
+    } else {
+      Some(FieldType::parse_at(bytes, &mut pos)?)
+    };
+    if pos != bytes.len() {
+      return Err(format!("Invalid method descriptor: {}", descriptor));
+    }
+    Ok(MethodDescriptor { parameters, return_type })
+  }
+
+  pub fn descriptor(&self) -> String {
+    let parameters: String = self.parameters.iter().map(|p| p.descriptor()).collect();
+    let return_type = self.return_type.as_ref().map_or("V".to_string(), |r| r.descriptor());
+    format!("({}){}", parameters, return_type)
+  }
+
+  // 引数が占めるローカル変数のスロット数 (thisは含まない)
+  pub fn parameter_slots(&self) -> usize {
+    self.parameters.iter().map(|p| if p.is_wide() { 2 } else { 1 }).sum()
+  }
+}
@@ -0,0 +1,15 @@
+// GraphvizのDOTの文字列 ("..."の中に入れる)
+pub fn escape(text: &str) -> String {
+  text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn quotes_backslashes_and_newlines_are_escaped() {
+    assert_eq!(escape("a.B.m(Ljava/lang/String;)V"), "a.B.m(Ljava/lang/String;)V");
+    assert_eq!(escape("ldc \"x\\y\"\n"), "ldc \\\"x\\\\y\\\"\\n");
+  }
+}
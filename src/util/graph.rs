@@ -0,0 +1,145 @@
+// ノードを0..nの番号で表し、各ノードの後続のリストで与えた有向グラフの解析
+
+pub fn predecessors(successors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+  let mut predecessors = vec![Vec::new(); successors.len()];
+  for (node, targets) in successors.iter().enumerate() {
+    for &target in targets {
+      if !predecessors[target].contains(&node) {
+        predecessors[target].push(node);
+      }
+    }
+  }
+  predecessors
+}
+
+// entryから到達できるノードの逆後順 (再帰を使わない深さ優先探索)
+pub fn reverse_postorder(successors: &[Vec<usize>], entry: usize) -> Vec<usize> {
+  let mut visited = vec![false; successors.len()];
+  let mut order = Vec::new();
+  let mut stack = vec![(entry, 0)];
+  visited[entry] = true;
+  while let Some((node, next)) = stack.last_mut() {
+    let node = *node;
+    if let Some(&target) = successors[node].get(*next) {
+      *next += 1;
+      if !visited[target] {
+        visited[target] = true;
+        stack.push((target, 0));
+      }
+    } else {
+      order.push(node);
+      stack.pop();
+    }
+  }
+  order.reverse();
+  order
+}
+
+// 各ノードの直接支配ノード (entryと到達できないノードはNone)
+// Cooper, Harvey, Kennedy "A Simple, Fast Dominance Algorithm"
+pub fn immediate_dominators(successors: &[Vec<usize>], entry: usize) -> Vec<Option<usize>> {
+  let order = reverse_postorder(successors, entry);
+  let mut position = vec![usize::MAX; successors.len()];
+  for (i, &node) in order.iter().enumerate() {
+    position[node] = i;
+  }
+  let predecessors = predecessors(successors);
+  let mut dominators = vec![None; successors.len()];
+  dominators[entry] = Some(entry);
+  let mut changed = true;
+  while changed {
+    changed = false;
+    for &node in order.iter().skip(1) {
+      let mut dominator = None;
+      for &predecessor in predecessors[node].iter().filter(|&&p| dominators[p].is_some()) {
+        dominator = Some(match dominator {
+          None => predecessor,
+          Some(current) => intersect(&dominators, &position, predecessor, current),
+        });
+      }
+      if dominator.is_some() && dominators[node] != dominator {
+        dominators[node] = dominator;
+        changed = true;
+      }
+    }
+  }
+  dominators[entry] = None;
+  dominators
+}
+
+fn intersect(dominators: &[Option<usize>], position: &[usize], mut a: usize, mut b: usize) -> usize {
+  while a != b {
+    while position[a] > position[b] {
+      a = dominators[a].expect("processed node has a dominator");
+    }
+    while position[b] > position[a] {
+      b = dominators[b].expect("processed node has a dominator");
+    }
+  }
+  a
+}
+
+// aがbを支配するか (ノードは自身を支配する)
+pub fn dominates(dominators: &[Option<usize>], a: usize, b: usize) -> bool {
+  let mut node = Some(b);
+  while let Some(current) = node {
+    if current == a {
+      return true;
+    }
+    node = dominators[current];
+  }
+  false
+}
+
+// 支配木の子
+pub fn dominator_tree(dominators: &[Option<usize>]) -> Vec<Vec<usize>> {
+  let mut children = vec![Vec::new(); dominators.len()];
+  for (node, dominator) in dominators.iter().enumerate() {
+    if let Some(dominator) = dominator {
+      children[*dominator].push(node);
+    }
+  }
+  children
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NaturalLoop {
+  pub header: usize,
+  // ヘッダを含むループ内のノード (昇順)
+  pub body: Vec<usize>,
+  // ヘッダに戻る辺の始点
+  pub latches: Vec<usize>,
+  // 外側のループ (戻り値の添字)
+  pub parent: Option<usize>,
+}
+
+// ヘッダが支配するノードからの後ろ向きの辺で決まる自然ループ (同じヘッダのループは1つにまとめる)
+// 外側のループが先に来る
+pub fn natural_loops(successors: &[Vec<usize>], entry: usize) -> Vec<NaturalLoop> {
+  let dominators = immediate_dominators(successors, entry);
+  let predecessors = predecessors(successors);
+  let mut loops: Vec<NaturalLoop> = Vec::new();
+  for header in reverse_postorder(successors, entry) {
+    let latches: Vec<usize> = predecessors[header].iter().copied().filter(|&p| dominates(&dominators, header, p)).collect();
+    if latches.is_empty() {
+      continue;
+    }
+    let mut body = vec![header];
+    let mut stack = latches.clone();
+    while let Some(node) = stack.pop() {
+      if body.contains(&node) {
+        continue;
+      }
+      body.push(node);
+      stack.extend(predecessors[node].iter().copied().filter(|&p| p == entry || dominators[p].is_some()));
+    }
+    body.sort_unstable();
+    loops.push(NaturalLoop { header, body, latches, parent: None });
+  }
+  for i in 0..loops.len() {
+    loops[i].parent = (0..loops.len())
+      .filter(|&j| j != i && loops[j].body.contains(&loops[i].header) && loops[j].body.len() > loops[i].body.len())
+      .min_by_key(|&j| loops[j].body.len());
+  }
+  loops
+}
@@ -0,0 +1,176 @@
+// DEFLATE (RFC 1951) の展開
+
+// 長さの符号257..285の基本値と追加ビット数
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [u16; 30] =
+  [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+// 符号長の符号長が並ぶ順番
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+struct BitReader<'a> {
+  input: &'a [u8],
+  pos: usize,
+  bit: u32,
+}
+
+impl BitReader<'_> {
+  fn bits(&mut self, count: u32) -> Result<u32, String> {
+    let mut value = 0;
+    for i in 0..count {
+      let byte = *self.input.get(self.pos).ok_or("unexpected end of deflate data")?;
+      value |= ((byte as u32 >> self.bit) & 1) << i;
+      self.bit += 1;
+      if self.bit == 8 {
+        self.bit = 0;
+        self.pos += 1;
+      }
+    }
+    Ok(value)
+  }
+
+  fn align(&mut self) {
+    if self.bit != 0 {
+      self.bit = 0;
+      self.pos += 1;
+    }
+  }
+}
+
+// 正規ハフマン符号 (符号長ごとの個数と、符号の順に並べた記号)
+struct Huffman {
+  counts: [u16; 16],
+  symbols: Vec<u16>,
+}
+
+impl Huffman {
+  fn new(lengths: &[u8]) -> Huffman {
+    let mut counts = [0u16; 16];
+    for &length in lengths {
+      counts[length as usize] += 1;
+    }
+    counts[0] = 0;
+    let mut offsets = [0u16; 16];
+    for length in 1..16 {
+      offsets[length] = offsets[length - 1] + counts[length - 1];
+    }
+    let mut symbols = vec![0; lengths.len()];
+    for (symbol, &length) in lengths.iter().enumerate() {
+      if length != 0 {
+        symbols[offsets[length as usize] as usize] = symbol as u16;
+        offsets[length as usize] += 1;
+      }
+    }
+    Huffman { counts, symbols }
+  }
+
+  fn decode(&self, reader: &mut BitReader) -> Result<u16, String> {
+    let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+    for length in 1..16 {
+      code |= reader.bits(1)? as i32;
+      let count = self.counts[length] as i32;
+      if code - first < count {
+        return self.symbols.get((index + code - first) as usize).copied().ok_or_else(|| "invalid Huffman code".to_string());
+      }
+      index += count;
+      first = (first + count) << 1;
+      code <<= 1;
+    }
+    Err("invalid Huffman code".to_string())
+  }
+}
+
+fn fixed_tables() -> (Huffman, Huffman) {
+  let mut lengths = [0u8; 288];
+  for (symbol, length) in lengths.iter_mut().enumerate() {
+    *length = match symbol {
+      0..=143 => 8,
+      144..=255 => 9,
+      256..=279 => 7,
+      _ => 8,
+    };
+  }
+  (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+fn dynamic_tables(reader: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+  let literals = reader.bits(5)? as usize + 257;
+  let distances = reader.bits(5)? as usize + 1;
+  let code_lengths = reader.bits(4)? as usize + 4;
+  let mut lengths = [0u8; 19];
+  for &symbol in &CODE_LENGTH_ORDER[..code_lengths] {
+    lengths[symbol] = reader.bits(3)? as u8;
+  }
+  let code_length_table = Huffman::new(&lengths);
+  let mut lengths = Vec::with_capacity(literals + distances);
+  while lengths.len() < literals + distances {
+    let symbol = code_length_table.decode(reader)?;
+    let (value, repeat) = match symbol {
+      0..=15 => (symbol as u8, 1),
+      16 => (*lengths.last().ok_or("repeat without a previous code length")?, 3 + reader.bits(2)?),
+      17 => (0, 3 + reader.bits(3)?),
+      _ => (0, 11 + reader.bits(7)?),
+    };
+    for _ in 0..repeat {
+      lengths.push(value);
+    }
+  }
+  if lengths.len() != literals + distances {
+    return Err("too many code lengths".to_string());
+  }
+  Ok((Huffman::new(&lengths[..literals]), Huffman::new(&lengths[literals..])))
+}
+
+pub fn inflate(input: &[u8], expected_size: usize) -> Result<Vec<u8>, String> {
+  let mut output = Vec::with_capacity(expected_size);
+  let mut reader = BitReader { input, pos: 0, bit: 0 };
+  loop {
+    let last = reader.bits(1)? == 1;
+    match reader.bits(2)? {
+      0 => {
+        reader.align();
+        let header = input.get(reader.pos..reader.pos + 4).ok_or("truncated stored block")?;
+        let length = u16::from_le_bytes([header[0], header[1]]) as usize;
+        reader.pos += 4;
+        output.extend_from_slice(input.get(reader.pos..reader.pos + length).ok_or("truncated stored block")?);
+        reader.pos += length;
+      },
+      kind @ (1 | 2) => {
+        let (literals, distances) = if kind == 1 { fixed_tables() } else { dynamic_tables(&mut reader)? };
+        loop {
+          let symbol = literals.decode(&mut reader)? as usize;
+          if symbol < 256 {
+            output.push(symbol as u8);
+            continue;
+          }
+          if symbol == 256 {
+            break;
+          }
+          let index = symbol - 257;
+          if index >= LENGTH_BASE.len() {
+            return Err("invalid length code".to_string());
+          }
+          let length = LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index] as u32)? as usize;
+          let index = distances.decode(&mut reader)? as usize;
+          if index >= DISTANCE_BASE.len() {
+            return Err("invalid distance code".to_string());
+          }
+          let distance = DISTANCE_BASE[index] as usize + reader.bits(DISTANCE_EXTRA[index] as u32)? as usize;
+          if distance > output.len() {
+            return Err("distance too far back".to_string());
+          }
+          // 重なりのあるコピーがあるので1バイトずつ
+          let start = output.len() - distance;
+          for i in 0..length {
+            output.push(output[start + i]);
+          }
+        }
+      },
+      _ => return Err("invalid deflate block type".to_string()),
+    }
+    if last {
+      return Ok(output);
+    }
+  }
+}
@@ -0,0 +1,27 @@
+use std::fmt::Write as _;
+
+// JSONの文字列リテラル
+pub fn string(value: &str) -> String {
+  let mut text = String::from("\"");
+  for c in value.chars() {
+    match c {
+      '"' => text.push_str("\\\""),
+      '\\' => text.push_str("\\\\"),
+      '\n' => text.push_str("\\n"),
+      '\r' => text.push_str("\\r"),
+      '\t' => text.push_str("\\t"),
+      c if (c as u32) < 0x20 => {
+        let _ = write!(text, "\\u{:04x}", c as u32);
+      },
+      c => text.push(c),
+    }
+  }
+  text.push('"');
+  text
+}
+
+// 文字列の配列
+pub fn string_array<'a>(values: impl IntoIterator<Item = &'a str>) -> String {
+  let values: Vec<String> = values.into_iter().map(string).collect();
+  format!("[{}]", values.join(", "))
+}
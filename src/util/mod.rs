@@ -1,2 +1,12 @@
 pub mod hex;
-pub mod class;
\ No newline at end of file
+pub mod class;
+pub mod class_path;
+pub mod descriptor;
+pub mod dot;
+pub mod graph;
+pub mod inflate;
+pub mod json;
+pub mod mutf8;
+pub mod number;
+pub mod signature;
+pub mod zip;
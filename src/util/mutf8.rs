@@ -0,0 +1,41 @@
+// クラスファイルのUtf8定数は修正UTF-8 (NULが0xC0 0x80、補助文字がサロゲートペア)
+pub fn decode(bytes: &[u8]) -> Vec<u16> {
+  let mut chars = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    let b = bytes[i] as u16;
+    if b & 0x80 == 0 {
+      chars.push(b);
+      i += 1;
+    } else if b & 0xE0 == 0xC0 && i + 1 < bytes.len() {
+      chars.push(((b & 0x1F) << 6) | (bytes[i + 1] as u16 & 0x3F));
+      i += 2;
+    } else if b & 0xF0 == 0xE0 && i + 2 < bytes.len() {
+      chars.push(((b & 0x0F) << 12) | ((bytes[i + 1] as u16 & 0x3F) << 6) | (bytes[i + 2] as u16 & 0x3F));
+      i += 3;
+    } else {
+      chars.push(0xFFFD);
+      i += 1;
+    }
+  }
+  chars
+}
+
+pub fn encode(chars: &[u16]) -> Vec<u8> {
+  let mut bytes = Vec::with_capacity(chars.len());
+  for &c in chars {
+    match c {
+      0x0001..=0x007F => bytes.push(c as u8),
+      0x0000 | 0x0080..=0x07FF => {
+        bytes.push(0xC0 | (c >> 6) as u8);
+        bytes.push(0x80 | (c & 0x3F) as u8);
+      },
+      _ => {
+        bytes.push(0xE0 | (c >> 12) as u8);
+        bytes.push(0x80 | ((c >> 6) & 0x3F) as u8);
+        bytes.push(0x80 | (c & 0x3F) as u8);
+      },
+    }
+  }
+  bytes
+}
@@ -0,0 +1,51 @@
+// Float.toString()と同じ形式にする
+pub fn float_to_string(value: f32) -> String {
+  if value.is_nan() || value.is_infinite() || value == 0.0 {
+    return special_to_string(value as f64);
+  }
+  java_format(&format!("{:e}", value))
+}
+
+// Double.toString()と同じ形式にする
+pub fn double_to_string(value: f64) -> String {
+  if value.is_nan() || value.is_infinite() || value == 0.0 {
+    return special_to_string(value);
+  }
+  java_format(&format!("{:e}", value))
+}
+
+fn special_to_string(value: f64) -> String {
+  if value.is_nan() {
+    "NaN".to_string()
+  } else if value.is_infinite() {
+    if value > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+  } else if value.is_sign_negative() {
+    "-0.0".to_string()
+  } else {
+    "0.0".to_string()
+  }
+}
+
+// Rustの最短表現 (1.25e-5 の形) を、10^-3以上10^7未満なら小数、それ以外は 1.25E-5 の形にする
+fn java_format(scientific: &str) -> String {
+  let (mantissa, exponent) = scientific.split_once('e').unwrap_or((scientific, "0"));
+  let exponent: i32 = exponent.parse().unwrap_or(0);
+  let (sign, mantissa) = match mantissa.strip_prefix('-') {
+    Some(m) => ("-", m),
+    None => ("", mantissa),
+  };
+  let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+  if (-3..7).contains(&exponent) {
+    if exponent >= 0 {
+      let point = exponent as usize + 1;
+      let integer = format!("{:0<width$}", &digits[..point.min(digits.len())], width = point);
+      let fraction = if digits.len() > point { &digits[point..] } else { "0" };
+      format!("{}{}.{}", sign, integer, fraction)
+    } else {
+      format!("{}0.{}{}", sign, "0".repeat((-exponent - 1) as usize), digits)
+    }
+  } else {
+    let fraction = if digits.len() > 1 { &digits[1..] } else { "0" };
+    format!("{}{}.{}E{}", sign, &digits[..1], fraction, exponent)
+  }
+}
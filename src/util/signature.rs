@@ -0,0 +1,323 @@
+use crate::util::descriptor::FieldType;
+
+// Signature属性のジェネリクスの型 (JVMS 4.7.9.1)
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeSignature {
+  // プリミティブ型
+  Base(FieldType),
+  // 先頭の要素の名前はパッケージを含む内部名、以降はネストしたクラスの名前
+  Class(Vec<ClassSegment>),
+  Variable(String),
+  Array(Box<TypeSignature>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassSegment {
+  pub name: String,
+  pub arguments: Vec<TypeArgument>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeArgument {
+  Any,
+  Exact(TypeSignature),
+  Extends(TypeSignature),
+  Super(TypeSignature),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeParameter {
+  pub name: String,
+  pub class_bound: Option<TypeSignature>,
+  pub interface_bounds: Vec<TypeSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassSignature {
+  pub type_parameters: Vec<TypeParameter>,
+  pub superclass: TypeSignature,
+  pub interfaces: Vec<TypeSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodSignature {
+  pub type_parameters: Vec<TypeParameter>,
+  pub parameters: Vec<TypeSignature>,
+  // voidならNone
+  pub return_type: Option<TypeSignature>,
+  pub throws: Vec<TypeSignature>,
+}
+
+struct Parser<'a> {
+  text: &'a str,
+  bytes: &'a [u8],
+  pos: usize,
+}
+
+impl<'a> Parser<'a> {
+  fn new(text: &'a str) -> Parser<'a> {
+    Parser { text, bytes: text.as_bytes(), pos: 0 }
+  }
+
+  fn peek(&self) -> Option<u8> {
+    self.bytes.get(self.pos).copied()
+  }
+
+  fn error(&self) -> String {
+    format!("Invalid signature: {}", self.text)
+  }
+
+  fn expect(&mut self, byte: u8) -> Result<(), String> {
+    if self.peek() != Some(byte) {
+      return Err(self.error());
+    }
+    self.pos += 1;
+    Ok(())
+  }
+
+  fn finish<T>(&self, value: T) -> Result<T, String> {
+    if self.pos != self.bytes.len() {
+      return Err(self.error());
+    }
+    Ok(value)
+  }
+
+  // 区切り文字までの名前 (stopに含まれる文字で止まる)
+  fn name(&mut self, stop: &[u8]) -> Result<String, String> {
+    let start = self.pos;
+    while let Some(byte) = self.peek() {
+      if stop.contains(&byte) {
+        break;
+      }
+      self.pos += 1;
+    }
+    if self.pos == start {
+      return Err(self.error());
+    }
+    Ok(self.text[start..self.pos].to_string())
+  }
+
+  fn type_parameters(&mut self) -> Result<Vec<TypeParameter>, String> {
+    let mut parameters = Vec::new();
+    if self.peek() != Some(b'<') {
+      return Ok(parameters);
+    }
+    self.pos += 1;
+    while self.peek() != Some(b'>') {
+      let name = self.name(b":>")?;
+      self.expect(b':')?;
+      let class_bound = match self.peek() {
+        Some(b'L' | b'T' | b'[') => Some(self.reference()?),
+        _ => None,
+      };
+      let mut interface_bounds = Vec::new();
+      while self.peek() == Some(b':') {
+        self.pos += 1;
+        interface_bounds.push(self.reference()?);
+      }
+      parameters.push(TypeParameter { name, class_bound, interface_bounds });
+    }
+    self.pos += 1;
+    Ok(parameters)
+  }
+
+  fn java_type(&mut self) -> Result<TypeSignature, String> {
+    let base = match self.peek() {
+      Some(b'B') => FieldType::Byte,
+      Some(b'C') => FieldType::Char,
+      Some(b'D') => FieldType::Double,
+      Some(b'F') => FieldType::Float,
+      Some(b'I') => FieldType::Int,
+      Some(b'J') => FieldType::Long,
+      Some(b'S') => FieldType::Short,
+      Some(b'Z') => FieldType::Boolean,
+      _ => return self.reference(),
+    };
+    self.pos += 1;
+    Ok(TypeSignature::Base(base))
+  }
+
+  fn reference(&mut self) -> Result<TypeSignature, String> {
+    match self.peek() {
+      Some(b'L') => self.class_type(),
+      Some(b'T') => {
+        self.pos += 1;
+        let name = self.name(b";")?;
+        self.expect(b';')?;
+        Ok(TypeSignature::Variable(name))
+      },
+      Some(b'[') => {
+        self.pos += 1;
+        Ok(TypeSignature::Array(Box::new(self.java_type()?)))
+      },
+      _ => Err(self.error()),
+    }
+  }
+
+  fn class_type(&mut self) -> Result<TypeSignature, String> {
+    self.expect(b'L')?;
+    let mut segments = Vec::new();
+    loop {
+      let name = self.name(b"<.;")?;
+      let arguments = self.type_arguments()?;
+      segments.push(ClassSegment { name, arguments });
+      match self.peek() {
+        Some(b'.') => self.pos += 1,
+        _ => break,
+      }
+    }
+    self.expect(b';')?;
+    Ok(TypeSignature::Class(segments))
+  }
+
+  fn type_arguments(&mut self) -> Result<Vec<TypeArgument>, String> {
+    let mut arguments = Vec::new();
+    if self.peek() != Some(b'<') {
+      return Ok(arguments);
+    }
+    self.pos += 1;
+    while self.peek() != Some(b'>') {
+      let argument = match self.peek() {
+        Some(b'*') => {
+          self.pos += 1;
+          TypeArgument::Any
+        },
+        Some(b'+') => {
+          self.pos += 1;
+          TypeArgument::Extends(self.reference()?)
+        },
+        Some(b'-') => {
+          self.pos += 1;
+          TypeArgument::Super(self.reference()?)
+        },
+        _ => TypeArgument::Exact(self.reference()?),
+      };
+      arguments.push(argument);
+    }
+    self.pos += 1;
+    Ok(arguments)
+  }
+}
+
+impl TypeSignature {
+  // フィールドやローカル変数のシグネチャ
+  pub fn parse(signature: &str) -> Result<TypeSignature, String> {
+    let mut parser = Parser::new(signature);
+    let value = parser.java_type()?;
+    parser.finish(value)
+  }
+
+  pub fn from_field_type(field_type: &FieldType) -> TypeSignature {
+    match field_type {
+      FieldType::Object(name) => TypeSignature::Class(vec![ClassSegment { name: name.clone(), arguments: Vec::new() }]),
+      FieldType::Array(component) => TypeSignature::Array(Box::new(TypeSignature::from_field_type(component))),
+      base => TypeSignature::Base(base.clone()),
+    }
+  }
+
+  // Javaのソースでの表記 (class_nameで内部名を表示用の名前にする)
+  pub fn to_java(&self, class_name: &dyn Fn(&str) -> String) -> String {
+    match self {
+      TypeSignature::Base(base) => base.to_string(),
+      TypeSignature::Variable(name) => name.clone(),
+      TypeSignature::Array(component) => format!("{}[]", component.to_java(class_name)),
+      TypeSignature::Class(segments) => {
+        let mut text = String::new();
+        for (i, segment) in segments.iter().enumerate() {
+          if i == 0 {
+            text.push_str(&class_name(&segment.name));
+          } else {
+            text.push('.');
+            text.push_str(&segment.name);
+          }
+          if !segment.arguments.is_empty() {
+            let arguments: Vec<String> = segment.arguments.iter().map(|argument| argument.to_java(class_name)).collect();
+            text.push_str(&format!("<{}>", arguments.join(", ")));
+          }
+        }
+        text
+      },
+    }
+  }
+}
+
+impl TypeArgument {
+  pub fn to_java(&self, class_name: &dyn Fn(&str) -> String) -> String {
+    match self {
+      TypeArgument::Any => "?".to_string(),
+      TypeArgument::Exact(signature) => signature.to_java(class_name),
+      TypeArgument::Extends(signature) => format!("? extends {}", signature.to_java(class_name)),
+      TypeArgument::Super(signature) => format!("? super {}", signature.to_java(class_name)),
+    }
+  }
+}
+
+impl TypeParameter {
+  pub fn to_java(&self, class_name: &dyn Fn(&str) -> String) -> String {
+    // 上限がObjectだけなら省略する
+    let object = TypeSignature::from_field_type(&FieldType::Object("java/lang/Object".to_string()));
+    let bounds: Vec<String> = self
+      .class_bound
+      .iter()
+      .filter(|bound| !(**bound == object && self.interface_bounds.is_empty()))
+      .chain(&self.interface_bounds)
+      .map(|bound| bound.to_java(class_name))
+      .collect();
+    if bounds.is_empty() {
+      self.name.clone()
+    } else {
+      format!("{} extends {}", self.name, bounds.join(" & "))
+    }
+  }
+}
+
+// "<T, U extends Comparable<U>>" (型引数がなければ空)
+pub fn type_parameters_to_java(parameters: &[TypeParameter], class_name: &dyn Fn(&str) -> String) -> String {
+  if parameters.is_empty() {
+    return String::new();
+  }
+  let parameters: Vec<String> = parameters.iter().map(|parameter| parameter.to_java(class_name)).collect();
+  format!("<{}>", parameters.join(", "))
+}
+
+impl ClassSignature {
+  pub fn parse(signature: &str) -> Result<ClassSignature, String> {
+    let mut parser = Parser::new(signature);
+    let type_parameters = parser.type_parameters()?;
+    let superclass = parser.class_type()?;
+    let mut interfaces = Vec::new();
+    while parser.peek().is_some() {
+      interfaces.push(parser.class_type()?);
+    }
+    parser.finish(ClassSignature { type_parameters, superclass, interfaces })
+  }
+}
+
+impl MethodSignature {
+  pub fn parse(signature: &str) -> Result<MethodSignature, String> {
+    let mut parser = Parser::new(signature);
+    let type_parameters = parser.type_parameters()?;
+    parser.expect(b'(')?;
+    let mut parameters = Vec::new();
+    while parser.peek() != Some(b')') {
+      if parser.peek().is_none() {
+        return Err(parser.error());
+      }
+      parameters.push(parser.java_type()?);
+    }
+    parser.pos += 1;
+    let return_type = if parser.peek() == Some(b'V') {
+      parser.pos += 1;
+      None
+    } else {
+      Some(parser.java_type()?)
+    };
+    let mut throws = Vec::new();
+    while parser.peek() == Some(b'^') {
+      parser.pos += 1;
+      throws.push(parser.reference()?);
+    }
+    parser.finish(MethodSignature { type_parameters, parameters, return_type, throws })
+  }
+}
@@ -0,0 +1,94 @@
+use std::{
+  fs,
+  io::{self, Result},
+  path::Path,
+};
+
+use crate::util::inflate::inflate;
+
+// jarファイル (ZIP) の読み込み。ZIP64と暗号化には対応しない
+
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const CENTRAL_DIRECTORY_HEADER: u32 = 0x0201_4b50;
+const LOCAL_FILE_HEADER: u32 = 0x0403_4b50;
+
+#[derive(Debug, Clone)]
+pub struct ZipEntry {
+  pub name: String,
+  // 0なら無圧縮、8ならDeflate
+  pub method: u16,
+  pub compressed_size: usize,
+  pub size: usize,
+  local_header_offset: usize,
+}
+
+pub struct ZipArchive {
+  data: Vec<u8>,
+  pub entries: Vec<ZipEntry>,
+}
+
+fn invalid(message: &str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn u16_at(data: &[u8], at: usize) -> Result<u16> {
+  data.get(at..at + 2).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]])).ok_or_else(|| invalid("truncated zip file"))
+}
+
+fn u32_at(data: &[u8], at: usize) -> Result<u32> {
+  data.get(at..at + 4).map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).ok_or_else(|| invalid("truncated zip file"))
+}
+
+impl ZipArchive {
+  pub fn open(path: &Path) -> Result<ZipArchive> {
+    ZipArchive::from_bytes(fs::read(path)?)
+  }
+
+  pub fn from_bytes(data: Vec<u8>) -> Result<ZipArchive> {
+    // 末尾のコメントは最大65535バイト
+    let lowest = data.len().saturating_sub(22 + 0xFFFF);
+    let end = (lowest..data.len().saturating_sub(21))
+      .rev()
+      .find(|&at| u32_at(&data, at).is_ok_and(|signature| signature == END_OF_CENTRAL_DIRECTORY))
+      .ok_or_else(|| invalid("not a zip file"))?;
+    let count = u16_at(&data, end + 10)? as usize;
+    let mut at = u32_at(&data, end + 16)? as usize;
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+      if u32_at(&data, at)? != CENTRAL_DIRECTORY_HEADER {
+        return Err(invalid("broken zip central directory"));
+      }
+      let name_length = u16_at(&data, at + 28)? as usize;
+      let extra_length = u16_at(&data, at + 30)? as usize;
+      let comment_length = u16_at(&data, at + 32)? as usize;
+      let name = data.get(at + 46..at + 46 + name_length).ok_or_else(|| invalid("truncated zip file"))?;
+      entries.push(ZipEntry {
+        name: String::from_utf8_lossy(name).into_owned(),
+        method: u16_at(&data, at + 10)?,
+        compressed_size: u32_at(&data, at + 20)? as usize,
+        size: u32_at(&data, at + 24)? as usize,
+        local_header_offset: u32_at(&data, at + 42)? as usize,
+      });
+      at += 46 + name_length + extra_length + comment_length;
+    }
+    Ok(ZipArchive { data, entries })
+  }
+
+  pub fn find(&self, name: &str) -> Option<&ZipEntry> {
+    self.entries.iter().find(|entry| entry.name == name)
+  }
+
+  pub fn read(&self, entry: &ZipEntry) -> Result<Vec<u8>> {
+    let at = entry.local_header_offset;
+    if u32_at(&self.data, at)? != LOCAL_FILE_HEADER {
+      return Err(invalid("broken zip local header"));
+    }
+    let start = at + 30 + u16_at(&self.data, at + 26)? as usize + u16_at(&self.data, at + 28)? as usize;
+    let compressed = self.data.get(start..start + entry.compressed_size).ok_or_else(|| invalid("truncated zip entry"))?;
+    match entry.method {
+      0 => Ok(compressed.to_vec()),
+      8 => inflate(compressed, entry.size).map_err(|e| invalid(&format!("{}: {}", entry.name, e))),
+      method => Err(invalid(&format!("{}: unsupported compression method {}", entry.name, method))),
+    }
+  }
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use std::{path::PathBuf, process::Output};
+
+use common::{compile, rust_jvm};
+
+// tests/java/apidiff/<version>のクラスをコンパイルしたディレクトリ
+fn version(name: &str, version: &str) -> PathBuf {
+  let sources = ["Base.java", "Gadget.java", "Sealed.java", "Widget.java"].map(|source| format!("apidiff/{}/{}", version, source));
+  compile(&format!("{}_{}", name, version), &sources.each_ref().map(String::as_str))
+}
+
+fn apidiff(old: &PathBuf, new: &PathBuf) -> Output {
+  rust_jvm().arg("apidiff").arg(old).arg(new).output().expect("failed to run rust-jvm")
+}
+
+#[test]
+fn changes_are_classified_by_binary_compatibility() {
+  let output = apidiff(&version("apidiff", "old"), &version("apidiff", "new"));
+  assert_eq!(
+    String::from_utf8_lossy(&output.stdout),
+    "\
+BREAKING    class Gadget: abstract added
+BREAKING    class Sealed: final added
+BREAKING    class Widget: supertypes removed: Base
+BREAKING    field Widget.count:I: static added
+BREAKING    field Widget.total:I: static removed
+BREAKING    method Gadget.work()V: abstract added
+BREAKING    method Widget.member()V: static added
+BREAKING    method Widget.narrowed()V: visibility reduced from public to protected
+BREAKING    method Widget.removed()V: removed
+BREAKING    method Widget.sealed()V: final added
+BREAKING    method Widget.utility()V: static removed
+compatible  method Widget.added()V: added
+compatible  method Widget.unsealed()V: final removed
+compatible  method Widget.widened()V: visibility increased from protected to public
+
+14 changes: 11 breaking, 3 compatible
+"
+  );
+  assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn identical_versions_have_no_changes() {
+  let old = version("apidiff_same", "old");
+  let output = apidiff(&old, &old);
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "0 changes: 0 breaking, 0 compatible\n");
+  assert_eq!(output.status.code(), Some(0));
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use std::path::Path;
+
+use common::{compile, rust_jvm};
+
+fn callgraph(classes: &Path, options: &[&str]) -> String {
+  let output = rust_jvm().arg("callgraph").args(options).arg(classes).output().expect("failed to run rust-jvm");
+  assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+  String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn cha_dispatches_to_every_implementation() {
+  let classes = compile("callgraph_cha", &["callgraph/Shapes.java"]);
+  assert_eq!(
+    callgraph(&classes, &[]),
+    "\
+Algorithm: CHA
+Entry points (1):
+   Shapes.main([Ljava/lang/String;)V
+Reachable methods: 5 of 11
+Unreachable methods (6):
+   Shapes.<init>()V
+   Shapes.helper()V
+   Shapes.onEvent()V
+   Shapes.tool()V
+   Shapes.unused()V
+   Square.<init>()V
+"
+  );
+}
+
+// Squareはnewされないので、RTAではSquare.areaに振り分けない
+#[test]
+fn rta_dispatches_only_to_instantiated_classes() {
+  let classes = compile("callgraph_rta", &["callgraph/Shapes.java"]);
+  assert_eq!(
+    callgraph(&classes, &["--rta"]),
+    "\
+Algorithm: RTA
+Entry points (1):
+   Shapes.main([Ljava/lang/String;)V
+Reachable methods: 4 of 11
+Unreachable methods (7):
+   Shapes.<init>()V
+   Shapes.helper()V
+   Shapes.onEvent()V
+   Shapes.tool()V
+   Shapes.unused()V
+   Square.<init>()V
+   Square.area()I
+"
+  );
+}
+
+#[test]
+fn entry_points_are_selected_by_name_and_annotation() {
+  let classes = compile("callgraph_entries", &["callgraph/Shapes.java"]);
+  assert_eq!(
+    callgraph(&classes, &["--entry", "Shapes.tool()V", "--entry-annotation", "Handler"]),
+    "\
+Algorithm: CHA
+Entry points (3):
+   Shapes.main([Ljava/lang/String;)V
+   Shapes.onEvent()V
+   Shapes.tool()V
+Reachable methods: 8 of 11
+Unreachable methods (3):
+   Shapes.<init>()V
+   Shapes.unused()V
+   Square.<init>()V
+"
+  );
+  // 記述子が合わなければ入口にしない
+  let output = callgraph(&classes, &["--entry", "Shapes.tool(I)V"]);
+  assert!(output.starts_with("Algorithm: CHA\nEntry points (1):\n"), "{}", output);
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use std::{
+  fs,
+  path::{Path, PathBuf},
+  process::Output,
+};
+
+use common::{compile, rust_jvm};
+
+// tests/java/classdiff/{old,new}のclassを同じ名前でコンパイルし、2つのクラスファイルを返す
+fn versions(class: &str) -> (PathBuf, PathBuf) {
+  let compile_version = |version: &str| {
+    let dir = compile(&format!("classdiff_{}_{}", version, class), &[&format!("classdiff/{}/{}.java", version, class)]);
+    dir.join(format!("{}.class", class))
+  };
+  (compile_version("old"), compile_version("new"))
+}
+
+fn diff(old: &Path, new: &Path) -> Output {
+  rust_jvm().arg("diff").arg(old).arg(new).output().expect("failed to run rust-jvm")
+}
+
+#[test]
+fn constant_pool_reordering_is_not_a_difference() {
+  let (old, new) = versions("Pool");
+  // 同じ文字列の番号が入れ替わっている
+  assert_ne!(fs::read(&old).unwrap(), fs::read(&new).unwrap());
+  let output = diff(&old, &new);
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "No structural differences\n");
+  assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn line_number_only_change_is_reported_as_line_number_table() {
+  let (old, new) = versions("Lines");
+  let output = diff(&old, &new);
+  assert_eq!(
+    String::from_utf8_lossy(&output.stdout),
+    "method twice(I)I: LineNumberTable changed\n    L0: line 4\n  - L4: line 5\n  + L4: line 6\n\n1 differences (LineNumberTable 1)\n"
+  );
+  assert_eq!(output.status.code(), Some(1));
+}
@@ -0,0 +1,70 @@
+// 結合テストの補助 (テストのクレートごとに使う関数が違うので、使わないものの警告は出さない)
+#![allow(dead_code)]
+
+use std::{
+  fs,
+  path::{Path, PathBuf},
+  process::{Command, Output},
+};
+
+// テストごとの作業ディレクトリ (中身は毎回作り直す)
+pub fn work_dir(name: &str) -> PathBuf {
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+  dir
+}
+
+pub fn java_source(name: &str) -> PathBuf {
+  Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/java").join(name)
+}
+
+// tests/java以下のソースをjavacでコンパイルし、クラスファイルを置いたディレクトリを返す
+pub fn compile(name: &str, sources: &[&str]) -> PathBuf {
+  compile_against(name, None, sources)
+}
+
+// 別にコンパイルしたクラスをクラスパスに置いてコンパイルする (バイナリ互換性のテストで使う)
+pub fn compile_against(name: &str, class_path: Option<&Path>, sources: &[&str]) -> PathBuf {
+  let dir = work_dir(name);
+  let output = Command::new("javac")
+    .args(["-g", "-encoding", "UTF-8", "-d"])
+    .arg(&dir)
+    .args(class_path.map(|path| ["-cp".as_ref(), path.as_os_str()]).into_iter().flatten())
+    .args(sources.iter().map(|source| java_source(source)))
+    .output()
+    .expect("failed to run javac");
+  assert!(output.status.success(), "javac failed: {}", String::from_utf8_lossy(&output.stderr));
+  dir
+}
+
+pub fn rust_jvm() -> Command {
+  Command::new(env!("CARGO_BIN_EXE_rust-jvm"))
+}
+
+// rust-jvm run -cp <class_path> [options...] <main> [args...]
+pub fn run_with(class_path: &Path, options: &[&str], main: &str, args: &[&str]) -> Output {
+  rust_jvm()
+    .arg("run")
+    .arg("-cp")
+    .arg(class_path)
+    .args(options)
+    .arg(main)
+    .args(args)
+    .output()
+    .expect("failed to run rust-jvm")
+}
+
+// 正常に終了したことを確かめて標準出力を返す
+pub fn run_main(class_path: &Path, main: &str, args: &[&str]) -> String {
+  let output = run_with(class_path, &[], main, args);
+  assert!(
+    output.status.success(),
+    "{} exited with {}\nstdout:\n{}\nstderr:\n{}",
+    main,
+    output.status,
+    String::from_utf8_lossy(&output.stdout),
+    String::from_utf8_lossy(&output.stderr)
+  );
+  String::from_utf8_lossy(&output.stdout).into_owned()
+}
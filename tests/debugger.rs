@@ -0,0 +1,49 @@
+mod common;
+
+use std::{io::Write, process::Stdio};
+
+use common::{compile, rust_jvm};
+
+#[test]
+fn scripted_session_stops_at_breakpoint() {
+  let classes = compile("debugger", &["S.java"]);
+  let mut child = rust_jvm()
+    .arg("debug")
+    .arg("-cp")
+    .arg(&classes)
+    .arg("S")
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+  let script = "break S.add:0\nrun\nlocals\nwhere\nnext\nnext\nnext\nnext\nlocals\nprint S.total\ncontinue\n";
+  child.stdin.take().unwrap().write_all(script.as_bytes()).unwrap();
+  let output = child.wait_with_output().unwrap();
+  assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "\
+Type 'help' for a list of commands. Use 'run' to start the program.
+(rdb) Breakpoint 1 at S.add:0 (deferred until the class is loaded)
+(rdb) Breakpoint 1
+S.add(S.java:6), pc 0 [main]
+  0: iload_0
+(rdb) a = 2
+b = 3
+(rdb) => #0 S.add(S.java:6), pc 0
+   #1 S.main(S.java:13), pc 9
+(rdb) S.add(S.java:6), pc 1 [main]
+  1: iload_1
+(rdb) S.add(S.java:6), pc 2 [main]
+  2: iadd
+(rdb) S.add(S.java:6), pc 3 [main]
+  3: istore_2
+(rdb) S.add(S.java:7), pc 4 [main]
+  4: getstatic #7 // Field S.total:I
+(rdb) a = 2
+b = 3
+sum = 5
+(rdb) S.total = 0
+(rdb) sum 5
+The program exited with status 0
+");
+}
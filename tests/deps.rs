@@ -0,0 +1,59 @@
+mod common;
+
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+use common::{compile, rust_jvm, work_dir};
+
+const EXPECTED: &str = "\
+classes -> java.base
+classes -> java.desktop
+classes -> java.sql
+classes -> jdk.unsupported
+classes -> not found
+   DepsUser (classes)
+      -> DepsGone             not found
+      -> java.lang.Object     java.base
+      -> java.lang.String     java.base
+      -> java.sql.Connection  java.sql
+      -> java.util.List       java.base
+      -> javax.swing.JFrame   java.desktop
+      -> sun.misc.Unsafe      jdk.unsupported
+
+Missing classes (1):
+   DepsGone <- DepsUser
+";
+
+// コンパイルした後でDepsGoneを消す
+fn classes(name: &str) -> PathBuf {
+  let dir = compile(name, &["DepsUser.java"]);
+  let classes = dir.join("classes");
+  fs::create_dir_all(&classes).unwrap();
+  fs::rename(dir.join("DepsUser.class"), classes.join("DepsUser.class")).unwrap();
+  classes
+}
+
+fn deps(classes: &Path, java_home: Option<&Path>) -> String {
+  let mut command = rust_jvm();
+  command.args(["deps", "--classes"]).arg(classes);
+  if let Some(java_home) = java_home {
+    command.env("JAVA_HOME", java_home);
+  }
+  let output = command.output().expect("failed to run rust-jvm");
+  assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+  String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn jdk_classes_are_grouped_by_module() {
+  assert_eq!(deps(&classes("deps_image"), None), EXPECTED);
+}
+
+// jmodsがなければ公開パッケージの表でモジュールを決める
+#[test]
+fn exported_packages_are_used_without_jmods() {
+  let java_home = work_dir("deps_no_jmods");
+  assert_eq!(deps(&classes("deps_packages"), Some(&java_home)), EXPECTED);
+}
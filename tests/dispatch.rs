@@ -0,0 +1,34 @@
+mod common;
+
+use std::env;
+
+use common::{compile, compile_against, run_with};
+
+#[test]
+fn virtual_and_interface_calls_select_like_java() {
+  // SquareはShapeにarea()とname()が追加される前にコンパイルしておく
+  let old = compile("dispatch/old", &["dispatch/old/Shape.java", "dispatch/Square.java"]);
+  let new = compile_against("dispatch/new", Some(&old), &[
+    "dispatch/new/Shape.java",
+    "dispatch/Dispatch.java",
+    "dispatch/p1/Base.java",
+    "dispatch/p1/SubSub.java",
+    "dispatch/p2/Sub.java",
+  ]);
+  let class_path = env::join_paths([&new, &old]).unwrap();
+  // 呼び出し箇所のキャッシュはインタプリタとJITの両方で、レシーバのクラスが変わったら選び直す
+  for options in [&["-Xint"][..], &["-XX:CompileThreshold=1"]] {
+    let output = run_with(class_path.as_ref(), options, "Dispatch", &[]);
+    assert!(output.status.success(), "{:?}: {}", options, String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), EXPECTED, "{:?}", options);
+  }
+}
+
+const EXPECTED: &str = "\
+Base.m Sub.m
+SubSub.m SubSub.m
+circle 1.0 3.0
+shape 3.0 4.5
+shape 2.0 AbstractMethodError
+woof woof meow ... woof meow meow
+";
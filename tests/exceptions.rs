@@ -0,0 +1,39 @@
+mod common;
+
+use common::{compile, run_with};
+
+// 期待値は同じクラスをjavaで実行した出力
+const EXPECTED: &str = "\
+true\n\
+java.lang.ArrayIndexOutOfBoundsException: Index 2 out of bounds for length 2\n\
+true\n\
+java.lang.NegativeArraySizeException: -3\n\
+java.lang.ArithmeticException: / by zero\n\
+java.lang.ArithmeticException: / by zero\n\
+Infinity\n\
+none\n\
+caught as IllegalStateException: custom true\n\
+caught by outer handler: Index -1 out of bounds for length 2\n\
+finally 1\n\
+finally 2\n\
+finally 3\n\
+unwound from depth 0: bottom\n\
+";
+
+#[test]
+fn exceptions_are_caught_unwound_and_reported() {
+  let classes = compile("exceptions", &["Exceptions.java"]);
+  let output = run_with(&classes, &[], "Exceptions", &[]);
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  assert_eq!(stdout, EXPECTED);
+  // キャッチされなかった例外はjavaと同じ形式でスタックトレースを出し、終了コード1で終わる
+  assert_eq!(output.status.code(), Some(1), "{}", stderr);
+  assert_eq!(
+    stderr,
+    "Exception in thread \"main\" java.lang.ArithmeticException: / by zero\n\
+     \tat Exceptions.bar(Exceptions.java:75)\n\
+     \tat Exceptions.foo(Exceptions.java:79)\n\
+     \tat Exceptions.main(Exceptions.java:86)\n"
+  );
+}
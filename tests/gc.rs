@@ -0,0 +1,41 @@
+mod common;
+
+use std::{path::PathBuf, sync::OnceLock};
+
+use common::{compile, run_with};
+
+fn classes() -> &'static PathBuf {
+  static CLASSES: OnceLock<PathBuf> = OnceLock::new();
+  CLASSES.get_or_init(|| compile("gc", &["GcChurn.java"]))
+}
+
+// -Xmxを小さくして実行し、標準出力とGCの回数を返す
+fn run(test: &str, max_heap: &str) -> (String, usize) {
+  let output = run_with(classes(), &[max_heap, "-verbose:gc"], "GcChurn", &[test]);
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  assert!(output.status.success(), "{}: {}", test, stderr);
+  let collections = stderr.lines().filter(|line| line.starts_with("[gc] GC(")).count();
+  (String::from_utf8(output.stdout).unwrap(), collections)
+}
+
+#[test]
+fn churn_is_collected_under_max_heap() {
+  // 合計256MBの確保を16MBのヒープで行う
+  let (output, collections) = run("churn", "-Xmx16m");
+  assert_eq!(output, format!("{}\n", (0..4000i64).sum::<i64>()));
+  assert!(collections >= 10, "only {} collections", collections);
+}
+
+#[test]
+fn out_of_memory_error_is_catchable() {
+  let (output, collections) = run("oom", "-Xmx8m");
+  assert_eq!(output, format!("caught Java heap space\n{}\n", (0..1000i64).sum::<i64>()));
+  assert!(collections > 0);
+}
+
+#[test]
+fn objects_reachable_from_roots_survive() {
+  let (output, collections) = run("roots", "-Xmx16m");
+  assert_eq!(output, format!("{}\nstatic true\nstack true\n", (0..2000i64).sum::<i64>()));
+  assert!(collections > 0);
+}
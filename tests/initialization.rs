@@ -0,0 +1,10 @@
+mod common;
+
+use common::{compile, run_main};
+
+#[test]
+fn failed_initializer_is_the_cause_of_no_class_def_found_error() {
+  let classes = compile("initialization", &["InitFailure.java"]);
+  let output = run_main(&classes, "InitFailure", &[]);
+  assert_eq!(output, "first / by zero\nCould not initialize class InitFailure$Broken\ncause true\n");
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use std::process::Command;
+
+use common::{compile, run_main};
+
+#[test]
+fn concat_recipes_and_capturing_lambdas() {
+  let classes = compile("invokedynamic", &["Indy.java"]);
+  let output = run_main(&classes, "Indy", &[]);
+  // 期待値は同じクラスをjavaで実行した出力
+  assert_eq!(output, "\
+-1|-9223372036854775808|c|true|0.33333334|-0.0|null|s|-128|7
+152fivetail12const42x1.5
+24
+[]Indy100
+p:1099511627788
+205
+p:q
+e
+Indy100
+3
+p:3
+");
+}
+
+#[test]
+fn ldc_method_handles_types_and_dynamic_constants() {
+  // javacはこれらのldcを出さないので、クラスファイルを書き出すプログラムをjavaで実行する
+  let classes = compile("dynamic_constants", &["GenerateConstants.java"]);
+  let status = Command::new("java")
+    .arg("-cp")
+    .arg(&classes)
+    .arg("GenerateConstants")
+    .arg(&classes)
+    .status()
+    .expect("failed to run java");
+  assert!(status.success());
+  let output = run_main(&classes, "Constants", &[]);
+  assert_eq!(output, "\
+(int,Object,long[])String
+MethodHandle(int)String
+ff
+built
+12
+6
+null
+1099511627776
+true
+GREEN
+7
+44
+-5
+");
+}
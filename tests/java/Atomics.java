@@ -0,0 +1,72 @@
+import java.lang.invoke.MethodHandles;
+import java.lang.invoke.VarHandle;
+import java.util.concurrent.atomic.AtomicInteger;
+import java.util.concurrent.atomic.AtomicLong;
+
+// 複数のスレッドから不可分な更新を繰り返し、最後の値を出力する
+public class Atomics {
+  static final AtomicInteger atomic = new AtomicInteger();
+  static final AtomicLong counter = new AtomicLong();
+  static final int[] elements = new int[4];
+  static final VarHandle FIELD;
+  static final VarHandle ELEMENT = MethodHandles.arrayElementVarHandle(int[].class);
+
+  static {
+    try {
+      FIELD = MethodHandles.lookup().findVarHandle(Atomics.class, "field", int.class);
+    } catch (ReflectiveOperationException e) {
+      throw new RuntimeException(e);
+    }
+  }
+
+  int field;
+
+  public static void main(String[] args) throws InterruptedException {
+    String test = args[0];
+    int threads = Integer.parseInt(args[1]);
+    int iterations = Integer.parseInt(args[2]);
+    Atomics target = new Atomics();
+    Thread[] workers = new Thread[threads];
+    for (int t = 0; t < threads; t++) {
+      workers[t] = new Thread(() -> {
+        for (int i = 0; i < iterations; i++) {
+          switch (test) {
+            case "atomic-integer":
+              atomic.getAndAdd(2);
+              break;
+            case "var-handle-field":
+              FIELD.getAndAdd(target, 3);
+              break;
+            case "var-handle-array":
+              ELEMENT.getAndAdd(elements, i % elements.length, 1);
+              break;
+            case "compare-and-set":
+              long current;
+              do {
+                current = counter.get();
+              } while (!counter.compareAndSet(current, current + 1));
+              break;
+            default:
+              throw new IllegalArgumentException(test);
+          }
+        }
+      });
+    }
+    for (Thread worker : workers) {
+      worker.start();
+    }
+    for (Thread worker : workers) {
+      worker.join();
+    }
+    int sum = 0;
+    for (int element : elements) {
+      sum += element;
+    }
+    switch (test) {
+      case "atomic-integer" -> System.out.println(atomic.get());
+      case "var-handle-field" -> System.out.println((int) FIELD.getVolatile(target));
+      case "var-handle-array" -> System.out.println(sum);
+      default -> System.out.println(counter.get());
+    }
+  }
+}
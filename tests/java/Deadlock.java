@@ -0,0 +1,37 @@
+// デッドロックの検出の結合テスト: 2つのスレッドが逆の順番で2つのロックを取る
+public class Deadlock {
+  static final Object first = new Object();
+  static final Object second = new Object();
+  static volatile boolean firstHeld;
+  static volatile boolean secondHeld;
+
+  public static void main(String[] args) throws InterruptedException {
+    Thread a = new Thread(() -> {
+      synchronized (first) {
+        firstHeld = true;
+        while (!secondHeld) {
+          Thread.yield();
+        }
+        synchronized (second) {
+          System.out.println("not reached");
+        }
+      }
+    }, "worker-a");
+    Thread b = new Thread(() -> {
+      synchronized (second) {
+        secondHeld = true;
+        while (!firstHeld) {
+          Thread.yield();
+        }
+        synchronized (first) {
+          System.out.println("not reached");
+        }
+      }
+    }, "worker-b");
+    a.start();
+    b.start();
+    System.out.println("started");
+    a.join();
+    b.join();
+  }
+}
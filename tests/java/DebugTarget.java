@@ -0,0 +1,12 @@
+// JDWPの結合テストでデバッグする対象
+public class DebugTarget {
+  static int compute(int a) {
+    int b = a * 2;
+    int c = b + 3;
+    return c;
+  }
+
+  public static void main(String[] args) {
+    System.out.println(compute(20));
+  }
+}
@@ -0,0 +1,11 @@
+// depsの結合テスト: JDKの各モジュールのクラスと、コンパイル後に消すクラスを参照する
+public class DepsUser {
+  javax.swing.JFrame frame;
+  sun.misc.Unsafe unsafe;
+  java.sql.Connection connection;
+  java.util.List<String> names;
+  DepsGone gone;
+}
+
+class DepsGone {
+}
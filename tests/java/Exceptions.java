@@ -0,0 +1,89 @@
+// 例外の結合テスト: 暗黙の例外、サブクラスでのcatch、フレームをまたぐ巻き戻し、キャッチされなかった例外
+public class Exceptions {
+  static class Custom extends IllegalStateException {
+    Custom(String message) {
+      super(message);
+    }
+  }
+
+  static int[] array = new int[2];
+  static Object text = "text";
+  static int zero;
+
+  static String describe(Runnable body) {
+    try {
+      body.run();
+      return "none";
+    } catch (RuntimeException e) {
+      return e.getClass().getName() + ": " + e.getMessage();
+    }
+  }
+
+  static void implicit() {
+    Object nothing = null;
+    System.out.println(describe(() -> nothing.hashCode()).startsWith("java.lang.NullPointerException"));
+    System.out.println(describe(() -> array[2] = 1));
+    System.out.println(describe(() -> System.out.println((Integer) text)).startsWith("java.lang.ClassCastException"));
+    System.out.println(describe(() -> System.out.println(new long[zero - 3].length)));
+    System.out.println(describe(() -> System.out.println(7 / zero)));
+    System.out.println(describe(() -> System.out.println(7L % zero)));
+    System.out.println(describe(() -> System.out.println(7.0 / zero)));
+  }
+
+  static void subclasses() {
+    try {
+      throw new Custom("custom");
+    } catch (IllegalArgumentException e) {
+      System.out.println("wrong handler");
+    } catch (IllegalStateException e) {
+      System.out.println("caught as IllegalStateException: " + e.getMessage() + " " + (e instanceof Custom));
+    }
+    try {
+      try {
+        System.out.println(array[zero - 1]);
+      } catch (ArithmeticException e) {
+        System.out.println("wrong handler");
+      }
+    } catch (IndexOutOfBoundsException e) {
+      System.out.println("caught by outer handler: " + e.getMessage());
+    }
+  }
+
+  static int depth;
+
+  static void recurse(int n) {
+    depth = n;
+    if (n == 0) {
+      throw new Custom("bottom");
+    }
+    try {
+      recurse(n - 1);
+    } finally {
+      System.out.println("finally " + n);
+    }
+  }
+
+  static void unwinding() {
+    try {
+      recurse(3);
+    } catch (Custom e) {
+      System.out.println("unwound from depth " + depth + ": " + e.getMessage());
+    }
+  }
+
+  static void bar(int x) {
+    System.out.println(100 / x);
+  }
+
+  static void foo(int x) {
+    bar(x - 1);
+  }
+
+  public static void main(String[] args) {
+    implicit();
+    subclasses();
+    unwinding();
+    foo(1);
+    System.out.println("not reached");
+  }
+}
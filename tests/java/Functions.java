@@ -0,0 +1,48 @@
+import java.util.Objects;
+import java.util.function.BinaryOperator;
+import java.util.function.Function;
+import java.util.function.IntSupplier;
+import java.util.function.Predicate;
+import java.util.function.Supplier;
+
+public class Functions {
+  class Inner {
+    int value() {
+      return base * 2;
+    }
+  }
+
+  int base = 21;
+
+  int base() {
+    return base;
+  }
+
+  public static void main(String[] args) {
+    Function<Integer, Integer> f = x -> x + 1;
+    System.out.println(f.apply(41));
+    BinaryOperator<String> concat = (a, b) -> a + b;
+    System.out.println(concat.apply("a", "b"));
+    Predicate<String> empty = String::isEmpty;
+    System.out.println(empty.test("") + " " + empty.test("x"));
+    Functions outer = new Functions();
+    IntSupplier bound = outer::base;
+    System.out.println(bound.getAsInt());
+    Functions.Inner inner = outer.new Inner();
+    System.out.println(inner.value());
+    Functions missing = null;
+    try {
+      Supplier<Integer> s = missing::base;
+      System.out.println(s.get());
+    } catch (NullPointerException e) {
+      System.out.println("npe");
+    }
+    System.out.println(Objects.equals("a", "a") + " " + Objects.equals(null, "a") + " " + Objects.hashCode(null));
+    System.out.println(Objects.hash(1, "a") + " " + Objects.toString(null, "none"));
+    try {
+      Objects.requireNonNull(null, "value");
+    } catch (NullPointerException e) {
+      System.out.println(e.getMessage());
+    }
+  }
+}
@@ -0,0 +1,71 @@
+// GCの結合テスト: 大量の一時オブジェクト、ヒープの枯渇、GCのルートから届くオブジェクト
+public class GcChurn {
+  static class Node {
+    final Node next;
+    final int[] payload;
+
+    Node(Node next, int size, int value) {
+      this.next = next;
+      this.payload = new int[size];
+      payload[size - 1] = value;
+    }
+  }
+
+  // staticフィールドだけから届く
+  static Node retained;
+
+  // すぐに不要になる配列を確保し続ける
+  static long churn(int rounds, int size) {
+    long sum = 0;
+    for (int i = 0; i < rounds; i++) {
+      int[] array = new int[size];
+      array[i % size] = i;
+      sum += array[i % size];
+    }
+    return sum;
+  }
+
+  static Node chain(int length, int base) {
+    Node node = null;
+    for (int i = 0; i < length; i++) {
+      node = new Node(node, 256, base + i);
+    }
+    return node;
+  }
+
+  static boolean check(Node node, int length, int base) {
+    for (int i = length - 1; i >= 0; i--) {
+      if (node == null || node.payload[255] != base + i) {
+        return false;
+      }
+      node = node.next;
+    }
+    return node == null;
+  }
+
+  public static void main(String[] args) {
+    String test = args[0];
+    if (test.equals("churn")) {
+      System.out.println(churn(4000, 16 * 1024));
+    } else if (test.equals("oom")) {
+      Node node = null;
+      try {
+        while (true) {
+          node = new Node(node, 1024, 0);
+        }
+      } catch (OutOfMemoryError e) {
+        node = null;
+        System.out.println("caught " + e.getMessage());
+      }
+      // 捕まえた後は、解放されたヒープで実行を続けられる
+      System.out.println(churn(1000, 16 * 1024));
+    } else if (test.equals("roots")) {
+      retained = chain(100, 1000);
+      Node local = chain(100, 2000);
+      System.out.println(churn(2000, 16 * 1024));
+      System.gc();
+      System.out.println("static " + check(retained, 100, 1000));
+      System.out.println("stack " + check(local, 100, 2000));
+    }
+  }
+}
@@ -0,0 +1,235 @@
+import java.io.ByteArrayOutputStream;
+import java.io.DataOutputStream;
+import java.io.FileOutputStream;
+import java.io.IOException;
+import java.util.ArrayList;
+import java.util.HashMap;
+import java.util.List;
+import java.util.Map;
+
+// 動的定数の結合テスト: javacはldcでMethodHandle/MethodTypeやCONSTANT_Dynamicを出さないので、
+// それらを読み込んで表示するConstants.classを直接書き出す
+public class GenerateConstants {
+  static final String LOOKUP = "Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/Class;";
+  static final String BOOTSTRAPS = "java/lang/invoke/ConstantBootstraps";
+
+  final ByteArrayOutputStream poolBytes = new ByteArrayOutputStream();
+  final DataOutputStream pool = new DataOutputStream(poolBytes);
+  final Map<String, Integer> entries = new HashMap<>();
+  final List<int[]> bootstrapMethods = new ArrayList<>();
+  int count = 1;
+
+  interface Entry {
+    void write(DataOutputStream out) throws IOException;
+  }
+
+  int constant(String key, Entry entry) throws IOException {
+    Integer index = entries.get(key);
+    if (index == null) {
+      entry.write(pool);
+      index = count;
+      count++;
+      entries.put(key, index);
+    }
+    return index;
+  }
+
+  int utf8(String text) throws IOException {
+    return constant("utf8 " + text, out -> {
+      out.writeByte(1);
+      out.writeUTF(text);
+    });
+  }
+
+  int integer(int value) throws IOException {
+    return constant("int " + value, out -> {
+      out.writeByte(3);
+      out.writeInt(value);
+    });
+  }
+
+  int string(String text) throws IOException {
+    int utf8 = utf8(text);
+    return constant("string " + text, out -> {
+      out.writeByte(8);
+      out.writeShort(utf8);
+    });
+  }
+
+  int classRef(String name) throws IOException {
+    int utf8 = utf8(name);
+    return constant("class " + name, out -> {
+      out.writeByte(7);
+      out.writeShort(utf8);
+    });
+  }
+
+  int nameAndType(String name, String descriptor) throws IOException {
+    int nameIndex = utf8(name);
+    int descriptorIndex = utf8(descriptor);
+    return constant("nat " + name + descriptor, out -> {
+      out.writeByte(12);
+      out.writeShort(nameIndex);
+      out.writeShort(descriptorIndex);
+    });
+  }
+
+  int member(int tag, String owner, String name, String descriptor) throws IOException {
+    int classIndex = classRef(owner);
+    int nat = nameAndType(name, descriptor);
+    return constant("member " + tag + owner + "." + name + descriptor, out -> {
+      out.writeByte(tag);
+      out.writeShort(classIndex);
+      out.writeShort(nat);
+    });
+  }
+
+  int methodHandle(int kind, int tag, String owner, String name, String descriptor) throws IOException {
+    int reference = member(tag, owner, name, descriptor);
+    return constant("handle " + kind + " " + reference, out -> {
+      out.writeByte(15);
+      out.writeByte(kind);
+      out.writeShort(reference);
+    });
+  }
+
+  int methodType(String descriptor) throws IOException {
+    int utf8 = utf8(descriptor);
+    return constant("type " + descriptor, out -> {
+      out.writeByte(16);
+      out.writeShort(utf8);
+    });
+  }
+
+  int dynamic(String bootstrap, String bootstrapDescriptor, String name, String type, int... arguments) throws IOException {
+    int handle = methodHandle(6, 10, BOOTSTRAPS, bootstrap, "(" + LOOKUP + bootstrapDescriptor);
+    int[] method = new int[arguments.length + 1];
+    method[0] = handle;
+    System.arraycopy(arguments, 0, method, 1, arguments.length);
+    int index = bootstrapMethods.size();
+    bootstrapMethods.add(method);
+    int nat = nameAndType(name, type);
+    return constant("dynamic " + index, out -> {
+      out.writeByte(17);
+      out.writeShort(index);
+      out.writeShort(nat);
+    });
+  }
+
+  final ByteArrayOutputStream codeBytes = new ByteArrayOutputStream();
+  final DataOutputStream code = new DataOutputStream(codeBytes);
+
+  void print(int load, int constant, String descriptor) throws IOException {
+    code.writeByte(0xb2);
+    code.writeShort(member(9, "java/lang/System", "out", "Ljava/io/PrintStream;"));
+    code.writeByte(load);
+    code.writeShort(constant);
+    code.writeByte(0xb6);
+    code.writeShort(member(10, "java/io/PrintStream", "println", "(" + descriptor + ")V"));
+  }
+
+  // MethodHandle.invokeExactで呼び出した結果を表示する
+  void printInvoked(int handle, int argument, String type, String descriptor) throws IOException {
+    code.writeByte(0xb2);
+    code.writeShort(member(9, "java/lang/System", "out", "Ljava/io/PrintStream;"));
+    code.writeByte(0x13);
+    code.writeShort(handle);
+    if (argument != 0) {
+      code.writeByte(0x13);
+      code.writeShort(argument);
+    }
+    code.writeByte(0xb6);
+    code.writeShort(member(10, "java/lang/invoke/MethodHandle", "invokeExact", type));
+    code.writeByte(0xb6);
+    code.writeShort(member(10, "java/io/PrintStream", "println", "(" + descriptor + ")V"));
+  }
+
+  byte[] generate() throws IOException {
+    final int ldc = 0x13;
+    final int ldc2 = 0x14;
+    int toHex = methodHandle(6, 10, "java/lang/Integer", "toHexString", "(I)Ljava/lang/String;");
+    print(ldc, methodType("(ILjava/lang/Object;[J)Ljava/lang/String;"), "Ljava/lang/Object;");
+    print(ldc, toHex, "Ljava/lang/Object;");
+    printInvoked(toHex, integer(255), "(I)Ljava/lang/String;", "Ljava/lang/String;");
+    printInvoked(methodHandle(8, 10, "java/lang/StringBuilder", "<init>", "(Ljava/lang/String;)V"), string("built"),
+      "(Ljava/lang/String;)Ljava/lang/StringBuilder;", "Ljava/lang/Object;");
+    printInvoked(methodHandle(2, 9, "Holder", "count", "I"), 0, "()I", "I");
+    printInvoked(methodHandle(5, 10, "java/lang/String", "length", "()I"), string("length"), "(Ljava/lang/String;)I", "I");
+
+    print(ldc, dynamic("nullConstant", ")Ljava/lang/Object;", "_", "Ljava/lang/Object;"), "Ljava/lang/Object;");
+    print(ldc2, dynamic("getStaticFinal", "Ljava/lang/Class;)Ljava/lang/Object;", "LIMIT", "J", classRef("Holder")), "J");
+    print(ldc, dynamic("getStaticFinal", ")Ljava/lang/Object;", "TRUE", "Ljava/lang/Boolean;"), "Ljava/lang/Object;");
+    print(ldc, dynamic("enumConstant", ")Ljava/lang/Enum;", "GREEN", "LColor;"), "Ljava/lang/Object;");
+    int max = methodHandle(6, 10, "java/lang/Math", "max", "(II)I");
+    print(ldc, dynamic("invoke", "Ljava/lang/invoke/MethodHandle;[Ljava/lang/Object;)Ljava/lang/Object;", "_", "I", max, integer(3), integer(7)), "I");
+    print(ldc, dynamic("explicitCast", "Ljava/lang/Object;)Ljava/lang/Object;", "_", "B", integer(300)), "I");
+    print(ldc2, dynamic("explicitCast", "Ljava/lang/Object;)Ljava/lang/Object;", "_", "J", integer(-5)), "J");
+    code.writeByte(0xb1);
+
+    int thisClass = classRef("Constants");
+    int superClass = classRef("java/lang/Object");
+    int main = utf8("main");
+    int mainDescriptor = utf8("([Ljava/lang/String;)V");
+    int codeName = utf8("Code");
+    int bootstrapName = utf8("BootstrapMethods");
+
+    ByteArrayOutputStream bytes = new ByteArrayOutputStream();
+    DataOutputStream out = new DataOutputStream(bytes);
+    out.writeInt(0xcafebabe);
+    out.writeShort(0);
+    out.writeShort(55);
+    out.writeShort(count);
+    out.write(poolBytes.toByteArray());
+    out.writeShort(0x21);
+    out.writeShort(thisClass);
+    out.writeShort(superClass);
+    out.writeShort(0);
+    out.writeShort(0);
+    out.writeShort(1);
+    out.writeShort(0x09);
+    out.writeShort(main);
+    out.writeShort(mainDescriptor);
+    out.writeShort(1);
+    out.writeShort(codeName);
+    out.writeInt(12 + codeBytes.size());
+    out.writeShort(4);
+    out.writeShort(1);
+    out.writeInt(codeBytes.size());
+    out.write(codeBytes.toByteArray());
+    out.writeShort(0);
+    out.writeShort(0);
+
+    out.writeShort(1);
+    out.writeShort(bootstrapName);
+    int length = 2;
+    for (int[] method : bootstrapMethods) {
+      length += 2 * (method.length + 1);
+    }
+    out.writeInt(length);
+    out.writeShort(bootstrapMethods.size());
+    for (int[] method : bootstrapMethods) {
+      out.writeShort(method[0]);
+      out.writeShort(method.length - 1);
+      for (int i = 1; i < method.length; i++) {
+        out.writeShort(method[i]);
+      }
+    }
+    return bytes.toByteArray();
+  }
+
+  public static void main(String[] args) throws IOException {
+    try (FileOutputStream file = new FileOutputStream(args[0] + "/Constants.class")) {
+      file.write(new GenerateConstants().generate());
+    }
+  }
+}
+
+class Holder {
+  static final long LIMIT = 1L << 40;
+  static int count = 12;
+}
+
+enum Color {
+  RED,
+  GREEN,
+}
@@ -0,0 +1,60 @@
+import java.util.function.BiFunction;
+import java.util.function.Function;
+import java.util.function.IntSupplier;
+import java.util.function.Supplier;
+
+// invokedynamicの結合テスト: 文字列連結のレシピ (定数と\u0001/\u0002のタグ) とキャプチャするラムダ
+public class Indy {
+  static final String CONSTANT = "const";
+
+  int base = 100;
+
+  public String toString() {
+    return "Indy" + base;
+  }
+
+  static String concat(int i, long j, char c, boolean z, float f, double d, Object o, String s, byte b, short h) {
+    return i + "|" + j + "|" + c + "|" + z + "|" + f + "|" + d + "|" + o + "|" + s + "|" + b + "|" + h;
+  }
+
+  // javacは\u0001と\u0002を含む文字列を定数の引数 (\u0002) として渡す
+  static String tags(int i, String s) {
+    return "\u0001" + i + "\u0002" + s + "tail\u0001\u0002" + CONSTANT + 42 + 'x' + 1.5;
+  }
+
+  IntSupplier adder(int delta) {
+    return () -> base + delta;
+  }
+
+  public static void main(String[] args) {
+    System.out.println(concat(-1, Long.MIN_VALUE, 'c', true, 1.0f / 3, -0.0, null, "s", (byte) -128, (short) 7));
+    String tagged = tags(5, "five");
+    System.out.println(tagged.replace('\u0001', '1').replace('\u0002', '2'));
+    System.out.println(tagged.length());
+    String empty = "";
+    System.out.println("[" + empty + empty + "]" + new Indy());
+
+    int captured = 3;
+    long wide = 1L << 40;
+    String prefix = "p:";
+    Function<Integer, String> capturing = x -> prefix + (x * captured + wide);
+    System.out.println(capturing.apply(4));
+    Indy indy = new Indy();
+    IntSupplier bound = indy.adder(5);
+    indy.base = 200;
+    System.out.println(bound.getAsInt());
+    Function<String, String> boundReference = prefix::concat;
+    System.out.println(boundReference.apply("q"));
+    BiFunction<String, Integer, Character> unbound = String::charAt;
+    System.out.println(unbound.apply("hello", 1));
+    Supplier<Indy> constructor = Indy::new;
+    System.out.println(constructor.get());
+    Function<Integer, int[]> array = int[]::new;
+    System.out.println(array.apply(3).length);
+    Runnable nested = () -> {
+      Supplier<String> inner = () -> prefix + captured;
+      System.out.println(inner.get());
+    };
+    nested.run();
+  }
+}
@@ -0,0 +1,25 @@
+public class InitFailure {
+  static class Broken {
+    static int value = 1 / zero();
+  }
+
+  static int zero() {
+    return 0;
+  }
+
+  public static void main(String[] args) {
+    Throwable first = null;
+    try {
+      System.out.println(Broken.value);
+    } catch (ExceptionInInitializerError e) {
+      first = e;
+      System.out.println("first " + e.getCause().getMessage());
+    }
+    try {
+      System.out.println(Broken.value);
+    } catch (NoClassDefFoundError e) {
+      System.out.println(e.getMessage());
+      System.out.println("cause " + (e.getCause() == first));
+    }
+  }
+}
@@ -0,0 +1,155 @@
+// JITの結合テスト: 同じ出力になることをインタプリタと比べる (演算、分岐、フィールド、switch、例外)
+public class JitWorkload {
+  int count;
+  long total;
+  double weight;
+  static int calls;
+  static long checksum;
+
+  static int ints(int a, int b) {
+    int r = a * 31 + b;
+    r ^= r >>> 7;
+    r -= b << 3;
+    r |= a & 0xff;
+    r += a >> 2;
+    r %= 1000003;
+    return -r + (a / (b | 1));
+  }
+
+  static long longs(long a, int shift) {
+    long r = a * 6364136223846793005L + 1442695040888963407L;
+    r ^= r >>> (shift & 63);
+    r += a << shift;
+    r -= a >> 3;
+    return r / 7 + r % 13;
+  }
+
+  static double doubles(double a, float b) {
+    double r = a * 1.5 + b / 3.0f - a % 2.5;
+    if (r > 100.0) {
+      r = r - 100.0;
+    }
+    return -r + (int) a + (long) (b * 2);
+  }
+
+  static int conversions(long a, double d) {
+    int i = (int) a;
+    byte b = (byte) i;
+    char c = (char) i;
+    short s = (short) i;
+    return i + b + c + s + (int) d + (int) (float) d + Long.compare(a, i) + Double.compare(d, a);
+  }
+
+  static int branches(int x) {
+    int r = 0;
+    if (x < 0) {
+      r += 1;
+    } else if (x == 0) {
+      r += 2;
+    } else if (x <= 10) {
+      r += 3;
+    } else if (x > 1000) {
+      r += 4;
+    }
+    if (x != 7 && x >= -3) {
+      r *= 5;
+    }
+    Object o = x % 2 == 0 ? null : "odd";
+    if (o == null) {
+      r += 10;
+    }
+    return r;
+  }
+
+  static int tableSwitch(int x) {
+    switch (x) {
+      case 0: return 11;
+      case 1: return 22;
+      case 2: return 33;
+      case 3: return 44;
+      case 4: return 55;
+      default: return -1;
+    }
+  }
+
+  static int lookupSwitch(int x) {
+    switch (x) {
+      case -100: return 1;
+      case 7: return 2;
+      case 1000: return 3;
+      case 65536: return 4;
+      default: return 0;
+    }
+  }
+
+  void update(int i) {
+    count++;
+    total += i * (long) i;
+    weight = weight * 0.5 + i;
+    calls++;
+  }
+
+  static int divide(int a, int b) {
+    try {
+      return a / b;
+    } catch (ArithmeticException e) {
+      return Integer.MIN_VALUE;
+    }
+  }
+
+  static int element(int[] array, int index) {
+    try {
+      return array[index];
+    } catch (ArrayIndexOutOfBoundsException e) {
+      return -2;
+    } catch (NullPointerException e) {
+      return -3;
+    }
+  }
+
+  static int thrower(int x) {
+    if (x % 5 == 0) {
+      throw new IllegalStateException("x=" + x);
+    }
+    return x;
+  }
+
+  static int catcher(int x) {
+    try {
+      return thrower(x);
+    } catch (IllegalStateException e) {
+      return e.getMessage().length();
+    }
+  }
+
+  static int fib(int n) {
+    return n < 2 ? n : fib(n - 1) + fib(n - 2);
+  }
+
+  public static void main(String[] args) {
+    JitWorkload state = new JitWorkload();
+    int[] array = {3, 1, 4, 1, 5, 9, 2, 6};
+    long sum = 0;
+    double dsum = 0;
+    for (int i = -20; i < 300; i++) {
+      sum += ints(i, i * 7 + 3);
+      sum += longs(i * 1234567L, i);
+      dsum += doubles(i * 0.75, i / 4.0f);
+      sum += conversions(i * 100000007L, i * 3.3);
+      sum += branches(i);
+      sum += tableSwitch(i % 7) + lookupSwitch(i * 50);
+      state.update(i);
+      sum += divide(i, i % 9);
+      sum += element(i % 3 == 0 ? null : array, i % 10);
+      sum += catcher(i);
+      checksum = checksum * 31 + sum;
+    }
+    System.out.println("sum " + sum);
+    System.out.println("dsum " + dsum);
+    System.out.println("fields " + state.count + " " + state.total + " " + state.weight + " " + calls);
+    System.out.println("checksum " + checksum);
+    System.out.println("fib " + fib(20));
+    System.out.println("lookup " + lookupSwitch(65536) + " " + lookupSwitch(7) + " " + tableSwitch(4));
+    System.out.println("min " + divide(Integer.MIN_VALUE, -1) + " " + (Long.MIN_VALUE / -1L) + " " + (Integer.MIN_VALUE % -1));
+  }
+}
@@ -0,0 +1,96 @@
+// メモリモデルのリトマステスト
+// 各回で観測した結果を重複なく出力する
+public class Litmus {
+  static volatile int x, y;
+  static volatile int flag;
+  static int data;
+  static int r1, r2, r3, r4;
+
+  static final class Holder {
+    final int value;
+
+    Holder(int value) {
+      this.value = value;
+    }
+  }
+
+  static Holder shared;
+  static int round;
+  static int sink;
+
+  // スレッドの切り替えが操作の間に入るよう、回ごとに違う長さだけ待つ
+  static void spin(int seed) {
+    int n = round * seed % 300;
+    for (int i = 0; i < n; i++) {
+      sink++;
+    }
+  }
+
+  static void run(Runnable... actions) throws InterruptedException {
+    Thread[] threads = new Thread[actions.length];
+    for (int i = 0; i < actions.length; i++) {
+      threads[i] = new Thread(actions[i]);
+    }
+    for (Thread thread : threads) {
+      thread.start();
+    }
+    for (Thread thread : threads) {
+      thread.join();
+    }
+  }
+
+  static String round(String test) throws InterruptedException {
+    switch (test) {
+      case "sb":
+        // Store Buffering: volatileなら(0, 0)は起こらない
+        x = 0;
+        y = 0;
+        run(() -> { spin(37); x = 1; r1 = y; }, () -> { spin(53); y = 1; r2 = x; });
+        return r1 + "," + r2;
+      case "mp":
+        // Message Passing: volatileなflagを見たら、その前に書いたdataも見える
+        data = 0;
+        flag = 0;
+        r1 = -1;
+        r2 = -1;
+        run(() -> { spin(37); data = 42; flag = 1; }, () -> { spin(53); r1 = flag; r2 = data; });
+        return r1 + "," + r2;
+      case "iriw":
+        // Independent Reads of Independent Writes: 2つの読み手が書き込みの順序に食い違った見方をしない
+        x = 0;
+        y = 0;
+        run(() -> { spin(37); x = 1; }, () -> { spin(53); y = 1; }, () -> { spin(71); r1 = x; r2 = y; }, () -> { spin(89); r3 = y; r4 = x; });
+        return r1 + "," + r2 + "," + r3 + "," + r4;
+      case "final":
+        // finalフィールド: 参照が見えたら、コンストラクタで書いた値が見える
+        shared = null;
+        run(() -> { spin(37); shared = new Holder(7); }, () -> {
+          spin(53);
+          Holder holder = shared;
+          r1 = holder == null ? -1 : holder.value;
+        });
+        return Integer.toString(r1);
+      default:
+        throw new IllegalArgumentException(test);
+    }
+  }
+
+  public static void main(String[] args) throws InterruptedException {
+    String test = args[0];
+    int rounds = Integer.parseInt(args[1]);
+    String[] seen = new String[rounds];
+    int count = 0;
+    for (int i = 0; i < rounds; i++) {
+      round = i;
+      String outcome = round(test);
+      boolean found = false;
+      for (int j = 0; j < count; j++) {
+        found |= seen[j].equals(outcome);
+      }
+      if (!found) {
+        seen[count++] = outcome;
+        System.out.println(outcome);
+      }
+    }
+  }
+}
@@ -0,0 +1,155 @@
+// モニターの結合テスト: synchronized、wait/notify、interrupt、IllegalMonitorStateException
+public class Monitors {
+  static final Object lock = new Object();
+  static int counter;
+  static String item;
+
+  static synchronized void increment() {
+    counter++;
+  }
+
+  static synchronized void fail() {
+    throw new IllegalStateException("inside synchronized method");
+  }
+
+  static void counters() throws InterruptedException {
+    Thread[] threads = new Thread[4];
+    for (int i = 0; i < threads.length; i++) {
+      threads[i] = new Thread(() -> {
+        for (int j = 0; j < 1000; j++) {
+          if (j % 2 == 0) {
+            increment();
+          } else {
+            synchronized (Monitors.class) {
+              counter++;
+            }
+          }
+          if (j % 100 == 0) {
+            Thread.yield();
+          }
+        }
+      });
+      threads[i].start();
+    }
+    for (Thread thread : threads) {
+      thread.join();
+    }
+    System.out.println("counter " + counter);
+  }
+
+  // 受け取り側がwait()し、送り側がnotifyAll()で起こす
+  static void handoff() throws InterruptedException {
+    Thread consumer = new Thread(() -> {
+      StringBuilder received = new StringBuilder();
+      for (int i = 0; i < 3; i++) {
+        synchronized (lock) {
+          while (item == null) {
+            try {
+              lock.wait();
+            } catch (InterruptedException e) {
+              throw new IllegalStateException(e);
+            }
+          }
+          received.append(item);
+          item = null;
+          lock.notifyAll();
+        }
+      }
+      System.out.println("received " + received);
+    });
+    consumer.start();
+    for (String next : new String[] {"a", "b", "c"}) {
+      synchronized (lock) {
+        while (item != null) {
+          lock.wait();
+        }
+        item = next;
+        lock.notifyAll();
+      }
+    }
+    consumer.join();
+  }
+
+  static void interrupts() throws InterruptedException {
+    Object monitor = new Object();
+    boolean[] ready = {false};
+    Thread waiter = new Thread(() -> {
+      synchronized (monitor) {
+        try {
+          ready[0] = true;
+          monitor.wait();
+          System.out.println("woke up normally");
+        } catch (InterruptedException e) {
+          System.out.println("wait interrupted, still owner " + Thread.holdsLock(monitor) + ", flag " + Thread.currentThread().isInterrupted());
+        }
+      }
+    });
+    waiter.start();
+    // waiterはwait()するまでロックを離さないので、ロックを取れたら待っている
+    while (true) {
+      synchronized (monitor) {
+        if (ready[0]) {
+          waiter.interrupt();
+          break;
+        }
+      }
+      Thread.yield();
+    }
+    waiter.join();
+
+    Thread sleeper = new Thread(() -> {
+      try {
+        Thread.sleep(60000);
+        System.out.println("slept");
+      } catch (InterruptedException e) {
+        System.out.println("sleep interrupted");
+      }
+    });
+    sleeper.start();
+    sleeper.interrupt();
+    sleeper.join();
+
+    Thread.currentThread().interrupt();
+    System.out.println("interrupted " + Thread.interrupted() + " " + Thread.interrupted());
+  }
+
+  static void illegalState() {
+    Object monitor = new Object();
+    try {
+      monitor.wait();
+    } catch (IllegalMonitorStateException e) {
+      System.out.println("wait: " + e.getMessage());
+    } catch (InterruptedException e) {
+      System.out.println("unexpected");
+    }
+    try {
+      monitor.notify();
+    } catch (IllegalMonitorStateException e) {
+      System.out.println("notify: " + e.getMessage());
+    }
+    try {
+      monitor.notifyAll();
+    } catch (IllegalMonitorStateException e) {
+      System.out.println("notifyAll: " + e.getMessage());
+    }
+    synchronized (monitor) {
+      synchronized (monitor) {
+        monitor.notify();
+      }
+      System.out.println("reentrant " + Thread.holdsLock(monitor));
+    }
+    System.out.println("released " + Thread.holdsLock(monitor));
+    try {
+      fail();
+    } catch (IllegalStateException e) {
+      System.out.println(e.getMessage() + ", released " + Thread.holdsLock(Monitors.class));
+    }
+  }
+
+  public static void main(String[] args) throws InterruptedException {
+    counters();
+    handoff();
+    interrupts();
+    illegalState();
+  }
+}
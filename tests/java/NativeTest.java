@@ -0,0 +1,43 @@
+// JNIの結合テスト (ネイティブメソッドはtests/native/native_test.c)
+public class NativeTest {
+  // 短い名前のシンボル (Java_NativeTest_add)
+  static native int add(int a, int b);
+
+  // オーバーロードされたメソッドは長い名前のシンボル (__と引数の記述子)
+  static native String describe(int value);
+  static native String describe(String value);
+
+  // JNI_OnLoadで呼んだRegisterNativesで登録する
+  static native int registered();
+
+  static native int onLoadVersion();
+
+  // ローカル参照を作って消す (作った参照の数を返す)
+  static native int localReferences(Object object, int count);
+
+  // グローバル参照で保持する
+  static native void remember(Object object);
+  static native Object recall();
+  static native void forget();
+
+  public static void main(String[] args) {
+    System.load(args[0]);
+    System.out.println("onLoad " + Integer.toHexString(onLoadVersion()));
+    System.out.println("add " + add(40, 2));
+    System.out.println(describe(7));
+    System.out.println(describe("seven"));
+    System.out.println("registered " + registered());
+    System.out.println("locals " + localReferences(new int[1], 10000));
+
+    // グローバル参照だけから届くオブジェクトはGCで回収されない
+    remember(new StringBuilder("global").append(42).toString());
+    for (int i = 0; i < 100000; i++) {
+      int[] garbage = new int[16];
+      garbage[0] = i;
+    }
+    System.gc();
+    System.out.println("recall " + recall());
+    forget();
+    System.out.println("forgotten " + (recall() == null));
+  }
+}
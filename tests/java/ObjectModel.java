@@ -0,0 +1,152 @@
+// オブジェクトモデルの結合テスト: フィールド、配列の生成と読み書き、instanceof/checkcast
+public class ObjectModel {
+  static class Point {
+    int x;
+    long y;
+    double z;
+    Object tag;
+    Point next;
+  }
+
+  static class Point3 extends Point implements Comparable<Point3> {
+    byte b;
+    char c;
+    short s;
+    boolean flag;
+    float f;
+
+    public int compareTo(Point3 other) {
+      return x - other.x;
+    }
+  }
+
+  static Object sink;
+
+  static String check(Runnable body) {
+    try {
+      body.run();
+      return "ok";
+    } catch (ArrayIndexOutOfBoundsException e) {
+      return "aioobe " + e.getMessage();
+    } catch (NegativeArraySizeException e) {
+      return "nase " + e.getMessage();
+    } catch (NullPointerException e) {
+      return "npe";
+    } catch (ClassCastException e) {
+      return "cce";
+    } catch (ArrayStoreException e) {
+      return "ase";
+    }
+  }
+
+  static void fields() {
+    Point3 p = new Point3();
+    System.out.println("defaults " + p.x + " " + p.y + " " + p.z + " " + p.tag + " " + p.next + " " + p.b + " " + (int) p.c + " " + p.s + " " + p.flag + " " + p.f);
+    p.x = 7;
+    p.y = 1L << 40;
+    p.z = 2.5;
+    p.tag = "tag";
+    p.next = new Point();
+    p.next.x = -3;
+    p.b = (byte) 200;
+    p.c = 'Z';
+    p.s = (short) 40000;
+    p.flag = true;
+    p.f = 1.25f;
+    p.x += p.next.x;
+    System.out.println("fields " + p.x + " " + p.y + " " + p.z + " " + p.tag + " " + p.next.x + " " + p.b + " " + p.c + " " + p.s + " " + p.flag + " " + p.f);
+    Point q = null;
+    System.out.println("getfield " + check(() -> System.out.println(q.x)));
+    System.out.println("putfield " + check(() -> q.y = 1));
+  }
+
+  static void newarrays() {
+    boolean[] z = new boolean[2];
+    byte[] b = new byte[2];
+    char[] c = new char[2];
+    short[] s = new short[2];
+    int[] i = new int[2];
+    long[] j = new long[2];
+    float[] f = new float[2];
+    double[] d = new double[2];
+    System.out.println("zero " + z[1] + " " + b[1] + " " + (int) c[1] + " " + s[1] + " " + i[1] + " " + j[1] + " " + f[1] + " " + d[1]);
+    z[0] = true;
+    b[0] = (byte) 0x1ff;
+    c[0] = (char) 0x10041;
+    s[0] = (short) 0x18000;
+    i[0] = Integer.MIN_VALUE;
+    j[0] = Long.MAX_VALUE;
+    f[0] = 0.1f;
+    d[0] = 0.1;
+    System.out.println("store " + z[0] + " " + b[0] + " " + c[0] + " " + s[0] + " " + i[0] + " " + j[0] + " " + f[0] + " " + d[0]);
+    System.out.println("lengths " + z.length + " " + new String[0].length + " " + new Point[5].length);
+    System.out.println("negative " + check(() -> System.out.println(new int[-1].length)));
+    System.out.println("negative objects " + check(() -> System.out.println(new Object[-2].length)));
+  }
+
+  static void multianewarray() {
+    int[][][] cube = new int[2][3][4];
+    cube[1][2][3] = 9;
+    System.out.println("cube " + cube.length + " " + cube[0].length + " " + cube[1][2].length + " " + cube[1][2][3] + " " + cube[0][0][0]);
+    long[][] partial = new long[3][];
+    System.out.println("partial " + partial.length + " " + partial[2]);
+    partial[2] = new long[] {5L};
+    System.out.println("filled " + partial[2][0]);
+    String[][] names = new String[2][2];
+    names[1][0] = "n";
+    System.out.println("names " + names[1][0] + " " + names[0][1]);
+    System.out.println("negative inner " + check(() -> System.out.println(new int[2][-1].length)));
+  }
+
+  static void bounds() {
+    boolean[] z = new boolean[1];
+    byte[] b = new byte[1];
+    char[] c = new char[1];
+    short[] s = new short[1];
+    int[] i = new int[1];
+    long[] j = new long[1];
+    float[] f = new float[1];
+    double[] d = new double[1];
+    Object[] a = new Object[1];
+    System.out.println("baload " + check(() -> System.out.println(z[1])) + " " + check(() -> System.out.println(b[-1])));
+    System.out.println("caload " + check(() -> System.out.println(c[1])) + " " + check(() -> System.out.println(s[2])));
+    System.out.println("iaload " + check(() -> System.out.println(i[1])) + " " + check(() -> System.out.println(j[1])));
+    System.out.println("faload " + check(() -> System.out.println(f[1])) + " " + check(() -> System.out.println(d[1])));
+    System.out.println("aaload " + check(() -> System.out.println(a[1])));
+    System.out.println("bastore " + check(() -> z[1] = true) + " " + check(() -> b[1] = 1));
+    System.out.println("castore " + check(() -> c[1] = 'a') + " " + check(() -> s[1] = 1));
+    System.out.println("iastore " + check(() -> i[-1] = 1) + " " + check(() -> j[1] = 1));
+    System.out.println("fastore " + check(() -> f[1] = 1) + " " + check(() -> d[1] = 1));
+    System.out.println("aastore " + check(() -> a[1] = "x"));
+    Object[] strings = new String[1];
+    System.out.println("covariant " + check(() -> strings[0] = Integer.valueOf(1)) + " " + check(() -> strings[0] = "s"));
+
+    int[] nullInts = null;
+    long[] nullLongs = null;
+    double[] nullDoubles = null;
+    Object[] nullObjects = null;
+    System.out.println("null load " + check(() -> System.out.println(nullInts[0])) + " " + check(() -> System.out.println(nullLongs[0])) + " " + check(() -> System.out.println(nullDoubles[0])) + " " + check(() -> System.out.println(nullObjects[0])));
+    System.out.println("null store " + check(() -> nullInts[0] = 1) + " " + check(() -> nullLongs[0] = 1) + " " + check(() -> nullDoubles[0] = 1) + " " + check(() -> nullObjects[0] = null));
+    System.out.println("null length " + check(() -> System.out.println(nullInts.length)));
+  }
+
+  static void casts() {
+    Object p3 = new Point3();
+    Object p = new Point();
+    Object ints = new int[1];
+    Object points = new Point3[1];
+    Object nothing = null;
+    System.out.println("instanceof " + (p3 instanceof Point) + " " + (p instanceof Point3) + " " + (p3 instanceof Comparable) + " " + (nothing instanceof Object));
+    System.out.println("arrays " + (ints instanceof int[]) + " " + (ints instanceof Object[]) + " " + (points instanceof Point[]) + " " + (points instanceof Object[]) + " " + (points instanceof Comparable[]) + " " + (ints instanceof Cloneable));
+    System.out.println("checkcast " + check(() -> sink = (Point) p3) + " " + check(() -> sink = (Point3) p) + " " + check(() -> sink = (String) nothing));
+    System.out.println("array cast " + check(() -> sink = (Point[]) points) + " " + check(() -> sink = (long[]) ints));
+  }
+
+  public static void main(String[] args) {
+    fields();
+    newarrays();
+    multianewarray();
+    bounds();
+    casts();
+  }
+}
@@ -0,0 +1,15 @@
+// デバッガのREPLの結合テストでデバッグする対象
+public class S {
+  static int total;
+
+  static int add(int a, int b) {
+    int sum = a + b;
+    total += sum;
+    return sum;
+  }
+
+  public static void main(String[] args) {
+    String label = "sum";
+    System.out.println(label + " " + add(2, 3));
+  }
+}
@@ -0,0 +1,4 @@
+// 公開APIの差分の結合テスト (新しい版): クラスとメソッドにabstractを付ける
+public abstract class Gadget {
+  public abstract void work();
+}
@@ -0,0 +1,3 @@
+// 公開APIの差分の結合テスト (新しい版): クラスにfinalを付ける
+public final class Sealed {
+}
@@ -0,0 +1,29 @@
+// 公開APIの差分の結合テスト (新しい版): JLS 13章で互換性を壊す変更と壊さない変更
+public class Widget implements Runnable {
+  public static int count;
+  public int total;
+
+  public void run() {
+  }
+
+  protected void narrowed() {
+  }
+
+  public void widened() {
+  }
+
+  public final void sealed() {
+  }
+
+  public void unsealed() {
+  }
+
+  public void utility() {
+  }
+
+  public static void member() {
+  }
+
+  public void added() {
+  }
+}
@@ -0,0 +1,5 @@
+// 公開APIの差分の結合テスト: Widgetは古い版だけでBaseを継承する
+public class Base {
+  public void inherited() {
+  }
+}
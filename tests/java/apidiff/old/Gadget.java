@@ -0,0 +1,5 @@
+// 公開APIの差分の結合テスト (古い版)
+public class Gadget {
+  public void work() {
+  }
+}
@@ -0,0 +1,3 @@
+// 公開APIの差分の結合テスト (古い版)
+public class Sealed {
+}
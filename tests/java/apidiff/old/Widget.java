@@ -0,0 +1,29 @@
+// 公開APIの差分の結合テスト (古い版)
+public class Widget extends Base implements Runnable {
+  public int count;
+  public static int total;
+
+  public void run() {
+  }
+
+  public void removed() {
+  }
+
+  public void narrowed() {
+  }
+
+  protected void widened() {
+  }
+
+  public void sealed() {
+  }
+
+  public final void unsealed() {
+  }
+
+  public static void utility() {
+  }
+
+  public void member() {
+  }
+}
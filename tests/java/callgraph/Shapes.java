@@ -0,0 +1,44 @@
+// コールグラフの結合テスト: CHAはShapeを実装する全てのクラスに振り分け、RTAはnewしたクラスだけに振り分ける
+interface Shape {
+  int area();
+}
+
+class Circle implements Shape {
+  public int area() {
+    return 3;
+  }
+}
+
+// どこでもnewしない
+class Square implements Shape {
+  public int area() {
+    return 4;
+  }
+}
+
+@interface Handler {
+}
+
+public class Shapes {
+  static int measure(Shape shape) {
+    return shape.area();
+  }
+
+  static void helper() {
+  }
+
+  static void tool() {
+    helper();
+  }
+
+  @Handler
+  static void onEvent() {
+  }
+
+  static void unused() {
+  }
+
+  public static void main(String[] args) {
+    System.out.println(measure(new Circle()));
+  }
+}
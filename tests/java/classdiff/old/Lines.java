@@ -0,0 +1,7 @@
+// classdiffの結合テスト: 空行を足して、LineNumberTableだけを変える
+public class Lines {
+  int twice(int x) {
+    int y = x * 2;
+    return y;
+  }
+}
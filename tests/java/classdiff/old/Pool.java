@@ -0,0 +1,5 @@
+// classdiffの結合テスト: 同じ行のメソッドの順序を入れ替えて、コンスタントプールの並びだけを変える
+public class Pool {
+  static void log(String message) { System.out.println(message); }
+  int first() { log("first"); return 1; } long second() { log("second"); return 2L; }
+}
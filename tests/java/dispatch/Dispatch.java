@@ -0,0 +1,79 @@
+// 仮想メソッド呼び出しの結合テスト: パッケージをまたぐオーバーライド、デフォルトメソッドとAbstractMethodError、多相な呼び出し箇所
+public class Dispatch {
+  static class Circle implements Shape {
+    public double side() {
+      return 1;
+    }
+
+    public double area() {
+      return 3;
+    }
+
+    public String name() {
+      return "circle";
+    }
+  }
+
+  static class Triangle implements Shape {
+    public double side() {
+      return 3;
+    }
+
+    public double area() {
+      return 4.5;
+    }
+  }
+
+  static class Animal {
+    String speak() {
+      return "...";
+    }
+  }
+
+  static class Dog extends Animal {
+    String speak() {
+      return "woof";
+    }
+  }
+
+  static class Cat extends Animal {
+    String speak() {
+      return "meow";
+    }
+  }
+
+  static void packagePrivate() {
+    p1.Base sub = new p2.Sub();
+    p1.Base subSub = new p1.SubSub();
+    System.out.println(sub.callM() + " " + ((p2.Sub) sub).m());
+    System.out.println(subSub.callM() + " " + ((p2.Sub) subSub).m());
+  }
+
+  static void defaults() {
+    Shape[] shapes = {new Circle(), new Triangle(), new Square()};
+    for (Shape shape : shapes) {
+      System.out.print(shape.name() + " " + shape.side());
+      try {
+        System.out.println(" " + shape.area());
+      } catch (AbstractMethodError e) {
+        System.out.println(" AbstractMethodError");
+      }
+    }
+  }
+
+  // 同じ呼び出し箇所に違うクラスのレシーバが来る
+  static void polymorphic() {
+    Animal[] animals = {new Dog(), new Dog(), new Cat(), new Animal(), new Dog(), new Cat(), new Cat()};
+    StringBuilder out = new StringBuilder();
+    for (Animal animal : animals) {
+      out.append(animal.speak()).append(' ');
+    }
+    System.out.println(out.toString().trim());
+  }
+
+  public static void main(String[] args) {
+    packagePrivate();
+    defaults();
+    polymorphic();
+  }
+}
@@ -0,0 +1,5 @@
+public class Square implements Shape {
+  public double side() {
+    return 2;
+  }
+}
@@ -0,0 +1,10 @@
+// 後から抽象メソッドとデフォルトメソッドを追加したShape
+public interface Shape {
+  double side();
+
+  double area();
+
+  default String name() {
+    return "shape";
+  }
+}
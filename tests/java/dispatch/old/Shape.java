@@ -0,0 +1,4 @@
+// Squareをコンパイルした時点のShape (area()もname()も無い)
+public interface Shape {
+  double side();
+}
@@ -0,0 +1,12 @@
+package p1;
+
+// パッケージプライベートのメソッドは同じパッケージのサブクラスだけがオーバーライドできる
+public class Base {
+  String m() {
+    return "Base.m";
+  }
+
+  public String callM() {
+    return m();
+  }
+}
@@ -0,0 +1,8 @@
+package p1;
+
+// Base.m()とSub.m()の両方をオーバーライドする
+public class SubSub extends p2.Sub {
+  public String m() {
+    return "SubSub.m";
+  }
+}
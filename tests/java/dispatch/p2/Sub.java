@@ -0,0 +1,8 @@
+package p2;
+
+// 別パッケージなのでBase.m()をオーバーライドしない
+public class Sub extends p1.Base {
+  public String m() {
+    return "Sub.m";
+  }
+}
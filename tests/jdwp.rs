@@ -0,0 +1,316 @@
+mod common;
+
+use std::{
+  io::{BufRead, BufReader, Read, Write},
+  path::Path,
+  net::TcpStream,
+  process::{Child, ChildStdout, Stdio},
+  time::Duration,
+};
+
+use common::{compile, rust_jvm};
+
+const HANDSHAKE: &[u8] = b"JDWP-Handshake";
+const REPLY: u8 = 0x80;
+
+const SINGLE_STEP: u8 = 1;
+const BREAKPOINT: u8 = 2;
+const CLASS_PREPARE: u8 = 8;
+const VM_START: u8 = 90;
+const SUSPEND_ALL: u8 = 2;
+
+// 返信とイベントのパケット (ヘッダの最後の2バイトは、返信ならエラーコード、コマンドならコマンドセットとコマンド)
+struct Packet {
+  flags: u8,
+  command: (u8, u8),
+  error: u16,
+  data: Vec<u8>,
+}
+
+#[derive(Default)]
+struct Writer(Vec<u8>);
+
+impl Writer {
+  fn u8(&mut self, value: u8) -> &mut Self {
+    self.0.push(value);
+    self
+  }
+
+  fn i32(&mut self, value: i32) -> &mut Self {
+    self.0.extend_from_slice(&value.to_be_bytes());
+    self
+  }
+
+  fn u64(&mut self, value: u64) -> &mut Self {
+    self.0.extend_from_slice(&value.to_be_bytes());
+    self
+  }
+
+  fn string(&mut self, value: &str) -> &mut Self {
+    self.i32(value.len() as i32);
+    self.0.extend_from_slice(value.as_bytes());
+    self
+  }
+}
+
+struct Reader<'a> {
+  data: &'a [u8],
+}
+
+impl Reader<'_> {
+  fn bytes<const N: usize>(&mut self) -> [u8; N] {
+    let (bytes, rest) = self.data.split_at(N);
+    self.data = rest;
+    bytes.try_into().unwrap()
+  }
+
+  fn u8(&mut self) -> u8 {
+    self.bytes::<1>()[0]
+  }
+
+  fn i32(&mut self) -> i32 {
+    i32::from_be_bytes(self.bytes())
+  }
+
+  fn u64(&mut self) -> u64 {
+    u64::from_be_bytes(self.bytes())
+  }
+
+  fn string(&mut self) -> String {
+    let length = self.i32() as usize;
+    let (bytes, rest) = self.data.split_at(length);
+    self.data = rest;
+    String::from_utf8(bytes.to_vec()).unwrap()
+  }
+
+  // Location: 型のタグ、クラスID、メソッドID、位置
+  fn location(&mut self) -> (u64, u64, u64) {
+    self.u8();
+    (self.u64(), self.u64(), self.u64())
+  }
+}
+
+// 台本どおりにコマンドを送るデバッガ
+struct Debugger {
+  child: Child,
+  stdout: BufReader<ChildStdout>,
+  stream: TcpStream,
+  next_id: u32,
+}
+
+impl Debugger {
+  // address=0で空いているポートを選ばせ、表示されたポートに接続する
+  fn launch(class_path: &Path, main: &str) -> Debugger {
+    let mut child = rust_jvm()
+      .arg("run")
+      .arg("-cp")
+      .arg(class_path)
+      .arg("-agentlib:jdwp=transport=dt_socket,server=y,suspend=y,address=0")
+      .arg(main)
+      .stdout(Stdio::piped())
+      .spawn()
+      .unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    stdout.read_line(&mut line).unwrap();
+    let port = line.trim().strip_prefix("Listening for transport dt_socket at address: ").expect(&line);
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+    stream.write_all(HANDSHAKE).unwrap();
+    let mut received = [0; HANDSHAKE.len()];
+    stream.read_exact(&mut received).unwrap();
+    assert_eq!(received, HANDSHAKE);
+    Debugger { child, stdout, stream, next_id: 1 }
+  }
+
+  fn read_packet(&mut self) -> Packet {
+    let mut header = [0; 11];
+    self.stream.read_exact(&mut header).unwrap();
+    let length = u32::from_be_bytes(header[0..4].try_into().unwrap()) as usize;
+    let mut data = vec![0; length - header.len()];
+    self.stream.read_exact(&mut data).unwrap();
+    let flags = header[8];
+    let error = u16::from_be_bytes([header[9], header[10]]);
+    Packet { flags, command: (header[9], header[10]), error, data }
+  }
+
+  fn command(&mut self, command_set: u8, command: u8, data: &Writer) -> Vec<u8> {
+    let id = self.next_id;
+    self.next_id += 1;
+    let mut packet = Vec::new();
+    packet.extend_from_slice(&(11 + data.0.len() as u32).to_be_bytes());
+    packet.extend_from_slice(&id.to_be_bytes());
+    packet.extend_from_slice(&[0, command_set, command]);
+    packet.extend_from_slice(&data.0);
+    self.stream.write_all(&packet).unwrap();
+    let reply = self.read_packet();
+    assert_eq!(reply.flags, REPLY, "unexpected event while waiting for a reply");
+    assert_eq!(reply.error, 0, "command ({}, {}) failed", command_set, command);
+    reply.data
+  }
+
+  // Compositeイベントを待ち、最初のイベントの種類と残りのデータを返す
+  fn event(&mut self) -> (u8, Vec<u8>) {
+    let packet = self.read_packet();
+    assert_eq!(packet.flags, 0);
+    assert_eq!(packet.command, (64, 100));
+    let mut reader = Reader { data: &packet.data };
+    reader.u8();
+    assert_eq!(reader.i32(), 1);
+    let kind = reader.u8();
+    reader.i32();
+    (kind, reader.data.to_vec())
+  }
+
+  fn resume(&mut self) {
+    self.command(1, 9, &Writer::default());
+  }
+
+  // 修飾子を1つ付けたイベントリクエスト
+  fn set_request(&mut self, kind: u8, modifier: &Writer) -> i32 {
+    let mut data = Writer::default();
+    data.u8(kind).u8(SUSPEND_ALL).i32(1);
+    data.0.extend_from_slice(&modifier.0);
+    let reply = self.command(15, 1, &data);
+    Reader { data: &reply }.i32()
+  }
+
+  // イベントで止まった位置 (スレッド、メソッドID、位置)
+  fn stopped_at(&mut self, expected: u8) -> (u64, u64, u64) {
+    let (kind, data) = self.event();
+    assert_eq!(kind, expected);
+    let mut reader = Reader { data: &data };
+    let thread = reader.u64();
+    let (_, method, index) = reader.location();
+    (thread, method, index)
+  }
+
+  fn line_table(&mut self, class: u64, method: u64) -> Vec<(u64, i32)> {
+    let mut data = Writer::default();
+    data.u64(class).u64(method);
+    let reply = self.command(6, 1, &data);
+    let mut reader = Reader { data: &reply };
+    reader.u64();
+    reader.u64();
+    (0..reader.i32()).map(|_| (reader.u64(), reader.i32())).collect()
+  }
+
+  fn line_at(&mut self, class: u64, method: u64, index: u64) -> i32 {
+    let table = self.line_table(class, method);
+    table.iter().filter(|(pc, _)| *pc <= index).max_by_key(|(pc, _)| *pc).unwrap().1
+  }
+
+  // 最も上のフレームのint型の局所変数
+  fn int_local(&mut self, thread: u64, slot: i32) -> i32 {
+    let mut data = Writer::default();
+    data.u64(thread).i32(0).i32(1);
+    let reply = self.command(11, 6, &data);
+    let mut reader = Reader { data: &reply };
+    assert_eq!(reader.i32(), 1);
+    let frame = reader.u64();
+    let mut data = Writer::default();
+    data.u64(thread).u64(frame).i32(1).i32(slot).u8(b'I');
+    let reply = self.command(16, 1, &data);
+    let mut reader = Reader { data: &reply };
+    assert_eq!(reader.i32(), 1);
+    assert_eq!(reader.u8(), b'I');
+    reader.i32()
+  }
+}
+
+#[test]
+fn breakpoint_step_and_locals() {
+  let classes = compile("jdwp", &["DebugTarget.java"]);
+  let mut debugger = Debugger::launch(&classes, "DebugTarget");
+  let (kind, _) = debugger.event();
+  assert_eq!(kind, VM_START);
+
+  let reply = debugger.command(1, 7, &Writer::default());
+  let mut reader = Reader { data: &reply };
+  assert!((0..5).all(|_| reader.i32() == 8));
+
+  // 対象のクラスが読み込まれるまで進める
+  let mut modifier = Writer::default();
+  modifier.u8(5).string("DebugTarget");
+  debugger.set_request(CLASS_PREPARE, &modifier);
+  debugger.resume();
+  let (kind, data) = debugger.event();
+  assert_eq!(kind, CLASS_PREPARE);
+  let mut reader = Reader { data: &data };
+  reader.u64();
+  reader.u8();
+  let class = reader.u64();
+  assert_eq!(reader.string(), "LDebugTarget;");
+
+  let mut data = Writer::default();
+  data.string("LDebugTarget;");
+  let reply = debugger.command(1, 2, &data);
+  let mut reader = Reader { data: &reply };
+  assert_eq!(reader.i32(), 1);
+  reader.u8();
+  assert_eq!(reader.u64(), class);
+
+  let mut data = Writer::default();
+  data.u64(class);
+  let reply = debugger.command(2, 5, &data);
+  let mut reader = Reader { data: &reply };
+  let methods: Vec<(u64, String, String)> = (0..reader.i32())
+    .map(|_| {
+      let method = (reader.u64(), reader.string(), reader.string());
+      reader.i32();
+      method
+    })
+    .collect();
+  let compute = methods.iter().find(|(_, name, signature)| name == "compute" && signature == "(I)I").unwrap().0;
+
+  // 局所変数の表から名前でスロットを引く
+  let mut data = Writer::default();
+  data.u64(class).u64(compute);
+  let reply = debugger.command(6, 2, &data);
+  let mut reader = Reader { data: &reply };
+  assert_eq!(reader.i32(), 1);
+  let variables: Vec<(String, String, i32)> = (0..reader.i32())
+    .map(|_| {
+      reader.u64();
+      let (name, signature) = (reader.string(), reader.string());
+      reader.i32();
+      (name, signature, reader.i32())
+    })
+    .collect();
+  let slot = |name: &str| variables.iter().find(|(n, signature, _)| n == name && signature == "I").unwrap().2;
+  let (a, b, c) = (slot("a"), slot("b"), slot("c"));
+
+  // 2行目 (int c = b + 3;) の先頭にブレークポイントを置く
+  let lines = debugger.line_table(class, compute);
+  assert_eq!(lines.len(), 3);
+  let (pc, line) = lines[1];
+  let mut modifier = Writer::default();
+  modifier.u8(7).u8(1).u64(class).u64(compute).u64(pc);
+  debugger.set_request(BREAKPOINT, &modifier);
+  debugger.resume();
+  let (thread, method, index) = debugger.stopped_at(BREAKPOINT);
+  assert_eq!((method, index), (compute, pc));
+  assert_eq!(debugger.int_local(thread, a), 20);
+  assert_eq!(debugger.int_local(thread, b), 40);
+
+  // 行単位のステップオーバーで次の行に進む
+  let mut modifier = Writer::default();
+  modifier.u8(10).u64(thread).i32(1).i32(1);
+  let step = debugger.set_request(SINGLE_STEP, &modifier);
+  debugger.resume();
+  let (_, method, index) = debugger.stopped_at(SINGLE_STEP);
+  assert_eq!(method, compute);
+  assert_eq!(debugger.line_at(class, compute, index), line + 1);
+  assert_eq!(debugger.int_local(thread, c), 43);
+
+  let mut data = Writer::default();
+  data.u8(SINGLE_STEP).i32(step);
+  debugger.command(15, 2, &data);
+  debugger.command(15, 3, &Writer::default());
+  debugger.resume();
+
+  let mut output = String::new();
+  debugger.stdout.read_to_string(&mut output).unwrap();
+  assert_eq!(output, "43\n");
+  assert!(debugger.child.wait().unwrap().success());
+}
@@ -0,0 +1,30 @@
+mod common;
+
+use std::{path::PathBuf, sync::OnceLock};
+
+use common::{compile, run_with};
+
+fn classes() -> &'static PathBuf {
+  static CLASSES: OnceLock<PathBuf> = OnceLock::new();
+  CLASSES.get_or_init(|| compile("jit", &["JitWorkload.java"]))
+}
+
+// 標準出力と標準エラー出力を返す
+fn run(options: &[&str]) -> (String, String) {
+  let output = run_with(classes(), options, "JitWorkload", &[]);
+  let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+  assert!(output.status.success(), "{:?}: {}", options, stderr);
+  (String::from_utf8(output.stdout).unwrap(), stderr)
+}
+
+#[test]
+fn compiled_code_matches_interpreter() {
+  let (interpreted, _) = run(&["-Xint"]);
+  let (compiled, stderr) = run(&["-XX:CompileThreshold=1", "-XX:+PrintCompilation"]);
+  assert_eq!(compiled, interpreted);
+  assert!(interpreted.starts_with("sum "), "{}", interpreted);
+  // 比べたメソッドが実際にコンパイルされている
+  for method in ["JitWorkload.ints(II)I", "JitWorkload.longs(JI)J", "JitWorkload.tableSwitch(I)I", "JitWorkload.update(I)V", "JitWorkload.divide(II)I"] {
+    assert!(stderr.contains(&format!("[jit] compiled {} ", method)), "{} was not compiled:\n{}", method, stderr);
+  }
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use std::{
+  env, fs,
+  path::{Path, PathBuf},
+  process::Command,
+};
+
+use common::{compile, run_with};
+
+// jni.hのあるJDKのディレクトリ (JAVA_HOMEが無ければjavacの場所から辿る)
+fn java_home() -> PathBuf {
+  if let Some(home) = env::var_os("JAVA_HOME") {
+    return PathBuf::from(home);
+  }
+  let output = Command::new("sh").args(["-c", "command -v javac"]).output().unwrap();
+  let javac = fs::canonicalize(String::from_utf8(output.stdout).unwrap().trim()).unwrap();
+  javac.ancestors().nth(2).unwrap().to_path_buf()
+}
+
+// tests/nativeのCのソースを共有ライブラリにする
+fn compile_library(dir: &Path, source: &str, name: &str) -> PathBuf {
+  let include = java_home().join("include");
+  let library = dir.join(name);
+  let status = Command::new("cc")
+    .args(["-shared", "-fPIC", "-Wall", "-o"])
+    .arg(&library)
+    .arg("-I")
+    .arg(&include)
+    .arg("-I")
+    .arg(include.join("linux"))
+    .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/native").join(source))
+    .status()
+    .unwrap();
+  assert!(status.success(), "cc failed for {}", source);
+  library
+}
+
+#[test]
+fn native_methods_and_references() {
+  let classes = compile("jni", &["NativeTest.java"]);
+  let library = compile_library(&classes, "native_test.c", "libnative_test.so");
+  // グローバル参照だけから届くオブジェクトが、小さいヒープでのGCの後も残っていることを確かめる
+  let output = run_with(&classes, &["-Xmx16m", "-verbose:gc"], "NativeTest", &[library.to_str().unwrap()]);
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  assert!(output.status.success(), "{}", stderr);
+  assert!(stderr.lines().any(|line| line.starts_with("[gc] GC(")));
+  assert_eq!(
+    String::from_utf8_lossy(&output.stdout),
+    "onLoad 10008\n\
+     add 42\n\
+     int 7\n\
+     String seven\n\
+     registered 123\n\
+     locals 20000\n\
+     recall global42\n\
+     forgotten true\n"
+  );
+}
@@ -0,0 +1,10 @@
+mod common;
+
+use common::{compile, run_main};
+
+#[test]
+fn lambdas_use_builtin_functional_interfaces_and_objects() {
+  let classes = compile("library", &["Functions.java"]);
+  let output = run_main(&classes, "Functions", &[]);
+  assert_eq!(output, "42\nab\ntrue false\n21\n42\nnpe\ntrue false 0\n1089 none\nvalue\n");
+}
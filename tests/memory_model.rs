@@ -0,0 +1,72 @@
+mod common;
+
+use std::{collections::BTreeSet, path::PathBuf, sync::OnceLock};
+
+use common::{compile, run_main};
+
+fn classes() -> &'static PathBuf {
+  static CLASSES: OnceLock<PathBuf> = OnceLock::new();
+  CLASSES.get_or_init(|| compile("memory_model", &["Litmus.java", "Atomics.java"]))
+}
+
+// 観測した結果が全て許される結果であることを確かめる
+fn litmus(test: &str, allowed: &[&str]) {
+  let output = run_main(classes(), "Litmus", &[test, "300"]);
+  let observed: BTreeSet<&str> = output.lines().collect();
+  assert!(!observed.is_empty());
+  for outcome in observed {
+    assert!(allowed.contains(&outcome), "{}: forbidden outcome {}", test, outcome);
+  }
+}
+
+#[test]
+fn store_buffering() {
+  litmus("sb", &["0,1", "1,0", "1,1"]);
+}
+
+#[test]
+fn message_passing() {
+  litmus("mp", &["0,0", "0,42", "1,42"]);
+}
+
+#[test]
+fn independent_reads_of_independent_writes() {
+  let mut allowed = Vec::new();
+  for bits in 0..16 {
+    let outcome = format!("{},{},{},{}", bits >> 3 & 1, bits >> 2 & 1, bits >> 1 & 1, bits & 1);
+    if outcome != "1,0,1,0" {
+      allowed.push(outcome);
+    }
+  }
+  let allowed: Vec<&str> = allowed.iter().map(String::as_str).collect();
+  litmus("iriw", &allowed);
+}
+
+#[test]
+fn final_field_freeze() {
+  litmus("final", &["-1", "7"]);
+}
+
+fn atomics(test: &str) -> String {
+  run_main(classes(), "Atomics", &[test, "4", "5000"]).trim().to_string()
+}
+
+#[test]
+fn atomic_integer_get_and_add() {
+  assert_eq!(atomics("atomic-integer"), "40000");
+}
+
+#[test]
+fn var_handle_get_and_add_field() {
+  assert_eq!(atomics("var-handle-field"), "60000");
+}
+
+#[test]
+fn var_handle_get_and_add_array_element() {
+  assert_eq!(atomics("var-handle-array"), "20000");
+}
+
+#[test]
+fn compare_and_set_loop() {
+  assert_eq!(atomics("compare-and-set"), "20000");
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use common::{compile, run_main, run_with};
+
+#[test]
+fn synchronized_wait_notify_and_interrupt() {
+  let classes = compile("monitors", &["Monitors.java"]);
+  let output = run_main(&classes, "Monitors", &[]);
+  // 期待値は同じクラスをjavaで実行した出力
+  assert_eq!(output, "\
+counter 4000
+received abc
+wait interrupted, still owner true, flag false
+sleep interrupted
+interrupted true false
+wait: current thread is not owner
+notify: current thread is not owner
+notifyAll: current thread is not owner
+reentrant true
+released false
+inside synchronized method, released false
+");
+}
+
+#[test]
+fn deadlock_is_reported_with_thread_dump() {
+  let classes = compile("deadlock", &["Deadlock.java"]);
+  let output = run_with(&classes, &[], "Deadlock", &[]);
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  assert_eq!(output.status.code(), Some(1), "{}", stderr);
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "started\n");
+  assert!(stderr.starts_with("Full thread dump:\n"), "{}", stderr);
+  assert!(stderr.contains("\"worker-a\":\n  waiting to lock "), "{}", stderr);
+  assert!(stderr.contains("which is held by \"worker-b\"\n"), "{}", stderr);
+  assert!(stderr.contains("which is held by \"worker-a\"\n"), "{}", stderr);
+  assert!(stderr.ends_with("Found 1 deadlock.\n"), "{}", stderr);
+}
@@ -0,0 +1,91 @@
+// tests/java/NativeTest.javaのネイティブメソッド
+#include <jni.h>
+#include <stdio.h>
+
+static jint on_load_version;
+static jobject remembered;
+
+static jint JNICALL registered(JNIEnv *env, jclass class) {
+  return 123;
+}
+
+JNIEXPORT jint JNICALL JNI_OnLoad(JavaVM *vm, void *reserved) {
+  JNIEnv *env;
+  if ((*vm)->GetEnv(vm, (void **) &env, JNI_VERSION_1_8) != JNI_OK) {
+    return JNI_ERR;
+  }
+  jclass class = (*env)->FindClass(env, "NativeTest");
+  if (class == NULL) {
+    return JNI_ERR;
+  }
+  JNINativeMethod methods[] = {{"registered", "()I", (void *) registered}};
+  if ((*env)->RegisterNatives(env, class, methods, 1) != JNI_OK) {
+    return JNI_ERR;
+  }
+  on_load_version = JNI_VERSION_1_8;
+  return JNI_VERSION_1_8;
+}
+
+JNIEXPORT jint JNICALL Java_NativeTest_onLoadVersion(JNIEnv *env, jclass class) {
+  return on_load_version;
+}
+
+JNIEXPORT jint JNICALL Java_NativeTest_add(JNIEnv *env, jclass class, jint a, jint b) {
+  return a + b;
+}
+
+JNIEXPORT jstring JNICALL Java_NativeTest_describe__I(JNIEnv *env, jclass class, jint value) {
+  char text[32];
+  snprintf(text, sizeof text, "int %d", value);
+  return (*env)->NewStringUTF(env, text);
+}
+
+JNIEXPORT jstring JNICALL Java_NativeTest_describe__Ljava_lang_String_2(JNIEnv *env, jclass class, jstring value) {
+  const char *chars = (*env)->GetStringUTFChars(env, value, NULL);
+  char text[64];
+  snprintf(text, sizeof text, "String %s", chars);
+  (*env)->ReleaseStringUTFChars(env, value, chars);
+  return (*env)->NewStringUTF(env, text);
+}
+
+// 作ったローカル参照はすぐに消すか、ローカルフレームごと解放する
+JNIEXPORT jint JNICALL Java_NativeTest_localReferences(JNIEnv *env, jclass class, jobject object, jint count) {
+  jint created = 0;
+  for (jint i = 0; i < count; i++) {
+    jobject local = (*env)->NewLocalRef(env, object);
+    if ((*env)->GetObjectRefType(env, local) != JNILocalRefType || !(*env)->IsSameObject(env, local, object)) {
+      return -1;
+    }
+    (*env)->DeleteLocalRef(env, local);
+    created++;
+  }
+  if ((*env)->PushLocalFrame(env, 16) != JNI_OK) {
+    return -1;
+  }
+  jobject inner = NULL;
+  for (jint i = 0; i < count; i++) {
+    inner = (*env)->NewLocalRef(env, object);
+    created++;
+  }
+  jobject result = (*env)->PopLocalFrame(env, inner);
+  if (!(*env)->IsSameObject(env, result, object)) {
+    return -1;
+  }
+  return created;
+}
+
+JNIEXPORT void JNICALL Java_NativeTest_remember(JNIEnv *env, jclass class, jobject object) {
+  remembered = (*env)->NewGlobalRef(env, object);
+  if ((*env)->GetObjectRefType(env, remembered) != JNIGlobalRefType) {
+    (*env)->FatalError(env, "not a global reference");
+  }
+}
+
+JNIEXPORT jobject JNICALL Java_NativeTest_recall(JNIEnv *env, jclass class) {
+  return remembered == NULL ? NULL : (*env)->NewLocalRef(env, remembered);
+}
+
+JNIEXPORT void JNICALL Java_NativeTest_forget(JNIEnv *env, jclass class) {
+  (*env)->DeleteGlobalRef(env, remembered);
+  remembered = NULL;
+}
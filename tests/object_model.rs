@@ -0,0 +1,46 @@
+mod common;
+
+use common::{compile, run_main};
+
+// 期待値は同じクラスをjavaで実行した出力
+const EXPECTED: &str = "\
+defaults 0 0 0.0 null null 0 0 0 false 0.0\n\
+fields 4 1099511627776 2.5 tag -3 -56 Z -25536 true 1.25\n\
+getfield npe\n\
+putfield npe\n\
+zero false 0 0 0 0 0 0.0 0.0\n\
+store true -1 A -32768 -2147483648 9223372036854775807 0.1 0.1\n\
+lengths 2 0 5\n\
+negative nase -1\n\
+negative objects nase -2\n\
+cube 2 3 4 9 0\n\
+partial 3 null\n\
+filled 5\n\
+names n null\n\
+negative inner nase -1\n\
+baload aioobe Index 1 out of bounds for length 1 aioobe Index -1 out of bounds for length 1\n\
+caload aioobe Index 1 out of bounds for length 1 aioobe Index 2 out of bounds for length 1\n\
+iaload aioobe Index 1 out of bounds for length 1 aioobe Index 1 out of bounds for length 1\n\
+faload aioobe Index 1 out of bounds for length 1 aioobe Index 1 out of bounds for length 1\n\
+aaload aioobe Index 1 out of bounds for length 1\n\
+bastore aioobe Index 1 out of bounds for length 1 aioobe Index 1 out of bounds for length 1\n\
+castore aioobe Index 1 out of bounds for length 1 aioobe Index 1 out of bounds for length 1\n\
+iastore aioobe Index -1 out of bounds for length 1 aioobe Index 1 out of bounds for length 1\n\
+fastore aioobe Index 1 out of bounds for length 1 aioobe Index 1 out of bounds for length 1\n\
+aastore aioobe Index 1 out of bounds for length 1\n\
+covariant ase ok\n\
+null load npe npe npe npe\n\
+null store npe npe npe npe\n\
+null length npe\n\
+instanceof true false true false\n\
+arrays true false true true true true\n\
+checkcast ok cce ok\n\
+array cast ok cce\n\
+";
+
+#[test]
+fn fields_arrays_and_casts_behave_like_java() {
+  let classes = compile("object_model", &["ObjectModel.java"]);
+  let output = run_main(&classes, "ObjectModel", &[]);
+  assert_eq!(output, EXPECTED);
+}
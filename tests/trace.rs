@@ -0,0 +1,25 @@
+mod common;
+
+use common::{compile, run_with, rust_jvm};
+
+#[test]
+fn trace_file_replays_as_text_trace() {
+  let classes = compile("trace", &["JitWorkload.java"]);
+  // switchと、例外を捕まえるメソッドに絞る
+  let filter = "--trace-filter=JitWorkload.tableSwitch,JitWorkload.divide";
+  let text = run_with(&classes, &["-Xint", "--trace", filter], "JitWorkload", &[]);
+  assert!(text.status.success());
+  let text = String::from_utf8(text.stderr).unwrap();
+  assert!(text.contains("[main] JitWorkload.tableSwitch(I)I 1: tableswitch { 0: 36, 1: 39, 2: 42, 3: 45, 4: 48, default: 51 }"), "{}", text);
+  assert!(text.contains("JitWorkload.divide(II)I"), "{}", text);
+
+  let file = classes.join("trace.bin");
+  let option = format!("--trace-file={}", file.display());
+  let binary = run_with(&classes, &["-Xint", filter, &option], "JitWorkload", &[]);
+  assert!(binary.status.success());
+  assert!(binary.stderr.is_empty(), "{}", String::from_utf8_lossy(&binary.stderr));
+
+  let replay = rust_jvm().arg("replay").arg(&file).output().unwrap();
+  assert!(replay.status.success(), "{}", String::from_utf8_lossy(&replay.stderr));
+  assert_eq!(String::from_utf8(replay.stdout).unwrap(), text);
+}